@@ -20,7 +20,7 @@ use language_model::{
     LanguageModelId, LanguageModelProviderId, LanguageModelRegistry, LanguageModelResponseMessage,
 };
 use prompt_library::PromptBuilder;
-use semantic_index::{CloudEmbeddingProvider, SemanticDb};
+use semantic_index::{CloudEmbeddingProvider, SemanticDb, SemanticIndexSettings};
 use serde::Deserialize;
 use settings::{Settings, SettingsStore};
 
@@ -120,7 +120,12 @@ pub fn init(
             )
             .await?;
 
-            cx.update(|cx| cx.set_global(semantic_index))
+            cx.update(|cx| {
+                // The semantic index configured in the settings takes precedence.
+                if !SemanticIndexSettings::get_global(cx).enabled {
+                    cx.set_global(semantic_index)
+                }
+            })
         }
     })
     .detach();
@@ -221,6 +226,7 @@ fn register_slash_commands(prompt_builder: Option<Arc<PromptBuilder>>, cx: &mut
     slash_command_registry
         .register_command(assistant_slash_commands::DiagnosticsSlashCommand, true);
     slash_command_registry.register_command(assistant_slash_commands::FetchSlashCommand, true);
+    slash_command_registry.register_command(assistant_slash_commands::RepoMapSlashCommand, true);
 
     if let Some(prompt_builder) = prompt_builder {
         cx.observe_flag::<assistant_slash_commands::ProjectSlashCommandFeatureFlag, _>({
@@ -243,7 +243,10 @@ impl TerminalInlineAssistant {
             .terminal
             .update(cx, |terminal, cx| {
                 let terminal = terminal.entity().read(cx);
-                let latest_output = terminal.last_n_non_empty_lines(DEFAULT_CONTEXT_LINES);
+                // When the shell reported that the last command failed, its output is the most relevant context.
+                let latest_output = terminal
+                    .last_failed_command_lines(DEFAULT_CONTEXT_LINES)
+                    .unwrap_or_else(|| terminal.last_n_non_empty_lines(DEFAULT_CONTEXT_LINES));
                 let working_directory = terminal
                     .working_directory()
                     .map(|path| path.to_string_lossy().to_string());
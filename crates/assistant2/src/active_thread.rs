@@ -1,24 +1,35 @@
 use std::sync::Arc;
 
-use assistant_tool::ToolWorkingSet;
+use anyhow::anyhow;
+use assistant_settings::{AssistantSettings, TokenUsageSettings};
+use assistant_tool::{ToolPermission, ToolPermissionsSettings, ToolWorkingSet};
 use collections::HashMap;
+use futures::channel::mpsc;
 use gpui::{
     list, AbsoluteLength, AnyElement, App, DefiniteLength, EdgesRefinement, Empty, Entity, Length,
-    ListAlignment, ListOffset, ListState, StyleRefinement, Subscription, TextStyleRefinement,
-    UnderlineStyle, WeakEntity,
+    ListAlignment, ListOffset, ListState, PromptLevel, StyleRefinement, Subscription, Task,
+    TextStyleRefinement, UnderlineStyle, WeakEntity,
 };
 use language::LanguageRegistry;
-use language_model::Role;
+use language_model::{LanguageModelToolUseId, Role};
 use markdown::{Markdown, MarkdownStyle};
-use settings::Settings as _;
+use settings::{update_settings_file, Settings as _};
 use theme::ThemeSettings;
-use ui::prelude::*;
+use ui::{prelude::*, Tooltip};
+use util::ResultExt as _;
 use workspace::Workspace;
 
-use crate::thread::{MessageId, Thread, ThreadError, ThreadEvent};
+use crate::agent_diff::AgentDiff;
+use crate::thread::{
+    token_usage_cost, MessageId, ModelTokenUsage, PendingToolUse, Thread, ThreadError, ThreadEvent,
+    ToolUseDecision,
+};
 use crate::thread_store::ThreadStore;
 use crate::ui::ContextPill;
 
+/// The most lines of a running tool's output we show.
+const MAX_TOOL_OUTPUT_LINES: usize = 12;
+
 pub struct ActiveThread {
     workspace: WeakEntity<Workspace>,
     language_registry: Arc<LanguageRegistry>,
@@ -247,22 +258,430 @@ impl ActiveThread {
                     .collect::<Vec<_>>();
 
                 for tool_use in pending_tool_uses {
-                    if let Some(tool) = self.tools.tool(&tool_use.name, cx) {
-                        let task = tool.run(tool_use.input, self.workspace.clone(), window, cx);
-
-                        self.thread.update(cx, |thread, cx| {
-                            thread.insert_tool_output(
-                                tool_use.assistant_message_id,
+                    match self.tool_permission(&tool_use.name, cx) {
+                        ToolPermission::Allow => self.run_tool_use(
+                            tool_use,
+                            ToolUseDecision::AllowedBySettings,
+                            window,
+                            cx,
+                        ),
+                        ToolPermission::Ask => self.thread.update(cx, |thread, cx| {
+                            thread.request_tool_use_confirmation(&tool_use.id, cx)
+                        }),
+                        ToolPermission::Deny => self.thread.update(cx, |thread, cx| {
+                            thread.deny_tool_use(
                                 tool_use.id.clone(),
-                                task,
+                                ToolUseDecision::DeniedBySettings,
                                 cx,
-                            );
-                        });
+                            )
+                        }),
                     }
                 }
             }
-            ThreadEvent::ToolFinished { .. } => {}
+            ThreadEvent::TokensUsed(usage) => {
+                self.record_token_usage(usage.clone(), cx);
+                self.thread_store
+                    .update(cx, |thread_store, cx| {
+                        thread_store.save_thread(&self.thread, cx)
+                    })
+                    .detach_and_log_err(cx);
+            }
+            ThreadEvent::StreamedToolOutput { .. } => {
+                cx.notify();
+            }
+            ThreadEvent::ProfileChanged => {
+                // Threads are saved once they have a message, which saves the profile with them.
+                if !self.thread.read(cx).is_empty() {
+                    self.thread_store
+                        .update(cx, |thread_store, cx| {
+                            thread_store.save_thread(&self.thread, cx)
+                        })
+                        .detach_and_log_err(cx);
+                }
+            }
+            ThreadEvent::ToolFinished { .. } => {
+                self.thread_store
+                    .update(cx, |thread_store, cx| {
+                        thread_store.save_thread(&self.thread, cx)
+                    })
+                    .detach_and_log_err(cx);
+            }
+        }
+    }
+
+    /// Adds the tokens to this month's usage, warning when their estimated cost approaches or
+    /// exceeds the monthly budget.
+    fn record_token_usage(&mut self, usage: ModelTokenUsage, cx: &mut Context<Self>) {
+        let spent_before =
+            token_usage_cost(self.thread_store.read(cx).monthly_token_usage(), cx).unwrap_or(0.);
+        let spent = spent_before + token_usage_cost(&[usage.clone()], cx).unwrap_or(0.);
+        self.thread_store.update(cx, |thread_store, cx| {
+            thread_store.record_token_usage(usage, cx)
+        });
+
+        let settings = TokenUsageSettings::get_global(cx);
+        let Some(budget) = settings.monthly_budget else {
+            return;
+        };
+        let warning_threshold = budget * settings.budget_warning_threshold;
+        // Warn once when crossing the threshold, and after every request once over budget.
+        if spent >= budget || (spent_before < warning_threshold && spent >= warning_threshold) {
+            self.last_error = Some(ThreadError::MonthlyBudget { spent, budget });
+            cx.notify();
+        }
+    }
+
+    /// Returns the permission of the tool, taking the settings of all the project's visible
+    /// worktrees into account.
+    fn tool_permission(&self, tool_name: &str, cx: &App) -> ToolPermission {
+        // The model is only offered the tools of the thread's profile, but it may still ask for
+        // others, for example after the profile was changed.
+        let context_server_id = self
+            .tools
+            .tool(tool_name, cx)
+            .and_then(|tool| tool.context_server_id());
+        let enabled_in_profile = AssistantSettings::get_global(cx)
+            .profile(self.thread.read(cx).profile_id())
+            .map_or(true, |(_, profile)| {
+                profile.is_tool_enabled(tool_name, context_server_id.as_deref())
+            });
+        if !enabled_in_profile {
+            return ToolPermission::Deny;
+        }
+
+        let worktree_ids = self
+            .workspace
+            .upgrade()
+            .map(|workspace| {
+                let project = workspace.read(cx).project().read(cx);
+                project
+                    .visible_worktrees(cx)
+                    .map(|worktree| worktree.read(cx).id())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        self.tools.permission(tool_name, worktree_ids, cx)
+    }
+
+    fn run_tool_use(
+        &mut self,
+        tool_use: PendingToolUse,
+        decision: ToolUseDecision,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (output_tx, output_rx) = mpsc::unbounded();
+        let task = match self.tools.tool(&tool_use.name, cx) {
+            Some(tool) => tool.run_streaming(
+                tool_use.input,
+                self.workspace.clone(),
+                output_tx,
+                window,
+                cx,
+            ),
+            None => Task::ready(Err(anyhow!("No tool named {} exists", tool_use.name))),
+        };
+
+        self.thread.update(cx, |thread, cx| {
+            thread.insert_tool_output(
+                tool_use.assistant_message_id,
+                tool_use.id.clone(),
+                decision,
+                task,
+                cx,
+            );
+            thread.stream_tool_output(tool_use.id.clone(), output_rx, cx);
+        });
+    }
+
+    fn allow_tool_use(
+        &mut self,
+        tool_use_id: &LanguageModelToolUseId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let tool_use = self
+            .thread
+            .read(cx)
+            .tool_uses_needing_confirmation()
+            .into_iter()
+            .find(|tool_use| &tool_use.id == tool_use_id)
+            .cloned();
+        if let Some(tool_use) = tool_use {
+            self.run_tool_use(tool_use, ToolUseDecision::AllowedByUser, window, cx);
+        }
+    }
+
+    /// Allows the tool from now on, and uses it wherever it is waiting for confirmation.
+    fn always_allow_tool(
+        &mut self,
+        tool_name: String,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(workspace) = self.workspace.upgrade() {
+            let fs = workspace.read(cx).app_state().fs.clone();
+            let name = tool_name.clone();
+            update_settings_file::<ToolPermissionsSettings>(fs, cx, move |settings, _| {
+                settings
+                    .tools
+                    .get_or_insert_with(Default::default)
+                    .insert(name, ToolPermission::Allow);
+            });
+        }
+
+        let tool_uses = self
+            .thread
+            .read(cx)
+            .tool_uses_needing_confirmation()
+            .into_iter()
+            .filter(|tool_use| tool_use.name == tool_name)
+            .cloned()
+            .collect::<Vec<_>>();
+        for tool_use in tool_uses {
+            self.run_tool_use(tool_use, ToolUseDecision::AllowedByUser, window, cx);
+        }
+    }
+
+    fn deny_tool_use(&mut self, tool_use_id: LanguageModelToolUseId, cx: &mut Context<Self>) {
+        self.thread.update(cx, |thread, cx| {
+            thread.deny_tool_use(tool_use_id, ToolUseDecision::DeniedByUser, cx)
+        });
+    }
+
+    fn restore_checkpoint(
+        &mut self,
+        message_id: MessageId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let user_edited_paths = self
+            .thread
+            .read(cx)
+            .user_edited_buffers_since(message_id, cx)
+            .into_iter()
+            .map(|buffer| match buffer.read(cx).file() {
+                Some(file) => file.full_path(cx).display().to_string(),
+                None => "untitled".to_string(),
+            })
+            .collect::<Vec<_>>();
+        if user_edited_paths.is_empty() {
+            self.thread
+                .update(cx, |thread, cx| thread.restore_checkpoint(message_id, cx))
+                .detach_and_log_err(cx);
+            return;
+        }
+
+        let detail = format!(
+            "You edited these files since this message was sent, and restoring them reverts your edits as well:\n\n{}",
+            user_edited_paths.join("\n")
+        );
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            "Restore files edited by you?",
+            Some(&detail),
+            &["Restore", "Cancel"],
+            cx,
+        );
+        let thread = self.thread.clone();
+        cx.spawn(|_, mut cx| async move {
+            if answer.await == Ok(0) {
+                thread
+                    .update(&mut cx, |thread, cx| {
+                        thread.restore_checkpoint(message_id, cx)
+                    })?
+                    .await?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn review_changes(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let thread = self.thread.clone();
+        self.workspace
+            .update(cx, |workspace, cx| {
+                AgentDiff::deploy(thread, workspace, window, cx)
+            })
+            .log_err();
+    }
+
+    fn render_changed_files(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let changed_file_count = self.thread.read(cx).changed_buffers(cx).len();
+        if changed_file_count == 0 {
+            return None;
         }
+
+        let colors = cx.theme().colors();
+        let label = if changed_file_count == 1 {
+            "1 file changed".to_string()
+        } else {
+            format!("{changed_file_count} files changed")
+        };
+
+        Some(
+            h_flex()
+                .mx_2p5()
+                .mb_2p5()
+                .py_1()
+                .px_2()
+                .justify_between()
+                .bg(colors.editor_background)
+                .rounded_md()
+                .border_1()
+                .border_color(colors.border)
+                .child(
+                    h_flex()
+                        .gap_1p5()
+                        .child(
+                            Icon::new(IconName::Diff)
+                                .size(IconSize::XSmall)
+                                .color(Color::Muted),
+                        )
+                        .child(Label::new(label).size(LabelSize::Small)),
+                )
+                .child(
+                    Button::new("review-changes", "Review Changes")
+                        .label_size(LabelSize::Small)
+                        .on_click(
+                            cx.listener(|this, _, window, cx| this.review_changes(window, cx)),
+                        ),
+                )
+                .into_any(),
+        )
+    }
+
+    fn render_tool_use_confirmation(
+        &self,
+        ix: usize,
+        tool_use: &PendingToolUse,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let colors = cx.theme().colors();
+        let input = serde_json::to_string_pretty(&tool_use.input)
+            .unwrap_or_else(|_| tool_use.input.to_string());
+
+        v_flex()
+            .id(("tool-use-confirmation", ix))
+            .mx_2p5()
+            .mb_2p5()
+            .bg(colors.editor_background)
+            .rounded_lg()
+            .border_1()
+            .border_color(colors.border)
+            .shadow_sm()
+            .child(
+                h_flex()
+                    .py_1()
+                    .px_2()
+                    .gap_1p5()
+                    .bg(colors.editor_foreground.opacity(0.05))
+                    .border_b_1()
+                    .border_color(colors.border)
+                    .rounded_t(px(6.))
+                    .child(
+                        Icon::new(IconName::Warning)
+                            .size(IconSize::XSmall)
+                            .color(Color::Warning),
+                    )
+                    .child(
+                        Label::new(format!("Allow the assistant to use {}?", tool_use.name))
+                            .size(LabelSize::Small),
+                    ),
+            )
+            .child(
+                div()
+                    .p_2()
+                    .max_h_48()
+                    .overflow_hidden()
+                    .font_buffer(cx)
+                    .text_xs()
+                    .text_color(colors.text_muted)
+                    .child(input),
+            )
+            .child(
+                h_flex()
+                    .p_1()
+                    .gap_1()
+                    .justify_end()
+                    .border_t_1()
+                    .border_color(colors.border_variant)
+                    .child(
+                        Button::new(("deny-tool-use", ix), "Deny").on_click(cx.listener({
+                            let tool_use_id = tool_use.id.clone();
+                            move |this, _, _, cx| this.deny_tool_use(tool_use_id.clone(), cx)
+                        })),
+                    )
+                    .child(
+                        Button::new(("always-allow-tool", ix), "Always Allow").on_click(
+                            cx.listener({
+                                let tool_name = tool_use.name.clone();
+                                move |this, _, window, cx| {
+                                    this.always_allow_tool(tool_name.clone(), window, cx)
+                                }
+                            }),
+                        ),
+                    )
+                    .child(
+                        Button::new(("allow-tool-use", ix), "Allow")
+                            .style(ButtonStyle::Filled)
+                            .on_click(cx.listener({
+                                let tool_use_id = tool_use.id.clone();
+                                move |this, _, window, cx| {
+                                    this.allow_tool_use(&tool_use_id, window, cx)
+                                }
+                            })),
+                    ),
+            )
+            .into_any()
+    }
+
+    fn render_tool_output(
+        &self,
+        ix: usize,
+        tool_use: &PendingToolUse,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let colors = cx.theme().colors();
+        let output = tool_use.output.trim_end();
+        let last_lines_start = output
+            .rmatch_indices('\n')
+            .nth(MAX_TOOL_OUTPUT_LINES - 1)
+            .map_or(0, |(ix, _)| ix + 1);
+
+        v_flex()
+            .id(("tool-output", ix))
+            .mx_2p5()
+            .mb_2p5()
+            .bg(colors.editor_background)
+            .rounded_lg()
+            .border_1()
+            .border_color(colors.border)
+            .child(
+                h_flex()
+                    .py_1()
+                    .px_2()
+                    .gap_1p5()
+                    .border_b_1()
+                    .border_color(colors.border)
+                    .child(
+                        Icon::new(IconName::Terminal)
+                            .size(IconSize::XSmall)
+                            .color(Color::Muted),
+                    )
+                    .child(
+                        Label::new(format!("Running {}…", tool_use.name)).size(LabelSize::Small),
+                    ),
+            )
+            .child(
+                div()
+                    .p_2()
+                    .font_buffer(cx)
+                    .text_xs()
+                    .text_color(colors.text_muted)
+                    .child(output[last_lines_start..].to_string()),
+            )
+            .into_any()
     }
 
     fn render_message(&self, ix: usize, cx: &mut Context<Self>) -> AnyElement {
@@ -294,6 +713,8 @@ impl ActiveThread {
                 }
             });
 
+        let has_checkpoint = self.thread.read(cx).has_checkpoint(message_id);
+
         let styled_message = match message.role {
             Role::User => v_flex()
                 .id(("message-container", ix))
@@ -328,7 +749,31 @@ impl ActiveThread {
                                                 .size(LabelSize::Small)
                                                 .color(Color::Muted),
                                         ),
-                                ),
+                                )
+                                .when(has_checkpoint, |header| {
+                                    header.child(
+                                        IconButton::new(
+                                            ("restore-checkpoint", ix),
+                                            IconName::RotateCcw,
+                                        )
+                                        .icon_size(IconSize::XSmall)
+                                        .icon_color(Color::Muted)
+                                        .tooltip(|window, cx| {
+                                            Tooltip::with_meta(
+                                                "Restore Files to Before This Message",
+                                                None,
+                                                "Checkpoints last until Zed is closed",
+                                                window,
+                                                cx,
+                                            )
+                                        })
+                                        .on_click(
+                                            cx.listener(move |this, _, window, cx| {
+                                                this.restore_checkpoint(message_id, window, cx)
+                                            }),
+                                        ),
+                                    )
+                                }),
                         )
                         .child(message_content),
                 ),
@@ -346,9 +791,37 @@ impl ActiveThread {
 }
 
 impl Render for ActiveThread {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let confirmations = self
+            .thread
+            .read(cx)
+            .tool_uses_needing_confirmation()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let streaming_output = self
+            .thread
+            .read(cx)
+            .tool_uses_streaming_output()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
         v_flex()
             .size_full()
             .child(list(self.list_state.clone()).flex_grow())
+            .children(self.render_changed_files(cx))
+            .children(
+                streaming_output
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, tool_use)| self.render_tool_output(ix, tool_use, cx)),
+            )
+            .children(
+                confirmations
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, tool_use)| self.render_tool_use_confirmation(ix, tool_use, cx)),
+            )
     }
 }
@@ -0,0 +1,614 @@
+use std::any::{Any, TypeId};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use buffer_diff::{BufferDiff, BufferDiffEvent, DiffHunkStatus};
+use collections::HashMap;
+use editor::{Editor, EditorEvent, ToPoint as _};
+use gpui::{
+    AnyElement, AnyView, App, Entity, EventEmitter, FocusHandle, Focusable, Subscription, Task,
+    WeakEntity,
+};
+use language::{Anchor, Buffer, BufferEvent, Capability, OffsetRangeExt as _};
+use multi_buffer::{MultiBuffer, PathKey};
+use project::{Project, ProjectPath};
+use text::BufferId;
+use ui::prelude::*;
+use util::ResultExt as _;
+use workspace::item::{BreadcrumbText, ItemEvent, TabContentParams};
+use workspace::searchable::SearchableItemHandle;
+use workspace::{Item, ItemNavHistory, ToolbarItemLocation, Workspace};
+
+use crate::thread::Thread;
+
+const AGENT_DIFF_NAMESPACE: &str = "agent";
+
+/// A review of the changes made to the project's files during a [`Thread`], whose hunks can be
+/// accepted or rejected one by one.
+pub struct AgentDiff {
+    thread: Entity<Thread>,
+    multibuffer: Entity<MultiBuffer>,
+    editor: Entity<Editor>,
+    focus_handle: FocusHandle,
+    diff_buffers: HashMap<BufferId, DiffBuffer>,
+    _subscription: Subscription,
+}
+
+struct DiffBuffer {
+    path_key: PathKey,
+    buffer: Entity<Buffer>,
+    /// The text the buffer is compared against: its text before the thread, along with the
+    /// hunks accepted since.
+    base_buffer: Entity<Buffer>,
+    diff: Entity<BufferDiff>,
+    recalculate_diff_task: Task<()>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl AgentDiff {
+    /// Opens the review of the thread's changes, or activates it if it is already open.
+    pub fn deploy(
+        thread: Entity<Thread>,
+        workspace: &mut Workspace,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) {
+        let existing = workspace
+            .items_of_type::<AgentDiff>(cx)
+            .find(|agent_diff| agent_diff.read(cx).thread == thread);
+        if let Some(existing) = existing {
+            workspace.activate_item(&existing, true, true, window, cx);
+        } else {
+            let agent_diff = cx.new(|cx| AgentDiff::new(thread, window, cx));
+            workspace.add_item_to_active_pane(Box::new(agent_diff), None, true, window, cx);
+        }
+    }
+
+    fn new(thread: Entity<Thread>, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let focus_handle = cx.focus_handle();
+        let multibuffer = cx.new(|_| MultiBuffer::new(Capability::ReadWrite));
+        let project = thread.read(cx).project().clone();
+
+        let agent_diff = cx.weak_entity();
+        let editor = cx.new(|cx| {
+            let mut editor =
+                Editor::for_multibuffer(multibuffer.clone(), Some(project), true, window, cx);
+            editor.set_expand_all_diff_hunks(cx);
+            editor.set_render_diff_hunk_controls(
+                Arc::new(
+                    move |row: u32,
+                          status: &DiffHunkStatus,
+                          hunk_range: Range<editor::Anchor>,
+                          line_height: Pixels,
+                          editor: &Entity<Editor>,
+                          _: &mut Window,
+                          cx: &mut App| {
+                        render_diff_hunk_controls(
+                            row,
+                            status,
+                            hunk_range,
+                            line_height,
+                            editor,
+                            agent_diff.clone(),
+                            cx,
+                        )
+                    },
+                ),
+                cx,
+            );
+            editor
+        });
+
+        let mut this = Self {
+            _subscription: cx.observe(&thread, |this, _, cx| this.refresh(cx)),
+            thread,
+            multibuffer,
+            editor,
+            focus_handle,
+            diff_buffers: HashMap::default(),
+        };
+        this.refresh(cx);
+        this
+    }
+
+    /// Adds the buffers the thread edited since the review was opened.
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        for (buffer, old_snapshot) in self.thread.read(cx).edited_buffers(cx) {
+            let buffer_id = buffer.read(cx).remote_id();
+            if !self.diff_buffers.contains_key(&buffer_id) {
+                self.register_buffer(buffer, old_snapshot, cx);
+            }
+        }
+    }
+
+    fn register_buffer(
+        &mut self,
+        buffer: Entity<Buffer>,
+        old_snapshot: text::BufferSnapshot,
+        cx: &mut Context<Self>,
+    ) {
+        let buffer_id = old_snapshot.remote_id();
+        let path: Arc<Path> = match buffer.read(cx).file() {
+            Some(file) => file.full_path(cx).into(),
+            None => Path::new(&buffer_id.to_string()).into(),
+        };
+
+        let base_buffer = cx.new(|cx| {
+            let mut base_buffer = Buffer::local(old_snapshot.text(), cx);
+            if let Some(language_registry) = buffer.read(cx).language_registry() {
+                base_buffer.set_language_registry(language_registry);
+            }
+            base_buffer.set_language(buffer.read(cx).language().cloned(), cx);
+            base_buffer
+        });
+        let diff = cx.new(|cx| {
+            let snapshot = buffer.read(cx).text_snapshot();
+            let mut diff = BufferDiff::new(&snapshot);
+            let _ = diff.set_base_text(base_buffer.clone(), snapshot, cx);
+            diff
+        });
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.add_diff(diff.clone(), cx);
+        });
+
+        let subscriptions = vec![
+            cx.subscribe(&buffer, move |this, _, event, cx| {
+                if matches!(event, BufferEvent::Edited) {
+                    this.recalculate_diff(buffer_id, cx);
+                }
+            }),
+            cx.subscribe(&base_buffer, move |this, _, event, cx| {
+                if matches!(event, BufferEvent::Edited) {
+                    this.recalculate_diff(buffer_id, cx);
+                }
+            }),
+            cx.subscribe(&diff, move |this, _, event, cx| {
+                if let BufferDiffEvent::DiffChanged { .. } = event {
+                    this.update_excerpts(buffer_id, cx);
+                }
+            }),
+        ];
+
+        self.diff_buffers.insert(
+            buffer_id,
+            DiffBuffer {
+                path_key: PathKey::namespaced(AGENT_DIFF_NAMESPACE, path),
+                buffer,
+                base_buffer,
+                diff,
+                recalculate_diff_task: Task::ready(()),
+                _subscriptions: subscriptions,
+            },
+        );
+    }
+
+    fn recalculate_diff(&mut self, buffer_id: BufferId, cx: &mut Context<Self>) {
+        let Some(diff_buffer) = self.diff_buffers.get_mut(&buffer_id) else {
+            return;
+        };
+        let buffer = diff_buffer.buffer.clone();
+        let base_buffer = diff_buffer.base_buffer.clone();
+        let diff = diff_buffer.diff.clone();
+        diff_buffer.recalculate_diff_task = cx.spawn(|_, mut cx| async move {
+            cx.background_executor()
+                .timer(Duration::from_millis(50))
+                .await;
+            diff.update(&mut cx, |diff, cx| {
+                let snapshot = buffer.read(cx).text_snapshot();
+                let _ = diff.set_base_text(base_buffer, snapshot, cx);
+            })
+            .log_err();
+        });
+    }
+
+    /// Shows an excerpt around each hunk of the buffer, and none if it has no hunks left.
+    fn update_excerpts(&mut self, buffer_id: BufferId, cx: &mut Context<Self>) {
+        let Some(diff_buffer) = self.diff_buffers.get(&buffer_id) else {
+            return;
+        };
+        let snapshot = diff_buffer.buffer.read(cx).snapshot();
+        let hunk_ranges = diff_buffer
+            .diff
+            .read(cx)
+            .hunks_intersecting_range(Anchor::MIN..Anchor::MAX, &snapshot, cx)
+            .map(|hunk| hunk.buffer_range.to_point(&snapshot))
+            .collect::<Vec<_>>();
+
+        let path_key = diff_buffer.path_key.clone();
+        let buffer = diff_buffer.buffer.clone();
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            if hunk_ranges.is_empty() {
+                multibuffer.remove_excerpts_for_path(path_key, cx);
+            } else {
+                multibuffer.set_excerpts_for_path(
+                    path_key,
+                    buffer,
+                    hunk_ranges,
+                    editor::DEFAULT_MULTIBUFFER_CONTEXT,
+                    cx,
+                );
+            }
+        });
+        cx.notify();
+    }
+
+    /// Accepts or rejects the hunks intersecting the given ranges. Accepted hunks stay in the
+    /// files, and rejected hunks are restored to the text they had before the thread.
+    fn resolve_hunks(
+        &mut self,
+        ranges: &[Range<editor::Anchor>],
+        accept: bool,
+        cx: &mut Context<Self>,
+    ) {
+        let snapshot = self.multibuffer.read(cx).snapshot(cx);
+        let mut hunks_by_buffer = HashMap::<BufferId, Vec<_>>::default();
+        for range in ranges {
+            let range = range.start.to_point(&snapshot)..range.end.to_point(&snapshot);
+            for hunk in snapshot.diff_hunks_in_range(range) {
+                let hunks = hunks_by_buffer.entry(hunk.buffer_id).or_default();
+                if !hunks
+                    .iter()
+                    .any(|existing: &multi_buffer::MultiBufferDiffHunk| {
+                        existing.diff_base_byte_range == hunk.diff_base_byte_range
+                    })
+                {
+                    hunks.push(hunk);
+                }
+            }
+        }
+
+        for (buffer_id, hunks) in hunks_by_buffer {
+            let Some(diff_buffer) = self.diff_buffers.get(&buffer_id) else {
+                continue;
+            };
+            let buffer = diff_buffer.buffer.clone();
+            let base_buffer = diff_buffer.base_buffer.clone();
+            if accept {
+                let edits = hunks
+                    .into_iter()
+                    .map(|hunk| {
+                        let new_text = buffer
+                            .read(cx)
+                            .text_for_range(hunk.buffer_range)
+                            .collect::<String>();
+                        (hunk.diff_base_byte_range, new_text)
+                    })
+                    .collect::<Vec<_>>();
+                base_buffer.update(cx, |base_buffer, cx| base_buffer.edit(edits, None, cx));
+            } else {
+                let edits = hunks
+                    .into_iter()
+                    .map(|hunk| {
+                        let old_text = base_buffer
+                            .read(cx)
+                            .text_for_range(hunk.diff_base_byte_range)
+                            .collect::<String>();
+                        (hunk.buffer_range, old_text)
+                    })
+                    .collect::<Vec<_>>();
+                buffer.update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            }
+        }
+    }
+
+    fn resolve_all_hunks(&mut self, accept: bool, cx: &mut Context<Self>) {
+        let range = editor::Anchor::min()..editor::Anchor::max();
+        self.resolve_hunks(&[range], accept, cx);
+    }
+}
+
+fn render_diff_hunk_controls(
+    row: u32,
+    _status: &DiffHunkStatus,
+    hunk_range: Range<editor::Anchor>,
+    line_height: Pixels,
+    _editor: &Entity<Editor>,
+    agent_diff: WeakEntity<AgentDiff>,
+    cx: &mut App,
+) -> AnyElement {
+    h_flex()
+        .h(line_height)
+        .mr_1()
+        .gap_1()
+        .px_1()
+        .pb_1()
+        .border_b_1()
+        .border_color(cx.theme().colors().border_variant)
+        .rounded_b_lg()
+        .bg(cx.theme().colors().editor_background)
+        .child(
+            Button::new(("reject-hunk", row as u64), "Reject").on_click({
+                let agent_diff = agent_diff.clone();
+                let hunk_range = hunk_range.clone();
+                move |_, _, cx| {
+                    agent_diff
+                        .update(cx, |agent_diff, cx| {
+                            agent_diff.resolve_hunks(&[hunk_range.clone()], false, cx)
+                        })
+                        .ok();
+                }
+            }),
+        )
+        .child(
+            Button::new(("accept-hunk", row as u64), "Accept").on_click(move |_, _, cx| {
+                agent_diff
+                    .update(cx, |agent_diff, cx| {
+                        agent_diff.resolve_hunks(&[hunk_range.clone()], true, cx)
+                    })
+                    .ok();
+            }),
+        )
+        .into_any_element()
+}
+
+impl EventEmitter<EditorEvent> for AgentDiff {}
+
+impl Focusable for AgentDiff {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        if self.multibuffer.read(cx).is_empty() {
+            self.focus_handle.clone()
+        } else {
+            self.editor.focus_handle(cx)
+        }
+    }
+}
+
+impl Item for AgentDiff {
+    type Event = EditorEvent;
+
+    fn tab_icon(&self, _window: &Window, _cx: &App) -> Option<Icon> {
+        Some(Icon::new(IconName::ZedAssistant).color(Color::Muted))
+    }
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn deactivated(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.deactivated(window, cx));
+    }
+
+    fn navigate(
+        &mut self,
+        data: Box<dyn Any>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        self.editor
+            .update(cx, |editor, cx| editor.navigate(data, window, cx))
+    }
+
+    fn tab_tooltip_text(&self, cx: &App) -> Option<SharedString> {
+        Some(format!("Changes in {}", self.thread.read(cx).summary_or_default()).into())
+    }
+
+    fn tab_content(&self, params: TabContentParams, _window: &Window, _: &App) -> AnyElement {
+        Label::new("Agent Changes")
+            .color(if params.selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("Agent Diff Opened")
+    }
+
+    fn as_searchable(&self, _: &Entity<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(self.editor.clone()))
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &App,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::ProjectItem),
+    ) {
+        self.editor.for_each_project_item(cx, f)
+    }
+
+    fn is_singleton(&self, _: &App) -> bool {
+        false
+    }
+
+    fn set_nav_history(
+        &mut self,
+        nav_history: ItemNavHistory,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor.update(cx, |editor, _| {
+            editor.set_nav_history(Some(nav_history));
+        });
+    }
+
+    fn is_dirty(&self, cx: &App) -> bool {
+        self.multibuffer.read(cx).is_dirty(cx)
+    }
+
+    fn has_conflict(&self, cx: &App) -> bool {
+        self.multibuffer.read(cx).has_conflict(cx)
+    }
+
+    fn can_save(&self, _: &App) -> bool {
+        true
+    }
+
+    fn save(
+        &mut self,
+        format: bool,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.editor.save(format, project, window, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        _: Entity<Project>,
+        _: ProjectPath,
+        _window: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        unreachable!()
+    }
+
+    fn reload(
+        &mut self,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.editor.reload(project, window, cx)
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a Entity<Self>,
+        _: &'a App,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+
+    fn breadcrumb_location(&self, _: &App) -> ToolbarItemLocation {
+        ToolbarItemLocation::PrimaryLeft
+    }
+
+    fn breadcrumbs(&self, theme: &theme::Theme, cx: &App) -> Option<Vec<BreadcrumbText>> {
+        self.editor.breadcrumbs(theme, cx)
+    }
+
+    fn added_to_workspace(
+        &mut self,
+        workspace: &mut Workspace,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor.update(cx, |editor, cx| {
+            editor.added_to_workspace(workspace, window, cx)
+        });
+    }
+}
+
+impl Render for AgentDiff {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let is_empty = self.multibuffer.read(cx).is_empty();
+
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .key_context(if is_empty { "EmptyPane" } else { "AgentDiff" })
+            .bg(cx.theme().colors().editor_background)
+            .size_full()
+            .when(is_empty, |el| {
+                el.items_center()
+                    .justify_center()
+                    .child(Label::new("No changes to review"))
+            })
+            .when(!is_empty, |el| {
+                el.child(
+                    h_flex()
+                        .p_1()
+                        .gap_1()
+                        .justify_end()
+                        .border_b_1()
+                        .border_color(cx.theme().colors().border_variant)
+                        .child(Button::new("reject-all", "Reject All").on_click(
+                            cx.listener(|this, _, _, cx| this.resolve_all_hunks(false, cx)),
+                        ))
+                        .child(Button::new("accept-all", "Accept All").on_click(
+                            cx.listener(|this, _, _, cx| this.resolve_all_hunks(true, cx)),
+                        )),
+                )
+                .child(div().flex_1().child(self.editor.clone()))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread::tests::{init_test, open_buffer, start_tool_use, test_project};
+    use assistant_tool::ToolWorkingSet;
+    use gpui::{TestAppContext, VisualTestContext};
+    use language::Point;
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_accepting_and_rejecting_hunks(cx: &mut TestAppContext) {
+        init_test(cx);
+        let project = test_project(json!({ "a.txt": "one\ntwo\nthree\nfour\nfive\n" }), cx).await;
+        let thread =
+            cx.new(|cx| Thread::new(project.clone(), Arc::new(ToolWorkingSet::default()), cx));
+        thread.update(cx, |thread, cx| {
+            thread.insert_user_message("Shout the first and last lines", Vec::new(), cx)
+        });
+
+        let tool_use = start_tool_use(&thread, cx);
+        let buffer = open_buffer(&project, "root/a.txt", cx).await;
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..3, "ONE"), (19..23, "FIVE")], None, cx)
+        });
+        drop(tool_use);
+        cx.run_until_parked();
+
+        let (agent_diff, cx) =
+            cx.add_window_view(|window, cx| AgentDiff::new(thread.clone(), window, cx));
+        cx.run_until_parked();
+
+        let hunk_ranges = |agent_diff: &Entity<AgentDiff>, cx: &mut VisualTestContext| {
+            agent_diff.update(cx, |agent_diff, cx| {
+                let snapshot = agent_diff.multibuffer.read(cx).snapshot(cx);
+                snapshot
+                    .diff_hunks_in_range(Point::zero()..snapshot.max_point())
+                    .map(|hunk| {
+                        let start = Point::new(hunk.row_range.start.0, 0);
+                        snapshot.anchor_before(start)..snapshot.anchor_after(start)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let hunks = hunk_ranges(&agent_diff, cx);
+        assert_eq!(hunks.len(), 2);
+
+        // Accepting a hunk keeps it in the file and takes it out of the review.
+        agent_diff.update(cx, |agent_diff, cx| {
+            agent_diff.resolve_hunks(&hunks[..1], true, cx)
+        });
+        cx.executor().advance_clock(Duration::from_millis(100));
+        cx.run_until_parked();
+        assert_eq!(
+            buffer.read_with(cx, |buffer, _| buffer.text()),
+            "ONE\ntwo\nthree\nfour\nFIVE\n"
+        );
+        let hunks = hunk_ranges(&agent_diff, cx);
+        assert_eq!(hunks.len(), 1);
+
+        // Rejecting a hunk restores its text from before the thread.
+        agent_diff.update(cx, |agent_diff, cx| {
+            agent_diff.resolve_hunks(&hunks, false, cx)
+        });
+        cx.executor().advance_clock(Duration::from_millis(100));
+        cx.run_until_parked();
+        assert_eq!(
+            buffer.read_with(cx, |buffer, _| buffer.text()),
+            "ONE\ntwo\nthree\nfour\nfive\n"
+        );
+        assert!(hunk_ranges(&agent_diff, cx).is_empty());
+        agent_diff.read_with(cx, |agent_diff, cx| {
+            assert!(agent_diff.multibuffer.read(cx).excerpt_ids().is_empty());
+        });
+    }
+}
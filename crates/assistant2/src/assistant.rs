@@ -1,8 +1,10 @@
 mod active_thread;
+mod agent_diff;
 mod assistant_configuration;
 mod assistant_model_selector;
 mod assistant_panel;
 mod buffer_codegen;
+mod code_review;
 mod context;
 mod context_picker;
 mod context_store;
@@ -11,22 +13,26 @@ mod history_store;
 mod inline_assistant;
 mod inline_prompt_editor;
 mod message_editor;
+mod prompt_picker;
 mod terminal_codegen;
 mod terminal_inline_assistant;
 mod thread;
+mod thread_export;
 mod thread_history;
 mod thread_store;
 mod ui;
 
 use std::sync::Arc;
 
-use assistant_settings::AssistantSettings;
+use assistant_settings::{AssistantSettings, TokenUsageSettings};
 use client::Client;
 use command_palette_hooks::CommandPaletteFilter;
 use feature_flags::{Assistant2FeatureFlag, FeatureFlagAppExt};
 use fs::Fs;
-use gpui::{actions, App};
+use gpui::{actions, impl_actions, App};
 use prompt_library::PromptBuilder;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use settings::Settings as _;
 
 pub use crate::assistant_panel::{AssistantPanel, ConcreteAssistantPanelDelegate};
@@ -52,10 +58,28 @@ actions!(
         FocusLeft,
         FocusRight,
         RemoveFocusedContext,
-        AcceptSuggestedContext
+        AcceptSuggestedContext,
+        CopyThreadAsMarkdown,
+        ExportThreadAsMarkdown,
+        ExportThreadAsJson,
+        ImportThread,
+        ShareThreadToChannelNotes,
+        ReviewStagedChanges
     ]
 );
 
+/// Reviews the changes since the current branch forked from another branch.
+#[derive(Clone, Default, Debug, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewBranchChanges {
+    /// The branch to compare with. Defaults to the remote's default branch, `main` or `master`,
+    /// whichever exists first.
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+impl_actions!(assistant2, [ReviewBranchChanges]);
+
 const NAMESPACE: &str = "assistant2";
 
 /// Initializes the `assistant2` crate.
@@ -66,6 +90,7 @@ pub fn init(
     cx: &mut App,
 ) {
     AssistantSettings::register(cx);
+    TokenUsageSettings::register(cx);
     thread_store::init(cx);
     assistant_panel::init(cx);
 
@@ -1,19 +1,33 @@
+mod bearer_token_view;
+
 use std::sync::Arc;
 
 use collections::HashMap;
-use gpui::{Action, AnyView, App, EventEmitter, FocusHandle, Focusable, Subscription};
+use context_server::manager::ContextServerManager;
+use context_server::ContextServerSettings;
+use gpui::{Action, AnyView, App, Entity, EventEmitter, FocusHandle, Focusable, Subscription};
 use language_model::{LanguageModelProvider, LanguageModelProviderId, LanguageModelRegistry};
+use settings::{Settings as _, SettingsStore};
 use ui::{prelude::*, Divider, DividerColor, ElevationIndex};
 use zed_actions::assistant::DeployPromptLibrary;
 
+use bearer_token_view::BearerTokenView;
+
 pub struct AssistantConfiguration {
     focus_handle: FocusHandle,
     configuration_views_by_provider: HashMap<LanguageModelProviderId, AnyView>,
+    context_server_manager: Entity<ContextServerManager>,
+    bearer_token_views_by_server: HashMap<Arc<str>, Entity<BearerTokenView>>,
     _registry_subscription: Subscription,
+    _settings_subscription: Subscription,
 }
 
 impl AssistantConfiguration {
-    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+    pub fn new(
+        context_server_manager: Entity<ContextServerManager>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
         let focus_handle = cx.focus_handle();
 
         let registry_subscription = cx.subscribe_in(
@@ -33,15 +47,52 @@ impl AssistantConfiguration {
             },
         );
 
+        let settings_subscription =
+            cx.observe_global_in::<SettingsStore>(window, |this, window, cx| {
+                this.build_bearer_token_views(window, cx);
+            });
+
         let mut this = Self {
             focus_handle,
             configuration_views_by_provider: HashMap::default(),
+            context_server_manager,
+            bearer_token_views_by_server: HashMap::default(),
             _registry_subscription: registry_subscription,
+            _settings_subscription: settings_subscription,
         };
         this.build_provider_configuration_views(window, cx);
+        this.build_bearer_token_views(window, cx);
         this
     }
 
+    /// Keeps a bearer token view for each remote context server in the settings.
+    fn build_bearer_token_views(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let remote_servers = ContextServerSettings::get_global(cx)
+            .context_servers
+            .iter()
+            .filter_map(|(id, config)| Some((id.clone(), config.remote.as_ref()?.url.clone())))
+            .collect::<Vec<_>>();
+
+        let mut views = HashMap::default();
+        for (server_id, url) in remote_servers {
+            let view = match self.bearer_token_views_by_server.remove(&server_id) {
+                Some(view) if view.read(cx).url() == url => view,
+                _ => cx.new(|cx| {
+                    BearerTokenView::new(
+                        server_id.clone(),
+                        url,
+                        self.context_server_manager.clone(),
+                        window,
+                        cx,
+                    )
+                }),
+            };
+            views.insert(server_id, view);
+        }
+        self.bearer_token_views_by_server = views;
+        cx.notify();
+    }
+
     fn build_provider_configuration_views(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         let providers = LanguageModelRegistry::read_global(cx).providers();
         for provider in providers {
@@ -195,5 +246,39 @@ impl Render for AssistantConfiguration {
                             .map(|provider| self.render_provider_configuration(&provider, cx)),
                     ),
             )
+            .when(!self.bearer_token_views_by_server.is_empty(), |parent| {
+                let mut bearer_token_views = self
+                    .bearer_token_views_by_server
+                    .iter()
+                    .collect::<Vec<_>>();
+                bearer_token_views.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                parent
+                    .child(Divider::horizontal().color(DividerColor::Border))
+                    .child(
+                        v_flex()
+                            .p(DynamicSpacing::Base16.rems(cx))
+                            .gap_4()
+                            .child(
+                                v_flex()
+                                    .gap_0p5()
+                                    .child(
+                                        Headline::new("Remote Context Servers")
+                                            .size(HeadlineSize::Small),
+                                    )
+                                    .child(
+                                        Label::new(
+                                            "Bearer tokens are stored in the system keychain under each server's URL.",
+                                        )
+                                        .color(Color::Muted),
+                                    ),
+                            )
+                            .children(
+                                bearer_token_views
+                                    .into_iter()
+                                    .map(|(_, view)| view.clone()),
+                            ),
+                    )
+            })
     }
 }
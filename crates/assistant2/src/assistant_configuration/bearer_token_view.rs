@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use context_server::manager::{self, ContextServerManager};
+use editor::{Editor, EditorElement, EditorStyle};
+use gpui::{Entity, FontStyle, Task, TextStyle, WhiteSpace};
+use settings::Settings as _;
+use theme::ThemeSettings;
+use ui::prelude::*;
+use util::ResultExt as _;
+
+/// Stores the bearer token of a remote context server in the keychain, or clears it.
+pub struct BearerTokenView {
+    server_id: Arc<str>,
+    url: String,
+    context_server_manager: Entity<ContextServerManager>,
+    token_editor: Entity<Editor>,
+    /// Whether the keychain has a token for the server, which is `None` until read.
+    has_token: Option<bool>,
+    _read_token_task: Task<()>,
+}
+
+impl BearerTokenView {
+    pub fn new(
+        server_id: Arc<str>,
+        url: String,
+        context_server_manager: Entity<ContextServerManager>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let token_editor = cx.new(|cx| {
+            let mut editor = Editor::single_line(window, cx);
+            editor.set_placeholder_text("Paste a bearer token and hit enter", cx);
+            editor
+        });
+
+        let has_token = manager::has_bearer_token(url.clone(), cx);
+        let read_token_task = cx.spawn(|this, mut cx| async move {
+            let has_token = has_token.await.log_err().unwrap_or(false);
+            this.update(&mut cx, |this, cx| {
+                this.has_token = Some(has_token);
+                cx.notify();
+            })
+            .ok();
+        });
+
+        Self {
+            server_id,
+            url,
+            context_server_manager,
+            token_editor,
+            has_token: None,
+            _read_token_task: read_token_task,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn save_token(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
+        let token = self.token_editor.read(cx).text(cx);
+        if token.trim().is_empty() {
+            return;
+        }
+        self.token_editor
+            .update(cx, |editor, cx| editor.set_text("", window, cx));
+
+        let write_token = manager::write_bearer_token(self.url.clone(), token.trim().into(), cx);
+        self.update_token(write_token, true, cx);
+    }
+
+    fn clear_token(&mut self, cx: &mut Context<Self>) {
+        let delete_token = manager::delete_bearer_token(self.url.clone(), cx);
+        self.update_token(delete_token, false, cx);
+    }
+
+    /// Restarts the server once the keychain is updated, so that it's sent the new token.
+    fn update_token(
+        &mut self,
+        update: Task<anyhow::Result<()>>,
+        has_token: bool,
+        cx: &mut Context<Self>,
+    ) {
+        let server_id = self.server_id.clone();
+        let context_server_manager = self.context_server_manager.clone();
+        cx.spawn(|this, mut cx| async move {
+            update.await?;
+            this.update(&mut cx, |this, cx| {
+                this.has_token = Some(has_token);
+                cx.notify();
+            })?;
+            context_server_manager
+                .update(&mut cx, |manager, cx| {
+                    manager.restart_server(&server_id, cx)
+                })?
+                .await
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn render_token_editor(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let settings = ThemeSettings::get_global(cx);
+        let text_style = TextStyle {
+            color: cx.theme().colors().text,
+            font_family: settings.ui_font.family.clone(),
+            font_features: settings.ui_font.features.clone(),
+            font_fallbacks: settings.ui_font.fallbacks.clone(),
+            font_size: rems(0.875).into(),
+            font_weight: settings.ui_font.weight,
+            font_style: FontStyle::Normal,
+            line_height: relative(1.3),
+            white_space: WhiteSpace::Normal,
+            ..Default::default()
+        };
+        EditorElement::new(
+            &self.token_editor,
+            EditorStyle {
+                background: cx.theme().colors().editor_background,
+                local_player: cx.theme().players().local(),
+                text: text_style,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl Render for BearerTokenView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let header = h_flex()
+            .gap_2()
+            .child(Label::new(self.server_id.clone()))
+            .child(
+                Label::new(self.url.clone())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            );
+
+        let body = match self.has_token {
+            None => div()
+                .child(Label::new("Reading the keychain...").color(Color::Muted))
+                .into_any_element(),
+            Some(true) => h_flex()
+                .justify_between()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(Icon::new(IconName::Check).color(Color::Success))
+                        .child(Label::new("Bearer token stored in the keychain.")),
+                )
+                .child(
+                    Button::new("clear-token", "Clear Token")
+                        .icon(IconName::Trash)
+                        .icon_size(IconSize::Small)
+                        .icon_position(IconPosition::Start)
+                        .on_click(cx.listener(|this, _, _, cx| this.clear_token(cx))),
+                )
+                .into_any_element(),
+            Some(false) => h_flex()
+                .w_full()
+                .px_2()
+                .py_1()
+                .bg(cx.theme().colors().editor_background)
+                .border_1()
+                .border_color(cx.theme().colors().border_variant)
+                .rounded_md()
+                .on_action(cx.listener(Self::save_token))
+                .child(self.render_token_editor(cx))
+                .into_any_element(),
+        };
+
+        v_flex().gap_1().child(header).child(body)
+    }
+}
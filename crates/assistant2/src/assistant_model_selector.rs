@@ -1,28 +1,44 @@
 use assistant_settings::AssistantSettings;
 use fs::Fs;
-use gpui::{Entity, FocusHandle, SharedString};
+use gpui::{Entity, FocusHandle, SharedString, Subscription};
 use language_model::LanguageModelRegistry;
 use language_model_selector::{LanguageModelSelector, LanguageModelSelectorPopoverMenu};
-use settings::update_settings_file;
+use settings::{update_settings_file, Settings as _, SettingsStore};
 use std::sync::Arc;
-use ui::{prelude::*, ButtonLike, PopoverMenuHandle, Tooltip};
+use ui::{prelude::*, ButtonLike, ContextMenu, PopoverMenu, PopoverMenuHandle, Tooltip};
 
+use crate::thread::{Thread, ThreadEvent};
 use crate::ToggleModelSelector;
 
 pub struct AssistantModelSelector {
     selector: Entity<LanguageModelSelector>,
     menu_handle: PopoverMenuHandle<LanguageModelSelector>,
     focus_handle: FocusHandle,
+    /// The thread whose agent profile can be picked, which inline assists don't have.
+    thread: Option<Entity<Thread>>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl AssistantModelSelector {
     pub(crate) fn new(
         fs: Arc<dyn Fs>,
+        thread: Option<Entity<Thread>>,
         menu_handle: PopoverMenuHandle<LanguageModelSelector>,
         focus_handle: FocusHandle,
         window: &mut Window,
-        cx: &mut App,
+        cx: &mut Context<Self>,
     ) -> Self {
+        let mut subscriptions = vec![cx.observe_global::<SettingsStore>(|_, cx| {
+            cx.notify();
+        })];
+        if let Some(thread) = thread.as_ref() {
+            subscriptions.push(cx.subscribe(thread, |_, _, event, cx| {
+                if matches!(event, ThreadEvent::ProfileChanged) {
+                    cx.notify();
+                }
+            }));
+        }
+
         Self {
             selector: cx.new(|cx| {
                 let fs = fs.clone();
@@ -40,48 +56,120 @@ impl AssistantModelSelector {
             }),
             menu_handle,
             focus_handle,
+            thread,
+            _subscriptions: subscriptions,
         }
     }
+
+    fn render_profile_selector(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let thread = self.thread.clone()?;
+        let settings = AssistantSettings::get_global(cx);
+        let (active_profile_id, active_profile) = settings.profile(thread.read(cx).profile_id())?;
+        let active_profile_id = active_profile_id.to_string();
+        let profiles = settings
+            .profiles
+            .iter()
+            .map(|(id, profile)| (id.clone(), SharedString::from(profile.name.clone())))
+            .collect::<Vec<_>>();
+
+        Some(
+            PopoverMenu::new("agent-profile-selector")
+                .trigger(
+                    ButtonLike::new("active-profile")
+                        .style(ButtonStyle::Subtle)
+                        .child(
+                            h_flex()
+                                .gap_0p5()
+                                .child(
+                                    Label::new(active_profile.name.clone())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                )
+                                .child(
+                                    Icon::new(IconName::ChevronDown)
+                                        .color(Color::Muted)
+                                        .size(IconSize::XSmall),
+                                ),
+                        )
+                        .tooltip(Tooltip::text("Change Profile")),
+                )
+                .anchor(gpui::Corner::BottomRight)
+                .menu(move |window, cx| {
+                    let thread = thread.clone();
+                    let profiles = profiles.clone();
+                    let active_profile_id = active_profile_id.clone();
+                    Some(ContextMenu::build(
+                        window,
+                        cx,
+                        move |mut menu, _window, _cx| {
+                            for (profile_id, name) in profiles {
+                                let thread = thread.clone();
+                                menu = menu.toggleable_entry(
+                                    name,
+                                    profile_id == active_profile_id,
+                                    IconPosition::Start,
+                                    None,
+                                    move |_window, cx| {
+                                        thread.update(cx, |thread, cx| {
+                                            thread.set_profile_id(Some(profile_id.clone()), cx)
+                                        });
+                                    },
+                                );
+                            }
+                            menu
+                        },
+                    ))
+                }),
+        )
+    }
 }
 
 impl Render for AssistantModelSelector {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let active_model = LanguageModelRegistry::read_global(cx).active_model();
+        let active_model = match self.thread.as_ref() {
+            Some(thread) => thread.read(cx).model(cx),
+            None => LanguageModelRegistry::read_global(cx).active_model(),
+        };
         let focus_handle = self.focus_handle.clone();
         let model_name = match active_model {
             Some(model) => model.name().0,
             _ => SharedString::from("No model selected"),
         };
 
-        LanguageModelSelectorPopoverMenu::new(
-            self.selector.clone(),
-            ButtonLike::new("active-model")
-                .style(ButtonStyle::Subtle)
-                .child(
-                    h_flex()
-                        .gap_0p5()
-                        .child(
-                            Label::new(model_name)
-                                .size(LabelSize::Small)
-                                .color(Color::Muted),
-                        )
+        h_flex()
+            .gap_0p5()
+            .children(self.render_profile_selector(cx))
+            .child(
+                LanguageModelSelectorPopoverMenu::new(
+                    self.selector.clone(),
+                    ButtonLike::new("active-model")
+                        .style(ButtonStyle::Subtle)
                         .child(
-                            Icon::new(IconName::ChevronDown)
-                                .color(Color::Muted)
-                                .size(IconSize::XSmall),
+                            h_flex()
+                                .gap_0p5()
+                                .child(
+                                    Label::new(model_name)
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                )
+                                .child(
+                                    Icon::new(IconName::ChevronDown)
+                                        .color(Color::Muted)
+                                        .size(IconSize::XSmall),
+                                ),
                         ),
-                ),
-            move |window, cx| {
-                Tooltip::for_action_in(
-                    "Change Model",
-                    &ToggleModelSelector,
-                    &focus_handle,
-                    window,
-                    cx,
+                    move |window, cx| {
+                        Tooltip::for_action_in(
+                            "Change Model",
+                            &ToggleModelSelector,
+                            &focus_handle,
+                            window,
+                            cx,
+                        )
+                    },
+                    gpui::Corner::BottomRight,
                 )
-            },
-            gpui::Corner::BottomRight,
-        )
-        .with_handle(self.menu_handle.clone())
+                .with_handle(self.menu_handle.clone()),
+            )
     }
 }
@@ -3,19 +3,22 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use assistant_context_editor::{
-    make_lsp_adapter_delegate, render_remaining_tokens, AssistantPanelDelegate, ConfigurationError,
-    ContextEditor, SlashCommandCompletionProvider,
+    humanize_token_count, make_lsp_adapter_delegate, render_remaining_tokens,
+    AssistantPanelDelegate, ConfigurationError, ContextEditor, SlashCommandCompletionProvider,
 };
 use assistant_settings::{AssistantDockPosition, AssistantSettings};
 use assistant_slash_command::SlashCommandWorkingSet;
 use assistant_tool::ToolWorkingSet;
 
+use call::ActiveCall;
+use channel::ChannelStore;
 use client::zed_urls;
 use editor::Editor;
 use fs::Fs;
 use gpui::{
-    prelude::*, px, svg, Action, AnyElement, App, AsyncWindowContext, Corner, Entity, EventEmitter,
-    FocusHandle, Focusable, FontWeight, Pixels, Subscription, Task, UpdateGlobal, WeakEntity,
+    prelude::*, px, svg, Action, AnyElement, App, AsyncWindowContext, ClipboardItem, Corner,
+    Entity, EventEmitter, FocusHandle, Focusable, FontWeight, PathPromptOptions, Pixels,
+    Subscription, Task, UpdateGlobal, WeakEntity,
 };
 use language::LanguageRegistry;
 use language_model::{LanguageModelProviderTosView, LanguageModelRegistry};
@@ -26,17 +29,24 @@ use time::UtcOffset;
 use ui::{prelude::*, ContextMenu, KeyBinding, PopoverMenu, PopoverMenuHandle, Tab, Tooltip};
 use util::ResultExt as _;
 use workspace::dock::{DockPosition, Panel, PanelEvent};
-use workspace::Workspace;
+use workspace::notifications::{DetachAndPromptErr, NotificationId};
+use workspace::{OpenChannelNotes, Toast, Workspace};
 use zed_actions::assistant::{DeployPromptLibrary, ToggleFocus};
 
 use crate::active_thread::ActiveThread;
 use crate::assistant_configuration::{AssistantConfiguration, AssistantConfigurationEvent};
+use crate::code_review;
 use crate::history_store::{HistoryEntry, HistoryStore};
 use crate::message_editor::MessageEditor;
-use crate::thread::{Thread, ThreadError, ThreadId};
+use crate::thread::{format_cost, token_usage_cost, Thread, ThreadError, ThreadId};
+use crate::thread_export::ExportedThread;
 use crate::thread_history::{PastContext, PastThread, ThreadHistory};
 use crate::thread_store::ThreadStore;
-use crate::{InlineAssistant, NewPromptEditor, NewThread, OpenConfiguration, OpenHistory};
+use crate::{
+    CopyThreadAsMarkdown, ExportThreadAsJson, ExportThreadAsMarkdown, ImportThread,
+    InlineAssistant, NewPromptEditor, NewThread, OpenConfiguration, OpenHistory,
+    ReviewBranchChanges, ReviewStagedChanges, ShareThreadToChannelNotes,
+};
 
 pub fn init(cx: &mut App) {
     cx.observe_new(
@@ -60,17 +70,29 @@ pub fn init(cx: &mut App) {
                         panel.update(cx, |panel, cx| panel.new_prompt_editor(window, cx));
                     }
                 })
+                .register_action(|workspace, action: &ImportThread, window, cx| {
+                    if let Some(panel) = workspace.panel::<AssistantPanel>(cx) {
+                        workspace.focus_panel::<AssistantPanel>(window, cx);
+                        panel.update(cx, |panel, cx| panel.import_thread(action, window, cx));
+                    }
+                })
                 .register_action(|workspace, _: &OpenConfiguration, window, cx| {
                     if let Some(panel) = workspace.panel::<AssistantPanel>(cx) {
                         workspace.focus_panel::<AssistantPanel>(window, cx);
                         panel.update(cx, |panel, cx| panel.open_configuration(window, cx));
                     }
                 });
+            code_review::register_actions(workspace);
         },
     )
     .detach();
 }
 
+enum ThreadExportFormat {
+    Markdown,
+    Json,
+}
+
 enum ActiveView {
     Thread,
     PromptEditor,
@@ -374,36 +396,156 @@ impl AssistantPanel {
         cx.spawn_in(window, |this, mut cx| async move {
             let thread = open_thread_task.await?;
             this.update_in(&mut cx, |this, window, cx| {
-                this.active_view = ActiveView::Thread;
-                this.thread = cx.new(|cx| {
-                    ActiveThread::new(
-                        thread.clone(),
-                        this.thread_store.clone(),
-                        this.workspace.clone(),
-                        this.language_registry.clone(),
-                        this.tools.clone(),
-                        window,
-                        cx,
-                    )
-                });
-                this.message_editor = cx.new(|cx| {
-                    MessageEditor::new(
-                        this.fs.clone(),
-                        this.workspace.clone(),
-                        this.thread_store.downgrade(),
-                        thread,
-                        window,
+                this.set_active_thread(thread, window, cx);
+            })
+        })
+    }
+
+    fn set_active_thread(
+        &mut self,
+        thread: Entity<Thread>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.active_view = ActiveView::Thread;
+        self.thread = cx.new(|cx| {
+            ActiveThread::new(
+                thread.clone(),
+                self.thread_store.clone(),
+                self.workspace.clone(),
+                self.language_registry.clone(),
+                self.tools.clone(),
+                window,
+                cx,
+            )
+        });
+        self.message_editor = cx.new(|cx| {
+            MessageEditor::new(
+                self.fs.clone(),
+                self.workspace.clone(),
+                self.thread_store.downgrade(),
+                thread,
+                window,
+                cx,
+            )
+        });
+        self.message_editor.focus_handle(cx).focus(window);
+    }
+
+    fn exported_thread(&self, cx: &App) -> ExportedThread {
+        ExportedThread::from_thread(self.active_thread(cx).read(cx))
+    }
+
+    fn copy_thread_as_markdown(
+        &mut self,
+        _: &CopyThreadAsMarkdown,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let markdown = self.exported_thread(cx).to_markdown();
+        cx.write_to_clipboard(ClipboardItem::new_string(markdown));
+    }
+
+    fn export_thread(&mut self, format: ThreadExportFormat, cx: &mut Context<Self>) {
+        let exported = self.exported_thread(cx);
+        let contents = match format {
+            ThreadExportFormat::Markdown => Ok(exported.to_markdown()),
+            ThreadExportFormat::Json => exported.to_json(),
+        };
+        let directory = self
+            .project
+            .read(cx)
+            .visible_worktrees(cx)
+            .next()
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .unwrap_or_else(|| paths::home_dir().clone());
+        let path = cx.prompt_for_new_path(&directory);
+        let fs = self.fs.clone();
+
+        cx.spawn(|_, _| async move {
+            let Some(path) = path.await?? else {
+                return Ok(());
+            };
+            fs.atomic_write(path, contents?).await
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn import_thread(&mut self, _: &ImportThread, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+        let fs = self.fs.clone();
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            let Some(path) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            let json = fs.load(&path).await?;
+            this.update_in(&mut cx, |this, window, cx| {
+                let thread = this
+                    .thread_store
+                    .update(cx, |thread_store, cx| thread_store.import_thread(&json, cx))?;
+                this.set_active_thread(thread, window, cx);
+                anyhow::Ok(())
+            })?
+        })
+        .detach_and_prompt_err("Failed to import thread", window, cx, |_, _, _| None);
+    }
+
+    /// Appends the thread to the notes of the channel of the current call.
+    fn share_thread_to_channel_notes(
+        &mut self,
+        _: &ShareThreadToChannelNotes,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(channel_id) = ActiveCall::global(cx).read(cx).channel_id(cx) else {
+            self.workspace
+                .update(cx, |workspace, cx| {
+                    workspace.show_toast(
+                        Toast::new(
+                            NotificationId::unique::<ShareThreadToChannelNotes>(),
+                            "Join a channel to share threads to its notes.",
+                        )
+                        .autohide(),
                         cx,
-                    )
+                    );
+                })
+                .ok();
+            return;
+        };
+        let markdown = self.exported_thread(cx).to_markdown();
+        let open_channel_buffer = ChannelStore::global(cx).update(cx, |channel_store, cx| {
+            channel_store.open_channel_buffer(channel_id, cx)
+        });
+
+        cx.spawn_in(window, |_, mut cx| async move {
+            let channel_buffer = open_channel_buffer.await?;
+            channel_buffer.update(&mut cx, |channel_buffer, cx| {
+                channel_buffer.buffer().update(cx, |buffer, cx| {
+                    let end = buffer.len();
+                    let separator = if end == 0 { "" } else { "\n\n" };
+                    buffer.edit([(end..end, format!("{separator}{markdown}\n"))], None, cx);
                 });
-                this.message_editor.focus_handle(cx).focus(window);
-            })
+            })?;
+            cx.update(|window, cx| window.dispatch_action(Box::new(OpenChannelNotes), cx))
         })
+        .detach_and_prompt_err(
+            "Failed to share thread to channel notes",
+            window,
+            cx,
+            |_, _, _| None,
+        );
     }
 
     pub(crate) fn open_configuration(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         self.active_view = ActiveView::Configuration;
-        self.configuration = Some(cx.new(|cx| AssistantConfiguration::new(window, cx)));
+        let context_server_manager = self.thread_store.read(cx).context_server_manager();
+        self.configuration =
+            Some(cx.new(|cx| AssistantConfiguration::new(context_server_manager, window, cx)));
 
         if let Some(configuration) = self.configuration.as_ref() {
             self.configuration_subscription = Some(cx.subscribe_in(
@@ -608,6 +750,12 @@ impl AssistantPanel {
                         self.context_editor
                             .as_ref()
                             .and_then(|editor| render_remaining_tokens(editor, cx))
+                            .map(IntoElement::into_any_element)
+                    } else {
+                        None
+                    })
+                    .children(if matches!(self.active_view, ActiveView::Thread) {
+                        self.render_token_usage(cx)
                     } else {
                         None
                     }),
@@ -633,9 +781,50 @@ impl AssistantPanel {
                                 Some(ContextMenu::build(window, cx, |menu, _window, _cx| {
                                     menu.action("New Thread", NewThread.boxed_clone())
                                         .action("New Prompt Editor", NewPromptEditor.boxed_clone())
+                                        .separator()
+                                        .action("Import Thread…", ImportThread.boxed_clone())
+                                        .separator()
+                                        .action(
+                                            "Review Staged Changes",
+                                            ReviewStagedChanges.boxed_clone(),
+                                        )
+                                        .action(
+                                            "Review Branch Changes",
+                                            ReviewBranchChanges::default().boxed_clone(),
+                                        )
                                 }))
                             }),
                     )
+                    .when(matches!(self.active_view, ActiveView::Thread), |this| {
+                        this.child(
+                            PopoverMenu::new("assistant-toolbar-thread-popover-menu")
+                                .trigger_with_tooltip(
+                                    IconButton::new("thread-options", IconName::Ellipsis)
+                                        .icon_size(IconSize::Small)
+                                        .style(ButtonStyle::Subtle),
+                                    Tooltip::text("Thread Options"),
+                                )
+                                .anchor(Corner::TopRight)
+                                .menu(move |window, cx| {
+                                    Some(ContextMenu::build(window, cx, |menu, _window, _cx| {
+                                        menu.action(
+                                            "Copy as Markdown",
+                                            CopyThreadAsMarkdown.boxed_clone(),
+                                        )
+                                        .action(
+                                            "Export as Markdown…",
+                                            ExportThreadAsMarkdown.boxed_clone(),
+                                        )
+                                        .action("Export as JSON…", ExportThreadAsJson.boxed_clone())
+                                        .separator()
+                                        .action(
+                                            "Share to Channel Notes",
+                                            ShareThreadToChannelNotes.boxed_clone(),
+                                        )
+                                    }))
+                                }),
+                        )
+                    })
                     .child(
                         IconButton::new("open-history", IconName::HistoryRerun)
                             .icon_size(IconSize::Small)
@@ -820,6 +1009,47 @@ impl AssistantPanel {
             })
     }
 
+    fn render_token_usage(&self, cx: &App) -> Option<AnyElement> {
+        let thread = self.thread.read(cx).thread().read(cx);
+        let usage = thread.total_token_usage();
+        if usage.is_empty() {
+            return None;
+        }
+        let cost = token_usage_cost(thread.token_usage(), cx);
+
+        let tooltip = format!(
+            "Input: {}\nOutput: {}\nCache writes: {}\nCache reads: {}",
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_creation_input_tokens,
+            usage.cache_read_input_tokens
+        );
+
+        Some(
+            h_flex()
+                .id("thread-token-usage")
+                .flex_none()
+                .gap_1()
+                .child(
+                    Label::new(format!(
+                        "{} tokens",
+                        humanize_token_count(usage.total_tokens() as usize)
+                    ))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+                )
+                .when_some(cost, |this, cost| {
+                    this.child(
+                        Label::new(format_cost(cost))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                })
+                .tooltip(Tooltip::text(tooltip))
+                .into_any_element(),
+        )
+    }
+
     fn render_last_error(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
         let last_error = self.thread.read(cx).last_error()?;
 
@@ -838,6 +1068,9 @@ impl AssistantPanel {
                     ThreadError::MaxMonthlySpendReached => {
                         self.render_max_monthly_spend_reached_error(cx)
                     }
+                    ThreadError::MonthlyBudget { spent, budget } => {
+                        self.render_monthly_budget_warning(spent, budget, cx)
+                    }
                     ThreadError::Message(error_message) => {
                         self.render_error_message(&error_message, cx)
                     }
@@ -940,6 +1173,50 @@ impl AssistantPanel {
             .into_any()
     }
 
+    fn render_monthly_budget_warning(
+        &self,
+        spent: f64,
+        budget: f64,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let (title, color) = if spent >= budget {
+            ("Monthly Budget Exceeded", Color::Error)
+        } else {
+            ("Monthly Budget Almost Spent", Color::Warning)
+        };
+        let message = format!(
+            "The tokens used this month cost an estimated {} of your {} budget.",
+            format_cost(spent),
+            format_cost(budget)
+        );
+
+        v_flex()
+            .gap_0p5()
+            .child(
+                h_flex()
+                    .gap_1p5()
+                    .items_center()
+                    .child(Icon::new(IconName::Warning).color(color))
+                    .child(Label::new(title).weight(FontWeight::MEDIUM)),
+            )
+            .child(Label::new(message))
+            .child(
+                h_flex()
+                    .justify_end()
+                    .mt_1()
+                    .child(Button::new("dismiss", "Dismiss").on_click(cx.listener(
+                        |this, _, _, cx| {
+                            this.thread.update(cx, |this, _cx| {
+                                this.clear_last_error();
+                            });
+
+                            cx.notify();
+                        },
+                    ))),
+            )
+            .into_any()
+    }
+
     fn render_error_message(
         &self,
         error_message: &SharedString,
@@ -996,6 +1273,17 @@ impl Render for AssistantPanel {
                 this.open_history(window, cx);
             }))
             .on_action(cx.listener(Self::deploy_prompt_library))
+            .on_action(cx.listener(Self::copy_thread_as_markdown))
+            .on_action(
+                cx.listener(|this, _: &ExportThreadAsMarkdown, _window, cx| {
+                    this.export_thread(ThreadExportFormat::Markdown, cx);
+                }),
+            )
+            .on_action(cx.listener(|this, _: &ExportThreadAsJson, _window, cx| {
+                this.export_thread(ThreadExportFormat::Json, cx);
+            }))
+            .on_action(cx.listener(Self::import_thread))
+            .on_action(cx.listener(Self::share_thread_to_channel_notes))
             .child(self.render_toolbar(cx))
             .map(|parent| match self.active_view {
                 ActiveView::Thread => parent
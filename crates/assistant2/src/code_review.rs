@@ -0,0 +1,1330 @@
+use std::any::{Any, TypeId};
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context as _, Result};
+use buffer_diff::{BufferDiff, BufferDiffEvent};
+use collections::HashSet;
+use db::kvp::KEY_VALUE_STORE;
+use editor::display_map::{BlockPlacement, BlockProperties, BlockStyle, CustomBlockId};
+use editor::{Editor, EditorEvent};
+use git::repository::{DiffType, RepoPath};
+use gpui::{
+    AnyElement, AnyView, App, AsyncApp, Entity, EventEmitter, FocusHandle, Focusable, Subscription,
+    Task, WeakEntity,
+};
+use language::{Anchor, Buffer, BufferEvent, Capability, OffsetRangeExt as _, Point, ToPoint as _};
+use language_model::{
+    LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage, LanguageModelTool,
+    MessageContent, Role,
+};
+use multi_buffer::{MultiBuffer, PathKey};
+use project::git::Repository;
+use project::{Project, ProjectPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use text::BufferId;
+use ui::{prelude::*, Tooltip};
+use util::ResultExt as _;
+use workspace::item::{BreadcrumbText, ItemEvent, TabContentParams};
+use workspace::notifications::NotificationId;
+use workspace::searchable::SearchableItemHandle;
+use workspace::{Item, ItemNavHistory, Toast, ToolbarItemLocation, Workspace};
+
+use crate::{ReviewBranchChanges, ReviewStagedChanges};
+
+const CODE_REVIEW_NAMESPACE: &str = "code_review";
+
+/// The branches a branch review compares against when none is given, in order of preference.
+const DEFAULT_BASE_BRANCHES: &[&str] = &["origin/HEAD", "main", "master"];
+
+/// How many unchanged lines surround each hunk in the diff the model reviews.
+const PROMPT_DIFF_CONTEXT_LINES: u32 = 3;
+
+const REVIEW_PROMPT: &str = "You are reviewing a change to a codebase. Below is the diff of every \
+file in the change. Lines starting with `+` were added, lines starting with `-` were removed, and \
+lines starting with a space are unchanged context. Each line that exists after the change is \
+prefixed with its 1-based line number in the changed file.
+
+Review the change like an experienced colleague would. Point out bugs, incorrect logic, missing \
+error handling, security issues and confusing code. Don't comment on code that's fine, and don't \
+restate what the change does. Only comment on lines that exist after the change, referring to \
+them by the line numbers in the diff. When you can show how to fix the problem, include the full \
+replacement text of the commented lines as a suggestion.";
+
+/// What a [`CodeReview`] reviews.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReviewTarget {
+    /// The changes staged in the index, compared to `HEAD`.
+    StagedChanges,
+    /// The changes in the working tree, compared to where the current branch forked from `base`.
+    /// Without a base, the first of [`DEFAULT_BASE_BRANCHES`] that exists is used.
+    Branch { base: Option<String> },
+}
+
+impl ReviewTarget {
+    fn label(&self) -> String {
+        match self {
+            ReviewTarget::StagedChanges => "Staged Changes".to_string(),
+            ReviewTarget::Branch { base: Some(base) } => format!("Changes Since {base}"),
+            ReviewTarget::Branch { base: None } => "Branch Changes".to_string(),
+        }
+    }
+
+    fn persistence_key(&self) -> String {
+        match self {
+            ReviewTarget::StagedChanges => "staged".to_string(),
+            ReviewTarget::Branch { base } => {
+                format!("branch:{}", base.as_deref().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// A review of a repository's changes by the active language model, whose comments are shown
+/// below the lines they're about, and whose suggestions can be applied to the files.
+///
+/// The comments are saved as they're resolved, and restored the next time the same changes are
+/// reviewed, so a review survives reloading the workspace.
+pub struct CodeReview {
+    project: Entity<Project>,
+    repository: Entity<Repository>,
+    /// The target the review was opened for, before the base of a branch review is resolved.
+    requested_target: ReviewTarget,
+    target: ReviewTarget,
+    multibuffer: Entity<MultiBuffer>,
+    editor: Entity<Editor>,
+    focus_handle: FocusHandle,
+    files: Vec<ReviewedFile>,
+    comments: Vec<ReviewComment>,
+    block_ids: HashSet<CustomBlockId>,
+    status: ReviewStatus,
+    persistence_key: Option<String>,
+    load_task: Task<()>,
+    review_task: Task<()>,
+}
+
+enum ReviewStatus {
+    Loading,
+    Reviewing,
+    Ready,
+    Failed(SharedString),
+}
+
+struct ReviewedFile {
+    repo_path: RepoPath,
+    path_key: PathKey,
+    buffer: Entity<Buffer>,
+    /// The file's text before the change.
+    base_text: String,
+    base_buffer: Entity<Buffer>,
+    /// The file's text after the change, which the model's line numbers refer to.
+    new_text: String,
+    diff: Entity<BufferDiff>,
+    recalculate_diff_task: Task<()>,
+    _subscriptions: Vec<Subscription>,
+}
+
+struct ReviewComment {
+    path: RepoPath,
+    buffer: Entity<Buffer>,
+    range: Range<Anchor>,
+    severity: Severity,
+    message: SharedString,
+    suggestion: Option<String>,
+    state: CommentState,
+    /// Whether the lines the comment was about couldn't be found when it was restored.
+    outdated: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Severity {
+    /// A bug, or code that won't work as intended.
+    Error,
+    /// Code that works, but is risky or likely to cause problems.
+    Warning,
+    /// A possible improvement.
+    #[default]
+    Suggestion,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+            Severity::Suggestion => "Suggestion",
+        }
+    }
+
+    fn icon(&self) -> (IconName, Color) {
+        match self {
+            Severity::Error => (IconName::XCircle, Color::Error),
+            Severity::Warning => (IconName::Warning, Color::Warning),
+            Severity::Suggestion => (IconName::Info, Color::Info),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CommentState {
+    Open,
+    Applied,
+    Dismissed,
+}
+
+/// The comments the model is asked to respond with.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ReviewComments {
+    /// The review comments, at most one per problem. Leave it empty if the change looks good.
+    comments: Vec<ReviewCommentInput>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ReviewCommentInput {
+    /// The path of the commented file, exactly as it's written in the diff.
+    path: String,
+    /// The 1-based number of the first line the comment is about, in the changed file.
+    start_line: u32,
+    /// The 1-based number of the last line the comment is about, in the changed file.
+    end_line: u32,
+    #[serde(default)]
+    severity: Severity,
+    /// The comment, in a sentence or two.
+    message: String,
+    /// The text to replace the lines from `start_line` to `end_line` with, including their
+    /// indentation, if the comment has a concrete fix.
+    #[serde(default)]
+    suggestion: Option<String>,
+}
+
+impl LanguageModelTool for ReviewComments {
+    fn name() -> String {
+        "review_comments".to_string()
+    }
+
+    fn description() -> String {
+        "Reports line-anchored review comments on a change".to_string()
+    }
+}
+
+/// A review comment as it's persisted between sessions.
+#[derive(Debug, Deserialize, Serialize)]
+struct SerializedComment {
+    path: PathBuf,
+    start_row: u32,
+    end_row: u32,
+    /// The text of the commented lines, to find them again if the file changed.
+    text: String,
+    severity: Severity,
+    message: String,
+    suggestion: Option<String>,
+    state: CommentState,
+}
+
+impl CodeReview {
+    /// Opens the review of the active repository's changes, or activates it if it's already open.
+    pub fn deploy(
+        target: ReviewTarget,
+        workspace: &mut Workspace,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) {
+        let project = workspace.project().clone();
+        let Some(repository) = project.read(cx).active_repository(cx) else {
+            workspace.show_toast(
+                Toast::new(
+                    NotificationId::unique::<CodeReview>(),
+                    "Open a git repository to review its changes.",
+                )
+                .autohide(),
+                cx,
+            );
+            return;
+        };
+
+        let existing = workspace.items_of_type::<CodeReview>(cx).find(|review| {
+            let review = review.read(cx);
+            review.repository == repository && review.requested_target == target
+        });
+        if let Some(existing) = existing {
+            workspace.activate_item(&existing, true, true, window, cx);
+        } else {
+            let review = cx.new(|cx| CodeReview::new(project, repository, target, window, cx));
+            workspace.add_item_to_active_pane(Box::new(review), None, true, window, cx);
+        }
+    }
+
+    fn new(
+        project: Entity<Project>,
+        repository: Entity<Repository>,
+        target: ReviewTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let focus_handle = cx.focus_handle();
+        let multibuffer = cx.new(|_| MultiBuffer::new(Capability::ReadWrite));
+        let editor = cx.new(|cx| {
+            let mut editor = Editor::for_multibuffer(
+                multibuffer.clone(),
+                Some(project.clone()),
+                true,
+                window,
+                cx,
+            );
+            editor.set_expand_all_diff_hunks(cx);
+            editor
+        });
+
+        let mut this = Self {
+            project,
+            repository,
+            requested_target: target.clone(),
+            target,
+            multibuffer,
+            editor,
+            focus_handle,
+            files: Vec::new(),
+            comments: Vec::new(),
+            block_ids: HashSet::default(),
+            status: ReviewStatus::Loading,
+            persistence_key: None,
+            load_task: Task::ready(()),
+            review_task: Task::ready(()),
+        };
+        this.load(cx);
+        this
+    }
+
+    /// Loads the changed files, then restores the saved review of them, or starts a new one.
+    fn load(&mut self, cx: &mut Context<Self>) {
+        self.status = ReviewStatus::Loading;
+        let project = self.project.clone();
+        let repository = self.repository.clone();
+        let target = self.target.clone();
+        self.load_task = cx.spawn(|this, mut cx| async move {
+            let result = async {
+                let (target, file_diffs) = load_diff(&repository, target, &cx).await?;
+
+                let mut files = Vec::new();
+                for file_diff in file_diffs {
+                    // Deleted files have no lines left to comment on.
+                    let Some(new_text) = file_diff.new_text else {
+                        continue;
+                    };
+                    let Some(project_path) = repository.read_with(&cx, |repository, _| {
+                        repository.repo_path_to_project_path(&file_diff.path)
+                    })?
+                    else {
+                        continue;
+                    };
+                    let Some(buffer) = project
+                        .update(&mut cx, |project, cx| project.open_buffer(project_path, cx))?
+                        .await
+                        .log_err()
+                    else {
+                        continue;
+                    };
+                    files.push((
+                        file_diff.path,
+                        buffer,
+                        file_diff.old_text.unwrap_or_default(),
+                        new_text,
+                    ));
+                }
+
+                let persistence_key = persistence_key(&project, &repository, &target, &cx)?;
+                let saved_comments = if let Some(key) = &persistence_key {
+                    let key = key.clone();
+                    cx.background_spawn(async move { KEY_VALUE_STORE.read_kvp(&key) })
+                        .await
+                        .log_err()
+                        .flatten()
+                        .and_then(|json| {
+                            serde_json::from_str::<Vec<SerializedComment>>(&json).log_err()
+                        })
+                } else {
+                    None
+                };
+
+                this.update(&mut cx, |this, cx| {
+                    this.target = target;
+                    this.persistence_key = persistence_key;
+                    for (repo_path, buffer, base_text, new_text) in files {
+                        this.register_file(repo_path, buffer, base_text, new_text, cx);
+                    }
+                    if let Some(saved_comments) = saved_comments {
+                        this.restore_comments(saved_comments, cx);
+                        this.status = ReviewStatus::Ready;
+                    } else if this.files.is_empty() {
+                        this.status = ReviewStatus::Ready;
+                    } else {
+                        this.run_review(cx);
+                    }
+                    cx.notify();
+                })
+            }
+            .await;
+
+            if let Err(error) = result {
+                this.update(&mut cx, |this, cx| {
+                    this.status = ReviewStatus::Failed(format!("{error:#}").into());
+                    cx.notify();
+                })
+                .ok();
+            }
+        });
+        cx.notify();
+    }
+
+    fn register_file(
+        &mut self,
+        repo_path: RepoPath,
+        buffer: Entity<Buffer>,
+        base_text: String,
+        new_text: String,
+        cx: &mut Context<Self>,
+    ) {
+        let base_buffer = cx.new(|cx| {
+            let mut base_buffer = Buffer::local(base_text.clone(), cx);
+            if let Some(language_registry) = buffer.read(cx).language_registry() {
+                base_buffer.set_language_registry(language_registry);
+            }
+            base_buffer.set_language(buffer.read(cx).language().cloned(), cx);
+            base_buffer
+        });
+        let diff = cx.new(|cx| {
+            let snapshot = buffer.read(cx).text_snapshot();
+            let mut diff = BufferDiff::new(&snapshot);
+            let _ = diff.set_base_text(base_buffer.clone(), snapshot, cx);
+            diff
+        });
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            multibuffer.add_diff(diff.clone(), cx);
+        });
+
+        let buffer_id = buffer.read(cx).remote_id();
+        let subscriptions = vec![
+            cx.subscribe(&buffer, move |this, _, event, cx| {
+                if matches!(event, BufferEvent::Edited) {
+                    this.recalculate_diff(buffer_id, cx);
+                }
+            }),
+            cx.subscribe(&diff, move |this, _, event, cx| {
+                if let BufferDiffEvent::DiffChanged { .. } = event {
+                    this.update_excerpts(buffer_id, cx);
+                }
+            }),
+        ];
+
+        self.files.push(ReviewedFile {
+            path_key: PathKey::namespaced(CODE_REVIEW_NAMESPACE, repo_path.0.clone()),
+            repo_path,
+            buffer,
+            base_text,
+            base_buffer,
+            new_text,
+            diff,
+            recalculate_diff_task: Task::ready(()),
+            _subscriptions: subscriptions,
+        });
+    }
+
+    fn file_for_buffer(&self, buffer_id: BufferId, cx: &App) -> Option<&ReviewedFile> {
+        self.files
+            .iter()
+            .find(|file| file.buffer.read(cx).remote_id() == buffer_id)
+    }
+
+    fn recalculate_diff(&mut self, buffer_id: BufferId, cx: &mut Context<Self>) {
+        let Some(file) = self
+            .files
+            .iter_mut()
+            .find(|file| file.buffer.read(cx).remote_id() == buffer_id)
+        else {
+            return;
+        };
+        let buffer = file.buffer.clone();
+        let diff = file.diff.clone();
+        let base_buffer = file.base_buffer.clone();
+        file.recalculate_diff_task = cx.spawn(|_, mut cx| async move {
+            cx.background_executor()
+                .timer(Duration::from_millis(50))
+                .await;
+            diff.update(&mut cx, |diff, cx| {
+                let snapshot = buffer.read(cx).text_snapshot();
+                let _ = diff.set_base_text(base_buffer, snapshot, cx);
+            })
+            .log_err();
+        });
+    }
+
+    /// Shows an excerpt around each hunk of the file and each of its open comments.
+    fn update_excerpts(&mut self, buffer_id: BufferId, cx: &mut Context<Self>) {
+        let Some(file) = self.file_for_buffer(buffer_id, cx) else {
+            return;
+        };
+        let snapshot = file.buffer.read(cx).snapshot();
+        let mut ranges = file
+            .diff
+            .read(cx)
+            .hunks_intersecting_range(Anchor::MIN..Anchor::MAX, &snapshot, cx)
+            .map(|hunk| hunk.buffer_range.to_point(&snapshot))
+            .collect::<Vec<_>>();
+        ranges.extend(
+            self.comments
+                .iter()
+                .filter(|comment| {
+                    comment.state == CommentState::Open
+                        && comment.buffer.read(cx).remote_id() == buffer_id
+                })
+                .map(|comment| comment.range.to_point(&snapshot)),
+        );
+        ranges.sort_by_key(|range| range.start);
+
+        let path_key = file.path_key.clone();
+        let buffer = file.buffer.clone();
+        self.multibuffer.update(cx, |multibuffer, cx| {
+            if ranges.is_empty() {
+                multibuffer.remove_excerpts_for_path(path_key, cx);
+            } else {
+                multibuffer.set_excerpts_for_path(
+                    path_key,
+                    buffer,
+                    ranges,
+                    editor::DEFAULT_MULTIBUFFER_CONTEXT,
+                    cx,
+                );
+            }
+        });
+        self.refresh_blocks(cx);
+    }
+
+    /// Asks the active model to review the changes, replacing the current comments.
+    fn run_review(&mut self, cx: &mut Context<Self>) {
+        let Some(model) = LanguageModelRegistry::read_global(cx).active_model() else {
+            self.status = ReviewStatus::Failed("No language model is configured.".into());
+            cx.notify();
+            return;
+        };
+
+        let mut prompt = REVIEW_PROMPT.to_string();
+        for file in &self.files {
+            let old_text = (!file.base_text.is_empty()).then_some(file.base_text.as_str());
+            write!(
+                prompt,
+                "\n\n```diff {}\n{}```",
+                file.repo_path.display(),
+                numbered_diff(old_text, &file.new_text, PROMPT_DIFF_CONTEXT_LINES)
+            )
+            .unwrap();
+        }
+        let request = LanguageModelRequest {
+            messages: vec![LanguageModelRequestMessage {
+                role: Role::User,
+                content: vec![MessageContent::Text(prompt)],
+                cache: false,
+            }],
+            tools: Vec::new(),
+            stop: Vec::new(),
+            temperature: None,
+        };
+
+        self.status = ReviewStatus::Reviewing;
+        self.review_task = cx.spawn(|this, mut cx| async move {
+            let response = model.use_tool::<ReviewComments>(request, &cx).await;
+            this.update(&mut cx, |this, cx| {
+                match response {
+                    Ok(response) => {
+                        this.set_comments(response.comments, cx);
+                        this.status = ReviewStatus::Ready;
+                        this.save_comments(cx);
+                    }
+                    Err(error) => {
+                        this.status = ReviewStatus::Failed(format!("{error:#}").into());
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        });
+        cx.notify();
+    }
+
+    fn set_comments(&mut self, inputs: Vec<ReviewCommentInput>, cx: &mut Context<Self>) {
+        self.comments.clear();
+        for input in inputs {
+            let Some(file) = self
+                .files
+                .iter()
+                .find(|file| file.repo_path.0.as_ref() == Path::new(&input.path))
+            else {
+                log::warn!("review comment on an unknown file: {}", input.path);
+                continue;
+            };
+
+            // The model's line numbers refer to the changed file, which the buffer may have
+            // diverged from, like when reviewing staged changes to a file with unstaged edits.
+            let snapshot = file.buffer.read(cx).snapshot();
+            let edits = language::line_diff(&file.new_text, &snapshot.text());
+            let start_row = input.start_line.saturating_sub(1);
+            let end_row = input.end_line.saturating_sub(1).max(start_row);
+            let (Some(start_row), Some(end_row)) =
+                (map_row(&edits, start_row), map_row(&edits, end_row))
+            else {
+                continue;
+            };
+            if end_row > snapshot.max_point().row || end_row < start_row {
+                continue;
+            }
+
+            self.comments.push(ReviewComment {
+                path: file.repo_path.clone(),
+                buffer: file.buffer.clone(),
+                range: line_range_anchors(&snapshot, start_row..end_row),
+                severity: input.severity,
+                message: input.message.into(),
+                suggestion: input.suggestion.filter(|suggestion| !suggestion.is_empty()),
+                state: CommentState::Open,
+                outdated: false,
+            });
+        }
+        self.refresh_all_excerpts(cx);
+    }
+
+    fn restore_comments(&mut self, saved_comments: Vec<SerializedComment>, cx: &mut Context<Self>) {
+        self.comments.clear();
+        for saved in saved_comments {
+            let Some(file) = self
+                .files
+                .iter()
+                .find(|file| file.repo_path.0.as_ref() == saved.path.as_path())
+            else {
+                continue;
+            };
+            let snapshot = file.buffer.read(cx).snapshot();
+            let row_count = saved.end_row.saturating_sub(saved.start_row);
+            let (start_row, outdated) =
+                match locate_lines(&snapshot.text(), saved.start_row, &saved.text) {
+                    Some(start_row) => (start_row, false),
+                    None => (saved.start_row.min(snapshot.max_point().row), true),
+                };
+            let end_row = (start_row + row_count).min(snapshot.max_point().row);
+
+            self.comments.push(ReviewComment {
+                path: file.repo_path.clone(),
+                buffer: file.buffer.clone(),
+                range: line_range_anchors(&snapshot, start_row..end_row),
+                severity: saved.severity,
+                message: saved.message.into(),
+                suggestion: saved.suggestion,
+                state: saved.state,
+                outdated,
+            });
+        }
+        self.refresh_all_excerpts(cx);
+    }
+
+    fn save_comments(&self, cx: &mut Context<Self>) {
+        let Some(key) = self.persistence_key.clone() else {
+            return;
+        };
+        let comments = self
+            .comments
+            .iter()
+            .map(|comment| {
+                let snapshot = comment.buffer.read(cx).snapshot();
+                let range = comment.range.to_point(&snapshot);
+                let text_range =
+                    Point::new(range.start.row, 0)..Point::new(range.end.row, u32::MAX);
+                let text_range = snapshot.clip_point(text_range.start, text::Bias::Left)
+                    ..snapshot.clip_point(text_range.end, text::Bias::Left);
+                SerializedComment {
+                    path: comment.path.to_path_buf(),
+                    start_row: range.start.row,
+                    end_row: range.end.row,
+                    text: snapshot.text_for_range(text_range).collect(),
+                    severity: comment.severity,
+                    message: comment.message.to_string(),
+                    suggestion: comment.suggestion.clone(),
+                    state: comment.state,
+                }
+            })
+            .collect::<Vec<_>>();
+        let Some(json) = serde_json::to_string(&comments).log_err() else {
+            return;
+        };
+        cx.background_spawn(async move { KEY_VALUE_STORE.write_kvp(key, json).await })
+            .detach_and_log_err(cx);
+    }
+
+    fn apply_suggestion(&mut self, ix: usize, cx: &mut Context<Self>) {
+        let Some(comment) = self.comments.get_mut(ix) else {
+            return;
+        };
+        // The lines of an outdated comment aren't the ones it was written for, so the
+        // suggestion would replace the wrong code.
+        if comment.outdated {
+            return;
+        }
+        let Some(suggestion) = comment.suggestion.clone() else {
+            return;
+        };
+        comment.buffer.update(cx, |buffer, cx| {
+            let range = comment.range.to_offset(&buffer.snapshot());
+            let new_text = suggestion.strip_suffix('\n').unwrap_or(&suggestion);
+            buffer.edit([(range, new_text)], None, cx);
+        });
+        comment.state = CommentState::Applied;
+        self.save_comments(cx);
+        self.refresh_blocks(cx);
+    }
+
+    fn dismiss_comment(&mut self, ix: usize, cx: &mut Context<Self>) {
+        let Some(comment) = self.comments.get_mut(ix) else {
+            return;
+        };
+        comment.state = CommentState::Dismissed;
+        self.save_comments(cx);
+        self.refresh_blocks(cx);
+    }
+
+    fn rerun_review(&mut self, cx: &mut Context<Self>) {
+        self.comments.clear();
+        self.refresh_blocks(cx);
+        self.run_review(cx);
+    }
+
+    fn refresh_all_excerpts(&mut self, cx: &mut Context<Self>) {
+        let buffer_ids = self
+            .files
+            .iter()
+            .map(|file| file.buffer.read(cx).remote_id())
+            .collect::<Vec<_>>();
+        for buffer_id in buffer_ids {
+            self.update_excerpts(buffer_id, cx);
+        }
+    }
+
+    /// Replaces the comment blocks, since the excerpts they're anchored in may have changed.
+    fn refresh_blocks(&mut self, cx: &mut Context<Self>) {
+        let review = cx.weak_entity();
+        let multibuffer = self.multibuffer.read(cx);
+        let blocks = self
+            .comments
+            .iter()
+            .enumerate()
+            .filter(|(_, comment)| comment.state == CommentState::Open)
+            .filter_map(|(ix, comment)| {
+                let snapshot = comment.buffer.read(cx).snapshot();
+                let end_row = comment.range.end.to_point(&snapshot).row;
+                let anchor = multibuffer.buffer_point_to_anchor(
+                    &comment.buffer,
+                    Point::new(end_row, 0),
+                    cx,
+                )?;
+                Some(comment_block(ix, comment, anchor, review.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        let old_block_ids = std::mem::take(&mut self.block_ids);
+        self.block_ids = self.editor.update(cx, |editor, cx| {
+            editor.remove_blocks(old_block_ids, None, cx);
+            editor.insert_blocks(blocks, None, cx).into_iter().collect()
+        });
+        cx.notify();
+    }
+
+    fn open_comment_count(&self) -> usize {
+        self.comments
+            .iter()
+            .filter(|comment| comment.state == CommentState::Open)
+            .count()
+    }
+}
+
+/// Returns the changed files of the target, resolving the base of a branch review.
+async fn load_diff(
+    repository: &Entity<Repository>,
+    target: ReviewTarget,
+    cx: &AsyncApp,
+) -> Result<(ReviewTarget, Vec<git::repository::FileDiff>)> {
+    match target {
+        ReviewTarget::StagedChanges => {
+            let diff = repository
+                .read_with(cx, |repository, cx| {
+                    repository.diff(DiffType::HeadToIndex, cx)
+                })?
+                .await?;
+            Ok((ReviewTarget::StagedChanges, diff))
+        }
+        ReviewTarget::Branch { base: Some(base) } => {
+            let diff = repository
+                .read_with(cx, |repository, cx| {
+                    repository.diff(DiffType::MergeBaseToWorktree { base: base.clone() }, cx)
+                })?
+                .await
+                .with_context(|| format!("failed to compare with {base}"))?;
+            Ok((ReviewTarget::Branch { base: Some(base) }, diff))
+        }
+        ReviewTarget::Branch { base: None } => {
+            for base in DEFAULT_BASE_BRANCHES {
+                let diff = repository
+                    .read_with(cx, |repository, cx| {
+                        repository.diff(
+                            DiffType::MergeBaseToWorktree {
+                                base: base.to_string(),
+                            },
+                            cx,
+                        )
+                    })?
+                    .await;
+                if let Ok(diff) = diff {
+                    let target = ReviewTarget::Branch {
+                        base: Some(base.to_string()),
+                    };
+                    return Ok((target, diff));
+                }
+            }
+            Err(anyhow!(
+                "couldn't find a branch to compare with, tried {}",
+                DEFAULT_BASE_BRANCHES.join(", ")
+            ))
+        }
+    }
+}
+
+/// Returns the key a review's comments are saved under, which is unique to the repository
+/// and what's reviewed.
+fn persistence_key(
+    project: &Entity<Project>,
+    repository: &Entity<Repository>,
+    target: &ReviewTarget,
+    cx: &AsyncApp,
+) -> Result<Option<String>> {
+    cx.update(|cx| {
+        let repository = repository.read(cx);
+        let project_path = repository.repo_path_to_project_path(&"".into())?;
+        let worktree = project
+            .read(cx)
+            .worktree_for_id(project_path.worktree_id, cx)?;
+        let work_directory = worktree.read(cx).abs_path().join(&project_path.path);
+        Some(format!(
+            "assistant2-code-review:{}:{}",
+            work_directory.display(),
+            target.persistence_key()
+        ))
+    })
+}
+
+fn line_range_anchors(snapshot: &language::BufferSnapshot, rows: Range<u32>) -> Range<Anchor> {
+    snapshot.anchor_before(Point::new(rows.start, 0))
+        ..snapshot.anchor_after(Point::new(rows.end, snapshot.line_len(rows.end)))
+}
+
+fn comment_block(
+    ix: usize,
+    comment: &ReviewComment,
+    anchor: editor::Anchor,
+    review: WeakEntity<CodeReview>,
+) -> BlockProperties<editor::Anchor> {
+    let severity = comment.severity;
+    let message = comment.message.clone();
+    let suggestion = comment.suggestion.clone();
+    let outdated = comment.outdated;
+    let height = 3
+        + message.lines().count() as u32
+        + suggestion
+            .as_ref()
+            .map_or(0, |suggestion| suggestion.lines().count() as u32 + 1);
+
+    BlockProperties {
+        placement: BlockPlacement::Below(anchor),
+        height,
+        style: BlockStyle::Flex,
+        render: Arc::new(move |cx| {
+            let (icon, color) = severity.icon();
+            let colors = cx.theme().colors();
+            v_flex()
+                .id(("review-comment", ix))
+                .block_mouse_down()
+                .w_full()
+                .pl(cx.gutter_dimensions.full_width())
+                .pr_4()
+                .py_1()
+                .child(
+                    v_flex()
+                        .p_2()
+                        .gap_1()
+                        .border_1()
+                        .border_color(colors.border_variant)
+                        .rounded_md()
+                        .bg(colors.surface_background)
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .child(Icon::new(icon).size(IconSize::Small).color(color))
+                                .child(Label::new(severity.label()).size(LabelSize::Small))
+                                .when(outdated, |el| {
+                                    el.child(
+                                        Label::new("Outdated")
+                                            .size(LabelSize::Small)
+                                            .color(Color::Muted),
+                                    )
+                                })
+                                .child(div().flex_1())
+                                .when(suggestion.is_some(), |el| {
+                                    el.child(
+                                        Button::new(("apply-suggestion", ix), "Apply Suggestion")
+                                            .label_size(LabelSize::Small)
+                                            .disabled(outdated)
+                                            .when(outdated, |button| {
+                                                button.tooltip(Tooltip::text(
+                                                    "The code changed since the review",
+                                                ))
+                                            })
+                                            .on_click({
+                                                let review = review.clone();
+                                                move |_, _, cx| {
+                                                    review
+                                                        .update(cx, |review, cx| {
+                                                            review.apply_suggestion(ix, cx)
+                                                        })
+                                                        .ok();
+                                                }
+                                            }),
+                                    )
+                                })
+                                .child(
+                                    Button::new(("dismiss-comment", ix), "Dismiss")
+                                        .label_size(LabelSize::Small)
+                                        .on_click({
+                                            let review = review.clone();
+                                            move |_, _, cx| {
+                                                review
+                                                    .update(cx, |review, cx| {
+                                                        review.dismiss_comment(ix, cx)
+                                                    })
+                                                    .ok();
+                                            }
+                                        }),
+                                ),
+                        )
+                        .child(Label::new(message.clone()))
+                        .when_some(suggestion.clone(), |el, suggestion| {
+                            el.child(
+                                div()
+                                    .p_1()
+                                    .rounded_sm()
+                                    .bg(colors.editor_background)
+                                    .font_buffer(cx)
+                                    .text_buffer(cx)
+                                    .child(suggestion),
+                            )
+                        }),
+                )
+                .into_any_element()
+        }),
+        priority: 0,
+    }
+}
+
+/// Formats the diff of a file for the model, numbering the lines that exist after the change.
+fn numbered_diff(old_text: Option<&str>, new_text: &str, context_lines: u32) -> String {
+    let old_lines = old_text.map_or(Vec::new(), |text| text.lines().collect::<Vec<_>>());
+    let new_lines = new_text.lines().collect::<Vec<_>>();
+    let edits = match old_text {
+        Some(old_text) => language::line_diff(old_text, new_text),
+        None => vec![(0..0, 0..new_lines.len() as u32)],
+    };
+    format_numbered_diff(&old_lines, &new_lines, &edits, context_lines)
+}
+
+fn format_numbered_diff(
+    old_lines: &[&str],
+    new_lines: &[&str],
+    edits: &[(Range<u32>, Range<u32>)],
+    context_lines: u32,
+) -> String {
+    let mut output = String::new();
+    let mut edits = edits.iter().peekable();
+    while let Some((old_range, new_range)) = edits.next() {
+        // Group the edits whose context overlaps into one hunk.
+        let mut hunk = vec![(old_range.clone(), new_range.clone())];
+        while let Some((next_old, next_new)) = edits.peek() {
+            let (last_old, _) = hunk.last().unwrap();
+            if next_old.start > last_old.end + 2 * context_lines {
+                break;
+            }
+            hunk.push((next_old.clone(), next_new.clone()));
+            edits.next();
+        }
+
+        let (first_old, first_new) = &hunk[0];
+        let context_start = first_old.start.saturating_sub(context_lines);
+        let mut old_row = context_start;
+        let mut new_row = first_new.start - (first_old.start - context_start);
+        writeln!(output, "@@").unwrap();
+        for (old_range, new_range) in &hunk {
+            while old_row < old_range.start {
+                writeln!(
+                    output,
+                    " {:>4} | {}",
+                    new_row + 1,
+                    old_lines[old_row as usize]
+                )
+                .unwrap();
+                old_row += 1;
+                new_row += 1;
+            }
+            for row in old_range.clone() {
+                writeln!(output, "-      | {}", old_lines[row as usize]).unwrap();
+            }
+            for row in new_range.clone() {
+                writeln!(output, "+{:>4} | {}", row + 1, new_lines[row as usize]).unwrap();
+            }
+            old_row = old_range.end;
+            new_row = new_range.end;
+        }
+        let context_end = (old_row + context_lines).min(old_lines.len() as u32);
+        while old_row < context_end {
+            writeln!(
+                output,
+                " {:>4} | {}",
+                new_row + 1,
+                old_lines[old_row as usize]
+            )
+            .unwrap();
+            old_row += 1;
+            new_row += 1;
+        }
+    }
+    output
+}
+
+/// Maps a row of the old side of a line diff to the new side, or returns `None` if it was
+/// changed.
+fn map_row(edits: &[(Range<u32>, Range<u32>)], row: u32) -> Option<u32> {
+    let mut delta = 0_i64;
+    for (old_range, new_range) in edits {
+        if old_range.start > row {
+            break;
+        }
+        if row < old_range.end {
+            return None;
+        }
+        delta = new_range.end as i64 - old_range.end as i64;
+    }
+    Some((row as i64 + delta) as u32)
+}
+
+/// Finds the lines of `expected` in `text`, preferring the occurrence starting closest to `row`.
+fn locate_lines(text: &str, row: u32, expected: &str) -> Option<u32> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let expected = expected.lines().collect::<Vec<_>>();
+    if expected.is_empty() || expected.len() > lines.len() {
+        return None;
+    }
+    (0..=lines.len() - expected.len())
+        .filter(|&start| lines[start..start + expected.len()] == expected[..])
+        .min_by_key(|&start| (start as i64 - row as i64).abs())
+        .map(|start| start as u32)
+}
+
+impl EventEmitter<EditorEvent> for CodeReview {}
+
+impl Focusable for CodeReview {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        if self.multibuffer.read(cx).is_empty() {
+            self.focus_handle.clone()
+        } else {
+            self.editor.focus_handle(cx)
+        }
+    }
+}
+
+impl Item for CodeReview {
+    type Event = EditorEvent;
+
+    fn tab_icon(&self, _window: &Window, _cx: &App) -> Option<Icon> {
+        Some(Icon::new(IconName::ZedAssistant).color(Color::Muted))
+    }
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn deactivated(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.deactivated(window, cx));
+    }
+
+    fn navigate(
+        &mut self,
+        data: Box<dyn Any>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        self.editor
+            .update(cx, |editor, cx| editor.navigate(data, window, cx))
+    }
+
+    fn tab_tooltip_text(&self, cx: &App) -> Option<SharedString> {
+        let repository = self
+            .repository
+            .read(cx)
+            .display_name(self.project.read(cx), cx);
+        Some(format!("Review of {} in {repository}", self.target.label()).into())
+    }
+
+    fn tab_content(&self, params: TabContentParams, _window: &Window, _: &App) -> AnyElement {
+        Label::new(format!("Review: {}", self.target.label()))
+            .color(if params.selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("Code Review Opened")
+    }
+
+    fn as_searchable(&self, _: &Entity<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(self.editor.clone()))
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &App,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::ProjectItem),
+    ) {
+        self.editor.for_each_project_item(cx, f)
+    }
+
+    fn is_singleton(&self, _: &App) -> bool {
+        false
+    }
+
+    fn set_nav_history(
+        &mut self,
+        nav_history: ItemNavHistory,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor.update(cx, |editor, _| {
+            editor.set_nav_history(Some(nav_history));
+        });
+    }
+
+    fn is_dirty(&self, cx: &App) -> bool {
+        self.multibuffer.read(cx).is_dirty(cx)
+    }
+
+    fn has_conflict(&self, cx: &App) -> bool {
+        self.multibuffer.read(cx).has_conflict(cx)
+    }
+
+    fn can_save(&self, _: &App) -> bool {
+        true
+    }
+
+    fn save(
+        &mut self,
+        format: bool,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.editor.save(format, project, window, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        _: Entity<Project>,
+        _: ProjectPath,
+        _window: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        unreachable!()
+    }
+
+    fn reload(
+        &mut self,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.editor.reload(project, window, cx)
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a Entity<Self>,
+        _: &'a App,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+
+    fn breadcrumb_location(&self, _: &App) -> ToolbarItemLocation {
+        ToolbarItemLocation::PrimaryLeft
+    }
+
+    fn breadcrumbs(&self, theme: &theme::Theme, cx: &App) -> Option<Vec<BreadcrumbText>> {
+        self.editor.breadcrumbs(theme, cx)
+    }
+
+    fn added_to_workspace(
+        &mut self,
+        workspace: &mut Workspace,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor.update(cx, |editor, cx| {
+            editor.added_to_workspace(workspace, window, cx)
+        });
+    }
+}
+
+impl Render for CodeReview {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let is_empty = self.multibuffer.read(cx).is_empty();
+        let status = match &self.status {
+            ReviewStatus::Loading => Label::new("Loading changes…").color(Color::Muted),
+            ReviewStatus::Reviewing => Label::new("Reviewing…").color(Color::Muted),
+            ReviewStatus::Ready => match self.open_comment_count() {
+                0 => Label::new("No open comments").color(Color::Muted),
+                1 => Label::new("1 open comment"),
+                count => Label::new(format!("{count} open comments")),
+            },
+            ReviewStatus::Failed(error) => Label::new(error.clone()).color(Color::Error),
+        };
+        let can_rerun = matches!(self.status, ReviewStatus::Ready | ReviewStatus::Failed(_))
+            && !self.files.is_empty();
+
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .key_context(if is_empty { "EmptyPane" } else { "CodeReview" })
+            .bg(cx.theme().colors().editor_background)
+            .size_full()
+            .child(
+                h_flex()
+                    .p_1()
+                    .pl_2()
+                    .gap_1()
+                    .justify_between()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .child(status.size(LabelSize::Small))
+                    .child(
+                        Button::new("rerun-review", "Re-run Review")
+                            .disabled(!can_rerun)
+                            .on_click(cx.listener(|this, _, _, cx| this.rerun_review(cx))),
+                    ),
+            )
+            .when(
+                is_empty && !matches!(self.status, ReviewStatus::Loading),
+                |el| {
+                    el.child(
+                        v_flex()
+                            .flex_1()
+                            .items_center()
+                            .justify_center()
+                            .child(Label::new("No changes to review")),
+                    )
+                },
+            )
+            .when(!is_empty, |el| {
+                el.child(div().flex_1().child(self.editor.clone()))
+            })
+    }
+}
+
+pub(crate) fn register_actions(workspace: &mut Workspace) {
+    workspace
+        .register_action(|workspace, _: &ReviewStagedChanges, window, cx| {
+            CodeReview::deploy(ReviewTarget::StagedChanges, workspace, window, cx);
+        })
+        .register_action(|workspace, action: &ReviewBranchChanges, window, cx| {
+            let target = ReviewTarget::Branch {
+                base: action.base.clone(),
+            };
+            CodeReview::deploy(target, workspace, window, cx);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_numbered_diff() {
+        let old_lines = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let new_lines = ["a", "B", "c", "d", "e", "f", "g", "h", "i", "j", "k"];
+        let edits = [(1..2, 1..2), (10..10, 10..11)];
+        assert_eq!(
+            format_numbered_diff(&old_lines, &new_lines, &edits, 1),
+            concat!(
+                "@@\n",
+                "    1 | a\n",
+                "-      | b\n",
+                "+   2 | B\n",
+                "    3 | c\n",
+                "@@\n",
+                "   10 | j\n",
+                "+  11 | k\n",
+            )
+        );
+
+        // Edits whose context overlaps share a hunk.
+        let edits = [(1..2, 1..2), (3..3, 3..4)];
+        let new_lines = ["a", "B", "c", "x", "d"];
+        assert_eq!(
+            format_numbered_diff(&old_lines[..4], &new_lines, &edits, 1),
+            concat!(
+                "@@\n",
+                "    1 | a\n",
+                "-      | b\n",
+                "+   2 | B\n",
+                "    3 | c\n",
+                "+   4 | x\n",
+                "    5 | d\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_map_row() {
+        // Line 1 was replaced by two lines, and line 4 was deleted.
+        let edits = [(1..2, 1..3), (4..5, 5..5)];
+        assert_eq!(map_row(&edits, 0), Some(0));
+        assert_eq!(map_row(&edits, 1), None);
+        assert_eq!(map_row(&edits, 2), Some(3));
+        assert_eq!(map_row(&edits, 3), Some(4));
+        assert_eq!(map_row(&edits, 4), None);
+        assert_eq!(map_row(&edits, 5), Some(5));
+        assert_eq!(map_row(&[(2..2, 2..4)], 2), Some(4));
+    }
+
+    #[test]
+    fn test_locate_lines() {
+        let text = "fn a() {\n    x\n}\nfn b() {\n    x\n}\n";
+        assert_eq!(locate_lines(text, 0, "    x\n}"), Some(1));
+        assert_eq!(locate_lines(text, 4, "    x\n}"), Some(4));
+        assert_eq!(locate_lines(text, 4, "fn a() {"), Some(0));
+        assert_eq!(locate_lines(text, 0, "    y"), None);
+    }
+}
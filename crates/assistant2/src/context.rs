@@ -1,5 +1,6 @@
 use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use file_icons::FileIcons;
 use gpui::{App, Entity, SharedString};
@@ -8,8 +9,10 @@ use language_model::{LanguageModelRequestMessage, MessageContent};
 use serde::{Deserialize, Serialize};
 use text::BufferId;
 use ui::IconName;
+use url::Url;
 use util::post_inc;
 
+use crate::thread_store::ResourceSubscription;
 use crate::{context_store::buffer_path_log_err, thread::Thread};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
@@ -34,12 +37,15 @@ pub struct ContextSnapshot {
     pub text: Box<[SharedString]>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ContextKind {
     File,
     Directory,
     FetchedUrl,
     Thread,
+    Resource,
+    RepoMap,
 }
 
 impl ContextKind {
@@ -49,6 +55,8 @@ impl ContextKind {
             ContextKind::Directory => "Folder",
             ContextKind::FetchedUrl => "Fetch",
             ContextKind::Thread => "Thread",
+            ContextKind::Resource => "Resource",
+            ContextKind::RepoMap => "Repo Map",
         }
     }
 
@@ -58,6 +66,8 @@ impl ContextKind {
             ContextKind::Directory => IconName::Folder,
             ContextKind::FetchedUrl => IconName::Globe,
             ContextKind::Thread => IconName::MessageCircle,
+            ContextKind::Resource => IconName::DatabaseZap,
+            ContextKind::RepoMap => IconName::FileTree,
         }
     }
 }
@@ -68,6 +78,8 @@ pub enum AssistantContext {
     Directory(DirectoryContext),
     FetchedUrl(FetchedUrlContext),
     Thread(ThreadContext),
+    Resource(ResourceContext),
+    RepoMap(RepoMapContext),
 }
 
 impl AssistantContext {
@@ -77,6 +89,8 @@ impl AssistantContext {
             Self::Directory(directory) => directory.snapshot.id,
             Self::FetchedUrl(url) => url.id,
             Self::Thread(thread) => thread.id,
+            Self::Resource(resource) => resource.id,
+            Self::RepoMap(repo_map) => repo_map.id,
         }
     }
 }
@@ -111,6 +125,27 @@ pub struct ThreadContext {
     pub text: SharedString,
 }
 
+/// A resource provided by a context server.
+#[derive(Debug)]
+pub struct ResourceContext {
+    pub id: ContextId,
+    pub server_id: Arc<str>,
+    pub uri: Url,
+    pub name: SharedString,
+    pub text: SharedString,
+    /// Keeps the text up to date while the context is attached, if the server supports it.
+    pub subscription: Option<Arc<ResourceSubscription>>,
+}
+
+/// A map of the symbols in the project's most referenced files.
+#[derive(Debug)]
+pub struct RepoMapContext {
+    pub id: ContextId,
+    pub text: SharedString,
+    pub included_file_count: usize,
+    pub file_count: usize,
+}
+
 // TODO: Model<Buffer> holds onto the buffer even if the file is deleted and closed. Should remove
 // the context from the message editor in this case.
 
@@ -129,6 +164,8 @@ impl AssistantContext {
             Self::Directory(directory_context) => Some(directory_context.snapshot()),
             Self::FetchedUrl(fetched_url_context) => Some(fetched_url_context.snapshot()),
             Self::Thread(thread_context) => Some(thread_context.snapshot(cx)),
+            Self::Resource(resource_context) => Some(resource_context.snapshot()),
+            Self::RepoMap(repo_map_context) => Some(repo_map_context.snapshot()),
         }
     }
 }
@@ -235,6 +272,36 @@ impl ThreadContext {
     }
 }
 
+impl ResourceContext {
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            id: self.id,
+            name: self.name.clone(),
+            parent: None,
+            tooltip: Some(self.uri.to_string().into()),
+            icon_path: None,
+            kind: ContextKind::Resource,
+            text: Box::new([self.text.clone()]),
+        }
+    }
+}
+
+impl RepoMapContext {
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            id: self.id,
+            name: "Repo Map".into(),
+            parent: None,
+            tooltip: Some(
+                format!("{} of {} files", self.included_file_count, self.file_count).into(),
+            ),
+            icon_path: None,
+            kind: ContextKind::RepoMap,
+            text: Box::new([self.text.clone()]),
+        }
+    }
+}
+
 pub fn attach_context_to_message(
     message: &mut LanguageModelRequestMessage,
     contexts: impl Iterator<Item = ContextSnapshot>,
@@ -243,6 +310,8 @@ pub fn attach_context_to_message(
     let mut directory_context = Vec::new();
     let mut fetch_context = Vec::new();
     let mut thread_context = Vec::new();
+    let mut resource_context = Vec::new();
+    let mut repo_map_context = Vec::new();
 
     let mut capacity = 0;
     for context in contexts {
@@ -252,6 +321,8 @@ pub fn attach_context_to_message(
             ContextKind::Directory => directory_context.push(context),
             ContextKind::FetchedUrl => fetch_context.push(context),
             ContextKind::Thread => thread_context.push(context),
+            ContextKind::Resource => resource_context.push(context),
+            ContextKind::RepoMap => repo_map_context.push(context),
         }
     }
     if !file_context.is_empty() {
@@ -266,6 +337,12 @@ pub fn attach_context_to_message(
     if !thread_context.is_empty() {
         capacity += 1 + thread_context.len();
     }
+    if !resource_context.is_empty() {
+        capacity += 1 + resource_context.len();
+    }
+    if !repo_map_context.is_empty() {
+        capacity += 1;
+    }
     if capacity == 0 {
         return;
     }
@@ -310,6 +387,25 @@ pub fn attach_context_to_message(
         }
     }
 
+    if !resource_context.is_empty() {
+        context_chunks.push("The following resources are available:\n");
+        for context in &resource_context {
+            context_chunks.push(&context.name);
+            for chunk in &context.text {
+                context_chunks.push(&chunk);
+            }
+        }
+    }
+
+    if !repo_map_context.is_empty() {
+        context_chunks.push("The following map of the repository's structure is available:\n");
+        for context in &repo_map_context {
+            for chunk in &context.text {
+                context_chunks.push(&chunk);
+            }
+        }
+    }
+
     debug_assert!(
         context_chunks.len() == capacity,
         "attach_context_message calculated capacity of {}, but length was {}",
@@ -1,12 +1,14 @@
 mod directory_context_picker;
 mod fetch_context_picker;
 mod file_context_picker;
+mod resource_context_picker;
 mod thread_context_picker;
 
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
+use context_server::protocol::ServerCapability;
 use editor::Editor;
 use file_context_picker::render_file_context_entry;
 use gpui::{App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity};
@@ -19,6 +21,7 @@ use crate::context::ContextKind;
 use crate::context_picker::directory_context_picker::DirectoryContextPicker;
 use crate::context_picker::fetch_context_picker::FetchContextPicker;
 use crate::context_picker::file_context_picker::FileContextPicker;
+use crate::context_picker::resource_context_picker::ResourceContextPicker;
 use crate::context_picker::thread_context_picker::ThreadContextPicker;
 use crate::context_store::ContextStore;
 use crate::thread_store::ThreadStore;
@@ -37,6 +40,7 @@ enum ContextPickerMode {
     Directory(Entity<DirectoryContextPicker>),
     Fetch(Entity<FetchContextPicker>),
     Thread(Entity<ThreadContextPicker>),
+    Resource(Entity<ResourceContextPicker>),
 }
 
 pub(super) struct ContextPicker {
@@ -96,6 +100,12 @@ impl ContextPicker {
             if self.allow_threads() {
                 context_kinds.push(ContextKind::Thread);
             }
+            if self.allow_resources(cx) {
+                context_kinds.push(ContextKind::Resource);
+            }
+            if self.allow_repo_map(cx) {
+                context_kinds.push(ContextKind::RepoMap);
+            }
 
             let menu = menu
                 .when(has_recent, |menu| {
@@ -143,6 +153,36 @@ impl ContextPicker {
         self.thread_store.is_some()
     }
 
+    /// Whether any running context server provides resources.
+    fn allow_resources(&self, cx: &App) -> bool {
+        let Some(thread_store) = self
+            .thread_store
+            .as_ref()
+            .and_then(|thread_store| thread_store.upgrade())
+        else {
+            return false;
+        };
+
+        thread_store
+            .read(cx)
+            .context_server_manager()
+            .read(cx)
+            .servers()
+            .iter()
+            .any(|server| {
+                server.client().map_or(false, |protocol| {
+                    protocol.capable(ServerCapability::Resources)
+                })
+            })
+    }
+
+    /// Whether the repo map is available as context, which needs the project's files on disk.
+    fn allow_repo_map(&self, cx: &App) -> bool {
+        self.workspace.upgrade().map_or(false, |workspace| {
+            workspace.read(cx).project().read(cx).is_local()
+        })
+    }
+
     fn select_kind(&mut self, kind: ContextKind, window: &mut Window, cx: &mut Context<Self>) {
         let context_picker = cx.entity().downgrade();
 
@@ -198,6 +238,27 @@ impl ContextPicker {
                     }));
                 }
             }
+            ContextKind::Resource => {
+                if let Some(thread_store) = self.thread_store.as_ref() {
+                    self.mode = ContextPickerMode::Resource(cx.new(|cx| {
+                        ResourceContextPicker::new(
+                            thread_store.clone(),
+                            context_picker.clone(),
+                            self.context_store.clone(),
+                            self.confirm_behavior,
+                            window,
+                            cx,
+                        )
+                    }));
+                }
+            }
+            ContextKind::RepoMap => {
+                if let Some(context_store) = self.context_store.upgrade() {
+                    context_store
+                        .update(cx, |context_store, cx| context_store.add_repo_map(cx))
+                        .detach_and_log_err(cx);
+                }
+            }
         }
 
         cx.notify();
@@ -397,6 +458,7 @@ impl Focusable for ContextPicker {
             ContextPickerMode::Directory(directory_picker) => directory_picker.focus_handle(cx),
             ContextPickerMode::Fetch(fetch_picker) => fetch_picker.focus_handle(cx),
             ContextPickerMode::Thread(thread_picker) => thread_picker.focus_handle(cx),
+            ContextPickerMode::Resource(resource_picker) => resource_picker.focus_handle(cx),
         }
     }
 }
@@ -414,6 +476,9 @@ impl Render for ContextPicker {
                 }
                 ContextPickerMode::Fetch(fetch_picker) => parent.child(fetch_picker.clone()),
                 ContextPickerMode::Thread(thread_picker) => parent.child(thread_picker.clone()),
+                ContextPickerMode::Resource(resource_picker) => {
+                    parent.child(resource_picker.clone())
+                }
             })
     }
 }
@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use context_server::protocol::ServerCapability;
+use fuzzy::StringMatchCandidate;
+use gpui::{App, DismissEvent, Entity, FocusHandle, Focusable, Task, WeakEntity};
+use picker::{Picker, PickerDelegate};
+use ui::{prelude::*, ListItem};
+use url::Url;
+use util::ResultExt as _;
+use workspace::notifications::NotifyResultExt;
+
+use crate::context_picker::{ConfirmBehavior, ContextPicker};
+use crate::context_store::ContextStore;
+use crate::thread_store::ThreadStore;
+
+pub struct ResourceContextPicker {
+    picker: Entity<Picker<ResourceContextPickerDelegate>>,
+}
+
+impl ResourceContextPicker {
+    pub fn new(
+        thread_store: WeakEntity<ThreadStore>,
+        context_picker: WeakEntity<ContextPicker>,
+        context_store: WeakEntity<ContextStore>,
+        confirm_behavior: ConfirmBehavior,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let load_entries = load_resource_entries(&thread_store, cx);
+        let delegate = ResourceContextPickerDelegate::new(
+            thread_store,
+            context_picker,
+            context_store,
+            confirm_behavior,
+        );
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            let entries = load_entries.await;
+            this.update_in(&mut cx, |this, window, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.entries = entries;
+                    picker.refresh(window, cx);
+                });
+            })
+            .ok();
+        })
+        .detach();
+
+        ResourceContextPicker { picker }
+    }
+}
+
+impl Focusable for ResourceContextPicker {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for ResourceContextPicker {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        self.picker.clone()
+    }
+}
+
+/// A resource, or a template for resources, provided by a context server.
+#[derive(Debug, Clone)]
+pub enum ResourceContextEntry {
+    Resource {
+        server_id: Arc<str>,
+        uri: Url,
+        name: SharedString,
+    },
+    Template {
+        server_id: Arc<str>,
+        uri_template: SharedString,
+        name: SharedString,
+    },
+}
+
+impl ResourceContextEntry {
+    fn name(&self) -> &SharedString {
+        match self {
+            Self::Resource { name, .. } | Self::Template { name, .. } => name,
+        }
+    }
+}
+
+fn load_resource_entries(
+    thread_store: &WeakEntity<ThreadStore>,
+    cx: &mut App,
+) -> Task<Vec<ResourceContextEntry>> {
+    let Some(thread_store) = thread_store.upgrade() else {
+        return Task::ready(Vec::new());
+    };
+    let servers = thread_store
+        .read(cx)
+        .context_server_manager()
+        .read(cx)
+        .servers();
+
+    cx.spawn(|_| async move {
+        let mut entries = Vec::new();
+        for server in servers {
+            let Some(protocol) = server
+                .client()
+                .filter(|protocol| protocol.capable(ServerCapability::Resources))
+            else {
+                continue;
+            };
+
+            if let Some(response) = protocol.list_resources().await.log_err() {
+                entries.extend(response.resources.into_iter().map(|resource| {
+                    ResourceContextEntry::Resource {
+                        server_id: server.id(),
+                        uri: resource.uri,
+                        name: resource.name.into(),
+                    }
+                }));
+            }
+            if let Some(templates) = protocol.list_resource_templates().await.log_err() {
+                entries.extend(templates.into_iter().filter_map(|template| {
+                    // Only templates with a single variable can be filled in from the query.
+                    let variables = template.uri_template.matches('{').count();
+                    (variables == 1).then(|| ResourceContextEntry::Template {
+                        server_id: server.id(),
+                        uri_template: template.uri_template.into(),
+                        name: template.name.into(),
+                    })
+                }));
+            }
+        }
+        entries
+    })
+}
+
+/// Fills in the single variable of a resource template with the given value.
+fn expand_template(uri_template: &str, value: &str) -> Option<Url> {
+    let start = uri_template.find('{')?;
+    let end = start + uri_template[start..].find('}')?;
+    Url::parse(&format!(
+        "{}{}{}",
+        &uri_template[..start],
+        value,
+        &uri_template[end + 1..]
+    ))
+    .ok()
+}
+
+pub struct ResourceContextPickerDelegate {
+    thread_store: WeakEntity<ThreadStore>,
+    context_picker: WeakEntity<ContextPicker>,
+    context_store: WeakEntity<ContextStore>,
+    confirm_behavior: ConfirmBehavior,
+    entries: Vec<ResourceContextEntry>,
+    matches: Vec<ResourceContextEntry>,
+    selected_index: usize,
+}
+
+impl ResourceContextPickerDelegate {
+    pub fn new(
+        thread_store: WeakEntity<ThreadStore>,
+        context_picker: WeakEntity<ContextPicker>,
+        context_store: WeakEntity<ContextStore>,
+        confirm_behavior: ConfirmBehavior,
+    ) -> Self {
+        ResourceContextPickerDelegate {
+            thread_store,
+            context_picker,
+            context_store,
+            confirm_behavior,
+            entries: Vec::new(),
+            matches: Vec::new(),
+            selected_index: 0,
+        }
+    }
+}
+
+impl PickerDelegate for ResourceContextPickerDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Search resources…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> SharedString {
+        "No resources found in the running context servers".into()
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let entries = self.entries.clone();
+        let executor = cx.background_executor().clone();
+        let search_task = cx.background_spawn(async move {
+            if query.is_empty() {
+                return entries;
+            }
+
+            let (resources, templates): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .partition(|entry| matches!(entry, ResourceContextEntry::Resource { .. }));
+            let candidates = resources
+                .iter()
+                .enumerate()
+                .map(|(id, entry)| StringMatchCandidate::new(id, entry.name()))
+                .collect::<Vec<_>>();
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &query,
+                false,
+                100,
+                &Default::default(),
+                executor,
+            )
+            .await;
+
+            // Templates always match, as the query fills them in.
+            matches
+                .into_iter()
+                .map(|mat| resources[mat.candidate_id].clone())
+                .chain(templates.into_iter().filter_map(|entry| match entry {
+                    ResourceContextEntry::Template {
+                        server_id,
+                        uri_template,
+                        name,
+                    } => Some(ResourceContextEntry::Resource {
+                        server_id,
+                        uri: expand_template(&uri_template, &query)?,
+                        name: format!("{name}: {query}").into(),
+                    }),
+                    ResourceContextEntry::Resource { .. } => None,
+                }))
+                .collect()
+        });
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            let matches = search_task.await;
+            this.update(&mut cx, |this, cx| {
+                this.delegate.matches = matches;
+                this.delegate.selected_index = 0;
+                cx.notify();
+            })
+            .ok();
+        })
+    }
+
+    fn confirm(&mut self, _secondary: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(ResourceContextEntry::Resource {
+            server_id,
+            uri,
+            name,
+        }) = self.matches.get(self.selected_index).cloned()
+        else {
+            return;
+        };
+        let Some(thread_store) = self.thread_store.upgrade() else {
+            return;
+        };
+
+        let add_resource_task = self.context_store.update(cx, |context_store, cx| {
+            context_store.add_resource(server_id, uri, name, &thread_store, cx)
+        });
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            add_resource_task
+                .map_err(|_| anyhow!("context store not available"))?
+                .await
+                .notify_async_err(&mut cx);
+
+            this.update_in(&mut cx, |this, window, cx| {
+                match this.delegate.confirm_behavior {
+                    ConfirmBehavior::KeepOpen => cx.notify(),
+                    ConfirmBehavior::Close => this.delegate.dismissed(window, cx),
+                }
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn dismissed(&mut self, _window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.context_picker
+            .update(cx, |_, cx| {
+                cx.emit(DismissEvent);
+            })
+            .ok();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let entry = &self.matches[ix];
+
+        Some(ListItem::new(ix).inset(true).toggle_state(selected).child(
+            render_resource_context_entry(entry, self.context_store.clone(), cx),
+        ))
+    }
+}
+
+pub fn render_resource_context_entry(
+    entry: &ResourceContextEntry,
+    context_store: WeakEntity<ContextStore>,
+    cx: &mut App,
+) -> Div {
+    let (detail, added) = match entry {
+        ResourceContextEntry::Resource { server_id, uri, .. } => (
+            SharedString::from(uri.to_string()),
+            context_store.upgrade().map_or(false, |context_store| {
+                context_store
+                    .read(cx)
+                    .includes_resource(server_id, uri)
+                    .is_some()
+            }),
+        ),
+        ResourceContextEntry::Template { uri_template, .. } => (uri_template.clone(), false),
+    };
+
+    h_flex()
+        .gap_1p5()
+        .w_full()
+        .child(
+            Icon::new(IconName::DatabaseZap)
+                .size(IconSize::XSmall)
+                .color(Color::Muted),
+        )
+        .child(Label::new(entry.name().clone()))
+        .child(
+            Label::new(detail)
+                .size(LabelSize::Small)
+                .color(Color::Muted)
+                .text_ellipsis(),
+        )
+        .child(div().w_full())
+        .when(added, |el| {
+            el.child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Icon::new(IconName::Check)
+                            .size(IconSize::Small)
+                            .color(Color::Success),
+                    )
+                    .child(Label::new("Added").size(LabelSize::Small)),
+            )
+        })
+}
@@ -4,19 +4,24 @@ use std::sync::Arc;
 use anyhow::{anyhow, bail, Result};
 use collections::{BTreeMap, HashMap, HashSet};
 use futures::{self, future, Future, FutureExt};
-use gpui::{App, AppContext as _, AsyncApp, Context, Entity, SharedString, Task, WeakEntity};
+use gpui::{
+    App, AppContext as _, AsyncApp, Context, Entity, SharedString, Subscription, Task, WeakEntity,
+};
 use language::Buffer;
+use language_model::LanguageModelRegistry;
 use project::{ProjectPath, Worktree};
 use rope::Rope;
 use text::BufferId;
+use url::Url;
 use workspace::Workspace;
 
 use crate::context::{
     AssistantContext, ContextBuffer, ContextId, ContextSnapshot, DirectoryContext,
-    FetchedUrlContext, FileContext, ThreadContext,
+    FetchedUrlContext, FileContext, RepoMapContext, ResourceContext, ThreadContext,
 };
 use crate::context_strip::SuggestedContext;
 use crate::thread::{Thread, ThreadId};
+use crate::thread_store::{ThreadStore, ThreadStoreEvent};
 
 pub struct ContextStore {
     workspace: WeakEntity<Workspace>,
@@ -27,6 +32,9 @@ pub struct ContextStore {
     directories: HashMap<PathBuf, ContextId>,
     threads: HashMap<ThreadId, ContextId>,
     fetched_urls: HashMap<String, ContextId>,
+    resources: HashMap<(Arc<str>, Url), ContextId>,
+    repo_map: Option<ContextId>,
+    thread_store_subscription: Option<Subscription>,
 }
 
 impl ContextStore {
@@ -39,6 +47,9 @@ impl ContextStore {
             directories: HashMap::default(),
             threads: HashMap::default(),
             fetched_urls: HashMap::default(),
+            resources: HashMap::default(),
+            repo_map: None,
+            thread_store_subscription: None,
         }
     }
 
@@ -58,6 +69,8 @@ impl ContextStore {
         self.directories.clear();
         self.threads.clear();
         self.fetched_urls.clear();
+        self.resources.clear();
+        self.repo_map = None;
     }
 
     pub fn add_file_from_path(
@@ -284,6 +297,100 @@ impl ContextStore {
             }));
     }
 
+    pub fn add_resource(
+        &mut self,
+        server_id: Arc<str>,
+        uri: Url,
+        name: SharedString,
+        thread_store: &Entity<ThreadStore>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        if let Some(context_id) = self.includes_resource(&server_id, &uri) {
+            self.remove_context(context_id);
+            return Task::ready(Ok(()));
+        }
+
+        self.thread_store_subscription.get_or_insert_with(|| {
+            cx.subscribe(thread_store, |this, _, event, _cx| match event {
+                ThreadStoreEvent::ResourceUpdated {
+                    server_id,
+                    uri,
+                    text,
+                } => this.update_resource_text(server_id, uri, text.clone()),
+            })
+        });
+
+        let read_task = thread_store.update(cx, |thread_store, cx| {
+            thread_store.read_resource(server_id.clone(), uri.clone(), cx)
+        });
+        cx.spawn(|this, mut cx| async move {
+            let (text, subscription) = read_task.await?;
+            this.update(&mut cx, |this, _cx| {
+                if this.includes_resource(&server_id, &uri).is_none() {
+                    let id = this.next_context_id.post_inc();
+                    this.resources.insert((server_id.clone(), uri.clone()), id);
+                    this.context
+                        .push(AssistantContext::Resource(ResourceContext {
+                            id,
+                            server_id,
+                            uri,
+                            name,
+                            text,
+                            subscription,
+                        }));
+                }
+            })
+        })
+    }
+
+    fn update_resource_text(&mut self, server_id: &Arc<str>, uri: &Url, text: SharedString) {
+        for context in self.context.iter_mut() {
+            if let AssistantContext::Resource(resource) = context {
+                if resource.server_id == *server_id && resource.uri == *uri {
+                    resource.text = text.clone();
+                }
+            }
+        }
+    }
+
+    pub fn add_repo_map(&mut self, cx: &mut Context<Self>) -> Task<Result<()>> {
+        if let Some(context_id) = self.repo_map {
+            self.remove_context(context_id);
+            return Task::ready(Ok(()));
+        }
+
+        let Some(project) = self
+            .workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("failed to read project")));
+        };
+
+        let model = LanguageModelRegistry::read_global(cx).active_model();
+        let repo_map =
+            repo_map::build_repo_map(&project, model, repo_map::DEFAULT_TOKEN_BUDGET, cx);
+        cx.spawn(|this, mut cx| async move {
+            let repo_map = repo_map.await?;
+            if repo_map.included_file_count == 0 {
+                bail!("No symbols found in the project");
+            }
+
+            this.update(&mut cx, |this, _cx| {
+                if this.repo_map.is_none() {
+                    let id = this.next_context_id.post_inc();
+                    this.repo_map = Some(id);
+                    this.context.push(AssistantContext::RepoMap(RepoMapContext {
+                        id,
+                        text: repo_map.text.into(),
+                        included_file_count: repo_map.included_file_count,
+                        file_count: repo_map.file_count,
+                    }));
+                }
+            })
+        })
+    }
+
     pub fn accept_suggested_context(
         &mut self,
         suggested: &SuggestedContext,
@@ -326,6 +433,12 @@ impl ContextStore {
             AssistantContext::Thread(_) => {
                 self.threads.retain(|_, context_id| *context_id != id);
             }
+            AssistantContext::Resource(_) => {
+                self.resources.retain(|_, context_id| *context_id != id);
+            }
+            AssistantContext::RepoMap(_) => {
+                self.repo_map = None;
+            }
         }
     }
 
@@ -391,6 +504,12 @@ impl ContextStore {
         self.fetched_urls.get(url).copied()
     }
 
+    pub fn includes_resource(&self, server_id: &Arc<str>, uri: &Url) -> Option<ContextId> {
+        self.resources
+            .get(&(server_id.clone(), uri.clone()))
+            .copied()
+    }
+
     /// Replaces the context that matches the ID of the new context, if any match.
     fn replace_context(&mut self, new_context: AssistantContext) {
         let id = new_context.id();
@@ -412,7 +531,9 @@ impl ContextStore {
                 }
                 AssistantContext::Directory(_)
                 | AssistantContext::FetchedUrl(_)
-                | AssistantContext::Thread(_) => None,
+                | AssistantContext::Thread(_)
+                | AssistantContext::Resource(_)
+                | AssistantContext::RepoMap(_) => None,
             })
             .collect()
     }
@@ -550,6 +671,12 @@ pub fn refresh_context_store_text(
             // and doing the caching properly could be tricky (unless it's already handled by
             // the HttpClient?).
             AssistantContext::FetchedUrl(_) => {}
+            // Resources are kept up to date by the updates of their context server, if it
+            // sends any.
+            AssistantContext::Resource(_) => {}
+            // Rebuilding the repo map parses every file in the project, which is too slow to do
+            // on every message.
+            AssistantContext::RepoMap(_) => {}
         }
     }
 
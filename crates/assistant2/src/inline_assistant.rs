@@ -218,7 +218,7 @@ impl InlineAssistant {
 
     pub fn inline_assist(
         workspace: &mut Workspace,
-        _action: &zed_actions::assistant::InlineAssist,
+        action: &zed_actions::assistant::InlineAssist,
         window: &mut Window,
         cx: &mut Context<Workspace>,
     ) {
@@ -265,6 +265,7 @@ impl InlineAssistant {
                             &active_terminal,
                             cx.entity().downgrade(),
                             thread_store,
+                            action.prompt.clone(),
                             window,
                             cx,
                         )
@@ -890,6 +890,7 @@ impl PromptEditor<BufferCodegen> {
             model_selector: cx.new(|cx| {
                 AssistantModelSelector::new(
                     fs,
+                    None,
                     model_selector_menu_handle.clone(),
                     prompt_editor.focus_handle(cx),
                     window,
@@ -1046,6 +1047,7 @@ impl PromptEditor<TerminalCodegen> {
             model_selector: cx.new(|cx| {
                 AssistantModelSelector::new(
                     fs,
+                    None,
                     model_selector_menu_handle.clone(),
                     prompt_editor.focus_handle(cx),
                     window,
@@ -1,14 +1,22 @@
 use std::sync::Arc;
 
+use anyhow::{anyhow, Result};
+use assistant_settings::AssistantSettings;
+use collections::HashMap;
 use editor::actions::MoveUp;
 use editor::{Editor, EditorElement, EditorEvent, EditorStyle};
 use fs::Fs;
+use futures::Future;
 use gpui::{
     pulsating_between, Animation, AnimationExt, App, DismissEvent, Entity, Focusable, Subscription,
-    TextStyle, WeakEntity,
+    Task, TextStyle, WeakEntity,
+};
+use language_model::{
+    LanguageModelRegistry, LanguageModelRequestMessage, LanguageModelRequestTool, MessageContent,
+    Role,
 };
-use language_model::{LanguageModelRegistry, LanguageModelRequestTool};
 use language_model_selector::LanguageModelSelector;
+use prompt_library::{built_in_prompt_variables, PromptStore};
 use rope::Point;
 use settings::Settings;
 use std::time::Duration;
@@ -23,18 +31,22 @@ use crate::assistant_model_selector::AssistantModelSelector;
 use crate::context_picker::{ConfirmBehavior, ContextPicker};
 use crate::context_store::{refresh_context_store_text, ContextStore};
 use crate::context_strip::{ContextStrip, ContextStripEvent, SuggestContextKind};
-use crate::thread::{RequestKind, Thread};
+use crate::prompt_picker::{self, PromptPicker};
+use crate::thread::{RequestKind, Thread, ThreadError, ThreadEvent};
 use crate::thread_store::ThreadStore;
 use crate::{Chat, ChatMode, RemoveAllContext, ToggleContextPicker, ToggleModelSelector};
 
 pub struct MessageEditor {
     thread: Entity<Thread>,
+    workspace: WeakEntity<Workspace>,
     editor: Entity<Editor>,
     context_store: Entity<ContextStore>,
     context_strip: Entity<ContextStrip>,
     context_picker_menu_handle: PopoverMenuHandle<ContextPicker>,
     inline_context_picker: Entity<ContextPicker>,
     inline_context_picker_menu_handle: PopoverMenuHandle<ContextPicker>,
+    thread_store: WeakEntity<ThreadStore>,
+    prompt_picker_menu_handle: PopoverMenuHandle<PromptPicker>,
     model_selector: Entity<AssistantModelSelector>,
     model_selector_menu_handle: PopoverMenuHandle<LanguageModelSelector>,
     use_tools: bool,
@@ -53,6 +65,7 @@ impl MessageEditor {
         let context_store = cx.new(|_cx| ContextStore::new(workspace.clone()));
         let context_picker_menu_handle = PopoverMenuHandle::default();
         let inline_context_picker_menu_handle = PopoverMenuHandle::default();
+        let prompt_picker_menu_handle = PopoverMenuHandle::default();
         let model_selector_menu_handle = PopoverMenuHandle::default();
 
         let editor = cx.new(|cx| {
@@ -98,23 +111,29 @@ impl MessageEditor {
             cx.subscribe_in(&context_strip, window, Self::handle_context_strip_event),
         ];
 
+        let model_selector = cx.new(|cx| {
+            AssistantModelSelector::new(
+                fs,
+                Some(thread.clone()),
+                model_selector_menu_handle.clone(),
+                editor.focus_handle(cx),
+                window,
+                cx,
+            )
+        });
+
         Self {
             thread,
+            workspace,
             editor: editor.clone(),
             context_store,
             context_strip,
             context_picker_menu_handle,
             inline_context_picker,
             inline_context_picker_menu_handle,
-            model_selector: cx.new(|cx| {
-                AssistantModelSelector::new(
-                    fs,
-                    model_selector_menu_handle.clone(),
-                    editor.focus_handle(cx),
-                    window,
-                    cx,
-                )
-            }),
+            thread_store,
+            prompt_picker_menu_handle,
+            model_selector,
             model_selector_menu_handle,
             use_tools: false,
             _subscriptions: subscriptions,
@@ -163,9 +182,7 @@ impl MessageEditor {
     }
 
     fn is_model_selected(&self, cx: &App) -> bool {
-        LanguageModelRegistry::read_global(cx)
-            .active_model()
-            .is_some()
+        self.thread.read(cx).model(cx).is_some()
     }
 
     fn send_to_model(
@@ -174,7 +191,10 @@ impl MessageEditor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let provider = LanguageModelRegistry::read_global(cx).active_provider();
+        let Some(model) = self.thread.read(cx).model(cx) else {
+            return;
+        };
+        let provider = LanguageModelRegistry::read_global(cx).provider(&model.provider_id());
         if provider
             .as_ref()
             .map_or(false, |provider| provider.must_accept_terms(cx))
@@ -183,10 +203,23 @@ impl MessageEditor {
             return;
         }
 
-        let model_registry = LanguageModelRegistry::read_global(cx);
-        let Some(model) = model_registry.active_model() else {
-            return;
-        };
+        let profile = AssistantSettings::get_global(cx)
+            .profile(self.thread.read(cx).profile_id())
+            .map(|(_, profile)| profile.clone());
+        let system_prompt = profile
+            .as_ref()
+            .and_then(|profile| profile.system_prompt.clone())
+            .map(|title| {
+                // There's no one to fill in the prompt's own variables, so only the built-in ones
+                // are available to it.
+                let variables = self
+                    .workspace
+                    .update(cx, |workspace, cx| {
+                        built_in_prompt_variables(workspace, window, cx)
+                    })
+                    .unwrap_or_else(|_| Task::ready(HashMap::default()));
+                load_prompt(title, variables, PromptStore::global(cx))
+            });
 
         let user_message = self.editor.update(cx, |editor, cx| {
             let text = editor.text(cx);
@@ -201,17 +234,50 @@ impl MessageEditor {
         let use_tools = self.use_tools;
         cx.spawn(move |_, mut cx| async move {
             refresh_task.await;
+            let system_prompt = match system_prompt {
+                Some(system_prompt) => system_prompt.await.map(Some),
+                None => Ok(None),
+            };
             thread
                 .update(&mut cx, |thread, cx| {
                     let context = context_store.read(cx).snapshot(cx).collect::<Vec<_>>();
                     thread.insert_user_message(user_message, context, cx);
                     let mut request = thread.to_completion_request(request_kind, cx);
 
+                    match system_prompt {
+                        Ok(Some(system_prompt)) => {
+                            request.messages.insert(
+                                0,
+                                LanguageModelRequestMessage {
+                                    role: Role::System,
+                                    content: vec![MessageContent::Text(system_prompt)],
+                                    cache: false,
+                                },
+                            );
+                        }
+                        Ok(None) => {}
+                        Err(error) => {
+                            // The message is still sent, so that it isn't lost, but without the
+                            // profile's system prompt.
+                            cx.emit(ThreadEvent::ShowError(ThreadError::Message(
+                                format!("Failed to load the system prompt: {error}").into(),
+                            )));
+                        }
+                    }
+
                     if use_tools {
                         request.tools = thread
                             .tools()
                             .tools(cx)
                             .into_iter()
+                            .filter(|tool| {
+                                profile.as_ref().map_or(true, |profile| {
+                                    profile.is_tool_enabled(
+                                        &tool.name(),
+                                        tool.context_server_id().as_deref(),
+                                    )
+                                })
+                            })
                             .map(|tool| LanguageModelRequestTool {
                                 name: tool.name(),
                                 description: tool.description(),
@@ -247,6 +313,12 @@ impl MessageEditor {
                         let char_behind_cursor = snapshot.chars_at(behind_cursor).next();
                         if char_behind_cursor == Some('@') {
                             self.inline_context_picker_menu_handle.show(window, cx);
+                        } else if char_behind_cursor == Some('/')
+                            && newest_cursor == Point::new(0, 1)
+                            && prompt_picker::has_prompts(&self.thread_store, cx)
+                        {
+                            // Like slash commands, prompts are picked at the start of a message.
+                            self.prompt_picker_menu_handle.show(window, cx);
                         }
                     }
                 });
@@ -266,6 +338,11 @@ impl MessageEditor {
         window.focus(&editor_focus_handle);
     }
 
+    fn handle_prompt_picker_dismissed(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let editor_focus_handle = self.editor.focus_handle(cx);
+        window.focus(&editor_focus_handle);
+    }
+
     fn handle_context_strip_event(
         &mut self,
         _context_strip: &Entity<ContextStrip>,
@@ -287,6 +364,7 @@ impl MessageEditor {
     fn move_up(&mut self, _: &MoveUp, window: &mut Window, cx: &mut Context<Self>) {
         if self.context_picker_menu_handle.is_deployed()
             || self.inline_context_picker_menu_handle.is_deployed()
+            || self.prompt_picker_menu_handle.is_deployed()
         {
             cx.propagate();
         } else {
@@ -295,6 +373,20 @@ impl MessageEditor {
     }
 }
 
+/// Loads the prompt library's prompt with the given title, rendered with the given variables.
+async fn load_prompt(
+    title: String,
+    variables: Task<HashMap<String, String>>,
+    prompt_store: impl Future<Output = Result<Arc<PromptStore>>>,
+) -> Result<String> {
+    let prompt_store = prompt_store.await?;
+    let prompt_id = prompt_store
+        .id_for_title(&title)
+        .ok_or_else(|| anyhow!("no prompt titled \"{title}\" in the prompt library"))?;
+    let template = prompt_store.load_template(prompt_id).await?;
+    template.render(&variables.await)
+}
+
 impl Focusable for MessageEditor {
     fn focus_handle(&self, cx: &App) -> gpui::FocusHandle {
         self.editor.focus_handle(cx)
@@ -373,6 +465,47 @@ impl Render for MessageEditor {
                             })
                             .with_handle(self.inline_context_picker_menu_handle.clone()),
                     )
+                    .child(
+                        PopoverMenu::new("prompt-picker")
+                            .menu({
+                                let thread_store = self.thread_store.clone();
+                                let editor = self.editor.downgrade();
+                                let message_editor = cx.entity().downgrade();
+                                move |window, cx| {
+                                    let prompt_picker = cx.new(|cx| {
+                                        PromptPicker::new(
+                                            thread_store.clone(),
+                                            editor.clone(),
+                                            window,
+                                            cx,
+                                        )
+                                    });
+                                    window
+                                        .subscribe(&prompt_picker, cx, {
+                                            let message_editor = message_editor.clone();
+                                            move |_, _: &DismissEvent, window, cx| {
+                                                message_editor
+                                                    .update(cx, |message_editor, cx| {
+                                                        message_editor
+                                                            .handle_prompt_picker_dismissed(
+                                                                window, cx,
+                                                            )
+                                                    })
+                                                    .ok();
+                                            }
+                                        })
+                                        .detach();
+                                    Some(prompt_picker)
+                                }
+                            })
+                            .attach(gpui::Corner::TopLeft)
+                            .anchor(gpui::Corner::BottomLeft)
+                            .offset(gpui::Point {
+                                x: px(0.0),
+                                y: (-ThemeSettings::get_global(cx).ui_font_size(cx) * 2) - px(4.0),
+                            })
+                            .with_handle(self.prompt_picker_menu_handle.clone()),
+                    )
                     .child(
                         h_flex()
                             .justify_between()
@@ -0,0 +1,374 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use context_server::protocol::ServerCapability;
+use context_server::types;
+use editor::actions::Backspace;
+use editor::Editor;
+use fuzzy::StringMatchCandidate;
+use gpui::{App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Task, WeakEntity};
+use picker::{Picker, PickerDelegate};
+use rope::Point;
+use ui::{prelude::*, ListItem};
+use util::ResultExt as _;
+use workspace::notifications::NotifyResultExt;
+
+use crate::thread_store::ThreadStore;
+
+/// Picks a prompt of a context server, and inserts the messages it returns into the editor,
+/// replacing the `/` it was opened with.
+///
+/// Like the context server slash commands, only prompts with at most one argument are offered.
+/// The argument is the text of the query after the prompt name.
+pub struct PromptPicker {
+    picker: Entity<Picker<PromptPickerDelegate>>,
+}
+
+impl PromptPicker {
+    pub fn new(
+        thread_store: WeakEntity<ThreadStore>,
+        editor: WeakEntity<Editor>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let load_prompts = load_prompt_entries(&thread_store, cx);
+        let delegate = PromptPickerDelegate {
+            thread_store,
+            editor,
+            prompt_picker: cx.weak_entity(),
+            prompts: Vec::new(),
+            matches: Vec::new(),
+            argument: String::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            let prompts = load_prompts.await;
+            this.update_in(&mut cx, |this, window, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.prompts = prompts;
+                    picker.refresh(window, cx);
+                });
+            })
+            .ok();
+        })
+        .detach();
+
+        Self { picker }
+    }
+}
+
+impl EventEmitter<DismissEvent> for PromptPicker {}
+
+impl Focusable for PromptPicker {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for PromptPicker {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .w(px(400.))
+            .min_w(px(400.))
+            .child(self.picker.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PromptEntry {
+    server_id: Arc<str>,
+    name: SharedString,
+    description: Option<SharedString>,
+    argument: Option<PromptEntryArgument>,
+}
+
+#[derive(Debug, Clone)]
+struct PromptEntryArgument {
+    name: String,
+    required: bool,
+}
+
+/// Whether any running context server provides prompts.
+pub fn has_prompts(thread_store: &WeakEntity<ThreadStore>, cx: &App) -> bool {
+    let Some(thread_store) = thread_store.upgrade() else {
+        return false;
+    };
+
+    thread_store
+        .read(cx)
+        .context_server_manager()
+        .read(cx)
+        .servers()
+        .iter()
+        .any(|server| {
+            server.client().map_or(false, |protocol| {
+                protocol.capable(ServerCapability::Prompts)
+            })
+        })
+}
+
+fn load_prompt_entries(
+    thread_store: &WeakEntity<ThreadStore>,
+    cx: &mut App,
+) -> Task<Vec<PromptEntry>> {
+    let Some(thread_store) = thread_store.upgrade() else {
+        return Task::ready(Vec::new());
+    };
+    let servers = thread_store
+        .read(cx)
+        .context_server_manager()
+        .read(cx)
+        .servers();
+
+    cx.spawn(|_| async move {
+        let mut entries = Vec::new();
+        for server in servers {
+            let Some(protocol) = server
+                .client()
+                .filter(|protocol| protocol.capable(ServerCapability::Prompts))
+            else {
+                continue;
+            };
+            let Some(prompts) = protocol.list_prompts().await.log_err() else {
+                continue;
+            };
+
+            for prompt in prompts {
+                let mut arguments = prompt.arguments.unwrap_or_default();
+                if arguments.len() > 1 {
+                    continue;
+                }
+                entries.push(PromptEntry {
+                    server_id: server.id(),
+                    name: prompt.name.into(),
+                    description: prompt.description.map(Into::into),
+                    argument: arguments.pop().map(|argument| PromptEntryArgument {
+                        name: argument.name,
+                        required: argument.required.unwrap_or(true),
+                    }),
+                });
+            }
+        }
+        entries
+    })
+}
+
+/// Joins the text of the messages a prompt returned.
+fn prompt_text(response: types::PromptsGetResponse) -> String {
+    response
+        .messages
+        .into_iter()
+        .filter_map(|message| match message.content {
+            types::MessageContent::Text { text, .. } => Some(text),
+            types::MessageContent::Image { .. } | types::MessageContent::Resource { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+pub struct PromptPickerDelegate {
+    thread_store: WeakEntity<ThreadStore>,
+    editor: WeakEntity<Editor>,
+    prompt_picker: WeakEntity<PromptPicker>,
+    prompts: Vec<PromptEntry>,
+    matches: Vec<PromptEntry>,
+    argument: String,
+    selected_index: usize,
+}
+
+impl PromptPickerDelegate {
+    fn run_prompt(&self, entry: &PromptEntry, cx: &App) -> Task<Result<String>> {
+        let Some(server) = self.thread_store.upgrade().and_then(|thread_store| {
+            thread_store
+                .read(cx)
+                .context_server_manager()
+                .read(cx)
+                .get_server(&entry.server_id)
+        }) else {
+            return Task::ready(Err(anyhow!(
+                "context server {} is not running",
+                entry.server_id
+            )));
+        };
+
+        let mut arguments = HashMap::default();
+        match &entry.argument {
+            Some(argument) if !self.argument.is_empty() => {
+                arguments.insert(argument.name.clone(), self.argument.clone());
+            }
+            Some(argument) if argument.required => {
+                return Task::ready(Err(anyhow!(
+                    "The {} prompt expects the {} argument after its name",
+                    entry.name,
+                    argument.name
+                )));
+            }
+            _ => {}
+        }
+
+        let name = entry.name.clone();
+        cx.spawn(|_| async move {
+            let protocol = server
+                .client()
+                .ok_or_else(|| anyhow!("context server is not running"))?;
+            let response = protocol.run_prompt(name, arguments).await?;
+            Ok(prompt_text(response))
+        })
+    }
+}
+
+impl PickerDelegate for PromptPickerDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Search prompts, then add an argument after a space…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, _cx: &mut App) -> SharedString {
+        "No prompts found in the running context servers".into()
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let (name, argument) = match query.split_once(' ') {
+            Some((name, argument)) => (name.to_string(), argument.trim().to_string()),
+            None => (query, String::new()),
+        };
+        self.argument = argument;
+
+        let prompts = self.prompts.clone();
+        let executor = cx.background_executor().clone();
+        let search_task = cx.background_spawn(async move {
+            if name.is_empty() {
+                return prompts;
+            }
+
+            let candidates = prompts
+                .iter()
+                .enumerate()
+                .map(|(id, prompt)| StringMatchCandidate::new(id, &prompt.name))
+                .collect::<Vec<_>>();
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &name,
+                false,
+                100,
+                &Default::default(),
+                executor,
+            )
+            .await;
+
+            matches
+                .into_iter()
+                .map(|mat| prompts[mat.candidate_id].clone())
+                .collect()
+        });
+
+        cx.spawn_in(window, |this, mut cx| async move {
+            let matches = search_task.await;
+            this.update(&mut cx, |this, cx| {
+                this.delegate.matches = matches;
+                this.delegate.selected_index = 0;
+                cx.notify();
+            })
+            .ok();
+        })
+    }
+
+    fn confirm(&mut self, _secondary: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        let Some(entry) = self.matches.get(self.selected_index) else {
+            return;
+        };
+
+        let run_prompt = self.run_prompt(entry, cx);
+        let editor = self.editor.clone();
+        cx.spawn_in(window, |this, mut cx| async move {
+            let Some(text) = run_prompt.await.notify_async_err(&mut cx) else {
+                return Ok(());
+            };
+
+            this.update_in(&mut cx, |this, window, cx| {
+                this.delegate.dismissed(window, cx);
+            })?;
+            editor.update_in(&mut cx, |editor, window, cx| {
+                let snapshot = editor.buffer().read(cx).snapshot(cx);
+                let cursor = editor.selections.newest::<Point>(cx).head();
+                if cursor.column > 0
+                    && snapshot
+                        .chars_at(Point::new(cursor.row, cursor.column - 1))
+                        .next()
+                        == Some('/')
+                {
+                    editor.backspace(&Backspace, window, cx);
+                }
+                editor.insert(&text, window, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn dismissed(&mut self, _window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.prompt_picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .ok();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let prompt = &self.matches[ix];
+
+        Some(
+            ListItem::new(ix).inset(true).toggle_state(selected).child(
+                h_flex()
+                    .gap_1p5()
+                    .w_full()
+                    .child(
+                        Icon::new(IconName::Slash)
+                            .size(IconSize::XSmall)
+                            .color(Color::Muted),
+                    )
+                    .child(Label::new(prompt.name.clone()))
+                    .children(prompt.argument.as_ref().map(|argument| {
+                        Label::new(format!("<{}>", argument.name))
+                            .size(LabelSize::Small)
+                            .color(Color::Placeholder)
+                    }))
+                    .children(prompt.description.clone().map(|description| {
+                        Label::new(description)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                            .text_ellipsis()
+                    })),
+            ),
+        )
+    }
+}
@@ -68,13 +68,18 @@ impl TerminalInlineAssistant {
         terminal_view: &Entity<TerminalView>,
         workspace: WeakEntity<Workspace>,
         thread_store: Option<WeakEntity<ThreadStore>>,
+        initial_prompt: Option<String>,
         window: &mut Window,
         cx: &mut App,
     ) {
         let terminal = terminal_view.read(cx).terminal().clone();
         let assist_id = self.next_assist_id.post_inc();
-        let prompt_buffer =
-            cx.new(|cx| MultiBuffer::singleton(cx.new(|cx| Buffer::local(String::new(), cx)), cx));
+        let prompt_buffer = cx.new(|cx| {
+            MultiBuffer::singleton(
+                cx.new(|cx| Buffer::local(initial_prompt.unwrap_or_default(), cx)),
+                cx,
+            )
+        });
         let context_store = cx.new(|_cx| ContextStore::new(workspace.clone()));
         let codegen = cx.new(|_| TerminalCodegen::new(terminal, self.telemetry.clone()));
 
@@ -223,7 +228,10 @@ impl TerminalInlineAssistant {
             .terminal
             .update(cx, |terminal, cx| {
                 let terminal = terminal.entity().read(cx);
-                let latest_output = terminal.last_n_non_empty_lines(DEFAULT_CONTEXT_LINES);
+                // When the shell reported that the last command failed, its output is the most relevant context.
+                let latest_output = terminal
+                    .last_failed_command_lines(DEFAULT_CONTEXT_LINES)
+                    .unwrap_or_else(|| terminal.last_n_non_empty_lines(DEFAULT_CONTEXT_LINES));
                 let working_directory = terminal
                     .working_directory()
                     .map(|path| path.to_string_lossy().to_string());
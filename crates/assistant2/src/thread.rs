@@ -1,25 +1,36 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use assistant_settings::{AssistantSettings, TokenUsageSettings};
 use assistant_tool::ToolWorkingSet;
 use chrono::{DateTime, Utc};
 use collections::{BTreeMap, HashMap, HashSet};
+use futures::channel::mpsc;
 use futures::future::Shared;
 use futures::{FutureExt as _, StreamExt as _};
-use gpui::{App, Context, EventEmitter, SharedString, Task};
+use gpui::{App, Context, Entity, EntityId, EventEmitter, SharedString, Subscription, Task};
+use language::{Buffer, BufferEvent};
 use language_model::{
     LanguageModel, LanguageModelCompletionEvent, LanguageModelRegistry, LanguageModelRequest,
     LanguageModelRequestMessage, LanguageModelToolResult, LanguageModelToolUse,
     LanguageModelToolUseId, MaxMonthlySpendReachedError, MessageContent, PaymentRequiredError,
-    Role, StopReason,
+    Role, StopReason, TokenUsage,
 };
+use project::buffer_store::{BufferStore, BufferStoreEvent};
+use project::Project;
 use serde::{Deserialize, Serialize};
+use settings::Settings as _;
+use text::BufferId;
 use util::{post_inc, TryFutureExt as _};
 use uuid::Uuid;
 
 use crate::context::{attach_context_to_message, ContextId, ContextSnapshot};
+use crate::thread_export::ExportedThread;
 use crate::thread_store::SavedThread;
 
+/// The most output we keep of a tool use while it runs, which is only shown to the user.
+const MAX_STREAMED_TOOL_OUTPUT_LEN: usize = 8 * 1024;
+
 #[derive(Debug, Clone, Copy)]
 pub enum RequestKind {
     Chat,
@@ -73,11 +84,38 @@ pub struct Thread {
     tool_uses_by_message: HashMap<MessageId, Vec<LanguageModelToolUse>>,
     tool_results_by_message: HashMap<MessageId, Vec<LanguageModelToolResult>>,
     pending_tool_uses_by_id: HashMap<LanguageModelToolUseId, PendingToolUse>,
+    tool_invocations: Vec<ToolInvocation>,
+    token_usage: Vec<ModelTokenUsage>,
+    /// The ID of the agent profile picked for the thread, if one was.
+    profile_id: Option<String>,
+    project: Entity<Project>,
+    /// The text of the project's buffers when each user message was sent. Checkpoints are not
+    /// saved with the thread, so they only last for the session.
+    checkpoints: BTreeMap<MessageId, HashMap<BufferId, text::BufferSnapshot>>,
+    /// The buffers edited by tools since the first checkpoint, which are kept open so they can
+    /// be restored.
+    edited_buffers: HashMap<BufferId, Entity<Buffer>>,
+    /// The last checkpoint taken before the user edited each buffer, for buffers edited while
+    /// no tool was running, other than by merging a tool's proposed changes.
+    user_edits: HashMap<BufferId, MessageId>,
+    /// The number of tool uses running, whose buffer edits are attributed to the agent.
+    running_tool_uses: usize,
+    /// The branches of buffers that tools created, like the edits proposed by the edit tool,
+    /// whose merges into their buffers are attributed to the agent, whenever they happen.
+    agent_branches: HashSet<EntityId>,
+    /// The buffers an agent branch is merging into, whose next edit is the merge.
+    agent_merges: HashSet<BufferId>,
+    buffer_subscriptions: HashMap<BufferId, Subscription>,
+    _buffer_store_subscription: Subscription,
 }
 
 impl Thread {
-    pub fn new(tools: Arc<ToolWorkingSet>, _cx: &mut Context<Self>) -> Self {
-        Self {
+    pub fn new(
+        project: Entity<Project>,
+        tools: Arc<ToolWorkingSet>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut this = Self {
             id: ThreadId::new(),
             updated_at: Utc::now(),
             summary: None,
@@ -92,18 +130,37 @@ impl Thread {
             tool_uses_by_message: HashMap::default(),
             tool_results_by_message: HashMap::default(),
             pending_tool_uses_by_id: HashMap::default(),
-        }
+            tool_invocations: Vec::new(),
+            token_usage: Vec::new(),
+            profile_id: None,
+            checkpoints: BTreeMap::default(),
+            edited_buffers: HashMap::default(),
+            user_edits: HashMap::default(),
+            running_tool_uses: 0,
+            agent_branches: HashSet::default(),
+            agent_merges: HashSet::default(),
+            buffer_subscriptions: HashMap::default(),
+            _buffer_store_subscription: cx.subscribe(
+                project.read(cx).buffer_store(),
+                Self::handle_buffer_store_event,
+            ),
+            project,
+        };
+        this.subscribe_to_open_buffers(cx);
+        this
     }
 
     pub fn from_saved(
         id: ThreadId,
         saved: SavedThread,
+        tool_invocations: Vec<ToolInvocation>,
+        project: Entity<Project>,
         tools: Arc<ToolWorkingSet>,
-        _cx: &mut Context<Self>,
+        cx: &mut Context<Self>,
     ) -> Self {
         let next_message_id = MessageId(saved.messages.len());
 
-        Self {
+        let mut this = Self {
             id,
             updated_at: saved.updated_at,
             summary: Some(saved.summary),
@@ -126,7 +183,79 @@ impl Thread {
             tool_uses_by_message: HashMap::default(),
             tool_results_by_message: HashMap::default(),
             pending_tool_uses_by_id: HashMap::default(),
+            tool_invocations,
+            token_usage: saved.token_usage,
+            profile_id: saved.profile_id,
+            checkpoints: BTreeMap::default(),
+            edited_buffers: HashMap::default(),
+            user_edits: HashMap::default(),
+            running_tool_uses: 0,
+            agent_branches: HashSet::default(),
+            agent_merges: HashSet::default(),
+            buffer_subscriptions: HashMap::default(),
+            _buffer_store_subscription: cx.subscribe(
+                project.read(cx).buffer_store(),
+                Self::handle_buffer_store_event,
+            ),
+            project,
+        };
+        this.subscribe_to_open_buffers(cx);
+        this
+    }
+
+    /// Creates a thread from an exported one, with a new ID.
+    pub fn from_exported(
+        exported: ExportedThread,
+        project: Entity<Project>,
+        tools: Arc<ToolWorkingSet>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut this = Self::new(project, tools, cx);
+        this.summary = Some(exported.summary.into());
+        this.updated_at = exported.updated_at;
+        this.token_usage = exported.token_usage;
+
+        // Context added to the thread later gets IDs counting up from zero, so the
+        // imported context counts down to keep them apart.
+        let mut next_context_id = usize::MAX;
+        for message in exported.messages {
+            let id = this.next_message_id.post_inc();
+            this.messages.push(Message {
+                id,
+                role: message.role,
+                text: message.text,
+            });
+
+            let mut context_ids = Vec::new();
+            for context in message.context {
+                let context_id = ContextId(next_context_id);
+                next_context_id -= 1;
+                this.context.insert(
+                    context_id,
+                    ContextSnapshot {
+                        id: context_id,
+                        name: context.name.into(),
+                        parent: context.parent.map(Into::into),
+                        tooltip: None,
+                        icon_path: None,
+                        kind: context.kind,
+                        text: Box::new([SharedString::from(context.text)]),
+                    },
+                );
+                context_ids.push(context_id);
+            }
+            if !context_ids.is_empty() {
+                this.context_by_message.insert(id, context_ids);
+            }
+            if !message.tool_uses.is_empty() {
+                this.tool_uses_by_message.insert(id, message.tool_uses);
+            }
+            if !message.tool_results.is_empty() {
+                this.tool_results_by_message
+                    .insert(id, message.tool_results);
+            }
         }
+        this
     }
 
     pub fn id(&self) -> &ThreadId {
@@ -159,6 +288,34 @@ impl Thread {
         cx.emit(ThreadEvent::SummaryChanged);
     }
 
+    /// Returns the ID of the agent profile picked for the thread, which is `None` for threads
+    /// that use the default profile.
+    pub fn profile_id(&self) -> Option<&str> {
+        self.profile_id.as_deref()
+    }
+
+    pub fn set_profile_id(&mut self, profile_id: Option<String>, cx: &mut Context<Self>) {
+        if self.profile_id != profile_id {
+            self.profile_id = profile_id;
+            cx.emit(ThreadEvent::ProfileChanged);
+        }
+    }
+
+    /// Returns the model to use in the thread, which is its profile's model when it has one
+    /// and that model is available, and the active model otherwise.
+    pub fn model(&self, cx: &App) -> Option<Arc<dyn LanguageModel>> {
+        let model_registry = LanguageModelRegistry::read_global(cx);
+        AssistantSettings::get_global(cx)
+            .profile(self.profile_id())
+            .and_then(|(_, profile)| profile.model.as_ref())
+            .and_then(|selection| {
+                model_registry.available_models(cx).find(|model| {
+                    model.provider_id().0 == selection.provider && model.id().0 == selection.model
+                })
+            })
+            .or_else(|| model_registry.active_model())
+    }
+
     pub fn message(&self, id: MessageId) -> Option<&Message> {
         self.messages.iter().find(|message| message.id == id)
     }
@@ -186,17 +343,118 @@ impl Thread {
         )
     }
 
+    pub fn tool_uses_for_message(&self, id: MessageId) -> &[LanguageModelToolUse] {
+        self.tool_uses_by_message
+            .get(&id)
+            .map_or(&[], |tool_uses| tool_uses.as_slice())
+    }
+
+    pub fn tool_results_for_message(&self, id: MessageId) -> &[LanguageModelToolResult] {
+        self.tool_results_by_message
+            .get(&id)
+            .map_or(&[], |tool_results| tool_results.as_slice())
+    }
+
     pub fn pending_tool_uses(&self) -> Vec<&PendingToolUse> {
         self.pending_tool_uses_by_id.values().collect()
     }
 
+    pub fn tool_uses_needing_confirmation(&self) -> Vec<&PendingToolUse> {
+        let mut tool_uses = self
+            .pending_tool_uses_by_id
+            .values()
+            .filter(|tool_use| matches!(tool_use.status, PendingToolUseStatus::NeedsConfirmation))
+            .collect::<Vec<_>>();
+        tool_uses.sort_by_key(|tool_use| tool_use.assistant_message_id);
+        tool_uses
+    }
+
+    /// Returns the tool uses that are running and have streamed some output.
+    pub fn tool_uses_streaming_output(&self) -> Vec<&PendingToolUse> {
+        let mut tool_uses = self
+            .pending_tool_uses_by_id
+            .values()
+            .filter(|tool_use| {
+                matches!(tool_use.status, PendingToolUseStatus::Running { .. })
+                    && !tool_use.output.is_empty()
+            })
+            .collect::<Vec<_>>();
+        tool_uses.sort_by_key(|tool_use| tool_use.assistant_message_id);
+        tool_uses
+    }
+
+    /// Returns every use of a tool in this thread, along with whether it was allowed and its result.
+    pub fn tool_invocations(&self) -> &[ToolInvocation] {
+        &self.tool_invocations
+    }
+
+    /// Returns the tokens each model used in this thread.
+    pub fn token_usage(&self) -> &[ModelTokenUsage] {
+        &self.token_usage
+    }
+
+    /// Returns the tokens used in this thread, across all models.
+    pub fn total_token_usage(&self) -> TokenUsage {
+        self.token_usage
+            .iter()
+            .fold(TokenUsage::default(), |total, model_usage| {
+                total + model_usage.usage
+            })
+    }
+
+    /// Holds off on using the tool until the user allows or denies it.
+    pub fn request_tool_use_confirmation(
+        &mut self,
+        tool_use_id: &LanguageModelToolUseId,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(tool_use) = self.pending_tool_uses_by_id.get_mut(tool_use_id) {
+            tool_use.status = PendingToolUseStatus::NeedsConfirmation;
+            cx.notify();
+        }
+    }
+
+    /// Answers the tool use with an error instead of using the tool.
+    pub fn deny_tool_use(
+        &mut self,
+        tool_use_id: LanguageModelToolUseId,
+        decision: ToolUseDecision,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(assistant_message_id) = self
+            .pending_tool_uses_by_id
+            .get(&tool_use_id)
+            .map(|tool_use| tool_use.assistant_message_id)
+        else {
+            return;
+        };
+        self.insert_tool_output(
+            assistant_message_id,
+            tool_use_id,
+            decision,
+            Task::ready(Err(anyhow!("Permission to use this tool was denied."))),
+            cx,
+        );
+    }
+
     pub fn insert_user_message(
         &mut self,
         text: impl Into<String>,
         context: Vec<ContextSnapshot>,
         cx: &mut Context<Self>,
     ) {
+        let checkpoint = self
+            .project
+            .read(cx)
+            .opened_buffers(cx)
+            .into_iter()
+            .map(|buffer| {
+                let buffer = buffer.read(cx);
+                (buffer.remote_id(), buffer.text_snapshot())
+            })
+            .collect();
         let message_id = self.insert_message(Role::User, text, cx);
+        self.checkpoints.insert(message_id, checkpoint);
         let context_ids = context.iter().map(|context| context.id).collect::<Vec<_>>();
         self.context
             .extend(context.into_iter().map(|context| (context.id, context)));
@@ -220,6 +478,196 @@ impl Thread {
         id
     }
 
+    pub fn project(&self) -> &Entity<Project> {
+        &self.project
+    }
+
+    /// Returns whether the project's files can be restored to how they were when the given
+    /// message was sent.
+    pub fn has_checkpoint(&self, message_id: MessageId) -> bool {
+        self.checkpoints.contains_key(&message_id)
+    }
+
+    /// Returns the buffers tools edited since the given message was sent, along with their text
+    /// at that time. Some of them may have been edited back to that text.
+    pub fn edited_buffers_since(
+        &self,
+        message_id: MessageId,
+        cx: &App,
+    ) -> Vec<(Entity<Buffer>, text::BufferSnapshot)> {
+        let Some(checkpoint) = self.checkpoints.get(&message_id) else {
+            return Vec::new();
+        };
+        let mut edited_buffers = self
+            .edited_buffers
+            .iter()
+            .filter_map(|(buffer_id, buffer)| {
+                let old_snapshot = checkpoint.get(buffer_id)?;
+                Some((buffer.clone(), old_snapshot.clone()))
+            })
+            .collect::<Vec<_>>();
+        edited_buffers.sort_by_key(|(buffer, _)| buffer.read(cx).remote_id());
+        edited_buffers
+    }
+
+    /// Returns the buffers edited since the first message of the thread was sent, along with
+    /// their text at that time.
+    pub fn edited_buffers(&self, cx: &App) -> Vec<(Entity<Buffer>, text::BufferSnapshot)> {
+        match self.checkpoints.keys().next() {
+            Some(first_message_id) => self.edited_buffers_since(*first_message_id, cx),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the buffers whose text changed since the given message was sent, along with
+    /// their text at that time.
+    pub fn changed_buffers_since(
+        &self,
+        message_id: MessageId,
+        cx: &App,
+    ) -> Vec<(Entity<Buffer>, text::BufferSnapshot)> {
+        let mut changed_buffers = self.edited_buffers_since(message_id, cx);
+        changed_buffers.retain(|(buffer, old_snapshot)| {
+            let new_snapshot = buffer.read(cx).text_snapshot();
+            let unchanged = new_snapshot.version() == old_snapshot.version()
+                || (new_snapshot.len() == old_snapshot.len()
+                    && new_snapshot
+                        .as_rope()
+                        .chunks()
+                        .flat_map(str::bytes)
+                        .eq(old_snapshot.as_rope().chunks().flat_map(str::bytes)));
+            !unchanged
+        });
+        changed_buffers
+    }
+
+    /// Returns the buffers whose text changed since the first message of the thread was sent,
+    /// along with their text at that time.
+    pub fn changed_buffers(&self, cx: &App) -> Vec<(Entity<Buffer>, text::BufferSnapshot)> {
+        match self.checkpoints.keys().next() {
+            Some(first_message_id) => self.changed_buffers_since(*first_message_id, cx),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the buffers that restoring the checkpoint of the given message would change,
+    /// which the user also edited since the message was sent. Restoring them reverts those
+    /// edits too.
+    pub fn user_edited_buffers_since(
+        &self,
+        message_id: MessageId,
+        cx: &App,
+    ) -> Vec<Entity<Buffer>> {
+        self.changed_buffers_since(message_id, cx)
+            .into_iter()
+            .filter(|(buffer, _)| {
+                self.user_edits
+                    .get(&buffer.read(cx).remote_id())
+                    .is_some_and(|last_checkpoint| *last_checkpoint >= message_id)
+            })
+            .map(|(buffer, _)| buffer)
+            .collect()
+    }
+
+    /// Restores the buffers tools changed since the given message was sent to their text at
+    /// that time.
+    pub fn restore_checkpoint(
+        &mut self,
+        message_id: MessageId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let restores = self
+            .changed_buffers_since(message_id, cx)
+            .into_iter()
+            .map(|(buffer, old_snapshot)| {
+                let diff = buffer.read(cx).diff(old_snapshot.text(), cx);
+                cx.spawn(|_, mut cx| async move {
+                    let diff = diff.await;
+                    buffer.update(&mut cx, |buffer, cx| {
+                        buffer.finalize_last_transaction();
+                        buffer.start_transaction();
+                        buffer.apply_diff(diff, cx);
+                        buffer.end_transaction(cx);
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        cx.spawn(|_, _| async move {
+            futures::future::try_join_all(restores).await?;
+            Ok(())
+        })
+    }
+
+    fn subscribe_to_open_buffers(&mut self, cx: &mut Context<Self>) {
+        for buffer in self.project.read(cx).opened_buffers(cx) {
+            self.subscribe_to_buffer(&buffer, cx);
+        }
+    }
+
+    fn subscribe_to_buffer(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let buffer_id = buffer.read(cx).remote_id();
+        let subscription = cx.subscribe(buffer, |this, buffer, event, cx| {
+            let buffer_id = buffer.read(cx).remote_id();
+            match event {
+                BufferEvent::BranchCreated { branch_id } => {
+                    if this.running_tool_uses > 0 {
+                        this.agent_branches.insert(*branch_id);
+                    }
+                    return;
+                }
+                BufferEvent::BranchMerged { branch_id } => {
+                    if this.agent_branches.contains(branch_id) {
+                        this.agent_merges.insert(buffer_id);
+                    }
+                    return;
+                }
+                BufferEvent::Edited => {}
+                _ => return,
+            }
+            let is_agent_merge = this.agent_merges.remove(&buffer_id);
+            let Some(last_checkpoint) = this.checkpoints.keys().next_back().copied() else {
+                return;
+            };
+            // Edits made while a tool runs are taken to be the tool's, even though the user
+            // could be typing at the same time.
+            if this.running_tool_uses > 0 || is_agent_merge {
+                if !this.edited_buffers.contains_key(&buffer_id) {
+                    this.edited_buffers.insert(buffer_id, buffer);
+                    cx.notify();
+                }
+            } else {
+                this.user_edits.insert(buffer_id, last_checkpoint);
+            }
+        });
+        self.buffer_subscriptions.insert(buffer_id, subscription);
+    }
+
+    fn handle_buffer_store_event(
+        &mut self,
+        _: Entity<BufferStore>,
+        event: &BufferStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            BufferStoreEvent::BufferAdded(buffer) => {
+                // The buffer was opened after these checkpoints were taken, so its text then was
+                // what it is now.
+                let snapshot = buffer.read(cx).text_snapshot();
+                for checkpoint in self.checkpoints.values_mut() {
+                    checkpoint
+                        .entry(snapshot.remote_id())
+                        .or_insert_with(|| snapshot.clone());
+                }
+                self.subscribe_to_buffer(buffer, cx);
+            }
+            BufferStoreEvent::BufferDropped(buffer_id) => {
+                self.buffer_subscriptions.remove(buffer_id);
+            }
+            BufferStoreEvent::BufferChangedFilePath { .. } => {}
+        }
+    }
+
     /// Returns the representation of this [`Thread`] in a textual form.
     ///
     /// This is the representation we use when attaching a thread as context to another thread.
@@ -317,9 +765,14 @@ impl Thread {
         cx: &mut Context<Self>,
     ) {
         let pending_completion_id = post_inc(&mut self.completion_count);
+        let provider_id = model.provider_id().0.to_string();
+        let model_id = model.id().0.to_string();
 
         let task = cx.spawn(|thread, mut cx| async move {
             let stream = model.stream_completion(request, &cx);
+            // Providers report the tokens used by the request so far, so only the
+            // difference from their last report is added to the thread.
+            let mut request_usage = TokenUsage::default();
             let stream_completion = async {
                 let mut events = stream.await?;
                 let mut stop_reason = StopReason::EndTurn;
@@ -335,6 +788,17 @@ impl Thread {
                             LanguageModelCompletionEvent::Stop(reason) => {
                                 stop_reason = reason;
                             }
+                            LanguageModelCompletionEvent::UsageUpdate(usage) => {
+                                add_model_token_usage(
+                                    &mut thread.token_usage,
+                                    ModelTokenUsage {
+                                        provider_id: provider_id.clone(),
+                                        model_id: model_id.clone(),
+                                        usage: usage - request_usage,
+                                    },
+                                );
+                                request_usage = usage;
+                            }
                             LanguageModelCompletionEvent::Text(chunk) => {
                                 if let Some(last_message) = thread.messages.last_mut() {
                                     if last_message.role == Role::Assistant {
@@ -372,6 +836,7 @@ impl Thread {
                                             id: tool_use.id,
                                             name: tool_use.name,
                                             input: tool_use.input,
+                                            output: String::new(),
                                             status: PendingToolUseStatus::Idle,
                                         },
                                     );
@@ -403,31 +868,43 @@ impl Thread {
             let result = stream_completion.await;
 
             thread
-                .update(&mut cx, |thread, cx| match result.as_ref() {
-                    Ok(stop_reason) => match stop_reason {
-                        StopReason::ToolUse => {
-                            cx.emit(ThreadEvent::UsePendingTools);
-                        }
-                        StopReason::EndTurn => {}
-                        StopReason::MaxTokens => {}
-                    },
-                    Err(error) => {
-                        if error.is::<PaymentRequiredError>() {
-                            cx.emit(ThreadEvent::ShowError(ThreadError::PaymentRequired));
-                        } else if error.is::<MaxMonthlySpendReachedError>() {
-                            cx.emit(ThreadEvent::ShowError(ThreadError::MaxMonthlySpendReached));
-                        } else {
-                            let error_message = error
-                                .chain()
-                                .map(|err| err.to_string())
-                                .collect::<Vec<_>>()
-                                .join("\n");
-                            cx.emit(ThreadEvent::ShowError(ThreadError::Message(
-                                SharedString::from(error_message.clone()),
-                            )));
-                        }
+                .update(&mut cx, |thread, cx| {
+                    if !request_usage.is_empty() {
+                        cx.emit(ThreadEvent::TokensUsed(ModelTokenUsage {
+                            provider_id,
+                            model_id,
+                            usage: request_usage,
+                        }));
+                    }
 
-                        thread.cancel_last_completion();
+                    match result.as_ref() {
+                        Ok(stop_reason) => match stop_reason {
+                            StopReason::ToolUse => {
+                                cx.emit(ThreadEvent::UsePendingTools);
+                            }
+                            StopReason::EndTurn => {}
+                            StopReason::MaxTokens => {}
+                        },
+                        Err(error) => {
+                            if error.is::<PaymentRequiredError>() {
+                                cx.emit(ThreadEvent::ShowError(ThreadError::PaymentRequired));
+                            } else if error.is::<MaxMonthlySpendReachedError>() {
+                                cx.emit(ThreadEvent::ShowError(
+                                    ThreadError::MaxMonthlySpendReached,
+                                ));
+                            } else {
+                                let error_message = error
+                                    .chain()
+                                    .map(|err| err.to_string())
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                cx.emit(ThreadEvent::ShowError(ThreadError::Message(
+                                    SharedString::from(error_message.clone()),
+                                )));
+                            }
+
+                            thread.cancel_last_completion();
+                        }
                     }
                 })
                 .ok();
@@ -440,10 +917,11 @@ impl Thread {
     }
 
     pub fn summarize(&mut self, cx: &mut Context<Self>) {
-        let Some(provider) = LanguageModelRegistry::read_global(cx).active_provider() else {
+        let Some(model) = self.model(cx) else {
             return;
         };
-        let Some(model) = LanguageModelRegistry::read_global(cx).active_model() else {
+        let Some(provider) = LanguageModelRegistry::read_global(cx).provider(&model.provider_id())
+        else {
             return;
         };
 
@@ -496,15 +974,35 @@ impl Thread {
         &mut self,
         assistant_message_id: MessageId,
         tool_use_id: LanguageModelToolUseId,
+        decision: ToolUseDecision,
         output: Task<Result<String>>,
         cx: &mut Context<Self>,
     ) {
+        let invocation_ix = self.tool_invocations.len();
+        let (tool_name, input) = self
+            .pending_tool_uses_by_id
+            .get(&tool_use_id)
+            .map(|tool_use| (tool_use.name.clone(), tool_use.input.to_string()))
+            .unwrap_or_default();
+        self.tool_invocations.push(ToolInvocation {
+            tool_use_id: tool_use_id.clone(),
+            tool_name,
+            input,
+            decision,
+            requested_at: Utc::now(),
+            finished_at: None,
+            result: None,
+        });
+
+        self.running_tool_uses += 1;
         let insert_output_task = cx.spawn(|thread, mut cx| {
             let tool_use_id = tool_use_id.clone();
             async move {
                 let output = output.await;
                 thread
                     .update(&mut cx, |thread, cx| {
+                        thread.running_tool_uses = thread.running_tool_uses.saturating_sub(1);
+
                         // The tool use was requested by an Assistant message,
                         // so we want to attach the tool results to the next
                         // user message.
@@ -515,15 +1013,15 @@ impl Thread {
                             .entry(next_user_message)
                             .or_default();
 
-                        match output {
+                        let result = match output {
                             Ok(output) => {
                                 tool_results.push(LanguageModelToolResult {
                                     tool_use_id: tool_use_id.to_string(),
-                                    content: output,
+                                    content: output.clone(),
                                     is_error: false,
                                 });
 
-                                cx.emit(ThreadEvent::ToolFinished { tool_use_id });
+                                ToolInvocationResult::Output(output)
                             }
                             Err(err) => {
                                 tool_results.push(LanguageModelToolResult {
@@ -537,8 +1035,16 @@ impl Thread {
                                 {
                                     tool_use.status = PendingToolUseStatus::Error(err.to_string());
                                 }
+
+                                ToolInvocationResult::Error(err.to_string())
                             }
+                        };
+
+                        if let Some(invocation) = thread.tool_invocations.get_mut(invocation_ix) {
+                            invocation.finished_at = Some(Utc::now());
+                            invocation.result = Some(result);
                         }
+                        cx.emit(ThreadEvent::ToolFinished { tool_use_id });
                     })
                     .ok();
             }
@@ -551,6 +1057,39 @@ impl Thread {
         }
     }
 
+    /// Appends the output that a tool sends while it runs to its pending tool use, so that it
+    /// can be shown before the tool finishes.
+    pub fn stream_tool_output(
+        &mut self,
+        tool_use_id: LanguageModelToolUseId,
+        mut output: mpsc::UnboundedReceiver<String>,
+        cx: &mut Context<Self>,
+    ) {
+        cx.spawn(|thread, mut cx| async move {
+            while let Some(chunk) = output.next().await {
+                let updated = thread.update(&mut cx, |thread, cx| {
+                    if let Some(tool_use) = thread.pending_tool_uses_by_id.get_mut(&tool_use_id) {
+                        tool_use.output.push_str(&chunk);
+                        if tool_use.output.len() > MAX_STREAMED_TOOL_OUTPUT_LEN {
+                            let mut start = tool_use.output.len() - MAX_STREAMED_TOOL_OUTPUT_LEN;
+                            while !tool_use.output.is_char_boundary(start) {
+                                start += 1;
+                            }
+                            tool_use.output.drain(..start);
+                        }
+                        cx.emit(ThreadEvent::StreamedToolOutput {
+                            tool_use_id: tool_use_id.clone(),
+                        });
+                    }
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
     /// Cancels the last pending completion, if there are any pending.
     ///
     /// Returns whether a completion was canceled.
@@ -567,6 +1106,11 @@ impl Thread {
 pub enum ThreadError {
     PaymentRequired,
     MaxMonthlySpendReached,
+    /// The estimated cost of this month's tokens approached or exceeded the monthly budget.
+    MonthlyBudget {
+        spent: f64,
+        budget: f64,
+    },
     Message(SharedString),
 }
 
@@ -577,11 +1121,18 @@ pub enum ThreadEvent {
     StreamedAssistantText(MessageId, String),
     MessageAdded(MessageId),
     SummaryChanged,
+    ProfileChanged,
     UsePendingTools,
+    /// A request finished having used these tokens.
+    TokensUsed(ModelTokenUsage),
     ToolFinished {
         #[allow(unused)]
         tool_use_id: LanguageModelToolUseId,
     },
+    StreamedToolOutput {
+        #[allow(unused)]
+        tool_use_id: LanguageModelToolUseId,
+    },
 }
 
 impl EventEmitter<ThreadEvent> for Thread {}
@@ -598,12 +1149,15 @@ pub struct PendingToolUse {
     pub assistant_message_id: MessageId,
     pub name: String,
     pub input: serde_json::Value,
+    /// The end of the output the tool has streamed while running.
+    pub output: String,
     pub status: PendingToolUseStatus,
 }
 
 #[derive(Debug, Clone)]
 pub enum PendingToolUseStatus {
     Idle,
+    NeedsConfirmation,
     Running { _task: Shared<Task<()>> },
     Error(#[allow(unused)] String),
 }
@@ -613,3 +1167,372 @@ impl PendingToolUseStatus {
         matches!(self, PendingToolUseStatus::Idle)
     }
 }
+
+/// The tokens a model used in a [`Thread`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelTokenUsage {
+    pub provider_id: String,
+    pub model_id: String,
+    pub usage: TokenUsage,
+}
+
+/// Adds the tokens a model used to those it used before.
+pub(crate) fn add_model_token_usage(
+    token_usage: &mut Vec<ModelTokenUsage>,
+    model_usage: ModelTokenUsage,
+) {
+    if let Some(existing) = token_usage.iter_mut().find(|existing| {
+        existing.provider_id == model_usage.provider_id && existing.model_id == model_usage.model_id
+    }) {
+        existing.usage += model_usage.usage;
+    } else {
+        token_usage.push(model_usage);
+    }
+}
+
+/// Returns the estimated cost of the tokens, in US dollars, or `None` when none of the
+/// models have a price. Models without a price are left out of the estimate.
+pub fn token_usage_cost(token_usage: &[ModelTokenUsage], cx: &App) -> Option<f64> {
+    let settings = TokenUsageSettings::get_global(cx);
+    token_usage
+        .iter()
+        .filter_map(|model_usage| {
+            settings.cost(
+                &model_usage.provider_id,
+                &model_usage.model_id,
+                &model_usage.usage,
+            )
+        })
+        .fold(None, |total, cost| Some(total.unwrap_or(0.) + cost))
+}
+
+/// Formats a cost in US dollars, with more precision for costs under a dollar.
+pub fn format_cost(cost: f64) -> String {
+    if cost < 1. {
+        format!("${cost:.4}")
+    } else {
+        format!("${cost:.2}")
+    }
+}
+
+/// Why a tool was or was not used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolUseDecision {
+    AllowedBySettings,
+    AllowedByUser,
+    DeniedBySettings,
+    DeniedByUser,
+}
+
+/// A record of a tool use in a [`Thread`], kept as an audit trail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub tool_use_id: LanguageModelToolUseId,
+    pub tool_name: String,
+    /// The JSON input the tool was given.
+    pub input: String,
+    pub decision: ToolUseDecision,
+    pub requested_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The tool's result, or `None` if the tool was still running when the thread was saved.
+    pub result: Option<ToolInvocationResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ToolInvocationResult {
+    Output(String),
+    Error(String),
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use editor::ProposedChangesEditor;
+    use futures::channel::oneshot;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::FakeFs;
+    use serde_json::json;
+    use settings::{Settings as _, SettingsStore};
+    use util::path;
+    use workspace::Workspace;
+
+    pub(crate) fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            client::init_settings(cx);
+            Project::init_settings(cx);
+            workspace::init_settings(cx);
+            editor::init(cx);
+        });
+    }
+
+    pub(crate) async fn test_project(
+        files: serde_json::Value,
+        cx: &mut TestAppContext,
+    ) -> Entity<Project> {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(path!("/root"), files).await;
+        Project::test(fs, [path!("/root").as_ref()], cx).await
+    }
+
+    pub(crate) async fn open_buffer(
+        project: &Entity<Project>,
+        path: &str,
+        cx: &mut TestAppContext,
+    ) -> Entity<Buffer> {
+        project
+            .update(cx, |project, cx| {
+                let project_path = project
+                    .find_project_path(std::path::Path::new(path), cx)
+                    .unwrap();
+                project.open_buffer(project_path, cx)
+            })
+            .await
+            .unwrap()
+    }
+
+    /// Starts a tool use that runs until the returned sender is dropped, so that the edits
+    /// made until then count as the agent's.
+    pub(crate) fn start_tool_use(
+        thread: &Entity<Thread>,
+        cx: &mut TestAppContext,
+    ) -> oneshot::Sender<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        thread.update(cx, |thread, cx| {
+            let message_id = thread.insert_message(Role::Assistant, "Editing files", cx);
+            let output = cx.background_spawn(async move {
+                done_rx.await.ok();
+                Ok("Done".to_string())
+            });
+            thread.insert_tool_output(
+                message_id,
+                "tool-1".into(),
+                ToolUseDecision::AllowedBySettings,
+                output,
+                cx,
+            );
+        });
+        done_tx
+    }
+
+    fn edit(buffer: &Entity<Buffer>, old_text: &str, new_text: &str, cx: &mut TestAppContext) {
+        buffer.update(cx, |buffer, cx| {
+            let start = buffer.text().find(old_text).unwrap();
+            buffer.edit([(start..start + old_text.len(), new_text)], None, cx);
+        });
+    }
+
+    fn text(buffer: &Entity<Buffer>, cx: &TestAppContext) -> String {
+        buffer.read_with(cx, |buffer, _| buffer.text())
+    }
+
+    #[gpui::test]
+    async fn test_checkpoint_capture_and_restore(cx: &mut TestAppContext) {
+        init_test(cx);
+        let project = test_project(json!({ "a.txt": "one\n", "b.txt": "two\n" }), cx).await;
+        let buffer_a = open_buffer(&project, "root/a.txt", cx).await;
+        let thread =
+            cx.new(|cx| Thread::new(project.clone(), Arc::new(ToolWorkingSet::default()), cx));
+
+        let first_message = thread.update(cx, |thread, cx| {
+            thread.insert_user_message("Edit the files", Vec::new(), cx);
+            thread.messages().last().unwrap().id
+        });
+
+        // The agent edits a buffer that was open, and one it opens itself.
+        let tool_use = start_tool_use(&thread, cx);
+        edit(&buffer_a, "one", "ONE", cx);
+        let buffer_b = open_buffer(&project, "root/b.txt", cx).await;
+        edit(&buffer_b, "two", "TWO", cx);
+        drop(tool_use);
+        cx.run_until_parked();
+
+        // The user edits one of them afterwards.
+        edit(&buffer_b, "TWO", "TWO!", cx);
+        cx.run_until_parked();
+
+        let second_message = thread.update(cx, |thread, cx| {
+            thread.insert_user_message("Thanks", Vec::new(), cx);
+            thread.messages().last().unwrap().id
+        });
+
+        thread.read_with(cx, |thread, cx| {
+            let changed = thread
+                .changed_buffers(cx)
+                .into_iter()
+                .map(|(buffer, old_snapshot)| (buffer.read(cx).text(), old_snapshot.text()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                changed,
+                [
+                    ("ONE\n".to_string(), "one\n".to_string()),
+                    ("TWO!\n".to_string(), "two\n".to_string())
+                ]
+            );
+            assert_eq!(
+                thread.user_edited_buffers_since(first_message, cx),
+                [buffer_b.clone()]
+            );
+            // The user's edit was made before the second message, so restoring it keeps it.
+            assert!(thread
+                .user_edited_buffers_since(second_message, cx)
+                .is_empty());
+            assert!(thread.changed_buffers_since(second_message, cx).is_empty());
+        });
+
+        thread
+            .update(cx, |thread, cx| {
+                thread.restore_checkpoint(first_message, cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(text(&buffer_a, cx), "one\n");
+        assert_eq!(text(&buffer_b, cx), "two\n");
+    }
+
+    #[gpui::test]
+    async fn test_user_edits_are_not_restored(cx: &mut TestAppContext) {
+        init_test(cx);
+        let project = test_project(json!({ "a.txt": "one\n" }), cx).await;
+        let buffer = open_buffer(&project, "root/a.txt", cx).await;
+        let thread =
+            cx.new(|cx| Thread::new(project.clone(), Arc::new(ToolWorkingSet::default()), cx));
+
+        let message = thread.update(cx, |thread, cx| {
+            thread.insert_user_message("Look at the file", Vec::new(), cx);
+            thread.messages().last().unwrap().id
+        });
+        edit(&buffer, "one", "typed by the user", cx);
+        cx.run_until_parked();
+
+        thread.read_with(cx, |thread, cx| {
+            assert!(thread.edited_buffers(cx).is_empty());
+            assert!(thread.changed_buffers_since(message, cx).is_empty());
+        });
+        thread
+            .update(cx, |thread, cx| thread.restore_checkpoint(message, cx))
+            .await
+            .unwrap();
+        assert_eq!(text(&buffer, cx), "typed by the user\n");
+    }
+
+    #[gpui::test]
+    async fn test_accepted_edit_proposals_are_restored(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(assistant_tools::init);
+        let project = test_project(json!({ "a.txt": "one\ntwo\n" }), cx).await;
+        let buffer = open_buffer(&project, "root/a.txt", cx).await;
+        let window = cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+        let workspace = window.root(cx).unwrap();
+        let cx = &mut VisualTestContext::from_window(*window, cx);
+        let thread =
+            cx.new(|cx| Thread::new(project.clone(), Arc::new(ToolWorkingSet::default()), cx));
+
+        let message = thread.update(cx, |thread, cx| {
+            thread.insert_user_message("Capitalize the first line", Vec::new(), cx);
+            thread.messages().last().unwrap().id
+        });
+
+        // The edit tool only proposes its edits, and finishes before the user accepts them.
+        thread.update_in(cx, |thread, window, cx| {
+            let assistant_message = thread.insert_message(Role::Assistant, "Editing a.txt", cx);
+            let tool = thread.tools().tool("edit-file", cx).unwrap();
+            let output = tool.run(
+                json!({
+                    "path": "root/a.txt",
+                    "edits": [{ "old_text": "one", "new_text": "ONE" }]
+                }),
+                workspace.downgrade(),
+                window,
+                cx,
+            );
+            thread.insert_tool_output(
+                assistant_message,
+                "tool-1".into(),
+                ToolUseDecision::AllowedBySettings,
+                output,
+                cx,
+            );
+        });
+        cx.run_until_parked();
+        assert_eq!(text(&buffer, cx), "one\ntwo\n");
+
+        let proposal = workspace
+            .read_with(cx, |workspace, cx| {
+                workspace.active_item_as::<ProposedChangesEditor>(cx)
+            })
+            .unwrap();
+        let branch = proposal
+            .read_with(cx, |proposal, _| proposal.branch_buffer_for_base(&buffer))
+            .unwrap();
+        branch.update(cx, |branch, cx| branch.merge_into_base(Vec::new(), cx));
+        cx.run_until_parked();
+        assert_eq!(text(&buffer, cx), "ONE\ntwo\n");
+
+        thread.read_with(cx, |thread, cx| {
+            assert_eq!(thread.edited_buffers(cx).len(), 1);
+            assert!(thread.user_edited_buffers_since(message, cx).is_empty());
+        });
+        thread
+            .update(cx, |thread, cx| thread.restore_checkpoint(message, cx))
+            .await
+            .unwrap();
+        assert_eq!(text(&buffer, cx), "one\ntwo\n");
+    }
+
+    #[gpui::test]
+    fn test_token_usage_cost(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(|cx| {
+            TokenUsageSettings::register(cx);
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings::<TokenUsageSettings>(cx, |settings| {
+                    settings.prices = Some(vec![assistant_settings::ModelPrice {
+                        provider: "anthropic".into(),
+                        model: "claude-3-5-sonnet-latest".into(),
+                        input: 3.,
+                        output: 15.,
+                        cache_write: None,
+                        cache_read: None,
+                    }]);
+                });
+            });
+        });
+
+        let model_usage = |provider: &str, model: &str, input_tokens, output_tokens| {
+            ModelTokenUsage {
+                provider_id: provider.into(),
+                model_id: model.into(),
+                usage: TokenUsage {
+                    input_tokens,
+                    output_tokens,
+                    ..Default::default()
+                },
+            }
+        };
+        let mut token_usage = Vec::new();
+        for usage in [
+            model_usage("anthropic", "claude-3-5-sonnet-latest", 500_000, 50_000),
+            model_usage("ollama", "qwen2.5-coder", 1_000_000, 100_000),
+            model_usage("anthropic", "claude-3-5-sonnet-latest", 500_000, 50_000),
+        ] {
+            add_model_token_usage(&mut token_usage, usage);
+        }
+
+        assert_eq!(
+            token_usage,
+            [
+                model_usage("anthropic", "claude-3-5-sonnet-latest", 1_000_000, 100_000),
+                model_usage("ollama", "qwen2.5-coder", 1_000_000, 100_000),
+            ]
+        );
+        cx.update(|cx| {
+            // The unpriced model is left out of the estimate.
+            assert_eq!(token_usage_cost(&token_usage, cx), Some(3. + 1.5));
+            assert_eq!(token_usage_cost(&token_usage[1..], cx), None);
+        });
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, Utc};
+use language_model::{LanguageModelToolResult, LanguageModelToolUse, Role};
+use serde::{Deserialize, Serialize};
+
+use crate::context::ContextKind;
+use crate::thread::{ModelTokenUsage, Thread};
+
+/// A [`Thread`] exported to share or archive it, which can be imported back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedThread {
+    /// The version of the format, which is increased whenever a change would keep
+    /// older versions of Zed from importing the thread.
+    pub version: u32,
+    pub summary: String,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<ExportedMessage>,
+    #[serde(default)]
+    pub token_usage: Vec<ModelTokenUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: Role,
+    pub text: String,
+    #[serde(default)]
+    pub context: Vec<ExportedContext>,
+    #[serde(default)]
+    pub tool_uses: Vec<LanguageModelToolUse>,
+    #[serde(default)]
+    pub tool_results: Vec<LanguageModelToolResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedContext {
+    pub kind: ContextKind,
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<String>,
+    pub text: String,
+}
+
+impl ExportedThread {
+    pub const VERSION: u32 = 1;
+
+    pub fn from_thread(thread: &Thread) -> Self {
+        Self {
+            version: Self::VERSION,
+            summary: thread.summary_or_default().to_string(),
+            updated_at: thread.updated_at(),
+            messages: thread
+                .messages()
+                .map(|message| ExportedMessage {
+                    role: message.role,
+                    text: message.text.clone(),
+                    context: thread
+                        .context_for_message(message.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|context| ExportedContext {
+                            kind: context.kind,
+                            name: context.name.to_string(),
+                            parent: context.parent.map(|parent| parent.to_string()),
+                            text: context.text.join("\n"),
+                        })
+                        .collect(),
+                    tool_uses: thread.tool_uses_for_message(message.id).to_vec(),
+                    tool_results: thread.tool_results_for_message(message.id).to_vec(),
+                })
+                .collect(),
+            token_usage: thread.token_usage().to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let Version { version } =
+            serde_json::from_str(json).context("the file is not an exported thread")?;
+        if version > Self::VERSION {
+            return Err(anyhow!(
+                "the thread was exported by a newer version of Zed (format version {version})"
+            ));
+        }
+        serde_json::from_str(json).context("failed to read the exported thread")
+    }
+
+    /// Renders the thread as Markdown, for reading rather than importing.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        write!(markdown, "# {}\n\n", self.summary).ok();
+
+        for message in &self.messages {
+            let role = match message.role {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                Role::System => "System",
+            };
+            write!(markdown, "## {role}\n\n").ok();
+
+            for tool_result in &message.tool_results {
+                let heading = if tool_result.is_error {
+                    "Tool Error"
+                } else {
+                    "Tool Result"
+                };
+                write!(
+                    markdown,
+                    "### {heading} for `{}`\n\n",
+                    tool_result.tool_use_id
+                )
+                .ok();
+                push_code_block(&mut markdown, "", &tool_result.content);
+            }
+
+            if !message.text.is_empty() {
+                markdown.push_str(message.text.trim_end());
+                markdown.push_str("\n\n");
+            }
+
+            for tool_use in &message.tool_uses {
+                write!(
+                    markdown,
+                    "### Tool Use: `{}` (`{}`)\n\n",
+                    tool_use.name, tool_use.id
+                )
+                .ok();
+                let input = serde_json::to_string_pretty(&tool_use.input).unwrap_or_default();
+                push_code_block(&mut markdown, "json", &input);
+            }
+
+            for context in &message.context {
+                write!(markdown, "### {}: {}", context.kind.label(), context.name).ok();
+                if let Some(parent) = context.parent.as_ref() {
+                    write!(markdown, " ({parent})").ok();
+                }
+                markdown.push_str("\n\n");
+                push_code_block(&mut markdown, "", &context.text);
+            }
+        }
+
+        while markdown.ends_with("\n\n") {
+            markdown.pop();
+        }
+        markdown
+    }
+}
+
+/// Appends a fenced code block, with a fence longer than any run of backticks in the text.
+fn push_code_block(markdown: &mut String, language: &str, text: &str) {
+    let longest_backtick_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_backtick_run.max(2) + 1);
+    write!(
+        markdown,
+        "{fence}{language}\n{}\n{fence}\n\n",
+        text.trim_end()
+    )
+    .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread::tests::{init_test, test_project};
+    use assistant_tool::ToolWorkingSet;
+    use gpui::{AppContext as _, TestAppContext};
+    use indoc::indoc;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn exported_thread() -> ExportedThread {
+        ExportedThread {
+            version: ExportedThread::VERSION,
+            summary: "Reading Files".into(),
+            updated_at: DateTime::default(),
+            messages: vec![
+                ExportedMessage {
+                    role: Role::User,
+                    text: "What does main do?".into(),
+                    context: vec![ExportedContext {
+                        kind: ContextKind::File,
+                        name: "main.rs".into(),
+                        parent: Some("src".into()),
+                        text: "fn main() {}".into(),
+                    }],
+                    tool_uses: Vec::new(),
+                    tool_results: Vec::new(),
+                },
+                ExportedMessage {
+                    role: Role::Assistant,
+                    text: "Let me read it.".into(),
+                    context: Vec::new(),
+                    tool_uses: vec![LanguageModelToolUse {
+                        id: "tool_1".into(),
+                        name: "read-file".into(),
+                        input: json!({ "path": "src/main.rs" }),
+                    }],
+                    tool_results: Vec::new(),
+                },
+                ExportedMessage {
+                    role: Role::User,
+                    text: String::new(),
+                    context: Vec::new(),
+                    tool_uses: Vec::new(),
+                    tool_results: vec![LanguageModelToolResult {
+                        tool_use_id: "tool_1".into(),
+                        content: "```rust\nfn main() {}\n```".into(),
+                        is_error: false,
+                    }],
+                },
+            ],
+            token_usage: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        assert_eq!(
+            exported_thread().to_markdown(),
+            indoc! {r#"
+                # Reading Files
+
+                ## User
+
+                What does main do?
+
+                ### File: main.rs (src)
+
+                ```
+                fn main() {}
+                ```
+
+                ## Assistant
+
+                Let me read it.
+
+                ### Tool Use: `read-file` (`tool_1`)
+
+                ```json
+                {
+                  "path": "src/main.rs"
+                }
+                ```
+
+                ## User
+
+                ### Tool Result for `tool_1`
+
+                ````
+                ```rust
+                fn main() {}
+                ```
+                ````
+            "#}
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let thread = exported_thread();
+        let json = thread.to_json().unwrap();
+        assert_eq!(ExportedThread::from_json(&json).unwrap(), thread);
+
+        let newer = json!({ "version": ExportedThread::VERSION + 1 }).to_string();
+        assert!(ExportedThread::from_json(&newer).is_err());
+        assert!(ExportedThread::from_json("{}").is_err());
+    }
+
+    #[gpui::test]
+    async fn test_import_and_export_thread(cx: &mut TestAppContext) {
+        init_test(cx);
+        let project = test_project(json!({ "src": { "main.rs": "fn main() {}" } }), cx).await;
+        let exported = exported_thread();
+
+        let thread = cx.new(|cx| {
+            Thread::from_exported(
+                exported.clone(),
+                project.clone(),
+                Arc::new(ToolWorkingSet::default()),
+                cx,
+            )
+        });
+        thread.read_with(cx, |thread, _| {
+            assert_eq!(thread.messages().count(), 3);
+            assert_eq!(ExportedThread::from_thread(thread), exported);
+        });
+    }
+}
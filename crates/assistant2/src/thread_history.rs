@@ -1,4 +1,4 @@
-use assistant_context_editor::SavedContextMetadata;
+use assistant_context_editor::{humanize_token_count, SavedContextMetadata};
 use gpui::{
     uniform_list, App, Entity, FocusHandle, Focusable, ScrollStrategy, UniformListScrollHandle,
     WeakEntity,
@@ -7,6 +7,7 @@ use time::{OffsetDateTime, UtcOffset};
 use ui::{prelude::*, IconButtonShape, ListItem, ListItemSpacing, Tooltip};
 
 use crate::history_store::{HistoryEntry, HistoryStore};
+use crate::thread::{format_cost, token_usage_cost};
 use crate::thread_store::SavedThreadMetadata;
 use crate::{AssistantPanel, RemoveSelectedThread};
 
@@ -247,6 +248,22 @@ impl RenderOnce for PastThread {
             time_format::TimestampFormat::EnhancedAbsolute,
         );
 
+        let total_tokens = self
+            .thread
+            .token_usage
+            .iter()
+            .map(|model_usage| model_usage.usage.total_tokens())
+            .sum::<u32>();
+        let token_usage =
+            (total_tokens > 0).then(|| match token_usage_cost(&self.thread.token_usage, cx) {
+                Some(cost) => format!(
+                    "{} tokens · {}",
+                    humanize_token_count(total_tokens as usize),
+                    format_cost(cost)
+                ),
+                None => format!("{} tokens", humanize_token_count(total_tokens as usize)),
+            });
+
         ListItem::new(SharedString::from(self.thread.id.to_string()))
             .outlined()
             .toggle_state(self.selected)
@@ -260,6 +277,13 @@ impl RenderOnce for PastThread {
             .end_slot(
                 h_flex()
                     .gap_1p5()
+                    .when_some(token_usage, |this, token_usage| {
+                        this.child(
+                            Label::new(token_usage)
+                                .color(Color::Muted)
+                                .size(LabelSize::XSmall),
+                        )
+                    })
                     .child(
                         Label::new(thread_timestamp)
                             .color(Color::Muted)
@@ -1,39 +1,60 @@
+use std::fmt;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 use anyhow::{anyhow, Result};
 use assistant_tool::{ToolId, ToolWorkingSet};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use collections::HashMap;
 use context_server::manager::ContextServerManager;
-use context_server::{ContextServerFactoryRegistry, ContextServerTool};
+use context_server::protocol::{InitializedContextServerProtocol, ServerCapability};
+use context_server::{types, ContextServerFactoryRegistry, ContextServerTool};
 use futures::future::{self, BoxFuture, Shared};
 use futures::FutureExt as _;
 use gpui::{
-    prelude::*, App, BackgroundExecutor, Context, Entity, Global, ReadGlobal, SharedString, Task,
+    prelude::*, App, BackgroundExecutor, Context, Entity, EventEmitter, Global, ReadGlobal,
+    SharedString, Task,
 };
 use heed::types::SerdeBincode;
 use heed::Database;
 use language_model::Role;
 use project::Project;
 use serde::{Deserialize, Serialize};
+use url::Url;
 use util::ResultExt as _;
 
-use crate::thread::{MessageId, Thread, ThreadId};
+use crate::thread::{
+    add_model_token_usage, MessageId, ModelTokenUsage, Thread, ThreadId, ToolInvocation,
+};
+use crate::thread_export::ExportedThread;
 
 pub fn init(cx: &mut App) {
     ThreadsDatabase::init(cx);
 }
 
 pub struct ThreadStore {
-    #[allow(unused)]
     project: Entity<Project>,
     tools: Arc<ToolWorkingSet>,
     context_server_manager: Entity<ContextServerManager>,
     context_server_tool_ids: HashMap<Arc<str>, Vec<ToolId>>,
+    resource_subscriptions: HashMap<(Arc<str>, Url), Weak<ResourceSubscription>>,
     threads: Vec<SavedThreadMetadata>,
+    /// The month of [`Self::monthly_token_usage`], formatted as `YYYY-MM`.
+    month: String,
+    monthly_token_usage: Vec<ModelTokenUsage>,
+}
+
+pub enum ThreadStoreEvent {
+    /// A context server resource with a live [`ResourceSubscription`] changed.
+    ResourceUpdated {
+        server_id: Arc<str>,
+        uri: Url,
+        text: SharedString,
+    },
 }
 
+impl EventEmitter<ThreadStoreEvent> for ThreadStore {}
+
 impl ThreadStore {
     pub fn new(
         project: Entity<Project>,
@@ -51,7 +72,10 @@ impl ThreadStore {
                 tools,
                 context_server_manager,
                 context_server_tool_ids: HashMap::default(),
+                resource_subscriptions: HashMap::default(),
                 threads: Vec::new(),
+                month: current_month(),
+                monthly_token_usage: Vec::new(),
             };
             this.register_context_server_handlers(cx);
             this.reload(cx).detach_and_log_err(cx);
@@ -62,6 +86,86 @@ impl ThreadStore {
         Ok(this)
     }
 
+    pub fn context_server_manager(&self) -> Entity<ContextServerManager> {
+        self.context_server_manager.clone()
+    }
+
+    /// Reads the text of a context server resource.
+    ///
+    /// When the server supports it, the returned subscription keeps the server sending
+    /// updates of the resource, which are emitted as [`ThreadStoreEvent::ResourceUpdated`].
+    pub fn read_resource(
+        &mut self,
+        server_id: Arc<str>,
+        uri: Url,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<(SharedString, Option<Arc<ResourceSubscription>>)>> {
+        let Some(client) = self
+            .context_server_manager
+            .read(cx)
+            .get_server(&server_id)
+            .and_then(|server| server.client())
+        else {
+            return Task::ready(Err(anyhow!("context server {server_id} is not running")));
+        };
+        let existing_subscription = self
+            .resource_subscriptions
+            .get(&(server_id.clone(), uri.clone()))
+            .and_then(Weak::upgrade);
+
+        cx.spawn(|this, mut cx| async move {
+            let text = resource_text(client.read_resource(uri.clone()).await?);
+            let subscription = match existing_subscription {
+                Some(subscription) => Some(subscription),
+                None if client.supports_resource_subscriptions() => client
+                    .subscribe_resource(uri.clone())
+                    .await
+                    .log_err()
+                    .map(|_| {
+                        Arc::new(ResourceSubscription {
+                            client,
+                            uri: uri.clone(),
+                            executor: cx.background_executor().clone(),
+                        })
+                    }),
+                None => None,
+            };
+            if let Some(subscription) = subscription.as_ref() {
+                this.update(&mut cx, |this, _| {
+                    this.resource_subscriptions
+                        .insert((server_id, uri), Arc::downgrade(subscription));
+                })?;
+            }
+            Ok((text.into(), subscription))
+        })
+    }
+
+    fn resource_updated(&mut self, server_id: Arc<str>, uri: Url, cx: &mut Context<Self>) {
+        let key = (server_id, uri);
+        let Some(subscription) = self
+            .resource_subscriptions
+            .get(&key)
+            .and_then(Weak::upgrade)
+        else {
+            self.resource_subscriptions.remove(&key);
+            return;
+        };
+
+        let (server_id, uri) = key;
+        cx.spawn(|this, mut cx| async move {
+            let response = subscription.client.read_resource(uri.clone()).await?;
+            let text = resource_text(response).into();
+            this.update(&mut cx, |_, cx| {
+                cx.emit(ThreadStoreEvent::ResourceUpdated {
+                    server_id,
+                    uri,
+                    text,
+                })
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
     /// Returns the number of threads.
     pub fn thread_count(&self) -> usize {
         self.threads.len()
@@ -77,8 +181,45 @@ impl ThreadStore {
         self.threads().into_iter().take(limit).collect()
     }
 
+    /// Returns the tokens each model used this month, across all threads.
+    pub fn monthly_token_usage(&self) -> &[ModelTokenUsage] {
+        if self.month == current_month() {
+            &self.monthly_token_usage
+        } else {
+            &[]
+        }
+    }
+
+    /// Adds the tokens a request used to this month's usage.
+    pub fn record_token_usage(&mut self, usage: ModelTokenUsage, cx: &mut Context<Self>) {
+        let month = current_month();
+        if self.month != month {
+            self.month = month.clone();
+            self.monthly_token_usage.clear();
+        }
+        add_model_token_usage(&mut self.monthly_token_usage, usage.clone());
+
+        let database_future = ThreadsDatabase::global_future(cx);
+        cx.background_executor()
+            .spawn(async move {
+                let database = database_future.await.map_err(|err| anyhow!(err))?;
+                database.add_monthly_token_usage(month, usage).await
+            })
+            .detach_and_log_err(cx);
+    }
+
     pub fn create_thread(&mut self, cx: &mut Context<Self>) -> Entity<Thread> {
-        cx.new(|cx| Thread::new(self.tools.clone(), cx))
+        cx.new(|cx| Thread::new(self.project.clone(), self.tools.clone(), cx))
+    }
+
+    /// Imports a thread exported as JSON, saving it as a new thread.
+    pub fn import_thread(&mut self, json: &str, cx: &mut Context<Self>) -> Result<Entity<Thread>> {
+        let exported = ExportedThread::from_json(json)?;
+        let thread = cx.new(|cx| {
+            Thread::from_exported(exported, self.project.clone(), self.tools.clone(), cx)
+        });
+        self.save_thread(&thread, cx).detach_and_log_err(cx);
+        Ok(thread)
     }
 
     pub fn open_thread(
@@ -94,16 +235,27 @@ impl ThreadStore {
                 .try_find_thread(id.clone())
                 .await?
                 .ok_or_else(|| anyhow!("no thread found with ID: {id:?}"))?;
+            let tool_invocations = database.tool_invocations(id.clone()).await?;
 
             this.update(&mut cx, |this, cx| {
-                cx.new(|cx| Thread::from_saved(id.clone(), thread, this.tools.clone(), cx))
+                cx.new(|cx| {
+                    Thread::from_saved(
+                        id.clone(),
+                        thread,
+                        tool_invocations,
+                        this.project.clone(),
+                        this.tools.clone(),
+                        cx,
+                    )
+                })
             })
         })
     }
 
     pub fn save_thread(&self, thread: &Entity<Thread>, cx: &mut Context<Self>) -> Task<Result<()>> {
-        let (metadata, thread) = thread.update(cx, |thread, _cx| {
+        let (metadata, thread, tool_invocations) = thread.update(cx, |thread, _cx| {
             let id = thread.id().clone();
+            let tool_invocations = thread.tool_invocations().to_vec();
             let thread = SavedThread {
                 summary: thread.summary_or_default(),
                 updated_at: thread.updated_at(),
@@ -115,15 +267,19 @@ impl ThreadStore {
                         text: message.text.clone(),
                     })
                     .collect(),
+                token_usage: thread.token_usage().to_vec(),
+                profile_id: thread.profile_id().map(str::to_string),
             };
 
-            (id, thread)
+            (id, thread, tool_invocations)
         });
 
         let database_future = ThreadsDatabase::global_future(cx);
         cx.spawn(|this, mut cx| async move {
             let database = database_future.await.map_err(|err| anyhow!(err))?;
-            database.save_thread(metadata, thread).await?;
+            database
+                .save_thread(metadata, thread, tool_invocations)
+                .await?;
 
             this.update(&mut cx, |this, cx| this.reload(cx))?.await
         })
@@ -144,15 +300,16 @@ impl ThreadStore {
 
     pub fn reload(&self, cx: &mut Context<Self>) -> Task<Result<()>> {
         let database_future = ThreadsDatabase::global_future(cx);
+        let month = current_month();
         cx.spawn(|this, mut cx| async move {
-            let threads = database_future
-                .await
-                .map_err(|err| anyhow!(err))?
-                .list_threads()
-                .await?;
+            let database = database_future.await.map_err(|err| anyhow!(err))?;
+            let threads = database.list_threads().await?;
+            let monthly_token_usage = database.monthly_token_usage(month.clone()).await?;
 
             this.update(&mut cx, |this, cx| {
                 this.threads = threads;
+                this.month = month;
+                this.monthly_token_usage = monthly_token_usage;
                 cx.notify();
             })
         })
@@ -176,6 +333,20 @@ impl ThreadStore {
         match event {
             context_server::manager::Event::ServerStarted { server_id } => {
                 if let Some(server) = context_server_manager.read(cx).get_server(server_id) {
+                    if let Some(protocol) = server
+                        .client()
+                        .filter(|protocol| protocol.capable(ServerCapability::Resources))
+                    {
+                        let this = cx.weak_entity();
+                        let server_id = server_id.clone();
+                        protocol.on_resource_updated(move |uri, mut cx| {
+                            this.update(&mut cx, |this, cx| {
+                                this.resource_updated(server_id.clone(), uri, cx)
+                            })
+                            .ok();
+                        });
+                    }
+
                     let context_server_manager = context_server_manager.clone();
                     cx.spawn({
                         let server = server.clone();
@@ -185,7 +356,7 @@ impl ThreadStore {
                                 return;
                             };
 
-                            if protocol.capable(context_server::protocol::ServerCapability::Tools) {
+                            if protocol.capable(ServerCapability::Tools) {
                                 if let Some(tools) = protocol.list_tools().await.log_err() {
                                     let tool_ids = tools
                                         .tools
@@ -220,16 +391,62 @@ impl ThreadStore {
                 if let Some(tool_ids) = self.context_server_tool_ids.remove(server_id) {
                     tool_working_set.remove(&tool_ids);
                 }
+                self.resource_subscriptions
+                    .retain(|(subscribed_server_id, _), _| subscribed_server_id != server_id);
             }
         }
     }
 }
 
+/// Keeps a context server sending updates of a resource, and unsubscribes once dropped.
+pub struct ResourceSubscription {
+    client: Arc<InitializedContextServerProtocol>,
+    uri: Url,
+    executor: BackgroundExecutor,
+}
+
+impl Drop for ResourceSubscription {
+    fn drop(&mut self) {
+        let client = self.client.clone();
+        let uri = self.uri.clone();
+        self.executor
+            .spawn(async move { client.unsubscribe_resource(uri).await.log_err() })
+            .detach();
+    }
+}
+
+impl fmt::Debug for ResourceSubscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceSubscription")
+            .field("uri", &self.uri)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the current month in the user's time zone, formatted as `YYYY-MM`.
+fn current_month() -> String {
+    Local::now().format("%Y-%m").to_string()
+}
+
+/// Joins the text contents of a resource, leaving out binary contents.
+fn resource_text(response: types::ResourcesReadResponse) -> String {
+    response
+        .contents
+        .into_iter()
+        .filter_map(|contents| match contents {
+            types::ResourceContentsType::Text(contents) => Some(contents.text),
+            types::ResourceContentsType::Blob(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedThreadMetadata {
     pub id: ThreadId,
     pub summary: SharedString,
     pub updated_at: DateTime<Utc>,
+    pub token_usage: Vec<ModelTokenUsage>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -237,6 +454,13 @@ pub struct SavedThread {
     pub summary: SharedString,
     pub updated_at: DateTime<Utc>,
     pub messages: Vec<SavedMessage>,
+    /// Kept in its own table rather than encoded with the thread, so that the thread's
+    /// encoding doesn't change.
+    #[serde(skip)]
+    pub token_usage: Vec<ModelTokenUsage>,
+    /// Also kept in its own table.
+    #[serde(skip)]
+    pub profile_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -256,6 +480,14 @@ pub(crate) struct ThreadsDatabase {
     executor: BackgroundExecutor,
     env: heed::Env,
     threads: Database<SerdeBincode<ThreadId>, SerdeBincode<SavedThread>>,
+    /// The tools used in each thread, kept apart from the threads so that their encoding doesn't change.
+    tool_invocations: Database<SerdeBincode<ThreadId>, SerdeBincode<Vec<ToolInvocation>>>,
+    /// The tokens used in each thread.
+    token_usage: Database<SerdeBincode<ThreadId>, SerdeBincode<Vec<ModelTokenUsage>>>,
+    /// The tokens used in each month, keyed by `YYYY-MM`, which outlive deleted threads.
+    monthly_token_usage: Database<SerdeBincode<String>, SerdeBincode<Vec<ModelTokenUsage>>>,
+    /// The ID of the agent profile picked for each thread.
+    profile_ids: Database<SerdeBincode<ThreadId>, SerdeBincode<String>>,
 }
 
 impl ThreadsDatabase {
@@ -287,34 +519,45 @@ impl ThreadsDatabase {
         let env = unsafe {
             heed::EnvOpenOptions::new()
                 .map_size(ONE_GB_IN_BYTES)
-                .max_dbs(1)
+                .max_dbs(5)
                 .open(path)?
         };
 
         let mut txn = env.write_txn()?;
         let threads = env.create_database(&mut txn, Some("threads"))?;
+        let tool_invocations = env.create_database(&mut txn, Some("tool_invocations"))?;
+        let token_usage = env.create_database(&mut txn, Some("token_usage"))?;
+        let monthly_token_usage = env.create_database(&mut txn, Some("monthly_token_usage"))?;
+        let profile_ids = env.create_database(&mut txn, Some("profile_ids"))?;
         txn.commit()?;
 
         Ok(Self {
             executor,
             env,
             threads,
+            tool_invocations,
+            token_usage,
+            monthly_token_usage,
+            profile_ids,
         })
     }
 
     pub fn list_threads(&self) -> Task<Result<Vec<SavedThreadMetadata>>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let token_usage = self.token_usage;
 
         self.executor.spawn(async move {
             let txn = env.read_txn()?;
             let mut iter = threads.iter(&txn)?;
             let mut threads = Vec::new();
             while let Some((key, value)) = iter.next().transpose()? {
+                let token_usage = token_usage.get(&txn, &key)?.unwrap_or_default();
                 threads.push(SavedThreadMetadata {
                     id: key,
                     summary: value.summary,
                     updated_at: value.updated_at,
+                    token_usage,
                 });
             }
 
@@ -325,21 +568,54 @@ impl ThreadsDatabase {
     pub fn try_find_thread(&self, id: ThreadId) -> Task<Result<Option<SavedThread>>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let token_usage = self.token_usage;
+        let profile_ids = self.profile_ids;
 
         self.executor.spawn(async move {
             let txn = env.read_txn()?;
-            let thread = threads.get(&txn, &id)?;
-            Ok(thread)
+            let Some(mut thread) = threads.get(&txn, &id)? else {
+                return Ok(None);
+            };
+            thread.token_usage = token_usage.get(&txn, &id)?.unwrap_or_default();
+            thread.profile_id = profile_ids.get(&txn, &id)?;
+            Ok(Some(thread))
         })
     }
 
-    pub fn save_thread(&self, id: ThreadId, thread: SavedThread) -> Task<Result<()>> {
+    pub fn tool_invocations(&self, id: ThreadId) -> Task<Result<Vec<ToolInvocation>>> {
+        let env = self.env.clone();
+        let tool_invocations = self.tool_invocations;
+
+        self.executor.spawn(async move {
+            let txn = env.read_txn()?;
+            let invocations = tool_invocations.get(&txn, &id)?;
+            Ok(invocations.unwrap_or_default())
+        })
+    }
+
+    pub fn save_thread(
+        &self,
+        id: ThreadId,
+        thread: SavedThread,
+        invocations: Vec<ToolInvocation>,
+    ) -> Task<Result<()>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let tool_invocations = self.tool_invocations;
+        let token_usage = self.token_usage;
+        let profile_ids = self.profile_ids;
 
         self.executor.spawn(async move {
             let mut txn = env.write_txn()?;
             threads.put(&mut txn, &id, &thread)?;
+            tool_invocations.put(&mut txn, &id, &invocations)?;
+            token_usage.put(&mut txn, &id, &thread.token_usage)?;
+            match thread.profile_id.as_ref() {
+                Some(profile_id) => profile_ids.put(&mut txn, &id, profile_id)?,
+                None => {
+                    profile_ids.delete(&mut txn, &id)?;
+                }
+            }
             txn.commit()?;
             Ok(())
         })
@@ -348,12 +624,211 @@ impl ThreadsDatabase {
     pub fn delete_thread(&self, id: ThreadId) -> Task<Result<()>> {
         let env = self.env.clone();
         let threads = self.threads;
+        let tool_invocations = self.tool_invocations;
+        let token_usage = self.token_usage;
+        let profile_ids = self.profile_ids;
 
         self.executor.spawn(async move {
             let mut txn = env.write_txn()?;
             threads.delete(&mut txn, &id)?;
+            tool_invocations.delete(&mut txn, &id)?;
+            token_usage.delete(&mut txn, &id)?;
+            profile_ids.delete(&mut txn, &id)?;
+            txn.commit()?;
+            Ok(())
+        })
+    }
+
+    pub fn monthly_token_usage(&self, month: String) -> Task<Result<Vec<ModelTokenUsage>>> {
+        let env = self.env.clone();
+        let monthly_token_usage = self.monthly_token_usage;
+
+        self.executor.spawn(async move {
+            let txn = env.read_txn()?;
+            let usage = monthly_token_usage.get(&txn, &month)?;
+            Ok(usage.unwrap_or_default())
+        })
+    }
+
+    pub fn add_monthly_token_usage(
+        &self,
+        month: String,
+        usage: ModelTokenUsage,
+    ) -> Task<Result<()>> {
+        let env = self.env.clone();
+        let monthly_token_usage = self.monthly_token_usage;
+
+        self.executor.spawn(async move {
+            let mut txn = env.write_txn()?;
+            let mut month_usage = monthly_token_usage.get(&txn, &month)?.unwrap_or_default();
+            add_model_token_usage(&mut month_usage, usage);
+            monthly_token_usage.put(&mut txn, &month, &month_usage)?;
             txn.commit()?;
             Ok(())
         })
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::thread::tests::{init_test, test_project};
+    use context_server::manager::ContextServer;
+    use context_server::transport::FakeTransport;
+    use context_server::ServerConfig;
+    use gpui::TestAppContext;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use settings::SettingsStore;
+    use std::path::Path;
+
+    /// Stores the threads of a test in the given directory.
+    pub(crate) fn init_test_database(path: &Path, cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let database =
+                ThreadsDatabase::new(path.to_path_buf(), cx.background_executor().clone())
+                    .map(Arc::new)
+                    .map_err(Arc::new);
+            cx.set_global(GlobalThreadsDatabase(
+                future::ready(database).boxed().shared(),
+            ));
+        });
+    }
+
+    fn request_count(transport: &FakeTransport, method: &str) -> usize {
+        transport
+            .sent_messages()
+            .iter()
+            .filter(|message| message["method"] == method)
+            .count()
+    }
+
+    #[gpui::test]
+    async fn test_resource_subscriptions(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(context_server::init);
+        let database_dir = tempfile::tempdir().unwrap();
+        init_test_database(database_dir.path(), cx);
+        let project = test_project(json!({}), cx).await;
+        let thread_store = cx
+            .update(|cx| ThreadStore::new(project.clone(), Arc::default(), cx))
+            .unwrap();
+        cx.run_until_parked();
+
+        let resource_text = Arc::new(Mutex::new("first"));
+        let transport = Arc::new(FakeTransport::new());
+        transport.on_request("initialize", |_| {
+            Ok(json!({
+                "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+                "capabilities": { "resources": { "subscribe": true } },
+                "serverInfo": { "name": "test", "version": "1.0" },
+            }))
+        });
+        transport.on_request("resources/read", {
+            let resource_text = resource_text.clone();
+            move |params| {
+                Ok(json!({
+                    "contents": [{ "uri": params["uri"], "text": *resource_text.lock() }],
+                }))
+            }
+        });
+        transport.on_request("resources/subscribe", |_| Ok(json!({})));
+        transport.on_request("resources/unsubscribe", |_| Ok(json!({})));
+
+        let server = Arc::new(ContextServer::new(
+            "test".into(),
+            Arc::new(ServerConfig::default()),
+        ));
+        server
+            .clone()
+            .start_with_transport(
+                "test".into(),
+                transport.clone(),
+                project.downgrade(),
+                &cx.to_async(),
+            )
+            .await
+            .unwrap();
+        let context_server_manager =
+            thread_store.read_with(cx, |thread_store, _| thread_store.context_server_manager());
+        context_server_manager.update(cx, |manager, cx| manager.insert_server(server, cx));
+        cx.run_until_parked();
+
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        cx.update(|cx| {
+            let updates = updates.clone();
+            cx.subscribe(&thread_store, move |_, event, _| match event {
+                ThreadStoreEvent::ResourceUpdated { text, .. } => {
+                    updates.lock().push(text.to_string())
+                }
+            })
+            .detach();
+        });
+
+        // Reading a resource again reuses its subscription.
+        let uri = Url::parse("file:///root/notes.txt").unwrap();
+        let (text, subscription) = thread_store
+            .update(cx, |thread_store, cx| {
+                thread_store.read_resource("test".into(), uri.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(text, "first");
+        let (_, second_subscription) = thread_store
+            .update(cx, |thread_store, cx| {
+                thread_store.read_resource("test".into(), uri.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            subscription.as_ref().unwrap(),
+            second_subscription.as_ref().unwrap()
+        ));
+        assert_eq!(request_count(&transport, "resources/subscribe"), 1);
+
+        let notify_updated = || {
+            transport.send_to_client(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": uri },
+            }));
+        };
+        *resource_text.lock() = "second";
+        notify_updated();
+        cx.run_until_parked();
+        assert_eq!(*updates.lock(), ["second"]);
+
+        // Dropping the last handle to the subscription unsubscribes, and later updates are ignored.
+        drop((subscription, second_subscription));
+        cx.run_until_parked();
+        assert_eq!(request_count(&transport, "resources/unsubscribe"), 1);
+        notify_updated();
+        cx.run_until_parked();
+        assert_eq!(*updates.lock(), ["second"]);
+        thread_store.read_with(cx, |thread_store, _| {
+            assert!(thread_store.resource_subscriptions.is_empty())
+        });
+
+        // Subscriptions are forgotten once their server stops, which it does here
+        // because it isn't in the settings.
+        let (_, _subscription) = thread_store
+            .update(cx, |thread_store, cx| {
+                thread_store.read_resource("test".into(), uri.clone(), cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(request_count(&transport, "resources/subscribe"), 2);
+        cx.update(|cx| SettingsStore::update_global(cx, |_, _| {}));
+        cx.run_until_parked();
+        thread_store.read_with(cx, |thread_store, _| {
+            assert!(thread_store.resource_subscriptions.is_empty())
+        });
+        let error = thread_store
+            .update(cx, |thread_store, cx| {
+                thread_store.read_resource("test".into(), uri.clone(), cx)
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "context server test is not running");
+    }
+}
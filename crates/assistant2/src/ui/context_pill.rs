@@ -184,7 +184,9 @@ impl RenderOnce for ContextPill {
                             ContextKind::File => "Active Tab",
                             ContextKind::Thread
                             | ContextKind::Directory
-                            | ContextKind::FetchedUrl => "Active",
+                            | ContextKind::FetchedUrl
+                            | ContextKind::Resource
+                            | ContextKind::RepoMap => "Active",
                         })
                         .size(LabelSize::XSmall)
                         .color(Color::Muted),
@@ -2255,7 +2255,8 @@ impl AssistantContext {
                                             cx,
                                         );
                                     }
-                                    LanguageModelCompletionEvent::ToolUse(_) => {}
+                                    LanguageModelCompletionEvent::ToolUse(_)
+                                    | LanguageModelCompletionEvent::UsageUpdate(_) => {}
                                 }
                             });
 
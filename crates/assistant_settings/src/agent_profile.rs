@@ -0,0 +1,68 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::LanguageModelSelection;
+
+/// A named set of the model, tools, and system prompt the assistant uses in a thread.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AgentProfile {
+    /// The name of the profile, shown in the assistant panel.
+    pub name: String,
+    /// The model to use in the profile's threads, instead of the default model.
+    #[serde(default)]
+    pub model: Option<LanguageModelSelection>,
+    /// The names of the tools the assistant may use, or `null` to allow every tool.
+    ///
+    /// Context server tools are enabled by `context_servers` instead.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// The IDs of the context servers whose tools the assistant may use, or `null` to
+    /// allow every context server.
+    #[serde(default)]
+    pub context_servers: Option<Vec<String>>,
+    /// The title of a prompt in the prompt library to use as the system prompt.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+impl AgentProfile {
+    /// Returns whether the tool is enabled in the profile, given the ID of the context server
+    /// that provides it, if any.
+    pub fn is_tool_enabled(&self, tool_name: &str, context_server_id: Option<&str>) -> bool {
+        match context_server_id {
+            Some(context_server_id) => self.context_servers.as_ref().map_or(true, |servers| {
+                servers.iter().any(|server| server == context_server_id)
+            }),
+            None => self
+                .tools
+                .as_ref()
+                .map_or(true, |tools| tools.iter().any(|tool| tool == tool_name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_tool_enabled() {
+        let profile = AgentProfile {
+            name: "Read-only".into(),
+            tools: Some(vec!["read-file".into()]),
+            context_servers: Some(vec!["docs".into()]),
+            ..Default::default()
+        };
+        assert!(profile.is_tool_enabled("read-file", None));
+        assert!(!profile.is_tool_enabled("edit-files", None));
+        assert!(profile.is_tool_enabled("search", Some("docs")));
+        assert!(!profile.is_tool_enabled("read-file", Some("github")));
+
+        let profile = AgentProfile {
+            name: "Write".into(),
+            ..Default::default()
+        };
+        assert!(profile.is_tool_enabled("edit-files", None));
+        assert!(profile.is_tool_enabled("search", Some("github")));
+    }
+}
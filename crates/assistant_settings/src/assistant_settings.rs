@@ -1,3 +1,6 @@
+mod agent_profile;
+mod token_usage_settings;
+
 use std::sync::Arc;
 
 use ::open_ai::Model as OpenAiModel;
@@ -5,6 +8,7 @@ use anthropic::Model as AnthropicModel;
 use deepseek::Model as DeepseekModel;
 use feature_flags::FeatureFlagAppExt;
 use gpui::{App, Pixels};
+use indexmap::IndexMap;
 use language_model::{CloudModel, LanguageModel};
 use lmstudio::Model as LmStudioModel;
 use ollama::Model as OllamaModel;
@@ -12,6 +16,9 @@ use schemars::{schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsSources};
 
+pub use crate::agent_profile::*;
+pub use crate::token_usage_settings::*;
+
 #[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AssistantDockPosition {
@@ -65,12 +72,26 @@ pub struct AssistantSettings {
     pub inline_alternatives: Vec<LanguageModelSelection>,
     pub using_outdated_settings_version: bool,
     pub enable_experimental_live_diffs: bool,
+    pub default_profile: String,
+    pub profiles: IndexMap<String, AgentProfile>,
 }
 
 impl AssistantSettings {
     pub fn are_live_diffs_enabled(&self, cx: &App) -> bool {
         cx.is_staff() || self.enable_experimental_live_diffs
     }
+
+    /// Returns the ID and the profile with the given ID, falling back to the default profile
+    /// when there's no such profile.
+    pub fn profile<'a>(
+        &'a self,
+        profile_id: Option<&'a str>,
+    ) -> Option<(&'a str, &'a AgentProfile)> {
+        profile_id
+            .and_then(|profile_id| self.profiles.get_key_value(profile_id))
+            .or_else(|| self.profiles.get_key_value(self.default_profile.as_str()))
+            .map(|(profile_id, profile)| (profile_id.as_str(), profile))
+    }
 }
 
 /// Assistant panel settings
@@ -164,6 +185,8 @@ impl AssistantSettingsContent {
                         }),
                     inline_alternatives: None,
                     enable_experimental_live_diffs: None,
+                    default_profile: None,
+                    profiles: None,
                 },
                 VersionedAssistantSettingsContent::V2(settings) => settings.clone(),
             },
@@ -184,6 +207,8 @@ impl AssistantSettingsContent {
                 }),
                 inline_alternatives: None,
                 enable_experimental_live_diffs: None,
+                default_profile: None,
+                profiles: None,
             },
         }
     }
@@ -312,6 +337,8 @@ impl Default for VersionedAssistantSettingsContent {
             default_model: None,
             inline_alternatives: None,
             enable_experimental_live_diffs: None,
+            default_profile: None,
+            profiles: None,
         })
     }
 }
@@ -346,6 +373,13 @@ pub struct AssistantSettingsContentV2 {
     ///
     /// Default: false
     enable_experimental_live_diffs: Option<bool>,
+    /// The ID of the profile that threads use until another profile is picked for them.
+    ///
+    /// Default: "write"
+    default_profile: Option<String>,
+    /// The profiles to pick from in the assistant panel, keyed by their IDs. Each picks the
+    /// model, tools, context servers, and system prompt of the threads that use it.
+    profiles: Option<IndexMap<String, AgentProfile>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -475,6 +509,10 @@ impl Settings for AssistantSettings {
                 &mut settings.enable_experimental_live_diffs,
                 value.enable_experimental_live_diffs,
             );
+            merge(&mut settings.default_profile, value.default_profile);
+            if let Some(profiles) = value.profiles {
+                settings.profiles.extend(profiles);
+            }
         }
 
         Ok(settings)
@@ -516,6 +554,11 @@ mod tests {
                     model: "claude-3-5-sonnet-latest".into(),
                 }
             );
+
+            let settings = AssistantSettings::get_global(cx);
+            assert_eq!(settings.profile(None).unwrap().0, "write");
+            assert_eq!(settings.profile(Some("read-only")).unwrap().0, "read-only");
+            assert_eq!(settings.profile(Some("deleted")).unwrap().0, "write");
         });
 
         cx.update(|cx| {
@@ -535,6 +578,8 @@ mod tests {
                             default_width: None,
                             default_height: None,
                             enable_experimental_live_diffs: None,
+                            default_profile: None,
+                            profiles: None,
                         }),
                     )
                 },
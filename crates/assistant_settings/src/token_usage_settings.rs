@@ -0,0 +1,126 @@
+use anyhow::Result;
+use gpui::App;
+use language_model::TokenUsage;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+/// The price of a model's tokens, in US dollars per million tokens.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ModelPrice {
+    /// The ID of the model's provider, such as `anthropic` or `openai`.
+    pub provider: String,
+    /// The ID of the model, such as `claude-3-5-sonnet-latest`.
+    pub model: String,
+    /// The price of input tokens.
+    pub input: f64,
+    /// The price of output tokens.
+    pub output: f64,
+    /// The price of input tokens written to the prompt cache.
+    ///
+    /// Default: the price of input tokens
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+    /// The price of input tokens read from the prompt cache.
+    ///
+    /// Default: the price of input tokens
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+}
+
+impl ModelPrice {
+    /// Returns the cost of the given tokens, in US dollars.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        const TOKENS_PER_PRICE: f64 = 1_000_000.;
+        let cache_write = self.cache_write.unwrap_or(self.input);
+        let cache_read = self.cache_read.unwrap_or(self.input);
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_creation_input_tokens as f64 * cache_write
+            + usage.cache_read_input_tokens as f64 * cache_read)
+            / TOKENS_PER_PRICE
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TokenUsageSettings {
+    pub prices: Vec<ModelPrice>,
+    pub monthly_budget: Option<f64>,
+    pub budget_warning_threshold: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TokenUsageSettingsContent {
+    /// The prices of the models, used to estimate what threads cost.
+    ///
+    /// Default: []
+    pub prices: Option<Vec<ModelPrice>>,
+    /// How many US dollars to spend on language models each month, across all threads.
+    /// The assistant warns when the estimated cost of the month's tokens approaches it.
+    ///
+    /// Default: null
+    pub monthly_budget: Option<f64>,
+    /// The fraction of the monthly budget at which to start warning.
+    ///
+    /// Default: 0.8
+    pub budget_warning_threshold: Option<f64>,
+}
+
+impl TokenUsageSettings {
+    pub fn price(&self, provider: &str, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .find(|price| price.provider == provider && price.model == model)
+    }
+
+    /// Returns the cost of the tokens a model used, or `None` when the model has no price.
+    pub fn cost(&self, provider: &str, model: &str, usage: &TokenUsage) -> Option<f64> {
+        self.price(provider, model).map(|price| price.cost(usage))
+    }
+}
+
+impl Settings for TokenUsageSettings {
+    const KEY: Option<&'static str> = Some("token_usage");
+
+    type FileContent = TokenUsageSettingsContent;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> Result<Self> {
+        let mut settings = Self::default();
+        for value in sources.defaults_and_customizations() {
+            if let Some(prices) = value.prices.clone() {
+                settings.prices = prices;
+            }
+            if value.monthly_budget.is_some() {
+                settings.monthly_budget = value.monthly_budget;
+            }
+            if let Some(threshold) = value.budget_warning_threshold {
+                settings.budget_warning_threshold = threshold.clamp(0., 1.);
+            }
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_price_cost() {
+        let price = ModelPrice {
+            provider: "anthropic".into(),
+            model: "claude-3-5-sonnet-latest".into(),
+            input: 3.,
+            output: 15.,
+            cache_write: Some(3.75),
+            cache_read: None,
+        };
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_creation_input_tokens: 200_000,
+            cache_read_input_tokens: 1_000_000,
+        };
+        assert_eq!(price.cost(&usage), 3. + 1.5 + 0.75 + 3.);
+    }
+}
@@ -10,6 +10,7 @@ mod file_command;
 mod now_command;
 mod project_command;
 mod prompt_command;
+mod repo_map_command;
 mod search_command;
 mod selection_command;
 mod streaming_example_command;
@@ -33,6 +34,7 @@ pub use crate::file_command::*;
 pub use crate::now_command::*;
 pub use crate::project_command::*;
 pub use crate::prompt_command::*;
+pub use crate::repo_map_command::*;
 pub use crate::search_command::*;
 pub use crate::selection_command::*;
 pub use crate::streaming_example_command::*;
@@ -10,8 +10,8 @@ use assistant_slash_command::{
 };
 use gpui::{App, BackgroundExecutor, Entity, Task, WeakEntity};
 use indexed_docs::{
-    DocsDotRsProvider, IndexedDocsRegistry, IndexedDocsStore, LocalRustdocProvider, PackageName,
-    ProviderId,
+    DocsDotRsProvider, GoDocsProvider, IndexedDocsRegistry, IndexedDocsStore, LocalRustdocProvider,
+    MdBookProvider, PackageName, ProviderId, PythonDocsProvider, TypeScriptDeclarationsProvider,
 };
 use language::{BufferSnapshot, LspAdapterDelegate};
 use project::{Project, ProjectPath};
@@ -19,6 +19,12 @@ use ui::prelude::*;
 use util::{maybe, ResultExt};
 use workspace::Workspace;
 
+/// The argument that starts a full-text search, in place of a provider.
+const FULL_TEXT_SEARCH_ARGUMENT: &str = "search";
+
+/// The most matches whose docs a full-text search inserts.
+const FULL_TEXT_SEARCH_LIMIT: usize = 5;
+
 pub struct DocsSlashCommand;
 
 impl DocsSlashCommand {
@@ -93,6 +99,76 @@ impl DocsSlashCommand {
         }
     }
 
+    /// Ensures that the indexed doc providers for the project's other ecosystems are
+    /// registered, based on the manifests at the root of the project's first worktree.
+    fn ensure_local_doc_providers_are_registered(
+        &self,
+        workspace: Option<WeakEntity<Workspace>>,
+        cx: &mut App,
+    ) {
+        let Some(workspace) = workspace.and_then(|workspace| workspace.upgrade()) else {
+            return;
+        };
+        let project = workspace.read(cx).project().clone();
+        let Some(worktree) = project.read(cx).worktrees(cx).next() else {
+            return;
+        };
+        let worktree = worktree.read(cx);
+        let project_root = worktree.abs_path().to_path_buf();
+        let has_any_entry = |paths: &[&str]| {
+            paths
+                .iter()
+                .any(|path| worktree.entry_for_path(path).is_some())
+        };
+        let fs = project.read(cx).fs().clone();
+
+        let indexed_docs_registry = IndexedDocsRegistry::global(cx);
+        let is_registered = |provider_id: ProviderId| {
+            indexed_docs_registry
+                .get_provider_store(provider_id)
+                .is_some()
+        };
+
+        if !is_registered(PythonDocsProvider::id())
+            && has_any_entry(&[
+                "pyproject.toml",
+                "setup.py",
+                "setup.cfg",
+                "requirements.txt",
+            ])
+        {
+            indexed_docs_registry.register_provider(Box::new(PythonDocsProvider::new(
+                fs.clone(),
+                project_root.clone(),
+            )));
+        }
+
+        if !is_registered(TypeScriptDeclarationsProvider::id()) && has_any_entry(&["package.json"])
+        {
+            indexed_docs_registry.register_provider(Box::new(TypeScriptDeclarationsProvider::new(
+                fs.clone(),
+                project_root.clone(),
+            )));
+        }
+
+        if !is_registered(GoDocsProvider::id()) && has_any_entry(&["go.mod"]) {
+            indexed_docs_registry
+                .register_provider(Box::new(GoDocsProvider::new(project_root.clone())));
+        }
+
+        if !is_registered(MdBookProvider::id())
+            && has_any_entry(&[
+                "book.toml",
+                "docs/book.toml",
+                "doc/book.toml",
+                "book/book.toml",
+            ])
+        {
+            indexed_docs_registry
+                .register_provider(Box::new(MdBookProvider::new(fs, project_root)));
+        }
+    }
+
     /// Runs just-in-time indexing for a given package, in case the slash command
     /// is run without any entries existing in the index.
     fn run_just_in_time_indexing(
@@ -168,10 +244,12 @@ impl SlashCommand for DocsSlashCommand {
         _: &mut Window,
         cx: &mut App,
     ) -> Task<Result<Vec<ArgumentCompletion>>> {
-        self.ensure_rust_doc_providers_are_registered(workspace, cx);
+        self.ensure_rust_doc_providers_are_registered(workspace.clone(), cx);
+        self.ensure_local_doc_providers_are_registered(workspace, cx);
 
         let indexed_docs_registry = IndexedDocsRegistry::global(cx);
-        let args = DocsSlashCommandArgs::parse(arguments);
+        let arguments = arguments.to_vec();
+        let args = DocsSlashCommandArgs::parse(&arguments);
         let store = args
             .provider()
             .ok_or_else(|| anyhow!("no docs provider specified"))
@@ -201,7 +279,7 @@ impl SlashCommand for DocsSlashCommand {
                         }]);
                     }
 
-                    Ok(providers
+                    let mut items = providers
                         .into_iter()
                         .map(|provider| ArgumentCompletion {
                             label: provider.to_string().into(),
@@ -209,7 +287,33 @@ impl SlashCommand for DocsSlashCommand {
                             after_completion: false.into(),
                             replace_previous_arguments: false,
                         })
-                        .collect())
+                        .collect::<Vec<_>>();
+                    items.push(ArgumentCompletion {
+                        label: "search (all indexed docs)".into(),
+                        new_text: FULL_TEXT_SEARCH_ARGUMENT.to_string(),
+                        after_completion: false.into(),
+                        replace_previous_arguments: false,
+                    });
+                    Ok(items)
+                }
+                DocsSlashCommandArgs::SearchFullText { query } => {
+                    // Leave the arguments as they are, since the query is free text.
+                    let last_argument = arguments.last().cloned().unwrap_or_default();
+                    if query.is_empty() {
+                        return Ok(vec![ArgumentCompletion {
+                            label: "Enter a search query.".into(),
+                            new_text: last_argument,
+                            after_completion: false.into(),
+                            replace_previous_arguments: false,
+                        }]);
+                    }
+
+                    Ok(vec![ArgumentCompletion {
+                        label: format!("Search indexed docs for \"{query}\"").into(),
+                        new_text: last_argument,
+                        after_completion: assistant_slash_command::AfterCompletion::Run,
+                        replace_previous_arguments: false,
+                    }])
                 }
                 DocsSlashCommandArgs::SearchPackageDocs {
                     provider,
@@ -283,6 +387,10 @@ impl SlashCommand for DocsSlashCommand {
         };
 
         let args = DocsSlashCommandArgs::parse(arguments);
+        if let DocsSlashCommandArgs::SearchFullText { query } = args {
+            return Self::run_full_text_search(query, cx);
+        }
+
         let executor = cx.background_executor().clone();
         let task = cx.background_spawn({
             let store = args
@@ -291,7 +399,10 @@ impl SlashCommand for DocsSlashCommand {
                 .and_then(|provider| IndexedDocsStore::try_global(provider, cx));
             async move {
                 let (provider, key) = match args.clone() {
-                    DocsSlashCommandArgs::NoProvider => bail!("no docs provider specified"),
+                    DocsSlashCommandArgs::NoProvider
+                    | DocsSlashCommandArgs::SearchFullText { .. } => {
+                        bail!("no docs provider specified")
+                    }
                     DocsSlashCommandArgs::SearchPackageDocs {
                         provider, package, ..
                     } => (provider, package),
@@ -364,6 +475,53 @@ impl SlashCommand for DocsSlashCommand {
     }
 }
 
+impl DocsSlashCommand {
+    /// Inserts the docs whose contents best match the query, across every provider.
+    fn run_full_text_search(query: String, cx: &mut App) -> Task<SlashCommandResult> {
+        if query.trim().is_empty() {
+            return Task::ready(Err(anyhow!("no search query provided")));
+        }
+
+        let indexed_docs_registry = IndexedDocsRegistry::global(cx);
+        let search = indexed_docs_registry.search_full_text(query.clone(), FULL_TEXT_SEARCH_LIMIT);
+        cx.background_spawn(async move {
+            let matches = search.await;
+            if matches.is_empty() {
+                bail!("no indexed docs found matching \"{query}\"");
+            }
+
+            let mut text = String::new();
+            let mut sections = Vec::new();
+            for (provider, mat) in matches {
+                let Some(store) = indexed_docs_registry.get_provider_store(provider.clone()) else {
+                    continue;
+                };
+                let Some(docs) = store.load(mat.key.clone()).await.log_err() else {
+                    continue;
+                };
+
+                let prev_len = text.len();
+                text.push_str(&docs.0);
+                text.push_str("\n");
+                sections.push(SlashCommandOutputSection {
+                    range: prev_len..text.len(),
+                    icon: IconName::FileDoc,
+                    label: format!("docs ({provider}): {}", mat.key).into(),
+                    metadata: None,
+                });
+                text.push_str("\n");
+            }
+
+            Ok(SlashCommandOutput {
+                text,
+                sections,
+                run_commands_in_text: false,
+            }
+            .to_event_stream())
+        })
+    }
+}
+
 fn is_item_path_delimiter(char: char) -> bool {
     !char.is_alphanumeric() && char != '-' && char != '_'
 }
@@ -371,6 +529,10 @@ fn is_item_path_delimiter(char: char) -> bool {
 #[derive(Debug, PartialEq, Clone)]
 pub enum DocsSlashCommandArgs {
     NoProvider,
+    /// Searches the contents of the docs indexed by every provider.
+    SearchFullText {
+        query: String,
+    },
     SearchPackageDocs {
         provider: ProviderId,
         package: String,
@@ -392,12 +554,28 @@ impl DocsSlashCommandArgs {
         else {
             return Self::NoProvider;
         };
+        if provider == FULL_TEXT_SEARCH_ARGUMENT {
+            return Self::SearchFullText {
+                query: arguments[1..].join(" ").trim().to_string(),
+            };
+        }
         let provider = ProviderId(provider.into());
         let Some(argument) = arguments.get(1) else {
             return Self::NoProvider;
         };
 
-        if let Some((package, rest)) = argument.split_once(is_item_path_delimiter) {
+        // Go import paths contain delimiters (e.g., `golang.org/x/net/html`), so only a
+        // delimiter after the last `/` separates the package from its items.
+        let package_start = if provider == GoDocsProvider::id() {
+            argument.rfind('/').map_or(0, |ix| ix + 1)
+        } else {
+            0
+        };
+
+        if let Some((package, rest)) = argument[package_start..]
+            .split_once(is_item_path_delimiter)
+            .map(|(package, rest)| (&argument[..package_start + package.len()], rest))
+        {
             if rest.trim().is_empty() {
                 Self::SearchPackageDocs {
                     provider,
@@ -422,7 +600,7 @@ impl DocsSlashCommandArgs {
 
     pub fn provider(&self) -> Option<ProviderId> {
         match self {
-            Self::NoProvider => None,
+            Self::NoProvider | Self::SearchFullText { .. } => None,
             Self::SearchPackageDocs { provider, .. } | Self::SearchItemDocs { provider, .. } => {
                 Some(provider.clone())
             }
@@ -431,7 +609,7 @@ impl DocsSlashCommandArgs {
 
     pub fn package(&self) -> Option<PackageName> {
         match self {
-            Self::NoProvider => None,
+            Self::NoProvider | Self::SearchFullText { .. } => None,
             Self::SearchPackageDocs { package, .. } | Self::SearchItemDocs { package, .. } => {
                 Some(package.as_str().into())
             }
@@ -445,6 +623,10 @@ fn package_term(provider: &ProviderId) -> &'static str {
         return "crate";
     }
 
+    if provider == &MdBookProvider::id() {
+        return "book";
+    }
+
     "package"
 }
 
@@ -537,5 +719,52 @@ mod tests {
                 item_path: "gleam_stdlib/gleam/int".into()
             }
         );
+
+        // Go import paths contain delimiters, so only the last path segment is split.
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["go".to_string(), "golang.org/x/net/html".to_string()]),
+            DocsSlashCommandArgs::SearchPackageDocs {
+                provider: ProviderId("go".into()),
+                package: "golang.org/x/net/html".into(),
+                index: false
+            }
+        );
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["go".to_string(), "net/http.".to_string()]),
+            DocsSlashCommandArgs::SearchPackageDocs {
+                provider: ProviderId("go".into()),
+                package: "net/http".into(),
+                index: true
+            }
+        );
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["go".to_string(), "net/http.Client.Do".to_string()]),
+            DocsSlashCommandArgs::SearchItemDocs {
+                provider: ProviderId("go".into()),
+                package: "net/http".into(),
+                item_path: "net/http.Client.Do".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_full_text_search_args() {
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["search".to_string()]),
+            DocsSlashCommandArgs::SearchFullText {
+                query: String::new()
+            }
+        );
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&[
+                "search".to_string(),
+                "spawn".to_string(),
+                "blocking".to_string(),
+                "".to_string()
+            ]),
+            DocsSlashCommandArgs::SearchFullText {
+                query: "spawn blocking".into()
+            }
+        );
     }
 }
@@ -5,7 +5,7 @@ use assistant_slash_command::{
 };
 use gpui::{Task, WeakEntity};
 use language::{BufferSnapshot, LspAdapterDelegate};
-use prompt_library::PromptStore;
+use prompt_library::{fill_in_prompt, PromptStore};
 use std::sync::{atomic::AtomicBool, Arc};
 use ui::prelude::*;
 use workspace::Workspace;
@@ -65,9 +65,9 @@ impl SlashCommand for PromptSlashCommand {
         arguments: &[String],
         _context_slash_command_output_sections: &[SlashCommandOutputSection<language::Anchor>],
         _context_buffer: BufferSnapshot,
-        _workspace: WeakEntity<Workspace>,
+        workspace: WeakEntity<Workspace>,
         _delegate: Option<Arc<dyn LspAdapterDelegate>>,
-        _: &mut Window,
+        window: &mut Window,
         cx: &mut App,
     ) -> Task<SlashCommandResult> {
         let title = arguments.to_owned().join(" ");
@@ -77,19 +77,19 @@ impl SlashCommand for PromptSlashCommand {
 
         let store = PromptStore::global(cx);
         let title = SharedString::from(title.clone());
-        let prompt = cx.background_spawn({
+        let template = cx.background_spawn({
             let title = title.clone();
             async move {
                 let store = store.await?;
                 let prompt_id = store
                     .id_for_title(&title)
                     .with_context(|| format!("no prompt found with title {:?}", title))?;
-                let body = store.load(prompt_id).await?;
-                anyhow::Ok(body)
+                store.load_template(prompt_id).await
             }
         });
-        cx.foreground_executor().spawn(async move {
-            let mut prompt = prompt.await?;
+        window.spawn(cx, |mut cx| async move {
+            let template = template.await?;
+            let mut prompt = fill_in_prompt(workspace, title.clone(), template, &mut cx).await?;
 
             if prompt.starts_with('/') {
                 // Prevent an edge case where the inserted prompt starts with a slash command (that leads to funky rendering).
@@ -0,0 +1,108 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _, Result};
+use assistant_slash_command::{
+    ArgumentCompletion, SlashCommand, SlashCommandOutput, SlashCommandOutputSection,
+    SlashCommandResult,
+};
+use gpui::{Task, WeakEntity};
+use language::{BufferSnapshot, CodeLabel, LspAdapterDelegate};
+use language_model::LanguageModelRegistry;
+use repo_map::{build_repo_map, DEFAULT_TOKEN_BUDGET};
+use ui::{prelude::*, App, Window};
+use workspace::Workspace;
+
+use crate::create_label_for_command;
+
+pub struct RepoMapSlashCommand;
+
+impl SlashCommand for RepoMapSlashCommand {
+    fn name(&self) -> String {
+        "repo-map".into()
+    }
+
+    fn label(&self, cx: &App) -> CodeLabel {
+        create_label_for_command("repo-map", &["[token budget]"], cx)
+    }
+
+    fn description(&self) -> String {
+        "Insert a map of the project's symbols".into()
+    }
+
+    fn icon(&self) -> IconName {
+        IconName::FileTree
+    }
+
+    fn menu_text(&self) -> String {
+        self.description()
+    }
+
+    fn requires_argument(&self) -> bool {
+        false
+    }
+
+    fn complete_argument(
+        self: Arc<Self>,
+        _arguments: &[String],
+        _cancel: Arc<AtomicBool>,
+        _workspace: Option<WeakEntity<Workspace>>,
+        _window: &mut Window,
+        _cx: &mut App,
+    ) -> Task<Result<Vec<ArgumentCompletion>>> {
+        Task::ready(Ok(Vec::new()))
+    }
+
+    fn run(
+        self: Arc<Self>,
+        arguments: &[String],
+        _context_slash_command_output_sections: &[SlashCommandOutputSection<language::Anchor>],
+        _context_buffer: BufferSnapshot,
+        workspace: WeakEntity<Workspace>,
+        _delegate: Option<Arc<dyn LspAdapterDelegate>>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<SlashCommandResult> {
+        let token_budget = match arguments.first() {
+            Some(argument) => match argument.parse::<usize>() {
+                Ok(token_budget) => token_budget,
+                Err(_) => {
+                    return Task::ready(Err(anyhow!("invalid token budget: {argument}")));
+                }
+            },
+            None => DEFAULT_TOKEN_BUDGET,
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+
+        let model = LanguageModelRegistry::read_global(cx).active_model();
+        let repo_map = build_repo_map(&project, model, token_budget, cx);
+        cx.foreground_executor().spawn(async move {
+            let repo_map = repo_map.await.context("failed to build a repo map")?;
+            if repo_map.included_file_count == 0 {
+                return Err(anyhow!("no symbols fit in a budget of {token_budget} tokens"));
+            }
+
+            let text = format!(
+                "Here is a map of the symbols in the project's {} most referenced files (of {}):\n\n{}",
+                repo_map.included_file_count, repo_map.file_count, repo_map.text
+            );
+            let range = 0..text.len();
+            Ok(SlashCommandOutput {
+                text,
+                sections: vec![SlashCommandOutputSection {
+                    range,
+                    icon: IconName::FileTree,
+                    label: "Repo Map".into(),
+                    metadata: None,
+                }],
+                run_commands_in_text: false,
+            }
+            .to_event_stream())
+        })
+    }
+}
@@ -83,14 +83,19 @@ impl SlashCommand for TerminalSlashCommand {
             .and_then(|s| s.parse::<usize>().ok())
             .unwrap_or(DEFAULT_CONTEXT_LINES);
 
-        let lines = active_terminal
-            .read(cx)
-            .entity()
-            .read(cx)
-            .last_n_non_empty_lines(line_count);
+        let terminal = active_terminal.read(cx).entity().read(cx);
+        // When the shell reported that the last command failed, its output is the most relevant context.
+        let (heading, label, lines) = match terminal.last_failed_command_lines(line_count) {
+            Some(lines) => ("Failed terminal command:\n", "Failed Command", lines),
+            None => (
+                "Terminal output:\n",
+                "Terminal",
+                terminal.last_n_non_empty_lines(line_count),
+            ),
+        };
 
         let mut text = String::new();
-        text.push_str("Terminal output:\n");
+        text.push_str(heading);
         text.push_str(&lines.join("\n"));
         let range = 0..text.len();
 
@@ -99,7 +104,7 @@ impl SlashCommand for TerminalSlashCommand {
             sections: vec![SlashCommandOutputSection {
                 range,
                 icon: IconName::Terminal,
-                label: "Terminal".into(),
+                label: label.into(),
                 metadata: None,
             }],
             run_commands_in_text: false,
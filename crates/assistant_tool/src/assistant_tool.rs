@@ -1,16 +1,21 @@
+mod tool_permissions;
 mod tool_registry;
 mod tool_working_set;
 
 use std::sync::Arc;
 
 use anyhow::Result;
+use futures::channel::mpsc;
 use gpui::{App, Task, WeakEntity, Window};
+use settings::Settings as _;
 use workspace::Workspace;
 
+pub use crate::tool_permissions::*;
 pub use crate::tool_registry::*;
 pub use crate::tool_working_set::*;
 
 pub fn init(cx: &mut App) {
+    ToolPermissionsSettings::register(cx);
     ToolRegistry::default_global(cx);
 }
 
@@ -27,6 +32,11 @@ pub trait Tool: 'static + Send + Sync {
         serde_json::Value::Object(serde_json::Map::default())
     }
 
+    /// Returns the ID of the context server that provides the tool, if any.
+    fn context_server_id(&self) -> Option<Arc<str>> {
+        None
+    }
+
     /// Runs the tool with the provided input.
     fn run(
         self: Arc<Self>,
@@ -35,4 +45,19 @@ pub trait Tool: 'static + Send + Sync {
         window: &mut Window,
         cx: &mut App,
     ) -> Task<Result<String>>;
+
+    /// Runs the tool, sending its output through `output` as it is produced. The task still
+    /// resolves to the complete output.
+    ///
+    /// Only tools whose output arrives over time need to implement this.
+    fn run_streaming(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _output: mpsc::UnboundedSender<String>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        self.run(input, workspace, window, cx)
+    }
 }
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use collections::HashMap;
+use gpui::App;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+/// Whether a tool can be used without asking the user first.
+///
+/// Permissions are ordered from the least to the most strict.
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    /// Use the tool without asking.
+    Allow,
+    /// Ask the user every time the tool is used.
+    #[default]
+    Ask,
+    /// Never use the tool.
+    Deny,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ToolPermissionsSettings {
+    pub default: ToolPermission,
+    pub tools: HashMap<String, ToolPermission>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ToolPermissionsSettingsContent {
+    /// The permission of the tools not listed in `tools`.
+    ///
+    /// Default: ask
+    pub default: Option<ToolPermission>,
+    /// The permission of each tool, by name.
+    ///
+    /// Default: tools that only read the project are allowed
+    pub tools: Option<HashMap<String, ToolPermission>>,
+}
+
+impl ToolPermissionsSettings {
+    pub fn permission(&self, tool_name: &str) -> ToolPermission {
+        self.tools.get(tool_name).copied().unwrap_or(self.default)
+    }
+}
+
+impl Settings for ToolPermissionsSettings {
+    const KEY: Option<&'static str> = Some("tool_permissions");
+
+    type FileContent = ToolPermissionsSettingsContent;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> Result<Self> {
+        let mut settings: Self = SettingsSources::json_merge_with(
+            [sources.default]
+                .into_iter()
+                .chain(sources.extensions)
+                .chain(sources.user)
+                .chain(sources.release_channel)
+                .chain(sources.server),
+        )?;
+
+        // Project settings come along with the project's files, so they can make tools
+        // stricter but never let them run without asking.
+        for project in sources.project {
+            if let Some(default) = project.default {
+                settings.default = settings.default.max(default);
+                for permission in settings.tools.values_mut() {
+                    *permission = (*permission).max(default);
+                }
+            }
+            for (tool_name, permission) in project.tools.iter().flatten() {
+                let stricter = settings.permission(tool_name).max(*permission);
+                settings.tools.insert(tool_name.clone(), stricter);
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(
+        default: Option<ToolPermission>,
+        tools: &[(&str, ToolPermission)],
+    ) -> ToolPermissionsSettingsContent {
+        ToolPermissionsSettingsContent {
+            default,
+            tools: Some(
+                tools
+                    .iter()
+                    .map(|(name, permission)| (name.to_string(), *permission))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[gpui::test]
+    fn test_project_settings_only_restrict_permissions(cx: &mut App) {
+        let default = content(
+            Some(ToolPermission::Ask),
+            &[("read-file", ToolPermission::Allow)],
+        );
+        let user = content(None, &[("edit-file", ToolPermission::Allow)]);
+        let project = content(
+            None,
+            &[
+                ("read-file", ToolPermission::Deny),
+                ("now", ToolPermission::Allow),
+            ],
+        );
+
+        let settings = ToolPermissionsSettings::load(
+            SettingsSources {
+                default: &default,
+                extensions: None,
+                user: Some(&user),
+                release_channel: None,
+                server: None,
+                project: &[],
+            },
+            cx,
+        )
+        .unwrap();
+        assert_eq!(settings.permission("read-file"), ToolPermission::Allow);
+        assert_eq!(settings.permission("edit-file"), ToolPermission::Allow);
+        assert_eq!(settings.permission("now"), ToolPermission::Ask);
+
+        let settings = ToolPermissionsSettings::load(
+            SettingsSources {
+                default: &default,
+                extensions: None,
+                user: Some(&user),
+                release_channel: None,
+                server: None,
+                project: &[&project],
+            },
+            cx,
+        )
+        .unwrap();
+        assert_eq!(settings.permission("read-file"), ToolPermission::Deny);
+        assert_eq!(settings.permission("edit-file"), ToolPermission::Allow);
+        assert_eq!(settings.permission("now"), ToolPermission::Ask);
+    }
+}
@@ -1,10 +1,12 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use collections::HashMap;
 use gpui::App;
 use parking_lot::Mutex;
+use settings::{Settings as _, SettingsLocation, WorktreeId};
 
-use crate::{Tool, ToolRegistry};
+use crate::{Tool, ToolPermission, ToolPermissionsSettings, ToolRegistry};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub struct ToolId(usize);
@@ -45,6 +47,27 @@ impl ToolWorkingSet {
         tools
     }
 
+    /// Returns whether the tool can be used without asking, according to the settings of the
+    /// given worktrees. Tools can reach all of them, so the strictest permission wins.
+    pub fn permission(
+        &self,
+        tool_name: &str,
+        worktree_ids: impl IntoIterator<Item = WorktreeId>,
+        cx: &App,
+    ) -> ToolPermission {
+        worktree_ids
+            .into_iter()
+            .map(|worktree_id| {
+                let location = SettingsLocation {
+                    worktree_id,
+                    path: Path::new(""),
+                };
+                ToolPermissionsSettings::get(Some(location), cx).permission(tool_name)
+            })
+            .max()
+            .unwrap_or_else(|| ToolPermissionsSettings::get_global(cx).permission(tool_name))
+    }
+
     pub fn insert(&self, command: Arc<dyn Tool>) -> ToolId {
         let mut state = self.state.lock();
         let command_id = state.next_tool_id;
@@ -75,3 +98,58 @@ impl WorkingSetState {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use settings::{LocalSettingsKind, SettingsStore};
+
+    use super::*;
+
+    #[gpui::test]
+    fn test_strictest_permission_across_worktrees(cx: &mut App) {
+        let store = SettingsStore::test(cx);
+        cx.set_global(store);
+        ToolPermissionsSettings::register(cx);
+
+        let first = WorktreeId::from_usize(1);
+        let second = WorktreeId::from_usize(2);
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store
+                .set_local_settings(
+                    first,
+                    Path::new("").into(),
+                    LocalSettingsKind::Settings,
+                    Some(r#"{ "tool_permissions": { "tools": { "edit-file": "ask" } } }"#),
+                    cx,
+                )
+                .unwrap();
+            store
+                .set_local_settings(
+                    second,
+                    Path::new("").into(),
+                    LocalSettingsKind::Settings,
+                    Some(r#"{ "tool_permissions": { "tools": { "edit-file": "deny" } } }"#),
+                    cx,
+                )
+                .unwrap();
+        });
+
+        let tools = ToolWorkingSet::default();
+        assert_eq!(
+            tools.permission("edit-file", [first], cx),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            tools.permission("edit-file", [first, second], cx),
+            ToolPermission::Deny
+        );
+        assert_eq!(
+            tools.permission("edit-file", [second, first], cx),
+            ToolPermission::Deny
+        );
+        assert_eq!(
+            tools.permission("edit-file", [], cx),
+            ToolPermissionsSettings::get_global(cx).permission("edit-file")
+        );
+    }
+}
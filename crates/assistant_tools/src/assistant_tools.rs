@@ -1,13 +1,83 @@
+mod diagnostics_tool;
+mod edit_file_tool;
+mod list_directory_tool;
 mod now_tool;
+mod read_file_tool;
+mod regex_search_tool;
+mod terminal_tool;
+mod terminal_tool_settings;
 
 use assistant_tool::ToolRegistry;
 use gpui::App;
+use settings::Settings as _;
 
+use crate::diagnostics_tool::DiagnosticsTool;
+use crate::edit_file_tool::EditFileTool;
+use crate::list_directory_tool::ListDirectoryTool;
 use crate::now_tool::NowTool;
+use crate::read_file_tool::ReadFileTool;
+use crate::regex_search_tool::RegexSearchTool;
+use crate::terminal_tool::TerminalTool;
+
+pub use crate::terminal_tool_settings::*;
 
 pub fn init(cx: &mut App) {
     assistant_tool::init(cx);
+    TerminalToolSettings::register(cx);
 
     let registry = ToolRegistry::global(cx);
     registry.register_tool(NowTool);
+    registry.register_tool(ReadFileTool);
+    registry.register_tool(EditFileTool);
+    registry.register_tool(RegexSearchTool);
+    registry.register_tool(ListDirectoryTool);
+    registry.register_tool(DiagnosticsTool);
+    registry.register_tool(TerminalTool);
+}
+
+#[cfg(test)]
+mod test_support {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use assistant_tool::Tool;
+    use gpui::{Entity, TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use settings::SettingsStore;
+    use workspace::Workspace;
+
+    /// Opens a workspace on a fake file system with the given files in `/root`.
+    pub(crate) async fn test_workspace(
+        files: serde_json::Value,
+        cx: &mut TestAppContext,
+    ) -> (Entity<Workspace>, VisualTestContext) {
+        cx.update(|cx| {
+            let settings = SettingsStore::test(cx);
+            cx.set_global(settings);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            client::init_settings(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+        });
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(util::path!("/root"), files).await;
+        let project = Project::test(fs, [util::path!("/root").as_ref()], cx).await;
+        let window = cx.add_window(|window, cx| Workspace::test_new(project, window, cx));
+        let workspace = window.root(cx).unwrap();
+        (workspace, VisualTestContext::from_window(*window, cx))
+    }
+
+    pub(crate) async fn run_tool(
+        tool: impl Tool,
+        input: serde_json::Value,
+        workspace: &Entity<Workspace>,
+        cx: &mut VisualTestContext,
+    ) -> Result<String> {
+        let tool = Arc::new(tool);
+        let workspace = workspace.downgrade();
+        cx.update(|window, cx| tool.run(input, workspace, window, cx))
+            .await
+    }
 }
@@ -0,0 +1,221 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use collections::BTreeMap;
+use gpui::{App, Task, WeakEntity, Window};
+use language::{DiagnosticSeverity, ToPoint as _};
+use project::DiagnosticSummary;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DiagnosticsToolInput {
+    /// The relative path of a file to get the errors and warnings of.
+    ///
+    /// This path should never be absolute, and the first component
+    /// of the path should always be a top-level directory in a project.
+    /// Leave it out to get the number of errors and warnings in every file
+    /// of the project that has any.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+pub struct DiagnosticsTool;
+
+impl Tool for DiagnosticsTool {
+    fn name(&self) -> String {
+        "diagnostics".into()
+    }
+
+    fn description(&self) -> String {
+        "Returns the errors and warnings reported by language servers for a file in the project, or how many there are in each file of the project. Use it to check whether edits introduced any errors.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(DiagnosticsToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: DiagnosticsToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+
+        let Some(path) = input.path else {
+            // Language servers report their diagnostics separately, so add them up by path.
+            let mut summaries = BTreeMap::<PathBuf, DiagnosticSummary>::default();
+            for (project_path, _, summary) in project.read(cx).diagnostic_summaries(false, cx) {
+                let Some(worktree) = project
+                    .read(cx)
+                    .worktree_for_id(project_path.worktree_id, cx)
+                else {
+                    continue;
+                };
+                let path = Path::new(worktree.read(cx).root_name()).join(&project_path.path);
+                let total = summaries.entry(path).or_default();
+                total.error_count += summary.error_count;
+                total.warning_count += summary.warning_count;
+            }
+
+            let mut output = String::new();
+            for (path, summary) in summaries {
+                writeln!(
+                    output,
+                    "{}: {} error(s), {} warning(s)",
+                    path.display(),
+                    summary.error_count,
+                    summary.warning_count
+                )
+                .ok();
+            }
+            if output.is_empty() {
+                output = "There are no errors or warnings in the project.".into();
+            }
+            return Task::ready(Ok(output));
+        };
+
+        let Some(project_path) = project.read(cx).find_project_path(&path, cx) else {
+            return Task::ready(Err(anyhow!("Path {} not found in project", path.display())));
+        };
+        cx.spawn(|mut cx| async move {
+            let buffer = project
+                .update(&mut cx, |project, cx| project.open_buffer(project_path, cx))?
+                .await?;
+            let snapshot = buffer.read_with(&cx, |buffer, _| buffer.snapshot())?;
+
+            let mut output = String::new();
+            for (_, group) in snapshot.diagnostic_groups(None) {
+                let entry = &group.entries[group.primary_ix];
+                let severity = match entry.diagnostic.severity {
+                    DiagnosticSeverity::ERROR => "error",
+                    DiagnosticSeverity::WARNING => "warning",
+                    _ => continue,
+                };
+                let row = entry.range.start.to_point(&snapshot).row;
+                writeln!(
+                    output,
+                    "{severity} at line {}: {}",
+                    row + 1,
+                    entry.diagnostic.message
+                )?;
+            }
+            if output.is_empty() {
+                output = format!("There are no errors or warnings in {}.", path.display());
+            }
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_tool, test_workspace};
+    use gpui::TestAppContext;
+    use language::{Diagnostic, DiagnosticEntry, LanguageServerId, PointUtf16, Unclipped};
+    use serde_json::json;
+
+    fn diagnostic(
+        row: u32,
+        severity: DiagnosticSeverity,
+        group_id: usize,
+        message: &str,
+    ) -> DiagnosticEntry<Unclipped<PointUtf16>> {
+        DiagnosticEntry {
+            range: Unclipped(PointUtf16::new(row, 0))..Unclipped(PointUtf16::new(row, 1)),
+            diagnostic: Diagnostic {
+                message: message.to_string(),
+                severity,
+                group_id,
+                is_primary: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[gpui::test]
+    async fn test_diagnostics_output(cx: &mut TestAppContext) {
+        let (workspace, mut cx) = test_workspace(
+            json!({
+                "main.rs": "fn main() {\n    a();\n    b();\n}\n",
+                "lib.rs": "",
+            }),
+            cx,
+        )
+        .await;
+        let cx = &mut cx;
+
+        assert_eq!(
+            run_tool(DiagnosticsTool, json!({}), &workspace, cx)
+                .await
+                .unwrap(),
+            "There are no errors or warnings in the project."
+        );
+
+        let project = workspace.read_with(cx, |workspace, _| workspace.project().clone());
+        let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+        lsp_store.update(cx, |lsp_store, cx| {
+            lsp_store
+                .update_diagnostic_entries(
+                    LanguageServerId(0),
+                    PathBuf::from(util::path!("/root/main.rs")),
+                    None,
+                    vec![
+                        diagnostic(1, DiagnosticSeverity::ERROR, 0, "cannot find function `a`"),
+                        diagnostic(2, DiagnosticSeverity::WARNING, 1, "unused result"),
+                        diagnostic(3, DiagnosticSeverity::HINT, 2, "a hint"),
+                    ],
+                    cx,
+                )
+                .unwrap();
+        });
+        cx.run_until_parked();
+
+        assert_eq!(
+            run_tool(DiagnosticsTool, json!({}), &workspace, cx)
+                .await
+                .unwrap(),
+            "root/main.rs: 1 error(s), 1 warning(s)\n"
+        );
+        // Hints and other diagnostics that are not errors or warnings are left out.
+        assert_eq!(
+            run_tool(
+                DiagnosticsTool,
+                json!({ "path": "root/main.rs" }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "error at line 2: cannot find function `a`\nwarning at line 3: unused result\n"
+        );
+        assert_eq!(
+            run_tool(
+                DiagnosticsTool,
+                json!({ "path": "root/lib.rs" }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "There are no errors or warnings in root/lib.rs."
+        );
+    }
+}
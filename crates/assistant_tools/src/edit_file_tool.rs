@@ -0,0 +1,203 @@
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _, Result};
+use assistant_tool::Tool;
+use editor::{ProposedChangeLocation, ProposedChangesEditor};
+use gpui::{App, AppContext as _, Task, WeakEntity, Window};
+use language::{Point, ToPoint as _};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+/// The number of unchanged lines shown around each edit for review.
+const CONTEXT_LINES: u32 = 3;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EditFileToolInput {
+    /// The relative path of the file to edit.
+    ///
+    /// This path should never be absolute, and the first component
+    /// of the path should always be a top-level directory in a project.
+    pub path: PathBuf,
+    /// The edits to make to the file.
+    pub edits: Vec<EditFileToolEdit>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EditFileToolEdit {
+    /// The text to replace. It must appear exactly once in the file, so include
+    /// enough of the surrounding lines to tell it apart.
+    pub old_text: String,
+    /// The text to replace it with.
+    pub new_text: String,
+}
+
+pub struct EditFileTool;
+
+impl Tool for EditFileTool {
+    fn name(&self) -> String {
+        "edit-file".into()
+    }
+
+    fn description(&self) -> String {
+        "Proposes edits to a file in the project, each replacing a piece of its text. The edits are shown to the user as a diff, and only land in the file once the user accepts them. Read the file first, so that the text to replace matches it exactly.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(EditFileToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: EditFileToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+        let Some(project_path) = project.read(cx).find_project_path(&input.path, cx) else {
+            return Task::ready(Err(anyhow!(
+                "Path {} not found in project",
+                input.path.display()
+            )));
+        };
+
+        cx.spawn(|mut cx| async move {
+            let buffer = project
+                .update(&mut cx, |project, cx| project.open_buffer(project_path, cx))?
+                .await?;
+            let text = buffer.read_with(&cx, |buffer, _| buffer.text())?;
+            let edits = resolve_edits(&text, &input.edits)?;
+            let edit_count = edits.len();
+            let path = input.path.display().to_string();
+
+            workspace.update_in(&mut cx, |workspace, window, cx| {
+                let snapshot = buffer.read(cx).snapshot();
+                let max_row = snapshot.max_point().row;
+                let mut ranges = Vec::<Range<Point>>::new();
+                for (range, _) in &edits {
+                    let start = range.start.to_point(&snapshot).row;
+                    let end = range.end.to_point(&snapshot).row;
+                    let start = Point::new(start.saturating_sub(CONTEXT_LINES), 0);
+                    let end_row = (end + CONTEXT_LINES).min(max_row);
+                    let end = Point::new(end_row, snapshot.line_len(end_row));
+                    match ranges.last_mut() {
+                        Some(last) if last.end.row + 1 >= start.row => last.end = end,
+                        _ => ranges.push(start..end),
+                    }
+                }
+
+                let editor = cx.new(|cx| {
+                    ProposedChangesEditor::new(
+                        format!("Proposed Edits: {path}"),
+                        vec![ProposedChangeLocation {
+                            buffer: buffer.clone(),
+                            ranges,
+                        }],
+                        Some(project.clone()),
+                        window,
+                        cx,
+                    )
+                });
+                let branch = editor
+                    .read(cx)
+                    .branch_buffer_for_base(&buffer)
+                    .context("no branch for the edited buffer")?;
+                branch.update(cx, |branch, cx| branch.edit(edits, None, cx));
+                editor.read(cx).recalculate_all_buffer_diffs();
+                workspace.add_item_to_active_pane(Box::new(editor), None, false, window, cx);
+                anyhow::Ok(())
+            })??;
+
+            Ok(format!(
+                "Proposed {edit_count} edit(s) to {path}. They are shown to the user for review, and land in the file once accepted."
+            ))
+        })
+    }
+}
+
+/// Finds the range of text each edit replaces, which must appear exactly once in the text.
+fn resolve_edits(text: &str, edits: &[EditFileToolEdit]) -> Result<Vec<(Range<usize>, String)>> {
+    let mut resolved = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if edit.old_text.is_empty() {
+            if !text.is_empty() {
+                return Err(anyhow!(
+                    "The text to replace can only be empty in an empty file"
+                ));
+            }
+            resolved.push((0..0, edit.new_text.clone()));
+            continue;
+        }
+
+        let mut matches = text.match_indices(&edit.old_text);
+        let Some((start, _)) = matches.next() else {
+            return Err(anyhow!(
+                "Could not find text to replace:\n{}",
+                edit.old_text
+            ));
+        };
+        if matches.next().is_some() {
+            return Err(anyhow!(
+                "Text to replace appears more than once, include more of the surrounding lines:\n{}",
+                edit.old_text
+            ));
+        }
+        resolved.push((start..start + edit.old_text.len(), edit.new_text.clone()));
+    }
+
+    resolved.sort_by_key(|(range, _)| range.start);
+    if resolved
+        .windows(2)
+        .any(|edits| edits[0].0.end > edits[1].0.start)
+    {
+        return Err(anyhow!("Edits must not overlap each other"));
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(old_text: &str, new_text: &str) -> EditFileToolEdit {
+        EditFileToolEdit {
+            old_text: old_text.into(),
+            new_text: new_text.into(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_edits() {
+        let text = "fn a() {}\nfn b() {}\nfn c() {}\n";
+        assert_eq!(
+            resolve_edits(text, &[edit("fn c", "fn d"), edit("fn a", "fn z")]).unwrap(),
+            [(0..4, "fn z".to_string()), (20..24, "fn d".to_string())]
+        );
+
+        assert!(resolve_edits(text, &[edit("fn e", "")]).is_err());
+        assert!(resolve_edits(text, &[edit("() {}", "")]).is_err());
+        assert!(resolve_edits(
+            text,
+            &[edit("fn a() {}\nfn b", ""), edit("a() {}\nfn b() {}", "")]
+        )
+        .is_err());
+        assert!(resolve_edits(text, &[edit("", "fn e() {}")]).is_err());
+        assert_eq!(
+            resolve_edits("", &[edit("", "fn e() {}")]).unwrap(),
+            [(0..0, "fn e() {}".to_string())]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use gpui::{App, Task, WeakEntity, Window};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ListDirectoryToolInput {
+    /// The relative path of the directory to list.
+    ///
+    /// This path should never be absolute, and the first component
+    /// of the path should always be a top-level directory in a project.
+    /// Leave it empty to list the top-level directories of the project.
+    #[serde(default)]
+    pub path: PathBuf,
+}
+
+pub struct ListDirectoryTool;
+
+impl Tool for ListDirectoryTool {
+    fn name(&self) -> String {
+        "list-directory".into()
+    }
+
+    fn description(&self) -> String {
+        "Lists the files and directories in a directory of the project. Directories end with a slash, and entries ignored by git are left out.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(ListDirectoryToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: ListDirectoryToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+        let project = project.read(cx);
+
+        let mut output = String::new();
+        if input.path.as_os_str().is_empty() || input.path == Path::new(".") {
+            for worktree in project.visible_worktrees(cx) {
+                writeln!(output, "{}/", worktree.read(cx).root_name()).ok();
+            }
+            return Task::ready(Ok(output));
+        }
+
+        let Some(project_path) = project.find_project_path(&input.path, cx) else {
+            return Task::ready(Err(anyhow!(
+                "Path {} not found in project",
+                input.path.display()
+            )));
+        };
+        let Some(worktree) = project.worktree_for_id(project_path.worktree_id, cx) else {
+            return Task::ready(Err(anyhow!("Worktree not found")));
+        };
+        let worktree = worktree.read(cx);
+        match worktree.entry_for_path(&project_path.path) {
+            Some(entry) if entry.is_dir() => {}
+            Some(_) => {
+                return Task::ready(Err(anyhow!("{} is a file", input.path.display())));
+            }
+            None => {
+                return Task::ready(Err(anyhow!(
+                    "Path {} not found in project",
+                    input.path.display()
+                )));
+            }
+        }
+
+        for entry in worktree.child_entries(&project_path.path) {
+            if entry.is_ignored {
+                continue;
+            }
+            let path = Path::new(worktree.root_name()).join(&entry.path);
+            let separator = if entry.is_dir() { "/" } else { "" };
+            writeln!(output, "{}{separator}", path.display()).ok();
+        }
+        if output.is_empty() {
+            output = format!("{} is empty.", input.path.display());
+        }
+        Task::ready(Ok(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_tool, test_workspace};
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_listing_directories(cx: &mut TestAppContext) {
+        let (workspace, mut cx) = test_workspace(
+            json!({
+                "src": {
+                    "main.rs": "",
+                    "empty": {},
+                    "lib.rs": "",
+                },
+                "README.md": "",
+            }),
+            cx,
+        )
+        .await;
+        let cx = &mut cx;
+
+        let list = |path: &str| json!({ "path": path });
+        assert_eq!(
+            run_tool(ListDirectoryTool, list(""), &workspace, cx)
+                .await
+                .unwrap(),
+            "root/\n"
+        );
+        assert_eq!(
+            run_tool(ListDirectoryTool, list("root"), &workspace, cx)
+                .await
+                .unwrap(),
+            "root/README.md\nroot/src/\n"
+        );
+        assert_eq!(
+            run_tool(ListDirectoryTool, list("root/src"), &workspace, cx)
+                .await
+                .unwrap(),
+            "root/src/empty/\nroot/src/lib.rs\nroot/src/main.rs\n"
+        );
+        assert_eq!(
+            run_tool(ListDirectoryTool, list("root/src/empty"), &workspace, cx)
+                .await
+                .unwrap(),
+            "root/src/empty is empty."
+        );
+        assert!(
+            run_tool(ListDirectoryTool, list("root/README.md"), &workspace, cx)
+                .await
+                .is_err()
+        );
+        assert!(
+            run_tool(ListDirectoryTool, list("root/missing"), &workspace, cx)
+                .await
+                .is_err()
+        );
+    }
+}
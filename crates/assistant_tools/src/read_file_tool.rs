@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use gpui::{App, Task, WeakEntity, Window};
+use language::Point;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReadFileToolInput {
+    /// The relative path of the file to read.
+    ///
+    /// This path should never be absolute, and the first component
+    /// of the path should always be a top-level directory in a project.
+    ///
+    /// For example, if the project has the following top-level directories:
+    ///
+    /// - directory1
+    /// - directory2
+    ///
+    /// To read `file.txt` in `directory1`, use the path `directory1/file.txt`.
+    pub path: PathBuf,
+    /// The line to start reading at, counting from 1. Defaults to the start of the file.
+    #[serde(default)]
+    pub start_line: Option<u32>,
+    /// The last line to read, inclusive. Defaults to the end of the file.
+    #[serde(default)]
+    pub end_line: Option<u32>,
+}
+
+pub struct ReadFileTool;
+
+impl Tool for ReadFileTool {
+    fn name(&self) -> String {
+        "read-file".into()
+    }
+
+    fn description(&self) -> String {
+        "Reads the content of a file in the project, or of a range of its lines. Reads the file as it is open in the editor, including unsaved changes.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(ReadFileToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: ReadFileToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+        let Some(project_path) = project.read(cx).find_project_path(&input.path, cx) else {
+            return Task::ready(Err(anyhow!(
+                "Path {} not found in project",
+                input.path.display()
+            )));
+        };
+
+        cx.spawn(|mut cx| async move {
+            let buffer = project
+                .update(&mut cx, |project, cx| project.open_buffer(project_path, cx))?
+                .await?;
+            buffer.read_with(&cx, |buffer, _| {
+                let max_row = buffer.max_point().row;
+                let start_row = input.start_line.unwrap_or(1).saturating_sub(1);
+                let end_row = input
+                    .end_line
+                    .map_or(max_row, |line| line.saturating_sub(1).min(max_row));
+                if start_row > end_row {
+                    return Err(anyhow!(
+                        "Line range is outside of the file, which has {} lines",
+                        max_row + 1
+                    ));
+                }
+                let range = Point::new(start_row, 0)..Point::new(end_row, buffer.line_len(end_row));
+                Ok(buffer.text_for_range(range).collect())
+            })?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_tool, test_workspace};
+    use gpui::TestAppContext;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[gpui::test]
+    async fn test_reading_line_ranges(cx: &mut TestAppContext) {
+        let (workspace, mut cx) =
+            test_workspace(json!({ "file.txt": "one\ntwo\nthree\nfour\n" }), cx).await;
+        let cx = &mut cx;
+
+        assert_eq!(
+            run_tool(
+                ReadFileTool,
+                json!({ "path": "root/file.txt" }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+        assert_eq!(
+            run_tool(
+                ReadFileTool,
+                json!({ "path": "root/file.txt", "start_line": 2, "end_line": 3 }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "two\nthree"
+        );
+        // Ranges past the end of the file are cut off.
+        assert_eq!(
+            run_tool(
+                ReadFileTool,
+                json!({ "path": "root/file.txt", "start_line": 3, "end_line": 100 }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "three\nfour\n"
+        );
+        assert!(run_tool(
+            ReadFileTool,
+            json!({ "path": "root/file.txt", "start_line": 10 }),
+            &workspace,
+            cx
+        )
+        .await
+        .is_err());
+        assert!(run_tool(
+            ReadFileTool,
+            json!({ "path": "root/missing.txt" }),
+            &workspace,
+            cx
+        )
+        .await
+        .is_err());
+    }
+
+    #[gpui::test]
+    async fn test_reading_unsaved_changes(cx: &mut TestAppContext) {
+        let (workspace, mut cx) = test_workspace(json!({ "file.txt": "one\ntwo\n" }), cx).await;
+        let cx = &mut cx;
+
+        let project = workspace.read_with(cx, |workspace, _| workspace.project().clone());
+        let buffer = project
+            .update(cx, |project, cx| {
+                let project_path = project
+                    .find_project_path(Path::new("root/file.txt"), cx)
+                    .unwrap();
+                project.open_buffer(project_path, cx)
+            })
+            .await
+            .unwrap();
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..3, "ONE")], None, cx));
+
+        let output = run_tool(
+            ReadFileTool,
+            json!({ "path": "root/file.txt", "end_line": 1 }),
+            &workspace,
+            cx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, "ONE");
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use gpui::{App, Task, WeakEntity, Window};
+use language::{Point, ToPoint as _};
+use project::search::{SearchQuery, SearchResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use util::paths::PathMatcher;
+use workspace::Workspace;
+
+/// The search stops after this many matching lines.
+const MAX_MATCHING_LINES: usize = 200;
+
+/// Longer lines are cut off in the results.
+const MAX_LINE_LENGTH: usize = 240;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RegexSearchToolInput {
+    /// A regular expression to search for, in Rust's regex syntax.
+    pub regex: String,
+    /// A glob that limits the search to the paths it matches, like `directory1/src/**/*.rs`.
+    #[serde(default)]
+    pub include: Option<String>,
+    /// Whether the search is case sensitive.
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+pub struct RegexSearchTool;
+
+impl Tool for RegexSearchTool {
+    fn name(&self) -> String {
+        "regex-search".into()
+    }
+
+    fn description(&self) -> String {
+        "Searches the files in the project for a regular expression, and returns the matching lines along with their paths and line numbers. Files ignored by git are not searched.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(RegexSearchToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: RegexSearchToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+
+        // Searches of a single worktree match globs against paths without its root name.
+        let include = input.include.map(|include| {
+            let project = project.read(cx);
+            let mut worktrees = project.visible_worktrees(cx);
+            match (worktrees.next(), worktrees.next()) {
+                (Some(worktree), None) => {
+                    let prefix = format!("{}/", worktree.read(cx).root_name());
+                    include
+                        .strip_prefix(&prefix)
+                        .map(ToString::to_string)
+                        .unwrap_or(include)
+                }
+                _ => include,
+            }
+        });
+        let query = include
+            .map(|include| PathMatcher::new(&[include]))
+            .transpose()
+            .map_err(|err| anyhow!(err))
+            .and_then(|files_to_include| {
+                SearchQuery::regex(
+                    &input.regex,
+                    false,
+                    input.case_sensitive,
+                    false,
+                    files_to_include.unwrap_or_default(),
+                    PathMatcher::default(),
+                    None,
+                )
+            });
+        let query = match query {
+            Ok(query) => query,
+            Err(err) => return Task::ready(Err(err)),
+        };
+        let results = project.update(cx, |project, cx| project.search(query, cx));
+
+        cx.spawn(|cx| async move {
+            let mut output = String::new();
+            let mut matching_lines = 0;
+            while let Ok(result) = results.recv().await {
+                let SearchResult::Buffer { buffer, ranges } = result else {
+                    writeln!(output, "The search stopped at the maximum number of files.")?;
+                    break;
+                };
+                buffer.read_with(&cx, |buffer, cx| {
+                    let Some(file) = buffer.file() else {
+                        return;
+                    };
+                    writeln!(output, "## {}", file.full_path(cx).display()).ok();
+                    let mut last_row = None;
+                    for range in ranges {
+                        let row = range.start.to_point(buffer).row;
+                        if last_row == Some(row) || matching_lines == MAX_MATCHING_LINES {
+                            continue;
+                        }
+                        last_row = Some(row);
+                        matching_lines += 1;
+
+                        let line_range = Point::new(row, 0)..Point::new(row, buffer.line_len(row));
+                        let line = buffer.text_for_range(line_range).collect::<String>();
+                        let line = match line.char_indices().nth(MAX_LINE_LENGTH) {
+                            Some((end, _)) => format!("{}…", &line[..end]),
+                            None => line,
+                        };
+                        writeln!(output, "{}: {}", row + 1, line.trim_end()).ok();
+                    }
+                })?;
+
+                if matching_lines == MAX_MATCHING_LINES {
+                    writeln!(
+                        output,
+                        "The search stopped after {MAX_MATCHING_LINES} matching lines, narrow it down to see more."
+                    )?;
+                    break;
+                }
+            }
+
+            if output.is_empty() {
+                Ok("No matches found.".into())
+            } else {
+                Ok(output)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_tool, test_workspace};
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_search_matches(cx: &mut TestAppContext) {
+        let (workspace, mut cx) = test_workspace(
+            json!({
+                "src": {
+                    "main.rs": "fn main() {\n    let todo = 1; // TODO: todo\n}\n",
+                    "lib.rs": "// nothing to do\n",
+                },
+                "notes.txt": "TODO: write notes\n",
+            }),
+            cx,
+        )
+        .await;
+        let cx = &mut cx;
+
+        // Lines with several matches are only listed once.
+        let output = run_tool(
+            RegexSearchTool,
+            json!({ "regex": "todo", "include": "root/src/**" }),
+            &workspace,
+            cx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            output,
+            "## root/src/main.rs\n2:     let todo = 1; // TODO: todo\n"
+        );
+
+        let output = run_tool(
+            RegexSearchTool,
+            json!({ "regex": "todo: write", "case_sensitive": true }),
+            &workspace,
+            cx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, "No matches found.");
+
+        assert!(
+            run_tool(RegexSearchTool, json!({ "regex": "(" }), &workspace, cx)
+                .await
+                .is_err()
+        );
+    }
+
+    #[gpui::test]
+    async fn test_search_limits(cx: &mut TestAppContext) {
+        let long_line = format!("match {}", "x".repeat(MAX_LINE_LENGTH));
+        let text = std::iter::repeat(long_line.as_str())
+            .take(MAX_MATCHING_LINES + 10)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (workspace, mut cx) = test_workspace(json!({ "file.txt": text }), cx).await;
+        let cx = &mut cx;
+
+        let output = run_tool(RegexSearchTool, json!({ "regex": "match" }), &workspace, cx)
+            .await
+            .unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "## root/file.txt");
+        assert_eq!(lines.len(), MAX_MATCHING_LINES + 2);
+        assert!(lines[MAX_MATCHING_LINES + 1].starts_with("The search stopped after"));
+
+        // Long lines are cut off.
+        let expected = format!("1: {}…", &long_line[..MAX_LINE_LENGTH]);
+        assert_eq!(lines[1], expected);
+    }
+}
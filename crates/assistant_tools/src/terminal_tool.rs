@@ -0,0 +1,715 @@
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use assistant_tool::Tool;
+use futures::channel::mpsc;
+use futures::{FutureExt as _, StreamExt as _};
+use gpui::{App, BackgroundExecutor, Task, WeakEntity, Window};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings as _, SettingsLocation};
+use smol::io::{AsyncRead, AsyncReadExt as _};
+use util::command::new_smol_command;
+use workspace::Workspace;
+
+use crate::terminal_tool_settings::TerminalToolSettings;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TerminalToolInput {
+    /// The shell command to run.
+    pub command: String,
+    /// The directory to run the command in, relative to the project.
+    ///
+    /// The first component of the path should always be a top-level directory in the
+    /// project. Defaults to the first top-level directory.
+    #[serde(default)]
+    pub cd: Option<PathBuf>,
+}
+
+pub struct TerminalTool;
+
+impl Tool for TerminalTool {
+    fn name(&self) -> String {
+        "terminal".into()
+    }
+
+    fn description(&self) -> String {
+        "Runs a shell command in a directory of the project, and returns its exit status and output. Commands are killed when they run for too long, and long output is cut down to its end, so don't run commands that never exit on their own, like servers or file watchers.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        let schema = schemars::schema_for!(TerminalToolInput);
+        serde_json::to_value(&schema).unwrap()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let (output_tx, _) = mpsc::unbounded();
+        self.run_streaming(input, workspace, output_tx, window, cx)
+    }
+
+    fn run_streaming(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakEntity<Workspace>,
+        output: mpsc::UnboundedSender<String>,
+        _window: &mut Window,
+        cx: &mut App,
+    ) -> Task<Result<String>> {
+        let input: TerminalToolInput = match serde_json::from_value(input) {
+            Ok(input) => input,
+            Err(err) => return Task::ready(Err(anyhow!(err))),
+        };
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return Task::ready(Err(anyhow!("workspace was dropped")));
+        };
+        if !project.read(cx).is_local() {
+            return Task::ready(Err(anyhow!("Commands can only be run in local projects.")));
+        }
+
+        let (worktree, relative_dir) = match input.cd.as_ref() {
+            Some(cd) => {
+                if cd.is_absolute() || cd.components().any(|c| c == Component::ParentDir) {
+                    return Task::ready(Err(anyhow!(
+                        "Directory {} is not inside the project",
+                        cd.display()
+                    )));
+                }
+                let project = project.read(cx);
+                let Some((worktree, project_path)) =
+                    project.find_project_path(cd, cx).and_then(|project_path| {
+                        let worktree = project.worktree_for_id(project_path.worktree_id, cx)?;
+                        Some((worktree, project_path))
+                    })
+                else {
+                    return Task::ready(Err(anyhow!(
+                        "Directory {} not found in project",
+                        cd.display()
+                    )));
+                };
+                (worktree, project_path.path)
+            }
+            None => {
+                let Some(worktree) = project.read(cx).visible_worktrees(cx).next() else {
+                    return Task::ready(Err(anyhow!("The project has no directories")));
+                };
+                (worktree, Path::new("").into())
+            }
+        };
+        let worktree_id = worktree.read(cx).id();
+        let worktree_abs_path = worktree.read(cx).abs_path().clone();
+        let cwd = worktree_abs_path.join(&relative_dir);
+
+        let settings = TerminalToolSettings::get(
+            Some(SettingsLocation {
+                worktree_id,
+                path: Path::new(""),
+            }),
+            cx,
+        )
+        .clone();
+        if settings.allowed_commands.is_some() {
+            match shell_invocations(&input.command) {
+                Some(invocations) if settings.allows(&invocations) => {}
+                Some(_) => {
+                    return Task::ready(Err(anyhow!(
+                        "The command is not allowed by the `terminal_tool.allowed_commands` setting."
+                    )));
+                }
+                None => {
+                    return Task::ready(Err(anyhow!(
+                        "The command can't be checked against the `terminal_tool.allowed_commands` setting, because it substitutes the output of other commands, or has unclosed quotes."
+                    )));
+                }
+            }
+        }
+
+        let writable_dirs = project
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .collect::<Vec<_>>();
+        let environment = project.read(cx).environment().clone();
+        let environment = environment.update(cx, |environment, cx| {
+            environment.get_environment(Some(worktree_id), Some(worktree_abs_path), cx)
+        });
+        let executor = cx.background_executor().clone();
+        cx.spawn(|cx| async move {
+            // Include the variables that the user's shell and direnv set in the directory.
+            let environment = environment.await;
+            cx.background_executor()
+                .spawn(async move {
+                    let mut command = if settings.sandbox.enabled {
+                        sandboxed_command(
+                            &input.command,
+                            &cwd,
+                            &writable_dirs,
+                            settings.sandbox.allow_network,
+                        )?
+                    } else {
+                        shell_command(&input.command)
+                    };
+                    if let Some(environment) = environment {
+                        command.envs(environment);
+                    }
+                    command.current_dir(&cwd);
+
+                    run_command(
+                        command,
+                        Duration::from_secs(settings.timeout_seconds),
+                        settings.max_output_bytes,
+                        output,
+                        executor,
+                    )
+                    .await
+                })
+                .await
+        })
+    }
+}
+
+fn shell_command(command: &str) -> smol::process::Command {
+    if cfg!(windows) {
+        let mut shell = new_smol_command("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = new_smol_command("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+fn sandboxed_command(
+    command: &str,
+    cwd: &Path,
+    writable_dirs: &[PathBuf],
+    allow_network: bool,
+) -> Result<smol::process::Command> {
+    if !cfg!(target_os = "linux") {
+        bail!("Sandboxing commands is only supported on Linux. Turn off the `terminal_tool.sandbox.enabled` setting to run commands.");
+    }
+    let bwrap = which::which("bwrap")
+        .context("Sandboxing commands needs bubblewrap (`bwrap`), which wasn't found")?;
+    let mut sandbox = new_smol_command(bwrap);
+    sandbox.args(sandbox_args(command, cwd, writable_dirs, allow_network));
+    Ok(sandbox)
+}
+
+/// Returns the arguments to `bwrap` that run the command with a read-only view of the file
+/// system, in which only the given directories and a private `/tmp` are writable.
+fn sandbox_args(
+    command: &str,
+    cwd: &Path,
+    writable_dirs: &[PathBuf],
+    allow_network: bool,
+) -> Vec<OsString> {
+    let mut args = ["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]
+        .into_iter()
+        .chain(["--tmpfs", "/tmp"])
+        .map(OsString::from)
+        .collect::<Vec<_>>();
+    // Bind the writable directories last, so that they stay visible when they're in `/tmp`.
+    for dir in writable_dirs {
+        args.push("--bind".into());
+        args.push(dir.into());
+        args.push(dir.into());
+    }
+    args.extend(
+        ["--unshare-pid", "--die-with-parent", "--new-session"]
+            .into_iter()
+            .map(OsString::from),
+    );
+    if !allow_network {
+        args.push("--unshare-net".into());
+    }
+    args.push("--chdir".into());
+    args.push(cwd.into());
+    args.extend(["--", "sh", "-c", command].into_iter().map(OsString::from));
+    args
+}
+
+async fn run_command(
+    mut command: smol::process::Command,
+    timeout: Duration,
+    max_output_len: usize,
+    output_tx: mpsc::UnboundedSender<String>,
+    executor: BackgroundExecutor,
+) -> Result<String> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Start the command in a session of its own, so that the processes it starts in the
+    // background can be killed along with it.
+    //
+    // safety: code in pre_exec should be signal safe.
+    // https://man7.org/linux/man-pages/man7/signal-safety.7.html
+    #[cfg(unix)]
+    unsafe {
+        use smol::process::unix::CommandExt as _;
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().context("failed to start the command")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to read the command's output")?;
+    let stderr = child
+        .stderr
+        .take()
+        .context("failed to read the command's output")?;
+
+    let mut output = CommandOutput::new(max_output_len);
+    let status = {
+        let (chunk_tx, mut chunk_rx) = mpsc::unbounded::<String>();
+        let run = async {
+            let collect_output = async {
+                while let Some(chunk) = chunk_rx.next().await {
+                    output.push(&chunk);
+                    output_tx.unbounded_send(chunk).ok();
+                }
+            };
+            futures::join!(
+                forward_output(stdout, chunk_tx.clone()),
+                forward_output(stderr, chunk_tx),
+                collect_output
+            );
+            child.status().await
+        };
+        futures::select_biased! {
+            status = run.fuse() => Some(status?),
+            _ = executor.timer(timeout).fuse() => None,
+        }
+    };
+
+    let mut result = match status {
+        Some(status) => match status.code() {
+            Some(code) => format!("The command exited with status {code}.\n"),
+            None => "The command was terminated by a signal.\n".to_string(),
+        },
+        None => {
+            kill_process_group(&mut child);
+            format!(
+                "The command timed out after {} seconds, and was killed.\n",
+                timeout.as_secs()
+            )
+        }
+    };
+    if output.omitted_len > 0 {
+        result.push_str(&format!(
+            "The first {} bytes of its output were omitted.\n",
+            output.omitted_len
+        ));
+    }
+    if output.text.trim().is_empty() {
+        result.push_str("It produced no output.");
+    } else {
+        result.push_str("Output:\n");
+        result.push_str(&output.text);
+    }
+    Ok(result)
+}
+
+/// Kills the command, and on Unix, every process in the session it leads.
+fn kill_process_group(child: &mut smol::process::Child) {
+    #[cfg(unix)]
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+    child.kill().ok();
+}
+
+async fn forward_output(mut reader: impl AsyncRead + Unpin, chunks: mpsc::UnboundedSender<String>) {
+    let mut buffer = [0; 4096];
+    let mut pending = Vec::new();
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(len) => {
+                pending.extend_from_slice(&buffer[..len]);
+                let text = take_utf8(&mut pending);
+                if !text.is_empty() {
+                    chunks.unbounded_send(text).ok();
+                }
+            }
+        }
+    }
+    if !pending.is_empty() {
+        chunks
+            .unbounded_send(String::from_utf8_lossy(&pending).into_owned())
+            .ok();
+    }
+}
+
+/// Takes the text from the start of the bytes, leaving behind a character that's cut off at
+/// the end for the next read to complete. Invalid UTF-8 is replaced.
+fn take_utf8(bytes: &mut Vec<u8>) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            let text = text.to_string();
+            bytes.clear();
+            text
+        }
+        Err(error) if error.error_len().is_none() => {
+            let valid_len = error.valid_up_to();
+            let text = String::from_utf8_lossy(&bytes[..valid_len]).into_owned();
+            bytes.drain(..valid_len);
+            text
+        }
+        Err(_) => {
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            bytes.clear();
+            text
+        }
+    }
+}
+
+/// The end of a command's output, up to a maximum length.
+struct CommandOutput {
+    text: String,
+    max_len: usize,
+    omitted_len: usize,
+}
+
+impl CommandOutput {
+    fn new(max_len: usize) -> Self {
+        Self {
+            text: String::new(),
+            max_len,
+            omitted_len: 0,
+        }
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+        if self.text.len() > self.max_len {
+            let mut start = self.text.len() - self.max_len;
+            while !self.text.is_char_boundary(start) {
+                start += 1;
+            }
+            self.text.drain(..start);
+            self.omitted_len += start;
+        }
+    }
+}
+
+/// A program invocation in a shell command.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ShellInvocation {
+    /// The words of the invocation, including the variable assignments before the program.
+    pub words: Vec<String>,
+    /// The indices of the words that redirect the output to a file, including the file's.
+    pub output_redirections: Vec<usize>,
+}
+
+/// Splits a shell command into each program invocation in it, so that they can be checked
+/// against the allowed commands.
+///
+/// Returns `None` for commands that can't be checked, because they substitute the output of
+/// other commands into theirs, or have unclosed quotes.
+pub(crate) fn shell_invocations(command: &str) -> Option<Vec<ShellInvocation>> {
+    let mut builder = InvocationBuilder::default();
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('\'') => {
+                if c == '\'' {
+                    quote = None;
+                } else {
+                    builder.word.push(c);
+                }
+                continue;
+            }
+            Some(_) => {
+                match c {
+                    '"' => quote = None,
+                    '`' => return None,
+                    '$' if chars.peek() == Some(&'(') => return None,
+                    '\\' => builder.word.extend(chars.next()),
+                    _ => builder.word.push(c),
+                }
+                continue;
+            }
+            None => {}
+        }
+
+        match c {
+            '`' => return None,
+            '$' | '<' | '>' if chars.peek() == Some(&'(') => return None,
+            '\'' | '"' => {
+                quote = Some(c);
+                builder.in_word = true;
+            }
+            '\\' => {
+                builder.word.extend(chars.next());
+                builder.in_word = true;
+            }
+            '>' => {
+                builder.word.push(c);
+                builder.in_word = true;
+                builder.word_redirects = true;
+            }
+            // `&` is part of redirections like `2>&1` and `&>`.
+            '&' if builder.word.ends_with('>')
+                || builder.word.ends_with('<')
+                || chars.peek() == Some(&'>') =>
+            {
+                builder.word.push(c);
+                builder.in_word = true;
+            }
+            ';' | '&' | '|' | '\n' | '(' | ')' => {
+                builder.push_word();
+                builder.push_invocation();
+            }
+            c if c.is_whitespace() => builder.push_word(),
+            c => {
+                builder.word.push(c);
+                builder.in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    builder.push_word();
+    builder.push_invocation();
+    Some(builder.invocations)
+}
+
+#[derive(Default)]
+struct InvocationBuilder {
+    invocations: Vec<ShellInvocation>,
+    words: Vec<String>,
+    output_redirections: Vec<usize>,
+    word: String,
+    in_word: bool,
+    /// Whether the word has an unquoted `>`.
+    word_redirects: bool,
+    /// Whether the next word is the file that the last word redirects the output to.
+    redirect_target_pending: bool,
+}
+
+impl InvocationBuilder {
+    fn push_word(&mut self) {
+        if !self.in_word {
+            return;
+        }
+        let word = std::mem::take(&mut self.word);
+        if self.redirect_target_pending {
+            self.output_redirections.push(self.words.len());
+            self.redirect_target_pending = false;
+        } else if self.word_redirects && !is_descriptor_duplication(&word) {
+            self.output_redirections.push(self.words.len());
+            self.redirect_target_pending = word.ends_with('>') || word.ends_with(">&");
+        }
+        self.words.push(word);
+        self.in_word = false;
+        self.word_redirects = false;
+    }
+
+    fn push_invocation(&mut self) {
+        self.redirect_target_pending = false;
+        let output_redirections = std::mem::take(&mut self.output_redirections);
+        if !self.words.is_empty() {
+            self.invocations.push(ShellInvocation {
+                words: std::mem::take(&mut self.words),
+                output_redirections,
+            });
+        }
+    }
+}
+
+/// Returns whether a redirection only points an output at another one, like `2>&1`, rather
+/// than at a file.
+fn is_descriptor_duplication(word: &str) -> bool {
+    word.rsplit_once('>')
+        .and_then(|(_, target)| target.strip_prefix('&'))
+        .map_or(false, |descriptor| {
+            descriptor == "-"
+                || (!descriptor.is_empty() && descriptor.chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+
+    fn invocation(words: &[&str], output_redirections: &[usize]) -> ShellInvocation {
+        ShellInvocation {
+            words: words.iter().map(|word| word.to_string()).collect(),
+            output_redirections: output_redirections.to_vec(),
+        }
+    }
+
+    fn invocations(invocations: &[&[&str]]) -> Option<Vec<ShellInvocation>> {
+        Some(
+            invocations
+                .iter()
+                .map(|words| invocation(words, &[]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_shell_invocations() {
+        assert_eq!(
+            shell_invocations("cargo test --workspace"),
+            invocations(&[&["cargo", "test", "--workspace"]])
+        );
+        assert_eq!(
+            shell_invocations("RUST_LOG=info cargo test 2>&1 | tail -n 20 && echo 'done; really'"),
+            invocations(&[
+                &["RUST_LOG=info", "cargo", "test", "2>&1"],
+                &["tail", "-n", "20"],
+                &["echo", "done; really"]
+            ])
+        );
+        assert_eq!(
+            shell_invocations("(cd src; ls) & git status\nrm \"a b\""),
+            invocations(&[&["cd", "src"], &["ls"], &["git", "status"], &["rm", "a b"]])
+        );
+        assert_eq!(
+            shell_invocations("echo '$(not substituted)'"),
+            invocations(&[&["echo", "$(not substituted)"]])
+        );
+        assert_eq!(shell_invocations("echo $(rm -rf ~)"), None);
+        assert_eq!(shell_invocations("echo \"`whoami`\""), None);
+        assert_eq!(shell_invocations("diff <(ls a) <(ls b)"), None);
+        assert_eq!(shell_invocations("echo 'unclosed"), None);
+    }
+
+    #[test]
+    fn test_shell_invocation_redirections() {
+        assert_eq!(
+            shell_invocations("ls > ~/.bashrc; echo x >>.git/hooks/pre-commit"),
+            Some(vec![
+                invocation(&["ls", ">", "~/.bashrc"], &[1, 2]),
+                invocation(&["echo", "x", ">>.git/hooks/pre-commit"], &[2]),
+            ])
+        );
+        // Pointing an output at another one doesn't write to a file.
+        assert_eq!(
+            shell_invocations("cargo test &> out.txt 2>&1 >&2 2>/dev/null"),
+            Some(vec![invocation(
+                &[
+                    "cargo",
+                    "test",
+                    "&>",
+                    "out.txt",
+                    "2>&1",
+                    ">&2",
+                    "2>/dev/null"
+                ],
+                &[2, 3, 6]
+            )])
+        );
+        assert_eq!(
+            shell_invocations("grep '->' src/main.rs"),
+            invocations(&[&["grep", "->", "src/main.rs"]])
+        );
+    }
+
+    #[test]
+    fn test_sandbox_args() {
+        let args = sandbox_args(
+            "cargo test",
+            Path::new("/work/project/crates"),
+            &[PathBuf::from("/work/project")],
+            false,
+        );
+        let args = args
+            .iter()
+            .map(|arg| arg.to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(
+            args,
+            "--ro-bind / / --dev /dev --proc /proc --tmpfs /tmp \
+             --bind /work/project /work/project \
+             --unshare-pid --die-with-parent --new-session --unshare-net \
+             --chdir /work/project/crates -- sh -c cargo test"
+        );
+    }
+
+    #[test]
+    fn test_command_output() {
+        let mut output = CommandOutput::new(8);
+        output.push("abc");
+        assert_eq!((output.text.as_str(), output.omitted_len), ("abc", 0));
+        output.push("defghij");
+        assert_eq!((output.text.as_str(), output.omitted_len), ("cdefghij", 2));
+
+        // The output is never cut in the middle of a character.
+        let mut output = CommandOutput::new(2);
+        output.push("éa");
+        assert_eq!((output.text.as_str(), output.omitted_len), ("a", 2));
+    }
+
+    #[cfg(unix)]
+    #[gpui::test]
+    async fn test_run_command_timeout_kills_background_processes(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let (output_tx, mut output_rx) = mpsc::unbounded();
+        let result = cx.executor().spawn(run_command(
+            shell_command("sleep 100 & echo $!; wait"),
+            Duration::from_secs(10),
+            1024,
+            output_tx,
+            cx.executor(),
+        ));
+        let pid = output_rx.next().await.unwrap().trim().to_string();
+
+        cx.executor().advance_clock(Duration::from_secs(10));
+        let result = result.await.unwrap();
+        assert!(
+            result.starts_with("The command timed out after 10 seconds, and was killed."),
+            "{result}"
+        );
+
+        // `sleep` was killed too, rather than left running without its shell.
+        let is_running = || {
+            let output = std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", &pid])
+                .output()
+                .unwrap();
+            let stat = String::from_utf8_lossy(&output.stdout);
+            !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+        };
+        for _ in 0..100 {
+            if !is_running() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        panic!("the background process {pid} is still running");
+    }
+
+    #[test]
+    fn test_take_utf8() {
+        let mut bytes = "aé".as_bytes().to_vec();
+        let last_byte = bytes.pop().unwrap();
+        assert_eq!(take_utf8(&mut bytes), "a");
+        bytes.push(last_byte);
+        assert_eq!(take_utf8(&mut bytes), "é");
+        assert!(bytes.is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use gpui::App;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+use crate::terminal_tool::ShellInvocation;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TerminalToolSettings {
+    pub allowed_commands: Option<Vec<String>>,
+    pub timeout_seconds: u64,
+    pub max_output_bytes: usize,
+    pub sandbox: SandboxSettings,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct SandboxSettings {
+    pub enabled: bool,
+    pub allow_network: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TerminalToolSettingsContent {
+    /// The commands the assistant may run, or `null` to allow any command.
+    ///
+    /// An entry allows the commands that start with its words, so `"cargo test"` allows
+    /// `cargo test --workspace`, and `"cargo"` allows every `cargo` subcommand. Variables set
+    /// before the command, and redirections of its output to files, are only allowed when the
+    /// entry spells them out, like `"RUST_LOG=info cargo test"`. Commands that use command
+    /// substitution can't be checked, and are never allowed.
+    ///
+    /// Default: null
+    pub allowed_commands: Option<Vec<String>>,
+    /// How long a command may run before it is killed, in seconds.
+    ///
+    /// Default: 120
+    pub timeout_seconds: Option<u64>,
+    /// The most output of a command that is given to the assistant, in bytes. Longer output
+    /// is cut down to its end.
+    ///
+    /// Default: 16384
+    pub max_output_bytes: Option<usize>,
+    /// Whether to run commands in a sandbox. Only supported on Linux, where it uses
+    /// bubblewrap (`bwrap`).
+    pub sandbox: Option<SandboxSettingsContent>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SandboxSettingsContent {
+    /// Whether to run commands in a sandbox, where they can only write to the project's
+    /// directories and a temporary `/tmp`.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// Whether sandboxed commands may use the network.
+    ///
+    /// Default: false
+    pub allow_network: Option<bool>,
+}
+
+impl TerminalToolSettings {
+    /// Returns whether every one of the command's program invocations is allowed.
+    pub(crate) fn allows(&self, invocations: &[ShellInvocation]) -> bool {
+        let Some(allowed_commands) = self.allowed_commands.as_ref() else {
+            return true;
+        };
+        invocations.iter().all(|invocation| {
+            allowed_commands.iter().any(|allowed_command| {
+                // Leading variable assignments don't match the entry's program, and output
+                // redirections must be part of the words the entry matched.
+                let allowed_len = allowed_command.split_whitespace().count();
+                command_allows(allowed_command, &invocation.words)
+                    && invocation
+                        .output_redirections
+                        .iter()
+                        .all(|&ix| ix < allowed_len)
+            })
+        })
+    }
+}
+
+/// Returns whether an entry of `allowed_commands` allows the invocation with the given words.
+fn command_allows(allowed_command: &str, words: &[impl AsRef<str>]) -> bool {
+    let allowed_words = allowed_command.split_whitespace().collect::<Vec<_>>();
+    !allowed_words.is_empty()
+        && words.len() >= allowed_words.len()
+        && words
+            .iter()
+            .zip(&allowed_words)
+            .all(|(word, allowed_word)| word.as_ref() == *allowed_word)
+}
+
+/// Returns the commands that both lists allow, so that `"cargo"` and `"cargo test"` allow
+/// `"cargo test"`.
+fn intersect_allowed_commands(a: &[String], b: &[String]) -> Vec<String> {
+    let allowed_by = |commands: &[String], command: &String| {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        commands
+            .iter()
+            .any(|allowed_command| command_allows(allowed_command, &words))
+    };
+    let mut commands = a
+        .iter()
+        .filter(|command| allowed_by(b, command))
+        .chain(b.iter().filter(|command| allowed_by(a, command)))
+        .cloned()
+        .collect::<Vec<_>>();
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+impl Settings for TerminalToolSettings {
+    const KEY: Option<&'static str> = Some("terminal_tool");
+
+    type FileContent = TerminalToolSettingsContent;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> Result<Self> {
+        let mut settings: Self = SettingsSources::json_merge_with(
+            [sources.default]
+                .into_iter()
+                .chain(sources.extensions)
+                .chain(sources.user)
+                .chain(sources.release_channel)
+                .chain(sources.server),
+        )?;
+
+        // Like tool permissions, project settings come along with the project's files, so
+        // they can restrict the tool but never loosen its restrictions.
+        for project in sources.project {
+            if let Some(project_commands) = project.allowed_commands.as_ref() {
+                settings.allowed_commands = Some(match settings.allowed_commands.take() {
+                    Some(allowed_commands) => {
+                        intersect_allowed_commands(&allowed_commands, project_commands)
+                    }
+                    None => project_commands.clone(),
+                });
+            }
+            if let Some(timeout_seconds) = project.timeout_seconds {
+                settings.timeout_seconds = settings.timeout_seconds.min(timeout_seconds);
+            }
+            if let Some(max_output_bytes) = project.max_output_bytes {
+                settings.max_output_bytes = settings.max_output_bytes.min(max_output_bytes);
+            }
+            if let Some(sandbox) = project.sandbox.as_ref() {
+                settings.sandbox.enabled |= sandbox.enabled.unwrap_or(false);
+                settings.sandbox.allow_network &= sandbox.allow_network.unwrap_or(true);
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invocations(command: &str) -> Vec<ShellInvocation> {
+        crate::terminal_tool::shell_invocations(command).unwrap()
+    }
+
+    #[test]
+    fn test_allows() {
+        let settings = TerminalToolSettings {
+            allowed_commands: Some(vec!["cargo test".into(), "ls".into()]),
+            timeout_seconds: 120,
+            max_output_bytes: 16384,
+            sandbox: SandboxSettings::default(),
+        };
+        assert!(settings.allows(&invocations("cargo test --workspace")));
+        assert!(settings.allows(&invocations("ls -la; cargo test 2>&1")));
+        assert!(!settings.allows(&invocations("cargo build")));
+        assert!(!settings.allows(&invocations("ls && rm -rf target")));
+
+        let settings = TerminalToolSettings {
+            allowed_commands: None,
+            ..settings
+        };
+        assert!(settings.allows(&invocations("rm -rf target")));
+    }
+
+    #[test]
+    fn test_allows_assignments_and_redirections_only_when_spelled_out() {
+        let settings = TerminalToolSettings {
+            allowed_commands: Some(vec![
+                "git diff".into(),
+                "cargo test".into(),
+                "echo".into(),
+                "RUST_LOG=info cargo test".into(),
+                "ls > files.txt".into(),
+            ]),
+            timeout_seconds: 120,
+            max_output_bytes: 16384,
+            sandbox: SandboxSettings::default(),
+        };
+        assert!(!settings.allows(&invocations("GIT_EXTERNAL_DIFF=/tmp/evil git diff")));
+        assert!(!settings.allows(&invocations("RUSTC_WRAPPER=sh cargo test")));
+        assert!(!settings.allows(&invocations("LD_PRELOAD=/tmp/x.so cargo test")));
+        assert!(settings.allows(&invocations("RUST_LOG=info cargo test --workspace")));
+
+        assert!(!settings.allows(&invocations("ls > ~/.bashrc")));
+        assert!(!settings.allows(&invocations("echo x >> .git/hooks/pre-commit")));
+        assert!(!settings.allows(&invocations("cargo test &>out.txt")));
+        assert!(settings.allows(&invocations("ls > files.txt")));
+        assert!(settings.allows(&invocations("echo '>' 1>&2")));
+    }
+
+    #[test]
+    fn test_intersect_allowed_commands() {
+        let commands = |commands: &[&str]| {
+            commands
+                .iter()
+                .map(|command| command.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            intersect_allowed_commands(
+                &commands(&["cargo", "git status", "ls"]),
+                &commands(&["cargo test", "cargo check", "git", "rm"])
+            ),
+            commands(&["cargo check", "cargo test", "git status"])
+        );
+        assert_eq!(
+            intersect_allowed_commands(&commands(&["ls"]), &commands(&["ls", "ls -la"])),
+            commands(&["ls", "ls -la"])
+        );
+        assert!(intersect_allowed_commands(&commands(&["ls"]), &commands(&["rm"])).is_empty());
+    }
+
+    #[gpui::test]
+    fn test_project_settings_only_restrict_the_tool(cx: &mut App) {
+        let default = TerminalToolSettingsContent {
+            allowed_commands: None,
+            timeout_seconds: Some(120),
+            max_output_bytes: Some(16384),
+            sandbox: Some(SandboxSettingsContent {
+                enabled: Some(false),
+                allow_network: Some(false),
+            }),
+        };
+        let user = TerminalToolSettingsContent {
+            allowed_commands: Some(vec!["cargo".into(), "ls".into()]),
+            sandbox: Some(SandboxSettingsContent {
+                enabled: None,
+                allow_network: Some(true),
+            }),
+            ..Default::default()
+        };
+        let project = TerminalToolSettingsContent {
+            allowed_commands: Some(vec!["cargo test".into(), "rm".into()]),
+            timeout_seconds: Some(600),
+            max_output_bytes: Some(4096),
+            sandbox: Some(SandboxSettingsContent {
+                enabled: Some(true),
+                allow_network: Some(true),
+            }),
+        };
+
+        let settings = TerminalToolSettings::load(
+            SettingsSources {
+                default: &default,
+                extensions: None,
+                user: Some(&user),
+                release_channel: None,
+                server: None,
+                project: &[&project],
+            },
+            cx,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.allowed_commands,
+            Some(vec!["cargo test".to_string()])
+        );
+        assert_eq!(settings.timeout_seconds, 120);
+        assert_eq!(settings.max_output_bytes, 4096);
+        assert!(settings.sandbox.enabled);
+        assert!(settings.sandbox.allow_network);
+    }
+}
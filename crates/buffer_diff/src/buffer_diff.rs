@@ -891,8 +891,9 @@ impl BufferDiff {
             let Some(this) = this.upgrade() else {
                 return;
             };
-            this.update(&mut cx, |this, _| {
-                this.set_state(snapshot, &buffer);
+            this.update(&mut cx, |this, cx| {
+                let changed_range = this.set_state(snapshot, &buffer);
+                cx.emit(BufferDiffEvent::DiffChanged { changed_range });
             })
             .log_err();
             drop(complete_on_drop)
@@ -1,16 +1,12 @@
 use anyhow::{anyhow, Context as _, Result};
 use collections::HashMap;
-use futures::{channel::oneshot, io::BufWriter, select, AsyncRead, AsyncWrite, FutureExt};
+use futures::{channel::oneshot, select, FutureExt, StreamExt as _};
 use gpui::{AppContext as _, AsyncApp, BackgroundExecutor, Task};
 use parking_lot::Mutex;
 use postage::barrier;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{value::RawValue, Value};
-use smol::{
-    channel,
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    process::Child,
-};
+use smol::channel;
 use std::{
     fmt,
     path::PathBuf,
@@ -22,6 +18,8 @@ use std::{
 };
 use util::TryFutureExt;
 
+use crate::transport::Transport;
+
 const JSON_RPC_VERSION: &str = "2.0";
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
@@ -34,6 +32,7 @@ pub const INTERNAL_ERROR: i32 = -32603;
 
 type ResponseHandler = Box<dyn Send + FnOnce(Result<String, Error>)>;
 type NotificationHandler = Box<dyn Send + FnMut(Value, AsyncApp)>;
+type RequestHandler = Box<dyn Send + FnMut(Value, AsyncApp) -> Task<Result<Value>>>;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -48,6 +47,7 @@ pub struct Client {
     outbound_tx: channel::Sender<String>,
     name: Arc<str>,
     notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+    request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
     response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
     #[allow(clippy::type_complexity)]
     #[allow(dead_code)]
@@ -55,7 +55,6 @@ pub struct Client {
     #[allow(dead_code)]
     output_done_rx: Mutex<Option<barrier::Receiver>>,
     executor: BackgroundExecutor,
-    server: Arc<Mutex<Option<Child>>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -79,6 +78,16 @@ struct Request<'a, T> {
     params: T,
 }
 
+#[derive(Deserialize)]
+struct AnyRequest<'a> {
+    #[allow(dead_code)]
+    jsonrpc: &'a str,
+    id: RequestId,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct AnyResponse<'a> {
     jsonrpc: &'a str,
@@ -123,6 +132,22 @@ struct AnyNotification<'a> {
     params: Option<Value>,
 }
 
+#[derive(Serialize)]
+struct OutgoingResponse {
+    jsonrpc: &'static str,
+    id: RequestId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<OutgoingError>,
+}
+
+#[derive(Serialize)]
+struct OutgoingError {
+    code: i32,
+    message: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Error {
     message: String,
@@ -138,63 +163,53 @@ pub struct ModelContextServerBinary {
 impl Client {
     /// Creates a new Client instance for a context server.
     ///
-    /// This function initializes a new Client by spawning a child process for the context server,
-    /// setting up communication channels, and initializing handlers for input/output operations.
-    /// It takes a server ID, binary information, and an async app context as input.
+    /// This function sets up communication channels over the given transport,
+    /// and initializes handlers for input/output operations.
+    /// It takes a server ID, a name for logging, the transport and an async app context as input.
     pub fn new(
         server_id: ContextServerId,
-        binary: ModelContextServerBinary,
+        name: Arc<str>,
+        transport: Arc<dyn Transport>,
         cx: AsyncApp,
     ) -> Result<Self> {
-        log::info!(
-            "starting context server (executable={:?}, args={:?})",
-            binary.executable,
-            &binary.args
-        );
-
-        let mut command = util::command::new_smol_command(&binary.executable);
-        command
-            .args(&binary.args)
-            .envs(binary.env.unwrap_or_default())
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
-
-        let mut server = command.spawn().with_context(|| {
-            format!(
-                "failed to spawn command. (path={:?}, args={:?})",
-                binary.executable, &binary.args
-            )
-        })?;
-
-        let stdin = server.stdin.take().unwrap();
-        let stdout = server.stdout.take().unwrap();
-        let stderr = server.stderr.take().unwrap();
-
         let (outbound_tx, outbound_rx) = channel::unbounded::<String>();
         let (output_done_tx, output_done_rx) = barrier::channel();
 
         let notification_handlers =
             Arc::new(Mutex::new(HashMap::<_, NotificationHandler>::default()));
+        let request_handlers = Arc::new(Mutex::new(HashMap::<_, RequestHandler>::default()));
         let response_handlers =
             Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
 
-        let stdout_input_task = cx.spawn({
+        let receive_input_task = cx.spawn({
             let notification_handlers = notification_handlers.clone();
+            let request_handlers = request_handlers.clone();
             let response_handlers = response_handlers.clone();
+            let outbound_tx = outbound_tx.clone();
+            let transport = transport.clone();
             move |cx| {
-                Self::handle_input(stdout, notification_handlers, response_handlers, cx).log_err()
+                Self::handle_input(
+                    transport,
+                    notification_handlers,
+                    request_handlers,
+                    response_handlers,
+                    outbound_tx,
+                    cx,
+                )
+                .log_err()
             }
         });
-        let stderr_input_task = cx.spawn(|_| Self::handle_stderr(stderr).log_err());
+        let receive_err_task = cx.spawn({
+            let transport = transport.clone();
+            move |_| Self::handle_err(transport).log_err()
+        });
         let input_task = cx.spawn(|_| async move {
-            let (stdout, stderr) = futures::join!(stdout_input_task, stderr_input_task);
-            stdout.or(stderr)
+            let (input, err) = futures::join!(receive_input_task, receive_err_task);
+            input.or(err)
         });
         let output_task = cx.background_spawn({
             Self::handle_output(
-                stdin,
+                transport,
                 outbound_rx,
                 output_done_tx,
                 response_handlers.clone(),
@@ -202,54 +217,84 @@ impl Client {
             .log_err()
         });
 
-        let mut context_server = Self {
+        Ok(Self {
             server_id,
             notification_handlers,
+            request_handlers,
             response_handlers,
-            name: "".into(),
+            name,
             next_id: Default::default(),
             outbound_tx,
             executor: cx.background_executor().clone(),
             io_tasks: Mutex::new(Some((input_task, output_task))),
             output_done_rx: Mutex::new(Some(output_done_rx)),
-            server: Arc::new(Mutex::new(Some(server))),
-        };
-
-        if let Some(name) = binary.executable.file_name() {
-            context_server.name = name.to_string_lossy().into();
-        }
-
-        Ok(context_server)
+        })
     }
 
-    /// Handles input from the server's stdout.
+    /// Handles input from the server.
     ///
-    /// This function continuously reads lines from the provided stdout stream,
-    /// parses them as JSON-RPC responses or notifications, and dispatches them
-    /// to the appropriate handlers. It processes both responses (which are matched
-    /// to pending requests) and notifications (which trigger registered handlers).
-    async fn handle_input<Stdout>(
-        stdout: Stdout,
+    /// This function continuously reads messages from the transport,
+    /// parses them as JSON-RPC requests, responses or notifications, and dispatches them
+    /// to the appropriate handlers. Requests from the server are answered through the
+    /// outbound channel, responses are matched to pending requests, and notifications
+    /// trigger registered handlers.
+    async fn handle_input(
+        transport: Arc<dyn Transport>,
         notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+        request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
+        outbound_tx: channel::Sender<String>,
         cx: AsyncApp,
-    ) -> anyhow::Result<()>
-    where
-        Stdout: AsyncRead + Unpin + Send + 'static,
-    {
-        let mut stdout = BufReader::new(stdout);
-        let mut buffer = String::new();
-
-        loop {
-            buffer.clear();
-            if stdout.read_line(&mut buffer).await? == 0 {
-                return Ok(());
-            }
+    ) -> anyhow::Result<()> {
+        let mut input = transport.receive();
 
-            let content = buffer.trim();
+        while let Some(message) = input.next().await {
+            let content = message.trim();
 
             if !content.is_empty() {
-                if let Ok(response) = serde_json::from_str::<AnyResponse>(content) {
+                // Requests are tried first, as only they have both an id and a method.
+                if let Ok(request) = serde_json::from_str::<AnyRequest>(content) {
+                    let task = request_handlers
+                        .lock()
+                        .get_mut(request.method.as_str())
+                        .map(|handler| handler(request.params.unwrap_or(Value::Null), cx.clone()));
+                    let id = request.id;
+                    let method = request.method;
+                    let outbound_tx = outbound_tx.clone();
+                    cx.spawn(|_| {
+                        async move {
+                            let (result, error) = match task {
+                                Some(task) => match task.await {
+                                    Ok(result) => (Some(result), None),
+                                    Err(error) => (
+                                        None,
+                                        Some(OutgoingError {
+                                            code: INTERNAL_ERROR,
+                                            message: error.to_string(),
+                                        }),
+                                    ),
+                                },
+                                None => (
+                                    None,
+                                    Some(OutgoingError {
+                                        code: METHOD_NOT_FOUND,
+                                        message: format!("method not found: {method}"),
+                                    }),
+                                ),
+                            };
+                            let response = serde_json::to_string(&OutgoingResponse {
+                                jsonrpc: JSON_RPC_VERSION,
+                                id,
+                                result,
+                                error,
+                            })?;
+                            outbound_tx.try_send(response)?;
+                            anyhow::Ok(())
+                        }
+                        .log_err()
+                    })
+                    .detach();
+                } else if let Ok(response) = serde_json::from_str::<AnyResponse>(content) {
                     if let Some(handlers) = response_handlers.lock().as_mut() {
                         if let Some(handler) = handlers.remove(&response.id) {
                             handler(Ok(content.to_string()));
@@ -267,40 +312,30 @@ impl Client {
 
             smol::future::yield_now().await;
         }
+
+        Ok(())
     }
 
-    /// Handles the stderr output from the context server.
+    /// Handles the diagnostic output of the context server, such as its stderr.
     /// Continuously reads and logs any error messages from the server.
-    async fn handle_stderr<Stderr>(stderr: Stderr) -> anyhow::Result<()>
-    where
-        Stderr: AsyncRead + Unpin + Send + 'static,
-    {
-        let mut stderr = BufReader::new(stderr);
-        let mut buffer = String::new();
-
-        loop {
-            buffer.clear();
-            if stderr.read_line(&mut buffer).await? == 0 {
-                return Ok(());
-            }
-            log::warn!("context server stderr: {}", buffer.trim());
+    async fn handle_err(transport: Arc<dyn Transport>) -> anyhow::Result<()> {
+        let mut err = transport.receive_err();
+        while let Some(line) = err.next().await {
+            log::warn!("context server stderr: {}", line.trim());
             smol::future::yield_now().await;
         }
+        Ok(())
     }
 
-    /// Handles the output to the context server's stdin.
+    /// Handles the output to the context server.
     /// This function continuously receives messages from the outbound channel,
-    /// writes them to the server's stdin, and manages the lifecycle of response handlers.
-    async fn handle_output<Stdin>(
-        stdin: Stdin,
+    /// sends them over the transport, and manages the lifecycle of response handlers.
+    async fn handle_output(
+        transport: Arc<dyn Transport>,
         outbound_rx: channel::Receiver<String>,
         output_done_tx: barrier::Sender,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
-    ) -> anyhow::Result<()>
-    where
-        Stdin: AsyncWrite + Unpin + Send + 'static,
-    {
-        let mut stdin = BufWriter::new(stdin);
+    ) -> anyhow::Result<()> {
         let _clear_response_handlers = util::defer({
             let response_handlers = response_handlers.clone();
             move || {
@@ -310,9 +345,7 @@ impl Client {
         while let Ok(message) = outbound_rx.recv().await {
             log::trace!("outgoing message: {}", message);
 
-            stdin.write_all(message.as_bytes()).await?;
-            stdin.write_all(b"\n").await?;
-            stdin.flush().await?;
+            transport.send(message).await?;
         }
         drop(output_done_tx);
         Ok(())
@@ -352,7 +385,7 @@ impl Client {
         let send = self
             .outbound_tx
             .try_send(request)
-            .context("failed to write to context server's stdin");
+            .context("failed to send to context server");
 
         let executor = self.executor.clone();
         let started = Instant::now();
@@ -407,6 +440,16 @@ impl Client {
             .insert(method, Box::new(f));
     }
 
+    /// Registers a handler for requests the server sends to the client.
+    ///
+    /// Requests without a handler are answered with a "method not found" error.
+    pub fn on_request<F>(&self, method: &'static str, f: F)
+    where
+        F: 'static + Send + FnMut(Value, AsyncApp) -> Task<Result<Value>>,
+    {
+        self.request_handlers.lock().insert(method, Box::new(f));
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -416,25 +459,108 @@ impl Client {
     }
 }
 
-impl Drop for Client {
-    fn drop(&mut self) {
-        if let Some(mut server) = self.server.lock().take() {
-            let _ = server.kill();
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::FakeTransport;
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    fn client(transport: &Arc<FakeTransport>, cx: &mut TestAppContext) -> Client {
+        Client::new(
+            ContextServerId("test".into()),
+            "test".into(),
+            transport.clone(),
+            cx.to_async(),
+        )
+        .unwrap()
     }
-}
 
-impl fmt::Display for ContextServerId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    fn message_with_id(transport: &FakeTransport, id: Value) -> Value {
+        transport
+            .sent_messages()
+            .into_iter()
+            .find(|message| message["id"] == id)
+            .unwrap()
     }
-}
 
-impl fmt::Debug for Client {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Context Server Client")
-            .field("id", &self.server_id.0)
-            .field("name", &self.name)
-            .finish_non_exhaustive()
+    #[gpui::test]
+    async fn test_server_requests(cx: &mut TestAppContext) {
+        let transport = Arc::new(FakeTransport::new());
+        let client = client(&transport, cx);
+        client.on_request("echo", |params, _| {
+            Task::ready(Ok(json!({ "echoed": params })))
+        });
+        client.on_request("fail", |_, _| Task::ready(Err(anyhow!("failed"))));
+
+        transport.send_to_client(json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "echo",
+            "params": { "a": 1 },
+        }));
+        transport.send_to_client(json!({ "jsonrpc": "2.0", "id": "b", "method": "unknown" }));
+        transport.send_to_client(json!({ "jsonrpc": "2.0", "id": 8, "method": "fail" }));
+        cx.run_until_parked();
+
+        assert_eq!(transport.sent_messages().len(), 3);
+        assert_eq!(
+            message_with_id(&transport, json!(7)),
+            json!({ "jsonrpc": "2.0", "id": 7, "result": { "echoed": { "a": 1 } } })
+        );
+        assert_eq!(
+            message_with_id(&transport, json!("b")),
+            json!({
+                "jsonrpc": "2.0",
+                "id": "b",
+                "error": { "code": METHOD_NOT_FOUND, "message": "method not found: unknown" },
+            })
+        );
+        assert_eq!(
+            message_with_id(&transport, json!(8)),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 8,
+                "error": { "code": INTERNAL_ERROR, "message": "failed" },
+            })
+        );
+    }
+
+    #[gpui::test]
+    async fn test_responses_are_matched_by_id(cx: &mut TestAppContext) {
+        let transport = Arc::new(FakeTransport::new());
+        let client = Arc::new(client(&transport, cx));
+        let first = cx.executor().spawn({
+            let client = client.clone();
+            async move { client.request::<Value>("first", json!({})).await }
+        });
+        let second = cx.executor().spawn({
+            let client = client.clone();
+            async move { client.request::<Value>("second", json!({})).await }
+        });
+        cx.run_until_parked();
+
+        let sent_messages = transport.sent_messages();
+        let id_of = |method: &str| {
+            sent_messages
+                .iter()
+                .find(|message| message["method"] == method)
+                .unwrap()["id"]
+                .clone()
+        };
+        let (first_id, second_id) = (id_of("first"), id_of("second"));
+        assert_ne!(first_id, second_id);
+
+        // Responses to unknown requests are ignored, and the others may come in any order.
+        transport.send_to_client(json!({ "jsonrpc": "2.0", "id": 99, "result": "unknown" }));
+        transport.send_to_client(json!({ "jsonrpc": "2.0", "id": second_id, "result": "second" }));
+        transport.send_to_client(json!({
+            "jsonrpc": "2.0",
+            "id": first_id,
+            "error": { "code": INTERNAL_ERROR, "message": "first failed" },
+        }));
+
+        assert_eq!(second.await.unwrap(), json!("second"));
+        assert_eq!(first.await.unwrap_err().to_string(), "first failed");
     }
 }
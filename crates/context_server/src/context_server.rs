@@ -4,10 +4,15 @@ mod extension_context_server;
 pub mod manager;
 pub mod protocol;
 mod registry;
+pub mod sampling;
+pub mod transport;
 pub mod types;
 
 use command_palette_hooks::CommandPaletteFilter;
-pub use context_server_settings::{ContextServerSettings, ServerCommand, ServerConfig};
+pub use context_server_settings::{
+    ContextServerSettings, RemoteServer, RemoteTransport, SamplingModel, SamplingSettings,
+    ServerCommand, ServerConfig,
+};
 use gpui::{actions, App};
 
 pub use crate::context_server_tool::ContextServerTool;
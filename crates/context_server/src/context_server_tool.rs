@@ -48,6 +48,10 @@ impl Tool for ContextServerTool {
         }
     }
 
+    fn context_server_id(&self) -> Option<Arc<str>> {
+        Some(self.server_id.clone())
+    }
+
     fn run(
         self: std::sync::Arc<Self>,
         input: serde_json::Value,
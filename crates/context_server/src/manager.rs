@@ -20,17 +20,20 @@ use std::sync::Arc;
 use anyhow::{bail, Result};
 use collections::HashMap;
 use command_palette_hooks::CommandPaletteFilter;
-use gpui::{AsyncApp, Context, Entity, EventEmitter, Subscription, Task, WeakEntity};
+use credentials_provider::CredentialsProvider;
+use gpui::{App, AsyncApp, Context, Entity, EventEmitter, Subscription, Task, WeakEntity};
 use log;
 use parking_lot::RwLock;
 use project::Project;
 use settings::{Settings, SettingsStore};
+use url::Url;
 use util::ResultExt as _;
 
-use crate::{ContextServerSettings, ServerConfig};
+use crate::{ContextServerSettings, RemoteServer, ServerConfig};
 
 use crate::{
     client::{self, Client},
+    transport::{HttpTransport, StdioTransport, Transport},
     types, ContextServerFactoryRegistry, CONTEXT_SERVERS_NAMESPACE,
 };
 
@@ -61,22 +64,60 @@ impl ContextServer {
         self.client.read().clone()
     }
 
-    pub async fn start(self: Arc<Self>, cx: &AsyncApp) -> Result<()> {
+    /// Starts the server, answering its `roots/list` requests with the
+    /// visible worktrees of the given project.
+    pub async fn start(self: Arc<Self>, project: WeakEntity<Project>, cx: &AsyncApp) -> Result<()> {
         log::info!("starting context server {}", self.id);
-        let Some(command) = &self.config.command else {
-            bail!("no command specified for server {}", self.id);
-        };
+        let (name, transport) = self.transport(cx).await?;
+        self.start_with_transport(name, transport, project, cx)
+            .await
+    }
+
+    /// Starts the server over the given transport, such as a fake one in tests.
+    pub async fn start_with_transport(
+        self: Arc<Self>,
+        name: Arc<str>,
+        transport: Arc<dyn Transport>,
+        project: WeakEntity<Project>,
+        cx: &AsyncApp,
+    ) -> Result<()> {
         let client = Client::new(
             client::ContextServerId(self.id.clone()),
-            client::ModelContextServerBinary {
-                executable: Path::new(&command.path).to_path_buf(),
-                args: command.args.clone(),
-                env: command.env.clone(),
-            },
+            name,
+            transport,
             cx.clone(),
         )?;
 
-        let protocol = crate::protocol::ModelContextProtocol::new(client);
+        let mut protocol = crate::protocol::ModelContextProtocol::new(client);
+        protocol.on_list_roots({
+            let project = project.clone();
+            move |cx| {
+                let roots = project.read_with(&cx, |project, cx| {
+                    project
+                        .visible_worktrees(cx)
+                        .filter_map(|worktree| {
+                            let worktree = worktree.read(cx);
+                            Some(types::Root {
+                                uri: Url::from_file_path(worktree.abs_path()).ok()?,
+                                name: Some(worktree.root_name().to_string()),
+                            })
+                        })
+                        .collect()
+                });
+                Task::ready(roots)
+            }
+        });
+        let sampling_enabled = cx.update(|cx| {
+            ContextServerSettings::get_global(cx)
+                .context_server_sampling
+                .enabled
+        })?;
+        if sampling_enabled {
+            let server_id = self.id.clone();
+            protocol.on_create_message(move |request, cx| {
+                crate::sampling::create_message(server_id.clone(), project.clone(), request, cx)
+            });
+        }
         let client_info = types::Implementation {
             name: "Zed".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
@@ -93,6 +134,39 @@ impl ContextServer {
         Ok(())
     }
 
+    /// Creates the transport to the server, spawning it for a command, or
+    /// connecting to it for a remote server.
+    async fn transport(&self, cx: &AsyncApp) -> Result<(Arc<str>, Arc<dyn Transport>)> {
+        if let Some(remote) = &self.config.remote {
+            let credentials_provider = cx.update(|cx| <dyn CredentialsProvider>::global(cx))?;
+            let headers = remote_headers(remote, credentials_provider.as_ref(), cx).await?;
+            let http_client = cx.update(|cx| cx.http_client())?;
+            let transport = HttpTransport::new(
+                &remote.url,
+                remote.transport,
+                headers,
+                http_client,
+                cx.background_executor().clone(),
+            )?;
+            return Ok((remote.url.as_str().into(), Arc::new(transport)));
+        }
+
+        let Some(command) = &self.config.command else {
+            bail!("no command specified for server {}", self.id);
+        };
+        let executable = Path::new(&command.path).to_path_buf();
+        let name = executable
+            .file_name()
+            .map(|name| name.to_string_lossy().into())
+            .unwrap_or_else(|| self.id.clone());
+        let transport = StdioTransport::new(client::ModelContextServerBinary {
+            executable,
+            args: command.args.clone(),
+            env: command.env.clone(),
+        })?;
+        Ok((name, Arc::new(transport)))
+    }
+
     pub fn stop(&self) -> Result<()> {
         let mut client = self.client.write();
         if let Some(protocol) = client.take() {
@@ -102,6 +176,61 @@ impl ContextServer {
     }
 }
 
+/// The username that bearer tokens of remote servers are stored under in the keychain.
+const BEARER_TOKEN_USERNAME: &str = "Bearer";
+
+/// Stores the bearer token sent to the remote server at the given URL in the keychain.
+pub fn write_bearer_token(url: String, token: String, cx: &App) -> Task<Result<()>> {
+    let credentials_provider = <dyn CredentialsProvider>::global(cx);
+    cx.spawn(|cx| async move {
+        credentials_provider
+            .write_credentials(&url, BEARER_TOKEN_USERNAME, token.as_bytes(), &cx)
+            .await
+    })
+}
+
+/// Removes the bearer token of the remote server at the given URL from the keychain.
+pub fn delete_bearer_token(url: String, cx: &App) -> Task<Result<()>> {
+    let credentials_provider = <dyn CredentialsProvider>::global(cx);
+    cx.spawn(|cx| async move { credentials_provider.delete_credentials(&url, &cx).await })
+}
+
+/// Whether the keychain has a bearer token for the remote server at the given URL.
+pub fn has_bearer_token(url: String, cx: &App) -> Task<Result<bool>> {
+    let credentials_provider = <dyn CredentialsProvider>::global(cx);
+    cx.spawn(|cx| async move {
+        Ok(credentials_provider
+            .read_credentials(&url, &cx)
+            .await?
+            .is_some())
+    })
+}
+
+/// The headers sent to a remote server, which carry its bearer token from the
+/// keychain unless they already authorize the requests.
+async fn remote_headers(
+    remote: &RemoteServer,
+    credentials_provider: &dyn CredentialsProvider,
+    cx: &AsyncApp,
+) -> Result<HashMap<String, String>> {
+    let mut headers = remote.headers.clone();
+    let has_authorization = headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("authorization"));
+    if !has_authorization {
+        if let Some((_, token)) = credentials_provider
+            .read_credentials(&remote.url, cx)
+            .await
+            .log_err()
+            .flatten()
+        {
+            let token = String::from_utf8(token)?;
+            headers.insert("Authorization".to_string(), format!("Bearer {token}"));
+        }
+    }
+    Ok(headers)
+}
+
 pub struct ContextServerManager {
     servers: HashMap<Arc<str>, Arc<ContextServer>>,
     project: Entity<Project>,
@@ -126,6 +255,12 @@ impl ContextServerManager {
     ) -> Self {
         let mut this = Self {
             _subscriptions: vec![
+                cx.subscribe(&project, |this, _project, event, _cx| match event {
+                    project::Event::WorktreeAdded(_) | project::Event::WorktreeRemoved(_) => {
+                        this.roots_changed();
+                    }
+                    _ => {}
+                }),
                 cx.observe(&registry, |this, _registry, cx| {
                     this.available_context_servers_changed(cx);
                 }),
@@ -180,6 +315,23 @@ impl ContextServerManager {
             .cloned()
     }
 
+    /// Adds a server that was started outside of the manager, such as one with a fake
+    /// transport, until the settings next change.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn insert_server(&mut self, server: Arc<ContextServer>, cx: &mut Context<Self>) {
+        let server_id = server.id();
+        self.servers.insert(server_id.clone(), server);
+        cx.emit(Event::ServerStarted { server_id });
+    }
+
+    fn roots_changed(&self) {
+        for server in self.servers.values() {
+            if let Some(client) = server.client() {
+                client.notify_roots_list_changed().log_err();
+            }
+        }
+    }
+
     pub fn restart_server(
         &mut self,
         id: &Arc<str>,
@@ -187,11 +339,14 @@ impl ContextServerManager {
     ) -> Task<anyhow::Result<()>> {
         let id = id.clone();
         cx.spawn(|this, mut cx| async move {
-            if let Some(server) = this.update(&mut cx, |this, _cx| this.servers.remove(&id))? {
+            if let Some((server, project)) = this.update(&mut cx, |this, _cx| {
+                let server = this.servers.remove(&id)?;
+                Some((server, this.project.downgrade()))
+            })? {
                 server.stop()?;
                 let config = server.config();
                 let new_server = Arc::new(ContextServer::new(id.clone(), config));
-                new_server.clone().start(&cx).await?;
+                new_server.clone().start(project, &cx).await?;
                 this.update(&mut cx, |this, cx| {
                     this.servers.insert(id.clone(), new_server);
                     cx.emit(Event::ServerStopped {
@@ -234,7 +389,7 @@ impl ContextServerManager {
             registry.read_with(&cx, |registry, _| registry.context_server_factories())?
         {
             let config = desired_servers.entry(id).or_default();
-            if config.command.is_none() {
+            if config.command.is_none() && config.remote.is_none() {
                 if let Some(extension_command) = factory(project.clone(), &cx).await.log_err() {
                     config.command = Some(extension_command);
                 }
@@ -276,7 +431,12 @@ impl ContextServerManager {
         }
 
         for (id, server) in servers_to_start {
-            if server.start(&cx).await.log_err().is_some() {
+            if server
+                .start(project.downgrade(), &cx)
+                .await
+                .log_err()
+                .is_some()
+            {
                 this.update(&mut cx, |_, cx| {
                     cx.emit(Event::ServerStarted { server_id: id })
                 })?;
@@ -286,3 +446,156 @@ impl ContextServerManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RemoteTransport;
+    use futures::{AsyncReadExt as _, FutureExt as _, StreamExt as _};
+    use gpui::TestAppContext;
+    use http_client::{FakeHttpClient, Response};
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    #[derive(Default)]
+    struct FakeCredentialsProvider {
+        credentials: Mutex<HashMap<String, (String, Vec<u8>)>>,
+    }
+
+    impl CredentialsProvider for FakeCredentialsProvider {
+        fn read_credentials<'a>(
+            &'a self,
+            url: &'a str,
+            _: &'a AsyncApp,
+        ) -> Pin<Box<dyn Future<Output = Result<Option<(String, Vec<u8>)>>> + 'a>> {
+            let credentials = self.credentials.lock().get(url).cloned();
+            async move { Ok(credentials) }.boxed_local()
+        }
+
+        fn write_credentials<'a>(
+            &'a self,
+            url: &'a str,
+            username: &'a str,
+            password: &'a [u8],
+            _: &'a AsyncApp,
+        ) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>> {
+            self.credentials
+                .lock()
+                .insert(url.to_string(), (username.to_string(), password.to_vec()));
+            async move { Ok(()) }.boxed_local()
+        }
+
+        fn delete_credentials<'a>(
+            &'a self,
+            url: &'a str,
+            _: &'a AsyncApp,
+        ) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>> {
+            self.credentials.lock().remove(url);
+            async move { Ok(()) }.boxed_local()
+        }
+    }
+
+    #[gpui::test]
+    async fn test_remote_headers(cx: &mut TestAppContext) {
+        let cx = cx.to_async();
+        let url = "https://mcp.example.com/mcp";
+        let credentials_provider = FakeCredentialsProvider::default();
+        let mut remote = RemoteServer {
+            url: url.to_string(),
+            transport: RemoteTransport::StreamableHttp,
+            headers: HashMap::from_iter([("X-Team".to_string(), "zed".to_string())]),
+        };
+
+        // Without a token, only the configured headers are sent.
+        assert_eq!(
+            remote_headers(&remote, &credentials_provider, &cx)
+                .await
+                .unwrap(),
+            remote.headers
+        );
+
+        credentials_provider
+            .write_credentials(url, BEARER_TOKEN_USERNAME, b"secret", &cx)
+            .await
+            .unwrap();
+        assert_eq!(
+            remote_headers(&remote, &credentials_provider, &cx)
+                .await
+                .unwrap(),
+            HashMap::from_iter([
+                ("X-Team".to_string(), "zed".to_string()),
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+            ])
+        );
+
+        // A configured authorization header wins over the keychain.
+        remote
+            .headers
+            .insert("authorization".to_string(), "Token configured".to_string());
+        assert_eq!(
+            remote_headers(&remote, &credentials_provider, &cx)
+                .await
+                .unwrap(),
+            remote.headers
+        );
+    }
+
+    #[gpui::test]
+    async fn test_remote_transport(cx: &mut TestAppContext) {
+        let authorizations = Arc::new(Mutex::new(Vec::new()));
+        let http_client = FakeHttpClient::create({
+            let authorizations = authorizations.clone();
+            move |request| {
+                let authorizations = authorizations.clone();
+                async move {
+                    authorizations.lock().push(
+                        request
+                            .headers()
+                            .get("Authorization")
+                            .map(|value| value.to_str().unwrap().to_string()),
+                    );
+                    let mut body = String::new();
+                    request.into_body().read_to_string(&mut body).await?;
+                    let message: Value = serde_json::from_str(&body)?;
+                    let response = json!({ "jsonrpc": "2.0", "id": message["id"], "result": {} });
+                    Ok(Response::builder()
+                        .header("Content-Type", "application/json")
+                        .body(response.to_string().into())?)
+                }
+            }
+        });
+        cx.update(|cx| cx.set_http_client(http_client));
+
+        let url = "http://test.example/mcp";
+        let server = ContextServer::new(
+            "remote".into(),
+            Arc::new(ServerConfig {
+                remote: Some(RemoteServer {
+                    url: url.to_string(),
+                    transport: RemoteTransport::StreamableHttp,
+                    headers: HashMap::from_iter([(
+                        "Authorization".to_string(),
+                        "Bearer configured".to_string(),
+                    )]),
+                }),
+                ..Default::default()
+            }),
+        );
+        let (name, transport) = server.transport(&cx.to_async()).await.unwrap();
+        assert_eq!(name.as_ref(), url);
+
+        let mut incoming = transport.receive();
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#.into())
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&incoming.next().await.unwrap()).unwrap();
+        assert_eq!(response, json!({ "jsonrpc": "2.0", "id": 1, "result": {} }));
+        assert_eq!(
+            *authorizations.lock(),
+            [Some("Bearer configured".to_string())]
+        );
+    }
+}
@@ -7,17 +7,63 @@
 
 use anyhow::Result;
 use collections::HashMap;
+use gpui::{AsyncApp, Task};
+use url::Url;
 
 use crate::client::Client;
 use crate::types;
 
 pub struct ModelContextProtocol {
     inner: Client,
+    provides_roots: bool,
+    provides_sampling: bool,
 }
 
 impl ModelContextProtocol {
     pub fn new(inner: Client) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            provides_roots: false,
+            provides_sampling: false,
+        }
+    }
+
+    /// Answers the server's `roots/list` requests with the roots returned by `f`,
+    /// and advertises the roots capability when initializing.
+    pub fn on_list_roots<F>(&mut self, mut f: F)
+    where
+        F: 'static + Send + FnMut(AsyncApp) -> Task<Result<Vec<types::Root>>>,
+    {
+        self.provides_roots = true;
+        self.inner
+            .on_request(types::RequestType::ListRoots.as_str(), move |_, cx| {
+                let roots = f(cx.clone());
+                cx.spawn(|_| async move {
+                    let response = types::ListRootsResponse {
+                        roots: roots.await?,
+                        meta: None,
+                    };
+                    Ok(serde_json::to_value(response)?)
+                })
+            });
+    }
+
+    /// Answers the server's `sampling/createMessage` requests with the result of `f`,
+    /// and advertises the sampling capability when initializing.
+    pub fn on_create_message<F>(&mut self, mut f: F)
+    where
+        F: 'static
+            + Send
+            + FnMut(types::CreateMessageRequest, AsyncApp) -> Task<Result<types::CreateMessageResult>>,
+    {
+        self.provides_sampling = true;
+        self.inner.on_request(
+            types::RequestType::CreateMessage.as_str(),
+            move |params, cx| {
+                let result = serde_json::from_value(params).map(|request| f(request, cx.clone()));
+                cx.spawn(|_| async move { Ok(serde_json::to_value(result?.await?)?) })
+            },
+        );
     }
 
     fn supported_protocols() -> Vec<types::ProtocolVersion> {
@@ -34,8 +80,10 @@ impl ModelContextProtocol {
             protocol_version: types::ProtocolVersion(types::LATEST_PROTOCOL_VERSION.to_string()),
             capabilities: types::ClientCapabilities {
                 experimental: None,
-                sampling: None,
-                roots: None,
+                sampling: self.provides_sampling.then(|| serde_json::json!({})),
+                roots: self.provides_roots.then_some(types::RootsCapabilities {
+                    list_changed: Some(true),
+                }),
             },
             meta: None,
             client_info,
@@ -136,6 +184,100 @@ impl InitializedContextServerProtocol {
         Ok(response)
     }
 
+    /// List the MCP resource templates.
+    pub async fn list_resource_templates(&self) -> Result<Vec<types::ResourceTemplate>> {
+        self.check_capability(ServerCapability::Resources)?;
+
+        let response: types::ListResourceTemplatesResponse = self
+            .inner
+            .request(
+                types::RequestType::ListResourceTemplates.as_str(),
+                serde_json::json!({}),
+            )
+            .await?;
+
+        Ok(response.resource_templates)
+    }
+
+    /// Reads the contents of the resource with the given URI.
+    pub async fn read_resource(&self, uri: Url) -> Result<types::ResourcesReadResponse> {
+        self.check_capability(ServerCapability::Resources)?;
+
+        let params = types::ResourcesReadParams { uri, meta: None };
+        let response: types::ResourcesReadResponse = self
+            .inner
+            .request(types::RequestType::ResourcesRead.as_str(), params)
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Whether the server notifies the client when a subscribed resource changes.
+    pub fn supports_resource_subscriptions(&self) -> bool {
+        self.initialize
+            .capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe)
+            .unwrap_or(false)
+    }
+
+    /// Asks the server to send a `notifications/resources/updated` notification
+    /// whenever the resource with the given URI changes.
+    pub async fn subscribe_resource(&self, uri: Url) -> Result<()> {
+        if !self.supports_resource_subscriptions() {
+            return Err(anyhow::anyhow!(
+                "Server does not support resource subscriptions"
+            ));
+        }
+
+        let params = types::ResourcesSubscribeParams { uri, meta: None };
+        self.inner
+            .request::<serde_json::Value>(types::RequestType::ResourcesSubscribe.as_str(), params)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stops the updates requested with [`Self::subscribe_resource`].
+    pub async fn unsubscribe_resource(&self, uri: Url) -> Result<()> {
+        if !self.supports_resource_subscriptions() {
+            return Err(anyhow::anyhow!(
+                "Server does not support resource subscriptions"
+            ));
+        }
+
+        let params = types::ResourcesUnsubscribeParams { uri, meta: None };
+        self.inner
+            .request::<serde_json::Value>(types::RequestType::ResourcesUnsubscribe.as_str(), params)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Calls `f` with the URI of each subscribed resource the server reports as updated.
+    pub fn on_resource_updated<F>(&self, mut f: F)
+    where
+        F: 'static + Send + FnMut(Url, AsyncApp),
+    {
+        self.inner.on_notification(
+            types::NotificationType::ResourcesUpdated.as_str(),
+            move |params, cx| match serde_json::from_value::<types::ResourcesUpdatedParams>(params)
+            {
+                Ok(params) => f(params.uri, cx),
+                Err(error) => log::error!("invalid resource update notification: {error}"),
+            },
+        );
+    }
+
+    /// Tells the server that the roots returned for `roots/list` changed.
+    pub fn notify_roots_list_changed(&self) -> Result<()> {
+        self.inner.notify(
+            types::NotificationType::RootsListChanged.as_str(),
+            serde_json::json!({}),
+        )
+    }
+
     /// Executes a prompt with the given arguments and returns the result.
     pub async fn run_prompt<P: AsRef<str>>(
         &self,
@@ -232,3 +374,118 @@ impl InitializedContextServerProtocol {
         self.inner.request(method, params).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ContextServerId;
+    use crate::transport::FakeTransport;
+    use gpui::TestAppContext;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn methods(transport: &FakeTransport) -> Vec<String> {
+        transport
+            .sent_messages()
+            .into_iter()
+            .filter_map(|message| Some(message["method"].as_str()?.to_string()))
+            .collect()
+    }
+
+    #[gpui::test]
+    async fn test_roots_and_resources(cx: &mut TestAppContext) {
+        let transport = Arc::new(FakeTransport::new());
+        transport.on_request("initialize", |_| {
+            Ok(json!({
+                "protocolVersion": types::LATEST_PROTOCOL_VERSION,
+                "capabilities": { "resources": { "subscribe": true } },
+                "serverInfo": { "name": "test", "version": "1.0" },
+            }))
+        });
+        transport.on_request("resources/read", |params| {
+            Ok(json!({ "contents": [{ "uri": params["uri"], "text": "contents" }] }))
+        });
+        transport.on_request("resources/subscribe", |_| Ok(json!({})));
+        transport.on_request("resources/unsubscribe", |_| Ok(json!({})));
+
+        let client = Client::new(
+            ContextServerId("test".into()),
+            "test".into(),
+            transport.clone(),
+            cx.to_async(),
+        )
+        .unwrap();
+        let mut protocol = ModelContextProtocol::new(client);
+        protocol.on_list_roots(|_| {
+            Task::ready(Ok(vec![types::Root {
+                uri: Url::parse("file:///root").unwrap(),
+                name: Some("root".into()),
+            }]))
+        });
+        let protocol = protocol
+            .initialize(types::Implementation {
+                name: "Zed".into(),
+                version: "1.0".into(),
+            })
+            .await
+            .unwrap();
+        let initialize = &transport.sent_messages()[0];
+        assert_eq!(
+            initialize["params"]["capabilities"]["roots"],
+            json!({ "listChanged": true })
+        );
+        assert!(protocol.supports_resource_subscriptions());
+
+        transport
+            .send_to_client(json!({ "jsonrpc": "2.0", "id": "roots", "method": "roots/list" }));
+        cx.run_until_parked();
+        let response = transport
+            .sent_messages()
+            .into_iter()
+            .find(|message| message["id"] == "roots")
+            .unwrap();
+        assert_eq!(
+            response["result"],
+            json!({ "roots": [{ "uri": "file:///root", "name": "root" }] })
+        );
+
+        let uri = Url::parse("file:///root/notes.txt").unwrap();
+        let response = protocol.read_resource(uri.clone()).await.unwrap();
+        match response.contents.as_slice() {
+            [types::ResourceContentsType::Text(contents)] => {
+                assert_eq!(contents.uri, uri);
+                assert_eq!(contents.text, "contents");
+            }
+            contents => panic!("unexpected contents {contents:?}"),
+        }
+
+        let updated_uris = Arc::new(Mutex::new(Vec::new()));
+        protocol.on_resource_updated({
+            let updated_uris = updated_uris.clone();
+            move |uri, _| updated_uris.lock().push(uri)
+        });
+        protocol.subscribe_resource(uri.clone()).await.unwrap();
+        transport.send_to_client(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/resources/updated",
+            "params": { "uri": uri },
+        }));
+        cx.run_until_parked();
+        assert_eq!(*updated_uris.lock(), [uri.clone()]);
+
+        protocol.unsubscribe_resource(uri.clone()).await.unwrap();
+        assert_eq!(
+            methods(&transport),
+            [
+                "initialize",
+                "notifications/initialized",
+                "resources/read",
+                "resources/subscribe",
+                "resources/unsubscribe",
+            ]
+        );
+        let unsubscribe = transport.sent_messages().pop().unwrap();
+        assert_eq!(unsubscribe["params"], json!({ "uri": uri }));
+    }
+}
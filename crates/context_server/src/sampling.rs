@@ -0,0 +1,598 @@
+//! Answers the `sampling/createMessage` requests of context servers with a
+//! completion from one of the user's language models, once the user approves it.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use context_server_settings::{SamplingModel, SamplingSettings};
+use futures::StreamExt as _;
+use gpui::{AsyncApp, PromptLevel, Task, WeakEntity};
+use language_model::{
+    LanguageModel, LanguageModelCompletionEvent, LanguageModelRegistry, LanguageModelRequest,
+    LanguageModelRequestMessage, MessageContent, Role, StopReason,
+};
+use project::Project;
+use settings::Settings as _;
+use workspace::Workspace;
+
+use crate::{types, ContextServerSettings};
+
+/// The longest a message can be in the approval prompt before it is cut off.
+const MAX_PROMPT_MESSAGE_CHARS: usize = 500;
+
+/// How many bytes of output count as a token, when the model doesn't report its usage.
+const BYTES_PER_TOKEN_GUESS: usize = 4;
+
+/// Requests a completion for a context server, after asking the user to approve it
+/// in the window of the workspace with the server's project.
+pub fn create_message(
+    server_id: Arc<str>,
+    project: WeakEntity<Project>,
+    request: types::CreateMessageRequest,
+    cx: AsyncApp,
+) -> Task<Result<types::CreateMessageResult>> {
+    cx.spawn(|mut cx| async move {
+        let model = cx
+            .update(|cx| {
+                let settings = &ContextServerSettings::get_global(cx).context_server_sampling;
+                let registry = LanguageModelRegistry::read_global(cx);
+                let available_models = registry.available_models(cx).collect::<Vec<_>>();
+                select_model(
+                    request.model_preferences.as_ref(),
+                    settings,
+                    registry.active_model(),
+                    &available_models,
+                )
+            })?
+            .context("no language model is configured")?;
+
+        if !request_approval(&server_id, &project, &request, model.as_ref(), &mut cx).await? {
+            bail!("the user declined the request");
+        }
+
+        complete(model.as_ref(), request, &cx).await
+    })
+}
+
+/// Streams the completion of an approved request from the model.
+async fn complete(
+    model: &dyn LanguageModel,
+    request: types::CreateMessageRequest,
+    cx: &AsyncApp,
+) -> Result<types::CreateMessageResult> {
+    let max_tokens = request.max_tokens as usize;
+    let completion_request = completion_request(request)?;
+    let mut events = model.stream_completion(completion_request, cx).await?;
+    let mut text = String::new();
+    let mut stop_reason = StopReason::EndTurn;
+    let mut reported_output_tokens = None;
+    while let Some(event) = events.next().await {
+        match event? {
+            LanguageModelCompletionEvent::Text(chunk) => text.push_str(&chunk),
+            LanguageModelCompletionEvent::Stop(reason) => stop_reason = reason,
+            LanguageModelCompletionEvent::UsageUpdate(usage) => {
+                reported_output_tokens = Some(usage.output_tokens as usize);
+            }
+            LanguageModelCompletionEvent::ToolUse(_)
+            | LanguageModelCompletionEvent::StartMessage { .. } => {}
+        }
+
+        // Stop streaming once the server's limit is reached, since not every
+        // model is told about it.
+        let output_tokens =
+            reported_output_tokens.unwrap_or_else(|| text.len().div_ceil(BYTES_PER_TOKEN_GUESS));
+        if output_tokens >= max_tokens {
+            if reported_output_tokens.is_none() {
+                let mut len = max_tokens * BYTES_PER_TOKEN_GUESS;
+                if len < text.len() {
+                    while !text.is_char_boundary(len) {
+                        len -= 1;
+                    }
+                    text.truncate(len);
+                }
+            }
+            stop_reason = StopReason::MaxTokens;
+            break;
+        }
+    }
+
+    Ok(types::CreateMessageResult {
+        role: types::Role::Assistant,
+        content: types::MessageContent::Text {
+            text,
+            annotations: None,
+        },
+        model: model.id().0.to_string(),
+        stop_reason: Some(stop_reason_name(stop_reason).to_string()),
+    })
+}
+
+/// The name of a stop reason in the protocol.
+fn stop_reason_name(stop_reason: StopReason) -> &'static str {
+    match stop_reason {
+        StopReason::EndTurn => "endTurn",
+        StopReason::MaxTokens => "maxTokens",
+        StopReason::ToolUse => "toolUse",
+    }
+}
+
+/// Picks the model for a request, from the server's preferences and the user's settings.
+///
+/// The hints are tried in order, then a server that prefers cost or speed over
+/// intelligence gets the fast model, and otherwise the active model is used.
+pub fn select_model(
+    preferences: Option<&types::ModelPreferences>,
+    settings: &SamplingSettings,
+    active_model: Option<Arc<dyn LanguageModel>>,
+    available_models: &[Arc<dyn LanguageModel>],
+) -> Option<Arc<dyn LanguageModel>> {
+    if let Some(preferences) = preferences {
+        let hints = preferences.hints.iter().flatten();
+        for hint in hints.filter_map(|hint| hint.name.as_deref()) {
+            let model = match configured_model_for_hint(hint, settings) {
+                Some(model) => find_model(available_models, model),
+                None => model_for_hint(hint, active_model.as_ref(), available_models),
+            };
+            if model.is_some() {
+                return model;
+            }
+        }
+
+        let intelligence = preferences.intelligence_priority.unwrap_or(0.);
+        let cost_or_speed = preferences
+            .cost_priority
+            .unwrap_or(0.)
+            .max(preferences.speed_priority.unwrap_or(0.));
+        if cost_or_speed > intelligence {
+            if let Some(model) = settings
+                .fast_model
+                .as_ref()
+                .and_then(|model| find_model(available_models, model))
+            {
+                return Some(model);
+            }
+        }
+    }
+
+    active_model
+}
+
+fn configured_model_for_hint<'a>(
+    hint: &str,
+    settings: &'a SamplingSettings,
+) -> Option<&'a SamplingModel> {
+    let hint = hint.to_lowercase();
+    settings
+        .hints
+        .iter()
+        .filter(|(name, _)| hint.contains(&name.to_lowercase()))
+        // The longest name is the most specific.
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, model)| model)
+}
+
+/// The active model when its name contains the hint, or else the first
+/// available model whose name contains it.
+fn model_for_hint(
+    hint: &str,
+    active_model: Option<&Arc<dyn LanguageModel>>,
+    available_models: &[Arc<dyn LanguageModel>],
+) -> Option<Arc<dyn LanguageModel>> {
+    let hint = hint.to_lowercase();
+    let matches = |model: &Arc<dyn LanguageModel>| {
+        model.id().0.to_lowercase().contains(&hint) || model.name().0.to_lowercase().contains(&hint)
+    };
+
+    active_model
+        .filter(|model| matches(model))
+        .or_else(|| available_models.iter().find(|model| matches(model)))
+        .cloned()
+}
+
+fn find_model(
+    available_models: &[Arc<dyn LanguageModel>],
+    model: &SamplingModel,
+) -> Option<Arc<dyn LanguageModel>> {
+    available_models
+        .iter()
+        .find(|candidate| {
+            candidate.provider_id().0.as_ref() == model.provider
+                && candidate.id().0.as_ref() == model.model
+        })
+        .cloned()
+}
+
+/// Shows the request to the user in the window of the workspace with the given
+/// project, and returns whether they allowed it.
+async fn request_approval(
+    server_id: &str,
+    project: &WeakEntity<Project>,
+    request: &types::CreateMessageRequest,
+    model: &dyn LanguageModel,
+    cx: &mut AsyncApp,
+) -> Result<bool> {
+    let window = cx
+        .update(|cx| {
+            cx.windows()
+                .into_iter()
+                .filter_map(|window| window.downcast::<Workspace>())
+                .find(|window| {
+                    window.read(cx).map_or(false, |workspace| {
+                        workspace.project().entity_id() == project.entity_id()
+                    })
+                })
+        })?
+        .context("no workspace window for the context server's project")?;
+
+    let message = format!(
+        "The context server {server_id} wants to request a completion from {}",
+        model.name().0
+    );
+    let detail = approval_detail(request);
+    let answer = window.update(cx, |_, window, cx| {
+        window.prompt(
+            PromptLevel::Info,
+            &message,
+            Some(&detail),
+            &["Allow", "Deny"],
+            cx,
+        )
+    })?;
+
+    Ok(answer.await? == 0)
+}
+
+fn approval_detail(request: &types::CreateMessageRequest) -> String {
+    let mut detail = String::new();
+    if let Some(system_prompt) = &request.system_prompt {
+        detail.push_str(&format!(
+            "System: {}\n\n",
+            util::truncate_and_trailoff(system_prompt, MAX_PROMPT_MESSAGE_CHARS)
+        ));
+    }
+    for message in &request.messages {
+        let role = match message.role {
+            types::Role::User => "User",
+            types::Role::Assistant => "Assistant",
+        };
+        let content = match &message.content {
+            types::MessageContent::Text { text, .. } => {
+                util::truncate_and_trailoff(text, MAX_PROMPT_MESSAGE_CHARS)
+            }
+            types::MessageContent::Image { mime_type, .. } => format!("[{mime_type} image]"),
+            types::MessageContent::Resource { resource, .. } => {
+                format!("[resource {}]", resource.uri)
+            }
+        };
+        detail.push_str(&format!("{role}: {content}\n\n"));
+    }
+    detail.push_str(&format!("At most {} tokens.", request.max_tokens));
+    detail
+}
+
+fn completion_request(request: types::CreateMessageRequest) -> Result<LanguageModelRequest> {
+    let mut messages = Vec::new();
+    if let Some(system_prompt) = request.system_prompt {
+        messages.push(LanguageModelRequestMessage {
+            role: Role::System,
+            content: vec![MessageContent::Text(system_prompt)],
+            cache: false,
+        });
+    }
+    for message in request.messages {
+        let role = match message.role {
+            types::Role::User => Role::User,
+            types::Role::Assistant => Role::Assistant,
+        };
+        let content = match message.content {
+            types::MessageContent::Text { text, .. } => text,
+            types::MessageContent::Resource { resource, .. } => {
+                format!("Resource: {}", resource.uri)
+            }
+            types::MessageContent::Image { .. } => {
+                return Err(anyhow!("images are not supported in sampling requests"));
+            }
+        };
+        messages.push(LanguageModelRequestMessage {
+            role,
+            content: vec![MessageContent::Text(content)],
+            cache: false,
+        });
+    }
+
+    Ok(LanguageModelRequest {
+        messages,
+        tools: Vec::new(),
+        stop: request.stop_sequences.unwrap_or_default(),
+        temperature: request.temperature.map(|temperature| temperature as f32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collections::HashMap;
+    use futures::future::BoxFuture;
+    use futures::stream::BoxStream;
+    use gpui::{App, TestAppContext};
+    use language_model::fake_provider::FakeLanguageModel;
+    use language_model::{
+        LanguageModelId, LanguageModelName, LanguageModelProviderId, LanguageModelProviderName,
+    };
+    use serde_json::json;
+
+    fn model(provider: &str, model: &str) -> SamplingModel {
+        SamplingModel {
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    /// A model that is only ever selected, never asked for a completion.
+    struct TestModel {
+        provider: &'static str,
+        id: &'static str,
+        name: &'static str,
+    }
+
+    impl LanguageModel for TestModel {
+        fn id(&self) -> LanguageModelId {
+            LanguageModelId::from(self.id.to_string())
+        }
+
+        fn name(&self) -> LanguageModelName {
+            LanguageModelName::from(self.name.to_string())
+        }
+
+        fn provider_id(&self) -> LanguageModelProviderId {
+            LanguageModelProviderId::from(self.provider.to_string())
+        }
+
+        fn provider_name(&self) -> LanguageModelProviderName {
+            LanguageModelProviderName::from(self.provider.to_string())
+        }
+
+        fn telemetry_id(&self) -> String {
+            self.id.to_string()
+        }
+
+        fn max_token_count(&self) -> usize {
+            100_000
+        }
+
+        fn count_tokens(
+            &self,
+            _: LanguageModelRequest,
+            _: &App,
+        ) -> BoxFuture<'static, Result<usize>> {
+            unimplemented!()
+        }
+
+        fn stream_completion(
+            &self,
+            _: LanguageModelRequest,
+            _: &AsyncApp,
+        ) -> BoxFuture<'static, Result<BoxStream<'static, Result<LanguageModelCompletionEvent>>>>
+        {
+            unimplemented!()
+        }
+
+        fn use_any_tool(
+            &self,
+            _: LanguageModelRequest,
+            _: String,
+            _: String,
+            _: serde_json::Value,
+            _: &AsyncApp,
+        ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+            unimplemented!()
+        }
+    }
+
+    fn test_model(
+        provider: &'static str,
+        id: &'static str,
+        name: &'static str,
+    ) -> Arc<dyn LanguageModel> {
+        Arc::new(TestModel { provider, id, name })
+    }
+
+    fn preferences(
+        hints: &[&str],
+        cost_priority: f64,
+        intelligence_priority: f64,
+    ) -> types::ModelPreferences {
+        types::ModelPreferences {
+            hints: Some(
+                hints
+                    .iter()
+                    .map(|hint| types::ModelHint {
+                        name: Some(hint.to_string()),
+                    })
+                    .collect(),
+            ),
+            cost_priority: Some(cost_priority),
+            speed_priority: None,
+            intelligence_priority: Some(intelligence_priority),
+        }
+    }
+
+    #[test]
+    fn test_configured_model_for_hint() {
+        let settings = SamplingSettings {
+            hints: HashMap::from_iter([
+                ("claude".to_string(), model("anthropic", "claude-3-5-haiku")),
+                (
+                    "claude-3-sonnet".to_string(),
+                    model("anthropic", "claude-3-5-sonnet"),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            configured_model_for_hint("Claude-3-Sonnet-20240229", &settings),
+            Some(&model("anthropic", "claude-3-5-sonnet"))
+        );
+        assert_eq!(
+            configured_model_for_hint("claude-3-opus", &settings),
+            Some(&model("anthropic", "claude-3-5-haiku"))
+        );
+        assert_eq!(configured_model_for_hint("gpt-4o", &settings), None);
+    }
+
+    #[test]
+    fn test_select_model() {
+        let sonnet = test_model("anthropic", "claude-3-5-sonnet", "Claude 3.5 Sonnet");
+        let haiku = test_model("anthropic", "claude-3-5-haiku", "Claude 3.5 Haiku");
+        let gpt = test_model("openai", "gpt-4o", "GPT-4o");
+        let available_models = [sonnet.clone(), haiku.clone(), gpt.clone()];
+        let settings = SamplingSettings {
+            hints: HashMap::from_iter([(
+                "gemini".to_string(),
+                model("anthropic", "claude-3-5-sonnet"),
+            )]),
+            fast_model: Some(model("anthropic", "claude-3-5-haiku")),
+            ..Default::default()
+        };
+        let select = |preferences: Option<types::ModelPreferences>,
+                      active_model: &Arc<dyn LanguageModel>| {
+            select_model(
+                preferences.as_ref(),
+                &settings,
+                Some(active_model.clone()),
+                &available_models,
+            )
+            .map(|model| model.id().0.to_string())
+        };
+
+        // Without preferences, the active model is used.
+        assert_eq!(select(None, &gpt).as_deref(), Some("gpt-4o"));
+
+        // Hints use the configured model, or else a model whose name contains them,
+        // preferring the active model.
+        assert_eq!(
+            select(Some(preferences(&["gemini-1.5-pro"], 0., 0.)), &gpt).as_deref(),
+            Some("claude-3-5-sonnet")
+        );
+        assert_eq!(
+            select(Some(preferences(&["o1", "haiku"], 0., 0.)), &gpt).as_deref(),
+            Some("claude-3-5-haiku")
+        );
+        assert_eq!(
+            select(Some(preferences(&["claude"], 0., 0.)), &gpt).as_deref(),
+            Some("claude-3-5-sonnet")
+        );
+        assert_eq!(
+            select(Some(preferences(&["claude"], 0., 0.)), &haiku).as_deref(),
+            Some("claude-3-5-haiku")
+        );
+
+        // Without a matching hint, preferring cost over intelligence picks the fast model.
+        assert_eq!(
+            select(Some(preferences(&["llama"], 0.8, 0.2)), &gpt).as_deref(),
+            Some("claude-3-5-haiku")
+        );
+        assert_eq!(
+            select(Some(preferences(&[], 0.2, 0.8)), &gpt).as_deref(),
+            Some("gpt-4o")
+        );
+    }
+
+    #[gpui::test]
+    async fn test_complete(cx: &mut TestAppContext) {
+        let model = Arc::new(FakeLanguageModel::default());
+        let request: types::CreateMessageRequest = serde_json::from_value(json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Summarize the diff" } },
+                { "role": "assistant", "content": { "type": "text", "text": "Which diff?" } },
+                {
+                    "role": "user",
+                    "content": { "type": "resource", "resource": { "uri": "file:///root/a.diff" } },
+                },
+            ],
+            "systemPrompt": "Be brief",
+            "temperature": 0.5,
+            "maxTokens": 100,
+            "stopSequences": ["END"],
+        }))
+        .unwrap();
+
+        let result = cx.spawn({
+            let model = model.clone();
+            |cx| async move { complete(model.as_ref(), request, &cx).await }
+        });
+        cx.run_until_parked();
+
+        let completion_request = model.pending_completions().pop().unwrap();
+        assert_eq!(
+            completion_request
+                .messages
+                .iter()
+                .map(|message| (message.role, message.string_contents()))
+                .collect::<Vec<_>>(),
+            [
+                (Role::System, "Be brief".to_string()),
+                (Role::User, "Summarize the diff".to_string()),
+                (Role::Assistant, "Which diff?".to_string()),
+                (Role::User, "Resource: file:///root/a.diff".to_string()),
+            ]
+        );
+        assert_eq!(completion_request.stop, ["END"]);
+        assert_eq!(completion_request.temperature, Some(0.5));
+
+        model.stream_last_completion_response("Nothing ".to_string());
+        model.stream_last_completion_response("changed.".to_string());
+        model.end_last_completion_stream();
+        let result = result.await.unwrap();
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "Nothing changed." },
+                "model": "fake",
+                "stopReason": "endTurn",
+            })
+        );
+    }
+
+    #[gpui::test]
+    async fn test_complete_stops_at_max_tokens(cx: &mut TestAppContext) {
+        let model = Arc::new(FakeLanguageModel::default());
+        let request: types::CreateMessageRequest = serde_json::from_value(json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Count to ten" } },
+            ],
+            "maxTokens": 3,
+        }))
+        .unwrap();
+
+        let result = cx.spawn({
+            let model = model.clone();
+            |cx| async move { complete(model.as_ref(), request, &cx).await }
+        });
+        cx.run_until_parked();
+
+        model.stream_last_completion_response("one two ".to_string());
+        model.stream_last_completion_response("three four five".to_string());
+        cx.run_until_parked();
+        // The completion is done without the model ending its stream.
+        let result = result.await.unwrap();
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "one two thre" },
+                "model": "fake",
+                "stopReason": "maxTokens",
+            })
+        );
+    }
+
+    #[test]
+    fn test_stop_reason_name() {
+        assert_eq!(stop_reason_name(StopReason::EndTurn), "endTurn");
+        assert_eq!(stop_reason_name(StopReason::MaxTokens), "maxTokens");
+        assert_eq!(stop_reason_name(StopReason::ToolUse), "toolUse");
+    }
+}
@@ -0,0 +1,674 @@
+//! Transports carry the JSON-RPC messages of a [`Client`](crate::client::Client)
+//! to and from a context server.
+//!
+//! Local servers are spawned as a process and talk over its stdio. Remote servers
+//! are reached over HTTP, either with the streamable HTTP transport or the older
+//! transport built on server-sent events.
+
+use std::mem;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use collections::HashMap;
+use futures::channel::oneshot;
+use futures::future::{self, BoxFuture, Shared};
+use futures::stream::{self, BoxStream};
+use futures::{AsyncRead, AsyncReadExt as _, FutureExt as _, StreamExt as _};
+use gpui::{BackgroundExecutor, Task};
+use http_client::{AsyncBody, HttpClient, Method, Request, Response, Url};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+use smol::{
+    channel,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout},
+};
+use util::ResultExt as _;
+
+use crate::client::{ModelContextServerBinary, RequestId, INTERNAL_ERROR};
+use crate::RemoteTransport;
+
+const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+
+pub trait Transport: Send + Sync + 'static {
+    /// Sends a message to the server.
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>>;
+
+    /// The messages the server sends. Only taken once.
+    fn receive(&self) -> BoxStream<'static, String>;
+
+    /// The diagnostic output of the server, which is logged. Only taken once.
+    fn receive_err(&self) -> BoxStream<'static, String> {
+        stream::empty().boxed()
+    }
+}
+
+/// Talks to a context server spawned as a child process, one message per line.
+pub struct StdioTransport {
+    stdin: Arc<smol::lock::Mutex<BufWriter<ChildStdin>>>,
+    stdout: Mutex<Option<ChildStdout>>,
+    stderr: Mutex<Option<ChildStderr>>,
+    server: Mutex<Option<Child>>,
+}
+
+impl StdioTransport {
+    pub fn new(binary: ModelContextServerBinary) -> Result<Self> {
+        log::info!(
+            "starting context server (executable={:?}, args={:?})",
+            binary.executable,
+            &binary.args
+        );
+
+        let mut command = util::command::new_smol_command(&binary.executable);
+        command
+            .args(&binary.args)
+            .envs(binary.env.unwrap_or_default())
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut server = command.spawn().with_context(|| {
+            format!(
+                "failed to spawn command. (path={:?}, args={:?})",
+                binary.executable, &binary.args
+            )
+        })?;
+
+        let stdin = server.stdin.take().unwrap();
+        let stdout = server.stdout.take().unwrap();
+        let stderr = server.stderr.take().unwrap();
+
+        Ok(Self {
+            stdin: Arc::new(smol::lock::Mutex::new(BufWriter::new(stdin))),
+            stdout: Mutex::new(Some(stdout)),
+            stderr: Mutex::new(Some(stderr)),
+            server: Mutex::new(Some(server)),
+        })
+    }
+}
+
+impl Transport for StdioTransport {
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+        let stdin = self.stdin.clone();
+        async move {
+            let mut stdin = stdin.lock().await;
+            stdin.write_all(message.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+            stdin.flush().await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn receive(&self) -> BoxStream<'static, String> {
+        match self.stdout.lock().take() {
+            Some(stdout) => lines(stdout),
+            None => stream::empty().boxed(),
+        }
+    }
+
+    fn receive_err(&self) -> BoxStream<'static, String> {
+        match self.stderr.lock().take() {
+            Some(stderr) => lines(stderr),
+            None => stream::empty().boxed(),
+        }
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        if let Some(mut server) = self.server.lock().take() {
+            let _ = server.kill();
+        }
+    }
+}
+
+fn lines(output: impl AsyncRead + Unpin + Send + 'static) -> BoxStream<'static, String> {
+    BufReader::new(output)
+        .lines()
+        .filter_map(|line| future::ready(line.log_err()))
+        .boxed()
+}
+
+/// Talks to a remote context server over HTTP.
+///
+/// Messages are posted to the server as they are sent, so that a slow reply
+/// does not hold up the messages after it.
+pub struct HttpTransport {
+    connection: Arc<HttpConnection>,
+    executor: BackgroundExecutor,
+    /// The URL to post messages to, which an SSE server names in its first event.
+    endpoint: Shared<oneshot::Receiver<Url>>,
+    incoming_rx: Mutex<Option<channel::Receiver<String>>>,
+    _event_stream: Task<()>,
+}
+
+struct HttpConnection {
+    url: Url,
+    headers: HashMap<String, String>,
+    http_client: Arc<dyn HttpClient>,
+    session_id: Mutex<Option<String>>,
+    incoming_tx: channel::Sender<String>,
+}
+
+impl HttpTransport {
+    pub fn new(
+        url: &str,
+        transport: RemoteTransport,
+        headers: HashMap<String, String>,
+        http_client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Result<Self> {
+        let url = Url::parse(url).with_context(|| format!("invalid context server URL {url}"))?;
+        let (incoming_tx, incoming_rx) = channel::unbounded();
+        let connection = Arc::new(HttpConnection {
+            url: url.clone(),
+            headers,
+            http_client,
+            session_id: Mutex::new(None),
+            incoming_tx,
+        });
+
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let event_stream = match transport {
+            RemoteTransport::StreamableHttp => {
+                endpoint_tx.send(url).ok();
+                Task::ready(())
+            }
+            RemoteTransport::Sse => {
+                let connection = connection.clone();
+                executor.spawn(async move {
+                    connection
+                        .listen(Some(endpoint_tx))
+                        .await
+                        .context("context server event stream failed")
+                        .log_err();
+                })
+            }
+        };
+
+        Ok(Self {
+            connection,
+            executor,
+            endpoint: endpoint_rx.shared(),
+            incoming_rx: Mutex::new(Some(incoming_rx)),
+            _event_stream: event_stream,
+        })
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+        let connection = self.connection.clone();
+        let endpoint = self.endpoint.clone();
+        self.executor
+            .spawn(async move {
+                let result = match endpoint.await {
+                    Ok(endpoint) => connection.post(&endpoint, message.clone()).await,
+                    Err(_) => Err(anyhow!("the server did not name an endpoint")),
+                };
+                if let Err(error) = result {
+                    log::error!("failed to send message to context server: {error:#}");
+                    connection.fail_request(&message, error);
+                }
+            })
+            .detach();
+        future::ready(Ok(())).boxed()
+    }
+
+    fn receive(&self) -> BoxStream<'static, String> {
+        match self.incoming_rx.lock().take() {
+            Some(incoming_rx) => incoming_rx.boxed(),
+            None => stream::empty().boxed(),
+        }
+    }
+}
+
+impl HttpConnection {
+    fn request(&self, method: Method, url: &Url) -> http_client::http::request::Builder {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(url.as_str())
+            .header("Accept", "application/json, text/event-stream");
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(session_id) = self.session_id.lock().as_ref() {
+            request = request.header(SESSION_ID_HEADER, session_id.as_str());
+        }
+        request
+    }
+
+    /// Posts a message, and forwards the messages in the response.
+    async fn post(&self, url: &Url, message: String) -> Result<()> {
+        let request = self
+            .request(Method::POST, url)
+            .header("Content-Type", "application/json")
+            .body(AsyncBody::from(message))?;
+        let response = self.http_client.send(request).await?;
+        let response = check_status(response).await?;
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self.session_id.lock() = Some(session_id.to_string());
+        }
+
+        if is_event_stream(&response) {
+            read_events(response.into_body(), |event| {
+                self.handle_event(event, &mut None);
+            })
+            .await
+        } else {
+            let mut body = String::new();
+            response.into_body().read_to_string(&mut body).await?;
+            self.forward(body.trim());
+            Ok(())
+        }
+    }
+
+    /// Reads the stream of events at the URL, which an SSE server sends its messages on.
+    async fn listen(&self, mut endpoint_tx: Option<oneshot::Sender<Url>>) -> Result<()> {
+        let request = self
+            .request(Method::GET, &self.url)
+            .body(AsyncBody::empty())?;
+        let response = self.http_client.send(request).await?;
+        let response = check_status(response).await?;
+        if !is_event_stream(&response) {
+            bail!("the server did not respond with an event stream");
+        }
+
+        read_events(response.into_body(), |event| {
+            self.handle_event(event, &mut endpoint_tx);
+        })
+        .await
+    }
+
+    fn handle_event(&self, event: SseEvent, endpoint_tx: &mut Option<oneshot::Sender<Url>>) {
+        match event.event.as_deref() {
+            None | Some("message") => self.forward(&event.data),
+            Some("endpoint") => match self.url.join(event.data.trim()) {
+                Ok(endpoint) => {
+                    if let Some(endpoint_tx) = endpoint_tx.take() {
+                        endpoint_tx.send(endpoint).ok();
+                    }
+                }
+                Err(error) => log::error!("invalid context server endpoint: {error}"),
+            },
+            Some(event) => log::debug!("ignoring context server event {event:?}"),
+        }
+    }
+
+    /// Forwards the messages in a body, which may hold a single message or a batch of them.
+    fn forward(&self, body: &str) {
+        if body.is_empty() {
+            return;
+        }
+        if body.starts_with('[') {
+            if let Some(messages) = serde_json::from_str::<Vec<Value>>(body).log_err() {
+                for message in messages {
+                    self.incoming_tx.try_send(message.to_string()).ok();
+                }
+            }
+        } else {
+            self.incoming_tx.try_send(body.to_string()).ok();
+        }
+    }
+
+    /// Answers a request that could not be sent with an error, so that it
+    /// does not wait for a reply until it times out.
+    fn fail_request(&self, message: &str, error: anyhow::Error) {
+        #[derive(Deserialize)]
+        struct AnyRequest {
+            id: RequestId,
+            #[allow(dead_code)]
+            method: String,
+        }
+
+        if let Ok(request) = serde_json::from_str::<AnyRequest>(message) {
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "error": {
+                    "code": INTERNAL_ERROR,
+                    "message": format!("{error:#}"),
+                },
+            });
+            self.incoming_tx.try_send(response.to_string()).ok();
+        }
+    }
+}
+
+async fn check_status(mut response: Response<AsyncBody>) -> Result<Response<AsyncBody>> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await.ok();
+    bail!(
+        "context server responded with status {}: {}",
+        response.status(),
+        body.trim()
+    )
+}
+
+fn is_event_stream(response: &Response<AsyncBody>) -> bool {
+    response
+        .headers()
+        .get("Content-Type")
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |content_type| {
+            content_type.starts_with("text/event-stream")
+        })
+}
+
+#[derive(Debug, PartialEq)]
+struct SseEvent {
+    event: Option<String>,
+    data: String,
+}
+
+/// Reads the server-sent events of a body until it ends.
+async fn read_events(
+    body: impl AsyncRead + Unpin,
+    mut on_event: impl FnMut(SseEvent),
+) -> Result<()> {
+    let mut lines = BufReader::new(body).lines();
+    let mut event = None;
+    let mut data = String::new();
+    while let Some(line) = lines.next().await {
+        let line = line?;
+        if line.is_empty() {
+            if !data.is_empty() {
+                on_event(SseEvent {
+                    event: event.take(),
+                    data: mem::take(&mut data),
+                });
+            }
+            event = None;
+        } else if let Some(value) = line.strip_prefix("event:") {
+            event = Some(value.trim_start().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    Ok(())
+}
+
+/// Talks to an in-memory server in tests, which answers requests with the handlers
+/// registered for their method, and leaves other requests for the test to answer.
+#[cfg(any(test, feature = "test-support"))]
+pub struct FakeTransport {
+    request_handlers: Mutex<HashMap<&'static str, FakeRequestHandler>>,
+    sent_messages: Mutex<Vec<Value>>,
+    incoming_tx: channel::Sender<String>,
+    incoming_rx: Mutex<Option<channel::Receiver<String>>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+type FakeRequestHandler = Box<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+
+#[cfg(any(test, feature = "test-support"))]
+impl FakeTransport {
+    pub fn new() -> Self {
+        let (incoming_tx, incoming_rx) = channel::unbounded();
+        Self {
+            request_handlers: Mutex::default(),
+            sent_messages: Mutex::default(),
+            incoming_tx,
+            incoming_rx: Mutex::new(Some(incoming_rx)),
+        }
+    }
+
+    /// Answers requests for the given method with the result of `handler`, which is
+    /// given their params. Its errors are sent as internal errors.
+    pub fn on_request(
+        &self,
+        method: &'static str,
+        handler: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) {
+        self.request_handlers
+            .lock()
+            .insert(method, Box::new(handler));
+    }
+
+    /// Sends a message to the client, as the server.
+    pub fn send_to_client(&self, message: Value) {
+        self.incoming_tx.try_send(message.to_string()).unwrap();
+    }
+
+    /// The messages the client sent to the server, in order.
+    pub fn sent_messages(&self) -> Vec<Value> {
+        self.sent_messages.lock().clone()
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Default for FakeTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Transport for FakeTransport {
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+        let message: Value = match serde_json::from_str(&message) {
+            Ok(message) => message,
+            Err(error) => return future::ready(Err(error.into())).boxed(),
+        };
+        let response = message["method"].as_str().and_then(|method| {
+            let id = message.get("id")?;
+            let handlers = self.request_handlers.lock();
+            let handler = handlers.get(method)?;
+            let response = match handler(message["params"].clone()) {
+                Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(error) => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": INTERNAL_ERROR, "message": error.to_string() },
+                }),
+            };
+            Some(response)
+        });
+        self.sent_messages.lock().push(message);
+        if let Some(response) = response {
+            self.send_to_client(response);
+        }
+        future::ready(Ok(())).boxed()
+    }
+
+    fn receive(&self) -> BoxStream<'static, String> {
+        match self.incoming_rx.lock().take() {
+            Some(incoming_rx) => incoming_rx.boxed(),
+            None => stream::empty().boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt as _;
+    use gpui::TestAppContext;
+    use http_client::FakeHttpClient;
+
+    #[gpui::test]
+    async fn test_read_events() {
+        let body = "event: endpoint\ndata: /messages?session=1\n\n: a comment\ndata: {\"a\":\ndata: 1}\n\nid: 2\nevent: message\ndata: {}\n\n";
+        let mut events = Vec::new();
+        read_events(body.as_bytes(), |event| events.push(event))
+            .await
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                SseEvent {
+                    event: Some("endpoint".into()),
+                    data: "/messages?session=1".into(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "{\"a\":\n1}".into(),
+                },
+                SseEvent {
+                    event: Some("message".into()),
+                    data: "{}".into(),
+                },
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_streamable_http(cx: &mut TestAppContext) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let http_client = FakeHttpClient::create({
+            let requests = requests.clone();
+            move |request| {
+                let requests = requests.clone();
+                async move {
+                    let session_id = request
+                        .headers()
+                        .get(SESSION_ID_HEADER)
+                        .map(|value| value.to_str().unwrap().to_string());
+                    let authorization = request
+                        .headers()
+                        .get("Authorization")
+                        .map(|value| value.to_str().unwrap().to_string());
+                    let mut body = String::new();
+                    request.into_body().read_to_string(&mut body).await?;
+                    let message: Value = serde_json::from_str(&body)?;
+                    requests.lock().push((session_id, authorization));
+
+                    let response = Response::builder().header(SESSION_ID_HEADER, "session-1");
+                    Ok(match message["method"].as_str() {
+                        Some("initialize") => response
+                            .header("Content-Type", "application/json")
+                            .body(format!(r#"{{"jsonrpc":"2.0","id":{},"result":{{}}}}"#, message["id"]).into())?,
+                        Some("tools/list") => response
+                            .header("Content-Type", "text/event-stream")
+                            .body(format!("event: message\ndata: {{\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}}\n\nevent: message\ndata: {{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{{}}}}\n\n", message["id"]).into())?,
+                        _ => response.status(500).body("unknown method".into())?,
+                    })
+                }
+            }
+        });
+
+        let transport = HttpTransport::new(
+            "http://test.example/mcp",
+            RemoteTransport::StreamableHttp,
+            HashMap::from_iter([("Authorization".to_string(), "Bearer token".to_string())]),
+            http_client,
+            cx.executor(),
+        )
+        .unwrap();
+        let mut incoming = transport.receive();
+
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#.into())
+            .await
+            .unwrap();
+        assert_eq!(
+            incoming.next().await.unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#
+        );
+
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#.into())
+            .await
+            .unwrap();
+        assert_eq!(
+            incoming.next().await.unwrap(),
+            r#"{"jsonrpc":"2.0","method":"notifications/progress"}"#
+        );
+        assert_eq!(
+            incoming.next().await.unwrap(),
+            r#"{"jsonrpc":"2.0","id":2,"result":{}}"#
+        );
+
+        // Requests the server fails are answered with an error.
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":3,"method":"prompts/list"}"#.into())
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&incoming.next().await.unwrap()).unwrap();
+        assert_eq!(response["id"], 3);
+        assert_eq!(response["error"]["code"], INTERNAL_ERROR);
+
+        assert_eq!(
+            *requests.lock(),
+            [
+                (None, Some("Bearer token".to_string())),
+                (
+                    Some("session-1".to_string()),
+                    Some("Bearer token".to_string())
+                ),
+                (
+                    Some("session-1".to_string()),
+                    Some("Bearer token".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_sse(cx: &mut TestAppContext) {
+        let (events_tx, events_rx) = smol::channel::unbounded::<String>();
+        let events_rx = Arc::new(Mutex::new(Some(events_rx)));
+        let http_client = FakeHttpClient::create(move |request| {
+            let events_tx = events_tx.clone();
+            let events_rx = events_rx.clone();
+            async move {
+                if request.method() == Method::GET {
+                    assert_eq!(request.uri().path(), "/sse");
+                    let events = events_rx.lock().take().unwrap();
+                    events_tx.try_send("event: endpoint\ndata: /messages?session=1\n\n".into())?;
+                    let body = events
+                        .map(|event| Ok::<_, std::io::Error>(event.into_bytes()))
+                        .into_async_read();
+                    return Ok(Response::builder()
+                        .header("Content-Type", "text/event-stream")
+                        .body(AsyncBody::from_reader(body))?);
+                }
+
+                assert_eq!(request.uri().path(), "/messages");
+                assert_eq!(request.uri().query(), Some("session=1"));
+                let mut body = String::new();
+                request.into_body().read_to_string(&mut body).await?;
+                let message: Value = serde_json::from_str(&body)?;
+                events_tx.try_send(format!(
+                    "event: message\ndata: {{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{{}}}}\n\n",
+                    message["id"]
+                ))?;
+                Ok(Response::builder().status(202).body(AsyncBody::empty())?)
+            }
+        });
+
+        let transport = HttpTransport::new(
+            "http://test.example/sse",
+            RemoteTransport::Sse,
+            HashMap::default(),
+            http_client,
+            cx.executor(),
+        )
+        .unwrap();
+        let mut incoming = transport.receive();
+
+        transport
+            .send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#.into())
+            .await
+            .unwrap();
+        assert_eq!(
+            incoming.next().await.unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#
+        );
+    }
+}
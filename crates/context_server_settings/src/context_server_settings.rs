@@ -18,6 +18,8 @@ pub struct ServerConfig {
     ///
     /// This will override the command set by an extension.
     pub command: Option<ServerCommand>,
+    /// The remote context server to connect to over HTTP, instead of running a command.
+    pub remote: Option<RemoteServer>,
     /// The settings for this context server.
     ///
     /// Consult the documentation for the context server to see what settings
@@ -40,11 +42,89 @@ pub struct ServerCommand {
     pub env: Option<HashMap<String, String>>,
 }
 
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct RemoteServer {
+    /// The URL of the server's MCP endpoint.
+    ///
+    /// A bearer token for the server is read from the system keychain, where it is
+    /// stored under this URL.
+    pub url: String,
+    /// How to exchange messages with the server.
+    ///
+    /// Default: streamable_http
+    #[serde(default)]
+    pub transport: RemoteTransport,
+    /// Headers to send with every request to the server.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteTransport {
+    /// Post each message to the URL, and read the replies from the response, which
+    /// may be a stream of server-sent events.
+    #[default]
+    StreamableHttp,
+    /// Read messages from a stream of server-sent events at the URL, and post
+    /// messages to the endpoint the stream names. This is the transport of older servers.
+    Sse,
+}
+
 #[derive(Deserialize, Serialize, Default, Clone, PartialEq, Eq, JsonSchema, Debug)]
 pub struct ContextServerSettings {
     /// Settings for context servers used in the Assistant.
     #[serde(default)]
     pub context_servers: HashMap<Arc<str>, ServerConfig>,
+    /// Settings for the completions context servers request from the language model.
+    #[serde(default)]
+    pub context_server_sampling: SamplingSettings,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct SamplingSettings {
+    /// Whether context servers may request completions from the language model.
+    /// Every request is shown to you for approval first.
+    ///
+    /// Default: true
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// The models to use for the model names context servers hint at, by name.
+    ///
+    /// A hint uses the model of the longest name it contains, which is the most
+    /// specific. Hints without a model here use the first available model whose
+    /// name contains the hint.
+    ///
+    /// Default: {}
+    #[serde(default)]
+    pub hints: HashMap<String, SamplingModel>,
+    /// The model to use when a context server prefers cost or speed over intelligence.
+    ///
+    /// Default: the active model
+    #[serde(default)]
+    pub fast_model: Option<SamplingModel>,
+}
+
+impl Default for SamplingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hints: HashMap::default(),
+            fast_model: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct SamplingModel {
+    /// The provider of the model, such as `anthropic` or `ollama`.
+    pub provider: String,
+    /// The id of the model.
+    pub model: String,
 }
 
 impl Settings for ContextServerSettings {
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use editor::{Editor, MultiBuffer};
+use gpui::{
+    actions, App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable,
+    SharedString, Task, WeakEntity, Window,
+};
+use indexed_docs::{FullTextMatch, IndexedDocsRegistry, ProviderId};
+use markdown_preview::markdown_preview_view::{MarkdownPreviewMode, MarkdownPreviewView};
+use picker::{Picker, PickerDelegate};
+use ui::{prelude::*, ListItem, ListItemSpacing};
+use util::ResultExt as _;
+use workspace::{ModalView, Workspace};
+
+actions!(docs_search, [Toggle]);
+
+/// The most matches we show in the picker.
+const MATCH_LIMIT: usize = 50;
+
+pub fn init(cx: &mut App) {
+    cx.observe_new(DocsSearch::register).detach();
+}
+
+/// A modal that searches the contents of the indexed docs, and opens the chosen item
+/// in a Markdown preview.
+pub struct DocsSearch {
+    picker: Entity<Picker<DocsSearchDelegate>>,
+}
+
+impl DocsSearch {
+    fn register(
+        workspace: &mut Workspace,
+        _window: Option<&mut Window>,
+        _: &mut Context<Workspace>,
+    ) {
+        workspace.register_action(|workspace, _: &Toggle, window, cx| {
+            let workspace_handle = workspace.weak_handle();
+            workspace.toggle_modal(window, cx, move |window, cx| {
+                DocsSearch::new(workspace_handle, window, cx)
+            });
+        });
+    }
+
+    fn new(workspace: WeakEntity<Workspace>, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let delegate = DocsSearchDelegate {
+            docs_search: cx.entity().downgrade(),
+            workspace,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new(|cx| Picker::uniform_list(delegate, window, cx));
+        Self { picker }
+    }
+}
+
+impl Render for DocsSearch {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex().w(rems(40.)).child(self.picker.clone())
+    }
+}
+
+impl Focusable for DocsSearch {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for DocsSearch {}
+impl ModalView for DocsSearch {}
+
+pub struct DocsSearchDelegate {
+    docs_search: WeakEntity<DocsSearch>,
+    workspace: WeakEntity<Workspace>,
+    matches: Vec<(ProviderId, FullTextMatch)>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for DocsSearchDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _window: &mut Window, _cx: &mut App) -> Arc<str> {
+        "Search indexed docs…".into()
+    }
+
+    fn no_matches_text(&self, _window: &mut Window, cx: &mut App) -> SharedString {
+        if IndexedDocsRegistry::global(cx).list_providers().is_empty() {
+            "No docs have been indexed yet. Index a package with /docs in the assistant.".into()
+        } else {
+            "No matching docs.".into()
+        }
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) -> Task<()> {
+        let search = IndexedDocsRegistry::global(cx).search_full_text(query, MATCH_LIMIT);
+        cx.spawn_in(window, |this, mut cx| async move {
+            let matches = search.await;
+            this.update(&mut cx, |this, cx| {
+                let delegate = &mut this.delegate;
+                delegate.matches = matches;
+                delegate.selected_index = 0;
+                cx.notify();
+            })
+            .log_err();
+        })
+    }
+
+    fn confirm(&mut self, _: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        if let Some((provider, mat)) = self.matches.get(self.selected_index) {
+            let provider = provider.clone();
+            let key = mat.key.clone();
+            self.workspace
+                .update(cx, |workspace, cx| {
+                    open_docs(provider, key, workspace, window, cx);
+                })
+                .log_err();
+        }
+        self.dismissed(window, cx);
+    }
+
+    fn dismissed(&mut self, _: &mut Window, cx: &mut Context<Picker<Self>>) {
+        self.docs_search
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut Window,
+        _: &mut Context<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let (provider, mat) = self.matches.get(ix)?;
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .toggle_state(selected)
+                .child(
+                    v_flex()
+                        .child(
+                            h_flex()
+                                .gap_2()
+                                .child(Label::new(mat.key.clone()).single_line())
+                                .child(
+                                    Label::new(provider.to_string())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                ),
+                        )
+                        .when(!mat.snippet.is_empty(), |this| {
+                            this.child(
+                                Label::new(mat.snippet.clone())
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted)
+                                    .single_line()
+                                    .text_ellipsis(),
+                            )
+                        }),
+                ),
+        )
+    }
+}
+
+/// Opens the docs stored under the given key in a Markdown preview.
+fn open_docs(
+    provider: ProviderId,
+    key: String,
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let Some(store) = IndexedDocsRegistry::global(cx).get_provider_store(provider) else {
+        return;
+    };
+    let markdown = workspace
+        .app_state()
+        .languages
+        .language_for_name("Markdown");
+
+    workspace
+        .with_local_workspace(window, cx, move |_, window, cx| {
+            cx.spawn_in(window, |workspace, mut cx| async move {
+                let markdown = markdown.await.log_err();
+                let docs = store.load(key.clone()).await?;
+                workspace.update_in(&mut cx, |workspace, window, cx| {
+                    let project = workspace.project().clone();
+                    let buffer = project.update(cx, |project, cx| {
+                        project.create_local_buffer("", markdown, cx)
+                    });
+                    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, docs.0)], None, cx));
+                    let language_registry = project.read(cx).languages().clone();
+
+                    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx));
+                    let editor = cx
+                        .new(|cx| Editor::for_multibuffer(buffer, Some(project), true, window, cx));
+                    let markdown_preview = MarkdownPreviewView::new(
+                        MarkdownPreviewMode::Default,
+                        editor,
+                        workspace.weak_handle(),
+                        language_registry,
+                        Some(SharedString::from(key)),
+                        window,
+                        cx,
+                    );
+                    workspace.add_item_to_active_pane(
+                        Box::new(markdown_preview),
+                        None,
+                        true,
+                        window,
+                        cx,
+                    );
+                })
+            })
+            .detach_and_log_err(cx);
+        })
+        .detach();
+}
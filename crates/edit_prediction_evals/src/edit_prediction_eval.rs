@@ -0,0 +1,300 @@
+//! Evaluates edit predictions offline, by replaying edit predictions recorded by `zeta`
+//! against a local model or an edit prediction provider, and scoring the predicted edits
+//! against the recorded ones.
+//!
+//! The examples are JSON Lines, each line holding the `input_events`, `input_excerpt` and
+//! `output_excerpt` of a recorded prediction, along with an optional `id` and `rating`:
+//!
+//! ```sh
+//! cargo run -p edit_prediction_evals -- --examples examples.jsonl \
+//!     zeta-model --api-url http://localhost:8000/v1 --model zed-industries/zeta
+//! cargo run -p edit_prediction_evals -- --examples examples.jsonl fim
+//! ```
+//!
+//! The report is written to `report.json` and `report.md` in the output directory.
+
+mod example;
+mod metrics;
+mod predictor;
+
+use anyhow::{Context as _, Result};
+use clap::Parser;
+use example::{output_editable_region, EditHistory, Excerpt, Rating, RecordedExample};
+use fim::FimCompletionProvider;
+use gpui::{AppContext as _, AsyncApp, Entity};
+use language::language_settings::all_language_settings;
+use metrics::{markdown_report, ExampleOutcome, Score, Summary};
+use predictor::{predict_with_provider, ZetaModelPredictor};
+use reqwest_client::ReqwestClient;
+use serde::Serialize;
+use settings::SettingsStore;
+use std::{
+    fs,
+    path::PathBuf,
+    process::exit,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(clap::Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// The JSON Lines file of recorded examples.
+    #[arg(long)]
+    examples: PathBuf,
+    /// The directory to write the report to.
+    #[arg(long, default_value = "target/edit_prediction_eval")]
+    output: PathBuf,
+    /// Evaluates at most this many examples.
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Also evaluates examples whose recorded prediction was rated negatively, whose output
+    /// isn't a good ground truth.
+    #[arg(long)]
+    include_negative: bool,
+    /// How long to wait for each prediction, in seconds.
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    #[command(subcommand)]
+    predictor: PredictorArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum PredictorArgs {
+    /// A model trained like `zeta`, served by an OpenAI-compatible API.
+    ZetaModel {
+        /// The URL that `/completions` is appended to, such as `http://localhost:8000/v1`.
+        #[arg(long)]
+        api_url: String,
+        #[arg(long)]
+        model: String,
+        /// The environment variable holding the API's key, if it needs one.
+        #[arg(long)]
+        api_key_env: Option<String>,
+        #[arg(long, default_value_t = 2048)]
+        max_tokens: u32,
+    },
+    /// The fill-in-the-middle provider, configured by the `edit_predictions.fim` settings.
+    Fim {
+        /// The settings file to read, instead of the user's.
+        #[arg(long)]
+        settings: Option<PathBuf>,
+    },
+}
+
+enum Predictor {
+    ZetaModel(ZetaModelPredictor),
+    Fim(Entity<FimCompletionProvider>),
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    predictor: &'a str,
+    summary: &'a Summary,
+    outcomes: &'a [ExampleOutcome],
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    env_logger::init();
+
+    gpui::Application::headless().run(move |cx| {
+        let http_client = Arc::new(ReqwestClient::user_agent("Zed edit prediction evals").unwrap());
+        cx.set_http_client(http_client);
+
+        let mut store = SettingsStore::new(cx);
+        store
+            .set_default_settings(settings::default_settings().as_ref(), cx)
+            .unwrap();
+        cx.set_global(store);
+        language::init(cx);
+
+        cx.spawn(|mut cx| async move {
+            if let Err(err) = run_evaluation(cli, &mut cx).await {
+                eprintln!("Error: {:?}", err);
+                exit(1);
+            }
+            exit(0);
+        })
+        .detach();
+    });
+
+    Ok(())
+}
+
+async fn run_evaluation(cli: Cli, cx: &mut AsyncApp) -> Result<()> {
+    let examples = fs::read_to_string(&cli.examples)
+        .with_context(|| format!("failed to read {:?}", cli.examples))?;
+    let examples = RecordedExample::parse_jsonl(&examples)?
+        .into_iter()
+        .filter(|example| cli.include_negative || example.rating != Some(Rating::Negative))
+        .take(cli.limit.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+    let timeout = Duration::from_secs(cli.timeout);
+
+    let (predictor, predictor_name) = match cli.predictor {
+        PredictorArgs::ZetaModel {
+            api_url,
+            model,
+            api_key_env,
+            max_tokens,
+        } => {
+            let api_key = api_key_env
+                .map(|name| std::env::var(&name).with_context(|| format!("{name} isn't set")))
+                .transpose()?;
+            let name = format!("{model} ({api_url})");
+            let predictor = ZetaModelPredictor {
+                http_client: cx.update(|cx| cx.http_client())?,
+                api_url,
+                api_key,
+                model,
+                max_tokens,
+            };
+            (Predictor::ZetaModel(predictor), name)
+        }
+        PredictorArgs::Fim { settings } => {
+            let settings_path = settings.unwrap_or_else(|| paths::settings_file().clone());
+            let user_settings = if settings_path.exists() {
+                fs::read_to_string(&settings_path)
+                    .with_context(|| format!("failed to read {settings_path:?}"))?
+            } else {
+                "{}".to_string()
+            };
+            cx.update(|cx| {
+                cx.update_global::<SettingsStore, _>(|store, cx| {
+                    store.set_user_settings(&user_settings, cx).map(|_| ())
+                })
+            })??;
+
+            let (provider, model) = cx.update(|cx| {
+                let model = all_language_settings(None, cx)
+                    .edit_predictions
+                    .fim
+                    .model
+                    .clone();
+                let http_client = cx.http_client();
+                let provider = cx.new(|_| FimCompletionProvider::new(http_client));
+                (provider, model)
+            })?;
+            (Predictor::Fim(provider), format!("fim: {model}"))
+        }
+    };
+
+    eprintln!(
+        "Evaluating {} examples with {predictor_name}...",
+        examples.len()
+    );
+    let started_at = Instant::now();
+    let mut outcomes = Vec::new();
+    for (ix, example) in examples.iter().enumerate() {
+        let id = example.id.clone().unwrap_or_else(|| (ix + 1).to_string());
+        let outcome = evaluate_example(id, example, &predictor, timeout, cx).await;
+        match (&outcome.score, &outcome.error) {
+            (Some(score), _) => eprintln!(
+                "[{}/{}] {}: exact match: {}, edit distance: {} (baseline {})",
+                ix + 1,
+                examples.len(),
+                outcome.id,
+                score.exact_match,
+                score.edit_distance,
+                score.baseline_edit_distance
+            ),
+            (None, error) => eprintln!(
+                "[{}/{}] {}: error: {}",
+                ix + 1,
+                examples.len(),
+                outcome.id,
+                error.as_deref().unwrap_or_default()
+            ),
+        }
+        outcomes.push(outcome);
+    }
+
+    let summary = Summary::new(&outcomes);
+    fs::create_dir_all(&cli.output)?;
+    let report = Report {
+        predictor: &predictor_name,
+        summary: &summary,
+        outcomes: &outcomes,
+    };
+    fs::write(
+        cli.output.join("report.json"),
+        serde_json::to_string_pretty(&report)?,
+    )?;
+    fs::write(
+        cli.output.join("report.md"),
+        markdown_report(&predictor_name, &summary, &outcomes, started_at.elapsed()),
+    )?;
+
+    eprintln!(
+        "Exact matches: {}/{} ({:.1}%), mean edit distance: {:.1} (baseline {:.1}), errors: {}",
+        summary.exact_matches,
+        summary.examples - summary.errors,
+        summary.exact_match_rate() * 100.,
+        summary.mean_edit_distance,
+        summary.mean_baseline_edit_distance,
+        summary.errors
+    );
+    eprintln!("Wrote the report to {:?}", cli.output);
+    Ok(())
+}
+
+async fn evaluate_example(
+    id: String,
+    example: &RecordedExample,
+    predictor: &Predictor,
+    timeout: Duration,
+    cx: &mut AsyncApp,
+) -> ExampleOutcome {
+    let mut outcome = ExampleOutcome {
+        id,
+        path: String::new(),
+        replayed_edits: 0,
+        skipped_hunks: 0,
+        latency_ms: None,
+        predicted: None,
+        expected: String::new(),
+        score: None,
+        error: None,
+    };
+
+    let prediction = async {
+        let excerpt = Excerpt::parse(&example.input_excerpt)?;
+        outcome.path = excerpt.path.clone();
+        outcome.expected = output_editable_region(&example.output_excerpt)
+            .context("failed to read the recorded output")?;
+        let history = EditHistory::for_excerpt(&example.input_events, &excerpt);
+        outcome.replayed_edits = history.edits.len();
+        outcome.skipped_hunks = history.skipped_hunks;
+
+        let started_at = Instant::now();
+        let predicted = match predictor {
+            Predictor::ZetaModel(predictor) => {
+                smol::future::or(predictor.predict(example), async {
+                    smol::Timer::after(timeout).await;
+                    Err(anyhow::anyhow!("timed out after {timeout:?}"))
+                })
+                .await?
+            }
+            Predictor::Fim(provider) => {
+                predict_with_provider(provider, &excerpt, &history, timeout, cx).await?
+            }
+        };
+        outcome.latency_ms = Some(started_at.elapsed().as_millis());
+        anyhow::Ok((excerpt, predicted))
+    }
+    .await;
+
+    match prediction {
+        Ok((excerpt, predicted)) => {
+            outcome.score = Some(Score::new(
+                excerpt.editable_text(),
+                &predicted,
+                &outcome.expected,
+            ));
+            outcome.predicted = Some(predicted);
+        }
+        Err(error) => outcome.error = Some(format!("{error:#}")),
+    }
+    outcome
+}
@@ -0,0 +1,333 @@
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+pub const CURSOR_MARKER: &str = "<|user_cursor_is_here|>";
+pub const START_OF_FILE_MARKER: &str = "<|start_of_file|>";
+pub const EDITABLE_REGION_START_MARKER: &str = "<|editable_region_start|>";
+pub const EDITABLE_REGION_END_MARKER: &str = "<|editable_region_end|>";
+
+/// An edit prediction recorded by `zeta`, in the shape of its "Edit Prediction Rated" events.
+/// The output excerpt of a prediction that was rated positively is the ground truth its inputs
+/// are scored against.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedExample {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub input_events: String,
+    pub input_excerpt: String,
+    #[serde(default)]
+    pub input_outline: Option<String>,
+    pub output_excerpt: String,
+    #[serde(default)]
+    pub rating: Option<Rating>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Rating {
+    Positive,
+    Negative,
+}
+
+impl RecordedExample {
+    /// Parses examples from JSON Lines, skipping blank lines.
+    pub fn parse_jsonl(text: &str) -> Result<Vec<Self>> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(ix, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("invalid example on line {}", ix + 1))
+            })
+            .collect()
+    }
+}
+
+/// The file excerpt a prediction was requested for, without `zeta`'s markers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Excerpt {
+    pub path: String,
+    pub text: String,
+    pub editable_range: Range<usize>,
+    pub cursor_offset: usize,
+}
+
+impl Excerpt {
+    pub fn parse(input_excerpt: &str) -> Result<Self> {
+        let (path, rest) = input_excerpt
+            .strip_prefix("```")
+            .and_then(|rest| rest.split_once('\n'))
+            .ok_or_else(|| anyhow!("the excerpt doesn't start with a code fence"))?;
+        let rest = rest
+            .strip_prefix(START_OF_FILE_MARKER)
+            .and_then(|rest| rest.strip_prefix('\n'))
+            .unwrap_or(rest);
+        let rest = rest
+            .strip_suffix("\n```")
+            .ok_or_else(|| anyhow!("the excerpt doesn't end with a code fence"))?;
+
+        let (before, rest) = rest
+            .split_once(&format!("{EDITABLE_REGION_START_MARKER}\n"))
+            .ok_or_else(|| anyhow!("the excerpt has no editable region"))?;
+        let (region, after) = rest
+            .split_once(&format!("\n{EDITABLE_REGION_END_MARKER}"))
+            .ok_or_else(|| anyhow!("the excerpt's editable region is never closed"))?;
+        let cursor_in_region = region
+            .find(CURSOR_MARKER)
+            .ok_or_else(|| anyhow!("the excerpt has no cursor"))?;
+        let region = region.replacen(CURSOR_MARKER, "", 1);
+
+        let editable_range = before.len()..before.len() + region.len();
+        Ok(Self {
+            path: path.trim().to_string(),
+            text: format!("{before}{region}{after}"),
+            cursor_offset: editable_range.start + cursor_in_region,
+            editable_range,
+        })
+    }
+
+    pub fn editable_text(&self) -> &str {
+        &self.text[self.editable_range.clone()]
+    }
+}
+
+/// Returns the editable region of a model's output, in the same way `zeta` reads it.
+pub fn output_editable_region(output_excerpt: &str) -> Result<String> {
+    let content = output_excerpt.replace(CURSOR_MARKER, "");
+    let start = content
+        .find(EDITABLE_REGION_START_MARKER)
+        .ok_or_else(|| anyhow!("the output has no editable region"))?;
+    let content = &content[start..];
+    let newline_ix = content
+        .find('\n')
+        .ok_or_else(|| anyhow!("could not find newline"))?;
+    let content = &content[newline_ix + 1..];
+    let end = content
+        .rfind(&format!("\n{EDITABLE_REGION_END_MARKER}"))
+        .ok_or_else(|| anyhow!("the output's editable region is never closed"))?;
+    Ok(content[..end].to_string())
+}
+
+/// How the excerpt's text was edited to reach its recorded state.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EditHistory {
+    /// The text before the edits.
+    pub initial_text: String,
+    /// The edits, in the order they were made, each applying to the text the previous one left.
+    pub edits: Vec<(Range<usize>, String)>,
+    /// How many of the recorded hunks couldn't be located in the excerpt.
+    pub skipped_hunks: usize,
+}
+
+impl EditHistory {
+    /// Reconstructs the edits the recorded events made to the excerpt's file, by undoing the
+    /// hunks of their diffs from the last one to the first. Hunks that touch parts of the file
+    /// outside of the excerpt can't be undone, and are left out of the history.
+    pub fn for_excerpt(input_events: &str, excerpt: &Excerpt) -> Self {
+        let mut text = excerpt.text.clone();
+        let mut undone_edits = Vec::new();
+        let mut skipped_hunks = 0;
+
+        for (path, diff) in parse_edit_events(input_events).into_iter().rev() {
+            if !paths_match(&excerpt.path, &path) {
+                continue;
+            }
+            for mut hunk in parse_hunks(&diff).into_iter().rev() {
+                let mut position = text.find(&hunk.new_text);
+                // The excerpt ends at the end of a line, without its newline.
+                if position.is_none()
+                    && hunk.new_text.ends_with('\n')
+                    && hunk.old_text.ends_with('\n')
+                {
+                    hunk.new_text.pop();
+                    hunk.old_text.pop();
+                    position = text.find(&hunk.new_text);
+                }
+                let Some(position) = position.filter(|_| !hunk.new_text.is_empty()) else {
+                    skipped_hunks += 1;
+                    continue;
+                };
+                text.replace_range(position..position + hunk.new_text.len(), &hunk.old_text);
+                undone_edits.push((position, hunk.old_text, hunk.new_text));
+            }
+        }
+
+        let edits = undone_edits
+            .into_iter()
+            .rev()
+            .filter_map(|(position, old_text, new_text)| {
+                let prefix_len = common_prefix_len(&old_text, &new_text);
+                let suffix_len =
+                    common_suffix_len(&old_text[prefix_len..], &new_text[prefix_len..]);
+                if old_text.len() == prefix_len && new_text.len() == prefix_len {
+                    return None;
+                }
+                let range = position + prefix_len..position + old_text.len() - suffix_len;
+                let new_text = new_text[prefix_len..new_text.len() - suffix_len].to_string();
+                Some((range, new_text))
+            })
+            .collect();
+
+        Self {
+            initial_text: text,
+            edits,
+            skipped_hunks,
+        }
+    }
+}
+
+/// Splits the events `zeta` sends with a prediction into the paths and diffs of the edits.
+fn parse_edit_events(input_events: &str) -> Vec<(String, String)> {
+    const EDIT_PREFIX: &str = "User edited ";
+    const DIFF_START: &str = ":\n```diff\n";
+
+    let mut events = Vec::new();
+    let mut rest = input_events;
+    while let Some(start) = rest.find(EDIT_PREFIX) {
+        rest = &rest[start + EDIT_PREFIX.len()..];
+        let Some((path, after_path)) = rest.split_once(DIFF_START) else {
+            break;
+        };
+        // The diff ends at the closing fence that's followed by the next event, or by nothing.
+        let mut end = None;
+        for (ix, _) in after_path.match_indices("\n```") {
+            let following = &after_path[ix + 4..];
+            if following.is_empty() || following.starts_with("\n\nUser ") {
+                end = Some(ix);
+                break;
+            }
+        }
+        let Some(end) = end else {
+            break;
+        };
+        events.push((unquote_path(path), after_path[..end].to_string()));
+        rest = &after_path[end..];
+    }
+    events
+}
+
+/// Paths are written with `Debug`, which quotes them.
+fn unquote_path(path: &str) -> String {
+    let path = path.trim();
+    let path = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+        .unwrap_or(path);
+    path.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+/// The excerpt's path starts with the name of its worktree, which the events' paths don't.
+fn paths_match(excerpt_path: &str, event_path: &str) -> bool {
+    excerpt_path == event_path
+        || excerpt_path
+            .strip_suffix(event_path)
+            .map_or(false, |prefix| prefix.ends_with('/'))
+}
+
+struct Hunk {
+    old_text: String,
+    new_text: String,
+}
+
+fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                old_text: String::new(),
+                new_text: String::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = current.as_mut() else {
+            continue;
+        };
+        if let Some(line) = line.strip_prefix(' ') {
+            hunk.old_text.push_str(line);
+            hunk.new_text.push_str(line);
+        } else if let Some(line) = line.strip_prefix('-') {
+            hunk.old_text.push_str(line);
+        } else if let Some(line) = line.strip_prefix('+') {
+            hunk.new_text.push_str(line);
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(a.len().min(b.len()), |((ix, _), _)| ix)
+}
+
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT_EXCERPT: &str = "```zed/src/main.rs\n<|start_of_file|>\nfn main() {\n<|editable_region_start|>\n    let x = 1;\n    pri<|user_cursor_is_here|>\n<|editable_region_end|>\n}\n```";
+
+    #[test]
+    fn test_parse_excerpt() {
+        let excerpt = Excerpt::parse(INPUT_EXCERPT).unwrap();
+        assert_eq!(excerpt.path, "zed/src/main.rs");
+        assert_eq!(excerpt.text, "fn main() {\n    let x = 1;\n    pri\n}");
+        assert_eq!(excerpt.editable_text(), "    let x = 1;\n    pri");
+        assert_eq!(
+            &excerpt.text[..excerpt.cursor_offset],
+            "fn main() {\n    let x = 1;\n    pri"
+        );
+
+        assert_eq!(
+            output_editable_region(
+                "<|editable_region_start|>\n    let x = 1;\n    println!(\"{x}\");<|user_cursor_is_here|>\n<|editable_region_end|>\n"
+            )
+            .unwrap(),
+            "    let x = 1;\n    println!(\"{x}\");"
+        );
+        assert!(output_editable_region("no markers").is_err());
+    }
+
+    #[test]
+    fn test_edit_history() {
+        let excerpt = Excerpt::parse(INPUT_EXCERPT).unwrap();
+        let input_events = concat!(
+            "User edited \"src/main.rs\":\n```diff\n@@ -1,2 +1,3 @@\n fn main() {\n+    let x = 1;\n }\n\n```",
+            "\n\n",
+            "User edited \"src/other.rs\":\n```diff\n@@ -1 +1 @@\n-a\n+b\n\n```",
+            "\n\n",
+            "User edited \"src/main.rs\":\n```diff\n@@ -1,3 +1,4 @@\n fn main() {\n     let x = 1;\n+    pri\n }\n\n```",
+            "\n\n",
+            "User edited \"src/main.rs\":\n```diff\n@@ -1 +1 @@\n-fn gone() {}\n+fn elsewhere() {}\n\n```",
+        );
+
+        let history = EditHistory::for_excerpt(input_events, &excerpt);
+        assert_eq!(history.initial_text, "fn main() {\n}");
+        assert_eq!(history.skipped_hunks, 1);
+
+        let mut text = history.initial_text.clone();
+        for (range, new_text) in &history.edits {
+            text.replace_range(range.clone(), new_text);
+        }
+        assert_eq!(text, excerpt.text);
+        assert_eq!(
+            history.edits,
+            [
+                (12..12, "    let x = 1;\n".to_string()),
+                (27..27, "    pri\n".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// How close a prediction's editable region came to the recorded one.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Score {
+    pub exact_match: bool,
+    /// The number of characters to insert, delete or replace to turn the prediction into the
+    /// recorded output.
+    pub edit_distance: usize,
+    /// The edit distance of not predicting anything, for reference.
+    pub baseline_edit_distance: usize,
+    /// Whether the prediction left the editable region as it was.
+    pub unchanged: bool,
+}
+
+impl Score {
+    pub fn new(input: &str, predicted: &str, expected: &str) -> Self {
+        Self {
+            exact_match: predicted == expected,
+            edit_distance: edit_distance(predicted, expected),
+            baseline_edit_distance: edit_distance(input, expected),
+            unchanged: predicted == input,
+        }
+    }
+}
+
+/// The result of evaluating one recorded example.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExampleOutcome {
+    pub id: String,
+    pub path: String,
+    pub replayed_edits: usize,
+    pub skipped_hunks: usize,
+    pub latency_ms: Option<u128>,
+    pub predicted: Option<String>,
+    pub expected: String,
+    pub score: Option<Score>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Summary {
+    pub examples: usize,
+    pub errors: usize,
+    pub exact_matches: usize,
+    pub unchanged: usize,
+    pub mean_edit_distance: f64,
+    pub mean_baseline_edit_distance: f64,
+    /// How much of the baseline's edit distance the predictions removed, between 0 and 1 when
+    /// they help and negative when they make things worse.
+    pub edit_distance_reduction: f64,
+    pub mean_latency_ms: f64,
+}
+
+impl Summary {
+    pub fn new(outcomes: &[ExampleOutcome]) -> Self {
+        let scores = outcomes
+            .iter()
+            .filter_map(|outcome| outcome.score.as_ref())
+            .collect::<Vec<_>>();
+        let latencies = outcomes
+            .iter()
+            .filter_map(|outcome| outcome.latency_ms)
+            .collect::<Vec<_>>();
+
+        let total_edit_distance = scores
+            .iter()
+            .map(|score| score.edit_distance)
+            .sum::<usize>();
+        let total_baseline_edit_distance = scores
+            .iter()
+            .map(|score| score.baseline_edit_distance)
+            .sum::<usize>();
+
+        Self {
+            examples: outcomes.len(),
+            errors: outcomes.len() - scores.len(),
+            exact_matches: scores.iter().filter(|score| score.exact_match).count(),
+            unchanged: scores.iter().filter(|score| score.unchanged).count(),
+            mean_edit_distance: mean(total_edit_distance as f64, scores.len()),
+            mean_baseline_edit_distance: mean(total_baseline_edit_distance as f64, scores.len()),
+            edit_distance_reduction: if total_baseline_edit_distance == 0 {
+                0.
+            } else {
+                1. - total_edit_distance as f64 / total_baseline_edit_distance as f64
+            },
+            mean_latency_ms: mean(
+                latencies.iter().map(|latency| *latency as f64).sum(),
+                latencies.len(),
+            ),
+        }
+    }
+
+    pub fn exact_match_rate(&self) -> f64 {
+        mean(self.exact_matches as f64, self.examples - self.errors)
+    }
+}
+
+fn mean(total: f64, count: usize) -> f64 {
+    if count == 0 {
+        0.
+    } else {
+        total / count as f64
+    }
+}
+
+/// The Levenshtein distance between two strings, in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<_>>();
+    let mut current_row = vec![0; b.len() + 1];
+    for (i, a_char) in a.iter().enumerate() {
+        current_row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != b_char);
+            current_row[j + 1] = substitution
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+    previous_row[b.len()]
+}
+
+/// Renders the report of a run as Markdown.
+pub fn markdown_report(
+    predictor_name: &str,
+    summary: &Summary,
+    outcomes: &[ExampleOutcome],
+    duration: Duration,
+) -> String {
+    let mut report = String::new();
+    writeln!(report, "# Edit Prediction Eval: {predictor_name}\n").unwrap();
+    writeln!(report, "| Metric | Value |").unwrap();
+    writeln!(report, "| --- | --- |").unwrap();
+    writeln!(report, "| Examples | {} |", summary.examples).unwrap();
+    writeln!(report, "| Errors | {} |", summary.errors).unwrap();
+    writeln!(
+        report,
+        "| Exact matches | {} ({:.1}%) |",
+        summary.exact_matches,
+        summary.exact_match_rate() * 100.
+    )
+    .unwrap();
+    writeln!(report, "| Unchanged | {} |", summary.unchanged).unwrap();
+    writeln!(
+        report,
+        "| Mean edit distance | {:.1} (baseline {:.1}) |",
+        summary.mean_edit_distance, summary.mean_baseline_edit_distance
+    )
+    .unwrap();
+    writeln!(
+        report,
+        "| Edit distance reduction | {:.1}% |",
+        summary.edit_distance_reduction * 100.
+    )
+    .unwrap();
+    writeln!(
+        report,
+        "| Mean latency | {:.0} ms |",
+        summary.mean_latency_ms
+    )
+    .unwrap();
+    writeln!(report, "| Duration | {:.1} s |", duration.as_secs_f64()).unwrap();
+
+    writeln!(report, "\n## Examples\n").unwrap();
+    writeln!(report, "| Example | Path | Exact | Distance | Baseline |").unwrap();
+    writeln!(report, "| --- | --- | --- | --- | --- |").unwrap();
+    for outcome in outcomes {
+        match (&outcome.score, &outcome.error) {
+            (Some(score), _) => writeln!(
+                report,
+                "| {} | `{}` | {} | {} | {} |",
+                outcome.id,
+                outcome.path,
+                if score.exact_match { "✓" } else { "" },
+                score.edit_distance,
+                score.baseline_edit_distance
+            ),
+            (None, error) => writeln!(
+                report,
+                "| {} | `{}` | error: {} | | |",
+                outcome.id,
+                outcome.path,
+                error.as_deref().unwrap_or_default().replace('\n', " ")
+            ),
+        }
+        .unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("héllo", "hello"), 1);
+    }
+
+    #[test]
+    fn test_summary() {
+        let outcome = |score: Option<Score>, latency_ms| ExampleOutcome {
+            id: String::new(),
+            path: String::new(),
+            replayed_edits: 0,
+            skipped_hunks: 0,
+            latency_ms,
+            predicted: None,
+            expected: String::new(),
+            error: score.is_none().then(|| "failed".to_string()),
+            score,
+        };
+        let outcomes = [
+            outcome(Some(Score::new("ab", "abc", "abc")), Some(100)),
+            outcome(Some(Score::new("ab", "ab", "abcd")), Some(300)),
+            outcome(None, None),
+        ];
+
+        let summary = Summary::new(&outcomes);
+        assert_eq!(
+            summary,
+            Summary {
+                examples: 3,
+                errors: 1,
+                exact_matches: 1,
+                unchanged: 1,
+                mean_edit_distance: 1.,
+                mean_baseline_edit_distance: 1.5,
+                edit_distance_reduction: 1. - 2. / 3.,
+                mean_latency_ms: 200.,
+            }
+        );
+        assert_eq!(summary.exact_match_rate(), 0.5);
+    }
+}
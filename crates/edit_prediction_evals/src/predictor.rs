@@ -0,0 +1,123 @@
+use crate::example::{output_editable_region, EditHistory, Excerpt, RecordedExample};
+use anyhow::{anyhow, bail, Context as _, Result};
+use gpui::{AppContext as _, AsyncApp, Entity};
+use http_client::HttpClient;
+use inline_completion::EditPredictionProvider;
+use language::Buffer;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How often to check whether a provider has finished refreshing its prediction.
+const REFRESH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The prompt `zeta`'s open model was trained with.
+const ZETA_PROMPT_TEMPLATE: &str = "### Instruction:\nYou are a code completion assistant and your task is to analyze user edits and then rewrite an excerpt that the user provides, suggesting the appropriate edits within the excerpt, taking into account the cursor location.\n\n### User Edits:\n\n{events}\n\n### User Excerpt:\n\n{excerpt}\n\n### Response:\n";
+
+/// Predicts edits with a model that's trained like `zeta`, such as `zeta` itself, served by an
+/// OpenAI-compatible API.
+pub struct ZetaModelPredictor {
+    pub http_client: Arc<dyn HttpClient>,
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub max_tokens: u32,
+}
+
+impl ZetaModelPredictor {
+    /// Returns the predicted text of the example's editable region.
+    pub async fn predict(&self, example: &RecordedExample) -> Result<String> {
+        let prompt = ZETA_PROMPT_TEMPLATE
+            .replace("{events}", &example.input_events)
+            .replace("{excerpt}", &example.input_excerpt);
+        let request = open_ai::TextCompletionRequest {
+            model: self.model.clone(),
+            prompt,
+            max_tokens: self.max_tokens,
+            temperature: 0.,
+            stop: Vec::new(),
+            stream: false,
+        };
+        let response = open_ai::text_completion(
+            self.http_client.as_ref(),
+            &self.api_url,
+            self.api_key.as_deref(),
+            &[],
+            request,
+        )
+        .await?;
+        let output = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.text)
+            .context("the API returned no completion")?;
+        output_editable_region(&output)
+    }
+}
+
+/// Predicts edits with an edit prediction provider, after replaying the example's edit history
+/// in a buffer holding its excerpt. Providers that only start tracking a buffer's edits once
+/// they're asked for a prediction see the buffer as it is after the history.
+pub async fn predict_with_provider<P: EditPredictionProvider>(
+    provider: &Entity<P>,
+    excerpt: &Excerpt,
+    history: &EditHistory,
+    timeout: Duration,
+    cx: &mut AsyncApp,
+) -> Result<String> {
+    let buffer = cx.new(|cx| Buffer::local(history.initial_text.clone(), cx))?;
+    for (range, new_text) in &history.edits {
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(range.clone(), new_text.as_str())], None, cx);
+        })?;
+    }
+
+    let (cursor, editable_range) = buffer.read_with(cx, |buffer, _| {
+        let snapshot = buffer.snapshot();
+        anyhow::ensure!(
+            snapshot.text() == excerpt.text,
+            "replaying the edit history didn't reproduce the excerpt"
+        );
+        let editable_range = snapshot.anchor_before(excerpt.editable_range.start)
+            ..snapshot.anchor_after(excerpt.editable_range.end);
+        Ok((
+            snapshot.anchor_before(excerpt.cursor_offset),
+            editable_range,
+        ))
+    })??;
+
+    let is_enabled =
+        provider.read_with(cx, |provider, cx| provider.is_enabled(&buffer, cursor, cx))?;
+    if !is_enabled {
+        bail!("{} isn't enabled, check its settings", P::display_name());
+    }
+
+    provider.update(cx, |provider, cx| {
+        provider.refresh(None, buffer.clone(), cursor, false, cx)
+    })?;
+    let started_at = Instant::now();
+    while provider.read_with(cx, |provider, _| provider.is_refreshing())? {
+        if started_at.elapsed() > timeout {
+            provider.update(cx, |provider, cx| provider.discard(cx))?;
+            return Err(anyhow!("timed out after {timeout:?}"));
+        }
+        cx.background_executor().timer(REFRESH_POLL_INTERVAL).await;
+    }
+
+    let completion = provider.update(cx, |provider, cx| {
+        let completion = provider.suggest(&buffer, cursor, cx);
+        provider.discard(cx);
+        completion
+    })?;
+    buffer.update(cx, |buffer, cx| {
+        if let Some(completion) = completion {
+            buffer.edit(completion.edits, None, cx);
+        }
+        buffer
+            .snapshot()
+            .text_for_range(editable_range)
+            .collect::<String>()
+    })
+}
@@ -206,6 +206,36 @@ pub enum UuidVersion {
     V7,
 }
 
+/// Plays back a macro, either the last one recorded or one saved in the settings.
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PlayMacro {
+    /// The name of a macro saved in the `macros` setting.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// How many times to play the macro.
+    #[serde(default)]
+    pub times: Option<u32>,
+}
+
+/// Plays back a macro once at the start of every selected line, or at every cursor.
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PlayMacroOnEachLine {
+    /// The name of a macro saved in the `macros` setting.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Saves the last recorded macro in the `macros` setting.
+#[derive(PartialEq, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SaveMacro {
+    /// The name to save the macro under, asked for when left out.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
 impl_actions!(
     editor,
     [
@@ -225,6 +255,9 @@ impl_actions!(
         MoveToBeginningOfLine,
         MoveToEndOfLine,
         MoveUpByLines,
+        PlayMacro,
+        PlayMacroOnEachLine,
+        SaveMacro,
         SelectDownByLines,
         SelectNext,
         SelectPrevious,
@@ -407,6 +440,7 @@ gpui::actions!(
         ToggleInlineDiagnostics,
         ToggleEditPrediction,
         ToggleLineNumbers,
+        ToggleMacroRecording,
         SwapSelectionEnds,
         SetMark,
         ToggleRelativeLineNumbers,
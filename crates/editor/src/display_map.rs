@@ -80,7 +80,17 @@ pub trait ToDisplayPoint {
     fn to_display_point(&self, map: &DisplaySnapshot) -> DisplayPoint;
 }
 
-type TextHighlights = TreeMap<TypeId, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>;
+/// Identifies a set of text highlights with a single style.
+///
+/// Highlights are usually keyed by the type adding them, with an index for the
+/// types that need more than one style at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HighlightKey {
+    Type(TypeId),
+    TypePlus(TypeId, usize),
+}
+
+type TextHighlights = TreeMap<HighlightKey, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>;
 type InlayHighlights = TreeMap<TypeId, TreeMap<InlayId, (HighlightStyle, InlayHighlight)>>;
 
 /// Decides how text in a [`MultiBuffer`] should be displayed in a buffer, handling inlay hints,
@@ -455,12 +465,11 @@ impl DisplayMap {
 
     pub fn highlight_text(
         &mut self,
-        type_id: TypeId,
+        key: HighlightKey,
         ranges: Vec<Range<Anchor>>,
         style: HighlightStyle,
     ) {
-        self.text_highlights
-            .insert(type_id, Arc::new((style, ranges)));
+        self.text_highlights.insert(key, Arc::new((style, ranges)));
     }
 
     pub(crate) fn highlight_inlays(
@@ -483,11 +492,28 @@ impl DisplayMap {
     }
 
     pub fn text_highlights(&self, type_id: TypeId) -> Option<(HighlightStyle, &[Range<Anchor>])> {
-        let highlights = self.text_highlights.get(&type_id)?;
+        let highlights = self.text_highlights.get(&HighlightKey::Type(type_id))?;
         Some((highlights.0, &highlights.1))
     }
     pub fn clear_highlights(&mut self, type_id: TypeId) -> bool {
-        let mut cleared = self.text_highlights.remove(&type_id).is_some();
+        let mut cleared = self
+            .text_highlights
+            .remove(&HighlightKey::Type(type_id))
+            .is_some();
+        let first_indexed_key = HighlightKey::TypePlus(type_id, 0);
+        let has_indexed_highlights = self
+            .text_highlights
+            .iter_from(&first_indexed_key)
+            .next()
+            .map_or(false, |(key, _)| {
+                matches!(key, HighlightKey::TypePlus(key_type_id, _) if *key_type_id == type_id)
+            });
+        if has_indexed_highlights {
+            self.text_highlights.retain(|key, _| {
+                !matches!(key, HighlightKey::TypePlus(key_type_id, _) if *key_type_id == type_id)
+            });
+            cleared = true;
+        }
         cleared |= self.inlay_highlights.remove(&type_id).is_some();
         cleared
     }
@@ -2242,7 +2268,7 @@ pub mod tests {
         // Insert a block in the middle of a multi-line diagnostic.
         map.update(cx, |map, cx| {
             map.highlight_text(
-                TypeId::of::<usize>(),
+                HighlightKey::Type(TypeId::of::<usize>()),
                 vec![
                     buffer_snapshot.anchor_before(Point::new(3, 9))
                         ..buffer_snapshot.anchor_after(Point::new(3, 14)),
@@ -2562,7 +2588,7 @@ pub mod tests {
 
         map.update(cx, |map, _cx| {
             map.highlight_text(
-                TypeId::of::<MyType>(),
+                HighlightKey::Type(TypeId::of::<MyType>()),
                 highlighted_ranges
                     .into_iter()
                     .map(|range| {
@@ -2588,6 +2614,52 @@ pub mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_clearing_keyed_text_highlights(cx: &mut gpui::App) {
+        init_test(cx, |_| {});
+
+        let buffer = MultiBuffer::build_simple("one two three", cx);
+        let buffer_snapshot = buffer.read(cx).snapshot(cx);
+        let map = cx.new(|cx| {
+            DisplayMap::new(
+                buffer,
+                font("Helvetica"),
+                px(14.0),
+                None,
+                true,
+                1,
+                1,
+                1,
+                FoldPlaceholder::test(),
+                cx,
+            )
+        });
+
+        enum Keyed {}
+        enum Other {}
+
+        let range = |start: usize, end: usize| {
+            buffer_snapshot.anchor_before(start)..buffer_snapshot.anchor_before(end)
+        };
+        map.update(cx, |map, _| {
+            let keyed = TypeId::of::<Keyed>();
+            let style = HighlightStyle::default();
+            map.highlight_text(HighlightKey::TypePlus(keyed, 0), vec![range(0, 3)], style);
+            map.highlight_text(HighlightKey::TypePlus(keyed, 1), vec![range(4, 7)], style);
+            map.highlight_text(
+                HighlightKey::Type(TypeId::of::<Other>()),
+                vec![range(8, 13)],
+                style,
+            );
+
+            // Clearing a type clears all of its keyed highlights, and only those.
+            assert!(map.clear_highlights(keyed));
+            assert!(!map.clear_highlights(keyed));
+            assert_eq!(map.text_highlights.iter().count(), 1);
+            assert!(map.text_highlights(TypeId::of::<Other>()).is_some());
+        });
+    }
+
     #[gpui::test]
     fn test_clip_point(cx: &mut gpui::App) {
         init_test(cx, |_| {});
@@ -30,6 +30,7 @@ mod inlay_hint_cache;
 pub mod items;
 mod linked_editing_ranges;
 mod lsp_ext;
+mod macros;
 mod mouse_context_menu;
 pub mod movement;
 mod persistence;
@@ -72,6 +73,7 @@ use futures::{
     FutureExt,
 };
 use fuzzy::StringMatchCandidate;
+pub use macros::MacroStep;
 
 use ::git::{status::FileStatus, Restore};
 use code_context_menus::{
@@ -272,6 +274,7 @@ pub fn init_settings(cx: &mut App) {
 
 pub fn init(cx: &mut App) {
     init_settings(cx);
+    macros::init(cx);
 
     workspace::register_project_item::<Editor>(cx);
     workspace::FollowableViewRegistry::register::<Editor>(cx);
@@ -540,6 +543,20 @@ impl EditorActionId {
 type BackgroundHighlight = (fn(&ThemeColors) -> Hsla, Arc<[Range<Anchor>]>);
 type GutterHighlight = (fn(&App) -> Hsla, Arc<[Range<Anchor>]>);
 
+/// Renders the controls shown next to an expanded diff hunk, given its first display row,
+/// status and range.
+pub type RenderDiffHunkControlsFn = Arc<
+    dyn Fn(
+        u32,
+        &DiffHunkStatus,
+        Range<Anchor>,
+        Pixels,
+        &Entity<Editor>,
+        &mut Window,
+        &mut App,
+    ) -> AnyElement,
+>;
+
 #[derive(Default)]
 struct ScrollbarMarkerState {
     scrollbar_size: Size<Pixels>,
@@ -732,6 +749,7 @@ pub struct Editor {
     show_selection_menu: Option<bool>,
     blame: Option<Entity<GitBlame>>,
     blame_subscription: Option<Subscription>,
+    render_diff_hunk_controls: RenderDiffHunkControlsFn,
     custom_context_menu: Option<
         Box<
             dyn 'static
@@ -1439,6 +1457,7 @@ impl Editor {
             edit_prediction_indent_conflict: false,
             edit_prediction_requires_modifier_in_indent_conflict: true,
             custom_context_menu: None,
+            render_diff_hunk_controls: Arc::new(element::diff_hunk_controls),
             show_git_blame_gutter: false,
             show_git_blame_inline: false,
             show_selection_menu: None,
@@ -1830,6 +1849,17 @@ impl Editor {
         self.custom_context_menu = Some(Box::new(f))
     }
 
+    /// Replaces the controls shown next to expanded diff hunks, which stage and restore
+    /// hunks by default.
+    pub fn set_render_diff_hunk_controls(
+        &mut self,
+        render_diff_hunk_controls: RenderDiffHunkControlsFn,
+        cx: &mut Context<Self>,
+    ) {
+        self.render_diff_hunk_controls = render_diff_hunk_controls;
+        cx.notify();
+    }
+
     pub fn set_completion_provider(&mut self, provider: Option<Box<dyn CompletionProvider>>) {
         self.completion_provider = provider;
     }
@@ -15035,7 +15065,27 @@ impl Editor {
         cx: &mut Context<Self>,
     ) {
         self.display_map.update(cx, |map, _| {
-            map.highlight_text(TypeId::of::<T>(), ranges, style)
+            map.highlight_text(HighlightKey::Type(TypeId::of::<T>()), ranges, style)
+        });
+        cx.notify();
+    }
+
+    /// Highlights text with one of several styles added by the same type, told apart by `key`.
+    ///
+    /// They are all cleared together by [`Self::clear_highlights`].
+    pub fn highlight_text_key<T: 'static>(
+        &mut self,
+        key: usize,
+        ranges: Vec<Range<Anchor>>,
+        style: HighlightStyle,
+        cx: &mut Context<Self>,
+    ) {
+        self.display_map.update(cx, |map, _| {
+            map.highlight_text(
+                HighlightKey::TypePlus(TypeId::of::<T>(), key),
+                ranges,
+                style,
+            )
         });
         cx.notify();
     }
@@ -1,3 +1,5 @@
+use crate::MacroStep;
+use collections::HashMap;
 use gpui::App;
 use language::CursorShape;
 use schemars::JsonSchema;
@@ -37,6 +39,8 @@ pub struct EditorSettings {
     pub auto_signature_help: bool,
     pub show_signature_help_after_edits: bool,
     pub jupyter: Jupyter,
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<MacroStep>>,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
@@ -380,6 +384,13 @@ pub struct EditorSettingsContent {
 
     /// Jupyter REPL settings.
     pub jupyter: Option<JupyterContent>,
+
+    /// Keyboard macros saved by name, to be played with `editor::PlayMacro`.
+    /// Each step is an action name, an action with arguments as in the keymap,
+    /// `{"text": "..."}` to type text, or `{"keystroke": "..."}` to press keys.
+    ///
+    /// Default: {}
+    pub macros: Option<HashMap<String, Vec<MacroStep>>>,
 }
 
 // Toolbar related settings
@@ -382,6 +382,10 @@ impl EditorElement {
         register_action(editor, window, Editor::fold_selected_ranges);
         register_action(editor, window, Editor::set_mark);
         register_action(editor, window, Editor::swap_selection_ends);
+        register_action(editor, window, Editor::toggle_macro_recording);
+        register_action(editor, window, Editor::play_macro);
+        register_action(editor, window, Editor::play_macro_on_each_line);
+        register_action(editor, window, Editor::save_macro);
         register_action(editor, window, Editor::show_completions);
         register_action(editor, window, Editor::toggle_code_actions);
         register_action(editor, window, Editor::open_excerpts);
@@ -3907,12 +3911,15 @@ impl EditorElement {
                         + text_hitbox.bounds.top()
                         - scroll_pixel_position.y;
 
-                    let mut element = diff_hunk_controls(
+                    let render_diff_hunk_controls =
+                        editor.read(cx).render_diff_hunk_controls.clone();
+                    let mut element = render_diff_hunk_controls(
                         display_row_range.start.0,
                         status,
                         multi_buffer_range.clone(),
                         line_height,
                         &editor,
+                        window,
                         cx,
                     );
                     let size =
@@ -8684,12 +8691,13 @@ mod tests {
     }
 }
 
-fn diff_hunk_controls(
+pub(crate) fn diff_hunk_controls(
     row: u32,
     status: &DiffHunkStatus,
     hunk_range: Range<Anchor>,
     line_height: Pixels,
     editor: &Entity<Editor>,
+    _window: &mut Window,
     cx: &mut App,
 ) -> AnyElement {
     h_flex()
@@ -0,0 +1,665 @@
+//! Keyboard macros, recorded and played back in any editor whether or not vim mode is enabled.
+//!
+//! While recording, the actions dispatched by keystrokes are captured along with the text typed
+//! into editors. Macros can be played back several times, or once on every selected line, and
+//! can be saved under a name in the `macros` setting to be bound in the keymap.
+
+use std::{cell::RefCell, collections::VecDeque, iter, ops::Range, rc::Rc, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use fs::Fs;
+use gpui::{
+    Action, App, AppContext as _, Context, DismissEvent, Entity, EntityId, EventEmitter,
+    FocusHandle, Focusable, Global, Keystroke, WeakEntity, Window,
+};
+use language::Point;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{update_settings_file, Settings};
+use ui::prelude::*;
+use workspace::ModalView;
+
+use crate::{
+    actions::{PlayMacro, PlayMacroOnEachLine, SaveMacro, ToggleMacroRecording},
+    scroll::Autoscroll,
+    Anchor, Editor, EditorEvent, EditorSettings,
+};
+
+/// Playback stops after this many steps, in case a macro ends up playing itself.
+const MAX_REPLAYED_STEPS: usize = 10000;
+
+/// A step of a macro saved in the settings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum MacroStep {
+    /// An action without arguments, like `"editor::MoveDown"`.
+    Action(String),
+    /// An action with arguments, written as in the keymap:
+    /// `["editor::MoveToBeginningOfLine", { "stop_at_indent": true }]`.
+    ActionWithArguments(String, serde_json::Value),
+    /// Text typed into the editor, optionally replacing the text around the cursors
+    /// like an accepted completion does.
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replace_utf16_range: Option<Range<isize>>,
+    },
+    /// Keys pressed, for actions whose arguments could not be saved.
+    Keystroke { keystroke: String },
+}
+
+impl MacroStep {
+    fn to_replay_step(&self, cx: &App) -> Result<ReplayStep> {
+        Ok(match self {
+            Self::Action(name) => ReplayStep::Action {
+                action: cx.build_action(name, None)?,
+                keystroke: None,
+            },
+            Self::ActionWithArguments(name, arguments) => ReplayStep::Action {
+                action: cx.build_action(name, Some(arguments.clone()))?,
+                keystroke: None,
+            },
+            Self::Text {
+                text,
+                replace_utf16_range,
+            } => ReplayStep::Text {
+                text: text.as_str().into(),
+                utf16_range_to_replace: replace_utf16_range.clone(),
+            },
+            Self::Keystroke { keystroke } => ReplayStep::Keystroke(Keystroke::parse(keystroke)?),
+        })
+    }
+}
+
+enum ReplayStep {
+    Action {
+        action: Box<dyn Action>,
+        /// The keystroke the action was dispatched by, to save the step as when the action's
+        /// arguments cannot be.
+        keystroke: Option<Keystroke>,
+    },
+    Keystroke(Keystroke),
+    Text {
+        text: Arc<str>,
+        utf16_range_to_replace: Option<Range<isize>>,
+    },
+    /// Moves the cursor of the editor the macro is played in, between plays on each line.
+    MoveTo(Anchor),
+}
+
+impl Clone for ReplayStep {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Action { action, keystroke } => Self::Action {
+                action: action.boxed_clone(),
+                keystroke: keystroke.clone(),
+            },
+            Self::Keystroke(keystroke) => Self::Keystroke(keystroke.clone()),
+            Self::Text {
+                text,
+                utf16_range_to_replace,
+            } => Self::Text {
+                text: text.clone(),
+                utf16_range_to_replace: utf16_range_to_replace.clone(),
+            },
+            Self::MoveTo(position) => Self::MoveTo(*position),
+        }
+    }
+}
+
+impl ReplayStep {
+    fn to_macro_step(&self, cx: &App) -> Option<MacroStep> {
+        match self {
+            Self::Action { action, keystroke } => {
+                // Actions can only be built from their name and arguments, not serialized,
+                // so actions with arguments are saved as the keystroke that dispatched them.
+                let name = action.name();
+                match cx.build_action(name, None) {
+                    Ok(built) if built.partial_eq(action.as_ref()) => {
+                        Some(MacroStep::Action(name.to_string()))
+                    }
+                    _ => keystroke.as_ref().map(|keystroke| MacroStep::Keystroke {
+                        keystroke: keystroke.unparse(),
+                    }),
+                }
+            }
+            Self::Keystroke(keystroke) => Some(MacroStep::Keystroke {
+                keystroke: keystroke.unparse(),
+            }),
+            Self::Text {
+                text,
+                utf16_range_to_replace,
+            } => Some(MacroStep::Text {
+                text: text.to_string(),
+                replace_utf16_range: utf16_range_to_replace.clone(),
+            }),
+            Self::MoveTo(_) => None,
+        }
+    }
+}
+
+/// Converts a recorded macro into steps to save, joining up text typed a character at a time.
+fn macro_steps_to_save(steps: &[ReplayStep], cx: &App) -> Vec<MacroStep> {
+    let mut saved = Vec::<MacroStep>::new();
+    for step in steps.iter().filter_map(|step| step.to_macro_step(cx)) {
+        if let (
+            Some(MacroStep::Text { text, .. }),
+            MacroStep::Text {
+                text: next_text,
+                replace_utf16_range: None,
+            },
+        ) = (saved.last_mut(), &step)
+        {
+            text.push_str(next_text);
+            continue;
+        }
+        saved.push(step);
+    }
+    saved
+}
+
+#[derive(Default)]
+struct MacroRecorder {
+    recording: Option<Recording>,
+    last_macro: Vec<ReplayStep>,
+    replayer: Option<Replayer>,
+}
+
+struct Recording {
+    /// The editor recording was started in. Text typed into other editors, like search bars
+    /// and pickers, is left out, as it would be played back into this one.
+    editor: EntityId,
+    steps: Vec<ReplayStep>,
+}
+
+impl Global for MacroRecorder {}
+
+impl MacroRecorder {
+    fn record(&mut self, step: ReplayStep) {
+        if self.replayer.is_some() {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.steps.push(step);
+        }
+    }
+
+    fn record_input(&mut self, editor: EntityId, step: ReplayStep) {
+        if self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| recording.editor == editor)
+        {
+            self.record(step);
+        }
+    }
+}
+
+pub(crate) fn init(cx: &mut App) {
+    cx.observe_keystrokes(|event, _, cx| {
+        let Some(action) = event.action.as_ref() else {
+            return;
+        };
+        if action.as_any().is::<ToggleMacroRecording>()
+            || action.as_any().is::<PlayMacro>()
+            || action.as_any().is::<PlayMacroOnEachLine>()
+            || action.as_any().is::<SaveMacro>()
+        {
+            return;
+        }
+        if let Some(recorder) = cx.try_global::<MacroRecorder>() {
+            if recorder.recording.is_some() {
+                let step = ReplayStep::Action {
+                    action: action.boxed_clone(),
+                    keystroke: Some(event.keystroke.clone()),
+                };
+                cx.global_mut::<MacroRecorder>().record(step);
+            }
+        }
+    })
+    .detach();
+
+    cx.observe_new(|_: &mut Editor, _, cx: &mut Context<Editor>| {
+        cx.subscribe(&cx.entity(), |_, editor, event: &EditorEvent, cx| {
+            if let EditorEvent::InputHandled {
+                text,
+                utf16_range_to_replace,
+            } = event
+            {
+                if let Some(recorder) = cx.try_global::<MacroRecorder>() {
+                    if recorder.recording.is_some() {
+                        cx.global_mut::<MacroRecorder>().record_input(
+                            editor.entity_id(),
+                            ReplayStep::Text {
+                                text: text.clone(),
+                                utf16_range_to_replace: utf16_range_to_replace.clone(),
+                            },
+                        );
+                    }
+                }
+            }
+        })
+        .detach();
+    })
+    .detach();
+}
+
+#[derive(Clone)]
+struct Replayer(Rc<RefCell<ReplayerState>>);
+
+struct ReplayerState {
+    editor: WeakEntity<Editor>,
+    steps: VecDeque<ReplayStep>,
+    replayed: usize,
+}
+
+impl Replayer {
+    fn start(
+        editor: WeakEntity<Editor>,
+        steps: Vec<ReplayStep>,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        let recorder = cx.default_global::<MacroRecorder>();
+        if recorder.replayer.is_some() || steps.is_empty() {
+            return;
+        }
+        // Macros played while recording become part of the recording, rather than the
+        // steps they dispatch.
+        if let Some(recording) = &mut recorder.recording {
+            recording.steps.extend(steps.iter().cloned());
+        }
+
+        let replayer = Self(Rc::new(RefCell::new(ReplayerState {
+            editor,
+            steps: steps.into(),
+            replayed: 0,
+        })));
+        recorder.replayer = Some(replayer.clone());
+        window.defer(cx, move |window, cx| replayer.next(window, cx));
+    }
+
+    fn next(self, window: &mut Window, cx: &mut App) {
+        let mut state = self.0.borrow_mut();
+        let step = if state.replayed < MAX_REPLAYED_STEPS {
+            state.steps.pop_front()
+        } else {
+            log::error!("Aborting macro after {MAX_REPLAYED_STEPS} steps");
+            None
+        };
+        state.replayed += 1;
+        let editor = state.editor.clone();
+        drop(state);
+        let Some(step) = step else {
+            cx.default_global::<MacroRecorder>().replayer.take();
+            return;
+        };
+
+        match step {
+            ReplayStep::Action { action, .. } => window.dispatch_action(action, cx),
+            ReplayStep::Keystroke(keystroke) => {
+                window.dispatch_keystroke(keystroke, cx);
+            }
+            ReplayStep::Text {
+                text,
+                utf16_range_to_replace,
+            } => {
+                // Text is only recorded from one editor, so it is played back into one as well.
+                if let Some(editor) = editor.upgrade() {
+                    editor.update(cx, |editor, cx| {
+                        editor.replay_insert_event(&text, utf16_range_to_replace, window, cx)
+                    });
+                }
+            }
+            ReplayStep::MoveTo(position) => {
+                if let Some(editor) = editor.upgrade() {
+                    window.focus(&editor.focus_handle(cx));
+                    editor.update(cx, |editor, cx| {
+                        editor.change_selections(Some(Autoscroll::fit()), window, cx, |s| {
+                            s.select_anchor_ranges([position..position])
+                        })
+                    });
+                }
+            }
+        }
+        window.defer(cx, move |window, cx| self.next(window, cx));
+    }
+}
+
+impl Editor {
+    pub fn toggle_macro_recording(
+        &mut self,
+        _: &ToggleMacroRecording,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let editor = cx.entity_id();
+        let recorder = cx.default_global::<MacroRecorder>();
+        if recorder.replayer.is_some() {
+            return;
+        }
+        match recorder.recording.take() {
+            Some(recording) => {
+                if !recording.steps.is_empty() {
+                    recorder.last_macro = recording.steps;
+                }
+            }
+            None => {
+                recorder.recording = Some(Recording {
+                    editor,
+                    steps: Vec::new(),
+                })
+            }
+        }
+    }
+
+    pub fn play_macro(&mut self, action: &PlayMacro, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(steps) = self.macro_to_play(action.name.as_deref(), cx) else {
+            return;
+        };
+        // Playback stops at the step limit anyway, so don't collect past it for large counts.
+        let times = (action.times.unwrap_or(1) as usize).min(MAX_REPLAYED_STEPS + 1);
+        let steps = iter::repeat(steps)
+            .take(times)
+            .flatten()
+            .take(MAX_REPLAYED_STEPS + 1)
+            .collect();
+        Replayer::start(cx.entity().downgrade(), steps, window, cx);
+    }
+
+    /// Plays the macro at the start of every line touched by a selection, and at every cursor
+    /// without a selection.
+    pub fn play_macro_on_each_line(
+        &mut self,
+        action: &PlayMacroOnEachLine,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(steps) = self.macro_to_play(action.name.as_deref(), cx) else {
+            return;
+        };
+
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut positions = Vec::new();
+        let mut last_row = None;
+        for selection in self.selections.all::<Point>(cx) {
+            if selection.is_empty() {
+                positions.push(buffer.anchor_before(selection.head()));
+                continue;
+            }
+            let mut end_row = selection.end.row;
+            if selection.end.column == 0 && end_row > selection.start.row {
+                end_row -= 1;
+            }
+            for row in selection.start.row..=end_row {
+                if last_row != Some(row) {
+                    positions.push(buffer.anchor_before(Point::new(row, 0)));
+                    last_row = Some(row);
+                }
+            }
+        }
+
+        let steps = positions
+            .into_iter()
+            .flat_map(|position| iter::once(ReplayStep::MoveTo(position)).chain(steps.clone()))
+            .take(MAX_REPLAYED_STEPS + 1)
+            .collect();
+        Replayer::start(cx.entity().downgrade(), steps, window, cx);
+    }
+
+    pub fn save_macro(&mut self, action: &SaveMacro, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(workspace) = self.workspace() else {
+            return;
+        };
+        let last_macro = cx.default_global::<MacroRecorder>().last_macro.clone();
+        let steps = macro_steps_to_save(&last_macro, cx);
+        if steps.is_empty() {
+            return;
+        }
+
+        let fs = workspace.read(cx).app_state().fs.clone();
+        match action.name.clone() {
+            Some(name) => write_macro(fs, name, steps, cx),
+            None => workspace.update(cx, |workspace, cx| {
+                workspace.toggle_modal(window, cx, |window, cx| {
+                    SaveMacroModal::new(fs, steps, window, cx)
+                })
+            }),
+        }
+    }
+
+    /// Returns the macro saved under `name`, or the last one recorded.
+    fn macro_to_play(&self, name: Option<&str>, cx: &mut Context<Self>) -> Option<Vec<ReplayStep>> {
+        let Some(name) = name else {
+            let last_macro = &cx.try_global::<MacroRecorder>()?.last_macro;
+            return (!last_macro.is_empty()).then(|| last_macro.clone());
+        };
+
+        let steps = EditorSettings::get_global(cx)
+            .macros
+            .get(name)
+            .with_context(|| format!("No macro named {name:?} in the settings"))
+            .and_then(|steps| {
+                steps
+                    .iter()
+                    .map(|step| step.to_replay_step(cx))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Invalid step in macro {name:?}"))
+            });
+        match steps {
+            Ok(steps) => Some(steps),
+            Err(error) => {
+                if let Some(workspace) = self.workspace() {
+                    workspace.update(cx, |workspace, cx| workspace.show_error(&error, cx));
+                } else {
+                    log::error!("{error:?}");
+                }
+                None
+            }
+        }
+    }
+}
+
+fn write_macro(fs: Arc<dyn Fs>, name: String, steps: Vec<MacroStep>, cx: &App) {
+    update_settings_file::<EditorSettings>(fs, cx, move |settings, _| {
+        settings
+            .macros
+            .get_or_insert_with(Default::default)
+            .insert(name, steps);
+    });
+}
+
+/// Asks for the name to save the last recorded macro under.
+struct SaveMacroModal {
+    name_editor: Entity<Editor>,
+    fs: Arc<dyn Fs>,
+    steps: Vec<MacroStep>,
+}
+
+impl ModalView for SaveMacroModal {}
+
+impl EventEmitter<DismissEvent> for SaveMacroModal {}
+
+impl Focusable for SaveMacroModal {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.name_editor.focus_handle(cx)
+    }
+}
+
+impl SaveMacroModal {
+    fn new(
+        fs: Arc<dyn Fs>,
+        steps: Vec<MacroStep>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let name_editor = cx.new(|cx| {
+            let mut editor = Editor::single_line(window, cx);
+            editor.set_placeholder_text("Macro name", cx);
+            editor
+        });
+        Self {
+            name_editor,
+            fs,
+            steps,
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, _: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, _: &mut Window, cx: &mut Context<Self>) {
+        let name = self.name_editor.read(cx).text(cx).trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        write_macro(self.fs.clone(), name, std::mem::take(&mut self.steps), cx);
+        cx.emit(DismissEvent);
+    }
+}
+
+impl Render for SaveMacroModal {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .w(rems(24.))
+            .elevation_2(cx)
+            .key_context("SaveMacro")
+            .on_action(cx.listener(Self::cancel))
+            .on_action(cx.listener(Self::confirm))
+            .child(
+                div()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .px_2()
+                    .py_1()
+                    .child(self.name_editor.clone()),
+            )
+            .child(h_flex().px_2().py_1().gap_1().child(
+                Label::new("Save the last recorded macro to the settings").color(Color::Muted),
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        actions::MoveDown, editor_tests::init_test, test::editor_test_context::EditorTestContext,
+    };
+    use gpui::{KeyBinding, TestAppContext, VisualContext as _};
+    use serde_json::json;
+
+    fn init_macro_test(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| cx.bind_keys([KeyBinding::new("down", MoveDown, Some("Editor"))]));
+    }
+
+    fn last_macro(cx: &mut EditorTestContext) -> Vec<MacroStep> {
+        cx.update(|_, cx| macro_steps_to_save(&cx.global::<MacroRecorder>().last_macro, cx))
+    }
+
+    #[gpui::test]
+    async fn test_record_and_replay(cx: &mut TestAppContext) {
+        init_macro_test(cx);
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇab\ncd\nef\ngh");
+
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_macro_recording(&ToggleMacroRecording, window, cx);
+            editor.handle_input("x", window, cx);
+        });
+        cx.simulate_keystrokes("down");
+
+        // Text typed into another editor, like a search bar, is not part of the macro.
+        let other_editor = cx.new_window_entity(|window, cx| Editor::single_line(window, cx));
+        other_editor.update_in(&mut cx.cx, |editor, window, cx| {
+            editor.handle_input("query", window, cx)
+        });
+
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_macro_recording(&ToggleMacroRecording, window, cx);
+        });
+        cx.assert_editor_state("xab\ncˇd\nef\ngh");
+        assert_eq!(
+            last_macro(&mut cx),
+            [
+                MacroStep::Text {
+                    text: "x".into(),
+                    replace_utf16_range: None,
+                },
+                MacroStep::Action("editor::MoveDown".into()),
+            ]
+        );
+
+        cx.update_editor(|editor, window, cx| {
+            editor.play_macro(
+                &PlayMacro {
+                    name: None,
+                    times: Some(2),
+                },
+                window,
+                cx,
+            );
+        });
+        cx.run_until_parked();
+        cx.assert_editor_state("xab\ncxd\nexf\ngˇh");
+    }
+
+    #[gpui::test]
+    async fn test_play_macro_on_each_line(cx: &mut TestAppContext) {
+        init_macro_test(cx);
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("ˇone\ntwo\nthree\nfour");
+
+        cx.update_editor(|editor, window, cx| {
+            editor.toggle_macro_recording(&ToggleMacroRecording, window, cx);
+            editor.handle_input("- ", window, cx);
+            editor.toggle_macro_recording(&ToggleMacroRecording, window, cx);
+        });
+
+        cx.set_state("o«ne\ntwo\nthˇ»ree\nfour");
+        cx.update_editor(|editor, window, cx| {
+            editor.play_macro_on_each_line(&PlayMacroOnEachLine { name: None }, window, cx);
+        });
+        cx.run_until_parked();
+        cx.assert_editor_state("- one\n- two\n- ˇthree\nfour");
+    }
+
+    #[test]
+    fn test_macro_step_formats() {
+        let steps: Vec<MacroStep> = serde_json::from_value(json!([
+            "editor::MoveDown",
+            ["editor::MoveToBeginningOfLine", { "stop_at_indent": true }],
+            { "text": "// " },
+            { "text": "x", "replace_utf16_range": { "start": -1, "end": 0 } },
+            { "keystroke": "ctrl-k" },
+        ]))
+        .unwrap();
+        assert_eq!(
+            steps,
+            [
+                MacroStep::Action("editor::MoveDown".into()),
+                MacroStep::ActionWithArguments(
+                    "editor::MoveToBeginningOfLine".into(),
+                    json!({ "stop_at_indent": true })
+                ),
+                MacroStep::Text {
+                    text: "// ".into(),
+                    replace_utf16_range: None,
+                },
+                MacroStep::Text {
+                    text: "x".into(),
+                    replace_utf16_range: Some(-1..0),
+                },
+                MacroStep::Keystroke {
+                    keystroke: "ctrl-k".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&steps[2]).unwrap(),
+            json!({ "text": "// " })
+        );
+    }
+}
@@ -14,10 +14,11 @@ use open_ai::OpenAiEmbeddingModel;
 use project::Project;
 use reqwest_client::ReqwestClient;
 use semantic_index::{
-    EmbeddingProvider, OpenAiEmbeddingProvider, ProjectIndex, SemanticDb, Status,
+    EmbeddingProvider, OpenAiEmbeddingProvider, ProjectIndex, SemanticDb, SemanticIndexSettings,
+    Status,
 };
 use serde::{Deserialize, Serialize};
-use settings::SettingsStore;
+use settings::{Settings as _, SettingsStore};
 use smol::channel::bounded;
 use smol::io::AsyncReadExt;
 use smol::Timer;
@@ -264,6 +265,7 @@ async fn run_evaluation(
         client::init_settings(cx);
         language::init(cx);
         Project::init_settings(cx);
+        SemanticIndexSettings::register(cx);
         http_client = Some(cx.http_client());
         cx.update_flags(false, vec![]);
     })
@@ -0,0 +1,432 @@
+//! Edit predictions from fill-in-the-middle prompts, completed by a model served
+//! by Ollama or an OpenAI-compatible API, such as one running on the same machine.
+
+use anyhow::{Context as _, Result};
+use gpui::{App, Context, Entity, EntityId, Task};
+use http_client::HttpClient;
+use inline_completion::{Direction, EditPredictionProvider, InlineCompletion};
+use language::{
+    language_settings::{all_language_settings, FimApi, FimSettings, FimTemplate, FimTokens},
+    Anchor, Buffer, BufferSnapshot,
+};
+use project::Project;
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+use text::{Point, ToOffset, ToPoint};
+
+/// How many completions to remember, so that the same prompt isn't sent twice.
+const MAX_CACHED_COMPLETIONS: usize = 64;
+
+pub struct FimCompletionProvider {
+    http_client: Arc<dyn HttpClient>,
+    current_completion: Option<CurrentCompletion>,
+    pending_refresh: Option<Task<Result<()>>>,
+    cache: VecDeque<(u64, String)>,
+}
+
+struct CurrentCompletion {
+    buffer_id: EntityId,
+    /// The cursor position the completion was requested at.
+    position: Anchor,
+    text: String,
+}
+
+impl FimCompletionProvider {
+    pub fn new(http_client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            http_client,
+            current_completion: None,
+            pending_refresh: None,
+            cache: VecDeque::new(),
+        }
+    }
+
+    fn cached_completion(&self, key: u64) -> Option<String> {
+        self.cache
+            .iter()
+            .find(|(cached_key, _)| *cached_key == key)
+            .map(|(_, text)| text.clone())
+    }
+
+    fn cache_completion(&mut self, key: u64, text: String) {
+        if self.cache.len() >= MAX_CACHED_COMPLETIONS {
+            self.cache.pop_front();
+        }
+        self.cache.push_back((key, text));
+    }
+}
+
+impl EditPredictionProvider for FimCompletionProvider {
+    fn name() -> &'static str {
+        "fim"
+    }
+
+    fn display_name() -> &'static str {
+        "Fill in the Middle"
+    }
+
+    fn show_completions_in_menu() -> bool {
+        false
+    }
+
+    fn is_enabled(&self, _buffer: &Entity<Buffer>, _cursor_position: Anchor, cx: &App) -> bool {
+        let settings = &all_language_settings(None, cx).edit_predictions.fim;
+        !settings.api_url.is_empty() && !settings.model.is_empty()
+    }
+
+    fn is_refreshing(&self) -> bool {
+        self.pending_refresh.is_some()
+    }
+
+    fn refresh(
+        &mut self,
+        _project: Option<Entity<Project>>,
+        buffer: Entity<Buffer>,
+        cursor_position: Anchor,
+        debounce: bool,
+        cx: &mut Context<Self>,
+    ) {
+        let settings = all_language_settings(None, cx).edit_predictions.fim.clone();
+        let snapshot = buffer.read(cx).snapshot();
+        let prompt = prompt_for_cursor(&snapshot, cursor_position, &settings);
+        let key = cache_key(&settings, &prompt.text);
+        let buffer_id = buffer.entity_id();
+        // Anchor before the cursor, so text typed there lands after the position
+        // and `suggest` can match it against the completion.
+        let cursor_position = snapshot.anchor_before(cursor_position.to_offset(&snapshot));
+
+        if let Some(text) = self.cached_completion(key) {
+            self.pending_refresh = None;
+            self.current_completion = Some(CurrentCompletion {
+                buffer_id,
+                position: cursor_position,
+                text,
+            });
+            cx.notify();
+            return;
+        }
+
+        // Replacing the pending refresh drops its task, which cancels its request.
+        let http_client = self.http_client.clone();
+        self.pending_refresh = Some(cx.spawn(|this, mut cx| async move {
+            if debounce {
+                cx.background_executor()
+                    .timer(Duration::from_millis(settings.debounce_ms))
+                    .await;
+            }
+
+            let completion = request_completion(http_client.as_ref(), &settings, prompt).await;
+
+            this.update(&mut cx, |this, cx| {
+                this.pending_refresh = None;
+                let text = completion?;
+                this.cache_completion(key, text.clone());
+                this.current_completion = Some(CurrentCompletion {
+                    buffer_id,
+                    position: cursor_position,
+                    text,
+                });
+                cx.notify();
+                Ok(())
+            })?
+        }));
+    }
+
+    fn cycle(
+        &mut self,
+        _buffer: Entity<Buffer>,
+        _cursor_position: Anchor,
+        _direction: Direction,
+        _cx: &mut Context<Self>,
+    ) {
+    }
+
+    fn accept(&mut self, _cx: &mut Context<Self>) {
+        self.pending_refresh = None;
+        self.current_completion = None;
+    }
+
+    fn discard(&mut self, _cx: &mut Context<Self>) {
+        self.pending_refresh = None;
+        self.current_completion = None;
+    }
+
+    fn suggest(
+        &mut self,
+        buffer: &Entity<Buffer>,
+        cursor_position: Anchor,
+        cx: &mut Context<Self>,
+    ) -> Option<InlineCompletion> {
+        let completion = self.current_completion.as_ref()?;
+        if completion.buffer_id != buffer.entity_id() {
+            return None;
+        }
+
+        let snapshot = buffer.read(cx).snapshot();
+        if cursor_position.cmp(&completion.position, &snapshot).is_lt() {
+            return None;
+        }
+
+        // Keep suggesting the completion while what's typed after it was requested matches it.
+        let typed = snapshot
+            .text_for_range(completion.position..cursor_position)
+            .collect::<String>();
+        let text = completion.text.strip_prefix(typed.as_str())?;
+
+        let cursor = cursor_position.to_point(&snapshot);
+        let rest_of_line = snapshot
+            .text_for_range(cursor..Point::new(cursor.row, snapshot.line_len(cursor.row)))
+            .collect::<String>();
+        let text = trim_completion(text, &rest_of_line);
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        let position = snapshot.anchor_after(cursor);
+        Some(InlineCompletion {
+            id: None,
+            edits: vec![(position..position, text.to_string())],
+            edit_preview: None,
+        })
+    }
+}
+
+struct FimPrompt {
+    text: String,
+    stop: Vec<String>,
+}
+
+fn prompt_for_cursor(
+    snapshot: &BufferSnapshot,
+    cursor_position: Anchor,
+    settings: &FimSettings,
+) -> FimPrompt {
+    let cursor = cursor_position.to_point(snapshot);
+    let start = Point::new(cursor.row.saturating_sub(settings.prefix_lines), 0);
+    let end_row = cursor
+        .row
+        .saturating_add(settings.suffix_lines)
+        .min(snapshot.max_point().row);
+    let end = Point::new(end_row, snapshot.line_len(end_row));
+
+    let prefix = snapshot.text_for_range(start..cursor).collect::<String>();
+    let suffix = snapshot.text_for_range(cursor..end).collect::<String>();
+    let tokens = settings
+        .tokens
+        .clone()
+        .unwrap_or_else(|| template_tokens(settings.template, &settings.model));
+
+    FimPrompt {
+        text: format!(
+            "{}{prefix}{}{suffix}{}",
+            tokens.prefix, tokens.suffix, tokens.middle
+        ),
+        stop: tokens.stop,
+    }
+}
+
+/// The tokens of a template, picking the template from the model's name when it's `auto`.
+fn template_tokens(template: FimTemplate, model: &str) -> FimTokens {
+    let template = match template {
+        FimTemplate::Auto => {
+            let model = model.to_lowercase();
+            if model.contains("qwen") {
+                FimTemplate::QwenCoder
+            } else if model.contains("deepseek") {
+                FimTemplate::DeepseekCoder
+            } else if model.contains("codellama") || model.contains("code-llama") {
+                FimTemplate::CodeLlama
+            } else {
+                // Many other code models, such as StarCoder's descendants, share its tokens.
+                FimTemplate::StarCoder
+            }
+        }
+        template => template,
+    };
+
+    let tokens = |prefix: &str, suffix: &str, middle: &str, stop: &[&str]| FimTokens {
+        prefix: prefix.to_string(),
+        suffix: suffix.to_string(),
+        middle: middle.to_string(),
+        stop: stop.iter().map(|stop| stop.to_string()).collect(),
+    };
+    match template {
+        FimTemplate::Auto | FimTemplate::QwenCoder => tokens(
+            "<|fim_prefix|>",
+            "<|fim_suffix|>",
+            "<|fim_middle|>",
+            &[
+                "<|endoftext|>",
+                "<|fim_pad|>",
+                "<|file_sep|>",
+                "<|repo_name|>",
+            ],
+        ),
+        FimTemplate::StarCoder => tokens(
+            "<fim_prefix>",
+            "<fim_suffix>",
+            "<fim_middle>",
+            &["<|endoftext|>", "<file_sep>"],
+        ),
+        FimTemplate::DeepseekCoder => tokens(
+            "<｜fim▁begin｜>",
+            "<｜fim▁hole｜>",
+            "<｜fim▁end｜>",
+            &["<｜end▁of▁sentence｜>"],
+        ),
+        FimTemplate::CodeLlama => tokens("<PRE> ", " <SUF>", " <MID>", &["<EOT>"]),
+    }
+}
+
+fn cache_key(settings: &FimSettings, prompt: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    settings.api.hash(&mut hasher);
+    settings.api_url.hash(&mut hasher);
+    settings.model.hash(&mut hasher);
+    settings.max_tokens.hash(&mut hasher);
+    prompt.hash(&mut hasher);
+    hasher.finish()
+}
+
+async fn request_completion(
+    client: &dyn HttpClient,
+    settings: &FimSettings,
+    prompt: FimPrompt,
+) -> Result<String> {
+    match settings.api {
+        FimApi::Ollama => {
+            let request = ollama::GenerateRequest {
+                model: settings.model.clone(),
+                prompt: prompt.text,
+                raw: true,
+                stream: false,
+                keep_alive: Default::default(),
+                options: Some(ollama::ChatOptions {
+                    num_predict: Some(settings.max_tokens as isize),
+                    stop: Some(prompt.stop),
+                    temperature: Some(0.),
+                    ..Default::default()
+                }),
+            };
+            let response = ollama::generate(client, &settings.api_url, request).await?;
+            Ok(response.response)
+        }
+        FimApi::OpenAiCompatible => {
+            let request = open_ai::TextCompletionRequest {
+                model: settings.model.clone(),
+                prompt: prompt.text,
+                max_tokens: settings.max_tokens,
+                temperature: 0.,
+                stop: prompt.stop,
+                stream: false,
+            };
+            let response =
+                open_ai::text_completion(client, &settings.api_url, None, &[], request).await?;
+            response
+                .choices
+                .into_iter()
+                .next()
+                .map(|choice| choice.text)
+                .context("the API returned no completion")
+        }
+    }
+}
+
+/// Cuts the completion to the end of its first line when there's text after the
+/// cursor on the cursor's line, so the completion doesn't split that line.
+fn trim_completion<'a>(text: &'a str, rest_of_line: &str) -> &'a str {
+    let text = if rest_of_line.trim().is_empty() {
+        text
+    } else {
+        text.split('\n').next().unwrap_or_default()
+    };
+    text.trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::AppContext as _;
+    use http_client::FakeHttpClient;
+
+    #[test]
+    fn test_template_tokens() {
+        let prefix = |template, model| template_tokens(template, model).prefix;
+
+        assert_eq!(
+            prefix(FimTemplate::Auto, "qwen2.5-coder:1.5b-base"),
+            "<|fim_prefix|>"
+        );
+        assert_eq!(
+            prefix(FimTemplate::Auto, "deepseek-coder-v2:16b"),
+            "<｜fim▁begin｜>"
+        );
+        assert_eq!(prefix(FimTemplate::Auto, "codellama:7b-code"), "<PRE> ");
+        assert_eq!(prefix(FimTemplate::Auto, "starcoder2:3b"), "<fim_prefix>");
+        assert_eq!(
+            prefix(FimTemplate::StarCoder, "qwen2.5-coder:1.5b-base"),
+            "<fim_prefix>"
+        );
+    }
+
+    #[test]
+    fn test_trim_completion() {
+        assert_eq!(
+            trim_completion("foo();\n    bar();\n", ""),
+            "foo();\n    bar();"
+        );
+        assert_eq!(trim_completion("foo, bar\n    baz", ")"), "foo, bar");
+    }
+
+    #[gpui::test]
+    fn test_prompt_for_cursor(cx: &mut App) {
+        let buffer = cx.new(|cx| Buffer::local("one\ntwo\nthree\nfour\nfive", cx));
+        let snapshot = buffer.read(cx).snapshot();
+        let cursor = snapshot.anchor_before(Point::new(2, 2));
+        let settings = FimSettings {
+            template: FimTemplate::StarCoder,
+            prefix_lines: 1,
+            suffix_lines: 1,
+            ..Default::default()
+        };
+
+        let prompt = prompt_for_cursor(&snapshot, cursor, &settings);
+        assert_eq!(
+            prompt.text,
+            "<fim_prefix>two\nth<fim_suffix>ree\nfour<fim_middle>"
+        );
+        assert_eq!(prompt.stop, ["<|endoftext|>", "<file_sep>"]);
+    }
+
+    #[gpui::test]
+    fn test_suggest_while_typing_the_completion(cx: &mut App) {
+        let buffer = cx.new(|cx| Buffer::local("let x = ;", cx));
+        let provider =
+            cx.new(|_| FimCompletionProvider::new(FakeHttpClient::with_404_response()));
+        let position = buffer.read(cx).snapshot().anchor_before(Point::new(0, 8));
+        provider.update(cx, |provider, _| {
+            provider.current_completion = Some(CurrentCompletion {
+                buffer_id: buffer.entity_id(),
+                position,
+                text: "foo(1, 2)".into(),
+            });
+        });
+
+        let suggestion = |cx: &mut App| {
+            let cursor = buffer.read(cx).snapshot().anchor_after(Point::new(0, 8 + 4));
+            provider
+                .update(cx, |provider, cx| provider.suggest(&buffer, cursor, cx))
+                .map(|completion| completion.edits[0].1.clone())
+        };
+
+        buffer.update(cx, |buffer, cx| buffer.edit([(8..8, "foo(")], None, cx));
+        assert_eq!(suggestion(cx).as_deref(), Some("1, 2)"));
+
+        buffer.update(cx, |buffer, cx| buffer.edit([(11..12, "[")], None, cx));
+        assert_eq!(suggestion(cx), None);
+    }
+}
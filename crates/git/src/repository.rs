@@ -97,6 +97,24 @@ pub struct CommitDetails {
     pub committer_name: SharedString,
 }
 
+/// Which changes to compare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffType {
+    /// The staged changes, from HEAD to the index.
+    HeadToIndex,
+    /// The changes of the current branch, committed or not, since it diverged from `base`.
+    MergeBaseToWorktree { base: String },
+}
+
+/// The old and new text of a file that differs between the two sides of a [`DiffType`]. The
+/// text is `None` on the side where the file doesn't exist, or isn't valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: RepoPath,
+    pub old_text: Option<String>,
+    pub new_text: Option<String>,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Remote {
     pub name: SharedString,
@@ -149,6 +167,9 @@ pub trait GitRepository: Send + Sync {
 
     fn show(&self, commit: &str) -> Result<CommitDetails>;
 
+    /// Returns the files that changed between the two sides of the diff, along with their text.
+    fn diff(&self, diff: DiffType) -> Result<Vec<FileDiff>>;
+
     fn blame(&self, path: &Path, content: Rope) -> Result<crate::blame::Blame>;
 
     /// Returns the absolute path to the repository. For worktrees, this will be the path to the
@@ -266,6 +287,66 @@ impl GitRepository for RealGitRepository {
         Ok(details)
     }
 
+    fn diff(&self, diff: DiffType) -> Result<Vec<FileDiff>> {
+        let repo = self.repository.lock();
+        let mut options = git2::DiffOptions::new();
+        let git_diff = match &diff {
+            DiffType::HeadToIndex => {
+                // Before the first commit, everything that's staged is new.
+                let head_tree = match repo.head() {
+                    Ok(head) => Some(head.peel_to_tree()?),
+                    Err(error) if error.code() == git2::ErrorCode::UnbornBranch => None,
+                    Err(error) => return Err(error.into()),
+                };
+                repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options))?
+            }
+            DiffType::MergeBaseToWorktree { base } => {
+                let base = repo
+                    .revparse_single(base)
+                    .with_context(|| format!("failed to find {base:?}"))?
+                    .peel_to_commit()?
+                    .id();
+                let head = repo.head()?.peel_to_commit()?.id();
+                let merge_base = repo.find_commit(repo.merge_base(base, head)?)?;
+                options.include_untracked(true).recurse_untracked_dirs(true);
+                repo.diff_tree_to_workdir_with_index(Some(&merge_base.tree()?), Some(&mut options))?
+            }
+        };
+
+        let read_blob = |oid: git2::Oid| -> Option<String> {
+            if oid.is_zero() {
+                return None;
+            }
+            let content = repo.find_blob(oid).log_err()?.content().to_owned();
+            String::from_utf8(content).ok()
+        };
+        let workdir = repo.workdir().map(Path::to_path_buf);
+
+        let mut files = Vec::new();
+        for delta in git_diff.deltas() {
+            let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+                continue;
+            };
+            let old_text = read_blob(delta.old_file().id());
+            let new_text = match &diff {
+                DiffType::HeadToIndex => read_blob(delta.new_file().id()),
+                // Files in the working tree may not have been hashed, so they're read instead.
+                DiffType::MergeBaseToWorktree { .. } => workdir
+                    .as_ref()
+                    .and_then(|workdir| std::fs::read_to_string(workdir.join(path)).ok()),
+            };
+            if old_text.is_none() && new_text.is_none() {
+                continue;
+            }
+            files.push(FileDiff {
+                path: RepoPath::new(path.to_path_buf()),
+                old_text,
+                new_text,
+            });
+        }
+        Ok(files)
+    }
+
     fn reset(&self, commit: &str, mode: ResetMode) -> Result<()> {
         let working_directory = self.working_directory()?;
 
@@ -788,6 +869,33 @@ impl GitRepository for FakeGitRepository {
         unimplemented!()
     }
 
+    fn diff(&self, diff: DiffType) -> Result<Vec<FileDiff>> {
+        let DiffType::HeadToIndex = diff else {
+            unimplemented!()
+        };
+        let state = self.state.lock();
+        let mut paths = state
+            .head_contents
+            .keys()
+            .chain(state.index_contents.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let old_text = state.head_contents.get(&path).cloned();
+                let new_text = state.index_contents.get(&path).cloned();
+                (old_text != new_text).then_some(FileDiff {
+                    path,
+                    old_text,
+                    new_text,
+                })
+            })
+            .collect())
+    }
+
     fn reset(&self, _: &str, _: ResetMode) -> Result<()> {
         unimplemented!()
     }
@@ -1131,3 +1239,25 @@ fn test_branches_parsing() {
         }]
     )
 }
+
+#[test]
+fn test_staged_diff_without_commits() {
+    let tree = util::test::TempTree::new(serde_json::json!({
+        "new.txt": "one\n",
+        "unstaged.txt": "two\n",
+    }));
+    let repo = git2::Repository::init(tree.path()).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("new.txt")).unwrap();
+    index.write().unwrap();
+
+    let repo = RealGitRepository::new(repo, None, Arc::default());
+    assert_eq!(
+        repo.diff(DiffType::HeadToIndex).unwrap(),
+        vec![FileDiff {
+            path: RepoPath::new(PathBuf::from("new.txt")),
+            old_text: None,
+            new_text: Some("one\n".into()),
+        }]
+    );
+}
@@ -134,6 +134,23 @@ pub struct GenerateContentRequest {
 pub struct GenerateContentResponse {
     pub candidates: Option<Vec<GenerateContentCandidate>>,
     pub prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    pub usage_metadata: Option<UsageMetadata>,
+}
+
+/// The tokens used by a request so far.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageMetadata {
+    /// The tokens of the prompt, including the cached ones.
+    #[serde(default)]
+    pub prompt_token_count: u32,
+    #[serde(default)]
+    pub cached_content_token_count: u32,
+    #[serde(default)]
+    pub candidates_token_count: u32,
+    #[serde(default)]
+    pub total_token_count: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,357 @@
+//! A BM25 full-text index over the Markdown of indexed docs, so that docs can be found by
+//! what they say, rather than only by the paths of their items.
+
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use collections::{BTreeMap, HashMap};
+use heed::types::{DecodeIgnore, SerdeBincode, Str};
+use heed::{Database, RoTxn, RwTxn};
+use util::terms::{is_identifier_char, terms_in};
+
+use crate::MarkdownDocs;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// The longest snippet we show for a match, in bytes.
+const MAX_SNIPPET_LEN: usize = 160;
+
+/// The longest key LMDB accepts, in bytes. Postings whose key would be longer are left out
+/// of the index, rather than failing to store the entry.
+const MAX_KEY_LEN: usize = 511;
+
+/// An entry whose Markdown matched a full-text search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FullTextMatch {
+    pub key: String,
+    pub score: f32,
+    /// The line of the entry's Markdown that best matches the query.
+    pub snippet: String,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct FullTextIndex {
+    /// Maps `{term}\0{entry key}` to the number of times the term appears in the entry.
+    postings: Database<Str, SerdeBincode<u32>>,
+    /// Maps each entry's key to the number of terms in its Markdown, counting repeats.
+    term_counts: Database<Str, SerdeBincode<u32>>,
+}
+
+impl FullTextIndex {
+    pub fn new(env: &heed::Env, txn: &mut RwTxn<'_>) -> Result<Self> {
+        Ok(Self {
+            postings: env.create_database(txn, Some("full_text_postings"))?,
+            term_counts: env.create_database(txn, Some("full_text_term_counts"))?,
+        })
+    }
+
+    /// Indexes every entry in the database, for databases that were populated before they
+    /// had a full-text index.
+    pub fn backfill(
+        &self,
+        txn: &mut RwTxn<'_>,
+        entries: Database<SerdeBincode<String>, SerdeBincode<MarkdownDocs>>,
+    ) -> Result<()> {
+        if !self.term_counts.is_empty(txn)? {
+            return Ok(());
+        }
+
+        let keys = entries
+            .remap_data_type::<DecodeIgnore>()
+            .iter(txn)?
+            .map(|entry| entry.map(|(key, _)| key))
+            .collect::<Result<Vec<_>, _>>()?;
+        for key in keys {
+            if let Some(docs) = entries.get(txn, &key)? {
+                self.insert(txn, &key, &docs.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert(&self, txn: &mut RwTxn<'_>, key: &str, markdown: &str) -> Result<()> {
+        let mut term_frequencies = BTreeMap::<String, u32>::default();
+        let mut term_count = 0;
+        for term in terms_in(markdown) {
+            *term_frequencies.entry(term).or_default() += 1;
+            term_count += 1;
+        }
+
+        for (term, frequency) in term_frequencies {
+            let posting_key = posting_key(&term, key);
+            if posting_key.len() <= MAX_KEY_LEN {
+                self.postings.put(txn, &posting_key, &frequency)?;
+            }
+        }
+        self.term_counts.put(txn, key, &term_count)?;
+        Ok(())
+    }
+
+    /// Removes the entry with the given key, whose Markdown we re-read its terms from.
+    pub fn remove(&self, txn: &mut RwTxn<'_>, key: &str, markdown: &str) -> Result<()> {
+        let mut terms = terms_in(markdown);
+        terms.sort_unstable();
+        terms.dedup();
+        for term in terms {
+            let posting_key = posting_key(&term, key);
+            if posting_key.len() <= MAX_KEY_LEN {
+                self.postings.delete(txn, &posting_key)?;
+            }
+        }
+        self.term_counts.delete(txn, key)?;
+        Ok(())
+    }
+
+    /// Scores the entries containing any of the query's terms with BM25, returning the best
+    /// matches along with a snippet of each.
+    pub fn search(
+        &self,
+        txn: &RoTxn<'_>,
+        entries: Database<SerdeBincode<String>, SerdeBincode<MarkdownDocs>>,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<FullTextMatch>> {
+        let mut query_terms = terms_in(query);
+        query_terms.sort_unstable();
+        query_terms.dedup();
+        if query_terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut entry_count = 0usize;
+        let mut total_term_count = 0u64;
+        for entry in self.term_counts.iter(txn)? {
+            let (_, term_count) = entry?;
+            entry_count += 1;
+            total_term_count += term_count as u64;
+        }
+        if entry_count == 0 {
+            return Ok(Vec::new());
+        }
+        let average_term_count = (total_term_count as f32 / entry_count as f32).max(1.);
+
+        let mut scores = HashMap::<String, f32>::default();
+        for term in &query_terms {
+            let prefix = posting_prefix(term);
+            let postings = self
+                .postings
+                .prefix_iter(txn, &prefix)?
+                .map(|entry| {
+                    entry.map(|(key, frequency)| (key[prefix.len()..].to_string(), frequency))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let document_frequency = postings.len() as f32;
+            let idf = (1.
+                + (entry_count as f32 - document_frequency + 0.5) / (document_frequency + 0.5))
+                .ln();
+            for (key, frequency) in postings {
+                let term_count = self
+                    .term_counts
+                    .get(txn, &key)?
+                    .map_or(average_term_count, |term_count| term_count as f32);
+                let frequency = frequency as f32;
+                let score = idf * frequency * (K1 + 1.)
+                    / (frequency + K1 * (1. - B + B * term_count / average_term_count));
+                *scores.entry(key).or_default() += score;
+            }
+        }
+
+        let mut scores = scores.into_iter().collect::<Vec<_>>();
+        scores.sort_unstable_by(|(key_a, score_a), (key_b, score_b)| {
+            score_b
+                .partial_cmp(score_a)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| key_a.cmp(key_b))
+        });
+        scores.truncate(limit);
+
+        let mut matches = Vec::with_capacity(scores.len());
+        for (key, score) in scores {
+            let snippet = entries
+                .get(txn, &key)?
+                .map(|docs| extract_snippet(&docs.0, &query_terms))
+                .unwrap_or_default();
+            matches.push(FullTextMatch {
+                key,
+                score,
+                snippet,
+            });
+        }
+        Ok(matches)
+    }
+}
+
+fn posting_prefix(term: &str) -> String {
+    format!("{term}\0")
+}
+
+fn posting_key(term: &str, key: &str) -> String {
+    format!("{term}\0{key}")
+}
+
+/// Returns the line of the Markdown that contains the most distinct query terms, preferring
+/// prose over code, cut down to the part around its first match when it's too long to show
+/// in full.
+pub(crate) fn extract_snippet(markdown: &str, query_terms: &[String]) -> String {
+    let mut best_line = None;
+    let mut best_rank = (0, false);
+    let mut in_code_block = false;
+    for line in markdown.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut line_terms = terms_in(line);
+        line_terms.sort_unstable();
+        line_terms.dedup();
+        let match_count = line_terms
+            .iter()
+            .filter(|term| query_terms.contains(term))
+            .count();
+        let rank = (match_count, !in_code_block);
+        if best_line.is_none() || rank > best_rank {
+            best_line = Some(line);
+            best_rank = rank;
+        }
+    }
+
+    let Some(line) = best_line else {
+        return String::new();
+    };
+    let line = line.trim_start_matches('#').trim_start();
+    if line.len() <= MAX_SNIPPET_LEN {
+        return line.to_string();
+    }
+
+    // Show some of the context before the first match, but keep the match itself in view.
+    let match_start = first_match_offset(line, query_terms).unwrap_or(0);
+    let mut start = match_start.saturating_sub(MAX_SNIPPET_LEN / 4);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + MAX_SNIPPET_LEN).min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(line[start..end].trim());
+    if end < line.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Returns the byte offset of the first identifier in the line that contains a query term.
+fn first_match_offset(line: &str, query_terms: &[String]) -> Option<usize> {
+    let mut identifier_start = None;
+    for (ix, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        if is_identifier_char(c) {
+            identifier_start.get_or_insert(ix);
+        } else if let Some(start) = identifier_start.take() {
+            if terms_in(&line[start..ix])
+                .iter()
+                .any(|term| query_terms.contains(term))
+            {
+                return Some(start);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(1024 * 1024)
+                .max_dbs(3)
+                .open(temp_dir.path())
+                .unwrap()
+        };
+        let mut txn = env.write_txn().unwrap();
+        let entries: Database<SerdeBincode<String>, SerdeBincode<MarkdownDocs>> =
+            env.create_database(&mut txn, Some("entries")).unwrap();
+        let index = FullTextIndex::new(&env, &mut txn).unwrap();
+
+        let docs = [
+            (
+                "std::vec::Vec",
+                "A contiguous growable array type.".to_string(),
+            ),
+            (
+                "std::collections::HashMap",
+                "A hash map with a growable capacity.".to_string(),
+            ),
+            // Terms too long to fit in a key, such as encoded data, are left out.
+            ("std::encoded", format!("Encoded {}", "a1".repeat(300))),
+        ];
+        for (key, markdown) in &docs {
+            index.insert(&mut txn, key, markdown).unwrap();
+            entries
+                .put(&mut txn, &key.to_string(), &MarkdownDocs(markdown.clone()))
+                .unwrap();
+        }
+
+        let keys = |txn: &RoTxn<'_>, query: &str| {
+            index
+                .search(txn, entries, query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|full_text_match| full_text_match.key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            keys(&txn, "growable array"),
+            ["std::vec::Vec", "std::collections::HashMap"]
+        );
+        assert_eq!(keys(&txn, "encoded"), ["std::encoded"]);
+
+        index.remove(&mut txn, "std::vec::Vec", &docs[0].1).unwrap();
+        index.remove(&mut txn, "std::encoded", &docs[2].1).unwrap();
+        assert_eq!(keys(&txn, "growable array"), ["std::collections::HashMap"]);
+        assert!(keys(&txn, "encoded").is_empty());
+    }
+
+    #[test]
+    fn test_extract_snippet() {
+        let query_terms = vec!["capacity".to_string(), "hashmap".to_string()];
+        let markdown = "# std::collections::HashMap\n\n```rust\nlet map = HashMap::with_capacity(10);\n```\n\nCreates an empty `HashMap` with at least the specified capacity.\n";
+        assert_eq!(
+            extract_snippet(markdown, &query_terms),
+            "Creates an empty `HashMap` with at least the specified capacity."
+        );
+
+        // Headings are shown without their markers.
+        assert_eq!(
+            extract_snippet("# Capacity\n\nUnrelated.", &query_terms),
+            "Capacity"
+        );
+
+        // Long lines are cut down to the part around the first match.
+        let long_line = format!(
+            "{} reserves capacity for more elements {}",
+            "word ".repeat(40).trim_end(),
+            "word ".repeat(40).trim_end()
+        );
+        let snippet = extract_snippet(&long_line, &query_terms);
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("reserves capacity"));
+        assert!(snippet.len() <= MAX_SNIPPET_LEN + 2 * '…'.len_utf8());
+    }
+}
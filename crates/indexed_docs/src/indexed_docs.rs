@@ -1,4 +1,5 @@
 mod extension_indexed_docs_provider;
+mod full_text;
 mod providers;
 mod registry;
 mod store;
@@ -6,7 +7,12 @@ mod store;
 use gpui::App;
 
 pub use crate::extension_indexed_docs_provider::ExtensionIndexedDocsProvider;
+pub use crate::full_text::FullTextMatch;
+pub use crate::providers::go::*;
+pub use crate::providers::mdbook::*;
+pub use crate::providers::python::*;
 pub use crate::providers::rustdoc::*;
+pub use crate::providers::typescript::*;
 pub use crate::registry::*;
 pub use crate::store::*;
 
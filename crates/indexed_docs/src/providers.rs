@@ -1 +1,61 @@
+pub mod go;
+mod html_docs;
+pub mod mdbook;
+pub mod python;
 pub mod rustdoc;
+pub mod typescript;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use collections::HashSet;
+use fs::Fs;
+use futures::StreamExt;
+
+/// Returns the files under the given directory that match `is_match`, sorted by path.
+///
+/// Directories for which `skip_dir` returns `true` are not descended into. Symlinked
+/// directories are followed, but each directory is only visited once, so that symlinks
+/// pointing back up the tree don't make us loop forever.
+async fn find_files(
+    fs: &dyn Fs,
+    dir: &Path,
+    is_match: impl Fn(&Path) -> bool,
+    skip_dir: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut visited_inodes = HashSet::default();
+    if let Ok(Some(metadata)) = fs.metadata(dir).await {
+        visited_inodes.insert(metadata.inode);
+    }
+    let mut dirs_to_visit = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs_to_visit.pop() {
+        let mut entries = fs.read_dir(&dir).await?;
+        while let Some(entry) = entries.next().await {
+            let path = entry?;
+            let Ok(Some(metadata)) = fs.metadata(&path).await else {
+                continue;
+            };
+            if metadata.is_dir {
+                if !skip_dir(&path) && visited_inodes.insert(metadata.inode) {
+                    dirs_to_visit.push(path);
+                }
+            } else if is_match(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the path relative to `root` with `/` separators, which is used in the keys of the
+/// pages of local documentation.
+fn relative_key(root: &Path, path: &Path) -> Option<String> {
+    let relative_path = path.strip_prefix(root).ok()?;
+    let components = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
@@ -0,0 +1,358 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use util::command::new_smol_command;
+
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The headings that `go doc -all` puts before each kind of declaration.
+const SECTION_HEADINGS: &[&str] = &["CONSTANTS", "VARIABLES", "FUNCTIONS", "TYPES"];
+
+/// Indexes the docs of Go packages, as printed by `go doc`.
+///
+/// Packages are named by their import path (e.g., `net/http`), and their functions
+/// and types are stored under `{import path}.{name}`, with methods stored under
+/// `{import path}.{type}.{method}`.
+pub struct GoDocsProvider {
+    project_root: PathBuf,
+}
+
+impl GoDocsProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("go".into())
+    }
+
+    pub fn new(project_root: PathBuf) -> Self {
+        Self { project_root }
+    }
+
+    async fn run_go(&self, args: &[&str]) -> Result<String> {
+        let output = new_smol_command("go")
+            .args(args)
+            .current_dir(&self.project_root)
+            .output()
+            .await
+            .context("failed to run `go`. is Go installed?")?;
+        if !output.status.success() {
+            bail!(
+                "`go {}` exited with {}: {}",
+                args.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for GoDocsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        paths::support_dir().join("docs/go/go-db.1.mdb")
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        static PACKAGES: LazyLock<RwLock<Option<(Vec<PackageName>, Instant)>>> =
+            LazyLock::new(|| RwLock::new(None));
+
+        if let Some((packages, fetched_at)) = &*PACKAGES.read() {
+            if fetched_at.elapsed() < Duration::from_secs(300) {
+                return Ok(packages.clone());
+            }
+        }
+
+        // The module's packages and everything they import, including the standard library.
+        let output = self.run_go(&["list", "-e", "-deps", "./..."]).await?;
+        let mut packages = output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.contains("/internal/"))
+            .map(PackageName::from)
+            .collect::<Vec<_>>();
+        packages.sort();
+        packages.dedup();
+
+        *PACKAGES.write() = Some((packages.clone(), Instant::now()));
+
+        Ok(packages)
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        if package.starts_with('-') {
+            bail!("'{package}' is not a valid Go import path");
+        }
+
+        let output = self.run_go(&["doc", "-all", package.as_ref()]).await?;
+        let docs = parse_go_doc(&output);
+
+        let mut package_markdown = format!("# {package}\n\n");
+        if !docs.overview.is_empty() {
+            writeln!(package_markdown, "{}\n", docs.overview).ok();
+        }
+        if !docs.values.is_empty() {
+            write!(
+                package_markdown,
+                "## Constants and Variables\n\n```go\n{}\n```\n\n",
+                docs.values
+            )
+            .ok();
+        }
+        if !docs.items.is_empty() {
+            package_markdown.push_str("## Index\n\n");
+            for item in &docs.items {
+                writeln!(package_markdown, "- `{}`", item.declaration_line()).ok();
+            }
+        }
+        database
+            .insert(package.to_string(), package_markdown.trim_end().to_string())
+            .await?;
+
+        for item in docs.items {
+            let key = format!("{package}.{}", item.name);
+            let mut markdown = format!("# {key}\n\n```go\n{}\n```\n", item.declaration);
+            if !item.docs.is_empty() {
+                write!(markdown, "\n{}\n", item.docs).ok();
+            }
+            database.insert(key, markdown).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct GoDocs {
+    /// The package clause and the package's documentation.
+    overview: String,
+    /// The declarations of the package's constants and variables, with their docs.
+    values: String,
+    items: Vec<GoDocItem>,
+}
+
+/// A function, method, or type.
+#[derive(Debug, PartialEq)]
+struct GoDocItem {
+    /// The item's name, qualified with its receiver's type for methods.
+    name: String,
+    declaration: String,
+    docs: String,
+}
+
+impl GoDocItem {
+    fn declaration_line(&self) -> &str {
+        let line = self.declaration.lines().next().unwrap_or_default();
+        line.strip_suffix(" {").unwrap_or(line)
+    }
+}
+
+/// Splits the output of `go doc -all` into the package's overview and the docs of
+/// its items.
+///
+/// Declarations start in the first column, and their docs follow, indented by four
+/// spaces.
+fn parse_go_doc(output: &str) -> GoDocs {
+    #[derive(PartialEq)]
+    enum Section {
+        Overview,
+        Values,
+        Items,
+    }
+
+    let mut docs = GoDocs::default();
+    let mut section = Section::Overview;
+    let mut overview = Vec::new();
+    let mut values = Vec::new();
+    let mut current_item: Option<(String, Vec<&str>, Vec<&str>)> = None;
+
+    for line in output.lines() {
+        if SECTION_HEADINGS.contains(&line) {
+            finish_item(current_item.take(), &mut docs);
+            section = match line {
+                "CONSTANTS" | "VARIABLES" => Section::Values,
+                _ => Section::Items,
+            };
+            continue;
+        }
+
+        match section {
+            Section::Overview => overview.push(line),
+            Section::Values => values.push(line),
+            Section::Items => {
+                if let Some(name) = item_name(line) {
+                    finish_item(current_item.take(), &mut docs);
+                    current_item = Some((name, vec![line], Vec::new()));
+                } else if let Some((_, declaration, item_docs)) = current_item.as_mut() {
+                    if line.starts_with("    ") || (line.is_empty() && !item_docs.is_empty()) {
+                        item_docs.push(line);
+                    } else {
+                        declaration.push(line);
+                    }
+                }
+            }
+        }
+    }
+    finish_item(current_item.take(), &mut docs);
+
+    docs.overview = unindent_docs(&overview);
+    docs.values = values.join("\n").trim().to_string();
+    docs
+}
+
+fn finish_item(item: Option<(String, Vec<&str>, Vec<&str>)>, docs: &mut GoDocs) {
+    if let Some((name, declaration, item_docs)) = item {
+        docs.items.push(GoDocItem {
+            name,
+            declaration: declaration.join("\n").trim_end().to_string(),
+            docs: unindent_docs(&item_docs),
+        });
+    }
+}
+
+/// Returns the name of the function, method, or type declared on the given line, if
+/// the line starts a declaration.
+fn item_name(line: &str) -> Option<String> {
+    if let Some(declaration) = line.strip_prefix("type ") {
+        let name = declaration
+            .split(|char: char| char.is_whitespace() || char == '[')
+            .next()?;
+        return Some(name.to_string());
+    }
+
+    let declaration = line.strip_prefix("func ")?;
+    if let Some(method) = declaration.strip_prefix('(') {
+        let (receiver, rest) = method.split_once(')')?;
+        let receiver_type = receiver
+            .split_whitespace()
+            .last()?
+            .trim_start_matches('*')
+            .split('[')
+            .next()?;
+        let name = rest.trim_start().split(['(', '[']).next()?;
+        Some(format!("{receiver_type}.{name}"))
+    } else {
+        let name = declaration.split(['(', '[']).next()?;
+        Some(name.to_string())
+    }
+}
+
+fn unindent_docs(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.strip_prefix("    ").unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_item_name() {
+        assert_eq!(item_name("type Client struct {"), Some("Client".into()));
+        assert_eq!(
+            item_name("type Set[T comparable] struct {"),
+            Some("Set".into())
+        );
+        assert_eq!(
+            item_name("func Get(url string) (resp *Response, err error)"),
+            Some("Get".into())
+        );
+        assert_eq!(
+            item_name("func (c *Client) Do(req *Request) (*Response, error)"),
+            Some("Client.Do".into())
+        );
+        assert_eq!(
+            item_name("func (s Set[T]) Add(value T)"),
+            Some("Set.Add".into())
+        );
+        assert_eq!(item_name("    func example() {}"), None);
+        assert_eq!(
+            item_name("var ErrNotFound = errors.New(\"not found\")"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_go_doc() {
+        let output = indoc! {r#"
+            package greet // import "example.com/greet"
+
+            Package greet says hello.
+
+            CONSTANTS
+
+            const Greeting = "Hello"
+                Greeting is what we say.
+
+
+            FUNCTIONS
+
+            func Say(name string) string
+                Say greets the given name.
+
+                For example:
+
+                    greet.Say("Gopher")
+
+
+            TYPES
+
+            type Greeter struct {
+            	Name string
+            }
+                A Greeter greets people.
+
+            func NewGreeter(name string) *Greeter
+
+            func (g *Greeter) Greet() string
+                Greet returns the greeting.
+        "#};
+
+        assert_eq!(
+            parse_go_doc(output),
+            GoDocs {
+                overview: "package greet // import \"example.com/greet\"\n\nPackage greet says hello."
+                    .into(),
+                values: "const Greeting = \"Hello\"\n    Greeting is what we say.".into(),
+                items: vec![
+                    GoDocItem {
+                        name: "Say".into(),
+                        declaration: "func Say(name string) string".into(),
+                        docs: "Say greets the given name.\n\nFor example:\n\n    greet.Say(\"Gopher\")"
+                            .into(),
+                    },
+                    GoDocItem {
+                        name: "Greeter".into(),
+                        declaration: "type Greeter struct {\n\tName string\n}".into(),
+                        docs: "A Greeter greets people.".into(),
+                    },
+                    GoDocItem {
+                        name: "NewGreeter".into(),
+                        declaration: "func NewGreeter(name string) *Greeter".into(),
+                        docs: String::new(),
+                    },
+                    GoDocItem {
+                        name: "Greeter.Greet".into(),
+                        declaration: "func (g *Greeter) Greet() string".into(),
+                        docs: "Greet returns the greeting.".into(),
+                    },
+                ],
+            }
+        );
+    }
+}
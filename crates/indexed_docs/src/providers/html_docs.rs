@@ -0,0 +1,354 @@
+use std::cell::RefCell;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use fs::Fs;
+use html_to_markdown::markdown::{
+    HeadingHandler, ListHandler, ParagraphHandler, StyledTextHandler, TableHandler,
+    WebpageChromeRemover,
+};
+use html_to_markdown::{
+    convert_html_to_markdown, HandleTag, HandlerOutcome, HtmlElement, MarkdownWriter,
+    StartTagOutcome, TagHandler,
+};
+
+use crate::providers::{find_files, relative_key};
+use crate::{IndexedDocsDatabase, PackageName};
+
+/// A static site generator whose HTML output we index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DocsSite {
+    Sphinx,
+    MdBook,
+}
+
+impl DocsSite {
+    /// The IDs and classes of the elements that make up the site's navigation and
+    /// theme, rather than its content.
+    fn chrome(&self) -> &'static [&'static str] {
+        match self {
+            Self::Sphinx => &[
+                "sphinxsidebar",
+                "related",
+                "footer",
+                "headerlink",
+                "rst-versions",
+                "wy-nav-side",
+                "wy-breadcrumbs",
+                "rst-footer-buttons",
+            ],
+            Self::MdBook => &[
+                "sidebar",
+                "menu-bar",
+                "menu-bar-hover-placeholder",
+                "theme-popup",
+                "nav-wrapper",
+                "nav-chapters",
+                "mobile-nav-chapters",
+                "search-wrapper",
+            ],
+        }
+    }
+
+    /// Returns whether the page at the given path (relative to the site's root) is
+    /// generated by the site itself, such as its search page, rather than the docs.
+    fn is_generated_page(&self, relative_path: &str) -> bool {
+        let pages: &[&str] = match self {
+            Self::Sphinx => &["genindex.html", "search.html", "py-modindex.html"],
+            Self::MdBook => &["print.html", "toc.html", "404.html"],
+        };
+        pages.contains(&relative_path)
+    }
+
+    fn is_asset_dir(&self, name: &str) -> bool {
+        let dirs: &[&str] = match self {
+            Self::Sphinx => &["_static", "_sources", "_modules", "_images", "_downloads"],
+            Self::MdBook => &["css", "fonts", "FontAwesome"],
+        };
+        dirs.contains(&name)
+    }
+}
+
+/// Converts a page of a generated documentation site to Markdown.
+pub(crate) fn convert_docs_html_to_markdown(html: impl Read, site: DocsSite) -> Result<String> {
+    let mut handlers: Vec<TagHandler> = vec![
+        Rc::new(RefCell::new(WebpageChromeRemover)),
+        Rc::new(RefCell::new(DocsChromeRemover { site })),
+        Rc::new(RefCell::new(ParagraphHandler)),
+        Rc::new(RefCell::new(HeadingHandler)),
+        Rc::new(RefCell::new(ListHandler)),
+        Rc::new(RefCell::new(TableHandler::new())),
+        Rc::new(RefCell::new(StyledTextHandler)),
+        Rc::new(RefCell::new(DocsCodeHandler::default())),
+    ];
+
+    convert_html_to_markdown(html, &mut handlers)
+}
+
+/// Indexes every page of the generated site in `site_dir`, storing the page at
+/// `index.html` under the package's name, and the other pages under
+/// `{package}/{path}`, without their `.html` extension.
+pub(crate) async fn index_docs_site(
+    fs: Arc<dyn Fs>,
+    package: &PackageName,
+    site_dir: &Path,
+    site: DocsSite,
+    database: &IndexedDocsDatabase,
+) -> Result<()> {
+    let pages = find_files(
+        fs.as_ref(),
+        site_dir,
+        |path| {
+            path.extension()
+                .map_or(false, |extension| extension == "html")
+        },
+        |path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(true, |name| site.is_asset_dir(name))
+        },
+    )
+    .await?;
+
+    let mut indexed_any = false;
+    for page in pages {
+        let Some(relative_path) = relative_key(site_dir, &page) else {
+            continue;
+        };
+        if site.is_generated_page(&relative_path) {
+            continue;
+        }
+
+        let Ok(html) = fs.load(&page).await else {
+            continue;
+        };
+        let markdown = convert_docs_html_to_markdown(html.as_bytes(), site)?;
+
+        let key = match relative_path.as_str() {
+            "index.html" => package.to_string(),
+            _ => format!(
+                "{package}/{}",
+                relative_path
+                    .trim_end_matches(".html")
+                    .trim_end_matches("/index")
+            ),
+        };
+        database.insert(key, markdown).await?;
+        indexed_any = true;
+    }
+
+    if !indexed_any {
+        bail!("no pages found in {}", site_dir.display());
+    }
+
+    Ok(())
+}
+
+struct DocsChromeRemover {
+    site: DocsSite,
+}
+
+impl HandleTag for DocsChromeRemover {
+    fn should_handle(&self, tag: &str) -> bool {
+        matches!(
+            tag,
+            "div" | "a" | "ul" | "aside" | "footer" | "header" | "button" | "span" | "form"
+        )
+    }
+
+    fn handle_tag_start(
+        &mut self,
+        tag: &HtmlElement,
+        _writer: &mut MarkdownWriter,
+    ) -> StartTagOutcome {
+        let chrome = self.site.chrome();
+        let is_chrome = tag.has_any_classes(chrome)
+            || tag
+                .attr("id")
+                .map_or(false, |id| chrome.contains(&id.as_str()));
+        if is_chrome {
+            return StartTagOutcome::Skip;
+        }
+
+        StartTagOutcome::Continue
+    }
+}
+
+/// Writes code blocks with the language that the site highlighted them as, which
+/// Sphinx puts on an enclosing `highlight-{language}` element and mdBook puts on the
+/// `<code>` element within the `<pre>`.
+#[derive(Default)]
+struct DocsCodeHandler {
+    /// Whether we've started a code fence and are still waiting to learn its language.
+    is_pending_language: bool,
+}
+
+impl DocsCodeHandler {
+    fn end_fence_line(&mut self, writer: &mut MarkdownWriter) {
+        if self.is_pending_language {
+            self.is_pending_language = false;
+            writer.push_str("\n");
+        }
+    }
+}
+
+impl HandleTag for DocsCodeHandler {
+    fn should_handle(&self, tag: &str) -> bool {
+        matches!(tag, "pre" | "code")
+    }
+
+    fn handle_tag_start(
+        &mut self,
+        tag: &HtmlElement,
+        writer: &mut MarkdownWriter,
+    ) -> StartTagOutcome {
+        match tag.tag() {
+            "code" => {
+                if writer.is_inside("pre") {
+                    if self.is_pending_language {
+                        let language = tag.classes().into_iter().find_map(|class| {
+                            class
+                                .strip_prefix("language-")
+                                .map(|language| language.to_string())
+                        });
+                        writer.push_str(language.as_deref().unwrap_or(""));
+                        self.end_fence_line(writer);
+                    }
+                } else {
+                    writer.push_str("`");
+                }
+            }
+            "pre" => {
+                let language = writer
+                    .current_element_stack()
+                    .iter()
+                    .flat_map(|element| element.classes())
+                    .find_map(|class| {
+                        class
+                            .strip_prefix("highlight-")
+                            .filter(|language| *language != "default")
+                            .map(|language| language.to_string())
+                    });
+                writer.push_str("\n\n```");
+                writer.push_str(language.as_deref().unwrap_or(""));
+                self.is_pending_language = language.is_none();
+                if language.is_some() {
+                    writer.push_str("\n");
+                }
+            }
+            _ => {}
+        }
+
+        StartTagOutcome::Continue
+    }
+
+    fn handle_tag_end(&mut self, tag: &HtmlElement, writer: &mut MarkdownWriter) {
+        match tag.tag() {
+            "code" => {
+                if !writer.is_inside("pre") {
+                    writer.push_str("`");
+                }
+            }
+            "pre" => {
+                self.end_fence_line(writer);
+                writer.push_str("\n```\n");
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_text(&mut self, text: &str, writer: &mut MarkdownWriter) -> HandlerOutcome {
+        if writer.is_inside("pre") {
+            self.end_fence_line(writer);
+            writer.push_str(text);
+            return HandlerOutcome::Handled;
+        }
+
+        HandlerOutcome::NoOp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_sphinx_page() {
+        let html = indoc! {r##"
+            <html>
+            <head><title>Client — mylib documentation</title></head>
+            <body>
+            <div class="related" role="navigation"><ul><li><a href="index.html">mylib</a></li></ul></div>
+            <div class="body" role="main">
+            <section id="client">
+            <h1>Client<a class="headerlink" href="#client">¶</a></h1>
+            <p>Sends <em>requests</em> to the server.</p>
+            <div class="highlight-python notranslate"><div class="highlight"><pre><span class="n">client</span> <span class="o">=</span> <span class="n">Client</span><span class="p">()</span>
+            </pre></div></div>
+            </section>
+            </div>
+            <div class="sphinxsidebar" role="navigation"><h3>Navigation</h3></div>
+            <div class="footer">&copy; 2024</div>
+            </body>
+            </html>
+        "##};
+
+        assert_eq!(
+            convert_docs_html_to_markdown(html.as_bytes(), DocsSite::Sphinx).unwrap(),
+            indoc! {r#"
+                # Client
+
+                Sends _requests_ to the server.
+
+                ```python
+                client = Client()
+
+                ```
+            "#}
+            .trim()
+        );
+    }
+
+    #[test]
+    fn test_mdbook_page() {
+        let html = indoc! {r##"
+            <html>
+            <head><title>Getting Started</title></head>
+            <body>
+            <nav id="sidebar" class="sidebar"><ol class="chapter"><li>Introduction</li></ol></nav>
+            <div id="page-wrapper" class="page-wrapper">
+            <div id="menu-bar" class="menu-bar"><h1 class="menu-title">The Book</h1></div>
+            <main>
+            <h1 id="getting-started"><a class="header" href="#getting-started">Getting Started</a></h1>
+            <p>Run the following:</p>
+            <pre><code class="language-sh">cargo run
+            </code></pre>
+            </main>
+            <nav class="nav-wrapper"><a rel="next" href="next.html">Next</a></nav>
+            </div>
+            </body>
+            </html>
+        "##};
+
+        assert_eq!(
+            convert_docs_html_to_markdown(html.as_bytes(), DocsSite::MdBook).unwrap(),
+            indoc! {r#"
+                # Getting Started
+
+                Run the following:
+
+                ```sh
+                cargo run
+
+                ```
+            "#}
+            .trim()
+        );
+    }
+}
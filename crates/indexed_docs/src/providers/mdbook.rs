@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use serde::Deserialize;
+
+use crate::providers::html_docs::{index_docs_site, DocsSite};
+use crate::providers::{find_files, relative_key};
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The directories that we look for a `book.toml` in, relative to the project root.
+const BOOK_DIRS: &[&str] = &["", "docs", "doc", "book"];
+
+/// Indexes the mdBook sites in the project.
+///
+/// Each book is named after the directory that contains its `book.toml`, or after
+/// the project when the book is at its root. We index the book's built HTML when
+/// it has been built, and its Markdown sources otherwise.
+pub struct MdBookProvider {
+    fs: Arc<dyn Fs>,
+    project_root: PathBuf,
+}
+
+impl MdBookProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("mdbook".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>, project_root: PathBuf) -> Self {
+        Self { fs, project_root }
+    }
+
+    /// Returns the books in the project, along with their root directories.
+    async fn books(&self) -> Vec<(PackageName, PathBuf)> {
+        let mut books = Vec::new();
+        for dir in BOOK_DIRS {
+            let book_root = self.project_root.join(dir);
+            if !self.fs.is_file(&book_root.join("book.toml")).await {
+                continue;
+            }
+
+            let name = if dir.is_empty() {
+                self.project_root.file_name().and_then(|name| name.to_str())
+            } else {
+                Some(*dir)
+            };
+            if let Some(name) = name {
+                books.push((PackageName::from(name), book_root));
+            }
+        }
+        books
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for MdBookProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        paths::support_dir().join("docs/mdbook/mdbook-db.1.mdb")
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        Ok(self
+            .books()
+            .await
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let book_root = self
+            .books()
+            .await
+            .into_iter()
+            .find_map(|(name, book_root)| (name == package).then_some(book_root))
+            .ok_or_else(|| anyhow!("no book named '{package}' found in the project"))?;
+
+        let config = self.fs.load(&book_root.join("book.toml")).await?;
+        let config = BookConfig::parse(&config)?;
+
+        let build_dir = book_root.join(&config.build_dir);
+        if self.fs.is_file(&build_dir.join("index.html")).await {
+            return index_docs_site(
+                self.fs.clone(),
+                &package,
+                &build_dir,
+                DocsSite::MdBook,
+                &database,
+            )
+            .await;
+        }
+
+        index_markdown_sources(
+            self.fs.as_ref(),
+            &package,
+            &book_root.join(&config.src_dir),
+            &database,
+        )
+        .await
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct BookConfig {
+    src_dir: PathBuf,
+    build_dir: PathBuf,
+}
+
+impl BookConfig {
+    fn parse(book_toml: &str) -> Result<Self> {
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct BookToml {
+            book: BookSection,
+            build: BuildSection,
+        }
+
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct BookSection {
+            src: Option<PathBuf>,
+        }
+
+        #[derive(Default, Deserialize)]
+        #[serde(default, rename_all = "kebab-case")]
+        struct BuildSection {
+            build_dir: Option<PathBuf>,
+        }
+
+        let book_toml: BookToml = toml::from_str(book_toml).context("invalid book.toml")?;
+        Ok(Self {
+            src_dir: book_toml.book.src.unwrap_or_else(|| "src".into()),
+            build_dir: book_toml.build.build_dir.unwrap_or_else(|| "book".into()),
+        })
+    }
+}
+
+/// Indexes the Markdown sources of a book that hasn't been built, which are already
+/// in the format we store.
+async fn index_markdown_sources(
+    fs: &dyn Fs,
+    package: &PackageName,
+    src_dir: &Path,
+    database: &IndexedDocsDatabase,
+) -> Result<()> {
+    let chapters = find_files(
+        fs,
+        src_dir,
+        |path| {
+            path.extension()
+                .map_or(false, |extension| extension == "md")
+        },
+        |_| false,
+    )
+    .await?;
+
+    let mut indexed_any = false;
+    for chapter in chapters {
+        let Some(relative_path) = relative_key(src_dir, &chapter) else {
+            continue;
+        };
+        if relative_path == "SUMMARY.md" {
+            continue;
+        }
+
+        let Ok(markdown) = fs.load(&chapter).await else {
+            continue;
+        };
+        let key = match relative_path.as_str() {
+            "README.md" | "index.md" => package.to_string(),
+            _ => format!("{package}/{}", relative_path.trim_end_matches(".md")),
+        };
+        database.insert(key, markdown).await?;
+        indexed_any = true;
+    }
+
+    if !indexed_any {
+        bail!("no chapters found in {}", src_dir.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_book_config() {
+        assert_eq!(
+            BookConfig::parse(indoc! {r#"
+                [book]
+                title = "Zed"
+                src = "chapters"
+
+                [build]
+                build-dir = "out/html"
+            "#})
+            .unwrap(),
+            BookConfig {
+                src_dir: "chapters".into(),
+                build_dir: "out/html".into(),
+            }
+        );
+
+        assert_eq!(
+            BookConfig::parse("[book]\ntitle = \"Zed\"\n").unwrap(),
+            BookConfig {
+                src_dir: "src".into(),
+                build_dir: "book".into(),
+            }
+        );
+    }
+}
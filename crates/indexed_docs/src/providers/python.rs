@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use util::command::new_smol_command;
+
+use crate::providers::html_docs::{index_docs_site, DocsSite};
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The directories that Sphinx commonly writes its HTML output to, relative to the
+/// project root.
+const SPHINX_HTML_DIRS: &[&str] = &[
+    "docs/_build/html",
+    "docs/build/html",
+    "doc/_build/html",
+    "doc/build/html",
+    "_build/html",
+    "build/html",
+];
+
+/// The virtual environments to look for a Python interpreter in, relative to the
+/// project root, so that we see the packages installed for the project.
+const VIRTUAL_ENV_DIRS: &[&str] = &[".venv", "venv", "env"];
+
+const LIST_PACKAGES_SCRIPT: &str = "import json, pkgutil; \
+    print(json.dumps(sorted({module.name for module in pkgutil.iter_modules() \
+    if not module.name.startswith('_')})))";
+
+/// Indexes the docs of Python packages, from the project's own Sphinx HTML output
+/// or, for installed packages, from their docstrings.
+pub struct PythonDocsProvider {
+    fs: Arc<dyn Fs>,
+    project_root: PathBuf,
+}
+
+impl PythonDocsProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("python".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>, project_root: PathBuf) -> Self {
+        Self { fs, project_root }
+    }
+
+    /// The name that the project's own Sphinx docs are indexed under.
+    fn project_package(&self) -> Option<PackageName> {
+        let name = self.project_root.file_name()?.to_str()?;
+        Some(PackageName::from(name))
+    }
+
+    async fn sphinx_html_dir(&self) -> Option<PathBuf> {
+        for dir in SPHINX_HTML_DIRS {
+            let dir = self.project_root.join(dir);
+            if self.fs.is_file(&dir.join("index.html")).await {
+                return Some(dir);
+            }
+        }
+        None
+    }
+
+    async fn python_interpreter(&self) -> PathBuf {
+        for dir in VIRTUAL_ENV_DIRS {
+            let interpreter = if cfg!(windows) {
+                self.project_root.join(dir).join("Scripts/python.exe")
+            } else {
+                self.project_root.join(dir).join("bin/python")
+            };
+            if self.fs.is_file(&interpreter).await {
+                return interpreter;
+            }
+        }
+        PathBuf::from(if cfg!(windows) { "python" } else { "python3" })
+    }
+
+    async fn run_python(&self, args: &[&str]) -> Result<String> {
+        let interpreter = self.python_interpreter().await;
+        let output = new_smol_command(&interpreter)
+            .args(args)
+            .current_dir(&self.project_root)
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", interpreter.display()))?;
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                interpreter.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for PythonDocsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        paths::support_dir().join("docs/python/python-db.1.mdb")
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        static INSTALLED_PACKAGES: LazyLock<RwLock<Option<(Vec<PackageName>, Instant)>>> =
+            LazyLock::new(|| RwLock::new(None));
+
+        let mut packages = Vec::new();
+        if self.sphinx_html_dir().await.is_some() {
+            packages.extend(self.project_package());
+        }
+
+        if let Some((installed_packages, fetched_at)) = &*INSTALLED_PACKAGES.read() {
+            if fetched_at.elapsed() < Duration::from_secs(300) {
+                packages.extend(installed_packages.iter().cloned());
+                return Ok(packages);
+            }
+        }
+
+        let output = self.run_python(&["-c", LIST_PACKAGES_SCRIPT]).await?;
+        let installed_packages = serde_json::from_str::<Vec<String>>(&output)
+            .context("failed to list the installed Python packages")?
+            .iter()
+            .map(|name| PackageName::from(name.as_str()))
+            .collect::<Vec<_>>();
+
+        *INSTALLED_PACKAGES.write() = Some((installed_packages.clone(), Instant::now()));
+
+        packages.extend(installed_packages);
+        Ok(packages)
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        if self.project_package().as_ref() == Some(&package) {
+            if let Some(sphinx_html_dir) = self.sphinx_html_dir().await {
+                return index_docs_site(
+                    self.fs.clone(),
+                    &package,
+                    &sphinx_html_dir,
+                    DocsSite::Sphinx,
+                    &database,
+                )
+                .await;
+            }
+        }
+
+        index_docstrings(self, package, &database).await
+    }
+}
+
+#[derive(Deserialize)]
+struct ModuleDocs {
+    name: String,
+    markdown: String,
+}
+
+/// Indexes the docstrings of an installed package and its submodules, which are
+/// stored under their module paths (e.g., `requests.adapters`).
+async fn index_docstrings(
+    provider: &PythonDocsProvider,
+    package: PackageName,
+    database: &IndexedDocsDatabase,
+) -> Result<()> {
+    if !is_module_path(&package) {
+        bail!("'{package}' is not a valid Python package name");
+    }
+
+    const EXTRACT_DOCS_SCRIPT: &str = include_str!("./python/extract_docs.py");
+
+    let output = provider
+        .run_python(&["-c", EXTRACT_DOCS_SCRIPT, package.as_ref()])
+        .await
+        .with_context(|| format!("failed to read the docs for '{package}'. is it installed?"))?;
+
+    let mut indexed_any = false;
+    for line in output.lines() {
+        let Ok(module) = serde_json::from_str::<ModuleDocs>(line) else {
+            continue;
+        };
+        database.insert(module.name, module.markdown).await?;
+        indexed_any = true;
+    }
+
+    if !indexed_any {
+        bail!("no modules found for '{package}'");
+    }
+
+    Ok(())
+}
+
+fn is_module_path(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|part| {
+            part.chars()
+                .next()
+                .map_or(false, |char| char.is_alphabetic() || char == '_')
+                && part
+                    .chars()
+                    .all(|char| char.is_alphanumeric() || char == '_')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_module_path() {
+        assert!(is_module_path("requests"));
+        assert!(is_module_path("google.protobuf"));
+        assert!(is_module_path("_pytest"));
+        assert!(!is_module_path(""));
+        assert!(!is_module_path("2to3"));
+        assert!(!is_module_path("foo; import os"));
+        assert!(!is_module_path("foo..bar"));
+    }
+}
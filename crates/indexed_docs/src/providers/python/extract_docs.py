@@ -0,0 +1,96 @@
+"""Prints the documentation of an installed Python package and its submodules as
+Markdown, one JSON object per module, in the way that `pydoc` would find it."""
+
+import importlib
+import inspect
+import json
+import pkgutil
+import sys
+
+
+def signature(obj):
+    try:
+        return str(inspect.signature(obj))
+    except (TypeError, ValueError):
+        return "(...)"
+
+
+def docstring(obj):
+    return inspect.getdoc(obj) or ""
+
+
+def public_members(module):
+    exported_names = getattr(module, "__all__", None)
+    for name, member in inspect.getmembers(module):
+        if inspect.ismodule(member):
+            continue
+        if exported_names is not None:
+            if name not in exported_names:
+                continue
+        elif name.startswith("_"):
+            continue
+        elif getattr(member, "__module__", module.__name__) != module.__name__:
+            # Skip the names the module imported from elsewhere.
+            continue
+        yield name, member
+
+
+def render_module(module):
+    lines = [f"# {module.__name__}", ""]
+    if docstring(module):
+        lines += [docstring(module), ""]
+
+    for name, member in public_members(module):
+        if inspect.isclass(member):
+            lines += [f"## class `{name}{signature(member)}`", ""]
+            if docstring(member):
+                lines += [docstring(member), ""]
+            for method_name, method in member.__dict__.items():
+                if method_name.startswith("_") and method_name != "__init__":
+                    continue
+                if not (inspect.isroutine(method) or isinstance(method, property)):
+                    continue
+                method = getattr(member, method_name, method)
+                if isinstance(method, property):
+                    lines += [f"### property `{name}.{method_name}`", ""]
+                else:
+                    lines += [f"### `{name}.{method_name}{signature(method)}`", ""]
+                if docstring(method):
+                    lines += [docstring(method), ""]
+        elif inspect.isroutine(member):
+            lines += [f"## `{name}{signature(member)}`", ""]
+            if docstring(member):
+                lines += [docstring(member), ""]
+        else:
+            lines += [f"## `{name}`", "", f"```python\n{name} = {member!r:.200}\n```", ""]
+
+    return "\n".join(lines).strip() + "\n"
+
+
+def modules(package_name):
+    package = importlib.import_module(package_name)
+    yield package
+    if not hasattr(package, "__path__"):
+        return
+    for module_info in pkgutil.walk_packages(
+        package.__path__, package.__name__ + ".", onerror=lambda _: None
+    ):
+        parts = module_info.name.split(".")
+        if any(part.startswith("_") or part in ("test", "tests") for part in parts):
+            continue
+        try:
+            yield importlib.import_module(module_info.name)
+        except BaseException:
+            continue
+
+
+def main():
+    for module in modules(sys.argv[1]):
+        try:
+            markdown = render_module(module)
+        except Exception:
+            continue
+        print(json.dumps({"name": module.__name__, "markdown": markdown}))
+
+
+main()
@@ -0,0 +1,251 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use futures::StreamExt;
+use serde::Deserialize;
+
+use crate::providers::{find_files, relative_key};
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The most declaration files we index for a single package, to bound the size of
+/// packages that ship a declaration file per source file.
+const MAX_DECLARATION_FILES: usize = 1000;
+
+/// Indexes the TypeScript declaration files of the packages in the project's
+/// `node_modules`.
+///
+/// Scoped packages are named the way DefinitelyTyped names them, so `@babel/core`
+/// is indexed as `babel__core`, which keeps the scope's `@` and `/` out of the
+/// package name.
+pub struct TypeScriptDeclarationsProvider {
+    fs: Arc<dyn Fs>,
+    project_root: PathBuf,
+}
+
+impl TypeScriptDeclarationsProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("typescript".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>, project_root: PathBuf) -> Self {
+        Self { fs, project_root }
+    }
+
+    fn node_modules_dir(&self) -> PathBuf {
+        self.project_root.join("node_modules")
+    }
+
+    /// Returns the directory of the package that ships the declarations for the
+    /// given package, which is either the package itself or its `@types` package.
+    async fn declarations_dir(&self, package: &PackageName) -> Result<PathBuf> {
+        let node_modules_dir = self.node_modules_dir();
+        let package_dir = node_modules_dir.join(npm_package_name(package));
+        if has_declarations(self.fs.as_ref(), &package_dir).await {
+            return Ok(package_dir);
+        }
+
+        let types_package_dir = node_modules_dir.join("@types").join(package.as_ref());
+        if has_declarations(self.fs.as_ref(), &types_package_dir).await {
+            return Ok(types_package_dir);
+        }
+
+        if self.fs.is_dir(&package_dir).await {
+            bail!(
+                "'{}' has no TypeScript declarations. try installing `@types/{package}`",
+                npm_package_name(package)
+            );
+        } else {
+            bail!(
+                "no '{}' package in node_modules. try running `npm install`",
+                npm_package_name(package)
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for TypeScriptDeclarationsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        paths::support_dir().join("docs/typescript/typescript-db.1.mdb")
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        let mut packages = Vec::new();
+        let mut entries = self.fs.read_dir(&self.node_modules_dir()).await?;
+        while let Some(entry) = entries.next().await {
+            let path = entry?;
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+
+            if let Some(scope) = name.strip_prefix('@') {
+                let mut scoped_entries = self.fs.read_dir(&path).await?;
+                while let Some(scoped_entry) = scoped_entries.next().await {
+                    let scoped_path = scoped_entry?;
+                    if let Some(name) = scoped_path.file_name().and_then(|name| name.to_str()) {
+                        // `@types/node` declares `node`, so we suggest the package it types.
+                        let package = if scope == "types" {
+                            name.to_string()
+                        } else {
+                            format!("{scope}__{name}")
+                        };
+                        packages.push(PackageName::from(package.as_str()));
+                    }
+                }
+            } else {
+                packages.push(PackageName::from(name));
+            }
+        }
+
+        packages.sort();
+        packages.dedup();
+        Ok(packages)
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let declarations_dir = self.declarations_dir(&package).await?;
+        let manifest = self
+            .fs
+            .load(&declarations_dir.join("package.json"))
+            .await
+            .ok()
+            .and_then(|manifest| serde_json::from_str::<PackageManifest>(&manifest).ok())
+            .unwrap_or_default();
+
+        let mut declaration_files = find_files(
+            self.fs.as_ref(),
+            &declarations_dir,
+            is_declaration_file,
+            |path| path.file_name().map_or(true, |name| name == "node_modules"),
+        )
+        .await?;
+        declaration_files.truncate(MAX_DECLARATION_FILES);
+
+        let entry_file = manifest
+            .types
+            .as_ref()
+            .or(manifest.typings.as_ref())
+            .map(|entry| declarations_dir.join(entry.trim_start_matches("./")))
+            .filter(|entry| declaration_files.contains(entry))
+            .or_else(|| {
+                let index = declarations_dir.join("index.d.ts");
+                declaration_files.contains(&index).then_some(index)
+            });
+
+        let mut declarations = Vec::new();
+        for path in &declaration_files {
+            let Some(relative_path) = relative_key(&declarations_dir, path) else {
+                continue;
+            };
+            let Ok(contents) = self.fs.load(path).await else {
+                continue;
+            };
+            declarations.push((path, relative_path, contents));
+        }
+
+        let mut package_markdown = format!("# {}\n\n", npm_package_name(&package));
+        if let Some(description) = manifest.description.as_ref() {
+            writeln!(package_markdown, "{description}\n").ok();
+        }
+        package_markdown.push_str("## Declaration Files\n\n");
+        for (_, relative_path, _) in &declarations {
+            writeln!(package_markdown, "- `{package}/{relative_path}`").ok();
+        }
+        if let Some((_, relative_path, contents)) = declarations
+            .iter()
+            .find(|(path, _, _)| Some(*path) == entry_file.as_ref())
+        {
+            write!(
+                package_markdown,
+                "\n## {relative_path}\n\n```ts\n{}\n```\n",
+                contents.trim_end()
+            )
+            .ok();
+        }
+        database
+            .insert(package.to_string(), package_markdown)
+            .await?;
+
+        for (_, relative_path, contents) in declarations {
+            let markdown = format!(
+                "# {package}/{relative_path}\n\n```ts\n{}\n```\n",
+                contents.trim_end()
+            );
+            database
+                .insert(format!("{package}/{relative_path}"), markdown)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackageManifest {
+    description: Option<String>,
+    types: Option<String>,
+    typings: Option<String>,
+}
+
+/// Returns the npm name of the package with the given docs name, turning
+/// `babel__core` back into `@babel/core`.
+fn npm_package_name(package: &str) -> String {
+    match package.split_once("__") {
+        Some((scope, name)) => format!("@{scope}/{name}"),
+        None => package.to_string(),
+    }
+}
+
+fn is_declaration_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| {
+            name.ends_with(".d.ts") || name.ends_with(".d.mts") || name.ends_with(".d.cts")
+        })
+}
+
+async fn has_declarations(fs: &dyn Fs, package_dir: &Path) -> bool {
+    if !fs.is_dir(package_dir).await {
+        return false;
+    }
+
+    let manifest = fs
+        .load(&package_dir.join("package.json"))
+        .await
+        .ok()
+        .and_then(|manifest| serde_json::from_str::<PackageManifest>(&manifest).ok())
+        .unwrap_or_default();
+    manifest.types.is_some()
+        || manifest.typings.is_some()
+        || fs.is_file(&package_dir.join("index.d.ts")).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npm_package_name() {
+        assert_eq!(npm_package_name("react"), "react");
+        assert_eq!(npm_package_name("babel__core"), "@babel/core");
+    }
+
+    #[test]
+    fn test_is_declaration_file() {
+        assert!(is_declaration_file(Path::new("lib/index.d.ts")));
+        assert!(is_declaration_file(Path::new("esm/index.d.mts")));
+        assert!(!is_declaration_file(Path::new("lib/index.ts")));
+        assert!(!is_declaration_file(Path::new("lib/index.js")));
+    }
+}
@@ -1,10 +1,13 @@
+use std::cmp::Ordering;
 use std::sync::Arc;
 
 use collections::HashMap;
-use gpui::{App, BackgroundExecutor, Global, ReadGlobal, UpdateGlobal};
+use futures::future;
+use gpui::{App, BackgroundExecutor, Global, ReadGlobal, Task, UpdateGlobal};
 use parking_lot::RwLock;
+use util::ResultExt;
 
-use crate::{IndexedDocsProvider, IndexedDocsStore, ProviderId};
+use crate::{FullTextMatch, IndexedDocsProvider, IndexedDocsStore, ProviderId};
 
 struct GlobalIndexedDocsRegistry(Arc<IndexedDocsRegistry>);
 
@@ -55,4 +58,45 @@ impl IndexedDocsRegistry {
     pub fn get_provider_store(&self, provider_id: ProviderId) -> Option<Arc<IndexedDocsStore>> {
         self.stores_by_provider.read().get(&provider_id).cloned()
     }
+
+    /// Searches the Markdown of the docs indexed by every provider, returning the best
+    /// matches across all of them.
+    pub fn search_full_text(
+        &self,
+        query: String,
+        limit: usize,
+    ) -> Task<Vec<(ProviderId, FullTextMatch)>> {
+        let stores = self
+            .stores_by_provider
+            .read()
+            .iter()
+            .map(|(provider, store)| (provider.clone(), store.clone()))
+            .collect::<Vec<_>>();
+
+        self.executor.spawn(async move {
+            let searches = stores.into_iter().map(|(provider, store)| {
+                let query = query.clone();
+                async move {
+                    store
+                        .search_full_text(query, limit)
+                        .await
+                        .log_err()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|mat| (provider.clone(), mat))
+                        .collect::<Vec<_>>()
+                }
+            });
+
+            let mut matches = future::join_all(searches)
+                .await
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            matches
+                .sort_by(|(_, a), (_, b)| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+            matches.truncate(limit);
+            matches
+        })
+    }
 }
@@ -16,6 +16,7 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use util::ResultExt;
 
+use crate::full_text::{FullTextIndex, FullTextMatch};
 use crate::IndexedDocsRegistry;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Deref, Display)]
@@ -129,6 +130,21 @@ impl IndexedDocsStore {
             .await
     }
 
+    /// Searches the Markdown of every entry for the given query, returning the best
+    /// matches first.
+    pub async fn search_full_text(
+        &self,
+        query: String,
+        limit: usize,
+    ) -> Result<Vec<FullTextMatch>> {
+        self.database_future
+            .clone()
+            .await
+            .map_err(|err| anyhow!(err))?
+            .search_full_text(query, limit)
+            .await
+    }
+
     pub fn suggest_packages(self: Arc<Self>) -> Task<Result<Vec<PackageName>>> {
         let this = self.clone();
         self.executor
@@ -236,6 +252,7 @@ pub struct IndexedDocsDatabase {
     executor: BackgroundExecutor,
     env: heed::Env,
     entries: Database<SerdeBincode<String>, SerdeBincode<MarkdownDocs>>,
+    full_text_index: FullTextIndex,
 }
 
 impl IndexedDocsDatabase {
@@ -246,18 +263,21 @@ impl IndexedDocsDatabase {
         let env = unsafe {
             heed::EnvOpenOptions::new()
                 .map_size(ONE_GB_IN_BYTES)
-                .max_dbs(1)
+                .max_dbs(3)
                 .open(path)?
         };
 
         let mut txn = env.write_txn()?;
         let entries = env.create_database(&mut txn, Some("rustdoc_entries"))?;
+        let full_text_index = FullTextIndex::new(&env, &mut txn)?;
+        full_text_index.backfill(&mut txn, entries)?;
         txn.commit()?;
 
         Ok(Self {
             executor,
             env,
             entries,
+            full_text_index,
         })
     }
 
@@ -325,12 +345,33 @@ impl IndexedDocsDatabase {
         })
     }
 
+    /// Returns the entries whose Markdown best matches the given query.
+    pub fn search_full_text(
+        &self,
+        query: String,
+        limit: usize,
+    ) -> Task<Result<Vec<FullTextMatch>>> {
+        let env = self.env.clone();
+        let entries = self.entries;
+        let full_text_index = self.full_text_index;
+
+        self.executor.spawn(async move {
+            let txn = env.read_txn()?;
+            full_text_index.search(&txn, entries, &query, limit)
+        })
+    }
+
     pub fn insert(&self, key: String, docs: String) -> Task<Result<()>> {
         let env = self.env.clone();
         let entries = self.entries;
+        let full_text_index = self.full_text_index;
 
         self.executor.spawn(async move {
             let mut txn = env.write_txn()?;
+            if let Some(previous_docs) = entries.get(&txn, &key)? {
+                full_text_index.remove(&mut txn, &key, &previous_docs.0)?;
+            }
+            full_text_index.insert(&mut txn, &key, &docs)?;
             entries.put(&mut txn, &key, &MarkdownDocs(docs))?;
             txn.commit()?;
             Ok(())
@@ -17,7 +17,9 @@ use gpui::{
 };
 use indoc::indoc;
 use language::{
-    language_settings::{self, all_language_settings, AllLanguageSettings, EditPredictionProvider},
+    language_settings::{
+        self, all_language_settings, AllLanguageSettings, EditPredictionProvider, FimApi,
+    },
     File, Language,
 };
 use regex::Regex;
@@ -231,6 +233,63 @@ impl Render for InlineCompletionButton {
                 );
             }
 
+            EditPredictionProvider::Fim => {
+                let enabled = self.editor_enabled.unwrap_or(true);
+                let icon = match all_language_settings.edit_predictions.fim.api {
+                    FimApi::Ollama => IconName::AiOllama,
+                    FimApi::OpenAiCompatible => IconName::Ai,
+                };
+                let model =
+                    SharedString::from(all_language_settings.edit_predictions.fim.model.clone());
+
+                let icon_button = IconButton::new("fim-icon", icon)
+                    .shape(IconButtonShape::Square)
+                    .when(!enabled, |this| {
+                        this.indicator(Indicator::dot().color(Color::Muted))
+                            .indicator_border_color(Some(cx.theme().colors().status_bar_background))
+                    })
+                    .when(!self.popover_menu_handle.is_deployed(), |element| {
+                        element.tooltip(move |window, cx| {
+                            Tooltip::with_meta(
+                                "Edit Prediction",
+                                Some(&ToggleMenu),
+                                model.clone(),
+                                window,
+                                cx,
+                            )
+                        })
+                    });
+
+                let this = cx.entity().clone();
+                let mut popover_menu = PopoverMenu::new("fim")
+                    .menu(move |window, cx| {
+                        Some(this.update(cx, |this, cx| this.build_fim_context_menu(window, cx)))
+                    })
+                    .anchor(Corner::BottomRight)
+                    .with_handle(self.popover_menu_handle.clone());
+
+                let is_refreshing = self
+                    .edit_prediction_provider
+                    .as_ref()
+                    .map_or(false, |provider| provider.is_refreshing(cx));
+
+                if is_refreshing {
+                    popover_menu = popover_menu.trigger(
+                        icon_button.with_animation(
+                            "pulsating-label",
+                            Animation::new(Duration::from_secs(2))
+                                .repeat()
+                                .with_easing(pulsating_between(0.2, 1.0)),
+                            |icon_button, delta| icon_button.alpha(delta),
+                        ),
+                    );
+                } else {
+                    popover_menu = popover_menu.trigger(icon_button);
+                }
+
+                div().child(popover_menu.into_any_element())
+            }
+
             EditPredictionProvider::Zed => {
                 if !cx.has_flag::<PredictEditsFeatureFlag>() {
                     return div();
@@ -678,6 +737,16 @@ impl InlineCompletionButton {
         })
     }
 
+    fn build_fim_context_menu(
+        &self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Entity<ContextMenu> {
+        ContextMenu::build(window, cx, |menu, window, cx| {
+            self.build_language_settings_menu(menu, window, cx)
+        })
+    }
+
     fn build_zeta_context_menu(
         &self,
         window: &mut Window,
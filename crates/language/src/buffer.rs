@@ -24,8 +24,8 @@ use collections::HashMap;
 use fs::MTime;
 use futures::channel::oneshot;
 use gpui::{
-    AnyElement, App, AppContext as _, Context, Entity, EventEmitter, HighlightStyle, Pixels,
-    SharedString, StyledText, Task, TaskLabel, TextStyle, Window,
+    AnyElement, App, AppContext as _, Context, Entity, EntityId, EventEmitter, HighlightStyle,
+    Pixels, SharedString, StyledText, Task, TaskLabel, TextStyle, Window,
 };
 use lsp::{LanguageServerId, NumberOrString};
 use parking_lot::Mutex;
@@ -302,6 +302,10 @@ pub enum BufferEvent {
     Closed,
     /// The buffer was discarded when closing.
     Discarded,
+    /// A branch of the buffer was created.
+    BranchCreated { branch_id: EntityId },
+    /// A branch of the buffer is merging its changes into the buffer, in the edit that follows.
+    BranchMerged { branch_id: EntityId },
 }
 
 /// The file associated with a buffer.
@@ -1079,7 +1083,7 @@ impl Buffer {
 
     pub fn branch(&mut self, cx: &mut Context<Self>) -> Entity<Self> {
         let this = cx.entity();
-        cx.new(|cx| {
+        let branch = cx.new(|cx| {
             let mut branch = Self {
                 branch_state: Some(BufferBranchState {
                     base_buffer: this.clone(),
@@ -1099,7 +1103,11 @@ impl Buffer {
             branch.reparse(cx);
 
             branch
-        })
+        });
+        cx.emit(BufferEvent::BranchCreated {
+            branch_id: branch.entity_id(),
+        });
+        branch
     }
 
     pub fn preview_edits(
@@ -1175,8 +1183,13 @@ impl Buffer {
             }
         }
 
+        if edits.is_empty() {
+            return;
+        }
+
+        let branch_id = cx.entity_id();
         let operation = base_buffer.update(cx, |base_buffer, cx| {
-            // cx.emit(BufferEvent::DiffBaseChanged);
+            cx.emit(BufferEvent::BranchMerged { branch_id });
             base_buffer.edit(edits, None, cx)
         });
 
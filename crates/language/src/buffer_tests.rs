@@ -2649,6 +2649,47 @@ fn test_undo_after_merge_into_base(cx: &mut TestAppContext) {
     branch.read_with(cx, |branch, _| assert_eq!(branch.text(), "ABCdefgHIjk"));
 }
 
+#[gpui::test]
+fn test_branch_events(cx: &mut TestAppContext) {
+    cx.update(|cx| init_settings(cx, |_| {}));
+
+    let base = cx.new(|cx| Buffer::local("abcdefghijk", cx));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    cx.update(|cx| {
+        let events = events.clone();
+        cx.subscribe(&base, move |_, event, _| match event {
+            BufferEvent::Operation { .. } => {}
+            event => events.lock().push(event.clone()),
+        })
+        .detach();
+    });
+
+    let branch = base.update(cx, |buffer, cx| buffer.branch(cx));
+    let branch_id = branch.entity_id();
+    assert_eq!(
+        mem::take(&mut *events.lock()),
+        [BufferEvent::BranchCreated { branch_id }]
+    );
+
+    // Merging announces the branch before the edit that merges it.
+    branch.update(cx, |branch, cx| {
+        branch.edit([(0..3, "ABC")], None, cx);
+        branch.merge_into_base(Vec::new(), cx);
+    });
+    assert_eq!(
+        mem::take(&mut *events.lock()),
+        [
+            BufferEvent::BranchMerged { branch_id },
+            BufferEvent::Edited,
+            BufferEvent::DirtyChanged
+        ]
+    );
+
+    // Merging a branch without changes does nothing.
+    branch.update(cx, |branch, cx| branch.merge_into_base(Vec::new(), cx));
+    assert!(events.lock().is_empty());
+}
+
 #[gpui::test]
 async fn test_preview_edits(cx: &mut TestAppContext) {
     cx.update(|cx| {
@@ -209,6 +209,9 @@ pub enum EditPredictionProvider {
     Copilot,
     Supermaven,
     Zed,
+    /// Fill-in-the-middle completions from a model served by Ollama or an
+    /// OpenAI-compatible API.
+    Fim,
 }
 
 impl EditPredictionProvider {
@@ -217,7 +220,8 @@ impl EditPredictionProvider {
             EditPredictionProvider::Zed => true,
             EditPredictionProvider::None
             | EditPredictionProvider::Copilot
-            | EditPredictionProvider::Supermaven => false,
+            | EditPredictionProvider::Supermaven
+            | EditPredictionProvider::Fim => false,
         }
     }
 }
@@ -236,6 +240,8 @@ pub struct EditPredictionSettings {
     pub mode: EditPredictionsMode,
     /// Settings specific to GitHub Copilot.
     pub copilot: CopilotSettings,
+    /// Settings specific to fill-in-the-middle completions.
+    pub fim: FimSettings,
 }
 
 /// The mode in which edit predictions should be displayed.
@@ -258,6 +264,104 @@ pub struct CopilotSettings {
     pub proxy_no_verify: Option<bool>,
 }
 
+#[derive(Clone, Debug)]
+pub struct FimSettings {
+    /// The API that serves the model.
+    pub api: FimApi,
+    /// The URL of the API.
+    pub api_url: String,
+    /// The model to complete the code with.
+    pub model: String,
+    /// The fill-in-the-middle tokens of the model's family.
+    pub template: FimTemplate,
+    /// The tokens to use instead of the template's.
+    pub tokens: Option<FimTokens>,
+    /// The most tokens to generate for a completion.
+    pub max_tokens: u32,
+    /// How many lines before the cursor to send to the model.
+    pub prefix_lines: u32,
+    /// How many lines after the cursor to send to the model.
+    pub suffix_lines: u32,
+    /// How long to wait after typing stops before requesting a completion.
+    pub debounce_ms: u64,
+}
+
+impl Default for FimSettings {
+    fn default() -> Self {
+        Self {
+            api: FimApi::default(),
+            api_url: "http://localhost:11434".to_string(),
+            model: "qwen2.5-coder:1.5b-base".to_string(),
+            template: FimTemplate::default(),
+            tokens: None,
+            max_tokens: 128,
+            prefix_lines: 64,
+            suffix_lines: 32,
+            debounce_ms: 150,
+        }
+    }
+}
+
+impl FimSettings {
+    fn merge(&mut self, content: &FimSettingsContent) {
+        fn merge<T>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        merge(&mut self.api, content.api);
+        merge(&mut self.api_url, content.api_url.clone());
+        merge(&mut self.model, content.model.clone());
+        merge(&mut self.template, content.template);
+        if let Some(tokens) = content.tokens.clone() {
+            self.tokens = Some(tokens);
+        }
+        merge(&mut self.max_tokens, content.max_tokens);
+        merge(&mut self.prefix_lines, content.prefix_lines);
+        merge(&mut self.suffix_lines, content.suffix_lines);
+        merge(&mut self.debounce_ms, content.debounce_ms);
+    }
+}
+
+/// The API that serves a fill-in-the-middle model.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FimApi {
+    /// Ollama's `/api/generate` endpoint.
+    #[default]
+    Ollama,
+    /// The `/completions` endpoint of an OpenAI-compatible API, such as
+    /// llama.cpp's server, vLLM or LM Studio.
+    OpenAiCompatible,
+}
+
+/// The family of a fill-in-the-middle model, which determines the tokens its prompts use.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FimTemplate {
+    /// Pick the family from the model's name.
+    #[default]
+    Auto,
+    QwenCoder,
+    StarCoder,
+    DeepseekCoder,
+    CodeLlama,
+}
+
+/// The tokens of a fill-in-the-middle prompt, which is made up of the prefix
+/// token, the text before the cursor, the suffix token, the text after the
+/// cursor and the middle token.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FimTokens {
+    pub prefix: String,
+    pub suffix: String,
+    pub middle: String,
+    /// The tokens that end a completion.
+    #[serde(default)]
+    pub stop: Vec<String>,
+}
+
 /// The settings for all languages.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AllLanguageSettingsContent {
@@ -478,6 +582,9 @@ pub struct EditPredictionSettingsContent {
     /// Settings specific to GitHub Copilot.
     #[serde(default)]
     pub copilot: CopilotSettingsContent,
+    /// Settings specific to fill-in-the-middle completions.
+    #[serde(default)]
+    pub fim: FimSettingsContent,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -494,6 +601,57 @@ pub struct CopilotSettingsContent {
     pub proxy_no_verify: Option<bool>,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct FimSettingsContent {
+    /// The API that serves the model.
+    ///
+    /// Default: ollama
+    #[serde(default)]
+    pub api: Option<FimApi>,
+    /// The URL of the API. For an OpenAI-compatible API, this is the URL that
+    /// `/completions` is appended to, such as `http://localhost:8080/v1`.
+    ///
+    /// Default: "http://localhost:11434"
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// The model to complete the code with. It must be trained for
+    /// fill-in-the-middle, as base code models usually are.
+    ///
+    /// Default: "qwen2.5-coder:1.5b-base"
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The family of the model, which determines the tokens its prompts use.
+    ///
+    /// Default: auto
+    #[serde(default)]
+    pub template: Option<FimTemplate>,
+    /// The tokens to use instead of the template's, for models of other families.
+    ///
+    /// Default: none
+    #[serde(default)]
+    pub tokens: Option<FimTokens>,
+    /// The most tokens to generate for a completion.
+    ///
+    /// Default: 128
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// How many lines before the cursor to send to the model.
+    ///
+    /// Default: 64
+    #[serde(default)]
+    pub prefix_lines: Option<u32>,
+    /// How many lines after the cursor to send to the model.
+    ///
+    /// Default: 32
+    #[serde(default)]
+    pub suffix_lines: Option<u32>,
+    /// How long to wait after typing stops before requesting a completion, in milliseconds.
+    ///
+    /// Default: 150
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+}
+
 /// The settings for enabling/disabling features.
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -1101,6 +1259,11 @@ impl settings::Settings for AllLanguageSettings {
             })
             .unwrap_or_default();
 
+        let mut fim_settings = FimSettings::default();
+        if let Some(edit_predictions) = default_value.edit_predictions.as_ref() {
+            fim_settings.merge(&edit_predictions.fim);
+        }
+
         let mut file_types: HashMap<Arc<str>, GlobSet> = HashMap::default();
 
         for (language, suffixes) in &default_value.file_types {
@@ -1131,6 +1294,8 @@ impl settings::Settings for AllLanguageSettings {
                 if let Some(disabled_globs) = edit_predictions.disabled_globs.as_ref() {
                     completion_globs.extend(disabled_globs.iter());
                 }
+
+                fim_settings.merge(&edit_predictions.fim);
             }
 
             if let Some(proxy) = user_settings
@@ -1201,6 +1366,7 @@ impl settings::Settings for AllLanguageSettings {
                     .collect(),
                 mode: edit_predictions_mode,
                 copilot: copilot_settings,
+                fim: fim_settings,
             },
             defaults,
             languages,
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
+use ::settings::{Settings as _, SettingsStore};
 use client::{Client, UserStore};
+use collections::HashSet;
 use fs::Fs;
 use gpui::{App, Context, Entity};
+use http_client::HttpClient;
 use language_model::{LanguageModelProviderId, LanguageModelRegistry, ZED_CLOUD_PROVIDER_ID};
 use provider::deepseek::DeepSeekLanguageModelProvider;
 
@@ -18,6 +21,7 @@ use crate::provider::lmstudio::LmStudioLanguageModelProvider;
 use crate::provider::mistral::MistralLanguageModelProvider;
 use crate::provider::ollama::OllamaLanguageModelProvider;
 use crate::provider::open_ai::OpenAiLanguageModelProvider;
+use crate::provider::open_ai_compatible::OpenAiCompatibleLanguageModelProvider;
 pub use crate::settings::*;
 
 pub fn init(user_store: Entity<UserStore>, client: Arc<Client>, fs: Arc<dyn Fs>, cx: &mut App) {
@@ -69,6 +73,7 @@ fn register_language_model_providers(
         cx,
     );
     registry.register_provider(CopilotChatLanguageModelProvider::new(cx), cx);
+    register_openai_compatible_providers(registry, client.http_client(), cx);
 
     cx.observe_flag::<feature_flags::LanguageModels, _>(move |enabled, cx| {
         let user_store = user_store.clone();
@@ -89,3 +94,52 @@ fn register_language_model_providers(
     })
     .detach();
 }
+
+/// Registers a provider for each OpenAI-compatible server in the settings, and
+/// keeps them in sync as the settings change.
+fn register_openai_compatible_providers(
+    registry: &mut LanguageModelRegistry,
+    http_client: Arc<dyn HttpClient>,
+    cx: &mut Context<LanguageModelRegistry>,
+) {
+    let mut registered = HashSet::default();
+    sync_openai_compatible_providers(registry, &mut registered, &http_client, cx);
+    cx.observe_global::<SettingsStore>(move |registry, cx| {
+        sync_openai_compatible_providers(registry, &mut registered, &http_client, cx);
+    })
+    .detach();
+}
+
+fn sync_openai_compatible_providers(
+    registry: &mut LanguageModelRegistry,
+    registered: &mut HashSet<Arc<str>>,
+    http_client: &Arc<dyn HttpClient>,
+    cx: &mut Context<LanguageModelRegistry>,
+) {
+    let names = AllLanguageModelSettings::get_global(cx)
+        .openai_compatible
+        .iter()
+        .map(|settings| settings.name.clone())
+        .collect::<HashSet<_>>();
+
+    for name in registered.iter().filter(|name| !names.contains(*name)) {
+        registry.unregister_provider(LanguageModelProviderId(name.to_string().into()), cx);
+    }
+    registered.retain(|name| names.contains(name));
+
+    for name in names {
+        if registered.contains(&name) {
+            continue;
+        }
+        let id = LanguageModelProviderId(name.to_string().into());
+        if registry.provider(&id).is_some() {
+            log::error!("not registering the OpenAI-compatible provider {name}, as a provider with its name exists");
+            continue;
+        }
+        registry.register_provider(
+            OpenAiCompatibleLanguageModelProvider::new(name.clone(), http_client.clone(), cx),
+            cx,
+        );
+        registered.insert(name);
+    }
+}
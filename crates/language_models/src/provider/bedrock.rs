@@ -28,7 +28,7 @@ use language_model::{
     AuthenticateError, LanguageModel, LanguageModelCacheConfiguration,
     LanguageModelCompletionEvent, LanguageModelId, LanguageModelName, LanguageModelProvider,
     LanguageModelProviderId, LanguageModelProviderName, LanguageModelProviderState,
-    LanguageModelRequest, LanguageModelToolUse, MessageContent, RateLimiter, Role,
+    LanguageModelRequest, LanguageModelToolUse, MessageContent, RateLimiter, Role, TokenUsage,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -750,6 +750,28 @@ pub fn map_to_language_model_completion_events(
                                             ));
                                         }
                                     }
+                                    ConverseStreamOutput::Metadata(metadata) => {
+                                        if let Some(usage) = metadata.usage() {
+                                            return Some((
+                                                Some(Ok(
+                                                    LanguageModelCompletionEvent::UsageUpdate(
+                                                        TokenUsage {
+                                                            input_tokens: usage
+                                                                .input_tokens()
+                                                                .max(0)
+                                                                as u32,
+                                                            output_tokens: usage
+                                                                .output_tokens()
+                                                                .max(0)
+                                                                as u32,
+                                                            ..Default::default()
+                                                        },
+                                                    ),
+                                                )),
+                                                state,
+                                            ));
+                                        }
+                                    }
                                     _ => {}
                                 },
                                 Err(err) => return Some((Some(Err(anyhow!(err))), state)),
@@ -37,8 +37,13 @@ use ui::{prelude::*, TintColor};
 use crate::provider::anthropic::{
     count_anthropic_tokens, into_anthropic, map_to_language_model_completion_events,
 };
-use crate::provider::google::into_google;
-use crate::provider::open_ai::{count_open_ai_tokens, into_open_ai};
+use crate::provider::google::{
+    into_google, map_to_language_model_completion_events as map_google_events,
+};
+use crate::provider::open_ai::{
+    count_open_ai_tokens, into_open_ai,
+    map_to_language_model_completion_events as map_open_ai_events,
+};
 use crate::AllLanguageModelSettings;
 
 pub const PROVIDER_NAME: &str = "Zed";
@@ -614,15 +619,9 @@ impl LanguageModel for CloudLanguageModel {
                         },
                     )
                     .await?;
-                    Ok(open_ai::extract_text_from_events(response_lines(response)))
+                    Ok(map_open_ai_events(response_lines(response)))
                 });
-                async move {
-                    Ok(future
-                        .await?
-                        .map(|result| result.map(LanguageModelCompletionEvent::Text))
-                        .boxed())
-                }
-                .boxed()
+                async move { Ok(future.await?.boxed()) }.boxed()
             }
             CloudModel::Google(model) => {
                 let client = self.client.clone();
@@ -641,17 +640,9 @@ impl LanguageModel for CloudLanguageModel {
                         },
                     )
                     .await?;
-                    Ok(google_ai::extract_text_from_events(response_lines(
-                        response,
-                    )))
+                    Ok(map_google_events(response_lines(response)))
                 });
-                async move {
-                    Ok(future
-                        .await?
-                        .map(|result| result.map(LanguageModelCompletionEvent::Text))
-                        .boxed())
-                }
-                .boxed()
+                async move { Ok(future.await?.boxed()) }.boxed()
             }
         }
     }
@@ -11,7 +11,7 @@ use http_client::HttpClient;
 use language_model::{
     AuthenticateError, LanguageModel, LanguageModelCompletionEvent, LanguageModelId,
     LanguageModelName, LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
-    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
+    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role, TokenUsage,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -332,20 +332,26 @@ impl LanguageModel for DeepSeekLanguageModel {
         async move {
             let stream = stream.await?;
             Ok(stream
-                .map(|result| {
-                    result.and_then(|response| {
-                        response
-                            .choices
-                            .first()
-                            .ok_or_else(|| anyhow!("Empty response"))
-                            .map(|choice| {
-                                choice
-                                    .delta
-                                    .content
-                                    .clone()
-                                    .unwrap_or_default()
-                                    .map(LanguageModelCompletionEvent::Text)
-                            })
+                .flat_map(|result| {
+                    futures::stream::iter(match result {
+                        Ok(response) => {
+                            let mut events = Vec::new();
+                            if let Some(content) = response
+                                .choices
+                                .into_iter()
+                                .next()
+                                .and_then(|choice| choice.delta.content)
+                            {
+                                events.push(Ok(LanguageModelCompletionEvent::Text(content)));
+                            }
+                            if let Some(usage) = response.usage {
+                                events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                                    token_usage(&usage),
+                                )));
+                            }
+                            events
+                        }
+                        Err(error) => vec![Err(error)],
                     })
                 })
                 .boxed())
@@ -475,6 +481,9 @@ pub fn into_deepseek(
         model,
         messages: merged_messages,
         stream: true,
+        stream_options: Some(deepseek::StreamOptions {
+            include_usage: true,
+        }),
         max_tokens: max_output_tokens,
         temperature: if is_reasoner {
             None
@@ -496,6 +505,18 @@ pub fn into_deepseek(
     }
 }
 
+/// DeepSeek reports the cached part of the prompt as cache hits, and the rest as misses.
+fn token_usage(usage: &deepseek::Usage) -> TokenUsage {
+    TokenUsage {
+        input_tokens: usage
+            .prompt_tokens
+            .saturating_sub(usage.prompt_cache_hit_tokens),
+        output_tokens: usage.completion_tokens,
+        cache_read_input_tokens: usage.prompt_cache_hit_tokens,
+        ..Default::default()
+    }
+}
+
 struct ConfigurationView {
     api_key_editor: Entity<Editor>,
     state: Entity<State>,
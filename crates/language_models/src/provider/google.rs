@@ -2,7 +2,7 @@ use anyhow::{anyhow, Context as _, Result};
 use collections::BTreeMap;
 use credentials_provider::CredentialsProvider;
 use editor::{Editor, EditorElement, EditorStyle};
-use futures::{future::BoxFuture, FutureExt, StreamExt};
+use futures::{future::BoxFuture, FutureExt, Stream, StreamExt};
 use google_ai::stream_generate_content;
 use gpui::{
     AnyView, App, AsyncApp, Context, Entity, FontStyle, Subscription, Task, TextStyle, WhiteSpace,
@@ -12,7 +12,7 @@ use language_model::{AuthenticateError, LanguageModelCompletionEvent};
 use language_model::{
     LanguageModel, LanguageModelId, LanguageModelName, LanguageModelProvider,
     LanguageModelProviderId, LanguageModelProviderName, LanguageModelProviderState,
-    LanguageModelRequest, RateLimiter, Role,
+    LanguageModelRequest, RateLimiter, Role, TokenUsage,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -318,15 +318,9 @@ impl LanguageModel for GoogleLanguageModel {
             let response =
                 stream_generate_content(http_client.as_ref(), &api_url, &api_key, request);
             let events = response.await?;
-            Ok(google_ai::extract_text_from_events(events).boxed())
+            Ok(map_to_language_model_completion_events(events).boxed())
         });
-        async move {
-            Ok(future
-                .await?
-                .map(|result| result.map(LanguageModelCompletionEvent::Text))
-                .boxed())
-        }
-        .boxed()
+        async move { Ok(future.await?.boxed()) }.boxed()
     }
 
     fn use_any_tool(
@@ -373,6 +367,41 @@ pub fn into_google(
     }
 }
 
+/// Maps the streamed responses to their text, along with the usage each response reports.
+pub fn map_to_language_model_completion_events(
+    events: impl Stream<Item = Result<google_ai::GenerateContentResponse>>,
+) -> impl Stream<Item = Result<LanguageModelCompletionEvent>> {
+    events.flat_map(|event| {
+        let mut completion_events = Vec::new();
+        match event {
+            Ok(event) => {
+                let text = event
+                    .candidates
+                    .into_iter()
+                    .flatten()
+                    .next()
+                    .and_then(|candidate| candidate.content.parts.into_iter().next());
+                if let Some(google_ai::Part::TextPart(google_ai::TextPart { text })) = text {
+                    completion_events.push(Ok(LanguageModelCompletionEvent::Text(text)));
+                }
+                if let Some(usage) = event.usage_metadata {
+                    let cached_tokens = usage.cached_content_token_count;
+                    completion_events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                        TokenUsage {
+                            input_tokens: usage.prompt_token_count.saturating_sub(cached_tokens),
+                            output_tokens: usage.candidates_token_count,
+                            cache_creation_input_tokens: 0,
+                            cache_read_input_tokens: cached_tokens,
+                        },
+                    )));
+                }
+            }
+            Err(error) => completion_events.push(Err(error)),
+        }
+        futures::stream::iter(completion_events)
+    })
+}
+
 pub fn count_google_tokens(
     request: LanguageModelRequest,
     cx: &App,
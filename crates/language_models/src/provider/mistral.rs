@@ -10,7 +10,7 @@ use http_client::HttpClient;
 use language_model::{
     AuthenticateError, LanguageModel, LanguageModelCompletionEvent, LanguageModelId,
     LanguageModelName, LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
-    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
+    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role, TokenUsage,
 };
 
 use futures::stream::BoxStream;
@@ -344,20 +344,26 @@ impl LanguageModel for MistralLanguageModel {
         async move {
             let stream = stream.await?;
             Ok(stream
-                .map(|result| {
-                    result.and_then(|response| {
-                        response
-                            .choices
-                            .first()
-                            .ok_or_else(|| anyhow!("Empty response"))
-                            .map(|choice| {
-                                choice
-                                    .delta
-                                    .content
-                                    .clone()
-                                    .unwrap_or_default()
-                                    .map(LanguageModelCompletionEvent::Text)
-                            })
+                .flat_map(|result| {
+                    futures::stream::iter(match result {
+                        Ok(response) => {
+                            let mut events = Vec::new();
+                            if let Some(content) = response
+                                .choices
+                                .into_iter()
+                                .next()
+                                .and_then(|choice| choice.delta.content)
+                            {
+                                events.push(Ok(LanguageModelCompletionEvent::Text(content)));
+                            }
+                            if let Some(usage) = response.usage {
+                                events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                                    token_usage(&usage),
+                                )));
+                            }
+                            events
+                        }
+                        Err(error) => vec![Err(error)],
                     })
                 })
                 .boxed())
@@ -461,6 +467,14 @@ pub fn into_mistral(
     }
 }
 
+fn token_usage(usage: &mistral::Usage) -> TokenUsage {
+    TokenUsage {
+        input_tokens: usage.prompt_tokens,
+        output_tokens: usage.completion_tokens,
+        ..Default::default()
+    }
+}
+
 struct ConfigurationView {
     api_key_editor: Entity<Editor>,
     state: gpui::Entity<State>,
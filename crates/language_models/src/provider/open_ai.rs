@@ -2,7 +2,7 @@ use anyhow::{anyhow, Context as _, Result};
 use collections::BTreeMap;
 use credentials_provider::CredentialsProvider;
 use editor::{Editor, EditorElement, EditorStyle};
-use futures::{future::BoxFuture, FutureExt, StreamExt};
+use futures::{future::BoxFuture, FutureExt, Stream, StreamExt};
 use gpui::{
     AnyView, App, AsyncApp, Context, Entity, FontStyle, Subscription, Task, TextStyle, WhiteSpace,
 };
@@ -10,7 +10,7 @@ use http_client::HttpClient;
 use language_model::{
     AuthenticateError, LanguageModel, LanguageModelCompletionEvent, LanguageModelId,
     LanguageModelName, LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
-    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
+    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role, TokenUsage,
 };
 use open_ai::{
     stream_completion, FunctionDefinition, ResponseStreamEvent, ToolChoice, ToolDefinition,
@@ -320,12 +320,8 @@ impl LanguageModel for OpenAiLanguageModel {
     > {
         let request = into_open_ai(request, self.model.id().into(), self.max_output_tokens());
         let completions = self.stream_completion(request, cx);
-        async move {
-            Ok(open_ai::extract_text_from_events(completions.await?)
-                .map(|result| result.map(LanguageModelCompletionEvent::Text))
-                .boxed())
-        }
-        .boxed()
+        async move { Ok(map_to_language_model_completion_events(completions.await?).boxed()) }
+            .boxed()
     }
 
     fn use_any_tool(
@@ -379,7 +375,7 @@ pub fn into_open_ai(
             .into_iter()
             .map(|msg| match msg.role {
                 Role::User => open_ai::RequestMessage::User {
-                    content: msg.string_contents(),
+                    content: msg.string_contents().into(),
                 },
                 Role::Assistant => open_ai::RequestMessage::Assistant {
                     content: Some(msg.string_contents()),
@@ -396,6 +392,48 @@ pub fn into_open_ai(
         max_tokens: max_output_tokens,
         tools: Vec::new(),
         tool_choice: None,
+        stream_options: stream.then_some(open_ai::StreamOptions {
+            include_usage: true,
+        }),
+    }
+}
+
+/// Maps the streamed deltas to text, along with the usage OpenAI sends at the end.
+pub fn map_to_language_model_completion_events(
+    events: impl Stream<Item = Result<ResponseStreamEvent>>,
+) -> impl Stream<Item = Result<LanguageModelCompletionEvent>> {
+    events.flat_map(|event| {
+        let mut completion_events = Vec::new();
+        match event {
+            Ok(event) => {
+                for choice in event.choices {
+                    if let Some(content) = choice.delta.content {
+                        completion_events.push(Ok(LanguageModelCompletionEvent::Text(content)));
+                    }
+                }
+                if let Some(usage) = event.usage {
+                    completion_events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                        token_usage(&usage),
+                    )));
+                }
+            }
+            Err(error) => completion_events.push(Err(error)),
+        }
+        futures::stream::iter(completion_events)
+    })
+}
+
+/// The usage of an OpenAI-compatible request, where the prompt tokens include the cached ones.
+pub fn token_usage(usage: &open_ai::Usage) -> TokenUsage {
+    let cached_tokens = usage
+        .prompt_tokens_details
+        .as_ref()
+        .map_or(0, |details| details.cached_tokens);
+    TokenUsage {
+        input_tokens: usage.prompt_tokens.saturating_sub(cached_tokens),
+        output_tokens: usage.completion_tokens,
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: cached_tokens,
     }
 }
 
@@ -0,0 +1,974 @@
+use anyhow::{anyhow, Context as _, Result};
+use collections::{BTreeMap, HashMap};
+use credentials_provider::CredentialsProvider;
+use editor::{Editor, EditorElement, EditorStyle};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, Stream, StreamExt};
+use gpui::{
+    AnyView, App, AsyncApp, Context, Entity, FontStyle, Subscription, Task, TextStyle, WhiteSpace,
+};
+use http_client::HttpClient;
+use language_model::{
+    AuthenticateError, LanguageModel, LanguageModelCompletionEvent, LanguageModelId,
+    LanguageModelName, LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
+    LanguageModelProviderState, LanguageModelRequest, LanguageModelToolUse, MessageContent,
+    RateLimiter, Role, StopReason,
+};
+use open_ai::{
+    FunctionContent, FunctionDefinition, ImageUrl, MessagePart, ResponseStreamEvent, ToolCall,
+    ToolCallContent, ToolChoice, ToolDefinition,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsStore};
+use std::sync::Arc;
+use theme::ThemeSettings;
+use ui::{prelude::*, Icon, IconName};
+use util::ResultExt;
+
+use crate::provider::open_ai::{count_open_ai_tokens, token_usage};
+use crate::AllLanguageModelSettings;
+
+/// The context length of the models found through the API, which it does not report.
+const DEFAULT_MAX_TOKENS: usize = 8192;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenAiCompatibleSettings {
+    pub name: Arc<str>,
+    pub api_url: String,
+    pub headers: HashMap<String, String>,
+    pub discover_models: bool,
+    pub available_models: Vec<AvailableModel>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AvailableModel {
+    /// The model's name in the API, such as "Qwen/Qwen2.5-Coder-32B-Instruct".
+    pub name: String,
+    /// The model's name in Zed's UI, such as in the model selector dropdown menu in the assistant panel.
+    pub display_name: Option<String>,
+    /// The model's context length.
+    pub max_tokens: usize,
+    /// The most tokens the model generates in a response.
+    pub max_output_tokens: Option<u32>,
+    /// Whether the model can call tools.
+    #[serde(default)]
+    pub supports_tools: bool,
+    /// Whether the model accepts images.
+    #[serde(default)]
+    pub supports_images: bool,
+}
+
+fn provider_settings<'a>(name: &str, cx: &'a App) -> Option<&'a OpenAiCompatibleSettings> {
+    AllLanguageModelSettings::get_global(cx)
+        .openai_compatible
+        .iter()
+        .find(|settings| settings.name.as_ref() == name)
+}
+
+/// A provider for a server with an OpenAI-compatible API, such as vLLM, llama.cpp,
+/// OpenRouter or LiteLLM. There is one for each entry of the `openai_compatible` setting.
+pub struct OpenAiCompatibleLanguageModelProvider {
+    name: Arc<str>,
+    http_client: Arc<dyn HttpClient>,
+    state: gpui::Entity<State>,
+}
+
+pub struct State {
+    name: Arc<str>,
+    http_client: Arc<dyn HttpClient>,
+    api_key: Option<String>,
+    credentials_loaded: bool,
+    discovered_models: Vec<AvailableModel>,
+    fetch_models_task: Option<Task<Result<()>>>,
+    _subscription: Subscription,
+}
+
+impl State {
+    /// Servers don't always need an API key, so the provider is ready once
+    /// the key has been looked up.
+    fn is_authenticated(&self) -> bool {
+        self.credentials_loaded
+    }
+
+    fn reset_api_key(&self, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let credentials_provider = <dyn CredentialsProvider>::global(cx);
+        let Some(api_url) = provider_settings(&self.name, cx).map(|s| s.api_url.clone()) else {
+            return Task::ready(Ok(()));
+        };
+        cx.spawn(|this, mut cx| async move {
+            credentials_provider
+                .delete_credentials(&api_url, &cx)
+                .await
+                .log_err();
+            this.update(&mut cx, |this, cx| {
+                this.api_key = None;
+                this.restart_fetch_models_task(cx);
+                cx.notify();
+            })
+        })
+    }
+
+    fn set_api_key(&mut self, api_key: String, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let credentials_provider = <dyn CredentialsProvider>::global(cx);
+        let Some(api_url) = provider_settings(&self.name, cx).map(|s| s.api_url.clone()) else {
+            return Task::ready(Ok(()));
+        };
+        cx.spawn(|this, mut cx| async move {
+            credentials_provider
+                .write_credentials(&api_url, "Bearer", api_key.as_bytes(), &cx)
+                .await
+                .log_err();
+            this.update(&mut cx, |this, cx| {
+                this.api_key = Some(api_key);
+                this.restart_fetch_models_task(cx);
+                cx.notify();
+            })
+        })
+    }
+
+    fn authenticate(&self, cx: &mut Context<Self>) -> Task<Result<(), AuthenticateError>> {
+        if self.is_authenticated() {
+            return Task::ready(Ok(()));
+        }
+
+        let credentials_provider = <dyn CredentialsProvider>::global(cx);
+        let Some(api_url) = provider_settings(&self.name, cx).map(|s| s.api_url.clone()) else {
+            return Task::ready(Err(anyhow!("{} is no longer configured", self.name).into()));
+        };
+        cx.spawn(|this, mut cx| async move {
+            let api_key = match credentials_provider.read_credentials(&api_url, &cx).await? {
+                Some((_, api_key)) => Some(String::from_utf8(api_key).context("invalid API key")?),
+                None => None,
+            };
+            this.update(&mut cx, |this, cx| {
+                this.api_key = api_key;
+                this.credentials_loaded = true;
+                this.restart_fetch_models_task(cx);
+                cx.notify();
+            })?;
+
+            Ok(())
+        })
+    }
+
+    /// Adds the models the server lists, when the settings ask for them.
+    fn fetch_models(&mut self, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let Some(settings) = provider_settings(&self.name, cx) else {
+            return Task::ready(Ok(()));
+        };
+        if !settings.discover_models {
+            self.discovered_models.clear();
+            return Task::ready(Ok(()));
+        }
+
+        let http_client = self.http_client.clone();
+        let api_url = settings.api_url.clone();
+        let headers = settings.headers.clone().into_iter().collect::<Vec<_>>();
+        let api_key = self.api_key.clone();
+        cx.spawn(|this, mut cx| async move {
+            let models =
+                open_ai::list_models(http_client.as_ref(), &api_url, api_key.as_deref(), &headers)
+                    .await?;
+
+            this.update(&mut cx, |this, cx| {
+                this.discovered_models = models
+                    .into_iter()
+                    .map(|model| AvailableModel {
+                        name: model.id,
+                        display_name: None,
+                        max_tokens: DEFAULT_MAX_TOKENS,
+                        max_output_tokens: None,
+                        supports_tools: false,
+                        supports_images: false,
+                    })
+                    .collect();
+                cx.notify();
+            })
+        })
+    }
+
+    fn restart_fetch_models_task(&mut self, cx: &mut Context<Self>) {
+        let task = self.fetch_models(cx);
+        self.fetch_models_task.replace(task);
+    }
+}
+
+impl OpenAiCompatibleLanguageModelProvider {
+    pub fn new(name: Arc<str>, http_client: Arc<dyn HttpClient>, cx: &mut App) -> Self {
+        let state = cx.new(|cx| {
+            let subscription = cx.observe_global::<SettingsStore>({
+                let name = name.clone();
+                let mut settings = provider_settings(&name, cx).cloned();
+                move |this: &mut State, cx| {
+                    let new_settings = provider_settings(&name, cx);
+                    if settings.as_ref() != new_settings {
+                        let api_url_changed = settings.as_ref().map(|s| &s.api_url)
+                            != new_settings.map(|s| &s.api_url);
+                        settings = new_settings.cloned();
+                        if api_url_changed && this.credentials_loaded {
+                            // The API key is stored under the URL, so look it up again.
+                            this.api_key = None;
+                            this.credentials_loaded = false;
+                            this.authenticate(cx).detach_and_log_err(cx);
+                        } else {
+                            this.restart_fetch_models_task(cx);
+                        }
+                        cx.notify();
+                    }
+                }
+            });
+
+            State {
+                name: name.clone(),
+                http_client: http_client.clone(),
+                api_key: None,
+                credentials_loaded: false,
+                discovered_models: Vec::new(),
+                fetch_models_task: None,
+                _subscription: subscription,
+            }
+        });
+
+        Self {
+            name,
+            http_client,
+            state,
+        }
+    }
+}
+
+impl LanguageModelProviderState for OpenAiCompatibleLanguageModelProvider {
+    type ObservableEntity = State;
+
+    fn observable_entity(&self) -> Option<gpui::Entity<Self::ObservableEntity>> {
+        Some(self.state.clone())
+    }
+}
+
+impl LanguageModelProvider for OpenAiCompatibleLanguageModelProvider {
+    fn id(&self) -> LanguageModelProviderId {
+        LanguageModelProviderId(self.name.to_string().into())
+    }
+
+    fn name(&self) -> LanguageModelProviderName {
+        LanguageModelProviderName(self.name.to_string().into())
+    }
+
+    fn icon(&self) -> IconName {
+        IconName::AiOpenAi
+    }
+
+    fn default_model(&self, cx: &App) -> Option<Arc<dyn LanguageModel>> {
+        self.provided_models(cx).into_iter().next()
+    }
+
+    fn provided_models(&self, cx: &App) -> Vec<Arc<dyn LanguageModel>> {
+        let mut models = BTreeMap::default();
+
+        // Add the models listed by the server
+        for model in &self.state.read(cx).discovered_models {
+            models.insert(model.name.clone(), model.clone());
+        }
+
+        // Override with available models from settings
+        if let Some(settings) = provider_settings(&self.name, cx) {
+            for model in &settings.available_models {
+                models.insert(model.name.clone(), model.clone());
+            }
+        }
+
+        models
+            .into_values()
+            .map(|model| {
+                Arc::new(OpenAiCompatibleLanguageModel {
+                    id: LanguageModelId::from(model.name.clone()),
+                    provider_name: self.name.clone(),
+                    model,
+                    state: self.state.clone(),
+                    http_client: self.http_client.clone(),
+                    request_limiter: RateLimiter::new(4),
+                }) as Arc<dyn LanguageModel>
+            })
+            .collect()
+    }
+
+    fn is_authenticated(&self, cx: &App) -> bool {
+        self.state.read(cx).is_authenticated()
+    }
+
+    fn authenticate(&self, cx: &mut App) -> Task<Result<(), AuthenticateError>> {
+        self.state.update(cx, |state, cx| state.authenticate(cx))
+    }
+
+    fn configuration_view(&self, window: &mut Window, cx: &mut App) -> AnyView {
+        cx.new(|cx| ConfigurationView::new(self.state.clone(), window, cx))
+            .into()
+    }
+
+    fn reset_credentials(&self, cx: &mut App) -> Task<Result<()>> {
+        self.state.update(cx, |state, cx| state.reset_api_key(cx))
+    }
+}
+
+pub struct OpenAiCompatibleLanguageModel {
+    id: LanguageModelId,
+    provider_name: Arc<str>,
+    model: AvailableModel,
+    state: gpui::Entity<State>,
+    http_client: Arc<dyn HttpClient>,
+    request_limiter: RateLimiter,
+}
+
+impl OpenAiCompatibleLanguageModel {
+    fn stream_completion(
+        &self,
+        request: open_ai::Request,
+        cx: &AsyncApp,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<ResponseStreamEvent>>>> {
+        let http_client = self.http_client.clone();
+        let Ok(settings) = cx.read_entity(&self.state, |state, cx| {
+            let settings = provider_settings(&state.name, cx)?;
+            Some((
+                state.api_key.clone(),
+                settings.api_url.clone(),
+                settings.headers.clone().into_iter().collect::<Vec<_>>(),
+            ))
+        }) else {
+            return futures::future::ready(Err(anyhow!("App state dropped"))).boxed();
+        };
+        let Some((api_key, api_url, headers)) = settings else {
+            return futures::future::ready(Err(anyhow!(
+                "{} is no longer configured",
+                self.provider_name
+            )))
+            .boxed();
+        };
+
+        let future = self.request_limiter.stream(async move {
+            open_ai::stream_completion_with_headers(
+                http_client.as_ref(),
+                &api_url,
+                api_key.as_deref(),
+                &headers,
+                request,
+            )
+            .await
+        });
+
+        async move { Ok(future.await?.boxed()) }.boxed()
+    }
+}
+
+impl LanguageModel for OpenAiCompatibleLanguageModel {
+    fn id(&self) -> LanguageModelId {
+        self.id.clone()
+    }
+
+    fn name(&self) -> LanguageModelName {
+        LanguageModelName::from(
+            self.model
+                .display_name
+                .clone()
+                .unwrap_or_else(|| self.model.name.clone()),
+        )
+    }
+
+    fn provider_id(&self) -> LanguageModelProviderId {
+        LanguageModelProviderId(self.provider_name.to_string().into())
+    }
+
+    fn provider_name(&self) -> LanguageModelProviderName {
+        LanguageModelProviderName(self.provider_name.to_string().into())
+    }
+
+    fn telemetry_id(&self) -> String {
+        format!("openai_compatible/{}", self.model.name)
+    }
+
+    fn max_token_count(&self) -> usize {
+        self.model.max_tokens
+    }
+
+    fn max_output_tokens(&self) -> Option<u32> {
+        self.model.max_output_tokens
+    }
+
+    fn count_tokens(
+        &self,
+        request: LanguageModelRequest,
+        cx: &App,
+    ) -> BoxFuture<'static, Result<usize>> {
+        let model = open_ai::Model::Custom {
+            name: self.model.name.clone(),
+            display_name: self.model.display_name.clone(),
+            max_tokens: self.model.max_tokens,
+            max_output_tokens: self.model.max_output_tokens,
+            max_completion_tokens: None,
+        };
+        count_open_ai_tokens(request, model, cx)
+    }
+
+    fn stream_completion(
+        &self,
+        request: LanguageModelRequest,
+        cx: &AsyncApp,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<LanguageModelCompletionEvent>>>> {
+        let request = into_open_ai_compatible(request, &self.model);
+        let completions = self.stream_completion(request, cx);
+        async move { Ok(map_to_language_model_completion_events(completions.await?).boxed()) }
+            .boxed()
+    }
+
+    fn use_any_tool(
+        &self,
+        request: LanguageModelRequest,
+        tool_name: String,
+        tool_description: String,
+        schema: serde_json::Value,
+        cx: &AsyncApp,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        if !self.model.supports_tools {
+            return futures::future::ready(Err(anyhow!(
+                "{} does not support tools",
+                self.model.name
+            )))
+            .boxed();
+        }
+
+        let mut request = into_open_ai_compatible(request, &self.model);
+        request.tool_choice = Some(ToolChoice::Other(ToolDefinition::Function {
+            function: FunctionDefinition {
+                name: tool_name.clone(),
+                description: None,
+                parameters: None,
+            },
+        }));
+        request.tools = vec![ToolDefinition::Function {
+            function: FunctionDefinition {
+                name: tool_name.clone(),
+                description: Some(tool_description),
+                parameters: Some(schema),
+            },
+        }];
+
+        let response = self.stream_completion(request, cx);
+        self.request_limiter
+            .run(async move {
+                let response = response.await?;
+                Ok(
+                    open_ai::extract_tool_args_from_events(tool_name, Box::pin(response))
+                        .await?
+                        .boxed(),
+                )
+            })
+            .boxed()
+    }
+}
+
+/// Converts a request for a model, leaving out the tools and images it doesn't support.
+pub fn into_open_ai_compatible(
+    request: LanguageModelRequest,
+    model: &AvailableModel,
+) -> open_ai::Request {
+    let mut messages = Vec::new();
+    for message in request.messages {
+        match message.role {
+            Role::System => messages.push(open_ai::RequestMessage::System {
+                content: message.string_contents(),
+            }),
+            Role::User => {
+                let mut parts = Vec::new();
+                for content in message.content {
+                    match content {
+                        MessageContent::Text(text) => parts.push(MessagePart::Text { text }),
+                        MessageContent::Image(image) if model.supports_images => {
+                            parts.push(MessagePart::ImageUrl {
+                                image_url: ImageUrl {
+                                    url: format!("data:image/png;base64,{}", image.source),
+                                },
+                            })
+                        }
+                        MessageContent::ToolResult(tool_result) if model.supports_tools => messages
+                            .push(open_ai::RequestMessage::Tool {
+                                content: tool_result.content,
+                                tool_call_id: tool_result.tool_use_id,
+                            }),
+                        MessageContent::ToolResult(tool_result) => parts.push(MessagePart::Text {
+                            text: tool_result.content,
+                        }),
+                        MessageContent::Image(_) | MessageContent::ToolUse(_) => {}
+                    }
+                }
+                if parts.is_empty() {
+                    continue;
+                }
+
+                let has_images = parts
+                    .iter()
+                    .any(|part| matches!(part, MessagePart::ImageUrl { .. }));
+                let content = if has_images {
+                    open_ai::MessageContent::Multipart(parts)
+                } else {
+                    open_ai::MessageContent::Plain(
+                        parts
+                            .into_iter()
+                            .filter_map(|part| match part {
+                                MessagePart::Text { text } => Some(text),
+                                MessagePart::ImageUrl { .. } => None,
+                            })
+                            .collect(),
+                    )
+                };
+                messages.push(open_ai::RequestMessage::User { content });
+            }
+            Role::Assistant => {
+                let tool_calls = if model.supports_tools {
+                    message
+                        .content
+                        .iter()
+                        .filter_map(|content| match content {
+                            MessageContent::ToolUse(tool_use) => Some(ToolCall {
+                                id: tool_use.id.to_string(),
+                                content: ToolCallContent::Function {
+                                    function: FunctionContent {
+                                        name: tool_use.name.clone(),
+                                        arguments: tool_use.input.to_string(),
+                                    },
+                                },
+                            }),
+                            _ => None,
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let content = message.string_contents();
+                if content.is_empty() && tool_calls.is_empty() {
+                    continue;
+                }
+                messages.push(open_ai::RequestMessage::Assistant {
+                    content: (!content.is_empty()).then_some(content),
+                    tool_calls,
+                });
+            }
+        }
+    }
+
+    let tools = if model.supports_tools {
+        request
+            .tools
+            .into_iter()
+            .map(|tool| ToolDefinition::Function {
+                function: FunctionDefinition {
+                    name: tool.name,
+                    description: Some(tool.description),
+                    parameters: Some(tool.input_schema),
+                },
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    open_ai::Request {
+        model: model.name.clone(),
+        messages,
+        stream: true,
+        stop: request.stop,
+        temperature: request.temperature.unwrap_or(1.0),
+        max_tokens: model.max_output_tokens,
+        tools,
+        tool_choice: None,
+        stream_options: Some(open_ai::StreamOptions {
+            include_usage: true,
+        }),
+    }
+}
+
+/// Maps the streamed deltas to completion events, gathering the calls of each tool
+/// until the response finishes.
+pub fn map_to_language_model_completion_events(
+    events: BoxStream<'static, Result<ResponseStreamEvent>>,
+) -> impl Stream<Item = Result<LanguageModelCompletionEvent>> {
+    #[derive(Default)]
+    struct RawToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    }
+
+    struct State {
+        events: BoxStream<'static, Result<ResponseStreamEvent>>,
+        tool_calls_by_index: BTreeMap<usize, RawToolCall>,
+    }
+
+    futures::stream::unfold(
+        State {
+            events,
+            tool_calls_by_index: BTreeMap::default(),
+        },
+        |mut state| async move {
+            let event = match state.events.next().await? {
+                Ok(event) => event,
+                Err(error) => return Some((vec![Err(error)], state)),
+            };
+
+            let mut completion_events = Vec::new();
+            for choice in event.choices {
+                if let Some(content) = choice.delta.content.filter(|content| !content.is_empty()) {
+                    completion_events.push(Ok(LanguageModelCompletionEvent::Text(content)));
+                }
+
+                for call in choice.delta.tool_calls.into_iter().flatten() {
+                    let tool_call = state.tool_calls_by_index.entry(call.index).or_default();
+                    if let Some(id) = call.id {
+                        tool_call.id = id;
+                    }
+                    if let Some(function) = call.function {
+                        if let Some(name) = function.name {
+                            tool_call.name = name;
+                        }
+                        if let Some(arguments) = function.arguments {
+                            tool_call.arguments.push_str(&arguments);
+                        }
+                    }
+                }
+
+                if let Some(finish_reason) = choice.finish_reason {
+                    for (_, tool_call) in std::mem::take(&mut state.tool_calls_by_index) {
+                        completion_events.push(
+                            if tool_call.arguments.is_empty() {
+                                Ok(serde_json::Value::Null)
+                            } else {
+                                serde_json::from_str(&tool_call.arguments)
+                                    .map_err(|error| anyhow!(error))
+                            }
+                            .map(|input| {
+                                LanguageModelCompletionEvent::ToolUse(LanguageModelToolUse {
+                                    id: tool_call.id.into(),
+                                    name: tool_call.name,
+                                    input,
+                                })
+                            }),
+                        );
+                    }
+
+                    let stop_reason = match finish_reason.as_str() {
+                        "length" => StopReason::MaxTokens,
+                        "tool_calls" => StopReason::ToolUse,
+                        _ => StopReason::EndTurn,
+                    };
+                    completion_events.push(Ok(LanguageModelCompletionEvent::Stop(stop_reason)));
+                }
+            }
+
+            if let Some(usage) = event.usage {
+                completion_events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(token_usage(
+                    &usage,
+                ))));
+            }
+
+            Some((completion_events, state))
+        },
+    )
+    .flat_map(futures::stream::iter)
+}
+
+struct ConfigurationView {
+    api_key_editor: Entity<Editor>,
+    state: gpui::Entity<State>,
+    load_credentials_task: Option<Task<()>>,
+}
+
+impl ConfigurationView {
+    fn new(state: gpui::Entity<State>, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let api_key_editor = cx.new(|cx| {
+            let mut editor = Editor::single_line(window, cx);
+            editor.set_placeholder_text("API key, if the server needs one", cx);
+            editor
+        });
+
+        cx.observe(&state, |_, _, cx| {
+            cx.notify();
+        })
+        .detach();
+
+        let load_credentials_task = Some(cx.spawn_in(window, {
+            let state = state.clone();
+            |this, mut cx| async move {
+                if let Some(task) = state
+                    .update(&mut cx, |state, cx| state.authenticate(cx))
+                    .log_err()
+                {
+                    task.await.log_err();
+                }
+
+                this.update(&mut cx, |this, cx| {
+                    this.load_credentials_task = None;
+                    cx.notify();
+                })
+                .log_err();
+            }
+        }));
+
+        Self {
+            api_key_editor,
+            state,
+            load_credentials_task,
+        }
+    }
+
+    fn save_api_key(&mut self, _: &menu::Confirm, window: &mut Window, cx: &mut Context<Self>) {
+        let api_key = self.api_key_editor.read(cx).text(cx);
+        if api_key.is_empty() {
+            return;
+        }
+
+        let state = self.state.clone();
+        cx.spawn_in(window, |_, mut cx| async move {
+            state
+                .update(&mut cx, |state, cx| state.set_api_key(api_key, cx))?
+                .await
+        })
+        .detach_and_log_err(cx);
+
+        cx.notify();
+    }
+
+    fn reset_api_key(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.api_key_editor
+            .update(cx, |editor, cx| editor.set_text("", window, cx));
+
+        let state = self.state.clone();
+        cx.spawn_in(window, |_, mut cx| async move {
+            state
+                .update(&mut cx, |state, cx| state.reset_api_key(cx))?
+                .await
+        })
+        .detach_and_log_err(cx);
+
+        cx.notify();
+    }
+
+    fn render_api_key_editor(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let settings = ThemeSettings::get_global(cx);
+        let text_style = TextStyle {
+            color: cx.theme().colors().text,
+            font_family: settings.ui_font.family.clone(),
+            font_features: settings.ui_font.features.clone(),
+            font_fallbacks: settings.ui_font.fallbacks.clone(),
+            font_size: rems(0.875).into(),
+            font_weight: settings.ui_font.weight,
+            font_style: FontStyle::Normal,
+            line_height: relative(1.3),
+            white_space: WhiteSpace::Normal,
+            ..Default::default()
+        };
+        EditorElement::new(
+            &self.api_key_editor,
+            EditorStyle {
+                background: cx.theme().colors().editor_background,
+                local_player: cx.theme().players().local(),
+                text: text_style,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl Render for ConfigurationView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let state = self.state.read(cx);
+        let name = state.name.clone();
+        let has_api_key = state.api_key.is_some();
+
+        if self.load_credentials_task.is_some() {
+            div().child(Label::new("Loading credentials...")).into_any()
+        } else if !has_api_key {
+            v_flex()
+                .size_full()
+                .on_action(cx.listener(Self::save_api_key))
+                .child(Label::new(format!(
+                    "If {name} needs an API key, paste it below and hit enter."
+                )))
+                .child(
+                    h_flex()
+                        .w_full()
+                        .my_2()
+                        .px_2()
+                        .py_1()
+                        .bg(cx.theme().colors().editor_background)
+                        .border_1()
+                        .border_color(cx.theme().colors().border_variant)
+                        .rounded_md()
+                        .child(self.render_api_key_editor(cx)),
+                )
+                .child(
+                    Label::new(
+                        "The URL, headers and models are set in the `openai_compatible` setting.",
+                    )
+                    .size(LabelSize::Small),
+                )
+                .into_any()
+        } else {
+            h_flex()
+                .size_full()
+                .justify_between()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(Icon::new(IconName::Check).color(Color::Success))
+                        .child(Label::new("API key configured.")),
+                )
+                .child(
+                    Button::new("reset-key", "Reset key")
+                        .icon(Some(IconName::Trash))
+                        .icon_size(IconSize::Small)
+                        .icon_position(IconPosition::Start)
+                        .on_click(
+                            cx.listener(|this, _, window, cx| this.reset_api_key(window, cx)),
+                        ),
+                )
+                .into_any()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_model::{LanguageModelRequestMessage, LanguageModelToolResult};
+    use open_ai::{ChoiceDelta, FunctionChunk, ResponseMessageDelta, ToolCallChunk};
+
+    fn event(
+        content: Option<&str>,
+        tool_calls: Option<Vec<ToolCallChunk>>,
+        finish_reason: Option<&str>,
+    ) -> Result<ResponseStreamEvent> {
+        Ok(ResponseStreamEvent {
+            created: 0,
+            model: "model".into(),
+            choices: vec![ChoiceDelta {
+                index: 0,
+                delta: ResponseMessageDelta {
+                    role: None,
+                    content: content.map(Into::into),
+                    tool_calls,
+                },
+                finish_reason: finish_reason.map(Into::into),
+            }],
+            usage: None,
+        })
+    }
+
+    fn tool_call_chunk(id: Option<&str>, name: Option<&str>, arguments: &str) -> ToolCallChunk {
+        ToolCallChunk {
+            index: 0,
+            id: id.map(Into::into),
+            function: Some(FunctionChunk {
+                name: name.map(Into::into),
+                arguments: Some(arguments.into()),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_map_to_language_model_completion_events() {
+        let events = futures::stream::iter([
+            event(Some("Let me look."), None, None),
+            event(
+                None,
+                Some(vec![tool_call_chunk(
+                    Some("call_1"),
+                    Some("read-file"),
+                    "{\"path\":",
+                )]),
+                None,
+            ),
+            event(
+                None,
+                Some(vec![tool_call_chunk(None, None, "\"a.rs\"}")]),
+                None,
+            ),
+            event(None, None, Some("tool_calls")),
+        ])
+        .boxed();
+
+        let events =
+            smol::block_on(map_to_language_model_completion_events(events).collect::<Vec<_>>())
+                .into_iter()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert!(
+            matches!(&events[0], LanguageModelCompletionEvent::Text(text) if text == "Let me look.")
+        );
+        let LanguageModelCompletionEvent::ToolUse(tool_use) = &events[1] else {
+            panic!("expected a tool use, got {:?}", events[1]);
+        };
+        assert_eq!(tool_use.id.to_string(), "call_1");
+        assert_eq!(tool_use.name, "read-file");
+        assert_eq!(tool_use.input, serde_json::json!({ "path": "a.rs" }));
+        assert!(matches!(
+            events[2],
+            LanguageModelCompletionEvent::Stop(StopReason::ToolUse)
+        ));
+    }
+
+    #[test]
+    fn test_into_open_ai_compatible_without_tools() {
+        let model = AvailableModel {
+            name: "model".into(),
+            display_name: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            max_output_tokens: None,
+            supports_tools: false,
+            supports_images: false,
+        };
+        let request = LanguageModelRequest {
+            messages: vec![
+                LanguageModelRequestMessage {
+                    role: Role::User,
+                    content: vec![MessageContent::Text("Read a.rs".into())],
+                    cache: false,
+                },
+                LanguageModelRequestMessage {
+                    role: Role::Assistant,
+                    content: vec![MessageContent::ToolUse(LanguageModelToolUse {
+                        id: "call_1".into(),
+                        name: "read-file".into(),
+                        input: serde_json::json!({ "path": "a.rs" }),
+                    })],
+                    cache: false,
+                },
+                LanguageModelRequestMessage {
+                    role: Role::User,
+                    content: vec![MessageContent::ToolResult(LanguageModelToolResult {
+                        tool_use_id: "call_1".into(),
+                        is_error: false,
+                        content: "fn main() {}".into(),
+                    })],
+                    cache: false,
+                },
+            ],
+            tools: Vec::new(),
+            stop: Vec::new(),
+            temperature: None,
+        };
+
+        let request = into_open_ai_compatible(request, &model);
+        assert_eq!(
+            request.messages,
+            vec![
+                open_ai::RequestMessage::User {
+                    content: "Read a.rs".to_string().into(),
+                },
+                open_ai::RequestMessage::User {
+                    content: "fn main() {}".to_string().into(),
+                },
+            ]
+        );
+        assert!(request.tools.is_empty());
+    }
+}
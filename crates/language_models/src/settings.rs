@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use collections::HashMap;
 use gpui::App;
 use language_model::LanguageModelCacheConfiguration;
 use project::Fs;
@@ -20,6 +21,7 @@ use crate::provider::{
     mistral::MistralSettings,
     ollama::OllamaSettings,
     open_ai::OpenAiSettings,
+    open_ai_compatible::OpenAiCompatibleSettings,
 };
 
 /// Initializes the language model settings.
@@ -61,6 +63,7 @@ pub struct AllLanguageModelSettings {
     pub bedrock: AmazonBedrockSettings,
     pub ollama: OllamaSettings,
     pub openai: OpenAiSettings,
+    pub openai_compatible: Vec<OpenAiCompatibleSettings>,
     pub zed_dot_dev: ZedDotDevSettings,
     pub google: GoogleSettings,
     pub copilot_chat: CopilotChatSettings,
@@ -75,6 +78,7 @@ pub struct AllLanguageModelSettingsContent {
     pub ollama: Option<OllamaSettingsContent>,
     pub lmstudio: Option<LmStudioSettingsContent>,
     pub openai: Option<OpenAiSettingsContent>,
+    pub openai_compatible: Option<Vec<OpenAiCompatibleSettingsContent>>,
     #[serde(rename = "zed.dev")]
     pub zed_dot_dev: Option<ZedDotDevSettingsContent>,
     pub google: Option<GoogleSettingsContent>,
@@ -245,6 +249,34 @@ pub struct OpenAiSettingsContentV1 {
     pub available_models: Option<Vec<provider::open_ai::AvailableModel>>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct OpenAiCompatibleSettingsContent {
+    /// The name of the provider, which identifies it in Zed's UI and settings.
+    pub name: String,
+    /// The URL of the API, including its version, such as "http://localhost:8000/v1".
+    pub api_url: String,
+    /// Headers to send with every request.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Whether to add the models the API lists. Models in `available_models` override them.
+    ///
+    /// Default: true
+    pub discover_models: Option<bool>,
+    pub available_models: Option<Vec<provider::open_ai_compatible::AvailableModel>>,
+}
+
+impl From<OpenAiCompatibleSettingsContent> for OpenAiCompatibleSettings {
+    fn from(content: OpenAiCompatibleSettingsContent) -> Self {
+        Self {
+            name: content.name.into(),
+            api_url: content.api_url,
+            headers: content.headers,
+            discover_models: content.discover_models.unwrap_or(true),
+            available_models: content.available_models.unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct GoogleSettingsContent {
     pub api_url: Option<String>,
@@ -349,6 +381,16 @@ impl settings::Settings for AllLanguageModelSettings {
                 &mut settings.openai.available_models,
                 openai.as_ref().and_then(|s| s.available_models.clone()),
             );
+
+            // OpenAI-compatible
+            merge(
+                &mut settings.openai_compatible,
+                value
+                    .openai_compatible
+                    .clone()
+                    .map(|providers| providers.into_iter().map(Into::into).collect()),
+            );
+
             merge(
                 &mut settings.zed_dot_dev.available_models,
                 value
@@ -2193,7 +2193,9 @@ impl MultiBuffer {
                 self.capability = buffer.read(cx).capability();
                 Event::CapabilityChanged
             }
-            language::BufferEvent::Operation { .. } => return,
+            language::BufferEvent::Operation { .. }
+            | language::BufferEvent::BranchCreated { .. }
+            | language::BufferEvent::BranchMerged { .. } => return,
         });
     }
 
@@ -183,6 +183,25 @@ pub struct ChatOptions {
     pub top_p: Option<f32>,
 }
 
+/// A request to generate a completion of a prompt, without a chat template.
+#[derive(Serialize, Debug)]
+pub struct GenerateRequest {
+    pub model: String,
+    pub prompt: String,
+    /// Whether to send the prompt to the model as is, rather than through its template.
+    pub raw: bool,
+    pub stream: bool,
+    pub keep_alive: KeepAlive,
+    pub options: Option<ChatOptions>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GenerateResponse {
+    pub response: String,
+    #[allow(unused)]
+    pub done: bool,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ChatResponseDelta {
     #[allow(unused)]
@@ -259,6 +278,33 @@ pub async fn complete(
     }
 }
 
+pub async fn generate(
+    client: &dyn HttpClient,
+    api_url: &str,
+    request: GenerateRequest,
+) -> Result<GenerateResponse> {
+    let uri = format!("{api_url}/api/generate");
+    let request = HttpRequest::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .body(AsyncBody::from(serde_json::to_string(&request)?))?;
+
+    let mut response = client.send(request).await?;
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await?;
+
+    if response.status().is_success() {
+        serde_json::from_str(&body).context("Unable to parse generate response")
+    } else {
+        Err(anyhow!(
+            "Failed to connect to Ollama API: {} {}",
+            response.status(),
+            body,
+        ))
+    }
+}
+
 pub async fn stream_chat_completion(
     client: &dyn HttpClient,
     api_url: &str,
@@ -179,6 +179,14 @@ pub struct Request {
     pub tool_choice: Option<ToolChoice>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tools: Vec<ToolDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Whether to send the usage of the request in a last event, with no choices.
+    pub include_usage: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -231,7 +239,7 @@ pub enum RequestMessage {
         tool_calls: Vec<ToolCall>,
     },
     User {
-        content: String,
+        content: MessageContent,
     },
     System {
         content: String,
@@ -242,6 +250,50 @@ pub enum RequestMessage {
     },
 }
 
+/// The content of a user message, which is either plain text or a list of parts
+/// for models that accept images.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Plain(String),
+    Multipart(Vec<MessagePart>),
+}
+
+impl MessageContent {
+    /// The text of the content, without its images.
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Plain(text) => text.clone(),
+            MessageContent::Multipart(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    MessagePart::Text { text } => Some(text.as_str()),
+                    MessagePart::ImageUrl { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Plain(text)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessagePart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct ImageUrl {
+    /// The URL of the image, which may be a `data:` URL holding the image itself.
+    pub url: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct ToolCall {
     pub id: String,
@@ -291,6 +343,15 @@ pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PromptTokensDetails {
+    /// The prompt tokens read from the prompt cache.
+    #[serde(default)]
+    pub cached_tokens: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -468,7 +529,7 @@ fn adapt_response_to_stream(response: Response) -> ResponseStreamEvent {
                     }),
                     content: match choice.message {
                         RequestMessage::Assistant { content, .. } => content,
-                        RequestMessage::User { content } => Some(content),
+                        RequestMessage::User { content } => Some(content.text()),
                         RequestMessage::System { content } => Some(content),
                         RequestMessage::Tool { content, .. } => Some(content),
                     },
@@ -493,12 +554,21 @@ pub async fn stream_completion(
         return Ok(stream::once(future::ready(response_stream_event)).boxed());
     }
 
+    stream_completion_with_headers(client, api_url, Some(api_key), &[], request).await
+}
+
+/// Streams a completion from an OpenAI-compatible API, which may not need an API key,
+/// sending the given headers along with the request.
+pub async fn stream_completion_with_headers(
+    client: &dyn HttpClient,
+    api_url: &str,
+    api_key: Option<&str>,
+    headers: &[(String, String)],
+    request: Request,
+) -> Result<BoxStream<'static, Result<ResponseStreamEvent>>> {
     let uri = format!("{api_url}/chat/completions");
-    let request_builder = HttpRequest::builder()
-        .method(Method::POST)
-        .uri(uri)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_key));
+    let request_builder = request_builder(Method::POST, uri, api_key, headers)
+        .header("Content-Type", "application/json");
 
     let request = request_builder.body(AsyncBody::from(serde_json::to_string(&request)?))?;
     let mut response = client.send(request).await?;
@@ -555,6 +625,108 @@ pub async fn stream_completion(
     }
 }
 
+fn request_builder(
+    method: Method,
+    uri: String,
+    api_key: Option<&str>,
+    headers: &[(String, String)],
+) -> http_client::http::request::Builder {
+    let mut request_builder = HttpRequest::builder().method(method).uri(uri);
+    if let Some(api_key) = api_key {
+        request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+    for (name, value) in headers {
+        request_builder = request_builder.header(name.as_str(), value.as_str());
+    }
+    request_builder
+}
+
+/// A request to the legacy `/completions` endpoint, which completes a raw prompt.
+#[derive(Debug, Serialize)]
+pub struct TextCompletionRequest {
+    pub model: String,
+    pub prompt: String,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    pub stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextCompletionChoice {
+    pub text: String,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextCompletionResponse {
+    pub choices: Vec<TextCompletionChoice>,
+}
+
+/// Completes a raw prompt with the `/completions` endpoint of an OpenAI-compatible API.
+pub async fn text_completion(
+    client: &dyn HttpClient,
+    api_url: &str,
+    api_key: Option<&str>,
+    headers: &[(String, String)],
+    request: TextCompletionRequest,
+) -> Result<TextCompletionResponse> {
+    let uri = format!("{api_url}/completions");
+    let request = request_builder(Method::POST, uri, api_key, headers)
+        .header("Content-Type", "application/json")
+        .body(AsyncBody::from(serde_json::to_string(&request)?))?;
+    let mut response = client.send(request).await?;
+
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await?;
+    if response.status().is_success() {
+        serde_json::from_str(&body).context("failed to parse the completion")
+    } else {
+        Err(anyhow!(
+            "Failed to complete the prompt: {} {}",
+            response.status(),
+            body,
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListedModel {
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+struct ListModelsResponse {
+    data: Vec<ListedModel>,
+}
+
+/// Lists the models an OpenAI-compatible API serves, from its `/models` endpoint.
+pub async fn list_models(
+    client: &dyn HttpClient,
+    api_url: &str,
+    api_key: Option<&str>,
+    headers: &[(String, String)],
+) -> Result<Vec<ListedModel>> {
+    let uri = format!("{api_url}/models");
+    let request = request_builder(Method::GET, uri, api_key, headers).body(AsyncBody::empty())?;
+    let mut response = client.send(request).await?;
+
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await?;
+    if response.status().is_success() {
+        let response: ListModelsResponse =
+            serde_json::from_str(&body).context("failed to parse the listed models")?;
+        Ok(response.data)
+    } else {
+        Err(anyhow!(
+            "Failed to list models: {} {}",
+            response.status(),
+            body,
+        ))
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum OpenAiEmbeddingModel {
     #[serde(rename = "text-embedding-3-small")]
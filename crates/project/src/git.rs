@@ -1,11 +1,11 @@
 use crate::buffer_store::BufferStore;
 use crate::worktree_store::{WorktreeStore, WorktreeStoreEvent};
 use crate::{Project, ProjectPath};
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
 use client::ProjectId;
 use futures::channel::{mpsc, oneshot};
 use futures::StreamExt as _;
-use git::repository::{Branch, CommitDetails, PushOptions, Remote, ResetMode};
+use git::repository::{Branch, CommitDetails, DiffType, FileDiff, PushOptions, Remote, ResetMode};
 use git::{
     repository::{GitRepository, RepoPath},
     status::{GitSummary, TrackedSummary},
@@ -1052,6 +1052,18 @@ impl Repository {
         }
     }
 
+    /// Returns the files that changed between the two sides of the diff, along with their text.
+    pub fn diff(&self, diff_type: DiffType, cx: &App) -> Task<Result<Vec<FileDiff>>> {
+        match self.git_repo.clone() {
+            GitRepo::Local(git_repository) => {
+                cx.background_spawn(async move { git_repository.diff(diff_type) })
+            }
+            GitRepo::Remote { .. } => Task::ready(Err(anyhow!(
+                "diffing repositories isn't supported in remote projects yet"
+            ))),
+        }
+    }
+
     fn buffer_store(&self, cx: &App) -> Option<Entity<BufferStore>> {
         Some(self.git_store.upgrade()?.read(cx).buffer_store.clone())
     }
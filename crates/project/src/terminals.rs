@@ -83,6 +83,18 @@ impl Project {
         kind: TerminalKind,
         window: AnyWindowHandle,
         cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Terminal>>> {
+        self.create_terminal_with_scrollback(kind, None, window, cx)
+    }
+
+    /// Creates a terminal that shows the scrollback of a previous session above the shell's
+    /// output.
+    pub fn create_terminal_with_scrollback(
+        &mut self,
+        kind: TerminalKind,
+        scrollback: Option<String>,
+        window: AnyWindowHandle,
+        cx: &mut Context<Self>,
     ) -> Task<Result<Entity<Terminal>>> {
         let path: Option<Arc<Path>> = match &kind {
             TerminalKind::Shell(path) => path.as_ref().map(|path| Arc::from(path.as_ref())),
@@ -117,7 +129,13 @@ impl Project {
                 None
             };
             project.update(&mut cx, |project, cx| {
-                project.create_terminal_with_venv(kind, python_venv_directory, window, cx)
+                project.create_terminal_with_venv(
+                    kind,
+                    python_venv_directory,
+                    scrollback,
+                    window,
+                    cx,
+                )
             })?
         })
     }
@@ -183,6 +201,7 @@ impl Project {
         &mut self,
         kind: TerminalKind,
         python_venv_directory: Option<PathBuf>,
+        scrollback: Option<String>,
         window: AnyWindowHandle,
         cx: &mut Context<Self>,
     ) -> Result<Entity<Terminal>> {
@@ -333,7 +352,9 @@ impl Project {
             settings.cursor_shape.unwrap_or_default(),
             settings.alternate_scroll,
             settings.max_scroll_history_lines,
+            settings.shell_integration,
             ssh_details.is_some(),
+            scrollback,
             window,
             completion_tx,
             cx,
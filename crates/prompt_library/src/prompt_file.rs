@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use collections::HashSet;
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// A prompt as it's imported from and exported to a Markdown file, with its metadata in
+/// front matter:
+///
+/// ```markdown
+/// ---
+/// title: Code Review
+/// default: true
+/// ---
+/// Review {{ZED_FILE}} for bugs.
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromptFile {
+    pub title: Option<String>,
+    pub default: bool,
+    pub body: String,
+}
+
+impl PromptFile {
+    /// Parses the file's text, taking the title from the file's name when its front matter
+    /// doesn't have one.
+    pub fn parse(text: &str, file_stem: Option<&str>) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let mut title = None;
+        let mut default = false;
+        let mut body = text.as_str();
+
+        if let Some(rest) = text
+            .strip_prefix(FRONT_MATTER_DELIMITER)
+            .and_then(|rest| rest.strip_prefix('\n'))
+        {
+            let (front_matter, rest) = if let Some(rest) = rest.strip_prefix("---") {
+                ("", rest)
+            } else {
+                let end = rest
+                    .find("\n---")
+                    .ok_or_else(|| anyhow!("the front matter is never closed with `---`"))?;
+                (&rest[..end], &rest[end + 4..])
+            };
+            body = rest.strip_prefix('\n').unwrap_or(rest);
+
+            for line in front_matter.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (key, value) = line
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("invalid front matter line: {line:?}"))?;
+                let value = value.trim();
+                match key.trim() {
+                    "title" => title = Some(unquote(value)),
+                    "default" => {
+                        default = value
+                            .parse()
+                            .map_err(|_| anyhow!("`default` must be true or false"))?
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let title = title
+            .or_else(|| file_stem.map(str::to_string))
+            .filter(|title| !title.trim().is_empty());
+        Ok(Self {
+            title,
+            default,
+            body: body.to_string(),
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = String::new();
+        text.push_str(FRONT_MATTER_DELIMITER);
+        text.push('\n');
+        if let Some(title) = self.title.as_ref() {
+            text.push_str("title: ");
+            text.push_str(&quote(title));
+            text.push('\n');
+        }
+        text.push_str(&format!("default: {}\n", self.default));
+        text.push_str(FRONT_MATTER_DELIMITER);
+        text.push('\n');
+        text.push_str(&self.body);
+        text
+    }
+
+    /// Returns a name for the prompt's file that's safe to use on any platform, and that isn't
+    /// in `taken_names` yet, adding it to them.
+    pub fn file_name(&self, taken_names: &mut HashSet<String>) -> String {
+        let stem = self
+            .title
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| {
+                if c.is_control()
+                    || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+                {
+                    '-'
+                } else {
+                    c
+                }
+            })
+            .collect::<String>();
+        let stem = stem.trim_matches(|c: char| c == '.' || c.is_whitespace());
+        let stem = if stem.is_empty() { "Untitled" } else { stem };
+
+        let mut file_name = format!("{stem}.md");
+        let mut suffix = 2;
+        while !taken_names.insert(file_name.to_lowercase()) {
+            file_name = format!("{stem} {suffix}.md");
+            suffix += 1;
+        }
+        file_name
+    }
+}
+
+/// Wraps a front matter value in double quotes, escaping what would otherwise end it or be
+/// trimmed away.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads a front matter value, unescaping it if it's wrapped in double quotes.
+fn unquote(value: &str) -> String {
+    let Some(quoted) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some(c) => unquoted.push(c),
+            None => unquoted.push('\\'),
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let file = PromptFile {
+            title: Some("Code Review: Rust".into()),
+            default: true,
+            body: "Review {{ZED_FILE}}.\n---\nBe brief.\n".into(),
+        };
+        let text = file.to_markdown();
+        assert_eq!(
+            text,
+            "---\ntitle: \"Code Review: Rust\"\ndefault: true\n---\nReview {{ZED_FILE}}.\n---\nBe brief.\n"
+        );
+        assert_eq!(PromptFile::parse(&text, Some("other")).unwrap(), file);
+
+        for title in [
+            "\"Quoted\"",
+            "  Indented ",
+            "# Heading",
+            "C:\\Prompts\\",
+            "Two\nlines",
+        ] {
+            let file = PromptFile {
+                title: Some(title.into()),
+                default: false,
+                body: "Body\n".into(),
+            };
+            assert_eq!(
+                PromptFile::parse(&file.to_markdown(), Some("other")).unwrap(),
+                file,
+                "title {title:?} didn't round-trip"
+            );
+        }
+        assert_eq!(
+            PromptFile {
+                title: Some("Say \"hi\" \\o/".into()),
+                default: false,
+                body: String::new(),
+            }
+            .to_markdown(),
+            "---\ntitle: \"Say \\\"hi\\\" \\\\o/\"\ndefault: false\n---\n"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PromptFile::parse("Just a body.", Some("Notes")).unwrap(),
+            PromptFile {
+                title: Some("Notes".into()),
+                default: false,
+                body: "Just a body.".into(),
+            }
+        );
+        assert_eq!(
+            PromptFile::parse("---\r\ntitle: \"Quoted\"\r\n---\r\nBody\r\n", None).unwrap(),
+            PromptFile {
+                title: Some("Quoted".into()),
+                default: false,
+                body: "Body\n".into(),
+            }
+        );
+        assert!(PromptFile::parse("---\ntitle: Unclosed\nBody", None).is_err());
+        assert!(PromptFile::parse("---\ndefault: yes\n---\n", None).is_err());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let mut taken_names = HashSet::default();
+        let file = |title: Option<&str>| PromptFile {
+            title: title.map(str::to_string),
+            default: false,
+            body: String::new(),
+        };
+        assert_eq!(
+            file(Some("Review: a/b?")).file_name(&mut taken_names),
+            "Review- a-b-.md"
+        );
+        assert_eq!(file(Some("Notes")).file_name(&mut taken_names), "Notes.md");
+        assert_eq!(
+            file(Some("notes")).file_name(&mut taken_names),
+            "notes 2.md"
+        );
+        assert_eq!(file(None).file_name(&mut taken_names), "Untitled.md");
+        assert_eq!(
+            file(Some(" .. ")).file_name(&mut taken_names),
+            "Untitled 2.md"
+        );
+    }
+}
@@ -1,14 +1,18 @@
+mod prompt_file;
 mod prompt_store;
+mod prompt_template;
+mod prompt_variables_modal;
 mod prompts;
 
 use anyhow::Result;
 use collections::{HashMap, HashSet};
 use editor::CompletionProvider;
 use editor::{actions::Tab, CurrentLineHighlight, Editor, EditorElement, EditorEvent, EditorStyle};
+use fs::Fs;
 use gpui::{
     actions, point, size, transparent_black, Action, App, Bounds, Entity, EventEmitter, Focusable,
-    PromptLevel, Subscription, Task, TextStyle, TitlebarOptions, WindowBounds, WindowHandle,
-    WindowOptions,
+    PathPromptOptions, PromptLevel, Subscription, Task, TextStyle, TitlebarOptions, WindowBounds,
+    WindowHandle, WindowOptions,
 };
 use language::{language_settings::SoftWrap, Buffer, LanguageRegistry};
 use language_model::{
@@ -26,10 +30,13 @@ use ui::{
     ParentElement, Render, SharedString, Styled, Tooltip, Window,
 };
 use util::{ResultExt, TryFutureExt};
-use workspace::Workspace;
+use workspace::{notifications::DetachAndPromptErr, Workspace};
 use zed_actions::assistant::InlineAssist;
 
+pub use crate::prompt_file::*;
 pub use crate::prompt_store::*;
+pub use crate::prompt_template::*;
+pub use crate::prompt_variables_modal::*;
 pub use crate::prompts::*;
 
 pub fn init(cx: &mut App) {
@@ -42,7 +49,9 @@ actions!(
         NewPrompt,
         DeletePrompt,
         DuplicatePrompt,
-        ToggleDefaultPrompt
+        ToggleDefaultPrompt,
+        ImportPrompts,
+        ExportPrompts
     ]
 );
 
@@ -753,6 +762,55 @@ impl PromptLibrary {
         }
     }
 
+    /// Adds the Markdown files of a directory the user picks to the library.
+    pub fn import_prompts(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, |this, mut cx| async move {
+            let Some(dir) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            store.import_from_directory(&dir, fs.as_ref()).await?;
+            this.update_in(&mut cx, |this, window, cx| {
+                // Imported prompts may have replaced the ones being edited, so reopen them.
+                this.prompt_editors.clear();
+                let active_prompt_id = this.active_prompt_id;
+                this.set_active_prompt(None, window, cx);
+                if let Some(prompt_id) = active_prompt_id {
+                    this.load_prompt(prompt_id, false, window, cx);
+                }
+                this.picker
+                    .update(cx, |picker, cx| picker.refresh(window, cx));
+                cx.notify();
+            })
+        })
+        .detach_and_prompt_err("Failed to import prompts", window, cx, |_, _, _| None);
+    }
+
+    /// Writes the user's prompts to a directory the user picks, one Markdown file per prompt.
+    pub fn export_prompts(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+        let store = self.store.clone();
+        let fs = <dyn Fs>::global(cx);
+        cx.spawn_in(window, |_, _| async move {
+            let Some(dir) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            store.export_to_directory(&dir, fs.as_ref()).await?;
+            anyhow::Ok(())
+        })
+        .detach_and_prompt_err("Failed to export prompts", window, cx, |_, _, _| None);
+    }
+
     fn focus_active_prompt(&mut self, _: &Tab, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(active_prompt) = self.active_prompt_id {
             self.prompt_editors[&active_prompt]
@@ -939,6 +997,29 @@ impl PromptLibrary {
                     .w_full()
                     .flex_none()
                     .justify_end()
+                    .gap_0p5()
+                    .child(
+                        IconButton::new("import-prompts", IconName::ArrowDownFromLine)
+                            .style(ButtonStyle::Transparent)
+                            .shape(IconButtonShape::Square)
+                            .tooltip(move |window, cx| {
+                                Tooltip::for_action("Import Prompts", &ImportPrompts, window, cx)
+                            })
+                            .on_click(|_, window, cx| {
+                                window.dispatch_action(Box::new(ImportPrompts), cx);
+                            }),
+                    )
+                    .child(
+                        IconButton::new("export-prompts", IconName::ArrowUpFromLine)
+                            .style(ButtonStyle::Transparent)
+                            .shape(IconButtonShape::Square)
+                            .tooltip(move |window, cx| {
+                                Tooltip::for_action("Export Prompts", &ExportPrompts, window, cx)
+                            })
+                            .on_click(|_, window, cx| {
+                                window.dispatch_action(Box::new(ExportPrompts), cx);
+                            }),
+                    )
                     .child(
                         IconButton::new("new-prompt", IconName::Plus)
                             .style(ButtonStyle::Transparent)
@@ -1227,6 +1308,12 @@ impl Render for PromptLibrary {
             .on_action(cx.listener(|this, &ToggleDefaultPrompt, window, cx| {
                 this.toggle_default_for_active_prompt(window, cx)
             }))
+            .on_action(
+                cx.listener(|this, &ImportPrompts, window, cx| this.import_prompts(window, cx)),
+            )
+            .on_action(
+                cx.listener(|this, &ExportPrompts, window, cx| this.export_prompts(window, cx)),
+            )
             .size_full()
             .overflow_hidden()
             .font(ui_font)
@@ -1,8 +1,9 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 use chrono::{DateTime, Utc};
-use collections::HashMap;
+use collections::{HashMap, HashSet};
+use fs::Fs;
 use futures::future::{self, BoxFuture, Shared};
-use futures::FutureExt as _;
+use futures::{FutureExt as _, StreamExt as _};
 use fuzzy::StringMatchCandidate;
 use gpui::{App, BackgroundExecutor, Global, ReadGlobal, SharedString, Task};
 use heed::{
@@ -15,13 +16,15 @@ use serde::{Deserialize, Serialize};
 use std::{
     cmp::Reverse,
     future::Future,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicBool, Arc},
 };
 use text::LineEnding;
 use util::ResultExt;
 use uuid::Uuid;
 
+use crate::{LoadedPromptTemplate, PromptFile, PromptTemplate};
+
 /// Init starts loading the PromptStore in the background and assigns
 /// a shared future to a global.
 pub fn init(cx: &mut App) {
@@ -248,6 +251,109 @@ impl PromptStore {
         })
     }
 
+    /// Loads the prompt as a template, along with the prompts it includes.
+    pub fn load_template(&self, id: PromptId) -> Task<Result<LoadedPromptTemplate>> {
+        let mut ids_by_title = HashMap::default();
+        for metadata in &self.metadata_cache.read().metadata {
+            if let Some(title) = metadata.title.as_ref() {
+                // Like `id_for_title`, prefer the first of the prompts with the same title.
+                ids_by_title.entry(title.to_string()).or_insert(metadata.id);
+            }
+        }
+
+        let env = self.env.clone();
+        let bodies = self.bodies;
+        self.executor.spawn(async move {
+            let txn = env.read_txn()?;
+            let load_body = |id: PromptId| -> Result<String> {
+                let mut body: String = bodies
+                    .get(&txn, &id)?
+                    .ok_or_else(|| anyhow!("prompt not found"))?
+                    .into();
+                LineEnding::normalize(&mut body);
+                Ok(body)
+            };
+
+            let template = PromptTemplate::parse(&load_body(id)?)?;
+            let mut includes = HashMap::default();
+            let mut pending_titles = template.includes();
+            while let Some(title) = pending_titles.pop() {
+                if includes.contains_key(&title) {
+                    continue;
+                }
+                // Missing prompts are reported when the template is rendered.
+                let Some(id) = ids_by_title.get(&title) else {
+                    continue;
+                };
+                let included = PromptTemplate::parse(&load_body(*id)?)
+                    .with_context(|| format!("failed to parse the prompt \"{title}\""))?;
+                pending_titles.extend(included.includes());
+                includes.insert(title, included);
+            }
+
+            Ok(LoadedPromptTemplate { template, includes })
+        })
+    }
+
+    /// Writes the user's prompts to the directory as Markdown files, returning the number of
+    /// prompts written.
+    pub async fn export_to_directory(&self, dir: &Path, fs: &dyn Fs) -> Result<usize> {
+        let prompts = self
+            .metadata_cache
+            .read()
+            .metadata
+            .iter()
+            .filter(|metadata| !metadata.id.is_built_in())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        fs.create_dir(dir).await?;
+        let mut file_names = HashSet::default();
+        for metadata in &prompts {
+            let file = PromptFile {
+                title: metadata.title.as_ref().map(|title| title.to_string()),
+                default: metadata.default,
+                body: self.load(metadata.id).await?,
+            };
+            let path = dir.join(file.file_name(&mut file_names));
+            fs.atomic_write(path, file.to_markdown()).await?;
+        }
+        Ok(prompts.len())
+    }
+
+    /// Adds the Markdown files in the directory to the library, replacing the prompts with the
+    /// same titles, and returns the number of prompts imported.
+    pub async fn import_from_directory(&self, dir: &Path, fs: &dyn Fs) -> Result<usize> {
+        let mut paths = fs.read_dir(dir).await?;
+        let mut imported_count = 0;
+        while let Some(path) = paths.next().await {
+            let path = path?;
+            if path.extension().map_or(true, |extension| extension != "md") {
+                continue;
+            }
+
+            let text = fs.load(&path).await?;
+            let file_stem = path.file_stem().and_then(|stem| stem.to_str());
+            let file = PromptFile::parse(&text, file_stem)
+                .with_context(|| format!("failed to import {path:?}"))?;
+            let id = file
+                .title
+                .as_deref()
+                .and_then(|title| self.id_for_title(title))
+                .filter(|id| !id.is_built_in())
+                .unwrap_or_else(PromptId::new);
+            self.save(
+                id,
+                file.title.map(Into::into),
+                file.default,
+                file.body.into(),
+            )
+            .await?;
+            imported_count += 1;
+        }
+        Ok(imported_count)
+    }
+
     pub fn default_prompt_metadata(&self) -> Vec<PromptMetadata> {
         return self
             .metadata_cache
@@ -0,0 +1,455 @@
+use std::mem;
+
+use anyhow::{anyhow, bail, Result};
+use collections::{HashMap, HashSet};
+use editor::Editor;
+use gpui::{App, AppContext as _, Task, Window};
+use task::ZED_VARIABLE_NAME_PREFIX;
+use workspace::Workspace;
+
+/// The built-in variable holding the name of the active file's language, which tasks have no
+/// variable for.
+pub const LANGUAGE_VARIABLE: &str = "ZED_LANGUAGE";
+
+/// The body of a prompt, which can use variables, conditional blocks, and other prompts:
+///
+/// - `{{name}}` is replaced by the value of the variable `name`, or by nothing if it has none.
+/// - `{{#if name}}…{{else}}…{{/if}}` keeps the text before `{{else}}` if `name` has a value
+///   that isn't blank, and the text after it otherwise. The `{{else}}` part is optional.
+/// - `{{> Title}}` is replaced by the prompt titled `Title`, filled in with the same variables.
+/// - `\{{` is a literal `{{`.
+///
+/// Variables whose names start with `ZED_` are filled in by Zed, like the variables of tasks,
+/// and the user is asked for the others. Anything else between double braces, like `{{a b}}`, is
+/// kept as it is. This does change prompts written before templates existed that have a single
+/// word between double braces, which now becomes a variable; escape those as `\{{word}}`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PromptTemplate {
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Include(String),
+}
+
+enum Tag<'a> {
+    Variable(&'a str),
+    If(&'a str),
+    Else,
+    EndIf,
+    Include(&'a str),
+}
+
+/// An `{{#if}}` block that hasn't been closed yet.
+struct OpenIf {
+    variable: String,
+    /// The nodes before `{{else}}`, once it has been seen.
+    then: Option<Vec<Node>>,
+    /// The nodes before the block.
+    outer: Vec<Node>,
+}
+
+impl PromptTemplate {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut open_ifs = Vec::<OpenIf>::new();
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                text.push_str(&rest[..start - 1]);
+                text.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            text.push_str(&rest[..start]);
+            let after_start = &rest[start + 2..];
+            let Some((tag, end)) = after_start
+                .find("}}")
+                .and_then(|end| Some((parse_tag(&after_start[..end])?, end)))
+            else {
+                text.push_str("{{");
+                rest = after_start;
+                continue;
+            };
+            rest = &after_start[end + 2..];
+
+            if !text.is_empty() {
+                nodes.push(Node::Text(mem::take(&mut text)));
+            }
+            match tag {
+                Tag::Variable(variable) => nodes.push(Node::Variable(variable.to_string())),
+                Tag::Include(title) => nodes.push(Node::Include(title.to_string())),
+                Tag::If(variable) => open_ifs.push(OpenIf {
+                    variable: variable.to_string(),
+                    then: None,
+                    outer: mem::take(&mut nodes),
+                }),
+                Tag::Else => {
+                    let Some(open_if) =
+                        open_ifs.last_mut().filter(|open_if| open_if.then.is_none())
+                    else {
+                        bail!("`{{{{else}}}}` outside of an `{{{{#if}}}}` block");
+                    };
+                    open_if.then = Some(mem::take(&mut nodes));
+                }
+                Tag::EndIf => {
+                    let Some(open_if) = open_ifs.pop() else {
+                        bail!("`{{{{/if}}}}` without an `{{{{#if}}}}`");
+                    };
+                    let (then, otherwise) = match open_if.then {
+                        Some(then) => (then, mem::take(&mut nodes)),
+                        None => (mem::take(&mut nodes), Vec::new()),
+                    };
+                    nodes = open_if.outer;
+                    nodes.push(Node::If {
+                        variable: open_if.variable,
+                        then,
+                        otherwise,
+                    });
+                }
+            }
+        }
+
+        if let Some(open_if) = open_ifs.pop() {
+            bail!(
+                "`{{{{#if {}}}}}` is never closed with `{{{{/if}}}}`",
+                open_if.variable
+            );
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Returns the titles of the prompts that the template includes directly.
+    pub fn includes(&self) -> Vec<String> {
+        fn collect(nodes: &[Node], titles: &mut Vec<String>) {
+            for node in nodes {
+                match node {
+                    Node::Include(title) => titles.push(title.clone()),
+                    Node::If {
+                        then, otherwise, ..
+                    } => {
+                        collect(then, titles);
+                        collect(otherwise, titles);
+                    }
+                    Node::Text(_) | Node::Variable(_) => {}
+                }
+            }
+        }
+
+        let mut titles = Vec::new();
+        collect(&self.nodes, &mut titles);
+        titles
+    }
+}
+
+/// A prompt's template, along with the templates of the prompts it includes directly or
+/// through other prompts, keyed by their titles.
+#[derive(Clone, Debug, Default)]
+pub struct LoadedPromptTemplate {
+    pub template: PromptTemplate,
+    pub includes: HashMap<String, PromptTemplate>,
+}
+
+impl LoadedPromptTemplate {
+    /// Returns the names of the variables that the user has to fill in, in the order they
+    /// first appear.
+    pub fn user_variables(&self) -> Vec<String> {
+        let mut variables = Vec::new();
+        self.collect_user_variables(
+            &self.template.nodes,
+            &mut HashSet::default(),
+            &mut variables,
+        );
+        variables
+    }
+
+    fn collect_user_variables<'a>(
+        &'a self,
+        nodes: &'a [Node],
+        visited_includes: &mut HashSet<&'a str>,
+        variables: &mut Vec<String>,
+    ) {
+        for node in nodes {
+            match node {
+                Node::Text(_) => {}
+                Node::Variable(variable) => push_user_variable(variable, variables),
+                Node::If {
+                    variable,
+                    then,
+                    otherwise,
+                } => {
+                    push_user_variable(variable, variables);
+                    self.collect_user_variables(then, visited_includes, variables);
+                    self.collect_user_variables(otherwise, visited_includes, variables);
+                }
+                Node::Include(title) => {
+                    if visited_includes.insert(title) {
+                        if let Some(template) = self.includes.get(title) {
+                            self.collect_user_variables(
+                                &template.nodes,
+                                visited_includes,
+                                variables,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills in the template with the variables, leaving out the ones without a value.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String> {
+        let mut output = String::new();
+        self.render_nodes(
+            &self.template.nodes,
+            variables,
+            &mut Vec::new(),
+            &mut output,
+        )?;
+        Ok(output)
+    }
+
+    fn render_nodes<'a>(
+        &'a self,
+        nodes: &'a [Node],
+        variables: &HashMap<String, String>,
+        include_stack: &mut Vec<&'a str>,
+        output: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Variable(variable) => {
+                    if let Some(value) = variables.get(variable) {
+                        output.push_str(value);
+                    }
+                }
+                Node::If {
+                    variable,
+                    then,
+                    otherwise,
+                } => {
+                    let is_set = variables
+                        .get(variable)
+                        .map_or(false, |value| !value.trim().is_empty());
+                    let nodes = if is_set { then } else { otherwise };
+                    self.render_nodes(nodes, variables, include_stack, output)?;
+                }
+                Node::Include(title) => {
+                    if include_stack.contains(&title.as_str()) {
+                        bail!("the prompt \"{title}\" includes itself");
+                    }
+                    let template = self
+                        .includes
+                        .get(title)
+                        .ok_or_else(|| anyhow!("no prompt titled \"{title}\" to include"))?;
+                    include_stack.push(title);
+                    self.render_nodes(&template.nodes, variables, include_stack, output)?;
+                    include_stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the values of the built-in variables for the workspace's active editor, which are
+/// the variables of tasks (see [`task::VariableName`]) and [`LANGUAGE_VARIABLE`].
+pub fn built_in_prompt_variables(
+    workspace: &Workspace,
+    window: &mut Window,
+    cx: &mut App,
+) -> Task<HashMap<String, String>> {
+    let language = workspace
+        .active_item(cx)
+        .and_then(|item| item.act_as::<Editor>(cx))
+        .and_then(|editor| {
+            let buffer = editor.read(cx).buffer().read(cx).as_singleton()?;
+            let language = buffer.read(cx).language()?;
+            Some(language.name().to_string())
+        });
+    let task_context = editor::tasks::task_context(workspace, window, cx);
+    cx.background_spawn(async move {
+        let mut variables = task_context
+            .await
+            .task_variables
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<HashMap<_, _>>();
+        if let Some(language) = language {
+            variables.insert(LANGUAGE_VARIABLE.to_string(), language);
+        }
+        variables
+    })
+}
+
+fn parse_tag(tag: &str) -> Option<Tag<'_>> {
+    let tag = tag.trim();
+    if let Some(title) = tag.strip_prefix('>') {
+        let title = title.trim();
+        return (!title.is_empty()).then_some(Tag::Include(title));
+    }
+    if let Some(variable) = tag.strip_prefix("#if ") {
+        let variable = variable.trim();
+        return is_variable_name(variable).then_some(Tag::If(variable));
+    }
+    match tag {
+        "else" => Some(Tag::Else),
+        "/if" => Some(Tag::EndIf),
+        _ => is_variable_name(tag).then_some(Tag::Variable(tag)),
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn push_user_variable(variable: &str, variables: &mut Vec<String>) {
+    if !variable.starts_with(ZED_VARIABLE_NAME_PREFIX) && !variables.iter().any(|v| v == variable) {
+        variables.push(variable.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(variables: &[(&str, &str)]) -> HashMap<String, String> {
+        variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn render(source: &str, values: &[(&str, &str)]) -> String {
+        LoadedPromptTemplate {
+            template: PromptTemplate::parse(source).unwrap(),
+            includes: HashMap::default(),
+        }
+        .render(&variables(values))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            render(
+                "Review {{ZED_FILE}} for {{ audience }}.",
+                &[("ZED_FILE", "src/main.rs"), ("audience", "beginners")]
+            ),
+            "Review src/main.rs for beginners."
+        );
+        assert_eq!(render("Hello, {{name}}!", &[]), "Hello, !");
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let source = "Explain{{#if ZED_SELECTED_TEXT}} this:\n{{ZED_SELECTED_TEXT}}{{else}} the file{{/if}}.";
+        assert_eq!(
+            render(source, &[("ZED_SELECTED_TEXT", "fn main() {}")]),
+            "Explain this:\nfn main() {}."
+        );
+        assert_eq!(
+            render(source, &[("ZED_SELECTED_TEXT", "  ")]),
+            "Explain the file."
+        );
+        assert_eq!(
+            render(
+                "{{#if a}}A{{#if b}}B{{/if}}{{/if}}",
+                &[("a", "1"), ("b", "1")]
+            ),
+            "AB"
+        );
+        assert_eq!(
+            render("{{#if a}}A{{#if b}}B{{/if}}{{/if}}", &[("b", "1")]),
+            ""
+        );
+
+        assert!(PromptTemplate::parse("{{#if a}}A").is_err());
+        assert!(PromptTemplate::parse("A{{/if}}").is_err());
+        assert!(PromptTemplate::parse("{{else}}").is_err());
+        assert!(PromptTemplate::parse("{{#if a}}A{{else}}B{{else}}C{{/if}}").is_err());
+    }
+
+    #[test]
+    fn test_text_that_isnt_a_tag() {
+        assert_eq!(
+            render("let x = {{ a: 1 }}; \\{{name}} {{", &[("name", "Ferris")]),
+            "let x = {{ a: 1 }}; {{name}} {{"
+        );
+    }
+
+    #[test]
+    fn test_includes() {
+        let template = LoadedPromptTemplate {
+            template: PromptTemplate::parse("{{> Style }}\nReview {{file}}.").unwrap(),
+            includes: [
+                (
+                    "Style".to_string(),
+                    PromptTemplate::parse("Write in {{ZED_LANGUAGE}} for {{audience}}.{{> Tone}}")
+                        .unwrap(),
+                ),
+                (
+                    "Tone".to_string(),
+                    PromptTemplate::parse(" Be {{tone}}, {{audience}}.").unwrap(),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(template.template.includes(), vec!["Style".to_string()]);
+        assert_eq!(
+            template.user_variables(),
+            vec![
+                "audience".to_string(),
+                "tone".to_string(),
+                "file".to_string()
+            ]
+        );
+        assert_eq!(
+            template
+                .render(&variables(&[
+                    ("ZED_LANGUAGE", "Rust"),
+                    ("audience", "reviewers"),
+                    ("tone", "brief"),
+                    ("file", "lib.rs"),
+                ]))
+                .unwrap(),
+            "Write in Rust for reviewers. Be brief, reviewers.\nReview lib.rs."
+        );
+
+        let template = LoadedPromptTemplate {
+            template: PromptTemplate::parse("{{> Loop}}").unwrap(),
+            includes: [(
+                "Loop".to_string(),
+                PromptTemplate::parse("again {{> Loop}}").unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+        };
+        assert!(template.render(&HashMap::default()).is_err());
+
+        let template = LoadedPromptTemplate {
+            template: PromptTemplate::parse("{{> Missing}}").unwrap(),
+            includes: HashMap::default(),
+        };
+        assert!(template.render(&HashMap::default()).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use editor::actions::{Tab, TabPrev};
+use editor::Editor;
+use futures::channel::oneshot;
+use gpui::{
+    App, AsyncWindowContext, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, WeakEntity,
+};
+use ui::prelude::*;
+use workspace::{ModalView, Workspace};
+
+use crate::{built_in_prompt_variables, LoadedPromptTemplate};
+
+/// Fills in the prompt with the built-in variables of the workspace's active editor, and with
+/// the values the user enters in a [`PromptVariablesModal`] for the other variables.
+pub async fn fill_in_prompt(
+    workspace: WeakEntity<Workspace>,
+    prompt_title: SharedString,
+    template: LoadedPromptTemplate,
+    cx: &mut AsyncWindowContext,
+) -> Result<String> {
+    let (built_in_variables, user_values) = workspace.update_in(cx, |workspace, window, cx| {
+        let built_in_variables = built_in_prompt_variables(workspace, window, cx);
+        let user_values = PromptVariablesModal::toggle(
+            workspace,
+            prompt_title,
+            template.user_variables(),
+            window,
+            cx,
+        );
+        (built_in_variables, user_values)
+    })?;
+
+    let mut variables = built_in_variables.await;
+    variables.extend(
+        user_values
+            .await
+            .map_err(|_| anyhow!("the prompt's variables weren't filled in"))?,
+    );
+    template.render(&variables)
+}
+
+/// A form asking for the values of a prompt's variables before the prompt is inserted.
+pub struct PromptVariablesModal {
+    prompt_title: SharedString,
+    fields: Vec<(String, Entity<Editor>)>,
+    values_tx: Option<oneshot::Sender<HashMap<String, String>>>,
+}
+
+impl ModalView for PromptVariablesModal {}
+
+impl EventEmitter<DismissEvent> for PromptVariablesModal {}
+
+impl Focusable for PromptVariablesModal {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.fields[0].1.focus_handle(cx)
+    }
+}
+
+impl PromptVariablesModal {
+    /// Shows the form in the workspace. The values are received once the form is confirmed, and
+    /// the receiver is canceled if the form is dismissed instead.
+    pub fn toggle(
+        workspace: &mut Workspace,
+        prompt_title: impl Into<SharedString>,
+        variables: Vec<String>,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) -> oneshot::Receiver<HashMap<String, String>> {
+        let (values_tx, values_rx) = oneshot::channel();
+        if variables.is_empty() {
+            values_tx.send(HashMap::default()).ok();
+            return values_rx;
+        }
+
+        let prompt_title = prompt_title.into();
+        workspace.toggle_modal(window, cx, move |window, cx| {
+            Self::new(prompt_title, variables, values_tx, window, cx)
+        });
+        values_rx
+    }
+
+    fn new(
+        prompt_title: SharedString,
+        variables: Vec<String>,
+        values_tx: oneshot::Sender<HashMap<String, String>>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let fields = variables
+            .into_iter()
+            .map(|variable| {
+                let editor = cx.new(|cx| Editor::single_line(window, cx));
+                (variable, editor)
+            })
+            .collect();
+
+        Self {
+            prompt_title,
+            fields,
+            values_tx: Some(values_tx),
+        }
+    }
+
+    fn focused_field_ix(&self, window: &Window, cx: &App) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|(_, editor)| editor.focus_handle(cx).is_focused(window))
+    }
+
+    fn focus_next_field(&mut self, _: &Tab, window: &mut Window, cx: &mut Context<Self>) {
+        let ix = self
+            .focused_field_ix(window, cx)
+            .map_or(0, |ix| (ix + 1) % self.fields.len());
+        window.focus(&self.fields[ix].1.focus_handle(cx));
+    }
+
+    fn focus_previous_field(&mut self, _: &TabPrev, window: &mut Window, cx: &mut Context<Self>) {
+        let ix = self
+            .focused_field_ix(window, cx)
+            .map_or(0, |ix| (ix + self.fields.len() - 1) % self.fields.len());
+        window.focus(&self.fields[ix].1.focus_handle(cx));
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, _: &mut Window, cx: &mut Context<Self>) {
+        let values = self
+            .fields
+            .iter()
+            .map(|(variable, editor)| (variable.clone(), editor.read(cx).text(cx)))
+            .collect();
+        if let Some(values_tx) = self.values_tx.take() {
+            values_tx.send(values).ok();
+        }
+        cx.emit(DismissEvent);
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, _: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(DismissEvent);
+    }
+}
+
+impl Render for PromptVariablesModal {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("PromptVariablesModal")
+            .w(rems(28.))
+            .elevation_2(cx)
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .capture_action(cx.listener(Self::focus_next_field))
+            .capture_action(cx.listener(Self::focus_previous_field))
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .child(Label::new(format!("Fill In “{}”", self.prompt_title))),
+            )
+            .child(
+                v_flex()
+                    .p_2()
+                    .gap_2()
+                    .children(self.fields.iter().map(|(variable, editor)| {
+                        v_flex()
+                            .gap_1()
+                            .child(
+                                Label::new(variable.clone())
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                            .child(
+                                div()
+                                    .px_1()
+                                    .py_0p5()
+                                    .border_1()
+                                    .rounded_md()
+                                    .border_color(cx.theme().colors().border)
+                                    .child(editor.clone()),
+                            )
+                    })),
+            )
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .justify_end()
+                    .border_t_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .child(Button::new("insert-prompt", "Insert").on_click(
+                        cx.listener(|this, _, window, cx| this.confirm(&menu::Confirm, window, cx)),
+                    )),
+            )
+    }
+}
@@ -0,0 +1,472 @@
+//! Builds a compact map of a project's structure for the assistant: the symbols that each
+//! file defines, listing the files that the rest of the project refers to most first, and
+//! cut down to fit a token budget.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use collections::{BTreeMap, BTreeSet};
+use gpui::{App, AppContext as _, Entity, Task};
+use language::{Buffer, OutlineItem, Rope};
+use language_model::{
+    LanguageModel, LanguageModelRequest, LanguageModelRequestMessage, MessageContent, Role,
+};
+use project::Project;
+use util::ResultExt as _;
+
+/// The token budget of a repo map, when none is given.
+pub const DEFAULT_TOKEN_BUDGET: usize = 2048;
+
+/// The most files we parse for a map, to bound the time it takes to build one.
+const MAX_FILE_COUNT: usize = 2000;
+
+/// Files larger than this are usually generated or vendored, so we leave them out.
+const MAX_FILE_SIZE: u64 = 256 * 1024;
+
+/// We list top-level symbols and their direct children, such as the methods of a type.
+const MAX_SYMBOL_DEPTH: usize = 1;
+
+/// Names shorter than this (e.g., `id`, `new`) are too common to link files together.
+const MIN_DEFINITION_LEN: usize = 4;
+
+const DAMPING_FACTOR: f64 = 0.85;
+const RANK_ITERATIONS: usize = 30;
+
+/// No tokenizer produces fewer than one token per this many bytes of a map, which lets us
+/// skip counting the tokens of files that can't possibly fit.
+const MAX_BYTES_PER_TOKEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoMap {
+    pub text: String,
+    /// The number of files whose symbols are in the map.
+    pub included_file_count: usize,
+    /// The number of files that define any symbols, including those that didn't fit.
+    pub file_count: usize,
+}
+
+/// Builds a map of the project's visible worktrees that fits in the given number of tokens,
+/// as counted by the given model, or estimated when there is none.
+pub fn build_repo_map(
+    project: &Entity<Project>,
+    model: Option<Arc<dyn LanguageModel>>,
+    token_budget: usize,
+    cx: &mut App,
+) -> Task<Result<RepoMap>> {
+    let project = project.read(cx);
+    if !project.is_local() {
+        return Task::ready(Err(anyhow!(
+            "repo maps are only available for local projects"
+        )));
+    }
+
+    let fs = project.fs().clone();
+    let languages = project.languages().clone();
+    let mut paths = Vec::new();
+    for worktree in project.visible_worktrees(cx) {
+        let worktree = worktree.read(cx);
+        let root_name = Path::new(worktree.root_name());
+        let abs_path = worktree.abs_path();
+        for entry in worktree.files(false, 0) {
+            if entry.is_external || entry.is_private || entry.size > MAX_FILE_SIZE {
+                continue;
+            }
+            paths.push((root_name.join(&entry.path), abs_path.join(&entry.path)));
+        }
+    }
+
+    cx.spawn(|cx| async move {
+        let mut files = Vec::new();
+        let mut parsed_file_count = 0;
+        for (path, abs_path) in paths {
+            let Ok(language) = languages.language_for_file_path(&path).await else {
+                continue;
+            };
+            // Only files in a known language count towards the cap, so that assets
+            // and other unparsed files can't crowd out the source files.
+            if parsed_file_count == MAX_FILE_COUNT {
+                break;
+            }
+            parsed_file_count += 1;
+            let Some(text) = fs.load(&abs_path).await.log_err() else {
+                continue;
+            };
+
+            let snapshot = cx.update(|cx| {
+                Buffer::build_snapshot(
+                    Rope::from(text.as_str()),
+                    Some(language),
+                    Some(languages.clone()),
+                    cx,
+                )
+            })?;
+            let file = cx
+                .background_spawn(async move {
+                    let snapshot = snapshot.await;
+                    let outline = snapshot.outline(None)?;
+                    FileSymbols::new(path, &outline.items, &text)
+                })
+                .await;
+            files.extend(file);
+        }
+
+        let file_count = files.len();
+        let ranks = cx
+            .background_spawn({
+                let files = files.clone();
+                async move { rank_files(&files) }
+            })
+            .await;
+        let mut ranked_files = files.into_iter().zip(ranks).collect::<Vec<_>>();
+        ranked_files.sort_by(|(file_a, rank_a), (file_b, rank_b)| {
+            rank_b
+                .total_cmp(rank_a)
+                .then_with(|| file_a.path.cmp(&file_b.path))
+        });
+        let ranked_files = ranked_files
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect::<Vec<_>>();
+
+        let included_file_count = fit_to_budget(&ranked_files, token_budget, |text| {
+            let count = model.as_ref().and_then(|model| {
+                let request = LanguageModelRequest {
+                    messages: vec![LanguageModelRequestMessage {
+                        role: Role::User,
+                        content: vec![MessageContent::Text(text.clone())],
+                        cache: false,
+                    }],
+                    tools: Vec::new(),
+                    stop: Vec::new(),
+                    temperature: None,
+                };
+                cx.update(|cx| model.count_tokens(request, cx)).log_err()
+            });
+            async move {
+                match count {
+                    Some(count) => count.await,
+                    None => Ok(estimate_token_count(&text)),
+                }
+            }
+        })
+        .await?;
+
+        Ok(RepoMap {
+            text: render_map(&ranked_files[..included_file_count]),
+            included_file_count,
+            file_count,
+        })
+    })
+}
+
+/// The symbols that a file defines, and the identifiers it refers to.
+#[derive(Debug, Clone, PartialEq)]
+struct FileSymbols {
+    path: PathBuf,
+    symbols: Vec<Symbol>,
+    definitions: BTreeSet<String>,
+    /// The number of times each identifier appears in the file.
+    references: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Symbol {
+    depth: usize,
+    text: String,
+}
+
+impl FileSymbols {
+    fn new<T>(path: PathBuf, outline_items: &[OutlineItem<T>], text: &str) -> Option<Self> {
+        let symbols = outline_items
+            .iter()
+            .filter(|item| item.depth <= MAX_SYMBOL_DEPTH)
+            .map(|item| Symbol {
+                depth: item.depth,
+                text: item.text.clone(),
+            })
+            .collect::<Vec<_>>();
+        if symbols.is_empty() {
+            return None;
+        }
+
+        let definitions = outline_items
+            .iter()
+            .flat_map(|item| {
+                item.name_ranges
+                    .iter()
+                    .filter_map(|range| item.text.get(range.clone()))
+            })
+            .flat_map(identifiers)
+            .filter(|name| name.len() >= MIN_DEFINITION_LEN)
+            .map(str::to_string)
+            .collect();
+
+        let mut references = BTreeMap::<String, u32>::default();
+        for identifier in identifiers(text) {
+            if identifier.len() >= MIN_DEFINITION_LEN {
+                *references.entry(identifier.to_string()).or_default() += 1;
+            }
+        }
+
+        Some(Self {
+            path,
+            symbols,
+            definitions,
+            references,
+        })
+    }
+}
+
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|identifier| {
+            identifier
+                .chars()
+                .next()
+                .map_or(false, |c| c.is_alphabetic() || c == '_')
+        })
+}
+
+/// Ranks files by their PageRank in the graph where each file links to the files that
+/// define the identifiers it refers to, so that the files the rest of the project depends
+/// on rank highest.
+fn rank_files(files: &[FileSymbols]) -> Vec<f64> {
+    let file_count = files.len();
+    if file_count == 0 {
+        return Vec::new();
+    }
+
+    let mut definers = BTreeMap::<&str, Vec<usize>>::default();
+    for (ix, file) in files.iter().enumerate() {
+        for definition in &file.definitions {
+            definers.entry(definition.as_str()).or_default().push(ix);
+        }
+    }
+
+    let mut links = vec![BTreeMap::<usize, f64>::default(); file_count];
+    for (source, file) in files.iter().enumerate() {
+        for (identifier, count) in &file.references {
+            let Some(targets) = definers.get(identifier.as_str()) else {
+                continue;
+            };
+            // Referring to a name many times says less than referring to many names, and
+            // a name that many files define says little about which one is meant.
+            let weight = (*count as f64).sqrt() / targets.len() as f64;
+            for &target in targets {
+                if target != source {
+                    *links[source].entry(target).or_default() += weight;
+                }
+            }
+        }
+    }
+
+    let total_weights = links
+        .iter()
+        .map(|targets| targets.values().sum::<f64>())
+        .collect::<Vec<_>>();
+    let mut ranks = vec![1. / file_count as f64; file_count];
+    for _ in 0..RANK_ITERATIONS {
+        // Files that link nowhere share their rank with every file.
+        let unlinked_rank = ranks
+            .iter()
+            .zip(&total_weights)
+            .filter(|(_, total_weight)| **total_weight == 0.)
+            .map(|(rank, _)| rank)
+            .sum::<f64>();
+        let base_rank = (1. - DAMPING_FACTOR + DAMPING_FACTOR * unlinked_rank) / file_count as f64;
+
+        let mut next_ranks = vec![base_rank; file_count];
+        for (source, targets) in links.iter().enumerate() {
+            for (&target, &weight) in targets {
+                next_ranks[target] +=
+                    DAMPING_FACTOR * ranks[source] * weight / total_weights[source];
+            }
+        }
+        ranks = next_ranks;
+    }
+    ranks
+}
+
+fn render_map(files: &[FileSymbols]) -> String {
+    let mut text = String::new();
+    for file in files {
+        text.push_str(&file.path.to_string_lossy());
+        text.push_str(":\n");
+        for symbol in &file.symbols {
+            for _ in 0..=symbol.depth {
+                text.push_str("  ");
+            }
+            text.push_str(&symbol.text);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Returns how many of the given files, in order, fit in the token budget.
+async fn fit_to_budget<F, R>(
+    files: &[FileSymbols],
+    token_budget: usize,
+    mut count_tokens: F,
+) -> Result<usize>
+where
+    F: FnMut(String) -> R,
+    R: Future<Output = Result<usize>>,
+{
+    // Skip the files that couldn't fit however they were tokenized.
+    let max_len = token_budget.saturating_mul(MAX_BYTES_PER_TOKEN);
+    let mut len = 0;
+    let candidate_count = files
+        .iter()
+        .take_while(|file| {
+            len += render_map(std::slice::from_ref(file)).len();
+            len <= max_len
+        })
+        .count();
+
+    // Find the most files that fit, knowing that fewer files never take more tokens.
+    let mut low = 0;
+    let mut high = candidate_count;
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if count_tokens(render_map(&files[..mid])).await? <= token_budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+fn estimate_token_count(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn outline_item(depth: usize, text: &str, name: &str) -> OutlineItem<usize> {
+        let name_start = text.rfind(name).unwrap();
+        OutlineItem {
+            depth,
+            range: 0..0,
+            text: text.to_string(),
+            highlight_ranges: Vec::new(),
+            name_ranges: vec![name_start..name_start + name.len()],
+            body_range: None,
+            annotation_range: None,
+        }
+    }
+
+    fn file(path: &str, items: &[(usize, &str, &str)], text: &str) -> FileSymbols {
+        let items = items
+            .iter()
+            .map(|(depth, text, name)| outline_item(*depth, text, name))
+            .collect::<Vec<_>>();
+        FileSymbols::new(path.into(), &items, text).unwrap()
+    }
+
+    #[test]
+    fn test_file_symbols() {
+        let file = file(
+            "app/src/config.rs",
+            &[
+                (0, "pub struct Config", "Config"),
+                (0, "impl Config", "Config"),
+                (1, "pub fn load", "load"),
+                (2, "fn parse_line", "parse_line"),
+            ],
+            "pub struct Config {}\nimpl Config { pub fn load() -> Config { parse_line(\"a\") } }",
+        );
+
+        assert_eq!(
+            file.symbols,
+            [
+                Symbol {
+                    depth: 0,
+                    text: "pub struct Config".into()
+                },
+                Symbol {
+                    depth: 0,
+                    text: "impl Config".into()
+                },
+                Symbol {
+                    depth: 1,
+                    text: "pub fn load".into()
+                },
+            ]
+        );
+        assert_eq!(
+            file.definitions.into_iter().collect::<Vec<_>>(),
+            ["Config", "load", "parse_line"]
+        );
+        assert_eq!(file.references.get("Config"), Some(&3));
+        assert_eq!(file.references.get("load"), Some(&1));
+        assert_eq!(file.references.get("pub"), None);
+    }
+
+    #[test]
+    fn test_rank_files() {
+        let files = vec![
+            file(
+                "app/src/main.rs",
+                &[(0, "fn main", "main")],
+                "fn main() { let config = Config::load(); run_server(config); }",
+            ),
+            file(
+                "app/src/server.rs",
+                &[(0, "pub fn run_server", "run_server")],
+                "pub fn run_server(config: Config) {}",
+            ),
+            file(
+                "app/src/config.rs",
+                &[(0, "pub struct Config", "Config")],
+                "pub struct Config {}",
+            ),
+        ];
+
+        let ranks = rank_files(&files);
+        assert!((ranks.iter().sum::<f64>() - 1.).abs() < 1e-9);
+        // Every file depends on the config, and nothing depends on `main`.
+        assert!(ranks[2] > ranks[1]);
+        assert!(ranks[1] > ranks[0]);
+    }
+
+    #[test]
+    fn test_fit_to_budget() {
+        let files = vec![
+            file("a.rs", &[(0, "fn alpha", "alpha")], ""),
+            file(
+                "b.rs",
+                &[(0, "struct Beta", "Beta"), (1, "fn gamma", "gamma")],
+                "",
+            ),
+            file("c.rs", &[(0, "fn delta", "delta")], ""),
+        ];
+        assert_eq!(
+            render_map(&files[..2]),
+            indoc! {"
+                a.rs:
+                  fn alpha
+                b.rs:
+                  struct Beta
+                    fn gamma
+            "}
+        );
+
+        let count_tokens = |text: String| async move { Ok(text.len()) };
+        let fit = |token_budget| {
+            futures::executor::block_on(fit_to_budget(&files, token_budget, count_tokens)).unwrap()
+        };
+        assert_eq!(fit(0), 0);
+        assert_eq!(fit(render_map(&files[..1]).len()), 1);
+        assert_eq!(fit(render_map(&files[..2]).len() + 1), 2);
+        assert_eq!(fit(render_map(&files).len()), 3);
+    }
+}
@@ -1,13 +1,17 @@
 use gpui::{Action, SharedString};
+use ui::IconName;
 
-use crate::{ActivateRegexMode, ActivateTextMode};
+use crate::{ActivateRegexMode, ActivateSemanticMode, ActivateTextMode};
 
-// TODO: Update the default search mode to get from config
+/// How project search matches the query against the project's files.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub enum SearchMode {
     #[default]
     Text,
     Regex,
+    /// Ranks chunks of the project's files by their meaning and keywords, using the semantic
+    /// index, instead of finding exact matches.
+    Semantic,
 }
 
 impl SearchMode {
@@ -15,22 +19,27 @@ impl SearchMode {
         match self {
             SearchMode::Text => "Text",
             SearchMode::Regex => "Regex",
+            SearchMode::Semantic => "Semantic",
         }
     }
+
+    pub(crate) fn icon(&self) -> IconName {
+        match self {
+            SearchMode::Text => IconName::CaseSensitive,
+            SearchMode::Regex => IconName::Regex,
+            SearchMode::Semantic => IconName::Sparkle,
+        }
+    }
+
     pub(crate) fn tooltip(&self) -> SharedString {
         format!("Activate {} Mode", self.label()).into()
     }
+
     pub(crate) fn action(&self) -> Box<dyn Action> {
         match self {
             SearchMode::Text => ActivateTextMode.boxed_clone(),
             SearchMode::Regex => ActivateRegexMode.boxed_clone(),
+            SearchMode::Semantic => ActivateSemanticMode.boxed_clone(),
         }
     }
 }
-
-pub(crate) fn next_mode(mode: &SearchMode) -> SearchMode {
-    match mode {
-        SearchMode::Text => SearchMode::Regex,
-        SearchMode::Regex => SearchMode::Text,
-    }
-}
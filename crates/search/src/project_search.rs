@@ -1,5 +1,6 @@
 use crate::{
-    buffer_search::Deploy, BufferSearchBar, FocusSearch, NextHistoryQuery, PreviousHistoryQuery,
+    buffer_search::Deploy, mode::SearchMode, ActivateRegexMode, ActivateSemanticMode,
+    ActivateTextMode, BufferSearchBar, FocusSearch, NextHistoryQuery, PreviousHistoryQuery,
     ReplaceAll, ReplaceNext, SearchOptions, SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive,
     ToggleIncludeIgnored, ToggleRegex, ToggleReplace, ToggleWholeWord,
 };
@@ -16,13 +17,14 @@ use gpui::{
     ParentElement, Point, Render, SharedString, Styled, Subscription, Task, TextStyle,
     UpdateGlobal, WeakEntity, Window,
 };
-use language::{Buffer, Language};
+use language::{Bias, Buffer, Language};
 use menu::Confirm;
 use project::{
     search::{SearchInputKind, SearchQuery},
     search_history::SearchHistoryCursor,
     Project, ProjectPath,
 };
+use semantic_index::SemanticDb;
 use settings::Settings;
 use std::{
     any::{Any, TypeId},
@@ -37,7 +39,7 @@ use ui::{
     h_flex, prelude::*, utils::SearchInputWidth, v_flex, Icon, IconButton, IconButtonShape,
     IconName, KeyBinding, Label, LabelCommon, LabelSize, Toggleable, Tooltip,
 };
-use util::paths::PathMatcher;
+use util::{paths::PathMatcher, ResultExt as _};
 use workspace::{
     item::{BreadcrumbText, Item, ItemEvent, ItemHandle},
     searchable::{Direction, SearchableItem, SearchableItemHandle},
@@ -50,6 +52,9 @@ actions!(
     [SearchInNew, ToggleFocus, NextField, ToggleFilters]
 );
 
+/// The number of ranked excerpts that a semantic search shows.
+const SEMANTIC_SEARCH_LIMIT: usize = 64;
+
 #[derive(Default)]
 struct ActiveSettings(HashMap<WeakEntity<Project>, ProjectSearchSettings>);
 
@@ -82,6 +87,21 @@ pub fn init(cx: &mut App) {
         register_workspace_action(workspace, move |search_bar, _: &ToggleRegex, _, cx| {
             search_bar.toggle_search_option(SearchOptions::REGEX, cx);
         });
+        register_workspace_action(workspace, move |search_bar, _: &ActivateTextMode, _, cx| {
+            search_bar.activate_search_mode(SearchMode::Text, cx);
+        });
+        register_workspace_action(
+            workspace,
+            move |search_bar, _: &ActivateRegexMode, _, cx| {
+                search_bar.activate_search_mode(SearchMode::Regex, cx);
+            },
+        );
+        register_workspace_action(
+            workspace,
+            move |search_bar, _: &ActivateSemanticMode, _, cx| {
+                search_bar.activate_search_mode(SearchMode::Semantic, cx);
+            },
+        );
         register_workspace_action(
             workspace,
             move |search_bar, action: &ToggleReplace, window, cx| {
@@ -141,6 +161,7 @@ pub struct ProjectSearch {
     search_id: usize,
     no_results: Option<bool>,
     limit_reached: bool,
+    semantic_search_error: Option<SharedString>,
     search_history_cursor: SearchHistoryCursor,
     search_included_history_cursor: SearchHistoryCursor,
     search_excluded_history_cursor: SearchHistoryCursor,
@@ -161,6 +182,7 @@ pub struct ProjectSearchView {
     replacement_editor: Entity<Editor>,
     results_editor: Entity<Editor>,
     search_options: SearchOptions,
+    semantic_search: bool,
     panels_with_errors: HashSet<InputPanel>,
     active_match_index: Option<usize>,
     search_id: usize,
@@ -176,6 +198,7 @@ pub struct ProjectSearchView {
 #[derive(Debug, Clone)]
 pub struct ProjectSearchSettings {
     search_options: SearchOptions,
+    semantic_search: bool,
     filters_enabled: bool,
 }
 
@@ -198,6 +221,7 @@ impl ProjectSearch {
             search_id: 0,
             no_results: None,
             limit_reached: false,
+            semantic_search_error: None,
             search_history_cursor: Default::default(),
             search_included_history_cursor: Default::default(),
             search_excluded_history_cursor: Default::default(),
@@ -217,6 +241,7 @@ impl ProjectSearch {
             search_id: self.search_id,
             no_results: self.no_results,
             limit_reached: self.limit_reached,
+            semantic_search_error: self.semantic_search_error.clone(),
             search_history_cursor: self.search_history_cursor.clone(),
             search_included_history_cursor: self.search_included_history_cursor.clone(),
             search_excluded_history_cursor: self.search_excluded_history_cursor.clone(),
@@ -259,6 +284,7 @@ impl ProjectSearch {
         self.last_search_query_text = Some(query.as_str().to_string());
         self.search_id += 1;
         self.active_query = Some(query);
+        self.semantic_search_error = None;
         self.match_ranges.clear();
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
             let mut matches = pin!(search.ready_chunks(1024));
@@ -319,6 +345,136 @@ impl ProjectSearch {
         }));
         cx.notify();
     }
+
+    /// Searches the semantic index for chunks matching the query by meaning or by keyword,
+    /// showing them as excerpts from the best match down.
+    fn semantic_search(&mut self, query: String, cx: &mut Context<Self>) {
+        self.project.update(cx, |project, _| {
+            project
+                .search_history_mut(SearchInputKind::Query)
+                .add(&mut self.search_history_cursor, query.clone());
+        });
+        self.last_search_query_text = Some(query.clone());
+        self.search_id += 1;
+        self.active_query = None;
+        self.semantic_search_error = None;
+        self.match_ranges.clear();
+
+        let project = self.project.clone();
+        let project_index = if cx.has_global::<SemanticDb>() {
+            cx.update_global(|semantic_db: &mut SemanticDb, cx| {
+                semantic_db.project_index(project, cx)
+            })
+        } else {
+            None
+        };
+        let Some(project_index) = project_index else {
+            self.excerpts.update(cx, |excerpts, cx| excerpts.clear(cx));
+            self.no_results = Some(true);
+            self.limit_reached = false;
+            self.semantic_search_error = Some(
+                "Enable the semantic index in your settings to search by meaning. \
+                It indexes your project with a local embedding model, such as one served by Ollama."
+                    .into(),
+            );
+            self.pending_search = None;
+            cx.notify();
+            return;
+        };
+
+        let search = project_index
+            .read(cx)
+            .hybrid_search(query, SEMANTIC_SEARCH_LIMIT, cx);
+        self.pending_search = Some(cx.spawn(|this, mut cx| async move {
+            let results = search.await;
+            let this = this.upgrade()?;
+            this.update(&mut cx, |this, cx| {
+                this.match_ranges.clear();
+                this.excerpts.update(cx, |this, cx| this.clear(cx));
+                this.no_results = Some(true);
+                this.limit_reached = false;
+            })
+            .ok()?;
+
+            let results = match results {
+                Ok(results) => results,
+                Err(error) => {
+                    this.update(&mut cx, |this, cx| {
+                        this.semantic_search_error =
+                            Some(format!("Semantic search failed: {error:#}").into());
+                        this.pending_search.take();
+                        cx.notify();
+                    })
+                    .ok()?;
+                    return None;
+                }
+            };
+
+            let buffers = this
+                .update(&mut cx, |this, cx| {
+                    this.project.update(cx, |project, cx| {
+                        results
+                            .iter()
+                            .map(|result| {
+                                let project_path = ProjectPath {
+                                    worktree_id: result.worktree.read(cx).id(),
+                                    path: result.path.clone(),
+                                };
+                                project.open_buffer(project_path, cx)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .ok()?;
+
+            // Push the excerpts one at a time, so that they stay in the order of the ranking
+            // rather than being grouped by file.
+            for (result, buffer) in results.into_iter().zip(buffers) {
+                let Some(buffer) = buffer.await.log_err() else {
+                    continue;
+                };
+                let range = buffer
+                    .read_with(&cx, |buffer, _| {
+                        // The file may have changed since it was indexed.
+                        let len = buffer.len();
+                        let start = buffer.clip_offset(result.range.start.min(len), Bias::Left);
+                        let end = buffer.clip_offset(result.range.end.min(len), Bias::Right);
+                        buffer.anchor_after(start)..buffer.anchor_before(end)
+                    })
+                    .ok()?;
+                let match_ranges = this
+                    .update(&mut cx, |this, cx| {
+                        this.excerpts.update(cx, |excerpts, cx| {
+                            excerpts.push_multiple_excerpts_with_context_lines(
+                                vec![(buffer, vec![range])],
+                                editor::DEFAULT_MULTIBUFFER_CONTEXT,
+                                cx,
+                            )
+                        })
+                    })
+                    .ok()?
+                    .await;
+
+                this.update(&mut cx, |this, cx| {
+                    this.match_ranges.extend(match_ranges);
+                    cx.notify();
+                })
+                .ok()?;
+            }
+
+            this.update(&mut cx, |this, cx| {
+                if !this.match_ranges.is_empty() {
+                    this.no_results = Some(false);
+                }
+                this.pending_search.take();
+                cx.notify();
+            })
+            .ok()?;
+
+            None
+        }));
+        cx.notify();
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -356,19 +512,27 @@ impl Render for ProjectSearchView {
                 .justify_center()
                 .child(Label::new(heading_text).size(LabelSize::Large));
 
-            let page_content: Option<AnyElement> = if let Some(no_results) = model.no_results {
-                if model.pending_search.is_none() && no_results {
+            let page_content: Option<AnyElement> =
+                if let Some(error) = model.semantic_search_error.clone() {
                     Some(
-                        Label::new("No results found in this project for the provided query")
+                        Label::new(error)
                             .size(LabelSize::Small)
+                            .color(Color::Error)
                             .into_any_element(),
                     )
+                } else if let Some(no_results) = model.no_results {
+                    if model.pending_search.is_none() && no_results {
+                        Some(
+                            Label::new("No results found in this project for the provided query")
+                                .size(LabelSize::Small)
+                                .into_any_element(),
+                        )
+                    } else {
+                        None
+                    }
                 } else {
-                    None
-                }
-            } else {
-                Some(self.landing_text_minor(window, cx).into_any_element())
-            };
+                    Some(self.landing_text_minor(window, cx).into_any_element())
+                };
 
             let page_content = page_content.map(|text| div().child(text));
 
@@ -604,19 +768,46 @@ impl ProjectSearchView {
     fn current_settings(&self) -> ProjectSearchSettings {
         ProjectSearchSettings {
             search_options: self.search_options,
+            semantic_search: self.semantic_search,
             filters_enabled: self.filters_enabled,
         }
     }
 
+    fn search_mode(&self) -> SearchMode {
+        if self.semantic_search {
+            SearchMode::Semantic
+        } else if self.search_options.contains(SearchOptions::REGEX) {
+            SearchMode::Regex
+        } else {
+            SearchMode::Text
+        }
+    }
+
     fn toggle_search_option(&mut self, option: SearchOptions, cx: &mut Context<Self>) {
         self.search_options.toggle(option);
+        if option.contains(SearchOptions::REGEX) {
+            self.semantic_search = false;
+        }
+        self.save_settings(cx);
+        self.adjust_query_regex_language(cx);
+    }
+
+    fn activate_search_mode(&mut self, mode: SearchMode, cx: &mut Context<Self>) {
+        self.semantic_search = mode == SearchMode::Semantic;
+        self.search_options
+            .set(SearchOptions::REGEX, mode == SearchMode::Regex);
+        self.save_settings(cx);
+        self.adjust_query_regex_language(cx);
+        cx.notify();
+    }
+
+    fn save_settings(&self, cx: &mut Context<Self>) {
         ActiveSettings::update_global(cx, |settings, cx| {
             settings.0.insert(
                 self.entity.read(cx).project.downgrade(),
                 self.current_settings(),
             );
         });
-        self.adjust_query_regex_language(cx);
     }
 
     fn toggle_opened_only(&mut self, _window: &mut Window, _cx: &mut Context<Self>) {
@@ -686,12 +877,16 @@ impl ProjectSearchView {
         let mut subscriptions = Vec::new();
 
         // Read in settings if available
-        let (mut options, filters_enabled) = if let Some(settings) = settings {
-            (settings.search_options, settings.filters_enabled)
+        let (mut options, semantic_search, filters_enabled) = if let Some(settings) = settings {
+            (
+                settings.search_options,
+                settings.semantic_search,
+                settings.filters_enabled,
+            )
         } else {
             let search_options =
                 SearchOptions::from_settings(&EditorSettings::get_global(cx).search);
-            (search_options, false)
+            (search_options, false, false)
         };
 
         {
@@ -821,6 +1016,7 @@ impl ProjectSearchView {
             query_editor,
             results_editor,
             search_options: options,
+            semantic_search,
             panels_with_errors: HashSet::default(),
             active_match_index: None,
             included_files_editor,
@@ -987,6 +1183,14 @@ impl ProjectSearchView {
     }
 
     fn search(&mut self, cx: &mut Context<Self>) {
+        if self.semantic_search {
+            let query = self.query_editor.read(cx).text(cx);
+            if !query.is_empty() {
+                self.entity
+                    .update(cx, |model, cx| model.semantic_search(query, cx));
+            }
+            return;
+        }
         if let Some(query) = self.build_search_query(cx) {
             self.entity.update(cx, |model, cx| model.search(query, cx));
         }
@@ -1511,6 +1715,21 @@ impl ProjectSearchBar {
         }
     }
 
+    fn activate_search_mode(&mut self, mode: SearchMode, cx: &mut Context<Self>) -> bool {
+        if let Some(search_view) = self.active_project_search.as_ref() {
+            search_view.update(cx, |search_view, cx| {
+                search_view.activate_search_mode(mode, cx);
+                if search_view.entity.read(cx).last_search_query_text.is_some() {
+                    search_view.search(cx);
+                }
+            });
+            cx.notify();
+            true
+        } else {
+            false
+        }
+    }
+
     fn toggle_replace(&mut self, _: &ToggleReplace, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(search) = &self.active_project_search {
             search.update(cx, |this, cx| {
@@ -1746,6 +1965,7 @@ impl Render for ProjectSearchBar {
         };
         let search = search.read(cx);
         let focus_handle = search.focus_handle(cx);
+        let search_mode = search.search_mode();
 
         let container_width = window.viewport_size().width;
         let input_width = SearchInputWidth::calc_width(container_width);
@@ -1772,31 +1992,33 @@ impl Render for ProjectSearchBar {
                 cx.listener(|this, action, window, cx| this.next_history_query(action, window, cx)),
             )
             .child(self.render_text_input(&search.query_editor, cx))
-            .child(
-                h_flex()
-                    .gap_1()
-                    .child(SearchOptions::CASE_SENSITIVE.as_button(
-                        self.is_option_enabled(SearchOptions::CASE_SENSITIVE, cx),
-                        focus_handle.clone(),
-                        cx.listener(|this, _, _, cx| {
-                            this.toggle_search_option(SearchOptions::CASE_SENSITIVE, cx);
-                        }),
-                    ))
-                    .child(SearchOptions::WHOLE_WORD.as_button(
-                        self.is_option_enabled(SearchOptions::WHOLE_WORD, cx),
-                        focus_handle.clone(),
-                        cx.listener(|this, _, _, cx| {
-                            this.toggle_search_option(SearchOptions::WHOLE_WORD, cx);
-                        }),
-                    ))
-                    .child(SearchOptions::REGEX.as_button(
-                        self.is_option_enabled(SearchOptions::REGEX, cx),
-                        focus_handle.clone(),
-                        cx.listener(|this, _, _, cx| {
-                            this.toggle_search_option(SearchOptions::REGEX, cx);
-                        }),
-                    )),
-            );
+            .when(search_mode != SearchMode::Semantic, |this| {
+                this.child(
+                    h_flex()
+                        .gap_1()
+                        .child(SearchOptions::CASE_SENSITIVE.as_button(
+                            self.is_option_enabled(SearchOptions::CASE_SENSITIVE, cx),
+                            focus_handle.clone(),
+                            cx.listener(|this, _, _, cx| {
+                                this.toggle_search_option(SearchOptions::CASE_SENSITIVE, cx);
+                            }),
+                        ))
+                        .child(SearchOptions::WHOLE_WORD.as_button(
+                            self.is_option_enabled(SearchOptions::WHOLE_WORD, cx),
+                            focus_handle.clone(),
+                            cx.listener(|this, _, _, cx| {
+                                this.toggle_search_option(SearchOptions::WHOLE_WORD, cx);
+                            }),
+                        ))
+                        .child(SearchOptions::REGEX.as_button(
+                            self.is_option_enabled(SearchOptions::REGEX, cx),
+                            focus_handle.clone(),
+                            cx.listener(|this, _, _, cx| {
+                                this.toggle_search_option(SearchOptions::REGEX, cx);
+                            }),
+                        )),
+                )
+            });
 
         let mode_column = h_flex()
             .gap_1()
@@ -1852,6 +2074,31 @@ impl Render for ProjectSearchBar {
                             )
                         }
                     }),
+            )
+            .child(
+                IconButton::new("project-search-semantic-mode", SearchMode::Semantic.icon())
+                    .shape(IconButtonShape::Square)
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        let mode = if search_mode == SearchMode::Semantic {
+                            SearchMode::Text
+                        } else {
+                            SearchMode::Semantic
+                        };
+                        this.activate_search_mode(mode, cx);
+                    }))
+                    .toggle_state(search_mode == SearchMode::Semantic)
+                    .tooltip({
+                        let focus_handle = focus_handle.clone();
+                        move |window, cx| {
+                            Tooltip::for_action_in(
+                                SearchMode::Semantic.tooltip(),
+                                SearchMode::Semantic.action().as_ref(),
+                                &focus_handle,
+                                window,
+                                cx,
+                            )
+                        }
+                    }),
             );
 
         let limit_reached = search.entity.read(cx).limit_reached;
@@ -10,6 +10,7 @@ use workspace::notifications::NotificationId;
 use workspace::{Toast, Workspace};
 
 pub mod buffer_search;
+pub mod mode;
 pub mod project_search;
 pub(crate) mod search_bar;
 
@@ -36,6 +37,9 @@ actions!(
         PreviousHistoryQuery,
         ReplaceAll,
         ReplaceNext,
+        ActivateTextMode,
+        ActivateRegexMode,
+        ActivateSemanticMode,
     ]
 );
 
@@ -0,0 +1,753 @@
+//! An approximate nearest-neighbour index over a worktree's embeddings, so that searches
+//! don't need to score every chunk once a worktree has many of them.
+//!
+//! This is an inverted file (IVF) index: the chunks' embeddings are clustered around
+//! centroids with k-means, and every chunk is posted under its nearest centroid. A search
+//! only scores the chunks posted under the centroids nearest to its queries. Worktrees with
+//! too few chunks for this to pay off have no centroids, and their searches score every chunk.
+
+use crate::embedding::Embedding;
+use crate::embedding_index::EmbeddedFile;
+use anyhow::{Context as _, Result};
+use collections::{BTreeMap, BTreeSet};
+use heed::types::{SerdeBincode, Str};
+use heed::{RoTxn, RwTxn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, slice, sync::Arc};
+
+/// The number of chunks at which a worktree's centroids are trained.
+const MIN_CHUNKS_TO_TRAIN: usize = 10_000;
+const MIN_CENTROIDS: usize = 16;
+const MAX_CENTROIDS: usize = 256;
+const SAMPLES_PER_CENTROID: usize = 32;
+const TRAINING_ITERATIONS: usize = 8;
+const CENTROIDS_KEY: &str = "centroids";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Centroids {
+    /// The number of chunks in the worktree when the centroids were trained.
+    trained_chunk_count: usize,
+    embeddings: Vec<Embedding>,
+}
+
+#[derive(Clone)]
+pub struct AnnIndex {
+    db_connection: heed::Env,
+    embeddings: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+    /// The indices of a file's chunks posted under a centroid, keyed by the centroid's index
+    /// followed by the file's key in the embedding database.
+    postings: heed::Database<Str, SerdeBincode<Vec<u32>>>,
+    centroids: heed::Database<Str, SerdeBincode<Centroids>>,
+    state: Arc<Mutex<AnnState>>,
+}
+
+#[derive(Default)]
+struct AnnState {
+    centroids: Option<Arc<Centroids>>,
+    /// The number of chunks in the embedding database, once they have been counted.
+    chunk_count: Option<usize>,
+}
+
+/// The changes that a write transaction makes to an [`AnnIndex`]'s in-memory state, which are
+/// applied once it has been committed.
+#[derive(Debug, Default)]
+pub struct AnnChanges {
+    added_chunks: usize,
+    removed_chunks: usize,
+    discard_centroids: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Maintenance {
+    None,
+    Train,
+    Discard,
+}
+
+impl AnnIndex {
+    pub fn load(
+        db_connection: heed::Env,
+        txn: &RoTxn<'_>,
+        embeddings: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+        postings: heed::Database<Str, SerdeBincode<Vec<u32>>>,
+        centroids: heed::Database<Str, SerdeBincode<Centroids>>,
+    ) -> Result<Self> {
+        let state = AnnState {
+            centroids: centroids
+                .get(txn, CENTROIDS_KEY)
+                .context("failed to read centroids")?
+                .map(Arc::new),
+            chunk_count: None,
+        };
+        Ok(Self {
+            db_connection,
+            embeddings,
+            postings,
+            centroids,
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// Posts the chunks of a file that is being written to the embedding database. The
+    /// in-memory state only changes once the transaction is committed with [`Self::commit`].
+    pub fn insert_file(
+        &self,
+        txn: &mut RwTxn<'_>,
+        key: &str,
+        file: &EmbeddedFile,
+        changes: &mut AnnChanges,
+    ) -> Result<()> {
+        changes.added_chunks += file.chunks.len();
+        if changes.discard_centroids {
+            return Ok(());
+        }
+        let Some(centroids) = self.state.lock().centroids.clone() else {
+            return Ok(());
+        };
+        if file
+            .chunks
+            .iter()
+            .any(|chunk| chunk.embedding.as_slice().len() != centroids.dimensions())
+        {
+            // The embedding provider changed, so the centroids are discarded until they
+            // can be trained on the new embeddings.
+            log::info!("discarding centroids trained on embeddings of other dimensions");
+            self.postings.clear(txn)?;
+            self.centroids.delete(txn, CENTROIDS_KEY)?;
+            changes.discard_centroids = true;
+            return Ok(());
+        }
+
+        for (centroid, chunk_indices) in centroids.assign(file) {
+            self.postings
+                .put(txn, &posting_key(centroid, key), &chunk_indices)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the postings of a file that is being deleted from the embedding database. The
+    /// in-memory state only changes once the transaction is committed with [`Self::commit`].
+    pub fn remove_file(
+        &self,
+        txn: &mut RwTxn<'_>,
+        key: &str,
+        file: &EmbeddedFile,
+        changes: &mut AnnChanges,
+    ) -> Result<()> {
+        changes.removed_chunks += file.chunks.len();
+        if changes.discard_centroids {
+            return Ok(());
+        }
+        if let Some(centroids) = self.state.lock().centroids.clone() {
+            for centroid in centroids.assign(file).into_keys() {
+                self.postings.delete(txn, &posting_key(centroid, key))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Commits a transaction that inserted or removed files, and then applies its changes to
+    /// the in-memory state, so that a failed commit leaves the state as it was.
+    pub fn commit(&self, txn: RwTxn<'_>, changes: AnnChanges) -> Result<()> {
+        // Hold the state's lock until it matches the committed transaction, like `train`.
+        let mut state = self.state.lock();
+        txn.commit()?;
+        if let Some(chunk_count) = state.chunk_count.as_mut() {
+            *chunk_count =
+                (*chunk_count + changes.added_chunks).saturating_sub(changes.removed_chunks);
+        }
+        if changes.discard_centroids {
+            state.centroids = None;
+        }
+        Ok(())
+    }
+
+    /// Returns the indices of the chunks worth scoring against the queries, keyed by their
+    /// file's key in the embedding database, or `None` when every chunk needs to be scored.
+    pub fn candidates(
+        &self,
+        txn: &RoTxn<'_>,
+        queries: &[Embedding],
+    ) -> Result<Option<BTreeMap<String, BTreeSet<u32>>>> {
+        let Some(centroids) = self.state.lock().centroids.clone() else {
+            return Ok(None);
+        };
+        if queries
+            .iter()
+            .any(|query| query.as_slice().len() != centroids.dimensions())
+        {
+            return Ok(None);
+        }
+
+        let mut probed_centroids = BTreeSet::new();
+        for query in queries {
+            probed_centroids.extend(centroids.nearest(query, probe_count(centroids.len())));
+        }
+
+        let mut candidates = BTreeMap::<String, BTreeSet<u32>>::new();
+        for centroid in probed_centroids {
+            let prefix = posting_prefix(centroid);
+            for entry in self.postings.prefix_iter(txn, &prefix)? {
+                let (key, chunk_indices) = entry?;
+                candidates
+                    .entry(key[prefix.len()..].to_string())
+                    .or_default()
+                    .extend(chunk_indices);
+            }
+        }
+        Ok(Some(candidates))
+    }
+
+    /// Trains the centroids once the worktree has enough chunks, retrains them when the
+    /// number of chunks has changed too much since, and discards them when it has shrunk.
+    pub fn maintain(&self) -> Result<()> {
+        let chunk_count = self.state.lock().chunk_count;
+        let chunk_count = match chunk_count {
+            Some(chunk_count) => chunk_count,
+            None => {
+                let chunk_count = self.count_chunks()?;
+                self.state.lock().chunk_count = Some(chunk_count);
+                chunk_count
+            }
+        };
+
+        let trained_chunk_count = self
+            .state
+            .lock()
+            .centroids
+            .as_ref()
+            .map(|centroids| centroids.trained_chunk_count);
+        match maintenance(chunk_count, trained_chunk_count) {
+            Maintenance::None => Ok(()),
+            Maintenance::Train => self.train(chunk_count),
+            Maintenance::Discard => {
+                log::info!("discarding centroids of an index with {chunk_count} chunks");
+                let mut txn = self.db_connection.write_txn()?;
+                self.postings.clear(&mut txn)?;
+                self.centroids.delete(&mut txn, CENTROIDS_KEY)?;
+                let mut state = self.state.lock();
+                txn.commit()?;
+                state.centroids = None;
+                Ok(())
+            }
+        }
+    }
+
+    fn count_chunks(&self) -> Result<usize> {
+        let txn = self
+            .db_connection
+            .read_txn()
+            .context("failed to create read transaction")?;
+        let mut chunk_count = 0;
+        for entry in self.embeddings.iter(&txn)? {
+            chunk_count += entry?.1.chunks.len();
+        }
+        Ok(chunk_count)
+    }
+
+    fn train(&self, chunk_count: usize) -> Result<()> {
+        let centroid_count = centroid_count(chunk_count);
+        log::info!("training {centroid_count} centroids for an index with {chunk_count} chunks");
+
+        let samples = {
+            let txn = self
+                .db_connection
+                .read_txn()
+                .context("failed to create read transaction")?;
+            let sample_count = centroid_count * SAMPLES_PER_CENTROID;
+            let stride = (chunk_count / sample_count).max(1);
+            let mut samples = Vec::with_capacity(sample_count);
+            let mut chunk_ix = 0;
+            'files: for entry in self.embeddings.iter(&txn)? {
+                for chunk in entry?.1.chunks {
+                    if chunk_ix % stride == 0 {
+                        samples.push(chunk.embedding);
+                        if samples.len() == sample_count {
+                            break 'files;
+                        }
+                    }
+                    chunk_ix += 1;
+                }
+            }
+            samples
+        };
+        let Some(mut centroids) = Centroids::train(&samples, centroid_count) else {
+            return Ok(());
+        };
+
+        // Assign every chunk in the same transaction that replaces the postings, so that no
+        // file can be written in between with postings for the previous centroids.
+        let mut txn = self.db_connection.write_txn()?;
+        let mut postings = Vec::new();
+        let mut chunk_count = 0;
+        for entry in self.embeddings.iter(&txn)? {
+            let (key, file) = entry?;
+            chunk_count += file.chunks.len();
+            for (centroid, chunk_indices) in centroids.assign(&file) {
+                postings.push((posting_key(centroid, key), chunk_indices));
+            }
+        }
+        self.postings.clear(&mut txn)?;
+        for (key, chunk_indices) in postings {
+            self.postings.put(&mut txn, &key, &chunk_indices)?;
+        }
+        centroids.trained_chunk_count = chunk_count;
+        self.centroids.put(&mut txn, CENTROIDS_KEY, &centroids)?;
+
+        // Writers read the centroids once they have a write transaction, so hold the state's
+        // lock until it matches the committed transaction.
+        let mut state = self.state.lock();
+        txn.commit()?;
+        state.centroids = Some(Arc::new(centroids));
+        state.chunk_count = Some(chunk_count);
+        Ok(())
+    }
+}
+
+impl Centroids {
+    /// Clusters the samples with spherical k-means, starting from evenly spaced samples.
+    fn train(samples: &[Embedding], centroid_count: usize) -> Option<Self> {
+        let stride = (samples.len() / centroid_count.max(1)).max(1);
+        let mut embeddings = samples
+            .iter()
+            .step_by(stride)
+            .take(centroid_count)
+            .cloned()
+            .collect::<Vec<_>>();
+        let dimensions = embeddings.first()?.as_slice().len();
+        if samples
+            .iter()
+            .any(|sample| sample.as_slice().len() != dimensions)
+        {
+            return None;
+        }
+
+        for _ in 0..TRAINING_ITERATIONS {
+            let mut sums = vec![vec![0f32; dimensions]; embeddings.len()];
+            let mut counts = vec![0usize; embeddings.len()];
+            for sample in samples {
+                let (_, nearest) = sample.similarity(&embeddings);
+                counts[nearest] += 1;
+                for (sum, value) in sums[nearest].iter_mut().zip(sample.as_slice()) {
+                    *sum += value;
+                }
+            }
+
+            for ((centroid, sum), count) in embeddings.iter_mut().zip(sums).zip(counts) {
+                // Keep centroids that no sample is nearest to where they are.
+                if count > 0 && sum.iter().any(|value| *value != 0.) {
+                    *centroid = Embedding::new(sum);
+                }
+            }
+        }
+
+        Some(Self {
+            trained_chunk_count: samples.len(),
+            embeddings,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.embeddings.len()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.embeddings
+            .first()
+            .map_or(0, |centroid| centroid.as_slice().len())
+    }
+
+    /// Groups the indices of the file's chunks by their nearest centroid.
+    fn assign(&self, file: &EmbeddedFile) -> BTreeMap<usize, Vec<u32>> {
+        let mut assignments = BTreeMap::<usize, Vec<u32>>::new();
+        for (chunk_ix, chunk) in file.chunks.iter().enumerate() {
+            if chunk.embedding.as_slice().len() == self.dimensions() {
+                let (_, nearest) = chunk.embedding.similarity(&self.embeddings);
+                assignments
+                    .entry(nearest)
+                    .or_default()
+                    .push(chunk_ix as u32);
+            }
+        }
+        assignments
+    }
+
+    /// Returns the indices of the `count` centroids nearest to the query.
+    fn nearest(&self, query: &Embedding, count: usize) -> impl Iterator<Item = usize> {
+        let mut scores = self
+            .embeddings
+            .iter()
+            .enumerate()
+            .map(|(ix, centroid)| (query.similarity(slice::from_ref(centroid)).0, ix))
+            .collect::<Vec<_>>();
+        scores.sort_unstable_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        scores.into_iter().take(count).map(|(_, ix)| ix)
+    }
+}
+
+fn maintenance(chunk_count: usize, trained_chunk_count: Option<usize>) -> Maintenance {
+    match trained_chunk_count {
+        None if chunk_count >= MIN_CHUNKS_TO_TRAIN => Maintenance::Train,
+        None => Maintenance::None,
+        // Leave some slack below the threshold, so that hovering around it doesn't keep
+        // discarding and retraining the centroids.
+        Some(_) if chunk_count < MIN_CHUNKS_TO_TRAIN / 2 => Maintenance::Discard,
+        Some(trained) if chunk_count > trained * 2 || chunk_count < trained / 2 => {
+            Maintenance::Train
+        }
+        Some(_) => Maintenance::None,
+    }
+}
+
+fn centroid_count(chunk_count: usize) -> usize {
+    ((chunk_count as f64).sqrt() as usize).clamp(MIN_CENTROIDS, MAX_CENTROIDS)
+}
+
+/// The number of centroids whose chunks are scored for each query.
+fn probe_count(centroid_count: usize) -> usize {
+    (centroid_count / 8).clamp(4, 32)
+}
+
+fn posting_prefix(centroid: usize) -> String {
+    format!("{centroid:08x}\0")
+}
+
+fn posting_key(centroid: usize, file_key: &str) -> String {
+    format!("{centroid:08x}\0{file_key}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::Chunk;
+    use crate::embedding_index::EmbeddedChunk;
+    use rand::prelude::*;
+    use std::{path::Path, time::Instant};
+
+    const CHUNKS_PER_FILE: usize = 10;
+
+    struct TestIndex {
+        _temp_dir: tempfile::TempDir,
+        db_connection: heed::Env,
+        embeddings: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+        ann_index: AnnIndex,
+    }
+
+    impl TestIndex {
+        fn new() -> Self {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let db_connection = unsafe {
+                heed::EnvOpenOptions::new()
+                    .map_size(1024 * 1024 * 1024)
+                    .max_dbs(3)
+                    .open(temp_dir.path())
+                    .unwrap()
+            };
+            let mut txn = db_connection.write_txn().unwrap();
+            let embeddings = db_connection
+                .create_database(&mut txn, Some("embeddings"))
+                .unwrap();
+            let postings = db_connection
+                .create_database(&mut txn, Some("postings"))
+                .unwrap();
+            let centroids = db_connection
+                .create_database(&mut txn, Some("centroids"))
+                .unwrap();
+            let ann_index =
+                AnnIndex::load(db_connection.clone(), &txn, embeddings, postings, centroids)
+                    .unwrap();
+            txn.commit().unwrap();
+            Self {
+                _temp_dir: temp_dir,
+                db_connection,
+                embeddings,
+                ann_index,
+            }
+        }
+
+        /// Writes a file the way `EmbeddingIndex::persist_embeddings` does.
+        fn put(&self, file: &EmbeddedFile) {
+            let mut txn = self.db_connection.write_txn().unwrap();
+            let mut changes = AnnChanges::default();
+            let key = file.path.to_string_lossy().to_string();
+            if let Some(previous) = self.embeddings.get(&txn, &key).unwrap() {
+                self.ann_index
+                    .remove_file(&mut txn, &key, &previous, &mut changes)
+                    .unwrap();
+            }
+            self.ann_index
+                .insert_file(&mut txn, &key, file, &mut changes)
+                .unwrap();
+            self.embeddings.put(&mut txn, &key, file).unwrap();
+            self.ann_index.commit(txn, changes).unwrap();
+        }
+
+        fn delete(&self, path: &str) {
+            let mut txn = self.db_connection.write_txn().unwrap();
+            let mut changes = AnnChanges::default();
+            let file = self.embeddings.get(&txn, path).unwrap().unwrap();
+            self.ann_index
+                .remove_file(&mut txn, path, &file, &mut changes)
+                .unwrap();
+            self.embeddings.delete(&mut txn, path).unwrap();
+            self.ann_index.commit(txn, changes).unwrap();
+        }
+
+        /// Returns the paths and indices of the chunks most similar to the query, using the
+        /// approximate index when it has been trained, or scoring every chunk otherwise.
+        fn search(&self, query: &Embedding, limit: usize, approximate: bool) -> Vec<(String, u32)> {
+            let txn = self.db_connection.read_txn().unwrap();
+            let candidates = if approximate {
+                self.ann_index
+                    .candidates(&txn, slice::from_ref(query))
+                    .unwrap()
+            } else {
+                None
+            };
+
+            let files = match candidates {
+                Some(candidates) => candidates
+                    .into_iter()
+                    .map(|(key, chunk_indices)| {
+                        let file = self.embeddings.get(&txn, &key).unwrap().unwrap();
+                        (key, file, Some(chunk_indices))
+                    })
+                    .collect::<Vec<_>>(),
+                None => self
+                    .embeddings
+                    .iter(&txn)
+                    .unwrap()
+                    .map(|entry| {
+                        let (key, file) = entry.unwrap();
+                        (key.to_string(), file, None)
+                    })
+                    .collect(),
+            };
+
+            let mut results = Vec::new();
+            for (key, file, chunk_indices) in files {
+                for (chunk_ix, chunk) in file.chunks.iter().enumerate() {
+                    let chunk_ix = chunk_ix as u32;
+                    if chunk_indices
+                        .as_ref()
+                        .map_or(true, |chunk_indices| chunk_indices.contains(&chunk_ix))
+                    {
+                        let (score, _) = chunk.embedding.similarity(slice::from_ref(query));
+                        results.push((score, key.clone(), chunk_ix));
+                    }
+                }
+            }
+            results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            results
+                .into_iter()
+                .take(limit)
+                .map(|(_, path, chunk_ix)| (path, chunk_ix))
+                .collect()
+        }
+    }
+
+    fn random_embedding(center: &[f32], spread: f32, rng: &mut StdRng) -> Embedding {
+        Embedding::new(
+            center
+                .iter()
+                .map(|value| value + rng.gen_range(-spread..spread))
+                .collect(),
+        )
+    }
+
+    fn random_files(
+        file_count: usize,
+        cluster_count: usize,
+        dimensions: usize,
+        rng: &mut StdRng,
+    ) -> Vec<EmbeddedFile> {
+        let clusters = (0..cluster_count)
+            .map(|_| {
+                (0..dimensions)
+                    .map(|_| rng.gen_range(-1.0..1.0))
+                    .collect::<Vec<f32>>()
+            })
+            .collect::<Vec<_>>();
+        (0..file_count)
+            .map(|file_ix| EmbeddedFile {
+                path: Path::new(&format!("file_{file_ix:06}")).into(),
+                mtime: None,
+                chunks: (0..CHUNKS_PER_FILE)
+                    .map(|chunk_ix| EmbeddedChunk {
+                        chunk: Chunk {
+                            range: chunk_ix * 100..(chunk_ix + 1) * 100,
+                            digest: [0; 32],
+                        },
+                        embedding: random_embedding(
+                            &clusters[rng.gen_range(0..cluster_count)],
+                            0.4,
+                            rng,
+                        ),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the fraction of the exact results that the approximate search found, and the
+    /// time each search took.
+    fn measure_recall(
+        index: &TestIndex,
+        queries: &[Embedding],
+        limit: usize,
+    ) -> (f64, std::time::Duration, std::time::Duration) {
+        let mut found = 0;
+        let mut exact_elapsed = std::time::Duration::ZERO;
+        let mut approximate_elapsed = std::time::Duration::ZERO;
+        for query in queries {
+            let start = Instant::now();
+            let exact = index.search(query, limit, false);
+            exact_elapsed += start.elapsed();
+
+            let start = Instant::now();
+            let approximate = index.search(query, limit, true);
+            approximate_elapsed += start.elapsed();
+
+            found += exact
+                .iter()
+                .filter(|result| approximate.contains(result))
+                .count();
+        }
+        let recall = found as f64 / (queries.len() * limit) as f64;
+        (recall, exact_elapsed, approximate_elapsed)
+    }
+
+    #[test]
+    fn test_maintenance() {
+        assert_eq!(maintenance(100, None), Maintenance::None);
+        assert_eq!(maintenance(MIN_CHUNKS_TO_TRAIN, None), Maintenance::Train);
+        assert_eq!(maintenance(15_000, Some(10_000)), Maintenance::None);
+        assert_eq!(maintenance(25_000, Some(10_000)), Maintenance::Train);
+        assert_eq!(maintenance(8_000, Some(20_000)), Maintenance::Train);
+        assert_eq!(maintenance(7_000, Some(10_000)), Maintenance::None);
+        assert_eq!(maintenance(4_000, Some(10_000)), Maintenance::Discard);
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let index = TestIndex::new();
+        let files = random_files(MIN_CHUNKS_TO_TRAIN / CHUNKS_PER_FILE, 64, 32, &mut rng);
+        for file in &files {
+            index.put(file);
+        }
+
+        // Without centroids, every chunk is scored.
+        let query = random_embedding(files[0].chunks[0].embedding.as_slice(), 0.1, &mut rng);
+        assert_eq!(
+            index.search(&query, 10, true),
+            index.search(&query, 10, false)
+        );
+
+        index.ann_index.maintain().unwrap();
+        let centroids = index.ann_index.state.lock().centroids.clone().unwrap();
+        assert_eq!(centroids.trained_chunk_count, MIN_CHUNKS_TO_TRAIN);
+        assert_eq!(centroids.len(), centroid_count(MIN_CHUNKS_TO_TRAIN));
+
+        let queries = (0..50)
+            .map(|_| {
+                let file = &files[rng.gen_range(0..files.len())];
+                let chunk = &file.chunks[rng.gen_range(0..CHUNKS_PER_FILE)];
+                random_embedding(chunk.embedding.as_slice(), 0.1, &mut rng)
+            })
+            .collect::<Vec<_>>();
+        let (recall, _, _) = measure_recall(&index, &queries, 10);
+        assert!(recall >= 0.9, "recall was {recall}");
+
+        // Files written after training are posted under the existing centroids.
+        let mut new_file = random_files(1, 1, 32, &mut rng).remove(0);
+        new_file.path = Path::new("new_file").into();
+        index.put(&new_file);
+        let query = new_file.chunks[3].embedding.clone();
+        assert_eq!(
+            index.search(&query, 1, true),
+            vec![("new_file".to_string(), 3)]
+        );
+
+        // Deleted files lose their postings.
+        index.delete("new_file");
+        let txn = index.db_connection.read_txn().unwrap();
+        let candidates = index
+            .ann_index
+            .candidates(&txn, slice::from_ref(&query))
+            .unwrap()
+            .unwrap();
+        assert!(!candidates.contains_key("new_file"));
+        assert!(!candidates.is_empty());
+    }
+
+    #[test]
+    fn test_aborted_writes_leave_state_unchanged() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let index = TestIndex::new();
+        let files = random_files(MIN_CHUNKS_TO_TRAIN / CHUNKS_PER_FILE, 64, 32, &mut rng);
+        for file in &files {
+            index.put(file);
+        }
+        index.ann_index.maintain().unwrap();
+        assert_eq!(
+            index.ann_index.state.lock().chunk_count,
+            Some(MIN_CHUNKS_TO_TRAIN)
+        );
+
+        // A file with embeddings of other dimensions discards the centroids, but only once
+        // its transaction is committed.
+        let mut other_file = random_files(1, 1, 16, &mut rng).remove(0);
+        other_file.path = Path::new("other_file").into();
+        let mut txn = index.db_connection.write_txn().unwrap();
+        let mut changes = AnnChanges::default();
+        index
+            .ann_index
+            .insert_file(&mut txn, "other_file", &other_file, &mut changes)
+            .unwrap();
+        drop(txn);
+        let state = index.ann_index.state.lock();
+        assert_eq!(state.chunk_count, Some(MIN_CHUNKS_TO_TRAIN));
+        assert!(state.centroids.is_some());
+        drop(state);
+
+        index.put(&other_file);
+        let state = index.ann_index.state.lock();
+        assert_eq!(
+            state.chunk_count,
+            Some(MIN_CHUNKS_TO_TRAIN + CHUNKS_PER_FILE)
+        );
+        assert!(state.centroids.is_none());
+    }
+
+    /// Compares the latency and recall of the approximate index to scoring every chunk.
+    /// Run with `cargo test -p semantic_index --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_search() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let index = TestIndex::new();
+        let files = random_files(20_000, 500, 256, &mut rng);
+        for file in &files {
+            index.put(file);
+        }
+        index.ann_index.maintain().unwrap();
+
+        let queries = (0..100)
+            .map(|_| {
+                let file = &files[rng.gen_range(0..files.len())];
+                let chunk = &file.chunks[rng.gen_range(0..CHUNKS_PER_FILE)];
+                random_embedding(chunk.embedding.as_slice(), 0.1, &mut rng)
+            })
+            .collect::<Vec<_>>();
+        let (recall, exact_elapsed, approximate_elapsed) = measure_recall(&index, &queries, 10);
+        println!(
+            "{} chunks, {} queries: recall {recall:.3}, brute force {:?} per query, approximate {:?} per query",
+            files.len() * CHUNKS_PER_FILE,
+            queries.len(),
+            exact_elapsed / queries.len() as u32,
+            approximate_elapsed / queries.len() as u32,
+        );
+    }
+}
@@ -44,6 +44,10 @@ impl Embedding {
         self.0.len()
     }
 
+    pub(crate) fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+
     pub fn similarity(&self, others: &[Embedding]) -> (f32, usize) {
         debug_assert!(others.iter().all(|other| self.0.len() == other.0.len()));
         others
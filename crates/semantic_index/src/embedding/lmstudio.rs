@@ -26,6 +26,21 @@ struct LmStudioEmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+impl LmStudioEmbeddingModel {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "nomic-embed-text" => Some(Self::NomicEmbedText),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::NomicEmbedText => "nomic-embed-text",
+        }
+    }
+}
+
 impl LmStudioEmbeddingProvider {
     pub fn new(client: Arc<dyn HttpClient>, model: LmStudioEmbeddingModel) -> Self {
         Self { client, model }
@@ -34,9 +49,7 @@ impl LmStudioEmbeddingProvider {
 
 impl EmbeddingProvider for LmStudioEmbeddingProvider {
     fn embed<'a>(&'a self, texts: &'a [TextToEmbed<'a>]) -> BoxFuture<'a, Result<Vec<Embedding>>> {
-        let model = match self.model {
-            LmStudioEmbeddingModel::NomicEmbedText => "nomic-embed-text",
-        };
+        let model = self.model.id();
 
         futures::future::try_join_all(texts.iter().map(|to_embed| {
             let request = LmStudioEmbeddingRequest {
@@ -27,6 +27,23 @@ struct OllamaEmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+impl OllamaEmbeddingModel {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "nomic-embed-text" => Some(Self::NomicEmbedText),
+            "mxbai-embed-large" => Some(Self::MxbaiEmbedLarge),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::NomicEmbedText => "nomic-embed-text",
+            Self::MxbaiEmbedLarge => "mxbai-embed-large",
+        }
+    }
+}
+
 impl OllamaEmbeddingProvider {
     pub fn new(client: Arc<dyn HttpClient>, model: OllamaEmbeddingModel) -> Self {
         Self { client, model }
@@ -35,11 +52,7 @@ impl OllamaEmbeddingProvider {
 
 impl EmbeddingProvider for OllamaEmbeddingProvider {
     fn embed<'a>(&'a self, texts: &'a [TextToEmbed<'a>]) -> BoxFuture<'a, Result<Vec<Embedding>>> {
-        //
-        let model = match self.model {
-            OllamaEmbeddingModel::NomicEmbedText => "nomic-embed-text",
-            OllamaEmbeddingModel::MxbaiEmbedLarge => "mxbai-embed-large",
-        };
+        let model = self.model.id();
 
         futures::future::try_join_all(texts.iter().map(|to_embed| {
             let request = OllamaEmbeddingRequest {
@@ -1,7 +1,10 @@
 use crate::{
+    ann_index::{AnnChanges, AnnIndex},
     chunking::{self, Chunk},
     embedding::{Embedding, EmbeddingProvider, TextToEmbed},
     indexing::{IndexingEntryHandle, IndexingEntrySet},
+    keyword_index::KeywordFile,
+    semantic_index_settings::SemanticIndexSettings,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::Bound;
@@ -11,11 +14,12 @@ use fs::MTime;
 use futures::{stream::StreamExt, FutureExt as _};
 use futures_batch::ChunksTimeoutStreamExt;
 use gpui::{App, AppContext as _, Entity, Task};
-use heed::types::{SerdeBincode, Str};
+use heed::types::{DecodeIgnore, SerdeBincode, Str};
 use language::LanguageRegistry;
 use log;
 use project::{Entry, UpdatedEntriesSet, Worktree};
 use serde::{Deserialize, Serialize};
+use settings::Settings as _;
 use smol::channel;
 use std::{cmp::Ordering, future::Future, iter, path::Path, pin::pin, sync::Arc, time::Duration};
 use util::ResultExt;
@@ -25,6 +29,8 @@ pub struct EmbeddingIndex {
     worktree: Entity<Worktree>,
     db_connection: heed::Env,
     db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+    ann_index: AnnIndex,
+    keyword_db: heed::Database<Str, SerdeBincode<KeywordFile>>,
     fs: Arc<dyn Fs>,
     language_registry: Arc<LanguageRegistry>,
     embedding_provider: Arc<dyn EmbeddingProvider>,
@@ -32,11 +38,14 @@ pub struct EmbeddingIndex {
 }
 
 impl EmbeddingIndex {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         worktree: Entity<Worktree>,
         fs: Arc<dyn Fs>,
         db_connection: heed::Env,
         embedding_db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+        ann_index: AnnIndex,
+        keyword_db: heed::Database<Str, SerdeBincode<KeywordFile>>,
         language_registry: Arc<LanguageRegistry>,
         embedding_provider: Arc<dyn EmbeddingProvider>,
         entry_ids_being_indexed: Arc<IndexingEntrySet>,
@@ -46,6 +55,8 @@ impl EmbeddingIndex {
             fs,
             db_connection,
             db: embedding_db,
+            ann_index,
+            keyword_db,
             language_registry,
             embedding_provider,
             entry_ids_being_indexed,
@@ -56,20 +67,32 @@ impl EmbeddingIndex {
         &self.db
     }
 
+    pub fn ann_index(&self) -> &AnnIndex {
+        &self.ann_index
+    }
+
+    pub fn keyword_db(&self) -> &heed::Database<Str, SerdeBincode<KeywordFile>> {
+        &self.keyword_db
+    }
+
     pub fn index_entries_changed_on_disk(&self, cx: &App) -> impl Future<Output = Result<()>> {
-        if !cx.is_staff() {
+        if !cx.is_staff() && !SemanticIndexSettings::get_global(cx).enabled {
             return async move { Ok(()) }.boxed();
         }
 
         let worktree = self.worktree.read(cx).snapshot();
         let worktree_abs_path = worktree.abs_path().clone();
         let scan = self.scan_entries(worktree, cx);
+        let index_keywords =
+            self.index_keywords(worktree_abs_path.clone(), scan.keywordless_files, cx);
         let chunk = self.chunk_files(worktree_abs_path, scan.updated_entries, cx);
         let embed = Self::embed_files(self.embedding_provider.clone(), chunk.files, cx);
         let persist = self.persist_embeddings(scan.deleted_entry_ranges, embed.files, cx);
+        let ann_index = self.ann_index.clone();
+        let executor = cx.background_executor().clone();
         async move {
-            futures::try_join!(scan.task, chunk.task, embed.task, persist)?;
-            Ok(())
+            futures::try_join!(scan.task, index_keywords, chunk.task, embed.task, persist)?;
+            executor.spawn(async move { ann_index.maintain() }).await
         }
         .boxed()
     }
@@ -79,7 +102,7 @@ impl EmbeddingIndex {
         updated_entries: UpdatedEntriesSet,
         cx: &App,
     ) -> impl Future<Output = Result<()>> {
-        if !cx.is_staff() {
+        if !cx.is_staff() && !SemanticIndexSettings::get_global(cx).enabled {
             return async move { Ok(()) }.boxed();
         }
 
@@ -89,9 +112,11 @@ impl EmbeddingIndex {
         let chunk = self.chunk_files(worktree_abs_path, scan.updated_entries, cx);
         let embed = Self::embed_files(self.embedding_provider.clone(), chunk.files, cx);
         let persist = self.persist_embeddings(scan.deleted_entry_ranges, embed.files, cx);
+        let ann_index = self.ann_index.clone();
+        let executor = cx.background_executor().clone();
         async move {
             futures::try_join!(scan.task, chunk.task, embed.task, persist)?;
-            Ok(())
+            executor.spawn(async move { ann_index.maintain() }).await
         }
         .boxed()
     }
@@ -99,8 +124,10 @@ impl EmbeddingIndex {
     fn scan_entries(&self, worktree: Snapshot, cx: &App) -> ScanEntries {
         let (updated_entries_tx, updated_entries_rx) = channel::bounded(512);
         let (deleted_entry_ranges_tx, deleted_entry_ranges_rx) = channel::bounded(128);
+        let (keywordless_files_tx, keywordless_files_rx) = channel::bounded(512);
         let db_connection = self.db_connection.clone();
         let db = self.db;
+        let keyword_db = self.keyword_db.remap_data_type::<DecodeIgnore>();
         let entries_being_indexed = self.entry_ids_being_indexed.clone();
         let task = cx.background_spawn(async move {
             let txn = db_connection
@@ -142,6 +169,20 @@ impl EmbeddingIndex {
                                         .await?;
                                 }
                                 saved_mtime = db_embedded_file.mtime;
+                                // Files embedded before keywords were indexed only need their
+                                // keywords indexed, from the chunks they were embedded in.
+                                if entry.mtime == saved_mtime
+                                    && keyword_db.get(&txn, &entry_db_key)?.is_none()
+                                {
+                                    let chunks = db_embedded_file
+                                        .chunks
+                                        .iter()
+                                        .map(|chunk| chunk.chunk.clone())
+                                        .collect::<Vec<_>>();
+                                    keywordless_files_tx
+                                        .send((entry.path.clone(), chunks))
+                                        .await?;
+                                }
                                 db_entries.next();
                                 break;
                             }
@@ -172,6 +213,7 @@ impl EmbeddingIndex {
         ScanEntries {
             updated_entries: updated_entries_rx,
             deleted_entry_ranges: deleted_entry_ranges_rx,
+            keywordless_files: keywordless_files_rx,
             task,
         }
     }
@@ -184,6 +226,8 @@ impl EmbeddingIndex {
     ) -> ScanEntries {
         let (updated_entries_tx, updated_entries_rx) = channel::bounded(512);
         let (deleted_entry_ranges_tx, deleted_entry_ranges_rx) = channel::bounded(128);
+        // Updated entries are embedded again, which indexes their keywords too.
+        let (_, keywordless_files_rx) = channel::bounded(1);
         let entries_being_indexed = self.entry_ids_being_indexed.clone();
         let task = cx.background_spawn(async move {
             for (path, entry_id, status) in updated_entries.iter() {
@@ -216,10 +260,37 @@ impl EmbeddingIndex {
         ScanEntries {
             updated_entries: updated_entries_rx,
             deleted_entry_ranges: deleted_entry_ranges_rx,
+            keywordless_files: keywordless_files_rx,
             task,
         }
     }
 
+    /// Indexes the keywords of files whose embeddings are up to date, from the chunks they were
+    /// embedded in, without embedding them again.
+    fn index_keywords(
+        &self,
+        worktree_abs_path: Arc<Path>,
+        files: channel::Receiver<(Arc<Path>, Vec<Chunk>)>,
+        cx: &App,
+    ) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let db_connection = self.db_connection.clone();
+        let keyword_db = self.keyword_db;
+        cx.background_spawn(async move {
+            while let Ok((path, chunks)) = files.recv().await {
+                let Some(text) = fs.load(&worktree_abs_path.join(&path)).await.log_err() else {
+                    continue;
+                };
+                let keyword_file = KeywordFile::new(path.clone(), &text, &chunks);
+                let mut txn = db_connection.write_txn()?;
+                log::debug!("saving keywords for file {:?}", path);
+                keyword_db.put(&mut txn, &db_key_for_path(&path), &keyword_file)?;
+                txn.commit()?;
+            }
+            Ok(())
+        })
+    }
+
     fn chunk_files(
         &self,
         worktree_abs_path: Arc<Path>,
@@ -317,6 +388,11 @@ impl EmbeddingIndex {
 
                 let mut embeddings = embeddings.into_iter();
                 for chunked_file in chunked_files {
+                    let keyword_file = KeywordFile::new(
+                        chunked_file.path.clone(),
+                        &chunked_file.text,
+                        &chunked_file.chunks,
+                    );
                     let mut embedded_file = EmbeddedFile {
                         path: chunked_file.path,
                         mtime: chunked_file.mtime,
@@ -338,7 +414,7 @@ impl EmbeddingIndex {
 
                     if embedded_all_chunks {
                         embedded_files_tx
-                            .send((embedded_file, chunked_file.handle))
+                            .send((embedded_file, keyword_file, chunked_file.handle))
                             .await?;
                     }
                 }
@@ -355,11 +431,13 @@ impl EmbeddingIndex {
     fn persist_embeddings(
         &self,
         deleted_entry_ranges: channel::Receiver<(Bound<String>, Bound<String>)>,
-        embedded_files: channel::Receiver<(EmbeddedFile, IndexingEntryHandle)>,
+        embedded_files: channel::Receiver<(EmbeddedFile, KeywordFile, IndexingEntryHandle)>,
         cx: &App,
     ) -> Task<Result<()>> {
         let db_connection = self.db_connection.clone();
         let db = self.db;
+        let ann_index = self.ann_index.clone();
+        let keyword_db = self.keyword_db;
 
         cx.background_spawn(async move {
             let mut deleted_entry_ranges = pin!(deleted_entry_ranges);
@@ -373,17 +451,40 @@ impl EmbeddingIndex {
                             let start = deletion_range.0.as_ref().map(|start| start.as_str());
                             let end = deletion_range.1.as_ref().map(|end| end.as_str());
                             log::debug!("deleting embeddings in range {:?}", &(start, end));
+                            let deleted_files = db
+                                .range(&txn, &(start, end))?
+                                .map(|entry| {
+                                    let (key, file) = entry?;
+                                    Ok((key.to_string(), file))
+                                })
+                                .collect::<Result<Vec<_>>>()?;
+                            let mut ann_changes = AnnChanges::default();
+                            for (key, file) in deleted_files {
+                                ann_index.remove_file(&mut txn, &key, &file, &mut ann_changes)?;
+                            }
                             db.delete_range(&mut txn, &(start, end))?;
-                            txn.commit()?;
+                            keyword_db.delete_range(&mut txn, &(start, end))?;
+                            ann_index.commit(txn, ann_changes)?;
                         }
                     },
                     file = embedded_files.next() => {
-                        if let Some((file, _)) = file {
+                        if let Some((file, keyword_file, _)) = file {
                             let mut txn = db_connection.write_txn()?;
                             log::debug!("saving embedding for file {:?}", file.path);
                             let key = db_key_for_path(&file.path);
+                            let mut ann_changes = AnnChanges::default();
+                            if let Some(previous_file) = db.get(&txn, &key)? {
+                                ann_index.remove_file(
+                                    &mut txn,
+                                    &key,
+                                    &previous_file,
+                                    &mut ann_changes,
+                                )?;
+                            }
+                            ann_index.insert_file(&mut txn, &key, &file, &mut ann_changes)?;
                             db.put(&mut txn, &key, &file)?;
-                            txn.commit()?;
+                            keyword_db.put(&mut txn, &key, &keyword_file)?;
+                            ann_index.commit(txn, ann_changes)?;
                         }
                     },
                     complete => break,
@@ -429,6 +530,8 @@ impl EmbeddingIndex {
 struct ScanEntries {
     updated_entries: channel::Receiver<(Entry, IndexingEntryHandle)>,
     deleted_entry_ranges: channel::Receiver<(Bound<String>, Bound<String>)>,
+    /// Files that were embedded without indexing their keywords, with their chunks.
+    keywordless_files: channel::Receiver<(Arc<Path>, Vec<Chunk>)>,
     task: Task<Result<()>>,
 }
 
@@ -446,7 +549,7 @@ pub struct ChunkedFile {
 }
 
 pub struct EmbedFiles {
-    pub files: channel::Receiver<(EmbeddedFile, IndexingEntryHandle)>,
+    pub files: channel::Receiver<(EmbeddedFile, KeywordFile, IndexingEntryHandle)>,
     pub task: Task<Result<()>>,
 }
 
@@ -0,0 +1,272 @@
+//! A BM25 keyword index over the same chunks as the embedding index, so that searches can
+//! find exact identifiers that embeddings tend to blur.
+
+use crate::chunking::Chunk;
+use anyhow::Result;
+use collections::{BTreeMap, HashMap};
+use heed::types::{SerdeBincode, Str};
+use heed::RoTxn;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, ops::Range, path::Path, sync::Arc};
+use util::terms::terms_in;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeywordFile {
+    pub path: Arc<Path>,
+    pub chunks: Vec<KeywordChunk>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeywordChunk {
+    pub range: Range<usize>,
+    /// The number of terms in the chunk, counting repeats.
+    pub term_count: u32,
+    /// The chunk's distinct terms with the number of times each appears, sorted by term.
+    pub terms: Vec<(String, u32)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct KeywordMatch {
+    pub path: Arc<Path>,
+    pub range: Range<usize>,
+    pub score: f32,
+}
+
+impl KeywordFile {
+    pub fn new(path: Arc<Path>, text: &str, chunks: &[Chunk]) -> Self {
+        Self {
+            path,
+            chunks: chunks
+                .iter()
+                .map(|chunk| KeywordChunk::new(text, chunk.range.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl KeywordChunk {
+    fn new(text: &str, range: Range<usize>) -> Self {
+        let mut terms = BTreeMap::<String, u32>::new();
+        let mut term_count = 0;
+        for term in terms_in(text.get(range.clone()).unwrap_or_default()) {
+            *terms.entry(term).or_default() += 1;
+            term_count += 1;
+        }
+        Self {
+            range,
+            term_count,
+            terms: terms.into_iter().collect(),
+        }
+    }
+
+    fn term_frequency(&self, term: &str) -> u32 {
+        self.terms
+            .binary_search_by(|(probe, _)| probe.as_str().cmp(term))
+            .map_or(0, |ix| self.terms[ix].1)
+    }
+}
+
+/// Scores every chunk in the database against the query with BM25, returning the best matches.
+pub fn search(
+    txn: &RoTxn<'_>,
+    db: heed::Database<Str, SerdeBincode<KeywordFile>>,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<KeywordMatch>> {
+    let mut query_terms = terms_in(query);
+    query_terms.sort_unstable();
+    query_terms.dedup();
+    if query_terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    struct Candidate {
+        path: Arc<Path>,
+        range: Range<usize>,
+        term_count: u32,
+        term_frequencies: Vec<u32>,
+    }
+
+    // Collect the statistics BM25 needs in a single pass, keeping only the chunks that
+    // contain at least one of the query's terms.
+    let mut chunk_count = 0usize;
+    let mut total_term_count = 0u64;
+    let mut document_frequencies = vec![0u32; query_terms.len()];
+    let mut candidates = Vec::new();
+    for entry in db.iter(txn)? {
+        let (_, file) = entry?;
+        for chunk in file.chunks {
+            chunk_count += 1;
+            total_term_count += chunk.term_count as u64;
+
+            let term_frequencies = query_terms
+                .iter()
+                .map(|term| chunk.term_frequency(term))
+                .collect::<Vec<_>>();
+            if term_frequencies.iter().any(|frequency| *frequency > 0) {
+                for (document_frequency, frequency) in
+                    document_frequencies.iter_mut().zip(&term_frequencies)
+                {
+                    if *frequency > 0 {
+                        *document_frequency += 1;
+                    }
+                }
+                candidates.push(Candidate {
+                    path: file.path.clone(),
+                    range: chunk.range,
+                    term_count: chunk.term_count,
+                    term_frequencies,
+                });
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let average_term_count = total_term_count as f32 / chunk_count as f32;
+    let inverse_document_frequencies = document_frequencies
+        .iter()
+        .map(|document_frequency| {
+            let document_frequency = *document_frequency as f32;
+            (1. + (chunk_count as f32 - document_frequency + 0.5) / (document_frequency + 0.5)).ln()
+        })
+        .collect::<Vec<_>>();
+
+    let mut matches = candidates
+        .into_iter()
+        .map(|candidate| {
+            let length_norm = 1. - B + B * candidate.term_count as f32 / average_term_count.max(1.);
+            let score = candidate
+                .term_frequencies
+                .iter()
+                .zip(&inverse_document_frequencies)
+                .map(|(frequency, inverse_document_frequency)| {
+                    let frequency = *frequency as f32;
+                    inverse_document_frequency * frequency * (K1 + 1.)
+                        / (frequency + K1 * length_norm)
+                })
+                .sum();
+            KeywordMatch {
+                path: candidate.path,
+                range: candidate.range,
+                score,
+            }
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.range.start.cmp(&b.range.start))
+    });
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+/// Combines rankings of the same items with reciprocal rank fusion, which only relies on the
+/// items' ranks, since the scores of different rankings aren't comparable. Returns the items
+/// with their fused scores, best first.
+pub fn reciprocal_rank_fusion<T, K: std::hash::Hash + Eq>(
+    rankings: impl IntoIterator<Item = Vec<T>>,
+    key: impl Fn(&T) -> K,
+) -> Vec<(T, f32)> {
+    // The constant from the original paper, which dampens the weight of the top ranks.
+    const K: f32 = 60.;
+
+    let mut fused = Vec::<(T, f32)>::new();
+    let mut indices_by_key = HashMap::<K, usize>::default();
+    for ranking in rankings {
+        for (rank, item) in ranking.into_iter().enumerate() {
+            let score = 1. / (K + rank as f32 + 1.);
+            match indices_by_key.entry(key(&item)) {
+                collections::hash_map::Entry::Occupied(entry) => fused[*entry.get()].1 += score,
+                collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(fused.len());
+                    fused.push((item, score));
+                }
+            }
+        }
+    }
+    // Sorting is stable, so ties keep the order of the earlier rankings.
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_connection = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(1024 * 1024)
+                .max_dbs(1)
+                .open(temp_dir.path())
+                .unwrap()
+        };
+        let mut txn = db_connection.write_txn().unwrap();
+        let db: heed::Database<Str, SerdeBincode<KeywordFile>> = db_connection
+            .create_database(&mut txn, Some("keywords"))
+            .unwrap();
+        let files = [
+            (
+                "config.rs",
+                "fn parse_config() {}\nfn load_config() { parse_config() }",
+            ),
+            ("main.rs", "fn main() { run() }\nfn run() { load_config() }"),
+            ("readme.md", "Nothing to see here."),
+        ];
+        for (path, text) in files {
+            let chunks = text
+                .match_indices('\n')
+                .map(|(ix, _)| ix)
+                .chain([text.len()])
+                .scan(0, |start, end| {
+                    let range = *start..end;
+                    *start = end;
+                    Some(Chunk {
+                        range,
+                        digest: [0; 32],
+                    })
+                })
+                .collect::<Vec<_>>();
+            let file = KeywordFile::new(Path::new(path).into(), text, &chunks);
+            db.put(&mut txn, path, &file).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let txn = db_connection.read_txn().unwrap();
+        let matches = search(&txn, db, "parse_config", 10).unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|keyword_match| (keyword_match.path.as_ref(), keyword_match.range.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (Path::new("config.rs"), 0..20),
+                (Path::new("config.rs"), 20..56),
+                (Path::new("main.rs"), 19..46),
+            ]
+        );
+        assert!(search(&txn, db, "nonexistent", 10).unwrap().is_empty());
+        assert_eq!(search(&txn, db, "config", 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let fused = reciprocal_rank_fusion([vec!["a", "b", "c"], vec!["b", "c", "d"]], |item| {
+            item.to_string()
+        });
+        assert_eq!(
+            fused.iter().map(|(item, _)| *item).collect::<Vec<_>>(),
+            ["b", "c", "a", "d"]
+        );
+    }
+}
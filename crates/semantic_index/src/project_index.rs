@@ -1,5 +1,6 @@
 use crate::{
     embedding::{EmbeddingProvider, TextToEmbed},
+    keyword_index,
     summary_index::FileSummary,
     worktree_index::{WorktreeIndex, WorktreeIndexHandle},
 };
@@ -234,45 +235,7 @@ impl ProjectIndex {
         limit: usize,
         cx: &App,
     ) -> Task<Result<Vec<SearchResult>>> {
-        let (chunks_tx, chunks_rx) = channel::bounded(1024);
-        let mut worktree_scan_tasks = Vec::new();
-        for worktree_index in self.worktree_indices.values() {
-            let worktree_index = worktree_index.clone();
-            let chunks_tx = chunks_tx.clone();
-            worktree_scan_tasks.push(cx.spawn(|cx| async move {
-                let index = match worktree_index {
-                    WorktreeIndexHandle::Loading { index } => {
-                        index.clone().await.map_err(|error| anyhow!(error))?
-                    }
-                    WorktreeIndexHandle::Loaded { index } => index.clone(),
-                };
-
-                index
-                    .read_with(&cx, |index, cx| {
-                        let worktree_id = index.worktree().read(cx).id();
-                        let db_connection = index.db_connection().clone();
-                        let db = *index.embedding_index().db();
-                        cx.background_spawn(async move {
-                            let txn = db_connection
-                                .read_txn()
-                                .context("failed to create read transaction")?;
-                            let db_entries = db.iter(&txn).context("failed to iterate database")?;
-                            for db_entry in db_entries {
-                                let (_key, db_embedded_file) = db_entry?;
-                                for chunk in db_embedded_file.chunks {
-                                    chunks_tx
-                                        .send((worktree_id, db_embedded_file.path.clone(), chunk))
-                                        .await?;
-                                }
-                            }
-                            anyhow::Ok(())
-                        })
-                    })?
-                    .await
-            }));
-        }
-        drop(chunks_tx);
-
+        let worktree_indices = self.worktree_indices.values().cloned().collect::<Vec<_>>();
         let project = self.project.clone();
         let embedding_provider = self.embedding_provider.clone();
         cx.spawn(|cx| async move {
@@ -290,6 +253,78 @@ impl ProjectIndex {
                     "The number of query embeddings does not match the number of queries"
                 ));
             }
+            let query_embeddings = Arc::new(query_embeddings);
+
+            // Worktrees with an approximate nearest-neighbour index only send the chunks near
+            // the queries, while the others send every chunk.
+            let (chunks_tx, chunks_rx) = channel::bounded(1024);
+            let mut worktree_scan_tasks = Vec::new();
+            for worktree_index in worktree_indices {
+                let chunks_tx = chunks_tx.clone();
+                let query_embeddings = query_embeddings.clone();
+                worktree_scan_tasks.push(cx.spawn(|cx| async move {
+                    let index = match worktree_index {
+                        WorktreeIndexHandle::Loading { index } => {
+                            index.clone().await.map_err(|error| anyhow!(error))?
+                        }
+                        WorktreeIndexHandle::Loaded { index } => index.clone(),
+                    };
+
+                    index
+                        .read_with(&cx, |index, cx| {
+                            let worktree_id = index.worktree().read(cx).id();
+                            let db_connection = index.db_connection().clone();
+                            let db = *index.embedding_index().db();
+                            let ann_index = index.embedding_index().ann_index().clone();
+                            cx.background_spawn(async move {
+                                let txn = db_connection
+                                    .read_txn()
+                                    .context("failed to create read transaction")?;
+                                if let Some(candidates) =
+                                    ann_index.candidates(&txn, &query_embeddings)?
+                                {
+                                    for (key, chunk_indices) in candidates {
+                                        let Some(db_embedded_file) = db.get(&txn, &key)? else {
+                                            continue;
+                                        };
+                                        for (chunk_ix, chunk) in
+                                            db_embedded_file.chunks.into_iter().enumerate()
+                                        {
+                                            if chunk_indices.contains(&(chunk_ix as u32)) {
+                                                chunks_tx
+                                                    .send((
+                                                        worktree_id,
+                                                        db_embedded_file.path.clone(),
+                                                        chunk,
+                                                    ))
+                                                    .await?;
+                                            }
+                                        }
+                                    }
+                                    return anyhow::Ok(());
+                                }
+
+                                let db_entries =
+                                    db.iter(&txn).context("failed to iterate database")?;
+                                for db_entry in db_entries {
+                                    let (_key, db_embedded_file) = db_entry?;
+                                    for chunk in db_embedded_file.chunks {
+                                        chunks_tx
+                                            .send((
+                                                worktree_id,
+                                                db_embedded_file.path.clone(),
+                                                chunk,
+                                            ))
+                                            .await?;
+                                    }
+                                }
+                                anyhow::Ok(())
+                            })
+                        })?
+                        .await
+                }));
+            }
+            drop(chunks_tx);
 
             let mut results_by_worker = Vec::new();
             for _ in 0..cx.background_executor().num_cpus() {
@@ -371,6 +406,99 @@ impl ProjectIndex {
         })
     }
 
+    /// Searches for chunks containing the query's words, ranked with BM25.
+    pub fn keyword_search(
+        &self,
+        query: String,
+        limit: usize,
+        cx: &App,
+    ) -> Task<Result<Vec<SearchResult>>> {
+        let query = Arc::<str>::from(query);
+        let mut worktree_search_tasks = Vec::new();
+        for worktree_index in self.worktree_indices.values() {
+            let worktree_index = worktree_index.clone();
+            let query = query.clone();
+            worktree_search_tasks.push(cx.spawn(|cx| async move {
+                let index = match worktree_index {
+                    WorktreeIndexHandle::Loading { index } => {
+                        index.clone().await.map_err(|error| anyhow!(error))?
+                    }
+                    WorktreeIndexHandle::Loaded { index } => index.clone(),
+                };
+
+                let (worktree, search) = index.read_with(&cx, |index, cx| {
+                    let db_connection = index.db_connection().clone();
+                    let keyword_db = *index.embedding_index().keyword_db();
+                    let search = cx.background_spawn(async move {
+                        let txn = db_connection
+                            .read_txn()
+                            .context("failed to create read transaction")?;
+                        keyword_index::search(&txn, keyword_db, &query, limit)
+                    });
+                    (index.worktree().clone(), search)
+                })?;
+                let matches = search.await?;
+                anyhow::Ok(
+                    matches
+                        .into_iter()
+                        .map(|keyword_match| SearchResult {
+                            worktree: worktree.clone(),
+                            path: keyword_match.path,
+                            range: keyword_match.range,
+                            score: keyword_match.score,
+                            query_index: 0,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            }));
+        }
+
+        cx.spawn(|_| async move {
+            let mut results = Vec::new();
+            for worktree_results in futures::future::join_all(worktree_search_tasks).await {
+                if let Some(worktree_results) = worktree_results.log_err() {
+                    results.extend(worktree_results);
+                }
+            }
+            results.sort_unstable_by(|a, b| {
+                b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal)
+            });
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
+
+    /// Searches both by meaning and by keyword, fusing the two rankings of the chunks with
+    /// reciprocal rank fusion. The results' scores are the fused scores.
+    pub fn hybrid_search(
+        &self,
+        query: String,
+        limit: usize,
+        cx: &App,
+    ) -> Task<Result<Vec<SearchResult>>> {
+        let semantic_search = self.search(vec![query.clone()], limit, cx);
+        let keyword_search = self.keyword_search(query, limit, cx);
+        cx.spawn(|_| async move {
+            let (semantic_results, keyword_results) =
+                futures::future::try_join(semantic_search, keyword_search).await?;
+            let mut results = keyword_index::reciprocal_rank_fusion(
+                [semantic_results, keyword_results],
+                |result| {
+                    (
+                        result.worktree.entity_id(),
+                        result.path.clone(),
+                        result.range.clone(),
+                    )
+                },
+            )
+            .into_iter()
+            .map(|(result, score)| SearchResult { score, ..result })
+            .collect::<Vec<_>>();
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
+
     #[cfg(test)]
     pub fn path_count(&self, cx: &App) -> Result<u64> {
         let mut result = 0;
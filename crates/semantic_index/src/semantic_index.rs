@@ -1,19 +1,24 @@
+mod ann_index;
 mod chunking;
 mod embedding;
 mod embedding_index;
 mod indexing;
+mod keyword_index;
 mod project_index;
 mod project_index_debug_view;
+mod semantic_index_settings;
 mod summary_backlog;
 mod summary_index;
 mod worktree_index;
 
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
 use collections::HashMap;
 use fs::Fs;
 use gpui::{App, AppContext as _, AsyncApp, BorrowAppContext, Context, Entity, Global, WeakEntity};
+use http_client::HttpClient;
 use language::LineEnding;
 use project::{Project, Worktree};
+use settings::{Settings as _, SettingsStore};
 use std::{
     cmp::Ordering,
     path::{Path, PathBuf},
@@ -25,8 +30,114 @@ use workspace::Workspace;
 pub use embedding::*;
 pub use project_index::{LoadedSearchResult, ProjectIndex, SearchResult, Status};
 pub use project_index_debug_view::ProjectIndexDebugView;
+pub use semantic_index_settings::*;
 pub use summary_index::FileSummary;
 
+pub fn init(http_client: Arc<dyn HttpClient>, cx: &mut App) {
+    SemanticIndexSettings::register(cx);
+    cx.set_global(LocalSemanticDb::default());
+    update_local_semantic_db(http_client.clone(), cx);
+    cx.observe_global::<SettingsStore>(move |cx| update_local_semantic_db(http_client.clone(), cx))
+        .detach();
+}
+
+/// The local embedding provider and model that the [`SemanticDb`] was opened with, when it
+/// was opened because the `semantic_index` setting is enabled.
+#[derive(Default)]
+struct LocalSemanticDb {
+    opened_with: Option<(LocalEmbeddingProvider, Option<String>)>,
+}
+
+impl Global for LocalSemanticDb {}
+
+/// Opens the [`SemanticDb`] with the local embedding provider when the `semantic_index`
+/// setting is enabled, and closes it when the setting is disabled.
+fn update_local_semantic_db(http_client: Arc<dyn HttpClient>, cx: &mut App) {
+    let settings = SemanticIndexSettings::get_global(cx).clone();
+    let opened_with = cx.global::<LocalSemanticDb>().opened_with.clone();
+    if !settings.enabled {
+        if opened_with.is_some() {
+            cx.global_mut::<LocalSemanticDb>().opened_with = None;
+            if cx.has_global::<SemanticDb>() {
+                cx.remove_global::<SemanticDb>();
+            }
+        }
+        return;
+    }
+
+    let configuration = (settings.provider, settings.model.clone());
+    if opened_with.as_ref() == Some(&configuration) {
+        return;
+    }
+    cx.global_mut::<LocalSemanticDb>().opened_with = Some(configuration.clone());
+
+    let (embedding_provider, model_id) =
+        match local_embedding_provider(&settings, http_client.clone()) {
+            Ok(provider) => provider,
+            Err(error) => {
+                log::error!("{error:#}");
+                return;
+            }
+        };
+    let db_path = paths::embeddings_dir().join(format!(
+        "semantic-index-db.{}-{model_id}.mdb",
+        settings.provider.id()
+    ));
+    cx.spawn(|mut cx| async move {
+        let semantic_db = SemanticDb::new(db_path, embedding_provider, &mut cx).await?;
+        cx.update(|cx| {
+            // The settings may have changed while the database was opening.
+            if cx.global::<LocalSemanticDb>().opened_with.as_ref() != Some(&configuration) {
+                return;
+            }
+            cx.set_global(semantic_db);
+
+            // Index the projects of the workspaces that were opened before the index.
+            let projects = cx
+                .windows()
+                .into_iter()
+                .filter_map(|window| window.downcast::<Workspace>())
+                .filter_map(|workspace| Some(workspace.read(cx).ok()?.project().clone()))
+                .collect::<Vec<_>>();
+            cx.update_global::<SemanticDb, _>(|semantic_db, cx| {
+                for project in projects {
+                    if semantic_db.project_index(project.clone(), cx).is_none() {
+                        semantic_db.create_project_index(project, cx);
+                    }
+                }
+            });
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+fn local_embedding_provider(
+    settings: &SemanticIndexSettings,
+    http_client: Arc<dyn HttpClient>,
+) -> Result<(Arc<dyn EmbeddingProvider>, &'static str)> {
+    let model_id = settings.model.as_deref().unwrap_or("nomic-embed-text");
+    match settings.provider {
+        LocalEmbeddingProvider::Ollama => {
+            let model = OllamaEmbeddingModel::from_id(model_id)
+                .ok_or_else(|| anyhow!("unsupported Ollama embedding model {model_id:?}"))?;
+            let model_id = model.id();
+            Ok((
+                Arc::new(OllamaEmbeddingProvider::new(http_client, model)),
+                model_id,
+            ))
+        }
+        LocalEmbeddingProvider::LmStudio => {
+            let model = LmStudioEmbeddingModel::from_id(model_id)
+                .ok_or_else(|| anyhow!("unsupported LM Studio embedding model {model_id:?}"))?;
+            let model_id = model.id();
+            Ok((
+                Arc::new(LmStudioEmbeddingProvider::new(http_client, model)),
+                model_id,
+            ))
+        }
+    }
+}
+
 pub struct SemanticDb {
     embedding_provider: Arc<dyn EmbeddingProvider>,
     db_connection: Option<heed::Env>,
@@ -61,7 +172,10 @@ impl SemanticDb {
 
                     if cx.has_global::<SemanticDb>() {
                         cx.update_global::<SemanticDb, _>(|this, cx| {
-                            this.create_project_index(project, cx);
+                            // A database opened later also observes new workspaces.
+                            if this.project_index(project.clone(), cx).is_none() {
+                                this.create_project_index(project, cx);
+                            }
                         })
                     } else {
                         log::info!("No SemanticDb, skipping project index")
@@ -277,7 +391,14 @@ mod tests {
     use serde_json::json;
     use settings::SettingsStore;
     use smol::channel;
-    use std::{future, path::Path, sync::Arc};
+    use std::{
+        future,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering::SeqCst},
+            Arc,
+        },
+    };
     use util::separator;
 
     fn init_test(cx: &mut TestAppContext) {
@@ -289,6 +410,7 @@ mod tests {
             language::init(cx);
             cx.update_flags(false, vec![]);
             Project::init_settings(cx);
+            SemanticIndexSettings::register(cx);
             SettingsStore::update(cx, |store, cx| {
                 store.update_user_settings::<AllLanguageSettings>(cx, |_| {});
             });
@@ -441,6 +563,99 @@ mod tests {
         assert!(content.contains("garbage in, garbage out"));
     }
 
+    #[gpui::test]
+    async fn test_indexing_keywords_of_embedded_files_does_not_embed_them_again(
+        cx: &mut TestAppContext,
+    ) {
+        cx.executor().allow_parking();
+
+        init_test(cx);
+
+        cx.update(|cx| {
+            // This functionality is staff-flagged.
+            cx.update_flags(true, vec![]);
+        });
+
+        let embedded_chunk_count = Arc::new(AtomicUsize::new(0));
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut semantic_index = SemanticDb::new(
+            temp_dir.path().into(),
+            Arc::new(TestEmbeddingProvider::new(16, {
+                let embedded_chunk_count = embedded_chunk_count.clone();
+                move |_| {
+                    embedded_chunk_count.fetch_add(1, SeqCst);
+                    Ok(Embedding::new(vec![1., 0.]))
+                }
+            })),
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+
+        let fs = FakeFs::new(cx.executor());
+        let project_path = Path::new("/fake_project");
+        fs.insert_tree(
+            project_path,
+            json!({
+                "main.rs": "fn parse_config() {}\n",
+                "notes.md": "Nothing to see here.\n",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, [project_path], cx).await;
+
+        let index_project = |semantic_index: &mut SemanticDb, cx: &mut TestAppContext| {
+            let project_index =
+                cx.update(|cx| semantic_index.create_project_index(project.clone(), cx));
+            cx.run_until_parked();
+            while cx
+                .update(|cx| semantic_index.remaining_summaries(&project.downgrade(), cx))
+                .unwrap()
+                > 0
+            {
+                cx.run_until_parked();
+            }
+            project_index
+        };
+        let search_keywords = |project_index: &Entity<ProjectIndex>, cx: &mut TestAppContext| {
+            cx.update(|cx| {
+                project_index
+                    .read(cx)
+                    .keyword_search("config".into(), 10, cx)
+            })
+        };
+
+        let project_index = index_project(&mut semantic_index, cx);
+        let results = search_keywords(&project_index, cx).await.unwrap();
+        assert_eq!(results.len(), 1);
+        let embedded_chunk_count_after_indexing = embedded_chunk_count.load(SeqCst);
+        assert!(embedded_chunk_count_after_indexing > 0);
+
+        // Forget the keywords, as if the files were embedded before keywords were indexed.
+        let worktree_abs_path = project.read_with(cx, |project, cx| {
+            project.worktrees(cx).next().unwrap().read(cx).abs_path()
+        });
+        let db_connection = semantic_index.db_connection.clone().unwrap();
+        let mut txn = db_connection.write_txn().unwrap();
+        let keyword_db: heed::Database<heed::types::Str, heed::types::DecodeIgnore> = db_connection
+            .create_database(
+                &mut txn,
+                Some(&format!("keywords-{}", worktree_abs_path.to_string_lossy())),
+            )
+            .unwrap();
+        keyword_db.clear(&mut txn).unwrap();
+        txn.commit().unwrap();
+        drop(project_index);
+
+        let project_index = index_project(&mut semantic_index, cx);
+        let results = search_keywords(&project_index, cx).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            embedded_chunk_count.load(SeqCst),
+            embedded_chunk_count_after_indexing
+        );
+    }
+
     #[gpui::test]
     async fn test_embed_files(cx: &mut TestAppContext) {
         cx.executor().allow_parking();
@@ -499,12 +714,27 @@ mod tests {
 
         let embedded_files_rx = embed_files_task.files;
         let mut embedded_files = Vec::new();
-        while let Ok((embedded_file, _)) = embedded_files_rx.recv().await {
+        let mut keyword_files = Vec::new();
+        while let Ok((embedded_file, keyword_file, _)) = embedded_files_rx.recv().await {
             embedded_files.push(embedded_file);
+            keyword_files.push(keyword_file);
         }
 
         assert_eq!(embedded_files.len(), 1);
         assert_eq!(embedded_files[0].path.as_ref(), Path::new("test2.md"));
+        assert_eq!(keyword_files[0].path.as_ref(), Path::new("test2.md"));
+        assert_eq!(
+            keyword_files[0]
+                .chunks
+                .iter()
+                .map(|chunk| chunk.terms.clone())
+                .collect::<Vec<_>>(),
+            [
+                vec![("qrst".to_string(), 1)],
+                vec![("uvwx".to_string(), 1)],
+                vec![("yz".to_string(), 1)],
+            ],
+        );
         assert_eq!(
             embedded_files[0]
                 .chunks
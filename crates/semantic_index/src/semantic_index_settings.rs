@@ -0,0 +1,69 @@
+use anyhow::Result;
+use gpui::App;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+/// A local server that computes embeddings, so that the semantic index works offline.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalEmbeddingProvider {
+    #[default]
+    Ollama,
+    #[serde(rename = "lmstudio")]
+    LmStudio,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Ollama => "ollama",
+            Self::LmStudio => "lmstudio",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SemanticIndexSettings {
+    pub enabled: bool,
+    pub provider: LocalEmbeddingProvider,
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SemanticIndexSettingsContent {
+    /// Whether to index the project's files, to search them by meaning in project search.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The local server that computes the embeddings, either `ollama` or `lmstudio`.
+    ///
+    /// Default: ollama
+    pub provider: Option<LocalEmbeddingProvider>,
+    /// The embedding model to use, such as `nomic-embed-text` or `mxbai-embed-large`.
+    ///
+    /// Default: nomic-embed-text
+    pub model: Option<String>,
+}
+
+impl Settings for SemanticIndexSettings {
+    const KEY: Option<&'static str> = Some("semantic_index");
+
+    type FileContent = SemanticIndexSettingsContent;
+
+    fn load(sources: SettingsSources<Self::FileContent>, _: &mut App) -> Result<Self> {
+        let mut settings = Self::default();
+        for value in sources.defaults_and_customizations() {
+            if let Some(enabled) = value.enabled {
+                settings.enabled = enabled;
+            }
+            if let Some(provider) = value.provider {
+                settings.provider = provider;
+            }
+            if value.model.is_some() {
+                settings.model = value.model.clone();
+            }
+        }
+        Ok(settings)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ann_index::AnnIndex;
 use crate::embedding::EmbeddingProvider;
 use crate::embedding_index::EmbeddingIndex;
 use crate::indexing::IndexingEntrySet;
@@ -60,12 +61,38 @@ impl WorktreeIndex {
                         let embedding_index = {
                             let db_name = worktree_abs_path.to_string_lossy();
                             let db = db_connection.create_database(&mut txn, Some(&db_name))?;
+                            let ann_postings_db = {
+                                let db_name =
+                                    format!("ann-postings-{}", worktree_abs_path.to_string_lossy());
+                                db_connection.create_database(&mut txn, Some(&db_name))?
+                            };
+                            let ann_centroids_db = {
+                                let db_name = format!(
+                                    "ann-centroids-{}",
+                                    worktree_abs_path.to_string_lossy()
+                                );
+                                db_connection.create_database(&mut txn, Some(&db_name))?
+                            };
+                            let keyword_db = {
+                                let db_name =
+                                    format!("keywords-{}", worktree_abs_path.to_string_lossy());
+                                db_connection.create_database(&mut txn, Some(&db_name))?
+                            };
+                            let ann_index = AnnIndex::load(
+                                db_connection.clone(),
+                                &txn,
+                                db,
+                                ann_postings_db,
+                                ann_centroids_db,
+                            )?;
 
                             EmbeddingIndex::new(
                                 worktree_for_index,
                                 embedding_fs,
                                 db_connection.clone(),
                                 db,
+                                ann_index,
+                                keyword_db,
                                 language_registry,
                                 embedding_provider,
                                 Arc::clone(&entries_being_indexed),
@@ -0,0 +1,840 @@
+//! Inline images, sent by programs through the Kitty graphics protocol, Sixel,
+//! or the iTerm2 inline images protocol.
+//!
+//! Alacritty supports none of them, so [`ImageFilter`] takes their sequences out
+//! of the PTY output before it reaches the parser, and replaces them with cursor
+//! movements that make room for the image in the grid. [`Terminal`](crate::Terminal)
+//! then anchors every image to the cells it covers, so it scrolls along with the text.
+
+mod iterm;
+mod kitty;
+mod sixel;
+
+use crate::{shell_integration::GridPosition, TerminalBounds};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+use collections::VecDeque;
+use gpui::{px, size, Pixels, RenderImage, Size};
+use image::{Frame, ImageReader, Limits, RgbaImage};
+use kitty::KittyGraphics;
+use smallvec::SmallVec;
+use std::{io::Cursor, sync::Arc};
+
+/// The largest width or height of an image, in pixels.
+const MAX_IMAGE_DIMENSION: usize = 4096;
+
+/// The largest amount of data a single image can be sent with.
+const MAX_IMAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The number of rows an image can take, however large it is displayed.
+const MAX_IMAGE_ROWS: usize = 1000;
+
+/// The number of images kept in a terminal, the oldest ones are dropped first.
+const MAX_IMAGE_PLACEMENTS: usize = 256;
+
+/// Programs do not agree on padding the base64 data of images, so accept both.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+const ITERM_PREFIX: &[u8] = b"1337;File=";
+
+/// The longest parameters of a DCS sequence that are checked for being a Sixel image.
+const MAX_DCS_PARAMS_LEN: usize = 32;
+
+/// The longest CSI parameters that are checked for clearing the screen.
+const MAX_CSI_PARAMS_LEN: usize = 8;
+
+/// A decoded image, ready to be painted.
+#[derive(Clone, Debug)]
+pub(crate) struct DecodedImage {
+    pub data: Arc<RenderImage>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DecodedImage {
+    fn from_rgba(width: u32, height: u32, mut pixels: Vec<u8>) -> Option<Self> {
+        if width == 0
+            || height == 0
+            || width as usize > MAX_IMAGE_DIMENSION
+            || height as usize > MAX_IMAGE_DIMENSION
+            || pixels.len() != width as usize * height as usize * 4
+        {
+            return None;
+        }
+
+        // Convert from RGBA to BGRA.
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        let buffer = RgbaImage::from_raw(width, height, pixels)?;
+        Some(Self {
+            data: Arc::new(RenderImage::new(SmallVec::from_elem(Frame::new(buffer), 1))),
+            width,
+            height,
+        })
+    }
+
+    fn from_rgb(width: u32, height: u32, pixels: &[u8]) -> Option<Self> {
+        if pixels.len() != width as usize * height as usize * 3 {
+            return None;
+        }
+        let pixels = pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect();
+        Self::from_rgba(width, height, pixels)
+    }
+
+    /// Decodes an image file, like a PNG or a JPEG.
+    fn from_encoded(data: &[u8]) -> Option<Self> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_DIMENSION as u32);
+        limits.max_image_height = Some(MAX_IMAGE_DIMENSION as u32);
+        limits.max_alloc = Some(MAX_IMAGE_BYTES as u64 * 2);
+
+        let mut reader = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .ok()?;
+        reader.limits(limits);
+        let image = reader.decode().ok()?.into_rgba8();
+        Self::from_rgba(image.width(), image.height(), image.into_raw())
+    }
+
+    #[cfg(test)]
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let bytes = self.data.as_bytes(0).unwrap();
+        let ix = (y * self.width + x) as usize * 4;
+        [bytes[ix + 2], bytes[ix + 1], bytes[ix], bytes[ix + 3]]
+    }
+}
+
+/// A length requested for an image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Dimension {
+    /// The length of the image itself.
+    #[default]
+    Auto,
+    Cells(u32),
+    Pixels(u32),
+    /// A percentage of the terminal's width or height.
+    Percent(u32),
+}
+
+/// The size an image is requested to be displayed at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ImageSize {
+    pub width: Dimension,
+    pub height: Dimension,
+    /// Whether to fit the image in the requested size, rather than stretch it.
+    pub preserve_aspect_ratio: bool,
+}
+
+impl Default for ImageSize {
+    fn default() -> Self {
+        Self {
+            width: Dimension::Auto,
+            height: Dimension::Auto,
+            preserve_aspect_ratio: true,
+        }
+    }
+}
+
+impl ImageSize {
+    fn from_cells(columns: u32, rows: u32) -> Self {
+        let dimension = |cells| match cells {
+            0 => Dimension::Auto,
+            cells => Dimension::Cells(cells),
+        };
+        Self {
+            width: dimension(columns),
+            height: dimension(rows),
+            preserve_aspect_ratio: false,
+        }
+    }
+
+    /// The size to paint the image at, with a pixel of the image taking a pixel of the terminal.
+    fn resolve(&self, image: &DecodedImage, bounds: &TerminalBounds) -> Size<Pixels> {
+        let resolve = |dimension, cell: Pixels, available: Pixels| match dimension {
+            Dimension::Auto => None,
+            Dimension::Cells(cells) => Some(cell * cells as f32),
+            Dimension::Pixels(pixels) => Some(px(pixels as f32)),
+            Dimension::Percent(percent) => Some(available * (percent.min(100) as f32 / 100.)),
+        };
+        let width = resolve(self.width, bounds.cell_width, bounds.width());
+        let height = resolve(self.height, bounds.line_height, bounds.height());
+
+        let image_width = px(image.width as f32);
+        let image_height = px(image.height as f32);
+        let (width, height) = match (width, height) {
+            (None, None) => (image_width, image_height),
+            (Some(width), None) => (width, image_height * (width / image_width)),
+            (None, Some(height)) => (image_width * (height / image_height), height),
+            (Some(width), Some(height)) if self.preserve_aspect_ratio => {
+                let scale = (width / image_width).min(height / image_height);
+                (image_width * scale, image_height * scale)
+            }
+            (Some(width), Some(height)) => (width, height),
+        };
+
+        // Images that keep their aspect ratio are shrunk to fit the terminal's width.
+        if self.preserve_aspect_ratio && width > bounds.width() && bounds.width() > px(0.) {
+            let scale = bounds.width() / width;
+            return size(bounds.width(), height * scale);
+        }
+        size(width, height)
+    }
+}
+
+/// Which images to remove from the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ImageDeletion {
+    /// The images on the screen.
+    Visible,
+    /// The images sent with the given Kitty image id.
+    Id(u32),
+    /// The images on the screen and in the scrollback.
+    All,
+}
+
+/// An image to add to the terminal, at the position of the cursor.
+#[derive(Clone, Debug)]
+pub(crate) struct NewPlacement {
+    pub image: Arc<RenderImage>,
+    pub image_id: Option<u32>,
+    pub size: Size<Pixels>,
+    pub rows: usize,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum ImageCommand {
+    Place(NewPlacement),
+    Delete(ImageDeletion),
+}
+
+/// Where the cursor ends up after an image is displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CursorMovement {
+    /// On the last row of the image, right after it, as Kitty and iTerm2 do.
+    AfterImage,
+    /// On the row below the image, as terminals that support Sixel do.
+    BelowImage,
+    None,
+}
+
+/// The PTY output after taking the images out of it.
+#[derive(Default)]
+pub(crate) struct FilteredOutput {
+    /// The output to pass on to Alacritty.
+    pub bytes: Vec<u8>,
+    /// Image commands, each along with the offset into `bytes` it was found at.
+    pub commands: VecDeque<(usize, ImageCommand)>,
+    /// Replies to write back to the program, which the protocols expect.
+    pub replies: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Protocol {
+    Kitty,
+    Sixel,
+    ITerm,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum FilterState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    DcsParams,
+    OscPrefix,
+    Payload(Protocol),
+    PayloadEscape(Protocol),
+}
+
+/// Incrementally takes image sequences out of the PTY output.
+///
+/// Every other byte is passed on untouched, so the filter only keeps track of
+/// escape sequences until it can tell they are not images. Screen clears are
+/// reported as well, since Alacritty has no way of telling that the images on
+/// the screen are gone.
+#[derive(Default)]
+pub(crate) struct ImageFilter {
+    state: FilterState,
+    prefix: Vec<u8>,
+    payload: Vec<u8>,
+    payload_overflowed: bool,
+    kitty: KittyGraphics,
+}
+
+impl ImageFilter {
+    pub fn filter(&mut self, input: &[u8], bounds: &TerminalBounds, output: &mut FilteredOutput) {
+        let mut ix = 0;
+        while ix < input.len() {
+            let byte = input[ix];
+            match self.state {
+                FilterState::Ground => {
+                    let end = input[ix..]
+                        .iter()
+                        .position(|&byte| byte == 0x1b)
+                        .map_or(input.len(), |position| ix + position);
+                    output.bytes.extend_from_slice(&input[ix..end]);
+                    if end < input.len() {
+                        self.state = FilterState::Escape;
+                    }
+                    ix = end + 1;
+                    continue;
+                }
+                FilterState::Escape => {
+                    self.prefix.clear();
+                    self.state = match byte {
+                        b'_' => self.start_payload(Protocol::Kitty),
+                        b'P' => FilterState::DcsParams,
+                        b']' => FilterState::OscPrefix,
+                        b'[' => {
+                            output.bytes.extend_from_slice(b"\x1b[");
+                            FilterState::Csi
+                        }
+                        0x1b => {
+                            output.bytes.push(0x1b);
+                            FilterState::Escape
+                        }
+                        _ => {
+                            output.bytes.extend_from_slice(&[0x1b, byte]);
+                            FilterState::Ground
+                        }
+                    };
+                }
+                FilterState::Csi => {
+                    if byte == 0x1b {
+                        self.state = FilterState::Escape;
+                    } else {
+                        output.bytes.push(byte);
+                        if (0x40..=0x7e).contains(&byte) {
+                            self.state = FilterState::Ground;
+                            if byte == b'J' {
+                                self.screen_cleared(output);
+                            }
+                        } else if self.prefix.len() < MAX_CSI_PARAMS_LEN {
+                            self.prefix.push(byte);
+                        }
+                    }
+                }
+                FilterState::DcsParams => match byte {
+                    // With intermediate bytes, as in `DCS $ q`, the sequence is something else.
+                    b'q' if !self.prefix.iter().any(|byte| (0x20..=0x2f).contains(byte)) => {
+                        self.state = self.start_payload(Protocol::Sixel)
+                    }
+                    0x20..=0x3f if self.prefix.len() < MAX_DCS_PARAMS_LEN => self.prefix.push(byte),
+                    _ => {
+                        // Not a Sixel image, leave the sequence to Alacritty.
+                        output.bytes.extend_from_slice(b"\x1bP");
+                        output.bytes.extend_from_slice(&self.prefix);
+                        self.state = FilterState::Ground;
+                        continue;
+                    }
+                },
+                FilterState::OscPrefix => {
+                    if ITERM_PREFIX.get(self.prefix.len()) == Some(&byte) {
+                        self.prefix.push(byte);
+                        if self.prefix.len() == ITERM_PREFIX.len() {
+                            self.state = self.start_payload(Protocol::ITerm);
+                        }
+                    } else {
+                        output.bytes.extend_from_slice(b"\x1b]");
+                        output.bytes.extend_from_slice(&self.prefix);
+                        self.state = FilterState::Ground;
+                        continue;
+                    }
+                }
+                FilterState::Payload(protocol) => {
+                    let end = input[ix..]
+                        .iter()
+                        .position(|&byte| matches!(byte, 0x07 | 0x18 | 0x1a | 0x1b))
+                        .map_or(input.len(), |position| ix + position);
+                    if self.payload.len() + (end - ix) > MAX_IMAGE_BYTES {
+                        self.payload_overflowed = true;
+                    } else {
+                        self.payload.extend_from_slice(&input[ix..end]);
+                    }
+                    ix = end;
+                    match input.get(end) {
+                        Some(0x1b) => self.state = FilterState::PayloadEscape(protocol),
+                        // BEL only terminates OSC sequences.
+                        Some(0x07) if protocol == Protocol::ITerm => {
+                            self.finish_payload(protocol, bounds, output)
+                        }
+                        Some(0x07) => {}
+                        // CAN and SUB cancel the sequence.
+                        Some(_) => self.state = FilterState::Ground,
+                        None => break,
+                    }
+                }
+                FilterState::PayloadEscape(protocol) => {
+                    if byte == b'\\' {
+                        self.finish_payload(protocol, bounds, output);
+                    } else {
+                        // Another escape sequence starting cancels the image.
+                        self.state = FilterState::Escape;
+                        continue;
+                    }
+                }
+            }
+            ix += 1;
+        }
+    }
+
+    fn start_payload(&mut self, protocol: Protocol) -> FilterState {
+        self.payload.clear();
+        self.payload_overflowed = false;
+        FilterState::Payload(protocol)
+    }
+
+    fn finish_payload(
+        &mut self,
+        protocol: Protocol,
+        bounds: &TerminalBounds,
+        output: &mut FilteredOutput,
+    ) {
+        self.state = FilterState::Ground;
+        if self.payload_overflowed {
+            return;
+        }
+        let payload = std::mem::take(&mut self.payload);
+
+        match protocol {
+            Protocol::Kitty => {
+                // Other APC sequences are dropped, as Alacritty would ignore them anyway.
+                let Some(sequence) = payload.strip_prefix(b"G") else {
+                    return;
+                };
+                let response = self.kitty.handle(sequence);
+                output.replies.extend(response.reply);
+                if let Some(deletion) = response.deletion {
+                    output
+                        .commands
+                        .push_back((output.bytes.len(), ImageCommand::Delete(deletion)));
+                }
+                if let Some(placement) = response.placement {
+                    let movement = if placement.move_cursor {
+                        CursorMovement::AfterImage
+                    } else {
+                        CursorMovement::None
+                    };
+                    let image_id = (placement.image_id != 0).then_some(placement.image_id);
+                    place(
+                        &placement.image,
+                        image_id,
+                        placement.size,
+                        movement,
+                        bounds,
+                        output,
+                    );
+                }
+            }
+            Protocol::Sixel => {
+                if let Some(image) = sixel::decode(&payload) {
+                    let size = ImageSize::default();
+                    place(
+                        &image,
+                        None,
+                        size,
+                        CursorMovement::BelowImage,
+                        bounds,
+                        output,
+                    );
+                }
+            }
+            Protocol::ITerm => {
+                if let Some(inline_image) = iterm::parse(&payload) {
+                    place(
+                        &inline_image.image,
+                        None,
+                        inline_image.size,
+                        CursorMovement::AfterImage,
+                        bounds,
+                        output,
+                    );
+                }
+            }
+        }
+    }
+
+    fn screen_cleared(&mut self, output: &mut FilteredOutput) {
+        let deletion = match self.prefix.as_slice() {
+            b"2" => ImageDeletion::Visible,
+            b"3" => ImageDeletion::All,
+            _ => return,
+        };
+        output
+            .commands
+            .push_back((output.bytes.len(), ImageCommand::Delete(deletion)));
+    }
+}
+
+fn place(
+    image: &DecodedImage,
+    image_id: Option<u32>,
+    size: ImageSize,
+    movement: CursorMovement,
+    bounds: &TerminalBounds,
+    output: &mut FilteredOutput,
+) {
+    let size = size.resolve(image, bounds);
+    let columns = (size.width / bounds.cell_width).ceil().max(1.) as usize;
+    let rows = ((size.height / bounds.line_height).ceil().max(1.) as usize).min(MAX_IMAGE_ROWS);
+
+    output.commands.push_back((
+        output.bytes.len(),
+        ImageCommand::Place(NewPlacement {
+            image: image.data.clone(),
+            image_id,
+            size,
+            rows,
+        }),
+    ));
+
+    // Line feeds move the cursor down without returning it to the first column,
+    // and scroll the screen when it is at the bottom, just like the image would.
+    match movement {
+        CursorMovement::AfterImage => {
+            output.bytes.extend(std::iter::repeat(b'\n').take(rows - 1));
+            output
+                .bytes
+                .extend_from_slice(format!("\x1b[{columns}C").as_bytes());
+        }
+        CursorMovement::BelowImage => {
+            output.bytes.extend(std::iter::repeat(b'\n').take(rows));
+        }
+        CursorMovement::None => {}
+    }
+}
+
+/// An image shown in the terminal, anchored to the cell its top left corner is in.
+#[derive(Clone, Debug)]
+pub(crate) struct ImagePlacement {
+    pub image: Arc<RenderImage>,
+    pub position: GridPosition,
+    pub size: Size<Pixels>,
+    pub rows: usize,
+    image_id: Option<u32>,
+    /// Images shown by full screen applications are only kept while they run.
+    alt_screen: bool,
+}
+
+/// The images shown in a terminal, in the order they were added.
+#[derive(Default)]
+pub(crate) struct ImagePlacements {
+    placements: VecDeque<ImagePlacement>,
+    /// Images no placement shows anymore, which the window has to release from its sprite atlas.
+    evicted: Vec<Arc<RenderImage>>,
+}
+
+impl ImagePlacements {
+    /// Applies an image command, found at the given position of the grid.
+    ///
+    /// `screen_top` is the row the screen starts at, below the scrollback.
+    pub(crate) fn handle_command(
+        &mut self,
+        command: ImageCommand,
+        position: GridPosition,
+        alt_screen: bool,
+        screen_top: usize,
+    ) {
+        match command {
+            ImageCommand::Place(placement) => {
+                self.placements.push_back(ImagePlacement {
+                    image: placement.image,
+                    position,
+                    size: placement.size,
+                    rows: placement.rows,
+                    image_id: placement.image_id,
+                    alt_screen,
+                });
+                if self.placements.len() > MAX_IMAGE_PLACEMENTS {
+                    if let Some(placement) = self.placements.pop_front() {
+                        self.evict(placement.image);
+                    }
+                }
+            }
+            ImageCommand::Delete(ImageDeletion::Visible) => self.retain(|placement| {
+                placement.alt_screen != alt_screen
+                    || placement.position.row + placement.rows <= screen_top
+            }),
+            ImageCommand::Delete(ImageDeletion::Id(image_id)) => {
+                self.retain(|placement| placement.image_id != Some(image_id))
+            }
+            ImageCommand::Delete(ImageDeletion::All) => {
+                self.retain(|placement| placement.alt_screen != alt_screen)
+            }
+        }
+    }
+
+    /// Moves the images of the main screen up, dropping those that leave the scrollback.
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.retain(|placement| {
+            if placement.alt_screen {
+                true
+            } else if placement.position.row >= lines {
+                placement.position.row -= lines;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    /// Drops the images shown by a full screen application, once it has exited.
+    pub fn leave_alt_screen(&mut self) {
+        self.retain(|placement| !placement.alt_screen);
+    }
+
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+
+    /// Takes the images that were dropped since the last call.
+    pub fn take_evicted(&mut self) -> Vec<Arc<RenderImage>> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    /// Every image still shown, or dropped without having been released yet.
+    pub fn all_images(&self) -> impl Iterator<Item = &Arc<RenderImage>> {
+        self.placements
+            .iter()
+            .map(|placement| &placement.image)
+            .chain(&self.evicted)
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&mut ImagePlacement) -> bool) {
+        let mut removed = Vec::new();
+        self.placements.retain_mut(|placement| {
+            let kept = keep(placement);
+            if !kept {
+                removed.push(placement.image.clone());
+            }
+            kept
+        });
+        for image in removed {
+            self.evict(image);
+        }
+    }
+
+    /// Kitty images can be placed several times, so they are only released with their last placement.
+    fn evict(&mut self, image: Arc<RenderImage>) {
+        let still_shown = self
+            .placements
+            .iter()
+            .any(|placement| Arc::ptr_eq(&placement.image, &image));
+        let already_evicted = self
+            .evicted
+            .iter()
+            .any(|evicted| Arc::ptr_eq(evicted, &image));
+        if !still_shown && !already_evicted {
+            self.evicted.push(image);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    /// The images of the main screen, or of the alternate one.
+    pub fn iter(&self, alt_screen: bool) -> impl Iterator<Item = &ImagePlacement> {
+        self.placements
+            .iter()
+            .filter(move |placement| placement.alt_screen == alt_screen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+    use gpui::{point, Bounds};
+
+    fn bounds() -> TerminalBounds {
+        TerminalBounds::new(
+            px(10.),
+            px(5.),
+            Bounds::new(point(px(0.), px(0.)), size(px(400.), px(200.))),
+        )
+    }
+
+    fn filter(filter: &mut ImageFilter, input: &[u8]) -> FilteredOutput {
+        let mut output = FilteredOutput::default();
+        filter.filter(input, &bounds(), &mut output);
+        output
+    }
+
+    fn placed_rows(output: &FilteredOutput) -> Vec<(usize, usize)> {
+        output
+            .commands
+            .iter()
+            .filter_map(|(offset, command)| match command {
+                ImageCommand::Place(placement) => Some((*offset, placement.rows)),
+                ImageCommand::Delete(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_filtering_kitty_images() {
+        let mut image_filter = ImageFilter::default();
+        // A 10x25 pixel image, taking two columns and three rows.
+        let pixels = BASE64.encode(vec![255; 10 * 25 * 4]);
+        let input = format!("ab\x1b_Ga=T,f=32,s=10,v=25;{pixels}\x1b\\cd\x1b[31me");
+        let output = filter(&mut image_filter, input.as_bytes());
+
+        assert_eq!(output.bytes, b"ab\n\n\x1b[2Ccd\x1b[31me");
+        assert_eq!(placed_rows(&output), vec![(2, 3)]);
+        assert!(output.replies.is_empty());
+    }
+
+    #[test]
+    fn test_filtering_sequences_split_across_reads() {
+        let mut image_filter = ImageFilter::default();
+        let input = b"a\x1bPq#0;2;0;0;0#0~~\x1b\\b\x1b]0;title\x07c\x1bP$q m\x1b\\";
+
+        let mut bytes = Vec::new();
+        let mut placements = Vec::new();
+        for chunk in input.chunks(3) {
+            let output = filter(&mut image_filter, chunk);
+            placements.extend(placed_rows(&output));
+            bytes.extend(output.bytes);
+        }
+
+        // The Sixel image is six pixels high, which fits in a row, and the cursor moves below it.
+        // Other OSC and DCS sequences are passed on.
+        assert_eq!(bytes, b"a\nb\x1b]0;title\x07c\x1bP$q m\x1b\\");
+        assert_eq!(placements.len(), 1);
+    }
+
+    #[test]
+    fn test_filtering_screen_clears() {
+        let mut image_filter = ImageFilter::default();
+        let output = filter(&mut image_filter, b"\x1b[H\x1b[2J\x1b[3J\x1b[J");
+
+        assert_eq!(output.bytes, b"\x1b[H\x1b[2J\x1b[3J\x1b[J");
+        let deletions = output
+            .commands
+            .iter()
+            .map(|(offset, command)| match command {
+                ImageCommand::Delete(deletion) => (*offset, *deletion),
+                ImageCommand::Place(_) => panic!("unexpected placement"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deletions,
+            vec![(7, ImageDeletion::Visible), (11, ImageDeletion::All)]
+        );
+    }
+
+    #[test]
+    fn test_resolving_image_sizes() {
+        let image = DecodedImage::from_rgba(100, 50, vec![0; 100 * 50 * 4]).unwrap();
+        let bounds = bounds();
+
+        assert_eq!(
+            ImageSize::default().resolve(&image, &bounds),
+            size(px(100.), px(50.))
+        );
+        assert_eq!(
+            ImageSize::from_cells(10, 0).resolve(&image, &bounds),
+            size(px(50.), px(25.))
+        );
+        assert_eq!(
+            ImageSize::from_cells(10, 10).resolve(&image, &bounds),
+            size(px(50.), px(100.))
+        );
+        let fitted = ImageSize {
+            width: Dimension::Percent(50),
+            height: Dimension::Pixels(20),
+            preserve_aspect_ratio: true,
+        };
+        assert_eq!(fitted.resolve(&image, &bounds), size(px(40.), px(20.)));
+    }
+
+    #[test]
+    fn test_placements_scroll_and_deletion() {
+        let mut placements = ImagePlacements::default();
+        let image = DecodedImage::from_rgba(1, 1, vec![0; 4]).unwrap();
+        let place = |image_id| {
+            ImageCommand::Place(NewPlacement {
+                image: image.data.clone(),
+                image_id,
+                size: size(px(1.), px(1.)),
+                rows: 2,
+            })
+        };
+
+        placements.handle_command(place(Some(1)), GridPosition::new(1, 0), false, 0);
+        placements.handle_command(place(Some(2)), GridPosition::new(10, 0), false, 0);
+        placements.handle_command(place(None), GridPosition::new(0, 0), true, 0);
+
+        placements.scroll_up(5);
+        let rows = placements
+            .iter(false)
+            .map(|placement| placement.position.row)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![5]);
+        assert_eq!(placements.iter(true).count(), 1);
+
+        placements.handle_command(
+            ImageCommand::Delete(ImageDeletion::Id(2)),
+            GridPosition::new(0, 0),
+            false,
+            0,
+        );
+        assert_eq!(placements.iter(false).count(), 0);
+
+        placements.leave_alt_screen();
+        assert!(placements.is_empty());
+    }
+
+    #[test]
+    fn test_evicted_images() {
+        let mut placements = ImagePlacements::default();
+        let shared = DecodedImage::from_rgba(1, 1, vec![0; 4]).unwrap();
+        let other = DecodedImage::from_rgba(1, 1, vec![0; 4]).unwrap();
+        let place = |image: &DecodedImage| {
+            ImageCommand::Place(NewPlacement {
+                image: image.data.clone(),
+                image_id: None,
+                size: size(px(1.), px(1.)),
+                rows: 1,
+            })
+        };
+
+        placements.handle_command(place(&shared), GridPosition::new(0, 0), false, 0);
+        placements.handle_command(place(&shared), GridPosition::new(4, 0), false, 0);
+        placements.handle_command(place(&other), GridPosition::new(8, 0), false, 0);
+
+        // The shared image is still shown further down.
+        placements.scroll_up(2);
+        assert!(placements.take_evicted().is_empty());
+
+        placements.scroll_up(4);
+        let evicted = placements.take_evicted();
+        assert_eq!(evicted.len(), 1);
+        assert!(Arc::ptr_eq(&evicted[0], &shared.data));
+
+        placements.clear();
+        let evicted = placements.take_evicted();
+        assert_eq!(evicted.len(), 1);
+        assert!(Arc::ptr_eq(&evicted[0], &other.data));
+        assert_eq!(placements.all_images().count(), 0);
+
+        for row in 0..=MAX_IMAGE_PLACEMENTS {
+            let image = DecodedImage::from_rgba(1, 1, vec![0; 4]).unwrap();
+            placements.handle_command(place(&image), GridPosition::new(row, 0), false, 0);
+        }
+        assert_eq!(placements.take_evicted().len(), 1);
+    }
+}
@@ -0,0 +1,73 @@
+//! The iTerm2 inline images protocol, sent in `OSC 1337 ; File = <arguments> : <data> ST` sequences.
+//!
+//! See <https://iterm2.com/documentation-images.html>.
+
+use super::{DecodedImage, Dimension, ImageSize};
+use base64::Engine as _;
+
+/// An image to display at the cursor.
+#[derive(Debug)]
+pub(super) struct InlineImage {
+    pub image: DecodedImage,
+    pub size: ImageSize,
+}
+
+/// Parses what follows `1337;File=` in the OSC sequence.
+///
+/// Files sent without `inline=1` are meant to be downloaded, which is not supported.
+pub(super) fn parse(sequence: &[u8]) -> Option<InlineImage> {
+    let separator = sequence.iter().position(|&byte| byte == b':')?;
+    let arguments = std::str::from_utf8(&sequence[..separator]).ok()?;
+    let data = &sequence[separator + 1..];
+
+    let mut inline = false;
+    let mut size = ImageSize::default();
+    for argument in arguments.split(';') {
+        let Some((key, value)) = argument.split_once('=') else {
+            continue;
+        };
+        match key {
+            "inline" => inline = value == "1",
+            "width" => size.width = parse_dimension(value)?,
+            "height" => size.height = parse_dimension(value)?,
+            "preserveAspectRatio" => size.preserve_aspect_ratio = value != "0",
+            _ => {}
+        }
+    }
+    if !inline {
+        return None;
+    }
+
+    let data = super::BASE64.decode(data).ok()?;
+    let image = DecodedImage::from_encoded(&data)?;
+    Some(InlineImage { image, size })
+}
+
+fn parse_dimension(value: &str) -> Option<Dimension> {
+    if value == "auto" {
+        Some(Dimension::Auto)
+    } else if let Some(pixels) = value.strip_suffix("px") {
+        pixels.parse().ok().map(Dimension::Pixels)
+    } else if let Some(percent) = value.strip_suffix('%') {
+        percent.parse().ok().map(Dimension::Percent)
+    } else {
+        value.parse().ok().map(Dimension::Cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_inline_image_arguments() {
+        assert_eq!(parse_dimension("auto"), Some(Dimension::Auto));
+        assert_eq!(parse_dimension("10"), Some(Dimension::Cells(10)));
+        assert_eq!(parse_dimension("120px"), Some(Dimension::Pixels(120)));
+        assert_eq!(parse_dimension("50%"), Some(Dimension::Percent(50)));
+        assert_eq!(parse_dimension("wide"), None);
+
+        // Images that are not inline are downloads.
+        assert!(parse(b"name=aW1hZ2U=;size=3:AAAA").is_none());
+    }
+}
@@ -0,0 +1,372 @@
+//! The Kitty graphics protocol, sent in `APC G <control data> ; <payload> ST` sequences.
+//!
+//! See <https://sw.kovidgoyal.net/kitty/graphics-protocol/>. Only images transmitted
+//! directly in the escape codes are supported, as reading them from files or shared
+//! memory would let anything printed to the terminal read local files.
+
+use super::{DecodedImage, ImageDeletion, ImageSize, MAX_IMAGE_BYTES};
+use base64::Engine as _;
+use collections::{HashMap, VecDeque};
+use std::io::Read as _;
+
+/// The number of transmitted images kept around for later placements.
+const MAX_STORED_IMAGES: usize = 64;
+
+/// The control data of a graphics command, with the protocol's defaults for omitted keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Command {
+    action: u8,
+    format: u32,
+    medium: u8,
+    compressed: bool,
+    width: u32,
+    height: u32,
+    image_id: u32,
+    image_number: u32,
+    more: bool,
+    quiet: u32,
+    columns: u32,
+    rows: u32,
+    move_cursor: bool,
+    delete: u8,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Self {
+            action: b't',
+            format: 32,
+            medium: b'd',
+            compressed: false,
+            width: 0,
+            height: 0,
+            image_id: 0,
+            image_number: 0,
+            more: false,
+            quiet: 0,
+            columns: 0,
+            rows: 0,
+            move_cursor: true,
+            delete: b'a',
+        }
+    }
+}
+
+impl Command {
+    fn parse(control_data: &[u8]) -> Self {
+        let mut command = Self::default();
+        for pair in control_data.split(|&byte| byte == b',') {
+            let Some((&key, value)) = pair.split_first() else {
+                continue;
+            };
+            let Some(value) = value.strip_prefix(b"=") else {
+                continue;
+            };
+            let number = || {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .unwrap_or(0)
+            };
+            let character = value.first().copied().unwrap_or(0);
+            match key {
+                b'a' => command.action = character,
+                b'f' => command.format = number(),
+                b't' => command.medium = character,
+                b'o' => command.compressed = character == b'z',
+                b's' => command.width = number(),
+                b'v' => command.height = number(),
+                b'i' => command.image_id = number(),
+                b'I' => command.image_number = number(),
+                b'm' => command.more = number() == 1,
+                b'q' => command.quiet = number(),
+                b'c' => command.columns = number(),
+                b'r' => command.rows = number(),
+                b'C' => command.move_cursor = number() != 1,
+                b'd' => command.delete = character,
+                _ => {}
+            }
+        }
+        command
+    }
+
+    fn key(&self) -> u32 {
+        if self.image_id != 0 {
+            self.image_id
+        } else {
+            self.image_number
+        }
+    }
+
+    fn reply(&self, result: &Result<(), String>) -> Option<Vec<u8>> {
+        if self.image_id == 0 && self.image_number == 0 {
+            return None;
+        }
+        let message = match result {
+            Ok(()) if self.quiet == 0 => "OK",
+            Ok(()) => return None,
+            Err(error) if self.quiet < 2 => error.as_str(),
+            Err(_) => return None,
+        };
+        let mut keys = Vec::new();
+        if self.image_id != 0 {
+            keys.push(format!("i={}", self.image_id));
+        }
+        if self.image_number != 0 {
+            keys.push(format!("I={}", self.image_number));
+        }
+        Some(format!("\x1b_G{};{}\x1b\\", keys.join(","), message).into_bytes())
+    }
+}
+
+/// What the terminal should do in response to a graphics command.
+#[derive(Debug, Default)]
+pub(super) struct Response {
+    pub placement: Option<Placement>,
+    pub deletion: Option<ImageDeletion>,
+    pub reply: Option<Vec<u8>>,
+}
+
+/// An image to display at the cursor.
+#[derive(Debug)]
+pub(super) struct Placement {
+    pub image: DecodedImage,
+    pub image_id: u32,
+    pub size: ImageSize,
+    pub move_cursor: bool,
+}
+
+/// A transmission split over several commands, which is only complete once its last chunk arrives.
+struct Transmission {
+    command: Command,
+    payload: Vec<u8>,
+    overflowed: bool,
+}
+
+#[derive(Default)]
+pub(super) struct KittyGraphics {
+    images: HashMap<u32, DecodedImage>,
+    image_order: VecDeque<u32>,
+    transmission: Option<Transmission>,
+}
+
+impl KittyGraphics {
+    /// Handles the contents of an APC sequence, without the leading `G`.
+    pub fn handle(&mut self, sequence: &[u8]) -> Response {
+        let (control_data, payload) = match sequence.iter().position(|&byte| byte == b';') {
+            Some(ix) => (&sequence[..ix], &sequence[ix + 1..]),
+            None => (sequence, &[][..]),
+        };
+        let command = Command::parse(control_data);
+
+        if let Some(mut transmission) = self.transmission.take() {
+            // Chunks after the first only carry the `m` key, and the rest comes from the first one.
+            if transmission.payload.len() + payload.len() > MAX_IMAGE_BYTES {
+                transmission.overflowed = true;
+            } else {
+                transmission.payload.extend_from_slice(payload);
+            }
+            if command.more {
+                self.transmission = Some(transmission);
+                return Response::default();
+            }
+            if transmission.overflowed {
+                let reply = transmission
+                    .command
+                    .reply(&Err("EFBIG:image data is too large".into()));
+                return Response {
+                    reply,
+                    ..Default::default()
+                };
+            }
+            return self.finish(transmission.command, &transmission.payload);
+        }
+
+        match command.action {
+            b't' | b'T' | b'q' if command.more => {
+                self.transmission = Some(Transmission {
+                    command,
+                    payload: payload.to_vec(),
+                    overflowed: payload.len() > MAX_IMAGE_BYTES,
+                });
+                Response::default()
+            }
+            b't' | b'T' | b'q' => self.finish(command, payload),
+            b'p' => {
+                let result = self
+                    .images
+                    .get(&command.key())
+                    .cloned()
+                    .ok_or_else(|| "ENOENT:no image with this id".to_string());
+                let reply = command.reply(&result.as_ref().map(|_| ()).map_err(Clone::clone));
+                Response {
+                    placement: result.ok().map(|image| Placement {
+                        size: ImageSize::from_cells(command.columns, command.rows),
+                        image_id: command.key(),
+                        move_cursor: command.move_cursor,
+                        image,
+                    }),
+                    reply,
+                    ..Default::default()
+                }
+            }
+            b'd' => {
+                let deletion = match command.delete {
+                    b'a' | b'A' => Some(ImageDeletion::Visible),
+                    b'i' | b'I' => Some(ImageDeletion::Id(command.key())),
+                    _ => None,
+                };
+                if command.delete.is_ascii_uppercase() {
+                    match deletion {
+                        Some(ImageDeletion::Id(key)) => self.forget(key),
+                        _ => {
+                            self.images.clear();
+                            self.image_order.clear();
+                        }
+                    }
+                }
+                Response {
+                    deletion,
+                    ..Default::default()
+                }
+            }
+            _ => Response::default(),
+        }
+    }
+
+    fn finish(&mut self, command: Command, payload: &[u8]) -> Response {
+        let image = decode(&command, payload);
+        let reply = command.reply(&image.as_ref().map(|_| ()).map_err(Clone::clone));
+        let Ok(image) = image else {
+            return Response {
+                reply,
+                ..Default::default()
+            };
+        };
+
+        if command.action == b'q' {
+            return Response {
+                reply,
+                ..Default::default()
+            };
+        }
+
+        let key = command.key();
+        if key != 0 {
+            self.store(key, image.clone());
+        }
+        Response {
+            placement: (command.action == b'T').then(|| Placement {
+                size: ImageSize::from_cells(command.columns, command.rows),
+                image_id: key,
+                move_cursor: command.move_cursor,
+                image,
+            }),
+            reply,
+            ..Default::default()
+        }
+    }
+
+    fn store(&mut self, key: u32, image: DecodedImage) {
+        if self.images.insert(key, image).is_none() {
+            self.image_order.push_back(key);
+        }
+        while self.image_order.len() > MAX_STORED_IMAGES {
+            if let Some(oldest) = self.image_order.pop_front() {
+                self.images.remove(&oldest);
+            }
+        }
+    }
+
+    fn forget(&mut self, key: u32) {
+        self.images.remove(&key);
+        self.image_order.retain(|stored| *stored != key);
+    }
+}
+
+fn decode(command: &Command, payload: &[u8]) -> Result<DecodedImage, String> {
+    if command.medium != b'd' {
+        return Err("EINVAL:only direct transmission is supported".into());
+    }
+
+    let mut data = super::BASE64
+        .decode(payload)
+        .map_err(|_| "EINVAL:invalid base64 data".to_string())?;
+    if command.compressed {
+        let mut decompressed = Vec::new();
+        flate2::read::ZlibDecoder::new(data.as_slice())
+            .take(MAX_IMAGE_BYTES as u64 + 1)
+            .read_to_end(&mut decompressed)
+            .map_err(|_| "EINVAL:invalid compressed data".to_string())?;
+        if decompressed.len() > MAX_IMAGE_BYTES {
+            return Err("EFBIG:image data is too large".into());
+        }
+        data = decompressed;
+    }
+
+    let image = match command.format {
+        100 => DecodedImage::from_encoded(&data),
+        24 => DecodedImage::from_rgb(command.width, command.height, &data),
+        32 => DecodedImage::from_rgba(command.width, command.height, data),
+        _ => return Err("EINVAL:unsupported format".into()),
+    };
+    image.ok_or_else(|| "EBADF:could not decode the image".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transmit(graphics: &mut KittyGraphics, control_data: &str, pixels: &[u8]) -> Response {
+        let payload = super::super::BASE64.encode(pixels);
+        graphics.handle(format!("{control_data};{payload}").as_bytes())
+    }
+
+    #[test]
+    fn test_transmitting_and_placing_images() {
+        let mut graphics = KittyGraphics::default();
+        let pixels = [255, 0, 0, 0, 255, 0];
+
+        let response = transmit(&mut graphics, "a=T,f=24,s=2,v=1,i=7,c=4", &pixels);
+        let placement = response.placement.unwrap();
+        assert_eq!((placement.image.width, placement.image.height), (2, 1));
+        assert_eq!(placement.image.pixel(1, 0), [0, 255, 0, 255]);
+        assert_eq!(placement.size, ImageSize::from_cells(4, 0));
+        assert_eq!(response.reply.unwrap(), b"\x1b_Gi=7;OK\x1b\\");
+
+        // The transmitted image can be placed again by its id.
+        let response = graphics.handle(b"a=p,i=7,q=1");
+        assert!(response.placement.is_some());
+        assert_eq!(response.reply, None);
+
+        let response = graphics.handle(b"a=p,i=8");
+        assert!(response.placement.is_none());
+        assert!(response.reply.unwrap().starts_with(b"\x1b_Gi=8;ENOENT"));
+    }
+
+    #[test]
+    fn test_chunked_transmission() {
+        let mut graphics = KittyGraphics::default();
+        let payload = super::super::BASE64.encode([1, 2, 3, 255, 4, 5, 6, 255]);
+        let (first, second) = payload.split_at(4);
+
+        let response = graphics.handle(format!("a=T,f=32,s=1,v=2,m=1;{first}").as_bytes());
+        assert!(response.placement.is_none());
+        let response = graphics.handle(format!("m=0;{second}").as_bytes());
+        let placement = response.placement.unwrap();
+        assert_eq!(placement.image.pixel(0, 1), [4, 5, 6, 255]);
+        // Without an id, there is nobody to reply to.
+        assert_eq!(response.reply, None);
+    }
+
+    #[test]
+    fn test_queries_and_unsupported_media() {
+        let mut graphics = KittyGraphics::default();
+        let response = transmit(&mut graphics, "a=q,f=24,s=1,v=1,i=31", &[0, 0, 0]);
+        assert!(response.placement.is_none());
+        assert_eq!(response.reply.unwrap(), b"\x1b_Gi=31;OK\x1b\\");
+
+        let response = graphics.handle(b"a=q,t=f,i=31;L3RtcC9pbWFnZQ==");
+        assert!(response.reply.unwrap().starts_with(b"\x1b_Gi=31;EINVAL"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Decoding of Sixel images, as sent in `DCS Ps q <data> ST` sequences.
+
+use super::{DecodedImage, MAX_IMAGE_DIMENSION};
+
+/// The palette of the VT340, which programs rely on when they do not define their own colors.
+const DEFAULT_PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [20, 20, 80],
+    [80, 13, 13],
+    [20, 80, 20],
+    [80, 20, 80],
+    [20, 80, 80],
+    [80, 80, 20],
+    [53, 53, 53],
+    [26, 26, 26],
+    [33, 33, 60],
+    [60, 26, 26],
+    [33, 60, 33],
+    [60, 33, 60],
+    [33, 60, 60],
+    [60, 60, 33],
+    [80, 80, 80],
+];
+
+const PALETTE_SIZE: usize = 256;
+
+/// Decodes the data of a Sixel sequence, the part after the `q`.
+///
+/// Pixels that are never drawn are left transparent, whatever background
+/// the sequence's parameters ask for.
+pub(super) fn decode(data: &[u8]) -> Option<DecodedImage> {
+    let mut palette = [[0u8; 4]; PALETTE_SIZE];
+    for (color, rgb) in palette.iter_mut().zip(DEFAULT_PALETTE) {
+        *color = rgb_from_percentages(rgb[0] as u32, rgb[1] as u32, rgb[2] as u32);
+    }
+
+    let mut canvas = Canvas::default();
+    let mut color = 0;
+    let mut x = 0;
+    let mut band = 0;
+
+    let mut ix = 0;
+    while ix < data.len() {
+        let byte = data[ix];
+        ix += 1;
+        match byte {
+            b'"' => {
+                let (params, next_ix) = parse_params(data, ix);
+                ix = next_ix;
+                // Raster attributes: pixel aspect ratio, then the size of the image.
+                if let [_, _, width, height, ..] = params[..] {
+                    canvas.reserve(width as usize, height as usize)?;
+                }
+            }
+            b'#' => {
+                let (params, next_ix) = parse_params(data, ix);
+                ix = next_ix;
+                match params[..] {
+                    [index] => color = index as usize % PALETTE_SIZE,
+                    [index, space, a, b, c, ..] => {
+                        color = index as usize % PALETTE_SIZE;
+                        palette[color] = match space {
+                            1 => rgb_from_hls(a, b, c),
+                            _ => rgb_from_percentages(a, b, c),
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            b'!' => {
+                let (params, next_ix) = parse_params(data, ix);
+                ix = next_ix;
+                let count = params.first().copied().unwrap_or(1).max(1) as usize;
+                if let Some(&sixel @ 0x3f..=0x7e) = data.get(ix) {
+                    ix += 1;
+                    canvas.draw(x, band, sixel - 0x3f, count, palette[color])?;
+                    x += count;
+                }
+            }
+            b'$' => x = 0,
+            b'-' => {
+                x = 0;
+                band += 1;
+            }
+            0x3f..=0x7e => {
+                canvas.draw(x, band, byte - 0x3f, 1, palette[color])?;
+                x += 1;
+            }
+            _ => {}
+        }
+    }
+
+    canvas.into_image()
+}
+
+/// Parses the numeric parameters that follow a Sixel control character, like `#1;2;100;0;0`.
+fn parse_params(data: &[u8], mut ix: usize) -> (Vec<u32>, usize) {
+    let mut params = Vec::new();
+    let mut current: Option<u32> = None;
+    while let Some(&byte) = data.get(ix) {
+        match byte {
+            b'0'..=b'9' => {
+                let digit = (byte - b'0') as u32;
+                current = Some(
+                    current
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+            }
+            b';' => params.push(current.take().unwrap_or(0)),
+            _ => break,
+        }
+        ix += 1;
+    }
+    if let Some(current) = current {
+        params.push(current);
+    }
+    (params, ix)
+}
+
+fn rgb_from_percentages(r: u32, g: u32, b: u32) -> [u8; 4] {
+    let channel = |value: u32| (value.min(100) * 255 / 100) as u8;
+    [channel(r), channel(g), channel(b), 255]
+}
+
+/// Converts a DEC HLS color, whose hue starts at blue rather than red.
+fn rgb_from_hls(hue: u32, lightness: u32, saturation: u32) -> [u8; 4] {
+    let hue = ((hue % 360 + 240) % 360) as f32 / 360.;
+    let lightness = lightness.min(100) as f32 / 100.;
+    let saturation = saturation.min(100) as f32 / 100.;
+
+    let q = if lightness < 0.5 {
+        lightness * (1. + saturation)
+    } else {
+        lightness + saturation - lightness * saturation
+    };
+    let p = 2. * lightness - q;
+    let channel = |t: f32| {
+        let t = t.rem_euclid(1.);
+        let value = if t < 1. / 6. {
+            p + (q - p) * 6. * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2. / 3. {
+            p + (q - p) * (2. / 3. - t) * 6.
+        } else {
+            p
+        };
+        (value * 255.).round() as u8
+    };
+    [
+        channel(hue + 1. / 3.),
+        channel(hue),
+        channel(hue - 1. / 3.),
+        255,
+    ]
+}
+
+/// Pixels drawn so far, growing as the image is drawn past its current size.
+#[derive(Default)]
+struct Canvas {
+    rows: Vec<Vec<[u8; 4]>>,
+    width: usize,
+    raster_height: usize,
+}
+
+impl Canvas {
+    fn reserve(&mut self, width: usize, height: usize) -> Option<()> {
+        if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+            return None;
+        }
+        self.width = self.width.max(width);
+        self.raster_height = self.raster_height.max(height);
+        Some(())
+    }
+
+    fn draw(
+        &mut self,
+        x: usize,
+        band: usize,
+        sixel: u8,
+        count: usize,
+        color: [u8; 4],
+    ) -> Option<()> {
+        let end = x + count;
+        if end > MAX_IMAGE_DIMENSION || (band + 1) * 6 > MAX_IMAGE_DIMENSION {
+            return None;
+        }
+        self.width = self.width.max(end);
+        for bit in 0..6 {
+            if sixel & (1 << bit) == 0 {
+                continue;
+            }
+            let y = band * 6 + bit;
+            if self.rows.len() <= y {
+                self.rows.resize_with(y + 1, Vec::new);
+            }
+            let row = &mut self.rows[y];
+            if row.len() < end {
+                row.resize(end, [0; 4]);
+            }
+            row[x..end].fill(color);
+        }
+        Some(())
+    }
+
+    fn into_image(self) -> Option<DecodedImage> {
+        let height = self.rows.len().max(self.raster_height);
+        if self.width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut pixels = Vec::with_capacity(self.width * height * 4);
+        for y in 0..height {
+            let row = self.rows.get(y).map(Vec::as_slice).unwrap_or_default();
+            for x in 0..self.width {
+                pixels.extend_from_slice(&row.get(x).copied().unwrap_or_default());
+            }
+        }
+        DecodedImage::from_rgba(self.width as u32, height as u32, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoding_sixels() {
+        // A red band three pixels wide, the last two drawn with a repeat.
+        let image = decode(b"#1;2;100;0;0#1~!2~-#1`").unwrap();
+        assert_eq!((image.width, image.height), (3, 12));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(2, 5), [255, 0, 0, 255]);
+        // In the second band, "`" only sets the top and the bottom pixels of the first column.
+        assert_eq!(image.pixel(0, 6), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(0, 7), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(0, 11), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 6), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sixel_colors() {
+        assert_eq!(rgb_from_percentages(100, 50, 0), [255, 127, 0, 255]);
+        // DEC hues: blue at 0, red at 120 and green at 240.
+        assert_eq!(rgb_from_hls(0, 50, 100), [0, 0, 255, 255]);
+        assert_eq!(rgb_from_hls(120, 50, 100), [255, 0, 0, 255]);
+        assert_eq!(rgb_from_hls(240, 50, 100), [0, 255, 0, 255]);
+        // Hues past a full turn wrap around, however large they are.
+        assert_eq!(rgb_from_hls(480, 50, 100), [255, 0, 0, 255]);
+        assert_eq!(rgb_from_hls(u32::MAX, 50, 100), rgb_from_hls(135, 50, 100));
+
+        let image = decode(b"#1;1;99999999999;50;100~").unwrap();
+        assert_eq!(image.pixel(0, 0), rgb_from_hls(u32::MAX, 50, 100));
+    }
+}
@@ -0,0 +1,247 @@
+//! Reading the PTY output ahead of Alacritty, for the escape sequences it does not support.
+//!
+//! Inline images are taken out of the output and replaced with room for them in the grid,
+//! while shell integration sequences are only observed. Both are queued up along with their
+//! estimated position in the output, for [`Terminal`](crate::Terminal) to pick up on wakeup.
+
+use crate::{
+    images::{FilteredOutput, ImageCommand, ImageFilter},
+    shell_integration::{OutputScanner, PendingEvent},
+    TerminalBounds, ZedListener,
+};
+use alacritty_terminal::{
+    event::{Event as AlacTermEvent, EventListener, OnResize, WindowSize},
+    tty::{ChildEvent, EventedPty, EventedReadWrite, Pty},
+};
+use parking_lot::Mutex;
+use polling::{Event as PollingEvent, PollMode, Poller};
+use std::{
+    io::{self, Read},
+    sync::Arc,
+};
+
+const READ_BUFFER_SIZE: usize = 0x10000;
+
+/// An image command, along with the estimated output position it was found at.
+pub(crate) struct PendingImageCommand {
+    pub command: ImageCommand,
+    pub line: u64,
+    pub column: usize,
+}
+
+/// Everything intercepted since it was last taken.
+#[derive(Default)]
+pub(crate) struct Intercepted {
+    pub events: Vec<PendingEvent>,
+    pub image_commands: Vec<PendingImageCommand>,
+    /// Replies to write back to the PTY.
+    pub replies: Vec<Vec<u8>>,
+    /// The line count of all output read so far.
+    pub lines_read: u64,
+}
+
+#[derive(Default)]
+struct SharedState {
+    intercepted: Intercepted,
+    bounds: TerminalBounds,
+}
+
+/// State shared between the PTY reader thread and the [`Terminal`](crate::Terminal).
+#[derive(Clone, Default)]
+pub(crate) struct InterceptedOutput(Arc<Mutex<SharedState>>);
+
+impl InterceptedOutput {
+    /// Returns everything intercepted since the last call.
+    pub fn take(&self) -> Intercepted {
+        let mut state = self.0.lock();
+        let intercepted = std::mem::take(&mut state.intercepted);
+        state.intercepted.lines_read = intercepted.lines_read;
+        intercepted
+    }
+
+    pub fn has_pending(&self) -> bool {
+        let state = self.0.lock();
+        !state.intercepted.events.is_empty()
+            || !state.intercepted.image_commands.is_empty()
+            || !state.intercepted.replies.is_empty()
+    }
+
+    pub fn lines_read(&self) -> u64 {
+        self.0.lock().intercepted.lines_read
+    }
+
+    /// Updates the size of the terminal, which images are laid out against.
+    pub fn set_bounds(&self, bounds: TerminalBounds) {
+        self.0.lock().bounds = bounds;
+    }
+}
+
+/// Wraps the PTY to process everything Alacritty reads from it.
+pub(crate) struct InterceptingPty {
+    pty: Pty,
+    state: InterceptedOutput,
+    listener: ZedListener,
+    filter: ImageFilter,
+    scanner: OutputScanner,
+    input: Vec<u8>,
+    output: FilteredOutput,
+    consumed: usize,
+}
+
+impl InterceptingPty {
+    pub fn new(pty: Pty, state: InterceptedOutput, listener: ZedListener) -> Self {
+        Self {
+            pty,
+            state,
+            listener,
+            filter: ImageFilter::default(),
+            scanner: OutputScanner::default(),
+            input: vec![0; READ_BUFFER_SIZE],
+            output: FilteredOutput::default(),
+            consumed: 0,
+        }
+    }
+
+    /// Hands the filtered output over to Alacritty.
+    ///
+    /// The output is scanned up to each image command before queueing it, so that
+    /// the command's position is exactly where the image was in the output.
+    fn take_output(&mut self, buf: &mut [u8]) -> usize {
+        let mut state = self.state.0.lock();
+        let SharedState {
+            intercepted,
+            bounds,
+        } = &mut *state;
+        self.scanner.set_columns(bounds.num_columns());
+
+        let commands_queued = intercepted.image_commands.len();
+        let replied = !self.output.replies.is_empty();
+        intercepted.replies.append(&mut self.output.replies);
+
+        let start = self.consumed;
+        let end = self.output.bytes.len().min(start + buf.len());
+        let mut scanned = start;
+        loop {
+            let command_offset = self
+                .output
+                .commands
+                .front()
+                .map(|(offset, _)| *offset)
+                .filter(|offset| *offset <= end);
+            let scan_end = command_offset.unwrap_or(end);
+            self.scanner.advance(
+                &self.output.bytes[scanned..scan_end],
+                |event, line, column| {
+                    intercepted.events.push(PendingEvent {
+                        event,
+                        line,
+                        column,
+                    })
+                },
+            );
+            scanned = scan_end;
+
+            let Some((_, command)) = command_offset.and_then(|_| self.output.commands.pop_front())
+            else {
+                break;
+            };
+            intercepted.image_commands.push(PendingImageCommand {
+                command,
+                line: self.scanner.line(),
+                column: self.scanner.column(),
+            });
+        }
+        intercepted.lines_read = self.scanner.line();
+        let wake_up = replied || intercepted.image_commands.len() > commands_queued;
+
+        let read = end - start;
+        buf[..read].copy_from_slice(&self.output.bytes[start..end]);
+        self.consumed = end;
+        if self.consumed == self.output.bytes.len() {
+            self.output.bytes.clear();
+            self.consumed = 0;
+        }
+        drop(state);
+
+        // Alacritty wakes the terminal up after parsing the output, but images and
+        // replies can come without any output for it to parse.
+        if wake_up && read == 0 {
+            self.listener.send_event(AlacTermEvent::Wakeup);
+        }
+        read
+    }
+
+    fn has_output(&self) -> bool {
+        self.consumed < self.output.bytes.len()
+            || !self.output.commands.is_empty()
+            || !self.output.replies.is_empty()
+    }
+}
+
+impl Read for InterceptingPty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.has_output() {
+                let read = self.take_output(buf);
+                if read > 0 {
+                    return Ok(read);
+                }
+            }
+
+            let read = self.pty.reader().read(&mut self.input)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            let bounds = self.state.0.lock().bounds;
+            self.filter
+                .filter(&self.input[..read], &bounds, &mut self.output);
+        }
+    }
+}
+
+impl EventedReadWrite for InterceptingPty {
+    type Reader = Self;
+    type Writer = <Pty as EventedReadWrite>::Writer;
+
+    unsafe fn register(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollingEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        self.pty.register(poll, interest, mode)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollingEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        self.pty.reregister(poll, interest, mode)
+    }
+
+    fn deregister(&mut self, poll: &Arc<Poller>) -> io::Result<()> {
+        self.pty.deregister(poll)
+    }
+
+    fn reader(&mut self) -> &mut Self::Reader {
+        self
+    }
+
+    fn writer(&mut self) -> &mut Self::Writer {
+        self.pty.writer()
+    }
+}
+
+impl EventedPty for InterceptingPty {
+    fn next_child_event(&mut self) -> Option<ChildEvent> {
+        self.pty.next_child_event()
+    }
+}
+
+impl OnResize for InterceptingPty {
+    fn on_resize(&mut self, window_size: WindowSize) {
+        self.pty.on_resize(window_size)
+    }
+}
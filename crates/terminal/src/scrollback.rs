@@ -0,0 +1,254 @@
+//! Copying the scrollback out of the terminal, along with how its text is styled.
+
+use std::ops::{Range, RangeInclusive};
+
+use alacritty_terminal::{
+    grid::Dimensions,
+    index::{Column, Line, Point as AlacPoint},
+    term::cell::{Cell, Flags},
+    vte::ansi::{Color, NamedColor},
+    Term,
+};
+
+use crate::shell_integration::GridPosition;
+
+/// The flags that change how text looks, rather than how it is laid out.
+const STYLE_FLAGS: Flags = Flags::BOLD
+    .union(Flags::ITALIC)
+    .union(Flags::DIM)
+    .union(Flags::INVERSE)
+    .union(Flags::ALL_UNDERLINES)
+    .union(Flags::STRIKEOUT);
+
+/// How a run of text is styled in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextStyle {
+    pub foreground: Color,
+    pub background: Color,
+    pub flags: Flags,
+}
+
+impl TextStyle {
+    fn of(cell: &Cell) -> Self {
+        Self {
+            foreground: cell.fg,
+            background: cell.bg,
+            flags: cell.flags & STYLE_FLAGS,
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.foreground == Color::Named(NamedColor::Foreground)
+            && self.background == Color::Named(NamedColor::Background)
+            && self.flags.is_empty()
+    }
+}
+
+/// A search match in a [`ScrollbackSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScrollbackMatch {
+    /// The byte range of the match in the snapshot's text.
+    pub range: Range<usize>,
+    /// Where the match is in the grid, for selecting it in the terminal.
+    pub start: GridPosition,
+    pub end: GridPosition,
+}
+
+/// The text of the scrollback and the screen, with the same row for each line of the grid.
+///
+/// Lines wrapped by the terminal are kept apart, so that rows of the text are rows of the grid.
+#[derive(Clone, Debug, Default)]
+pub struct ScrollbackSnapshot {
+    pub text: String,
+    /// The byte ranges of text that is not in the terminal's default style, in order.
+    pub styles: Vec<(Range<usize>, TextStyle)>,
+    pub matches: Vec<ScrollbackMatch>,
+}
+
+impl ScrollbackSnapshot {
+    pub(crate) fn new<T>(term: &Term<T>, matches: &[RangeInclusive<AlacPoint>]) -> Self {
+        let grid = term.grid();
+        let history_size = grid.history_size() as i32;
+        let columns = grid.columns();
+
+        // The ends of the matches, ordered by where they are in the grid.
+        let mut match_ends = matches
+            .iter()
+            .enumerate()
+            .flat_map(|(ix, search_match)| {
+                [
+                    (*search_match.start(), ix, false),
+                    (*search_match.end(), ix, true),
+                ]
+            })
+            .collect::<Vec<_>>();
+        match_ends.sort_by_key(|(point, _, _)| *point);
+        let mut match_ends = match_ends.into_iter().peekable();
+        let mut match_ranges = vec![0..0; matches.len()];
+
+        let mut snapshot = Self::default();
+        let mut column_offsets = Vec::with_capacity(columns + 1);
+        for line in term.topmost_line().0..=term.bottommost_line().0 {
+            let row_start = snapshot.text.len();
+            let row = &grid[Line(line)];
+            column_offsets.clear();
+            for column in 0..columns {
+                column_offsets.push(snapshot.text.len());
+                let cell = &row[Column(column)];
+                if cell
+                    .flags
+                    .intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+                {
+                    continue;
+                }
+
+                let start = snapshot.text.len();
+                snapshot.text.push(cell.c);
+                if let Some(zerowidth) = cell.zerowidth() {
+                    snapshot.text.extend(zerowidth);
+                }
+                snapshot.push_style(start..snapshot.text.len(), TextStyle::of(cell));
+            }
+
+            let row_end = row_start + snapshot.text[row_start..].trim_end().len();
+            snapshot.text.truncate(row_end);
+            snapshot.truncate_styles(row_end);
+            column_offsets.push(snapshot.text.len());
+            for offset in &mut column_offsets {
+                *offset = (*offset).min(row_end);
+            }
+
+            while let Some((point, ix, is_end)) =
+                match_ends.next_if(|(point, _, _)| point.line.0 <= line)
+            {
+                if point.line.0 < line {
+                    continue;
+                }
+                let column = point.column.0.min(columns - 1);
+                if is_end {
+                    match_ranges[ix].end = column_offsets[column + 1];
+                } else {
+                    match_ranges[ix].start = column_offsets[column];
+                }
+            }
+            snapshot.text.push('\n');
+        }
+
+        let text_end = snapshot.text.trim_end().len();
+        snapshot.text.truncate(text_end);
+        snapshot.truncate_styles(text_end);
+
+        let to_position = |point: AlacPoint| {
+            GridPosition::new(
+                (point.line.0 + history_size).max(0) as usize,
+                point.column.0,
+            )
+        };
+        snapshot.matches = matches
+            .iter()
+            .zip(match_ranges)
+            .filter(|(_, range)| range.start < range.end)
+            .map(|(search_match, range)| ScrollbackMatch {
+                range,
+                start: to_position(*search_match.start()),
+                end: to_position(*search_match.end()),
+            })
+            .collect();
+        snapshot
+    }
+
+    fn push_style(&mut self, range: Range<usize>, style: TextStyle) {
+        if style.is_default() {
+            return;
+        }
+        if let Some((last_range, last_style)) = self.styles.last_mut() {
+            if *last_style == style && last_range.end == range.start {
+                last_range.end = range.end;
+                return;
+            }
+        }
+        self.styles.push((range, style));
+    }
+
+    /// Drops the styles of text that was trimmed off, past `len`.
+    fn truncate_styles(&mut self, len: usize) {
+        while let Some((range, _)) = self.styles.last_mut() {
+            if range.start >= len {
+                self.styles.pop();
+            } else {
+                range.end = range.end.min(len);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacritty_terminal::{
+        event::VoidListener,
+        term::{test::TermSize, Config},
+        vte::ansi::Processor,
+    };
+
+    fn term_with_output(output: &str) -> Term<VoidListener> {
+        let size = TermSize::new(20, 3);
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        let mut parser: Processor = Processor::new();
+        parser.advance(&mut term, output.as_bytes());
+        term
+    }
+
+    #[test]
+    fn test_snapshot_text_and_styles() {
+        let term =
+            term_with_output("plain \x1b[31mred\x1b[0m\r\n\x1b[1mbold\x1b[0m text\r\nlast\r\nline");
+        let snapshot = ScrollbackSnapshot::new(&term, &[]);
+
+        assert_eq!(snapshot.text, "plain red\nbold text\nlast\nline");
+        assert_eq!(
+            snapshot
+                .styles
+                .iter()
+                .map(|(range, _)| &snapshot.text[range.clone()])
+                .collect::<Vec<_>>(),
+            ["red", "bold"]
+        );
+        assert_eq!(
+            snapshot.styles[0].1.foreground,
+            Color::Named(NamedColor::Red)
+        );
+        assert_eq!(snapshot.styles[1].1.flags, Flags::BOLD);
+    }
+
+    #[test]
+    fn test_snapshot_matches() {
+        let term = term_with_output("first match\r\nsecond\r\nmatch again\r\n");
+        // The first line has scrolled into the scrollback.
+        assert_eq!(term.grid().history_size(), 1);
+
+        let search_match =
+            AlacPoint::new(Line(-1), Column(6))..=AlacPoint::new(Line(-1), Column(10));
+        let other_match = AlacPoint::new(Line(1), Column(0))..=AlacPoint::new(Line(1), Column(4));
+        let snapshot = ScrollbackSnapshot::new(&term, &[other_match, search_match]);
+
+        let matches = snapshot
+            .matches
+            .iter()
+            .map(|search_match| {
+                (
+                    &snapshot.text[search_match.range.clone()],
+                    search_match.start,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            matches,
+            [
+                ("match", GridPosition::new(2, 0)),
+                ("match", GridPosition::new(0, 6)),
+            ]
+        );
+    }
+}
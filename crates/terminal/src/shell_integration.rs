@@ -0,0 +1,622 @@
+//! Shell integration: command boundaries reported by the shell through the
+//! OSC 133 semantic prompt protocol, and working directories reported through OSC 7.
+//!
+//! Alacritty discards both sequences, so the PTY output is scanned before it reaches
+//! the parser. The scanner also estimates on which line of the output each sequence
+//! was found, which lets [`Terminal`](crate::Terminal) place the markers in the grid
+//! even when several of them arrive in a single batch.
+
+use collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use task::Shell;
+use util::ResultExt;
+
+const BASH_SCRIPT: &str = include_str!("shell_integration/zed.bash");
+const ZSH_SCRIPT: &str = include_str!("shell_integration/zed.zsh");
+const ZSH_ENV: &str = include_str!("shell_integration/zshenv.zsh");
+const ZSH_PROFILE: &str = include_str!("shell_integration/zprofile.zsh");
+const ZSH_RC: &str = include_str!("shell_integration/zshrc.zsh");
+const ZSH_LOGIN: &str = include_str!("shell_integration/zlogin.zsh");
+const FISH_SCRIPT: &str = include_str!("shell_integration/zed.fish");
+
+/// OSC payloads longer than this are not shell integration sequences (e.g. OSC 52 clipboard
+/// writes), and are skipped instead of being buffered.
+const MAX_OSC_LEN: usize = 4096;
+/// How many commands are remembered per terminal.
+const MAX_COMMAND_BLOCKS: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShellIntegrationEvent {
+    /// `OSC 133 ; A`: the shell is about to print the prompt.
+    PromptStart,
+    /// `OSC 133 ; B`: the prompt has been printed, and the user is typing a command.
+    CommandStart,
+    /// `OSC 133 ; C`: the command was submitted, and its output follows.
+    CommandExecuted,
+    /// `OSC 133 ; D [; exit code]`: the command has finished.
+    CommandFinished { exit_code: Option<i32> },
+    /// `OSC 7 ; file://host/path`: the shell's current working directory.
+    WorkingDirectory(PathBuf),
+}
+
+impl ShellIntegrationEvent {
+    fn parse(payload: &[u8]) -> Option<Self> {
+        let payload = std::str::from_utf8(payload).ok()?;
+        if let Some(marker) = payload.strip_prefix("133;") {
+            let mut params = marker.split(';');
+            match params.next()? {
+                "A" => Some(Self::PromptStart),
+                "B" => Some(Self::CommandStart),
+                "C" => Some(Self::CommandExecuted),
+                "D" => Some(Self::CommandFinished {
+                    exit_code: params.next().and_then(|code| code.trim().parse().ok()),
+                }),
+                _ => None,
+            }
+        } else if let Some(url) = payload.strip_prefix("7;") {
+            let location = url.strip_prefix("file://")?;
+            // Skip the host name, the path starts at the first slash.
+            let path = &location[location.find('/')?..];
+            Some(Self::WorkingDirectory(PathBuf::from(percent_decode(path))))
+        } else {
+            None
+        }
+    }
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ix = 0;
+    while ix < bytes.len() {
+        if bytes[ix] == b'%' && ix + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[ix + 1..ix + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                ix += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[ix]);
+        ix += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+/// Incrementally scans PTY output for shell integration sequences.
+///
+/// Besides the sequences themselves, the scanner keeps a rough count of the lines
+/// the output has advanced by (line feeds and soft wraps), so that every event can
+/// be mapped back to a grid line once Alacritty has processed the same bytes.
+#[derive(Default)]
+pub(crate) struct OutputScanner {
+    state: ScanState,
+    osc: Vec<u8>,
+    osc_overflowed: bool,
+    line: u64,
+    column: usize,
+    columns: usize,
+}
+
+impl OutputScanner {
+    pub fn set_columns(&mut self, columns: usize) {
+        self.columns = columns;
+    }
+
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn advance(
+        &mut self,
+        bytes: &[u8],
+        mut on_event: impl FnMut(ShellIntegrationEvent, u64, usize),
+    ) {
+        for &byte in bytes {
+            match self.state {
+                ScanState::Ground => self.ground(byte),
+                ScanState::Escape => self.escape(byte),
+                ScanState::Csi => {
+                    if (0x40..=0x7e).contains(&byte) {
+                        self.state = ScanState::Ground;
+                    }
+                }
+                ScanState::Osc => match byte {
+                    0x07 => self.finish_osc(&mut on_event),
+                    0x1b => self.state = ScanState::OscEscape,
+                    _ => {
+                        if self.osc.len() < MAX_OSC_LEN {
+                            self.osc.push(byte);
+                        } else {
+                            self.osc_overflowed = true;
+                        }
+                    }
+                },
+                ScanState::OscEscape => {
+                    if byte == b'\\' {
+                        self.finish_osc(&mut on_event);
+                    } else {
+                        // An escape inside of an OSC aborts it and starts a new sequence.
+                        self.escape(byte);
+                    }
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        match byte {
+            0x1b => self.state = ScanState::Escape,
+            b'\n' | 0x0b | 0x0c => self.line += 1,
+            b'\r' => self.column = 0,
+            0x08 => self.column = self.column.saturating_sub(1),
+            0x00..=0x1f | 0x7f => {}
+            // UTF-8 continuation bytes belong to the character that was already counted.
+            0x80..=0xbf => {}
+            _ => {
+                if self.columns > 0 && self.column >= self.columns {
+                    self.line += 1;
+                    self.column = 0;
+                }
+                self.column += 1;
+            }
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = match byte {
+            b'[' => ScanState::Csi,
+            b']' => {
+                self.osc.clear();
+                self.osc_overflowed = false;
+                ScanState::Osc
+            }
+            // Intermediate bytes (as in `ESC ( B`) are followed by a final byte.
+            0x20..=0x2f => ScanState::Escape,
+            _ => ScanState::Ground,
+        };
+    }
+
+    fn finish_osc(&mut self, on_event: &mut impl FnMut(ShellIntegrationEvent, u64, usize)) {
+        self.state = ScanState::Ground;
+        if !self.osc_overflowed {
+            if let Some(event) = ShellIntegrationEvent::parse(&self.osc) {
+                on_event(event, self.line, self.column);
+            }
+        }
+        self.osc.clear();
+    }
+}
+
+/// A shell integration event, along with the estimated output position it was found at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PendingEvent {
+    pub event: ShellIntegrationEvent,
+    pub line: u64,
+    pub column: usize,
+}
+
+/// A position in the terminal grid, with rows counted from the top of the scrollback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GridPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl GridPosition {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+/// A prompt, the command typed into it, and the output that command produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandBlock {
+    pub prompt_start: GridPosition,
+    pub command_start: Option<GridPosition>,
+    pub output_start: Option<GridPosition>,
+    pub output_end: Option<GridPosition>,
+    pub exit_code: Option<i32>,
+    pub working_directory: Option<PathBuf>,
+}
+
+impl CommandBlock {
+    fn new(prompt_start: GridPosition, working_directory: Option<PathBuf>) -> Self {
+        Self {
+            prompt_start,
+            command_start: None,
+            output_start: None,
+            output_end: None,
+            exit_code: None,
+            working_directory,
+        }
+    }
+
+    pub fn was_executed(&self) -> bool {
+        self.output_start.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.output_end.is_some()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.exit_code.map_or(false, |code| code != 0)
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        for position in [
+            Some(&mut self.prompt_start),
+            self.command_start.as_mut(),
+            self.output_start.as_mut(),
+            self.output_end.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if position.row < lines {
+                *position = GridPosition::new(0, 0);
+            } else {
+                position.row -= lines;
+            }
+        }
+    }
+}
+
+/// The commands run in a terminal, as reported by the shell.
+#[derive(Default)]
+pub struct CommandBlocks {
+    blocks: VecDeque<CommandBlock>,
+    working_directory: Option<PathBuf>,
+}
+
+impl CommandBlocks {
+    pub fn handle_event(&mut self, event: ShellIntegrationEvent, position: GridPosition) {
+        match event {
+            ShellIntegrationEvent::PromptStart => {
+                let working_directory = self.working_directory.clone();
+                match self.blocks.back_mut() {
+                    // Prompts that were abandoned without running a command are not interesting.
+                    Some(block) if !block.was_executed() => {
+                        *block = CommandBlock::new(position, working_directory);
+                    }
+                    block => {
+                        // The shell did not report the end of the previous command,
+                        // but a new prompt means that it is over.
+                        if let Some(block) = block.filter(|block| !block.is_finished()) {
+                            block.output_end = Some(position);
+                        }
+                        if self.blocks.len() == MAX_COMMAND_BLOCKS {
+                            self.blocks.pop_front();
+                        }
+                        self.blocks
+                            .push_back(CommandBlock::new(position, working_directory));
+                    }
+                }
+            }
+            ShellIntegrationEvent::CommandStart => {
+                if let Some(block) = self.open_prompt() {
+                    block.command_start = Some(position);
+                }
+            }
+            ShellIntegrationEvent::CommandExecuted => {
+                if let Some(block) = self.open_prompt() {
+                    block.command_start.get_or_insert(position);
+                    block.output_start = Some(position);
+                }
+            }
+            ShellIntegrationEvent::CommandFinished { exit_code } => {
+                if let Some(block) = self.blocks.back_mut() {
+                    if block.was_executed() && !block.is_finished() {
+                        block.output_end = Some(position);
+                        block.exit_code = exit_code;
+                    }
+                }
+            }
+            ShellIntegrationEvent::WorkingDirectory(path) => {
+                if let Some(block) = self.open_prompt() {
+                    block.working_directory = Some(path.clone());
+                }
+                self.working_directory = Some(path);
+            }
+        }
+    }
+
+    fn open_prompt(&mut self) -> Option<&mut CommandBlock> {
+        self.blocks.back_mut().filter(|block| !block.was_executed())
+    }
+
+    /// Adjusts the blocks after `lines` lines were dropped from the top of the scrollback.
+    pub fn scroll_up(&mut self, lines: usize) {
+        while self
+            .blocks
+            .front()
+            .map_or(false, |block| block.prompt_start.row < lines)
+        {
+            self.blocks.pop_front();
+        }
+        for block in &mut self.blocks {
+            block.scroll_up(lines);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &CommandBlock> {
+        self.blocks.iter()
+    }
+
+    /// The shell's working directory, as last reported by it.
+    pub fn working_directory(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+
+    /// The most recent command that was run, whether it has finished or not.
+    pub fn last_executed(&self) -> Option<&CommandBlock> {
+        self.blocks.iter().rev().find(|block| block.was_executed())
+    }
+
+    /// The most recent command that has finished running.
+    pub fn last_finished(&self) -> Option<&CommandBlock> {
+        self.blocks.iter().rev().find(|block| block.is_finished())
+    }
+
+    /// The row of the closest prompt that starts above `row`.
+    pub fn previous_prompt(&self, row: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .rev()
+            .map(|block| block.prompt_start.row)
+            .find(|prompt_row| *prompt_row < row)
+    }
+
+    /// The row of the closest prompt that starts below `row`.
+    pub fn next_prompt(&self, row: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .map(|block| block.prompt_start.row)
+            .find(|prompt_row| *prompt_row > row)
+    }
+}
+
+/// Sets up the shell to report its prompts and commands, if it is a shell Zed has integration scripts for.
+///
+/// Returns the shell to spawn instead, with the environment adjusted accordingly.
+pub(crate) fn inject(shell: &Shell, env: &mut HashMap<String, String>) -> Option<Shell> {
+    let program = match shell {
+        Shell::System => std::env::var("SHELL").ok()?,
+        Shell::Program(program) => program.clone(),
+        // Custom arguments might conflict with the ones needed to load the scripts.
+        Shell::WithArguments { .. } => return None,
+    };
+    let shell_name = Path::new(&program).file_name()?.to_str()?.to_owned();
+    // The system shell is started as a login shell on macOS, keep doing that.
+    let login = matches!(shell, Shell::System) && cfg!(target_os = "macos");
+
+    let dir = paths::temp_dir().join("shell_integration");
+    let args = match shell_name.as_str() {
+        "bash" => {
+            let script = dir.join("zed.bash");
+            write_script(&script, BASH_SCRIPT)?;
+            if login {
+                env.insert("ZED_SHELL_INTEGRATION_LOGIN".to_string(), "1".to_string());
+            }
+            vec![
+                "--rcfile".to_string(),
+                script.to_string_lossy().into_owned(),
+                "-i".to_string(),
+            ]
+        }
+        "zsh" => {
+            let zdotdir = dir.join("zsh");
+            write_script(&zdotdir.join(".zshenv"), ZSH_ENV)?;
+            write_script(&zdotdir.join(".zprofile"), ZSH_PROFILE)?;
+            write_script(&zdotdir.join(".zshrc"), ZSH_RC)?;
+            write_script(&zdotdir.join(".zlogin"), ZSH_LOGIN)?;
+            write_script(&zdotdir.join("zed.zsh"), ZSH_SCRIPT)?;
+            let user_zdotdir = env
+                .get("ZDOTDIR")
+                .cloned()
+                .or_else(|| std::env::var("ZDOTDIR").ok());
+            if let Some(user_zdotdir) = user_zdotdir {
+                env.insert("ZED_USER_ZDOTDIR".to_string(), user_zdotdir);
+            }
+            env.insert(
+                "ZDOTDIR".to_string(),
+                zdotdir.to_string_lossy().into_owned(),
+            );
+            if login {
+                vec!["-l".to_string()]
+            } else {
+                Vec::new()
+            }
+        }
+        "fish" => {
+            let script = dir.join("zed.fish");
+            write_script(&script, FISH_SCRIPT)?;
+            let mut args = vec![
+                "--init-command".to_string(),
+                format!("source '{}'", script.to_string_lossy().replace('\'', "\\'")),
+            ];
+            if login {
+                args.push("-l".to_string());
+            }
+            args
+        }
+        _ => return None,
+    };
+
+    env.insert("ZED_SHELL_INTEGRATION".to_string(), "1".to_string());
+    Some(Shell::WithArguments {
+        program,
+        args,
+        title_override: None,
+    })
+}
+
+fn write_script(path: &Path, contents: &str) -> Option<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).log_err()?;
+    }
+    if std::fs::read_to_string(path).ok().as_deref() != Some(contents) {
+        std::fs::write(path, contents).log_err()?;
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(scanner: &mut OutputScanner, input: &[u8]) -> Vec<PendingEvent> {
+        let mut events = Vec::new();
+        scanner.advance(input, |event, line, column| {
+            events.push(PendingEvent {
+                event,
+                line,
+                column,
+            })
+        });
+        events
+    }
+
+    #[test]
+    fn test_scanning_semantic_prompt_sequences() {
+        let mut scanner = OutputScanner::default();
+        let events = scan(
+            &mut scanner,
+            b"\x1b]133;A\x07$ \x1b]133;B\x1b\\ls\r\n\x1b]133;C\x07a\r\nb\r\n\x1b]133;D;2\x07",
+        );
+        assert_eq!(
+            events,
+            vec![
+                PendingEvent {
+                    event: ShellIntegrationEvent::PromptStart,
+                    line: 0,
+                    column: 0,
+                },
+                PendingEvent {
+                    event: ShellIntegrationEvent::CommandStart,
+                    line: 0,
+                    column: 2,
+                },
+                PendingEvent {
+                    event: ShellIntegrationEvent::CommandExecuted,
+                    line: 1,
+                    column: 0,
+                },
+                PendingEvent {
+                    event: ShellIntegrationEvent::CommandFinished { exit_code: Some(2) },
+                    line: 3,
+                    column: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanning_sequences_split_across_reads() {
+        let mut scanner = OutputScanner::default();
+        assert!(scan(&mut scanner, b"\x1b]13").is_empty());
+        assert!(scan(&mut scanner, b"3;D").is_empty());
+        assert_eq!(
+            scan(
+                &mut scanner,
+                b"\x07\x1b[31mred\x1b[0m\x1b]7;file://host/tmp/a%20b\x1b\\"
+            ),
+            vec![
+                PendingEvent {
+                    event: ShellIntegrationEvent::CommandFinished { exit_code: None },
+                    line: 0,
+                    column: 0,
+                },
+                PendingEvent {
+                    event: ShellIntegrationEvent::WorkingDirectory(PathBuf::from("/tmp/a b")),
+                    line: 0,
+                    column: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanner_ignores_other_sequences_and_counts_wrapped_lines() {
+        let mut scanner = OutputScanner::default();
+        scanner.set_columns(4);
+        let events = scan(
+            &mut scanner,
+            "\x1b]2;title\x07héllo wörld\x1b]133;A\x07".as_bytes(),
+        );
+        assert_eq!(
+            events,
+            vec![PendingEvent {
+                event: ShellIntegrationEvent::PromptStart,
+                line: 2,
+                column: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_command_block_lifecycle() {
+        let mut blocks = CommandBlocks::default();
+        blocks.handle_event(
+            ShellIntegrationEvent::WorkingDirectory(PathBuf::from("/project")),
+            GridPosition::new(0, 0),
+        );
+        blocks.handle_event(ShellIntegrationEvent::PromptStart, GridPosition::new(0, 0));
+        // Prompts without a command are replaced by the next prompt.
+        blocks.handle_event(ShellIntegrationEvent::PromptStart, GridPosition::new(1, 0));
+        blocks.handle_event(ShellIntegrationEvent::CommandStart, GridPosition::new(1, 2));
+        blocks.handle_event(
+            ShellIntegrationEvent::CommandExecuted,
+            GridPosition::new(2, 0),
+        );
+        assert!(blocks.last_finished().is_none());
+        blocks.handle_event(
+            ShellIntegrationEvent::CommandFinished { exit_code: Some(1) },
+            GridPosition::new(5, 0),
+        );
+        blocks.handle_event(ShellIntegrationEvent::PromptStart, GridPosition::new(5, 0));
+
+        assert_eq!(blocks.iter().count(), 2);
+        let finished = blocks.last_finished().unwrap();
+        assert_eq!(finished.prompt_start, GridPosition::new(1, 0));
+        assert_eq!(finished.command_start, Some(GridPosition::new(1, 2)));
+        assert_eq!(finished.output_start, Some(GridPosition::new(2, 0)));
+        assert_eq!(finished.output_end, Some(GridPosition::new(5, 0)));
+        assert_eq!(finished.working_directory, Some(PathBuf::from("/project")));
+        assert!(finished.failed());
+
+        assert_eq!(blocks.previous_prompt(5), Some(1));
+        assert_eq!(blocks.previous_prompt(1), None);
+        assert_eq!(blocks.next_prompt(1), Some(5));
+        assert_eq!(blocks.next_prompt(5), None);
+
+        blocks.scroll_up(3);
+        assert_eq!(blocks.iter().count(), 1);
+        assert_eq!(blocks.previous_prompt(10), Some(2));
+    }
+}
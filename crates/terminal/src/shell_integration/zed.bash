@@ -0,0 +1,51 @@
+# Zed shell integration for bash.
+#
+# Zed starts bash with `--rcfile` pointing at this file, so the user's own
+# startup files are sourced from here before the integration is installed.
+
+if [[ -n "$ZED_SHELL_INTEGRATION_LOGIN" ]]; then
+    unset ZED_SHELL_INTEGRATION_LOGIN
+    [[ -r /etc/profile ]] && source /etc/profile
+    for __zed_profile in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [[ -r "$__zed_profile" ]]; then
+            source "$__zed_profile"
+            break
+        fi
+    done
+    unset __zed_profile
+else
+    [[ -r /etc/bash.bashrc ]] && source /etc/bash.bashrc
+    [[ -r ~/.bashrc ]] && source ~/.bashrc
+fi
+
+if [[ -z "$__zed_integration_installed" ]]; then
+    __zed_integration_installed=1
+
+    # Runs before any other prompt command, so that `$?` is still the command's exit code.
+    __zed_precmd() {
+        local exit_code="$?"
+        printf '\e]133;D;%s\a' "$exit_code"
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+        printf '\e]133;A\a'
+        return "$exit_code"
+    }
+
+    # Runs after every other prompt command, as those may rebuild the prompt.
+    __zed_prompt_end() {
+        local exit_code="$?"
+        if [[ "$PS1" != *'133;B'* ]]; then
+            PS1="$PS1"'\[\e]133;B\a\]'
+        fi
+        return "$exit_code"
+    }
+
+    if [[ "$PS0" != *'133;C'* ]]; then
+        PS0="$PS0"'\e]133;C\a'
+    fi
+
+    if [[ "$(declare -p PROMPT_COMMAND 2>/dev/null)" == "declare -a"* ]]; then
+        PROMPT_COMMAND=(__zed_precmd "${PROMPT_COMMAND[@]}" __zed_prompt_end)
+    else
+        PROMPT_COMMAND="__zed_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __zed_prompt_end"
+    fi
+fi
@@ -0,0 +1,30 @@
+# Zed shell integration for fish, loaded with `--init-command` after the user's configuration.
+
+if status is-interactive; and not set -q __zed_integration_installed
+    set -g __zed_integration_installed 1
+
+    function __zed_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+
+    function __zed_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    function __zed_report_cwd --on-variable PWD
+        printf '\e]7;file://%s%s\a' $hostname $PWD
+    end
+    __zed_report_cwd
+
+    function __zed_prompt_start --on-event fish_prompt
+        printf '\e]133;A\a'
+    end
+
+    if functions -q fish_prompt
+        functions -c fish_prompt __zed_user_fish_prompt
+        function fish_prompt
+            __zed_user_fish_prompt
+            printf '\e]133;B\a'
+        end
+    end
+end
@@ -2,7 +2,11 @@ pub mod mappings;
 
 pub use alacritty_terminal;
 
+mod images;
+mod intercept;
 mod pty_info;
+pub mod scrollback;
+pub mod shell_integration;
 pub mod terminal_settings;
 
 use alacritty_terminal::{
@@ -21,6 +25,7 @@ use alacritty_terminal::{
     vi_mode::{ViModeCursor, ViMotion},
     vte::ansi::{
         ClearMode, CursorStyle as AlacCursorStyle, Handler, NamedPrivateMode, PrivateMode,
+        Processor,
     },
     Term,
 };
@@ -38,19 +43,23 @@ use mappings::mouse::{
 
 use collections::{HashMap, VecDeque};
 use futures::StreamExt;
+use images::ImagePlacements;
+use intercept::{Intercepted, InterceptedOutput, InterceptingPty, PendingImageCommand};
 use pty_info::PtyProcessInfo;
+use scrollback::ScrollbackSnapshot;
 use serde::{Deserialize, Serialize};
 use settings::Settings;
+use shell_integration::{CommandBlock, CommandBlocks, GridPosition, PendingEvent};
 use smol::channel::{Receiver, Sender};
 use task::{HideStrategy, Shell, TaskId};
 use terminal_settings::{AlternateScroll, CursorShape, TerminalSettings};
 use theme::{ActiveTheme, Theme};
-use util::{paths::home_dir, truncate_and_trailoff};
+use util::{paths::home_dir, truncate_and_trailoff, ResultExt as _};
 
 use std::{
     cmp::{self, min},
     fmt::Display,
-    ops::{Deref, Index, RangeInclusive},
+    ops::{Deref, Index, Range, RangeInclusive},
     path::PathBuf,
     sync::Arc,
     time::Duration,
@@ -80,7 +89,12 @@ actions!(
         ScrollPageDown,
         ScrollToTop,
         ScrollToBottom,
+        ScrollToPreviousPrompt,
+        ScrollToNextPrompt,
+        CopyLastCommandOutput,
         ToggleViMode,
+        OpenScrollbackInEditor,
+        CollectAllMatches,
     ]
 );
 
@@ -335,7 +349,9 @@ impl TerminalBuilder {
         cursor_shape: CursorShape,
         alternate_scroll: AlternateScroll,
         max_scroll_history_lines: Option<usize>,
+        shell_integration: bool,
         is_ssh_terminal: bool,
+        scrollback: Option<String>,
         window: AnyWindowHandle,
         completion_tx: Sender<()>,
         cx: &App,
@@ -357,6 +373,13 @@ impl TerminalBuilder {
             release_channel::AppVersion::global(cx).to_string(),
         );
 
+        // Tasks are not interactive, so they have no prompts to integrate with.
+        let shell = if shell_integration && task.is_none() {
+            shell_integration::inject(&shell, &mut env).unwrap_or(shell)
+        } else {
+            shell
+        };
+
         let mut terminal_title_override = None;
 
         let pty_options = {
@@ -420,6 +443,11 @@ impl TerminalBuilder {
             term.unset_private_mode(PrivateMode::Named(NamedPrivateMode::AlternateScroll));
         }
 
+        // The scrollback goes in before the PTY exists, so the shell's output always follows it.
+        if let Some(scrollback) = scrollback.filter(|scrollback| !scrollback.is_empty()) {
+            seed_scrollback(&mut term, &scrollback, scrolling_history);
+        }
+
         let term = Arc::new(FairMutex::new(term));
 
         //Setup the pty...
@@ -439,6 +467,8 @@ impl TerminalBuilder {
         };
 
         let pty_info = PtyProcessInfo::new(&pty);
+        let intercepted = InterceptedOutput::default();
+        let pty = InterceptingPty::new(pty, intercepted.clone(), ZedListener(events_tx.clone()));
 
         //And connect them together
         let event_loop = EventLoop::new(
@@ -476,6 +506,11 @@ impl TerminalBuilder {
             vi_mode_enabled: false,
             is_ssh_terminal,
             python_venv_directory,
+            intercepted,
+            command_blocks: CommandBlocks::default(),
+            deferred_events: Vec::new(),
+            images: ImagePlacements::default(),
+            output_position: OutputPosition::default(),
         };
 
         Ok(TerminalBuilder {
@@ -574,6 +609,43 @@ pub struct TerminalContent {
     pub cursor_char: char,
     pub terminal_bounds: TerminalBounds,
     pub last_hovered_word: Option<HoveredWord>,
+    pub command_marks: Vec<CommandMark>,
+    pub images: Vec<ImageMark>,
+}
+
+/// The part of the viewport taken by a command that was run in the terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandMark {
+    /// The viewport lines spanned by the command's prompt and output.
+    pub lines: Range<usize>,
+    /// The exit code of a finished command, if the shell reported one.
+    pub exit_code: Option<i32>,
+    pub finished: bool,
+}
+
+/// An image shown in the viewport.
+#[derive(Clone, Debug)]
+pub struct ImageMark {
+    pub image: Arc<RenderImage>,
+    /// The viewport line the top of the image is on, negative when it starts above the viewport.
+    pub line: i32,
+    pub column: usize,
+    pub size: Size<Pixels>,
+}
+
+/// A command that finished running in the terminal, as reported by shell integration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FinishedCommand {
+    pub command: String,
+    pub output: String,
+    pub exit_code: Option<i32>,
+    pub working_directory: Option<PathBuf>,
+}
+
+impl FinishedCommand {
+    pub fn failed(&self) -> bool {
+        self.exit_code.map_or(false, |code| code != 0)
+    }
 }
 
 #[derive(Clone)]
@@ -598,6 +670,8 @@ impl Default for TerminalContent {
             cursor_char: Default::default(),
             terminal_bounds: Default::default(),
             last_hovered_word: None,
+            command_marks: Vec::new(),
+            images: Vec::new(),
         }
     }
 }
@@ -631,6 +705,20 @@ pub struct Terminal {
     task: Option<TaskState>,
     vi_mode_enabled: bool,
     is_ssh_terminal: bool,
+    intercepted: InterceptedOutput,
+    command_blocks: CommandBlocks,
+    /// Shell integration events received while a full screen application was running.
+    deferred_events: Vec<PendingEvent>,
+    images: ImagePlacements,
+    output_position: OutputPosition,
+}
+
+/// Where the PTY output had gotten to when intercepted sequences were last processed.
+#[derive(Default)]
+struct OutputPosition {
+    lines_read: u64,
+    cursor_line: i32,
+    history_size: usize,
 }
 
 pub struct TaskState {
@@ -711,6 +799,7 @@ impl Terminal {
                 //NOOP, Handled in render
             }
             AlacTermEvent::Wakeup => {
+                self.sync_intercepted_output();
                 cx.emit(Event::Wakeup);
 
                 if self.pty_info.has_changed() {
@@ -755,6 +844,7 @@ impl Terminal {
                 new_bounds.bounds.size.width = cmp::max(new_bounds.cell_width, new_bounds.width());
 
                 self.last_content.terminal_bounds = new_bounds;
+                self.intercepted.set_bounds(new_bounds);
 
                 self.pty_tx.0.send(Msg::Resize(new_bounds.into())).ok();
 
@@ -763,6 +853,8 @@ impl Terminal {
             InternalEvent::Clear => {
                 // Clear back buffer
                 term.clear_screen(ClearMode::Saved);
+                self.command_blocks.clear();
+                self.images.clear();
 
                 let cursor = term.grid().cursor.point;
 
@@ -1312,11 +1404,72 @@ impl Terminal {
             self.process_terminal_event(&e, &mut terminal, window, cx)
         }
 
-        self.last_content = Self::make_content(&terminal, &self.last_content);
+        self.last_content = Self::make_content(
+            &terminal,
+            &self.last_content,
+            &self.command_blocks,
+            &self.images,
+        );
+
+        for image in self.images.take_evicted() {
+            window.drop_image(image).log_err();
+        }
+    }
+
+    /// Releases the images this terminal has painted from the window's sprite atlas, as it is closed.
+    pub fn release_images(&mut self, window: &mut Window) {
+        for image in self.images.all_images() {
+            window.drop_image(image.clone()).log_err();
+        }
+        self.images.take_evicted();
     }
 
-    fn make_content(term: &Term<ZedListener>, last_content: &TerminalContent) -> TerminalContent {
+    fn make_content(
+        term: &Term<ZedListener>,
+        last_content: &TerminalContent,
+        command_blocks: &CommandBlocks,
+        images: &ImagePlacements,
+    ) -> TerminalContent {
         let content = term.renderable_content();
+        let alt_screen = content.mode.contains(TermMode::ALT_SCREEN);
+        let history_size = term.grid().history_size();
+        let viewport_top = history_size - content.display_offset;
+        let viewport_bottom = viewport_top + term.screen_lines();
+        let command_marks = if alt_screen {
+            Vec::new()
+        } else {
+            let cursor_row = (history_size as i32 + content.cursor.point.line.0) as usize;
+            command_blocks
+                .iter()
+                .filter(|block| block.was_executed())
+                .filter_map(|block| {
+                    let start = block.prompt_start.row;
+                    let end = block
+                        .output_end
+                        .map_or(cursor_row + 1, |end| end.row.max(start + 1));
+                    let start = start.max(viewport_top);
+                    let end = end.min(viewport_bottom);
+                    (start < end).then(|| CommandMark {
+                        lines: start - viewport_top..end - viewport_top,
+                        exit_code: block.exit_code,
+                        finished: block.is_finished(),
+                    })
+                })
+                .collect()
+        };
+        let images = images
+            .iter(alt_screen)
+            .filter(|placement| {
+                placement.position.row < viewport_bottom
+                    && placement.position.row + placement.rows > viewport_top
+            })
+            .map(|placement| ImageMark {
+                image: placement.image.clone(),
+                line: placement.position.row as i32 - viewport_top as i32,
+                column: placement.position.column,
+                size: placement.size,
+            })
+            .collect();
         TerminalContent {
             cells: content
                 .display_iter
@@ -1340,6 +1493,8 @@ impl Terminal {
             cursor_char: term.grid()[content.cursor.point].c,
             terminal_bounds: last_content.terminal_bounds,
             last_hovered_word: last_content.last_hovered_word.clone(),
+            command_marks,
+            images,
         }
     }
 
@@ -1368,6 +1523,209 @@ impl Terminal {
         lines
     }
 
+    /// Returns the command and output of the most recent command, if it failed.
+    ///
+    /// At most `max_lines` of the output are returned, keeping the last ones.
+    pub fn last_failed_command_lines(&self, max_lines: usize) -> Option<Vec<String>> {
+        let command = self.last_failed_command()?;
+
+        let mut lines = vec![format!("$ {}", command.command)];
+        let output_lines = command
+            .output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        let skip = output_lines.len().saturating_sub(max_lines);
+        lines.extend(output_lines[skip..].iter().map(|line| line.to_string()));
+        if let Some(exit_code) = command.exit_code {
+            lines.push(format!("(exit code {exit_code})"));
+        }
+        Some(lines)
+    }
+
+    /// Applies the shell integration events and image commands read from the PTY since the last wakeup.
+    fn sync_intercepted_output(&mut self) {
+        if self.command_blocks.is_empty()
+            && self.images.is_empty()
+            && self.deferred_events.is_empty()
+            && !self.intercepted.has_pending()
+        {
+            self.output_position.lines_read = self.intercepted.lines_read();
+            return;
+        }
+
+        let Intercepted {
+            events,
+            image_commands,
+            replies,
+            lines_read,
+        } = self.intercepted.take();
+        for reply in replies {
+            self.write_bytes_to_pty(reply);
+        }
+
+        let term = self.term.clone();
+        let term = term.lock();
+        let grid = term.grid();
+        let cursor_line = grid.cursor.point.line.0;
+        let last_column = grid.columns().saturating_sub(1);
+
+        if term.mode().contains(TermMode::ALT_SCREEN) {
+            // The alternate screen has no scrollback, so its images are placed on screen rows.
+            for PendingImageCommand {
+                command,
+                line,
+                column,
+            } in image_commands
+            {
+                let lines_since_command = lines_read.saturating_sub(line) as i32;
+                let row = (cursor_line - lines_since_command).max(0) as usize;
+                let position = GridPosition::new(row, column.min(last_column));
+                self.images.handle_command(command, position, true, 0);
+            }
+            // Output of full screen applications does not move the scrollback, keep it
+            // from being counted when estimating where markers are. Shell integration
+            // events are applied once the application exits.
+            self.deferred_events.extend(events);
+            self.output_position.lines_read = lines_read;
+            return;
+        }
+        self.images.leave_alt_screen();
+
+        let history_size = grid.history_size();
+        let last = &self.output_position;
+        if history_size < last.history_size {
+            // The scrollback was cleared, and the commands and images in it with it.
+            self.command_blocks.clear();
+            self.images.clear();
+        } else if history_size == self.term_config.scrolling_history {
+            // A full scrollback drops a line from its top for every line that is added,
+            // which does not show in its size. Estimate the number of dropped lines
+            // from the amount of output that was read instead.
+            let lines_advanced = lines_read.saturating_sub(last.lines_read) as i64;
+            let cursor_moved = (cursor_line - last.cursor_line) as i64;
+            let history_grown = (history_size - last.history_size) as i64;
+            let dropped = lines_advanced - cursor_moved - history_grown;
+            if dropped > 0 {
+                self.command_blocks.scroll_up(dropped as usize);
+                self.images.scroll_up(dropped as usize);
+            }
+        }
+
+        let grid_position = |line: u64, column: usize| {
+            let lines_since = lines_read.saturating_sub(line) as i32;
+            let line = (cursor_line - lines_since).max(-(history_size as i32));
+            let row = (line + history_size as i32) as usize;
+            GridPosition::new(row, column.min(last_column))
+        };
+
+        // Events from while a full screen application ran are placed where it was started.
+        let baseline = self.output_position.lines_read;
+        for PendingEvent { event, column, .. } in self.deferred_events.drain(..) {
+            self.command_blocks
+                .handle_event(event, grid_position(baseline, column));
+        }
+        for PendingEvent {
+            event,
+            line,
+            column,
+        } in events
+        {
+            self.command_blocks
+                .handle_event(event, grid_position(line, column));
+        }
+        for PendingImageCommand {
+            command,
+            line,
+            column,
+        } in image_commands
+        {
+            self.images
+                .handle_command(command, grid_position(line, column), false, history_size);
+        }
+
+        self.output_position = OutputPosition {
+            lines_read,
+            cursor_line,
+            history_size,
+        };
+    }
+
+    /// The commands run in this terminal, if its shell reports them.
+    pub fn command_blocks(&self) -> &CommandBlocks {
+        &self.command_blocks
+    }
+
+    /// The shell's working directory, if the shell reports it.
+    pub fn reported_working_directory(&self) -> Option<&std::path::Path> {
+        self.command_blocks.working_directory()
+    }
+
+    pub fn finished_command(&self, block: &CommandBlock) -> Option<FinishedCommand> {
+        let command_start = block.command_start?;
+        let output_start = block.output_start?;
+        let output_end = block.output_end?;
+
+        let term = self.term.lock();
+        Some(FinishedCommand {
+            command: grid_text(&term, command_start, output_start)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            output: grid_text(&term, output_start, output_end).unwrap_or_default(),
+            exit_code: block.exit_code,
+            working_directory: block.working_directory.clone(),
+        })
+    }
+
+    pub fn last_finished_command(&self) -> Option<FinishedCommand> {
+        let block = self.command_blocks.last_finished()?;
+        self.finished_command(block)
+    }
+
+    /// The most recent command, if it has failed.
+    pub fn last_failed_command(&self) -> Option<FinishedCommand> {
+        let block = self
+            .command_blocks
+            .last_executed()
+            .filter(|block| block.failed())?;
+        self.finished_command(block)
+    }
+
+    pub fn copy_last_command_output(&mut self, cx: &mut Context<Self>) {
+        if let Some(command) = self.last_finished_command() {
+            cx.write_to_clipboard(ClipboardItem::new_string(command.output));
+        }
+    }
+
+    /// Absolute row of the top of the viewport, counted from the top of the scrollback.
+    fn viewport_top_row(&self) -> usize {
+        let term = self.term.lock();
+        term.grid().history_size() - term.grid().display_offset()
+    }
+
+    pub fn scroll_to_previous_prompt(&mut self) {
+        let viewport_top = self.viewport_top_row();
+        if let Some(row) = self.command_blocks.previous_prompt(viewport_top) {
+            self.events
+                .push_back(InternalEvent::Scroll(AlacScroll::Delta(
+                    (viewport_top - row) as i32,
+                )));
+        }
+    }
+
+    pub fn scroll_to_next_prompt(&mut self) {
+        let viewport_top = self.viewport_top_row();
+        match self.command_blocks.next_prompt(viewport_top) {
+            Some(row) => self
+                .events
+                .push_back(InternalEvent::Scroll(AlacScroll::Delta(
+                    -((row - viewport_top) as i32),
+                ))),
+            None => self.scroll_to_bottom(),
+        }
+    }
+
     pub fn focus_in(&self) {
         if self.last_content.mode.contains(TermMode::FOCUS_IN_OUT) {
             self.write_to_pty("\x1b[I".to_string());
@@ -1654,6 +2012,59 @@ impl Terminal {
         })
     }
 
+    /// Copies the scrollback and the screen out of the terminal, along with the current search matches.
+    pub fn scrollback_snapshot(&self, cx: &App) -> Task<ScrollbackSnapshot> {
+        let term = self.term.clone();
+        let matches = self.matches.clone();
+        cx.background_spawn(async move {
+            let term = term.lock();
+            ScrollbackSnapshot::new(&term, &matches)
+        })
+    }
+
+    /// Selects a range of the grid and scrolls to it, like a search match.
+    pub fn select_grid_range(&mut self, start: GridPosition, end: GridPosition) {
+        let term = self.term.lock();
+        let history_size = term.grid().history_size() as i32;
+        let topmost_line = term.topmost_line();
+        let bottommost_line = term.bottommost_line();
+        let last_column = term.last_column();
+        drop(term);
+
+        let to_point = |position: GridPosition| {
+            AlacPoint::new(
+                Line(position.row as i32 - history_size).clamp(topmost_line, bottommost_line),
+                Column(position.column).min(last_column),
+            )
+        };
+        let range = to_point(start)..=to_point(end);
+        self.set_selection(Some((make_selection(&range), *range.end())));
+        self.events
+            .push_back(InternalEvent::ScrollToAlacPoint(*range.start()));
+    }
+
+    /// Returns the text of the scrollback and the screen above the cursor line,
+    /// for restoring it in a later session.
+    ///
+    /// Full screen applications have no scrollback of their own, so there is nothing to return while they run.
+    pub fn scrollback_text(&self, cx: &App) -> Task<Option<String>> {
+        let term = self.term.clone();
+        cx.background_spawn(async move {
+            let term = term.lock();
+            if term.mode().contains(TermMode::ALT_SCREEN) {
+                return None;
+            }
+
+            let cursor_line = term.grid().cursor.point.line;
+            if cursor_line <= term.topmost_line() {
+                return Some(String::new());
+            }
+            let start = AlacPoint::new(term.topmost_line(), Column(0));
+            let end = AlacPoint::new(Line(cursor_line.0 - 1), term.last_column());
+            Some(term.bounds_to_string(start, end).trim_end().to_string())
+        })
+    }
+
     pub fn working_directory(&self) -> Option<PathBuf> {
         if self.is_ssh_terminal {
             // We can't yet reliably detect the working directory of a shell on the
@@ -1894,6 +2305,24 @@ pub fn visible_regex_match_iter<'a, T>(
         .take_while(move |rm| rm.start().line <= viewport_end)
 }
 
+/// The text in between two grid positions, excluding the end one.
+fn grid_text<T>(term: &Term<T>, start: GridPosition, end: GridPosition) -> Option<String> {
+    if end <= start {
+        return None;
+    }
+    let history_size = term.grid().history_size() as i32;
+    let last_column = term.last_column();
+    let to_point = |position: GridPosition| {
+        AlacPoint::new(
+            Line(position.row as i32 - history_size),
+            Column(position.column).min(last_column),
+        )
+    };
+    let start = to_point(start);
+    let end = to_point(end).sub(term, Boundary::Grid, 1);
+    Some(term.bounds_to_string(start, end).trim_end().to_string())
+}
+
 fn make_selection(range: &RangeInclusive<AlacPoint>) -> Selection {
     let mut selection = Selection::new(SelectionType::Simple, *range.start(), AlacDirection::Left);
     selection.update(*range.end(), AlacDirection::Right);
@@ -2001,6 +2430,23 @@ pub fn rgba_color(r: u8, g: u8, b: u8) -> Hsla {
     .into()
 }
 
+/// Writes the scrollback of a previous session into a new terminal, keeping at most
+/// `scrolling_history` of its last lines.
+fn seed_scrollback<T: EventListener>(
+    term: &mut Term<T>,
+    scrollback: &str,
+    scrolling_history: usize,
+) {
+    let lines = scrollback.lines().collect::<Vec<_>>();
+    let skip = lines.len().saturating_sub(scrolling_history);
+
+    let mut parser: Processor = Processor::new();
+    for line in &lines[skip..] {
+        parser.advance(term, line.as_bytes());
+        parser.advance(term, b"\r\n");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alacritty_terminal::{
@@ -2011,7 +2457,8 @@ mod tests {
     use rand::{distributions::Alphanumeric, rngs::ThreadRng, thread_rng, Rng};
 
     use crate::{
-        content_index_for_mouse, rgb_for_index, IndexedCell, TerminalBounds, TerminalContent,
+        content_index_for_mouse, rgb_for_index, seed_scrollback, IndexedCell, TerminalBounds,
+        TerminalContent, ZedListener,
     };
 
     #[test]
@@ -2199,4 +2646,29 @@ mod tests {
             vec!["Main.cs:20:5:Error", "desc"],
         );
     }
+
+    #[test]
+    fn test_seed_scrollback() {
+        use alacritty_terminal::{grid::Dimensions as _, term::Config, Term};
+
+        let (events_tx, _events_rx) = futures::channel::mpsc::unbounded();
+        let mut term = Term::new(
+            Config::default(),
+            &TerminalBounds::default(),
+            ZedListener(events_tx),
+        );
+        seed_scrollback(&mut term, "one\ntwo\nthree", 2);
+
+        // Only the last lines that fit in the history are kept, and the shell's output starts
+        // on the line after them.
+        assert_eq!(term.grid().cursor.point, AlacPoint::new(Line(2), Column(0)));
+        let text = term.bounds_to_string(
+            AlacPoint::new(term.topmost_line(), Column(0)),
+            AlacPoint::new(Line(1), term.last_column()),
+        );
+        assert_eq!(
+            text.lines().map(str::trim_end).collect::<Vec<_>>(),
+            ["two", "three"]
+        );
+    }
 }
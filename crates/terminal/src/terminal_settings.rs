@@ -46,6 +46,8 @@ pub struct TerminalSettings {
     pub default_height: Pixels,
     pub detect_venv: VenvSettings,
     pub max_scroll_history_lines: Option<usize>,
+    pub shell_integration: bool,
+    pub restore_scrollback: bool,
     pub toolbar: Toolbar,
     pub scrollbar: ScrollbarSettings,
 }
@@ -219,6 +221,19 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: 10_000
     pub max_scroll_history_lines: Option<usize>,
+    /// Whether to set up bash, zsh and fish to report their prompts, commands,
+    /// exit codes and working directories to Zed (OSC 133 and OSC 7).
+    /// Enables jumping between prompts and other command-aware features.
+    /// Existing terminals will not pick up this change until they are recreated.
+    ///
+    /// Default: true
+    pub shell_integration: Option<bool>,
+    /// Whether to save the scrollback of terminals along with the workspace,
+    /// and show it again when the terminals are restored.
+    /// At most `max_scroll_history_lines` lines are restored.
+    ///
+    /// Default: true
+    pub restore_scrollback: Option<bool>,
     /// Toolbar related settings
     pub toolbar: Option<ToolbarContent>,
     /// Scrollbar-related settings
@@ -403,6 +403,10 @@ define_connection! {
             DROP TABLE terminals;
 
             ALTER TABLE terminals2 RENAME TO terminals;
+        ),
+        sql!(
+            ALTER TABLE terminals ADD COLUMN title TEXT;
+            ALTER TABLE terminals ADD COLUMN scrollback TEXT;
         )];
 }
 
@@ -423,10 +427,26 @@ impl TerminalDb {
         pub async fn save_working_directory(
             item_id: ItemId,
             workspace_id: WorkspaceId,
-            working_directory: PathBuf
+            working_directory: PathBuf,
+            title: String
         ) -> Result<()> {
-            INSERT OR REPLACE INTO terminals(item_id, workspace_id, working_directory)
-            VALUES (?, ?, ?)
+            INSERT INTO terminals(item_id, workspace_id, working_directory, title)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT DO UPDATE SET
+                working_directory = excluded.working_directory,
+                title = excluded.title
+        }
+    }
+
+    query! {
+        pub async fn save_scrollback(
+            item_id: ItemId,
+            workspace_id: WorkspaceId,
+            scrollback: Option<String>
+        ) -> Result<()> {
+            UPDATE terminals
+            SET scrollback = ?3
+            WHERE item_id = ?1 AND workspace_id = ?2
         }
     }
 
@@ -438,6 +458,14 @@ impl TerminalDb {
         }
     }
 
+    query! {
+        pub fn get_title_and_scrollback(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<(Option<String>, Option<String>)>> {
+            SELECT title, scrollback
+            FROM terminals
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
+
     pub async fn delete_unloaded_items(
         &self,
         workspace: WorkspaceId,
@@ -464,3 +492,81 @@ impl TerminalDb {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::sqlez::{connection::Connection, domain::Domain as _};
+
+    #[test]
+    fn test_migration_keeps_existing_terminals() {
+        let connection = Connection::open_memory(Some("test_migration_keeps_existing_terminals"));
+        let migrations = TerminalDb::migrations();
+        connection
+            .migrate(TerminalDb::name(), &migrations[..migrations.len() - 1])
+            .unwrap();
+        connection
+            .exec_bound::<(i64, i64, PathBuf)>(sql!(
+                INSERT INTO terminals(workspace_id, item_id, working_directory) VALUES (?, ?, ?)
+            ))
+            .unwrap()((1, 2, PathBuf::from("/tmp")))
+        .unwrap();
+
+        connection.migrate(TerminalDb::name(), migrations).unwrap();
+        let row = connection
+            .select_row_bound::<(i64, i64), (PathBuf, Option<String>, Option<String>)>(sql!(
+                SELECT working_directory, title, scrollback
+                FROM terminals
+                WHERE workspace_id = ? AND item_id = ?
+            ))
+            .unwrap()((1, 2))
+        .unwrap();
+        assert_eq!(row, Some((PathBuf::from("/tmp"), None, None)));
+    }
+
+    #[gpui::test]
+    async fn test_save_title_and_scrollback() {
+        let workspace_id = workspace::WORKSPACE_DB.next_id().await.unwrap();
+        TERMINAL_DB
+            .save_working_directory(1, workspace_id, PathBuf::from("/tmp"), "vim".into())
+            .await
+            .unwrap();
+        TERMINAL_DB
+            .save_scrollback(1, workspace_id, Some("$ ls\nfile".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            TERMINAL_DB
+                .get_title_and_scrollback(1, workspace_id)
+                .unwrap(),
+            Some((Some("vim".into()), Some("$ ls\nfile".into())))
+        );
+
+        // Saving the working directory and title again keeps the scrollback.
+        TERMINAL_DB
+            .save_working_directory(1, workspace_id, PathBuf::from("/home"), "zsh".into())
+            .await
+            .unwrap();
+        assert_eq!(
+            TERMINAL_DB.get_working_directory(1, workspace_id).unwrap(),
+            Some(PathBuf::from("/home"))
+        );
+        assert_eq!(
+            TERMINAL_DB
+                .get_title_and_scrollback(1, workspace_id)
+                .unwrap(),
+            Some((Some("zsh".into()), Some("$ ls\nfile".into())))
+        );
+
+        TERMINAL_DB
+            .save_scrollback(1, workspace_id, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            TERMINAL_DB
+                .get_title_and_scrollback(1, workspace_id)
+                .unwrap(),
+            Some((Some("zsh".into()), None))
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Copies of a terminal's scrollback in editors, for reading through long output and search results.
+
+use std::{any::TypeId, ops::Range};
+
+use editor::{scroll::Autoscroll, Anchor, Editor, EditorEvent, MultiBuffer, ToPoint as _};
+use gpui::{
+    actions, AnyView, App, Context, Entity, EventEmitter, FocusHandle, Focusable, FontStyle,
+    FontWeight, HighlightStyle, SharedString, StrikethroughStyle, UnderlineStyle, WeakEntity,
+};
+use language::Buffer;
+use project::Project;
+use terminal::{
+    alacritty_terminal::{
+        term::cell::Flags,
+        vte::ansi::{Color, NamedColor},
+    },
+    scrollback::{ScrollbackMatch, ScrollbackSnapshot, TextStyle},
+};
+use theme::{ActiveTheme, Theme};
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::{item::Item, searchable::SearchableItemHandle, ItemNavHistory, Workspace};
+
+use crate::{terminal_element::convert_color, terminal_panel::TerminalPanel, TerminalView};
+
+actions!(terminal, [JumpToTerminal]);
+
+/// The number of lines shown around each match.
+const MATCH_CONTEXT_LINES: u32 = 1;
+
+/// Output that changes colors more often than this is only colored up to the last new style.
+const MAX_HIGHLIGHT_STYLES: usize = 256;
+
+struct TerminalTextStyles;
+
+/// Opens the terminal's scrollback in a read-only editor, keeping the colors of its output.
+pub(crate) fn open_scrollback_in_editor(
+    terminal_view: &TerminalView,
+    window: &mut Window,
+    cx: &mut Context<TerminalView>,
+) {
+    let terminal = terminal_view.terminal().read(cx);
+    let title = format!("Scrollback: {}", terminal.title(true));
+    let snapshot = terminal.scrollback_snapshot(cx);
+    let workspace = terminal_view.workspace.clone();
+    cx.spawn_in(window, |_, mut cx| async move {
+        let snapshot = snapshot.await;
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            let project = workspace.project().clone();
+            let buffer = create_buffer(&project, &snapshot, cx);
+            let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+            let editor = cx.new(|cx| {
+                let mut editor = Editor::for_multibuffer(buffer, Some(project), true, window, cx);
+                editor.set_read_only(true);
+                highlight_text_styles(&mut editor, &snapshot.styles, cx);
+
+                // Start at the bottom, where the terminal's latest output is.
+                let end = editor.buffer().read(cx).len(cx);
+                editor.change_selections(Some(Autoscroll::fit()), window, cx, |selections| {
+                    selections.select_ranges([end..end])
+                });
+                editor
+            });
+            workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Collects the terminal's current search matches into excerpts of its scrollback.
+pub(crate) fn collect_all_matches(
+    terminal_view: &TerminalView,
+    window: &mut Window,
+    cx: &mut Context<TerminalView>,
+) {
+    let terminal = terminal_view.terminal().read(cx);
+    if terminal.matches.is_empty() {
+        return;
+    }
+    let title = format!("Matches: {}", terminal.title(true));
+    let snapshot = terminal.scrollback_snapshot(cx);
+    let workspace = terminal_view.workspace.clone();
+    let terminal_view = cx.entity().downgrade();
+    cx.spawn_in(window, |_, mut cx| async move {
+        let snapshot = snapshot.await;
+        workspace.update_in(&mut cx, |workspace, window, cx| {
+            let project = workspace.project().clone();
+            let weak_workspace = cx.entity().downgrade();
+            let matches = cx.new(|cx| {
+                ScrollbackMatches::new(
+                    title.into(),
+                    snapshot,
+                    terminal_view,
+                    weak_workspace,
+                    project,
+                    window,
+                    cx,
+                )
+            });
+            workspace.add_item_to_active_pane(Box::new(matches), None, true, window, cx);
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+fn create_buffer(
+    project: &Entity<Project>,
+    snapshot: &ScrollbackSnapshot,
+    cx: &mut App,
+) -> Entity<Buffer> {
+    project.update(cx, |project, cx| {
+        project.create_local_buffer(&snapshot.text, None, cx)
+    })
+}
+
+/// Highlights text in a singleton editor with the styles it had in the terminal.
+fn highlight_text_styles(
+    editor: &mut Editor,
+    styles: &[(Range<usize>, TextStyle)],
+    cx: &mut Context<Editor>,
+) {
+    let buffer = editor.buffer().read(cx).snapshot(cx);
+    let mut highlights: Vec<(TextStyle, Vec<Range<Anchor>>)> = Vec::new();
+    for (range, style) in styles {
+        let range = buffer.anchor_after(range.start)..buffer.anchor_before(range.end);
+        match highlights
+            .iter_mut()
+            .find(|(existing, _)| existing == style)
+        {
+            Some((_, ranges)) => ranges.push(range),
+            None if highlights.len() < MAX_HIGHLIGHT_STYLES => {
+                highlights.push((*style, vec![range]))
+            }
+            None => {}
+        }
+    }
+
+    let theme = cx.theme().clone();
+    for (key, (style, ranges)) in highlights.into_iter().enumerate() {
+        editor.highlight_text_key::<TerminalTextStyles>(
+            key,
+            ranges,
+            highlight_style(&style, &theme),
+            cx,
+        );
+    }
+}
+
+fn highlight_style(style: &TextStyle, theme: &Theme) -> HighlightStyle {
+    let (mut foreground, mut background) = (style.foreground, style.background);
+    if style.flags.contains(Flags::INVERSE) {
+        std::mem::swap(&mut foreground, &mut background);
+    }
+
+    let mut color = convert_color(&foreground, theme);
+    if style.flags.contains(Flags::DIM) {
+        color.a *= 0.7;
+    }
+    let has_color = foreground != Color::Named(NamedColor::Foreground)
+        || style.flags.intersects(Flags::DIM | Flags::INVERSE);
+
+    HighlightStyle {
+        color: has_color.then_some(color),
+        background_color: (background != Color::Named(NamedColor::Background))
+            .then(|| convert_color(&background, theme)),
+        font_weight: style
+            .flags
+            .contains(Flags::BOLD)
+            .then_some(FontWeight::BOLD),
+        font_style: style
+            .flags
+            .contains(Flags::ITALIC)
+            .then_some(FontStyle::Italic),
+        underline: style
+            .flags
+            .intersects(Flags::ALL_UNDERLINES)
+            .then(|| UnderlineStyle {
+                color: Some(color),
+                thickness: px(1.),
+                wavy: style.flags.contains(Flags::UNDERCURL),
+            }),
+        strikethrough: style
+            .flags
+            .contains(Flags::STRIKEOUT)
+            .then(|| StrikethroughStyle {
+                color: Some(color),
+                thickness: px(1.),
+            }),
+        ..Default::default()
+    }
+}
+
+/// A terminal's search matches, shown in excerpts of its scrollback.
+///
+/// Each match can be jumped back to in the terminal, as long as its scrollback has not been
+/// cleared or filled up since.
+pub struct ScrollbackMatches {
+    editor: Entity<Editor>,
+    terminal_view: WeakEntity<TerminalView>,
+    workspace: WeakEntity<Workspace>,
+    matches: Vec<(Range<Anchor>, ScrollbackMatch)>,
+    title: SharedString,
+}
+
+impl ScrollbackMatches {
+    fn new(
+        title: SharedString,
+        snapshot: ScrollbackSnapshot,
+        terminal_view: WeakEntity<TerminalView>,
+        workspace: WeakEntity<Workspace>,
+        project: Entity<Project>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let buffer = create_buffer(&project, &snapshot, cx);
+        let excerpts = cx.new(|_| MultiBuffer::new(language::Capability::ReadOnly));
+        let match_ranges = excerpts.update(cx, |excerpts, cx| {
+            excerpts.push_excerpts_with_context_lines(
+                buffer,
+                snapshot
+                    .matches
+                    .iter()
+                    .map(|search_match| search_match.range.clone())
+                    .collect(),
+                MATCH_CONTEXT_LINES,
+                cx,
+            )
+        });
+
+        let editor = cx.new(|cx| {
+            let mut editor = Editor::for_multibuffer(excerpts, Some(project), true, window, cx);
+            editor.set_read_only(true);
+            editor.highlight_background::<Self>(
+                &match_ranges,
+                |colors| colors.search_match_background,
+                cx,
+            );
+            if let Some(first_match) = match_ranges.first() {
+                let start = first_match.start;
+                editor.change_selections(Some(Autoscroll::fit()), window, cx, |selections| {
+                    selections.select_anchor_ranges([start..start])
+                });
+            }
+            editor
+        });
+
+        Self {
+            editor,
+            terminal_view,
+            workspace,
+            matches: match_ranges.into_iter().zip(snapshot.matches).collect(),
+            title,
+        }
+    }
+
+    /// Selects the match closest to the cursor in the terminal it was found in.
+    fn jump_to_terminal(
+        &mut self,
+        _: &JumpToTerminal,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let editor = self.editor.read(cx);
+        let buffer = editor.buffer().read(cx).snapshot(cx);
+        let cursor = editor.selections.newest_anchor().head().to_point(&buffer);
+        let Some((_, search_match)) = self.matches.iter().min_by_key(|(range, _)| {
+            let start = range.start.to_point(&buffer);
+            (
+                start.row.abs_diff(cursor.row),
+                start.column.abs_diff(cursor.column),
+            )
+        }) else {
+            return;
+        };
+        let (start, end) = (search_match.start, search_match.end);
+
+        let Some(terminal_view) = self.terminal_view.upgrade() else {
+            return;
+        };
+        terminal_view.update(cx, |terminal_view, cx| {
+            terminal_view
+                .terminal()
+                .update(cx, |terminal, _| terminal.select_grid_range(start, end));
+            cx.notify();
+        });
+        self.workspace
+            .update(cx, |workspace, cx| {
+                TerminalPanel::reveal_terminal_view(workspace, &terminal_view, window, cx)
+            })
+            .log_err();
+    }
+}
+
+impl Render for ScrollbackMatches {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size_full()
+            .key_context("ScrollbackMatches")
+            .on_action(cx.listener(Self::jump_to_terminal))
+            .child(self.editor.clone())
+    }
+}
+
+impl Focusable for ScrollbackMatches {
+    fn focus_handle(&self, cx: &App) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<EditorEvent> for ScrollbackMatches {}
+
+impl Item for ScrollbackMatches {
+    type Event = EditorEvent;
+
+    fn tab_icon(&self, _window: &Window, _cx: &App) -> Option<Icon> {
+        Some(Icon::new(IconName::Terminal))
+    }
+
+    fn tab_content_text(&self, _window: &Window, _cx: &App) -> Option<SharedString> {
+        Some(self.title.clone())
+    }
+
+    fn as_searchable(&self, _: &Entity<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(self.editor.clone()))
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a Entity<Self>,
+        _: &'a App,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+
+    fn deactivated(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.deactivated(window, cx));
+    }
+
+    fn navigate(
+        &mut self,
+        data: Box<dyn std::any::Any>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        self.editor
+            .update(cx, |editor, cx| Item::navigate(editor, data, window, cx))
+    }
+
+    fn set_nav_history(
+        &mut self,
+        nav_history: ItemNavHistory,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.editor.update(cx, |editor, cx| {
+            Item::set_nav_history(editor, nav_history, window, cx)
+        });
+    }
+}
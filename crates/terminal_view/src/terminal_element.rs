@@ -1,11 +1,11 @@
 use editor::{CursorLayout, HighlightedRange, HighlightedRangeLine};
 use gpui::{
     div, fill, point, px, relative, size, AnyElement, App, AvailableSpace, Bounds, ContentMask,
-    Context, DispatchPhase, Element, ElementId, Entity, FocusHandle, Font, FontStyle, FontWeight,
-    GlobalElementId, HighlightStyle, Hitbox, Hsla, InputHandler, InteractiveElement, Interactivity,
-    IntoElement, LayoutId, ModifiersChangedEvent, MouseButton, MouseMoveEvent, Pixels, Point,
-    ShapedLine, StatefulInteractiveElement, StrikethroughStyle, Styled, TextRun, TextStyle,
-    UTF16Selection, UnderlineStyle, WeakEntity, WhiteSpace, Window, WindowTextSystem,
+    Context, Corners, DispatchPhase, Element, ElementId, Entity, FocusHandle, Font, FontStyle,
+    FontWeight, GlobalElementId, HighlightStyle, Hitbox, Hsla, InputHandler, InteractiveElement,
+    Interactivity, IntoElement, LayoutId, ModifiersChangedEvent, MouseButton, MouseMoveEvent,
+    Pixels, Point, ShapedLine, StatefulInteractiveElement, StrikethroughStyle, Styled, TextRun,
+    TextStyle, UTF16Selection, UnderlineStyle, WeakEntity, WhiteSpace, Window, WindowTextSystem,
 };
 use itertools::Itertools;
 use language::CursorShape;
@@ -21,10 +21,11 @@ use terminal::{
         },
     },
     terminal_settings::TerminalSettings,
-    HoveredWord, IndexedCell, Terminal, TerminalBounds, TerminalContent,
+    CommandMark, HoveredWord, ImageMark, IndexedCell, Terminal, TerminalBounds, TerminalContent,
 };
 use theme::{ActiveTheme, Theme, ThemeSettings};
 use ui::{ParentElement, Tooltip};
+use util::ResultExt;
 use workspace::Workspace;
 
 use std::mem;
@@ -47,6 +48,8 @@ pub struct LayoutState {
     gutter: Pixels,
     last_hovered_word: Option<HoveredWord>,
     block_below_cursor_element: Option<AnyElement>,
+    command_marks: Vec<(CommandMark, Hsla)>,
+    images: Vec<ImageMark>,
 }
 
 /// Helper struct for converting data between Alacritty's cursor points, and displayed cursor points.
@@ -729,11 +732,27 @@ impl Element for TerminalElement {
                     cursor_char,
                     selection,
                     cursor,
+                    command_marks,
+                    images,
                     ..
                 } = &self.terminal.read(cx).last_content;
+                let images = images.clone();
                 let mode = *mode;
                 let display_offset = *display_offset;
 
+                let command_marks = command_marks
+                    .iter()
+                    .map(|mark| {
+                        let color = match mark.exit_code {
+                            Some(0) => theme.status().success,
+                            Some(_) => theme.status().error,
+                            None if mark.finished => theme.colors().text_muted,
+                            None => theme.status().info,
+                        };
+                        (mark.clone(), color)
+                    })
+                    .collect();
+
                 // searches, highlights to a single range representations
                 let mut relative_highlighted_ranges = Vec::new();
                 for search_match in search_matches {
@@ -854,6 +873,8 @@ impl Element for TerminalElement {
                     gutter,
                     last_hovered_word,
                     block_below_cursor_element,
+                    command_marks,
+                    images,
                 }
             },
         )
@@ -920,6 +941,20 @@ impl Element for TerminalElement {
                         rect.paint(origin, &layout.dimensions, window);
                     }
 
+                    // Commands reported by the shell are marked in the gutter, colored by their exit status.
+                    let line_height = layout.dimensions.line_height;
+                    for (mark, color) in &layout.command_marks {
+                        let mark_origin = point(
+                            bounds.origin.x,
+                            origin.y + mark.lines.start as f32 * line_height,
+                        );
+                        let mark_size = size(
+                            layout.gutter * 0.25,
+                            mark.lines.len() as f32 * line_height - px(2.),
+                        );
+                        window.paint_quad(fill(Bounds::new(mark_origin, mark_size), *color));
+                    }
+
                     for (relative_highlighted_range, color) in
                         layout.relative_highlighted_ranges.iter()
                     {
@@ -941,6 +976,24 @@ impl Element for TerminalElement {
                         cell.paint(origin, &layout.dimensions, bounds, window, cx);
                     }
 
+                    let cell_width = layout.dimensions.cell_width;
+                    for image in &layout.images {
+                        let image_origin = origin
+                            + point(
+                                cell_width * image.column as f32,
+                                line_height * image.line as f32,
+                            );
+                        window
+                            .paint_image(
+                                Bounds::new(image_origin, image.size),
+                                Corners::default(),
+                                image.image.clone(),
+                                0,
+                                false,
+                            )
+                            .log_err();
+                    }
+
                     if self.cursor_visible {
                         if let Some(mut cursor) = cursor {
                             cursor.paint(origin, window, cx);
@@ -436,7 +436,13 @@ impl TerminalPanel {
         let window_handle = window.window_handle();
         let terminal = project
             .update(cx, |project, cx| {
-                project.create_terminal_with_venv(kind, python_venv_directory, window_handle, cx)
+                project.create_terminal_with_venv(
+                    kind,
+                    python_venv_directory,
+                    None,
+                    window_handle,
+                    cx,
+                )
             })
             .ok()?;
 
@@ -643,6 +649,33 @@ impl TerminalPanel {
             .collect()
     }
 
+    /// Activates and focuses a terminal, in the center of the workspace or in the terminal panel.
+    pub(crate) fn reveal_terminal_view(
+        workspace: &mut Workspace,
+        terminal_view: &Entity<TerminalView>,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) {
+        if workspace.activate_item(terminal_view, true, true, window, cx) {
+            return;
+        }
+        let Some(terminal_panel) = workspace.panel::<Self>(cx) else {
+            return;
+        };
+        let pane_and_index = terminal_panel
+            .read(cx)
+            .center
+            .panes()
+            .into_iter()
+            .find_map(|pane| Some((pane.clone(), pane.read(cx).index_for_item(terminal_view)?)));
+        if let Some((pane, index)) = pane_and_index {
+            workspace.open_panel::<Self>(window, cx);
+            terminal_panel
+                .read(cx)
+                .activate_terminal_view(&pane, index, true, window, cx);
+        }
+    }
+
     fn activate_terminal_view(
         &self,
         pane: &Entity<Pane>,
@@ -1,4 +1,5 @@
 mod persistence;
+pub mod scrollback;
 pub mod terminal_element;
 pub mod terminal_panel;
 pub mod terminal_scrollbar;
@@ -8,9 +9,10 @@ use collections::HashSet;
 use editor::{actions::SelectAll, scroll::ScrollbarAutoHide, Editor, EditorSettings};
 use futures::{stream::FuturesUnordered, StreamExt};
 use gpui::{
-    anchored, deferred, div, impl_actions, AnyElement, App, DismissEvent, Entity, EventEmitter,
-    FocusHandle, Focusable, KeyContext, KeyDownEvent, Keystroke, MouseButton, MouseDownEvent,
-    Pixels, Render, ScrollWheelEvent, Stateful, Styled, Subscription, Task, WeakEntity,
+    actions, anchored, deferred, div, impl_actions, AnyElement, App, DismissEvent, Entity,
+    EventEmitter, FocusHandle, Focusable, KeyContext, KeyDownEvent, Keystroke, MouseButton,
+    MouseDownEvent, Pixels, Render, ScrollWheelEvent, Stateful, Styled, Subscription, Task,
+    WeakEntity,
 };
 use persistence::TERMINAL_DB;
 use project::{search::SearchQuery, terminals::TerminalKind, Fs, Metadata, Project};
@@ -21,9 +23,10 @@ use terminal::{
         term::{search::RegexSearch, TermMode},
     },
     terminal_settings::{self, CursorShape, TerminalBlink, TerminalSettings, WorkingDirectory},
-    Clear, Copy, Event, MaybeNavigationTarget, Paste, ScrollLineDown, ScrollLineUp, ScrollPageDown,
-    ScrollPageUp, ScrollToBottom, ScrollToTop, ShowCharacterPalette, TaskStatus, Terminal,
-    TerminalBounds, ToggleViMode,
+    Clear, CollectAllMatches, Copy, CopyLastCommandOutput, Event, MaybeNavigationTarget,
+    OpenScrollbackInEditor, Paste, ScrollLineDown, ScrollLineUp, ScrollPageDown, ScrollPageUp,
+    ScrollToBottom, ScrollToNextPrompt, ScrollToPreviousPrompt, ScrollToTop, ShowCharacterPalette,
+    TaskStatus, Terminal, TerminalBounds, ToggleViMode,
 };
 use terminal_element::{is_blank, TerminalElement};
 use terminal_panel::TerminalPanel;
@@ -54,11 +57,12 @@ use zed_actions::assistant::InlineAssist;
 
 use std::{
     cmp,
+    future::Future,
     ops::RangeInclusive,
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 const REGEX_SPECIAL_CHARS: &[char] = &[
@@ -67,6 +71,10 @@ const REGEX_SPECIAL_CHARS: &[char] = &[
 
 const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
 
+/// Serializing the scrollback is much more expensive than the rest of the terminal's state,
+/// so it is saved at most this often instead of on every update.
+const SCROLLBACK_SERIALIZATION_INTERVAL: Duration = Duration::from_secs(5);
+
 const GIT_DIFF_PATH_PREFIXES: &[char] = &['a', 'b'];
 
 /// Event to transmit the scroll from the element to the view
@@ -81,6 +89,8 @@ pub struct SendKeystroke(String);
 
 impl_actions!(terminal, [SendText, SendKeystroke]);
 
+actions!(terminal, [AssistWithFailedCommand]);
+
 pub fn init(cx: &mut App) {
     terminal_panel::init(cx);
     terminal::init(cx);
@@ -127,6 +137,9 @@ pub struct TerminalView {
     scroll_handle: TerminalScrollHandle,
     show_scrollbar: bool,
     hide_scrollbar_task: Option<Task<()>>,
+    last_scrollback_serialization: Option<Instant>,
+    /// Whether the scrollback changed since it was last saved.
+    scrollback_serialization_pending: bool,
     _subscriptions: Vec<Subscription>,
     _terminal_subscriptions: Vec<Subscription>,
 }
@@ -209,10 +222,17 @@ impl TerminalView {
             scroll_handle,
             show_scrollbar: !Self::should_autohide_scrollbar(cx),
             hide_scrollbar_task: None,
+            last_scrollback_serialization: None,
+            scrollback_serialization_pending: false,
             _subscriptions: vec![
                 focus_in,
                 focus_out,
                 cx.observe_global::<SettingsStore>(Self::settings_changed),
+                cx.on_app_quit(Self::save_pending_scrollback),
+                cx.on_release_in(window, |this, window, cx| {
+                    this.terminal
+                        .update(cx, |terminal, _| terminal.release_images(window));
+                }),
             ],
             _terminal_subscriptions: terminal_subscriptions,
         }
@@ -222,6 +242,28 @@ impl TerminalView {
         &self.terminal
     }
 
+    /// Saves the scrollback that serialization put off, so that the latest output isn't lost
+    /// when quitting.
+    fn save_pending_scrollback(&mut self, cx: &mut Context<Self>) -> impl Future<Output = ()> {
+        let scrollback = self
+            .workspace_id
+            .filter(|_| {
+                self.scrollback_serialization_pending
+                    && TerminalSettings::get_global(cx).restore_scrollback
+            })
+            .map(|workspace_id| (workspace_id, self.terminal.read(cx).scrollback_text(cx)));
+        self.scrollback_serialization_pending = false;
+        let item_id = cx.entity_id().as_u64();
+        async move {
+            if let Some((workspace_id, scrollback)) = scrollback {
+                TERMINAL_DB
+                    .save_scrollback(item_id, workspace_id, scrollback.await)
+                    .await
+                    .log_err();
+            }
+        }
+    }
+
     pub fn has_bell(&self) -> bool {
         self.has_bell
     }
@@ -244,6 +286,13 @@ impl TerminalView {
             .map_or(false, |terminal_panel| {
                 terminal_panel.read(cx).assistant_enabled()
             });
+        let terminal = self.terminal.read(cx);
+        let has_command_output = terminal.command_blocks().last_finished().is_some();
+        let has_matches = !terminal.matches.is_empty();
+        let has_failed_command = terminal
+            .command_blocks()
+            .last_executed()
+            .map_or(false, |block| block.failed());
         let context_menu = ContextMenu::build(window, cx, |menu, _, _| {
             menu.context(self.focus_handle.clone())
                 .action("New Terminal", Box::new(NewTerminal))
@@ -252,9 +301,25 @@ impl TerminalView {
                 .action("Paste", Box::new(Paste))
                 .action("Select All", Box::new(SelectAll))
                 .action("Clear", Box::new(Clear))
+                .when(has_command_output, |menu| {
+                    menu.action("Copy Last Command Output", Box::new(CopyLastCommandOutput))
+                })
+                .action(
+                    "Open Scrollback in Editor",
+                    Box::new(OpenScrollbackInEditor),
+                )
+                .when(has_matches, |menu| {
+                    menu.action("Collect All Matches", Box::new(CollectAllMatches))
+                })
                 .when(assistant_enabled, |menu| {
                     menu.separator()
                         .action("Inline Assist", Box::new(InlineAssist::default()))
+                        .when(has_failed_command, |menu| {
+                            menu.action(
+                                "Assist with Failed Command",
+                                Box::new(AssistWithFailedCommand),
+                            )
+                        })
                 })
                 .separator()
                 .action(
@@ -462,6 +527,75 @@ impl TerminalView {
         cx.notify();
     }
 
+    fn scroll_to_previous_prompt(
+        &mut self,
+        _: &ScrollToPreviousPrompt,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.terminal
+            .update(cx, |term, _| term.scroll_to_previous_prompt());
+        cx.notify();
+    }
+
+    fn scroll_to_next_prompt(
+        &mut self,
+        _: &ScrollToNextPrompt,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.terminal
+            .update(cx, |term, _| term.scroll_to_next_prompt());
+        cx.notify();
+    }
+
+    fn copy_last_command_output(
+        &mut self,
+        _: &CopyLastCommandOutput,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.terminal
+            .update(cx, |term, cx| term.copy_last_command_output(cx));
+    }
+
+    fn open_scrollback_in_editor(
+        &mut self,
+        _: &OpenScrollbackInEditor,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        scrollback::open_scrollback_in_editor(self, window, cx);
+    }
+
+    fn collect_all_matches(
+        &mut self,
+        _: &CollectAllMatches,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        scrollback::collect_all_matches(self, window, cx);
+    }
+
+    fn assist_with_failed_command(
+        &mut self,
+        _: &AssistWithFailedCommand,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(command) = self.terminal.read(cx).last_failed_command() else {
+            return;
+        };
+        // The inline assistant includes the failed command's output in its context.
+        let prompt = format!("Fix the failed command `{}`", command.command);
+        window.dispatch_action(
+            Box::new(InlineAssist {
+                prompt: Some(prompt),
+            }),
+            cx,
+        );
+    }
+
     fn toggle_vi_mode(&mut self, _: &ToggleViMode, _: &mut Window, cx: &mut Context<Self>) {
         self.terminal.update(cx, |term, _| term.toggle_vi_mode());
         cx.notify();
@@ -1152,6 +1286,12 @@ impl Render for TerminalView {
             .on_action(cx.listener(TerminalView::scroll_page_down))
             .on_action(cx.listener(TerminalView::scroll_to_top))
             .on_action(cx.listener(TerminalView::scroll_to_bottom))
+            .on_action(cx.listener(TerminalView::scroll_to_previous_prompt))
+            .on_action(cx.listener(TerminalView::scroll_to_next_prompt))
+            .on_action(cx.listener(TerminalView::copy_last_command_output))
+            .on_action(cx.listener(TerminalView::open_scrollback_in_editor))
+            .on_action(cx.listener(TerminalView::collect_all_matches))
+            .on_action(cx.listener(TerminalView::assist_with_failed_command))
             .on_action(cx.listener(TerminalView::toggle_vi_mode))
             .on_action(cx.listener(TerminalView::show_character_palette))
             .on_action(cx.listener(TerminalView::select_all))
@@ -1312,6 +1452,7 @@ impl Item for TerminalView {
                 project.create_terminal_with_venv(
                     TerminalKind::Shell(working_directory),
                     python_venv_directory,
+                    None,
                     window_handle,
                     cx,
                 )
@@ -1414,7 +1555,7 @@ impl SerializableItem for TerminalView {
         &mut self,
         _workspace: &mut Workspace,
         item_id: workspace::ItemId,
-        _closing: bool,
+        closing: bool,
         _: &mut Window,
         cx: &mut Context<Self>,
     ) -> Option<Task<gpui::Result<()>>> {
@@ -1423,15 +1564,36 @@ impl SerializableItem for TerminalView {
             return None;
         }
 
-        if let Some((cwd, workspace_id)) = terminal.working_directory().zip(self.workspace_id) {
-            Some(cx.background_spawn(async move {
-                TERMINAL_DB
-                    .save_working_directory(item_id, workspace_id, cwd)
-                    .await
-            }))
-        } else {
+        let (cwd, workspace_id) = terminal.working_directory().zip(self.workspace_id)?;
+        let title = terminal.breadcrumb_text.clone();
+
+        let scrollback_due = closing
+            || self.last_scrollback_serialization.map_or(true, |last| {
+                last.elapsed() >= SCROLLBACK_SERIALIZATION_INTERVAL
+            });
+        let scrollback = if !scrollback_due {
             None
+        } else if TerminalSettings::get_global(cx).restore_scrollback {
+            Some(terminal.scrollback_text(cx))
+        } else {
+            Some(Task::ready(None))
+        };
+        if scrollback.is_some() {
+            self.last_scrollback_serialization = Some(Instant::now());
         }
+        self.scrollback_serialization_pending = scrollback.is_none();
+
+        Some(cx.background_spawn(async move {
+            TERMINAL_DB
+                .save_working_directory(item_id, workspace_id, cwd, title)
+                .await?;
+            if let Some(scrollback) = scrollback {
+                TERMINAL_DB
+                    .save_scrollback(item_id, workspace_id, scrollback.await)
+                    .await?;
+            }
+            Ok(())
+        }))
     }
 
     fn should_serialize(&self, event: &Self::Event) -> bool {
@@ -1468,12 +1630,29 @@ impl SerializableItem for TerminalView {
                 .ok()
                 .flatten();
 
+            let (title, scrollback) = TERMINAL_DB
+                .get_title_and_scrollback(item_id, workspace_id)
+                .log_err()
+                .flatten()
+                .unwrap_or_default();
+            let scrollback = scrollback.filter(|_| {
+                cx.update(|_, cx| TerminalSettings::get_global(cx).restore_scrollback)
+                    .unwrap_or(false)
+            });
             let terminal = project
                 .update(&mut cx, |project, cx| {
-                    project.create_terminal(TerminalKind::Shell(cwd), window_handle, cx)
+                    project.create_terminal_with_scrollback(
+                        TerminalKind::Shell(cwd),
+                        scrollback,
+                        window_handle,
+                        cx,
+                    )
                 })?
                 .await?;
             cx.update(|window, cx| {
+                if let Some(title) = title {
+                    terminal.update(cx, |terminal, _| terminal.breadcrumb_text = title);
+                }
                 cx.new(|cx| {
                     TerminalView::new(
                         terminal,
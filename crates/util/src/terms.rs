@@ -0,0 +1,97 @@
+//! Splitting text into the terms of the full-text search indexes.
+
+/// Splits text into lowercase terms. Identifiers are indexed whole and also split into their
+/// `snake_case` and `camelCase` words, so that `parse_config` matches a search for `config`.
+pub fn terms_in(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for identifier in text
+        .split(|c: char| !is_identifier_char(c))
+        .filter(|identifier| !identifier.is_empty())
+    {
+        let words = words_in_identifier(identifier);
+        if words.len() != 1 || words[0].len() != identifier.len() {
+            push_term(&mut terms, identifier);
+        }
+        for word in words {
+            push_term(&mut terms, word);
+        }
+    }
+    terms
+}
+
+/// Returns whether the character is part of the identifiers that [`terms_in`] splits.
+pub fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn push_term(terms: &mut Vec<String>, term: &str) {
+    // Single characters are too common to tell documents apart.
+    if term.chars().nth(1).is_some() {
+        terms.push(term.to_lowercase());
+    }
+}
+
+fn words_in_identifier(identifier: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for part in identifier.split('_').filter(|part| !part.is_empty()) {
+        let mut word_start = 0;
+        let mut previous: Option<char> = None;
+        let mut chars = part.char_indices().peekable();
+        while let Some((ix, c)) = chars.next() {
+            if let Some(previous) = previous {
+                let next_is_lowercase = chars.peek().map_or(false, |(_, next)| next.is_lowercase());
+                // Split `parseHTTPRequest` into `parse`, `HTTP` and `Request`.
+                let starts_word = c.is_uppercase()
+                    && (previous.is_lowercase()
+                        || previous.is_numeric()
+                        || (previous.is_uppercase() && next_is_lowercase));
+                if starts_word {
+                    words.push(&part[word_start..ix]);
+                    word_start = ix;
+                }
+            }
+            previous = Some(c);
+        }
+        words.push(&part[word_start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_in() {
+        assert_eq!(
+            terms_in("fn parse_config(path: &Path) -> parseHTTPRequest2 a"),
+            [
+                "fn",
+                "parse_config",
+                "parse",
+                "config",
+                "path",
+                "path",
+                "parsehttprequest2",
+                "parse",
+                "http",
+                "request2",
+            ]
+        );
+        assert_eq!(
+            terms_in("Creates an empty `HashMap<K, V>`. See [with_capacity]."),
+            [
+                "creates",
+                "an",
+                "empty",
+                "hashmap",
+                "hash",
+                "map",
+                "see",
+                "with_capacity",
+                "with",
+                "capacity"
+            ]
+        );
+    }
+}
@@ -4,6 +4,7 @@ pub mod fs;
 pub mod markdown;
 pub mod paths;
 pub mod serde;
+pub mod terms;
 #[cfg(any(test, feature = "test-support"))]
 pub mod test;
 
@@ -449,6 +449,7 @@ fn main() {
             app_state.user_store.clone(),
             cx,
         );
+        semantic_index::init(app_state.client.http_client(), cx);
         let prompt_builder = PromptBuilder::load(app_state.fs.clone(), stdout_is_a_pty(), cx);
         assistant::init(
             app_state.fs.clone(),
@@ -513,6 +514,7 @@ fn main() {
         git_ui::init(cx);
         feedback::init(cx);
         markdown_preview::init(cx);
+        docs_search::init(cx);
         welcome::init(cx);
         settings_ui::init(cx);
         extensions_ui::init(cx);